@@ -0,0 +1,478 @@
+#!/usr/bin/env python3
+"""
+Generates the vroomrs.pyi type stubs from the pyo3 bindings.
+
+Signatures are derived from the Rust sources: every #[pyclass] (along with
+its #[pyo3(get)] fields and #[pymethods]), every #[pyfunction] and every
+create_exception! gets a stub. Docstrings already present in vroomrs.pyi are kept as is, new items
+are documented with their Rust doc comments.
+
+Usage:
+    scripts/generate-stubs.py          # rewrites vroomrs.pyi
+    scripts/generate-stubs.py --check  # fails if vroomrs.pyi is outdated
+"""
+
+import argparse
+import ast
+import difflib
+import re
+import sys
+from pathlib import Path
+from typing import Dict, List, NamedTuple, Optional, Tuple
+
+ROOT = Path(__file__).resolve().parent.parent
+SOURCES = ROOT / "src"
+STUBS = ROOT / "vroomrs.pyi"
+LINE_LENGTH = 88
+
+EXCEPTION = re.compile(
+    r'create_exception!\(\s*\w+\s*,\s*(\w+)\s*,\s*([\w:]+)\s*(?:,\s*"((?:[^"\\]|\\.)*)")?\s*,?\s*\)'
+)
+
+INT_TYPES = {"i8", "i16", "i32", "i64", "isize", "u8", "u16", "u32", "u64", "usize"}
+STR_TYPES = {"str", "String", "Cow<str>", "DateTime<Utc>"}
+
+
+class Param(NamedTuple):
+    name: str
+    type: str
+    default: Optional[str]
+
+
+class Function(NamedTuple):
+    name: str
+    params: List[Param]
+    returns: str
+    doc: List[str]
+    decorator: Optional[str]
+
+
+class Attribute(NamedTuple):
+    name: str
+    type: str
+    doc: List[str]
+
+
+class Class(NamedTuple):
+    name: str
+    doc: List[str]
+    attributes: List[Attribute]
+    methods: List[Function]
+    bases: Tuple[str, ...] = ()
+
+
+# Rust parsing
+
+
+def matching(text: str, start: int) -> int:
+    """Returns the index of the bracket closing the one at `start`."""
+    pairs = {"(": ")", "[": "]", "{": "}", "<": ">"}
+    opening, closing, depth = text[start], pairs[text[start]], 0
+    for i in range(start, len(text)):
+        if text[i] == opening:
+            depth += 1
+        elif text[i] == closing and text[i - 1] != "-":
+            depth -= 1
+            if depth == 0:
+                return i
+    raise ValueError(f"unbalanced {opening!r} at offset {start}")
+
+
+def split_top_level(text: str) -> List[str]:
+    """Splits on commas which are not nested in brackets."""
+    parts, depth, current = [], 0, ""
+    for c in text:
+        if c in "([{<":
+            depth += 1
+        elif c in ")]}>":
+            depth -= 1
+        if c == "," and depth == 0:
+            parts.append(current.strip())
+            current = ""
+        else:
+            current += c
+    if current.strip():
+        parts.append(current.strip())
+    return parts
+
+
+def preamble(text: str, start: int) -> Tuple[List[str], List[str]]:
+    """Returns the doc comment lines and attributes preceding `start`."""
+    doc, attributes = [], []
+    lines = text[:start].split("\n")[:-1]
+    for line in reversed(lines):
+        line = line.strip()
+        if line.startswith("///"):
+            doc.insert(0, line[4:] if line.startswith("/// ") else line[3:])
+        elif line.startswith("#["):
+            attributes.insert(0, line)
+        else:
+            break
+    while doc and not doc[-1].strip():
+        doc.pop()
+    return doc, attributes
+
+
+def pyo3_option(attributes: List[str], option: str) -> Optional[str]:
+    for attribute in attributes:
+        if not attribute.startswith("#[pyo3("):
+            continue
+        inner = attribute[len("#[pyo3(") : attribute.rindex(")]")]
+        for part in split_top_level(inner):
+            key, _, value = part.partition("=")
+            if key.strip() == option:
+                return value.strip() or option
+    return None
+
+
+def py_default(value: str) -> str:
+    return {"true": "True", "false": "False"}.get(value, value)
+
+
+def py_type(rust: str, self_name: Optional[str], classes: set) -> str:
+    rust = re.sub(r"'\w+\s*,?\s*", "", rust).strip()
+    rust = re.sub(r"^&\s*(mut\s+)?", "", rust).strip()
+    if rust.startswith("dyn ") or rust.startswith("impl "):
+        raise ValueError(f"unsupported type {rust!r}")
+
+    generic = re.fullmatch(r"([\w:]+)\s*<(.*)>", rust, re.DOTALL)
+    if rust.startswith("(") and rust.endswith(")"):
+        items = split_top_level(rust[1:-1])
+        if not items:
+            return "None"
+        types = ", ".join(py_type(item, self_name, classes) for item in items)
+        return f"Tuple[{types}]"
+    if rust == "[u8]":
+        return "bytes"
+    if rust.startswith("[") and rust.endswith("]"):
+        return f"List[{py_type(rust[1:-1], self_name, classes)}]"
+    if rust in STR_TYPES or rust.startswith("Cow<"):
+        return "str"
+    if rust in INT_TYPES:
+        return "int"
+    if rust in {"f32", "f64"}:
+        return "float"
+    if rust == "bool":
+        return "bool"
+    if rust == "Self":
+        return self_name or rust
+    if rust in classes:
+        return rust
+    if generic:
+        name, args = generic.group(1).split("::")[-1], split_top_level(generic.group(2))
+        if name in {"PyResult", "Result"}:
+            return py_type(args[0], self_name, classes)
+        if name in {"PyRef", "PyRefMut", "Bound", "Py"}:
+            return py_type(args[-1], self_name, classes)
+        if name == "Option":
+            return f"Optional[{py_type(args[0], self_name, classes)}]"
+        if name == "Vec" and args == ["u8"]:
+            return "bytes"
+        if name in {"Vec", "HashSet", "BTreeSet"}:
+            container = "List" if name == "Vec" else "Set"
+            return f"{container}[{py_type(args[0], self_name, classes)}]"
+        if name in {"HashMap", "BTreeMap"}:
+            key, value = (py_type(arg, self_name, classes) for arg in args)
+            return f"Dict[{key}, {value}]"
+    if rust in {"PyAny", "PyObject"}:
+        return "Any"
+    raise ValueError(f"unsupported type {rust!r}")
+
+
+def parse_function(
+    text: str, start: int, self_name: Optional[str], classes: set
+) -> Tuple[Function, int]:
+    """Parses the `fn` item at `start` and returns it with the end of its body."""
+    doc, attributes = preamble(text, start)
+    header = re.compile(r"fn\s+(\w+)\s*(<[^(]*>)?\s*\(").match(text, start)
+    rust_name = header.group(1)
+    params_end = matching(text, header.end() - 1)
+    body_start = text.index("{", params_end)
+    returns = text[params_end + 1 : body_start].strip()
+    returns = returns[2:].split(" where ")[0].strip() if returns.startswith("->") else "()"
+
+    decorator, name = None, pyo3_option(attributes, "name")
+    name = (name or rust_name).strip('"')
+    if "#[new]" in attributes:
+        name, returns = "__init__", "()"
+    elif "#[staticmethod]" in attributes:
+        decorator = "staticmethod"
+    elif "#[classmethod]" in attributes:
+        decorator = "classmethod"
+    elif "#[getter]" in attributes:
+        decorator = "property"
+
+    rust_params = {}
+    for param in split_top_level(text[header.end() : params_end]):
+        if re.fullmatch(r"&?\s*('\w+\s+)?(mut\s+)?self", param):
+            continue
+        param_name, _, param_type = param.partition(":")
+        param_name = re.sub(r"^mut\s+", "", param_name.strip())
+        if param_name in {"slf", "cls", "py"} or param_type.strip().startswith("Python"):
+            continue
+        rust_params[param_name] = py_type(param_type, self_name, classes)
+
+    params = []
+    signature = pyo3_option(attributes, "signature")
+    if signature is None:
+        params = [Param(n, t, None) for n, t in rust_params.items()]
+    else:
+        for part in split_top_level(signature.strip()[1:-1]):
+            param_name, _, default = part.partition("=")
+            param_name = param_name.strip()
+            if param_name.startswith("*"):
+                params.append(Param(param_name, "", None))
+                continue
+            default = py_default(default.strip()) if default else None
+            params.append(Param(param_name, rust_params[param_name], default))
+
+    # Python objects are typed by the docstring, e.g. `callback (Callable[[dict], int]): ...`
+    for i, param in enumerate(params):
+        documented = re.compile(rf"^\s*{param.name} \((.+)\):").match
+        if param.type == "Any":
+            for line in doc:
+                if documented(line):
+                    params[i] = param._replace(type=documented(line).group(1))
+
+    returns = "None" if returns == "()" else py_type(returns, self_name, classes)
+    function = Function(name, params, returns, doc, decorator)
+    return function, matching(text, body_start)
+
+
+def parse_sources() -> Tuple[List[Class], List[Function]]:
+    files = sorted(SOURCES.rglob("*.rs"))
+    texts = [(path, path.read_text()) for path in files]
+
+    declared = {}
+    for _, text in texts:
+        for match in re.finditer(r"#\[pyclass[^\]]*\]", text):
+            struct = re.compile(r"pub(\([\w:]+\))?\s+struct\s+(\w+)").search(text, match.end())
+            declared[struct.group(2)] = (text, match.start(), struct)
+    names = set(declared)
+
+    classes = {}
+    for name, (text, start, struct) in declared.items():
+        doc, _ = preamble(text, start)
+        attributes = []
+        body_start = struct.end()
+        if text[body_start:].lstrip().startswith("{"):
+            body_start = text.index("{", body_start)
+            body = text[body_start + 1 : matching(text, body_start)]
+            for field in re.finditer(r"pub(\([\w:]+\))?\s+(\w+)\s*:", body):
+                field_doc, field_attributes = preamble(body, field.start())
+                if not any(a.startswith("#[pyo3(") and "get" in a for a in field_attributes):
+                    continue
+                type_end = len(body)
+                for end in (m.start() for m in re.finditer(",", body[field.end() :])):
+                    candidate = body[field.end() : field.end() + end]
+                    if candidate.count("<") == candidate.count(">"):
+                        type_end = field.end() + end
+                        break
+                field_type = py_type(body[field.end() : type_end], name, names)
+                attributes.append(Attribute(field.group(2), field_type, field_doc))
+        classes[name] = Class(name, doc, attributes, [])
+
+    for _, text in texts:
+        for match in EXCEPTION.finditer(text):
+            name, base, doc = match.groups()
+            # pyo3 names builtin exceptions after Python's, prefixed with Py
+            base = re.sub(r"^Py(?=[A-Z])", "", base.split("::")[-1])
+            classes[name] = Class(name, [doc] if doc else [], [], [], (base,))
+
+    functions = []
+    for _, text in texts:
+        for block in re.finditer(r"#\[pymethods\]\s*impl\s+(\w+)\s*\{", text):
+            name, position = block.group(1), block.end()
+            end = matching(text, block.end() - 1)
+            while True:
+                item = re.compile(r"\bfn\s+\w+").search(text, position, end)
+                if item is None:
+                    break
+                method, position = parse_function(text, item.start(), name, names)
+                classes[name].methods.append(method)
+        for item in re.finditer(r"#\[pyfunction\]", text):
+            start = re.compile(r"\bfn\s+\w+").search(text, item.end()).start()
+            functions.append(parse_function(text, start, None, names)[0])
+
+    return list(classes.values()), functions
+
+
+# Existing stubs
+
+
+class Docstrings(NamedTuple):
+    """Docstrings of the current stubs, in their original formatting."""
+
+    classes: Dict[str, Optional[str]]
+    members: Dict[Tuple[Optional[str], str], Optional[str]]
+
+
+def read_docstrings() -> Docstrings:
+    docstrings = Docstrings({}, {})
+    if not STUBS.exists():
+        return docstrings
+    source = STUBS.read_text()
+    lines = source.split("\n")
+
+    def text(node: ast.AST) -> str:
+        start, end = node.lineno - 1, node.end_lineno - 1
+        if start == end:
+            return lines[start][node.col_offset : node.end_col_offset]
+        return "\n".join(
+            [lines[start][node.col_offset :]]
+            + lines[start + 1 : end]
+            + [lines[end][: node.end_col_offset]]
+        )
+
+    def docstring(body: List[ast.stmt], index: int = 0) -> Optional[str]:
+        if index < len(body):
+            node = body[index]
+            if isinstance(node, ast.Expr) and isinstance(node.value, ast.Constant):
+                if isinstance(node.value.value, str):
+                    return text(node)
+        return None
+
+    def read_members(scope: Optional[str], body: List[ast.stmt]) -> None:
+        for i, node in enumerate(body):
+            if isinstance(node, ast.FunctionDef):
+                docstrings.members[(scope, node.name)] = docstring(node.body)
+            elif isinstance(node, ast.AnnAssign) and isinstance(node.target, ast.Name):
+                docstrings.members[(scope, node.target.id)] = docstring(body, i + 1)
+
+    module = ast.parse(source)
+    for node in module.body:
+        if isinstance(node, ast.ClassDef):
+            docstrings.classes[node.name] = docstring(node.body)
+            read_members(node.name, node.body)
+    read_members(None, module.body)
+    return docstrings
+
+
+# Rendering
+
+
+def render_docstring(doc: List[str], indent: str) -> Optional[str]:
+    if not doc:
+        return None
+    lines = [f"{indent}{line}".rstrip() for line in doc]
+    return "\n".join([f'{indent}"""'] + lines + [f'{indent}"""'])
+
+
+def render_kept(docstring: str, indent: str) -> str:
+    return indent + docstring
+
+
+def render_signature(function: Function, indent: str, method: bool, body: str) -> List[str]:
+    params = (["cls" if function.decorator == "classmethod" else "self"] if method else [])
+    if function.decorator == "staticmethod":
+        params = []
+    for param in function.params:
+        if not param.type:
+            params.append(param.name)
+        elif param.default is None:
+            params.append(f"{param.name}: {param.type}")
+        else:
+            params.append(f"{param.name}: {param.type} = {param.default}")
+
+    prefix = f"{indent}def {function.name}("
+    suffix = f") -> {function.returns}:{body}"
+    line = prefix + ", ".join(params) + suffix
+    if len(line) <= LINE_LENGTH:
+        return [line]
+    inner = f"{indent}    " + ", ".join(params)
+    if len(inner) <= LINE_LENGTH:
+        return [prefix, inner, indent + suffix]
+    return [prefix] + [f"{indent}    {param}," for param in params] + [indent + suffix]
+
+
+def render_function(
+    function: Function, scope: Optional[str], docstrings: Docstrings, indent: str
+) -> List[str]:
+    kept = docstrings.members.get((scope, function.name))
+    if kept is not None:
+        docstring = render_kept(kept, indent + "    ")
+    else:
+        docstring = render_docstring(function.doc, indent + "    ")
+    decorators = [f"{indent}@{function.decorator}"] if function.decorator else []
+    if docstring is None:
+        return decorators + render_signature(function, indent, scope is not None, " ...")
+    signature = render_signature(function, indent, scope is not None, "")
+    return decorators + signature + [docstring, f"{indent}    ..."]
+
+
+def render_attribute(attribute: Attribute, scope: str, docstrings: Docstrings) -> List[str]:
+    kept = docstrings.members.get((scope, attribute.name))
+    lines = [f"    {attribute.name}: {attribute.type}"]
+    if kept is not None:
+        lines.append(render_kept(kept, "    "))
+    elif attribute.doc:
+        lines.append('    """' + " ".join(line.strip() for line in attribute.doc) + '"""')
+    return lines
+
+
+def ordered(items: list, known: List[str]) -> list:
+    """Orders items as they appear in the current stubs, new ones last."""
+    rank = {name: i for i, name in enumerate(known)}
+    return sorted(items, key=lambda item: rank.get(item.name, len(rank)))
+
+
+def render(classes: List[Class], functions: List[Function], docstrings: Docstrings) -> str:
+    def members(scope: Optional[str]) -> List[str]:
+        return [name for member_scope, name in docstrings.members if member_scope == scope]
+
+    blocks = []
+    for cls in ordered(classes, list(docstrings.classes)):
+        kept = docstrings.classes.get(cls.name)
+        docstring = render_kept(kept, "    ") if kept else render_docstring(cls.doc, "    ")
+        items = [
+            render_attribute(item, cls.name, docstrings)
+            if isinstance(item, Attribute)
+            else render_function(item, cls.name, docstrings, "    ")
+            for item in ordered(cls.attributes + cls.methods, members(cls.name))
+        ]
+        if docstring:
+            items.insert(0, [docstring])
+        header = f"class {cls.name}({', '.join(cls.bases)})" if cls.bases else f"class {cls.name}"
+        if not items:
+            blocks.append(f"{header}: ...")
+            continue
+        blocks.append(f"{header}:\n" + "\n\n".join("\n".join(i) for i in items))
+    for function in ordered(functions, members(None)):
+        blocks.append("\n".join(render_function(function, None, docstrings, "")))
+
+    types = [a.type for c in classes for a in c.attributes]
+    for function in functions + [m for c in classes for m in c.methods]:
+        types += [p.type for p in function.params] + [function.returns]
+    used = set(re.findall(r"\w+", " ".join(types)))
+    typing = ("Any", "Callable", "Dict", "List", "Optional", "Set", "Tuple")
+    imports = [name for name in typing if name in used]
+    body = "\n\n".join(blocks) + "\n"
+    return f"from typing import {', '.join(imports)}\n\n" + body
+
+
+def main() -> int:
+    parser = argparse.ArgumentParser(description=__doc__.strip().split("\n")[0])
+    parser.add_argument("--check", action="store_true", help="fail if the stubs are outdated")
+    args = parser.parse_args()
+
+    classes, functions = parse_sources()
+    stubs = render(classes, functions, read_docstrings())
+    current = STUBS.read_text() if STUBS.exists() else ""
+    if not args.check:
+        STUBS.write_text(stubs)
+        return 0
+    if stubs != current:
+        diff = difflib.unified_diff(
+            current.splitlines(keepends=True),
+            stubs.splitlines(keepends=True),
+            fromfile=f"{STUBS.name} (current)",
+            tofile=f"{STUBS.name} (generated)",
+        )
+        sys.stdout.writelines(diff)
+        print(f"\n{STUBS.name} is outdated, run scripts/generate-stubs.py", file=sys.stderr)
+        return 1
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
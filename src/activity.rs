@@ -0,0 +1,132 @@
+//! Counts the samples of each thread of a profile chunk per time bucket, for
+//! overviews of which thread was busy when (e.g. the minimap of the UI).
+
+use std::collections::BTreeMap;
+
+use pyo3::{pyclass, pymethods};
+
+use crate::{timestamp::nanos_from_seconds, types::ChunkInterface};
+
+/// The number of samples of each thread per time bucket.
+#[pyclass]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ActivityTimeline {
+    pub start_ns: u64,
+    pub bucket_ns: u64,
+    /// Sample counts by thread ID, one per bucket.
+    pub threads: BTreeMap<String, Vec<u64>>,
+}
+
+#[pymethods]
+impl ActivityTimeline {
+    /// Returns the start of the first bucket.
+    ///
+    /// Returns:
+    ///     int
+    ///         The UTC Unix timestamp in nanoseconds of the start of the chunk.
+    pub fn get_start_ns(&self) -> u64 {
+        self.start_ns
+    }
+
+    /// Returns the duration of the buckets.
+    ///
+    /// Returns:
+    ///     int
+    ///         The duration of a bucket, in nanoseconds.
+    pub fn get_bucket_ns(&self) -> u64 {
+        self.bucket_ns
+    }
+
+    /// Returns the number of samples of each thread per bucket, the i-th
+    /// bucket starting `i * bucket_ns` after the start of the chunk.
+    ///
+    /// Returns:
+    ///     dict[str, list[int]]
+    ///         The sample counts by thread ID, every thread having the same
+    ///         number of buckets.
+    pub fn get_threads(&self) -> BTreeMap<String, Vec<u64>> {
+        self.threads.clone()
+    }
+}
+
+/// Returns the activity timeline of a chunk, with buckets of `bucket_ns`
+/// covering the chunk from its start. Android traces without samples count
+/// the events entering methods instead.
+///
+/// Panics if `bucket_ns` is 0.
+pub fn activity_timeline(chunk: &dyn ChunkInterface, bucket_ns: u64) -> ActivityTimeline {
+    let timestamps = chunk.sample_timestamps();
+    let start_ns = timestamps
+        .values()
+        .flatten()
+        .copied()
+        .chain(nanos_from_seconds(chunk.start_timestamp()))
+        .min()
+        .unwrap_or_default();
+    let end_ns = timestamps
+        .values()
+        .flatten()
+        .copied()
+        .chain(nanos_from_seconds(chunk.end_timestamp()))
+        .max()
+        .unwrap_or_default();
+    // a bucket past the end, for samples right at the end of the chunk
+    let buckets = ((end_ns - start_ns) / bucket_ns + 1) as usize;
+
+    let threads = timestamps
+        .into_iter()
+        .map(|(thread_id, timestamps)| {
+            let mut counts = vec![0; buckets];
+            for timestamp_ns in timestamps {
+                counts[((timestamp_ns - start_ns) / bucket_ns) as usize] += 1;
+            }
+            (thread_id, counts)
+        })
+        .collect();
+    ActivityTimeline {
+        start_ns,
+        bucket_ns,
+        threads,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::activity_timeline;
+    use crate::{
+        android::chunk::AndroidChunkBuilder, frame::Frame, sample::v2::SampleChunkBuilder,
+    };
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_activity_timeline() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let main = builder.frame(Frame {
+            function: Some("main".to_string()),
+            ..Default::default()
+        });
+        for i in 0..5 {
+            builder.sample("1", 10.0 + i as f64 * 0.1, &[main]);
+        }
+        builder
+            .sample("2", 10.05, &[main])
+            .sample("2", 10.35, &[main]);
+        let timeline = activity_timeline(&builder.build(), 200_000_000);
+        assert_eq!(timeline.start_ns, 10_000_000_000);
+        assert_eq!(timeline.threads["1"], vec![2, 2, 1]);
+        assert_eq!(timeline.threads["2"], vec![1, 1, 0]);
+
+        let mut builder = AndroidChunkBuilder::new(1737465052.0);
+        let on_create = builder.method("com.example.MainActivity", "onCreate", "()V", "");
+        builder
+            .thread(1, "main")
+            .enter(1, on_create, 0)
+            .exit(1, on_create, 10_000_000)
+            .enter(1, on_create, 30_000_000)
+            .exit(1, on_create, 40_000_000);
+        let timeline = activity_timeline(&builder.build(), 20_000_000);
+        assert_eq!(timeline.start_ns, 1_737_465_052_000_000_000);
+        assert_eq!(timeline.threads["1"], vec![1, 1, 0]);
+    }
+}
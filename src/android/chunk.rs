@@ -1,15 +1,29 @@
-use std::{borrow::Cow, cell::RefCell, collections::HashMap, ops::Mul, rc::Rc, time::Duration};
+use std::{borrow::Cow, collections::HashMap, ops::Mul, time::Duration};
 
+use pyo3::{pyclass, pymethods};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::{
-    nodetree::Node,
-    types::{CallTreeError, CallTreesStr, ChunkInterface, ClientSDK, DebugMeta},
+    frame::{
+        in_app_overrides::organization_overrides, Frame, FrameOverrideError, FrameOverrides,
+        InAppRules,
+    },
+    limits::max_stack_depth,
+    measurements::{Measurement, Measurements},
+    profile_chunk::{ProfileChunk, ANDROID_TRACE_FORMAT_VERSION},
+    types::{
+        CallTreeError, CallTreeOptions, CallTreesStr, ChunkInterface, ClientSDK, DebugMeta,
+        TraceContext,
+    },
 };
 
-use super::Android;
+use super::{
+    Action, Android, AndroidEvent, AndroidMethod, AndroidSample, AndroidThread, Clock,
+    EventMonotonic, EventTime,
+};
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct AndroidChunk {
     #[serde(skip_serializing_if = "Option::is_none")]
     build_id: Option<String>,
@@ -17,6 +31,8 @@ pub struct AndroidChunk {
     profiler_id: String,
     #[serde(default, skip_serializing_if = "DebugMeta::is_empty")]
     debug_meta: DebugMeta,
+    #[serde(default, skip_serializing_if = "TraceContext::is_empty")]
+    trace_context: TraceContext,
     #[serde(skip_serializing_if = "Option::is_none")]
     client_sdk: Option<ClientSDK>,
     duration_ns: u64,
@@ -30,23 +46,33 @@ pub struct AndroidChunk {
     version: Option<String>,
 
     profile: Android,
-    measurements: Option<serde_json::Value>,
+    #[serde(default, with = "crate::measurements::chunk_format")]
+    measurements: Option<Measurements>,
 
+    #[serde(default)]
     organization_id: u64,
+    #[serde(default)]
     project_id: u64,
+    #[serde(default)]
     received: f64,
+    #[serde(default)]
     retention_days: i32,
 }
 
 impl ChunkInterface for AndroidChunk {
-    fn call_trees(
+    fn call_trees_with_options(
         &mut self,
         _active_thread_id: Option<&str>,
+        options: CallTreeOptions,
     ) -> Result<CallTreesStr<'_>, CallTreeError> {
-        self.profile.sdk_start_time = Some(self.timestamp.mul(1e9) as u64);
-        let call_trees = self.profile.call_trees()?;
+        let start_ns = self.timestamp.mul(1e9) as u64;
+        self.profile.sdk_start_time = Some(start_ns);
+        self.profile.sdk_end_time = Some(start_ns + self.duration_ns);
+        let call_trees = self
+            .profile
+            .call_trees_with_options(max_stack_depth(), options.collapse_recursion)?;
 
-        let mut trees_by_thread_id: HashMap<Cow<str>, Vec<Rc<RefCell<Node>>>> = HashMap::new();
+        let mut trees_by_thread_id: CallTreesStr = HashMap::new();
         for (tid, call_tree) in call_trees {
             trees_by_thread_id
                 .entry(Cow::Owned(tid.to_string()))
@@ -55,12 +81,23 @@ impl ChunkInterface for AndroidChunk {
         Ok(trees_by_thread_id)
     }
 
-    fn normalize(&mut self) {}
+    fn normalize_with_rules(&mut self, in_app_rules: Option<&InAppRules>) {
+        if let Some(overrides) = organization_overrides(self.organization_id) {
+            self.profile.apply_in_app_overrides(&overrides);
+        }
+        if let Some(overrides) = in_app_rules.and_then(|rules| rules.overrides("android")) {
+            self.profile.apply_in_app_overrides(overrides);
+        }
+    }
 
     fn get_environment(&self) -> Option<&str> {
         self.environment.as_deref()
     }
 
+    fn get_trace_context(&self) -> &TraceContext {
+        &self.trace_context
+    }
+
     fn get_chunk_id(&self) -> &str {
         &self.chunk_id
     }
@@ -93,6 +130,29 @@ impl ChunkInterface for AndroidChunk {
         self.retention_days
     }
 
+    fn get_main_thread_id(&self) -> Option<String> {
+        match self.profile.active_thread_id() {
+            0 => None,
+            thread_id => Some(thread_id.to_string()),
+        }
+    }
+
+    fn set_organization_id(&mut self, organization_id: u64) {
+        self.organization_id = organization_id
+    }
+
+    fn set_project_id(&mut self, project_id: u64) {
+        self.project_id = project_id
+    }
+
+    fn set_received(&mut self, received: f64) {
+        self.received = received
+    }
+
+    fn set_retention_days(&mut self, retention_days: i32) {
+        self.retention_days = retention_days
+    }
+
     fn duration_ms(&self) -> u64 {
         Duration::from_nanos(self.duration_ns).as_millis() as u64
     }
@@ -113,6 +173,46 @@ impl ChunkInterface for AndroidChunk {
         self.client_sdk.as_deref().map(|sdk| sdk.version.as_str())
     }
 
+    fn sample_count(&self) -> usize {
+        self.profile.events.len() + self.profile.samples.len()
+    }
+
+    fn stack_count(&self) -> usize {
+        self.profile.stacks.len()
+    }
+
+    fn repaired_timestamps(&self) -> u64 {
+        self.profile.repaired_timestamps
+    }
+
+    fn truncated_stacks(&self) -> u64 {
+        self.profile.truncated_stacks
+    }
+
+    fn thread_names(&self) -> HashMap<String, String> {
+        self.profile.thread_names()
+    }
+
+    fn sample_timestamps(&self) -> HashMap<String, Vec<u64>> {
+        let start_ns = self.timestamp.mul(1e9) as u64;
+        self.profile
+            .activity_timestamps()
+            .into_iter()
+            .map(|(thread_id, timestamps)| {
+                let timestamps = timestamps.into_iter().map(|ts| start_ns + ts).collect();
+                (thread_id.to_string(), timestamps)
+            })
+            .collect()
+    }
+
+    fn fixed_up_events(&self) -> HashMap<String, u64> {
+        self.profile
+            .fixed_up_events
+            .iter()
+            .map(|(thread_id, count)| (thread_id.to_string(), *count))
+            .collect()
+    }
+
     fn storage_path(&self) -> String {
         format!(
             "{}/{}/{}/{}",
@@ -125,16 +225,363 @@ impl ChunkInterface for AndroidChunk {
         serde_json::to_vec(&self)
     }
 
+    fn get_measurements(&self) -> Option<&Measurements> {
+        self.measurements.as_ref()
+    }
+
+    fn frames(&self) -> Vec<Cow<'_, Frame>> {
+        self.profile.frames()
+    }
+
+    fn override_frames(
+        &mut self,
+        overrides: &[(usize, FrameOverrides)],
+    ) -> Result<(), FrameOverrideError> {
+        self.profile.override_frames(overrides)
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn clone_box(&self) -> Box<dyn ChunkInterface + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+/// Builds a valid [`AndroidChunk`] programmatically (threads, methods, events
+/// and measurements), so tests don't need to rely on large JSON fixtures.
+///
+/// Event timestamps are expressed in nanoseconds relative to the chunk
+/// `timestamp`.
+#[pyclass]
+#[derive(Debug, Default)]
+pub struct AndroidChunkBuilder {
+    chunk_id: String,
+    profiler_id: String,
+    organization_id: u64,
+    project_id: u64,
+    release: Option<String>,
+    environment: Option<String>,
+    timestamp: f64,
+    profile: Android,
+    measurements: Measurements,
+}
+
+impl AndroidChunkBuilder {
+    pub fn new(timestamp: f64) -> Self {
+        AndroidChunkBuilder {
+            chunk_id: Uuid::new_v4().simple().to_string(),
+            profiler_id: Uuid::new_v4().simple().to_string(),
+            organization_id: 1,
+            project_id: 1,
+            timestamp,
+            profile: Android {
+                clock: Clock::Wall,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    pub fn chunk_id(&mut self, chunk_id: &str) -> &mut Self {
+        self.chunk_id = chunk_id.to_string();
+        self
+    }
+
+    pub fn profiler_id(&mut self, profiler_id: &str) -> &mut Self {
+        self.profiler_id = profiler_id.to_string();
+        self
+    }
+
+    pub fn organization_id(&mut self, organization_id: u64) -> &mut Self {
+        self.organization_id = organization_id;
+        self
+    }
+
+    pub fn project_id(&mut self, project_id: u64) -> &mut Self {
+        self.project_id = project_id;
+        self
+    }
+
+    pub fn release(&mut self, release: &str) -> &mut Self {
+        self.release = Some(release.to_string());
+        self
+    }
+
+    pub fn environment(&mut self, environment: &str) -> &mut Self {
+        self.environment = Some(environment.to_string());
+        self
+    }
+
+    pub fn thread(&mut self, thread_id: u64, name: &str) -> &mut Self {
+        self.profile.threads.push(AndroidThread {
+            id: thread_id,
+            name: name.to_string(),
+        });
+        self
+    }
+
+    /// Adds a method and returns its ID.
+    pub fn method(
+        &mut self,
+        class_name: &str,
+        name: &str,
+        signature: &str,
+        source_file: &str,
+    ) -> u64 {
+        let id = self.profile.methods.len() as u64 + 1;
+        self.profile.methods.push(AndroidMethod {
+            class_name: class_name.to_string(),
+            id,
+            name: name.to_string(),
+            signature: signature.to_string(),
+            source_file: source_file.to_string(),
+            ..Default::default()
+        });
+        id
+    }
+
+    pub fn enter(&mut self, thread_id: u64, method_id: u64, ts_ns: u64) -> &mut Self {
+        self.event(Action::Enter, thread_id, method_id, ts_ns)
+    }
+
+    pub fn exit(&mut self, thread_id: u64, method_id: u64, ts_ns: u64) -> &mut Self {
+        self.event(Action::Exit, thread_id, method_id, ts_ns)
+    }
+
+    pub fn unwind(&mut self, thread_id: u64, method_id: u64, ts_ns: u64) -> &mut Self {
+        self.event(Action::Unwind, thread_id, method_id, ts_ns)
+    }
+
+    fn event(&mut self, action: Action, thread_id: u64, method_id: u64, ts_ns: u64) -> &mut Self {
+        self.profile.events.push(AndroidEvent {
+            action,
+            thread_id,
+            method_id,
+            time: wall_time(ts_ns),
+        });
+        self
+    }
+
+    /// Adds a sampled stack, listing method IDs from the innermost frame to
+    /// the outermost one, for chunks in the sampled variant of the format.
+    /// Call trees are built from events when a chunk has both.
+    pub fn sample(&mut self, thread_id: u64, ts_ns: u64, stack: &[u64]) -> &mut Self {
+        let stack_id = match self.profile.stacks.iter().position(|s| s == stack) {
+            Some(stack_id) => stack_id,
+            None => {
+                self.profile.stacks.push(stack.to_vec());
+                self.profile.stacks.len() - 1
+            }
+        };
+        self.profile.samples.push(AndroidSample {
+            thread_id,
+            stack_id,
+            time: wall_time(ts_ns),
+        });
+        self
+    }
+
+    /// Adds a measurement, values being `(timestamp, value)` pairs, with
+    /// timestamps in seconds.
+    ///
+    /// Panics if a timestamp is negative.
+    pub fn measurement(&mut self, name: &str, unit: &str, values: &[(f64, f64)]) -> &mut Self {
+        self.measurements
+            .insert(name.to_string(), Measurement::from_seconds(unit, values));
+        self
+    }
+
+    pub fn build(self) -> AndroidChunk {
+        let duration_ns = self
+            .profile
+            .events
+            .iter()
+            .map(|e| &e.time)
+            .chain(self.profile.samples.iter().map(|s| &s.time))
+            .filter_map(|t| t.monotonic.as_ref()?.wall.as_ref())
+            .map(|w| w.secs.unwrap_or_default() * 1_000_000_000 + w.nanos.unwrap_or_default())
+            .max()
+            .unwrap_or_default();
+        AndroidChunk {
+            build_id: None,
+            chunk_id: self.chunk_id,
+            profiler_id: self.profiler_id,
+            debug_meta: DebugMeta::default(),
+            trace_context: TraceContext::default(),
+            client_sdk: None,
+            duration_ns,
+            environment: self.environment,
+            platform: "android".to_string(),
+            release: self.release,
+            timestamp: self.timestamp,
+            version: Some(ANDROID_TRACE_FORMAT_VERSION.to_string()),
+            profile: self.profile,
+            measurements: if self.measurements.is_empty() {
+                None
+            } else {
+                Some(self.measurements)
+            },
+            organization_id: self.organization_id,
+            project_id: self.project_id,
+            received: self.timestamp + (duration_ns as f64 * 1e-9),
+            retention_days: 90,
+        }
+    }
+}
+
+fn wall_time(ts_ns: u64) -> EventTime {
+    EventTime {
+        global: None,
+        monotonic: Some(EventMonotonic {
+            wall: Some(super::Duration {
+                secs: Some(ts_ns / 1_000_000_000),
+                nanos: Some(ts_ns % 1_000_000_000),
+            }),
+            cpu: None,
+        }),
+    }
+}
+
+#[pymethods]
+impl AndroidChunkBuilder {
+    #[new]
+    #[pyo3(signature = (timestamp, chunk_id=None, profiler_id=None, organization_id=1, project_id=1, release=None, environment=None))]
+    fn py_new(
+        timestamp: f64,
+        chunk_id: Option<&str>,
+        profiler_id: Option<&str>,
+        organization_id: u64,
+        project_id: u64,
+        release: Option<&str>,
+        environment: Option<&str>,
+    ) -> Self {
+        let mut builder = AndroidChunkBuilder::new(timestamp);
+        builder
+            .organization_id(organization_id)
+            .project_id(project_id);
+        if let Some(chunk_id) = chunk_id {
+            builder.chunk_id(chunk_id);
+        }
+        if let Some(profiler_id) = profiler_id {
+            builder.profiler_id(profiler_id);
+        }
+        if let Some(release) = release {
+            builder.release(release);
+        }
+        if let Some(environment) = environment {
+            builder.environment(environment);
+        }
+        builder
+    }
+
+    /// Adds a thread to the profile.
+    ///
+    /// The thread named "main" is considered the active thread.
+    ///
+    /// Args:
+    ///     thread_id (int): The thread ID.
+    ///     name (str): The thread name.
+    pub fn add_thread(&mut self, thread_id: u64, name: &str) {
+        self.thread(thread_id, name);
+    }
+
+    /// Adds a method to the profile.
+    ///
+    /// Args:
+    ///     class_name (str): The fully qualified class name.
+    ///     name (str): The method name.
+    ///     signature (str, optional): The method signature.
+    ///     source_file (str, optional): The source file.
+    ///
+    /// Returns:
+    ///     int
+    ///         The method ID, to be used in events.
+    #[pyo3(signature = (class_name, name, signature="", source_file=""))]
+    pub fn add_method(
+        &mut self,
+        class_name: &str,
+        name: &str,
+        signature: &str,
+        source_file: &str,
+    ) -> u64 {
+        self.method(class_name, name, signature, source_file)
+    }
+
+    /// Adds a method enter event.
+    ///
+    /// Args:
+    ///     thread_id (int): The thread ID.
+    ///     method_id (int): The method ID.
+    ///     ts_ns (int): The event time, in nanoseconds since the chunk start.
+    pub fn add_enter(&mut self, thread_id: u64, method_id: u64, ts_ns: u64) {
+        self.enter(thread_id, method_id, ts_ns);
+    }
+
+    /// Adds a method exit event.
+    ///
+    /// Args:
+    ///     thread_id (int): The thread ID.
+    ///     method_id (int): The method ID.
+    ///     ts_ns (int): The event time, in nanoseconds since the chunk start.
+    pub fn add_exit(&mut self, thread_id: u64, method_id: u64, ts_ns: u64) {
+        self.exit(thread_id, method_id, ts_ns);
+    }
+
+    /// Adds a method unwind event, the method exiting because of an exception.
+    ///
+    /// Args:
+    ///     thread_id (int): The thread ID.
+    ///     method_id (int): The method ID.
+    ///     ts_ns (int): The event time, in nanoseconds since the chunk start.
+    pub fn add_unwind(&mut self, thread_id: u64, method_id: u64, ts_ns: u64) {
+        self.unwind(thread_id, method_id, ts_ns);
+    }
+
+    /// Adds a sampled stack, for chunks in the sampled variant of the
+    /// format. Call trees are built from the enter and exit events instead
+    /// if the chunk has any.
+    ///
+    /// Args:
+    ///     thread_id (int): The thread ID.
+    ///     ts_ns (int): The sample time, in nanoseconds since the chunk start.
+    ///     stack (list[int]): The method IDs, from the innermost frame to the outermost one.
+    pub fn add_sample(&mut self, thread_id: u64, ts_ns: u64, stack: Vec<u64>) {
+        self.sample(thread_id, ts_ns, &stack);
+    }
+
+    /// Adds a measurement to the profile.
+    ///
+    /// Args:
+    ///     name (str): The measurement name (e.g. "cpu_usage").
+    ///     unit (str): The measurement unit (e.g. "percent").
+    ///     values (list[tuple[float, float]]): ``(timestamp, value)`` pairs.
+    pub fn add_measurement(&mut self, name: &str, unit: &str, values: Vec<(f64, f64)>) {
+        self.measurement(name, unit, &values);
+    }
+
+    /// Builds the profile chunk.
+    ///
+    /// The builder is left empty afterwards.
+    ///
+    /// Returns:
+    ///     :class:`ProfileChunk`
+    ///         The profile chunk built.
+    #[pyo3(name = "build")]
+    pub fn py_build(&mut self) -> ProfileChunk {
+        ProfileChunk::new(Box::new(std::mem::take(self).build()))
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use serde_path_to_error::Error;
 
-    use super::AndroidChunk;
+    use super::{AndroidChunk, AndroidChunkBuilder};
+    use crate::types::{CallTreeOptions, ChunkInterface};
 
     #[test]
     fn test_android_valid() {
@@ -143,4 +590,240 @@ mod tests {
         let r: Result<AndroidChunk, Error<_>> = serde_path_to_error::deserialize(d);
         assert!(r.is_ok(), "{r:#?}")
     }
+
+    #[test]
+    fn test_android_chunk_builder() {
+        let mut builder = AndroidChunkBuilder::new(1737465052.0);
+        let on_create = builder.method("com.example.MainActivity", "onCreate", "()V", "");
+        let inflate = builder.method("android.view.LayoutInflater", "inflate", "()V", "");
+        builder
+            .thread(1, "main")
+            .enter(1, on_create, 0)
+            .enter(1, inflate, 10_000_000)
+            .exit(1, inflate, 40_000_000)
+            .exit(1, on_create, 50_000_000);
+        let mut chunk = builder.build();
+
+        assert_eq!(chunk.duration_ms(), 50);
+
+        let payload = chunk.to_json_vec().unwrap();
+        let r: Result<AndroidChunk, _> = serde_json::from_slice(&payload);
+        assert!(r.is_ok(), "{r:#?}");
+
+        let call_trees = chunk.call_trees(None).unwrap();
+        let tree = &call_trees["1"];
+        let root = tree.roots()[0];
+        assert_eq!(tree[root].name, "com.example.MainActivity.onCreate()V");
+        assert_eq!(tree[root].duration_ns, 50_000_000);
+        assert_eq!(tree.children(root).len(), 1);
+        assert_eq!(tree[tree.children(root)[0]].duration_ns, 30_000_000);
+    }
+
+    #[test]
+    fn test_android_sampled_chunk() {
+        let mut builder = AndroidChunkBuilder::new(1737465052.0);
+        let on_create = builder.method("com.example.MainActivity", "onCreate", "()V", "");
+        let inflate = builder.method("android.view.LayoutInflater", "inflate", "()V", "");
+        builder
+            .thread(1, "main")
+            .thread(2, "worker")
+            .sample(1, 0, &[on_create])
+            .sample(1, 10_000_000, &[inflate, on_create])
+            .sample(1, 20_000_000, &[inflate, on_create])
+            .sample(2, 20_000_000, &[inflate])
+            .sample(1, 40_000_000, &[99, on_create])
+            .sample(1, 50_000_000, &[on_create]);
+        let built = builder.build();
+        assert_eq!(built.duration_ms(), 50);
+
+        let payload = built.to_json_vec().unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+        assert_eq!(value["profile"]["events"], serde_json::json!([]));
+        assert_eq!(value["profile"]["samples"].as_array().unwrap().len(), 6);
+        let mut chunk: AndroidChunk = serde_json::from_slice(&payload).unwrap();
+        assert_eq!(chunk, built);
+
+        let call_trees = chunk.call_trees(None).unwrap();
+        assert_eq!(call_trees.len(), 1);
+        let tree = &call_trees["1"];
+        assert_eq!(tree.roots().len(), 1);
+        let root = tree.roots()[0];
+        assert_eq!(tree[root].name, "com.example.MainActivity.onCreate()V");
+        assert_eq!(tree[root].duration_ns, 50_000_000);
+        assert_eq!(tree[root].sample_count, 4);
+        let children: Vec<_> = tree
+            .children(root)
+            .iter()
+            .map(|&child| (tree[child].name.as_str(), tree[child].duration_ns))
+            .collect();
+        assert_eq!(
+            children,
+            vec![
+                ("android.view.LayoutInflater.inflate()V", 30_000_000),
+                ("unknown.unknown", 10_000_000),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_android_chunk_collapse_recursion() {
+        let options = CallTreeOptions {
+            collapse_recursion: true,
+            ..Default::default()
+        };
+        let mut builder = AndroidChunkBuilder::new(1737465052.0);
+        let parse = builder.method("com.example.Parser", "parse", "()V", "");
+        let read = builder.method("com.example.Parser", "read", "()V", "");
+        builder
+            .thread(1, "main")
+            .enter(1, parse, 0)
+            .enter(1, parse, 10_000_000)
+            .enter(1, parse, 20_000_000)
+            .enter(1, read, 30_000_000)
+            .exit(1, read, 40_000_000)
+            .exit(1, parse, 50_000_000)
+            .exit(1, parse, 60_000_000)
+            .enter(1, read, 70_000_000)
+            .exit(1, read, 80_000_000)
+            .exit(1, parse, 90_000_000);
+        let mut chunk = builder.build();
+
+        let call_trees = chunk.call_trees_with_options(None, options).unwrap();
+        let tree = &call_trees["1"];
+        let root = tree.roots()[0];
+        assert_eq!(tree[root].name, "com.example.Parser.parse()V");
+        assert_eq!(tree[root].recursion_count, 2);
+        assert_eq!(tree[root].duration_ns, 90_000_000);
+        let children: Vec<_> = tree
+            .children(root)
+            .iter()
+            .map(|&child| (tree[child].name.as_str(), tree[child].duration_ns))
+            .collect();
+        assert_eq!(
+            children,
+            vec![
+                ("com.example.Parser.read()V", 10_000_000),
+                ("com.example.Parser.read()V", 10_000_000),
+            ]
+        );
+        assert!(chunk.fixed_up_events().is_empty());
+
+        let mut builder = AndroidChunkBuilder::new(1737465052.0);
+        let parse = builder.method("com.example.Parser", "parse", "()V", "");
+        let read = builder.method("com.example.Parser", "read", "()V", "");
+        builder
+            .thread(1, "main")
+            .sample(1, 0, &[read, parse, parse, parse])
+            .sample(1, 10_000_000, &[read, parse])
+            .sample(1, 20_000_000, &[parse]);
+        let mut chunk = builder.build();
+
+        let call_trees = chunk.call_trees_with_options(None, options).unwrap();
+        let tree = &call_trees["1"];
+        assert_eq!(tree.len(), 2);
+        let root = tree.roots()[0];
+        assert_eq!(tree[root].recursion_count, 2);
+        let read = tree.children(root)[0];
+        assert_eq!(
+            (tree[read].sample_count, tree[read].duration_ns),
+            (2, 20_000_000)
+        );
+        assert_eq!(chunk.call_trees(None).unwrap()["1"].len(), 5);
+    }
+
+    #[test]
+    fn test_android_sampled_chunk_invalid_stack() {
+        let payload = serde_json::json!({
+            "chunk_id": "0432a0a4c25f4697bf9f0a2fcbe6a814",
+            "profiler_id": "4d229f1d3807421ba62a5f8bc295d836",
+            "duration_ns": 10,
+            "platform": "android",
+            "timestamp": 1737465052.0,
+            "profile": {
+                "clock": "Wall",
+                "methods": [],
+                "start_time": 0,
+                "threads": [{"id": 1, "name": "main"}],
+                "samples": [
+                    {"thread_id": 1, "stack_id": 3, "time": {"Monotonic": {"wall": {"secs": 0, "nanos": 0}}}},
+                    {"thread_id": 1, "stack_id": 3, "time": {"Monotonic": {"wall": {"secs": 0, "nanos": 10}}}}
+                ],
+                "stacks": []
+            }
+        });
+        let mut chunk: AndroidChunk = serde_json::from_value(payload).unwrap();
+        assert!(chunk.call_trees(None).is_err());
+    }
+
+    #[test]
+    fn test_android_chunk_fixed_up_events() {
+        let mut builder = AndroidChunkBuilder::new(1737465052.0);
+        let ids: Vec<u64> = ["a", "b", "c", "d", "e", "f", "x", "y"]
+            .iter()
+            .map(|name| builder.method("com.example.Main", name, "()V", ""))
+            .collect();
+        let [a, b, c, d, e, f, x, y] = ids[..] else {
+            unreachable!()
+        };
+        builder
+            .thread(1, "main")
+            .enter(1, a, 0)
+            // orphaned exit
+            .exit(1, x, 5_000_000)
+            .enter(1, b, 10_000_000)
+            // c never exits
+            .enter(1, c, 20_000_000)
+            .exit(1, b, 30_000_000)
+            .enter(1, d, 40_000_000)
+            .unwind(1, d, 50_000_000)
+            .enter(1, e, 60_000_000)
+            // unwinds e and a
+            .unwind(1, y, 70_000_000)
+            // f is still open at the end of the chunk
+            .enter(1, f, 80_000_000)
+            .exit(1, x, 100_000_000);
+        let mut chunk = builder.build();
+        assert!(chunk.fixed_up_events().is_empty());
+
+        let call_trees = chunk.call_trees(None).unwrap();
+        let tree = &call_trees["1"];
+        let start_ns = 1_737_465_052_000_000_000;
+        let spans = |ids: &[crate::nodetree::NodeId]| -> Vec<(String, u64, u64)> {
+            ids.iter()
+                .map(|&id| {
+                    (
+                        tree[id].name.clone(),
+                        tree[id].start_ns - start_ns,
+                        tree[id].end_ns - start_ns,
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(
+            spans(tree.roots()),
+            vec![
+                ("com.example.Main.a()V".to_string(), 0, 70_000_000),
+                ("com.example.Main.f()V".to_string(), 80_000_000, 100_000_000),
+            ]
+        );
+        let root = tree.roots()[0];
+        assert_eq!(
+            spans(tree.children(root)),
+            vec![
+                ("com.example.Main.b()V".to_string(), 10_000_000, 30_000_000),
+                ("com.example.Main.d()V".to_string(), 40_000_000, 50_000_000),
+                ("com.example.Main.e()V".to_string(), 60_000_000, 70_000_000),
+            ]
+        );
+        let b_node = tree.children(root)[0];
+        assert_eq!(
+            spans(tree.children(b_node)),
+            vec![("com.example.Main.c()V".to_string(), 20_000_000, 30_000_000)]
+        );
+        drop(call_trees);
+        assert_eq!(
+            chunk.fixed_up_events(),
+            HashMap::from([("1".to_string(), 6)])
+        );
+    }
 }
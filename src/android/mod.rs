@@ -2,19 +2,21 @@ pub mod chunk;
 pub mod profile;
 
 use std::borrow::Cow;
-use std::cell::RefCell;
 use std::collections::HashMap;
 use std::hash::Hasher;
 use std::path::Path;
-use std::rc::Rc;
+use std::sync::Arc;
 
-use fnv_rs::Fnv64;
 use serde::{Deserialize, Serialize};
 
-use crate::frame::{self, Frame};
-use crate::nodetree;
+use crate::frame::{self, Frame, FrameOverrideError, FrameOverrides, InAppOverrides};
+use crate::hashing::FingerprintHasher;
+use crate::sample::SampleError;
 use crate::types::{CallTreeError, CallTreesU64};
-use crate::{nodetree::Node, MAX_STACK_DEPTH};
+use crate::{
+    limits::max_stack_depth,
+    nodetree::{CallTree, Node, NodeId},
+};
 
 const MAIN_THREAD: &str = "main";
 const ANDROID_PACKAGE_PREFIXES: [&str; 11] = [
@@ -33,9 +35,9 @@ const ANDROID_PACKAGE_PREFIXES: [&str; 11] = [
 
 #[derive(Debug)]
 pub enum AndroidError {
-    FillSampleMetadataError(Box<dyn std::error::Error>),
+    FillSampleMetadataError(Box<dyn std::error::Error + Send + Sync>),
 }
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
 pub struct AndroidThread {
     id: u64,
     name: String,
@@ -174,7 +176,7 @@ struct Data {
     orig_in_app: Option<i8>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Default, Eq, PartialEq, Clone)]
 struct Duration {
     #[serde(skip_serializing_if = "Option::is_none")]
     secs: Option<u64>,
@@ -182,7 +184,7 @@ struct Duration {
     nanos: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Default, Eq, PartialEq, Clone)]
 struct EventMonotonic {
     #[serde(skip_serializing_if = "Option::is_none")]
     wall: Option<Duration>,
@@ -190,7 +192,7 @@ struct EventMonotonic {
     cpu: Option<Duration>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Default, Eq, PartialEq, Clone)]
 struct EventTime {
     #[serde(skip_serializing_if = "Option::is_none")]
     global: Option<Duration>,
@@ -215,7 +217,7 @@ enum Clock {
     None,
 }
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
 struct AndroidEvent {
     action: Action,
     thread_id: u64,
@@ -229,10 +231,26 @@ struct AndroidEvent {
     time: EventTime,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default, Eq, PartialEq)]
+/// A stack sampled on a thread, sent by SDKs sampling stacks instead of
+/// tracing method enters and exits.
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+struct AndroidSample {
+    thread_id: u64,
+    stack_id: usize,
+    time: EventTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Eq, PartialEq, Clone)]
 struct Android {
     clock: Clock,
+    #[serde(default)]
     events: Vec<AndroidEvent>,
+    // Sampled profiles have samples and stacks instead of events. Stacks
+    // list method IDs, from the innermost frame to the outermost one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    samples: Vec<AndroidSample>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    stacks: Vec<Vec<u64>>,
     methods: Vec<AndroidMethod>,
     start_time: u64,
     threads: Vec<AndroidThread>,
@@ -241,10 +259,78 @@ struct Android {
     // This is used to control the ts during callTree generation.
     #[serde(skip_serializing)]
     sdk_start_time: Option<u64>,
+    // sdk_end_time, if set, is the absolute end of the chunk in Ns, at which
+    // frames still open at the end of the trace are closed.
+    #[serde(skip)]
+    sdk_end_time: Option<u64>,
+    // Number of events fixed up while building the call trees, by thread ID:
+    // orphaned exits, and frames closed without an exit event.
+    #[serde(skip)]
+    fixed_up_events: HashMap<u64, u64>,
+    // Number of events whose wall-clock time was repaired, the clock going
+    // backwards.
+    #[serde(skip)]
+    repaired_timestamps: u64,
+    // Number of stacks cut at the maximum depth while building the call
+    // trees.
+    #[serde(skip)]
+    truncated_stacks: u64,
 }
 
 impl Android {
-    /// Returns the thread ID of the main thread, or 0 if not found
+    /// Forces the in_app value of the methods whose package matches an
+    /// override.
+    fn apply_in_app_overrides(&mut self, overrides: &InAppOverrides) {
+        for method in &mut self.methods {
+            if let Some(in_app) = overrides.in_app(method.package_name()) {
+                method.in_app = Some(in_app);
+            }
+        }
+    }
+
+    fn frames(&self) -> Vec<Cow<'_, Frame>> {
+        self.methods
+            .iter()
+            .map(|method| Cow::Owned(method.frame()))
+            .collect()
+    }
+
+    fn override_frames(
+        &mut self,
+        overrides: &[(usize, FrameOverrides)],
+    ) -> Result<(), FrameOverrideError> {
+        for (index, frame_overrides) in overrides {
+            if *index >= self.methods.len() {
+                return Err(FrameOverrideError::InvalidFrameIndex(*index));
+            }
+            // function and package names are derived from the class name,
+            // method name and signature, which can't be overridden independently
+            if let Some(field) = frame_overrides.unsupported_field(&["path", "lineno", "in_app"]) {
+                return Err(FrameOverrideError::UnsupportedField(field));
+            }
+        }
+        for (index, frame_overrides) in overrides {
+            let method = &mut self.methods[*index];
+            if let Some(path) = &frame_overrides.path {
+                method.source_file.clone_from(path);
+            }
+            method.source_line = frame_overrides.line.or(method.source_line);
+            method.in_app = frame_overrides.in_app.or(method.in_app);
+        }
+        Ok(())
+    }
+
+    /// Returns the names of the threads of the trace, by thread ID.
+    pub(crate) fn thread_names(&self) -> HashMap<String, String> {
+        self.threads
+            .iter()
+            .filter(|thread| !thread.name.is_empty())
+            .map(|thread| (thread.id.to_string(), thread.name.clone()))
+            .collect()
+    }
+
+    /// Returns the thread ID of the main thread, the thread named "main", or
+    /// 0 if not found.
     fn active_thread_id(&self) -> u64 {
         self.threads
             .iter()
@@ -323,6 +409,9 @@ impl Android {
                     let latest_time = *thread_latest_sample_time_ns.get(&thread_id).unwrap_or(&0);
 
                     let new_time = get_adjusted_time(max_time, latest_time, current);
+                    if new_time != current {
+                        self.repaired_timestamps += 1;
+                    }
 
                     thread_max_time_ns
                         .entry(thread_id)
@@ -397,18 +486,58 @@ impl Android {
     }
 
     fn call_trees(&mut self) -> Result<CallTreesU64, CallTreeError> {
-        self.call_trees_with_max_depth(MAX_STACK_DEPTH)
+        self.call_trees_with_options(max_stack_depth(), false)
+    }
+
+    /// Returns the timestamps of the samples, relative to the start of the
+    /// trace, by thread ID. Traces without samples report the events entering
+    /// methods instead.
+    pub(crate) fn activity_timestamps(&self) -> HashMap<u64, Vec<u64>> {
+        let build_timestamp = self.timestamp_getter();
+        let mut timestamps: HashMap<u64, Vec<u64>> = HashMap::new();
+        if self.events.is_empty() {
+            for sample in &self.samples {
+                timestamps
+                    .entry(sample.thread_id)
+                    .or_default()
+                    .push(build_timestamp(&sample.time));
+            }
+        } else {
+            for event in &self.events {
+                if event.action == Action::Enter {
+                    timestamps
+                        .entry(event.thread_id)
+                        .or_default()
+                        .push(build_timestamp(&event.time));
+                }
+            }
+        }
+        timestamps
     }
 
-    fn call_trees_with_max_depth(&mut self, max_depth: u64) -> Result<CallTreesU64, CallTreeError> {
+    /// Builds the call trees, cutting stacks deeper than `max_depth` and
+    /// collapsing consecutive calls of the same method into a single node if
+    /// `collapse_recursion` is set.
+    pub(crate) fn call_trees_with_options(
+        &mut self,
+        max_depth: u64,
+        collapse_recursion: bool,
+    ) -> Result<CallTreesU64, CallTreeError> {
+        if self.events.is_empty() && !self.samples.is_empty() {
+            return self.sampled_call_trees(max_depth, collapse_recursion);
+        }
+
         // in case wall-clock.secs is not monotonic, "fix" it
         self.fix_samples_time();
 
         let active_thread_id = self.active_thread_id();
 
         let build_timestamp = self.timestamp_getter();
-        let mut trees_by_thread_id: HashMap<u64, Vec<Rc<RefCell<Node>>>> = HashMap::new();
-        let mut stacks: HashMap<u64, Vec<Rc<RefCell<Node>>>> = HashMap::new();
+        let mut trees_by_thread_id: CallTreesU64 = HashMap::new();
+        let mut stacks: HashMap<u64, Vec<NodeId>> = HashMap::new();
+        // number of recursive calls still open, collapsed into each node of
+        // the stacks
+        let mut recursions: HashMap<u64, Vec<u64>> = HashMap::new();
         let mut stack_depth: HashMap<u64, i64> = HashMap::new();
 
         let mut methods: HashMap<u64, Cow<AndroidMethod>> = HashMap::new();
@@ -416,21 +545,16 @@ impl Android {
             methods.insert(method.id, Cow::Borrowed(method));
         }
 
-        let close_frame =
-            |thread_id: u64, ts: u64, i: i64, stacks: &HashMap<u64, Vec<Rc<RefCell<Node>>>>| {
-                let mut n = stacks
-                    .get(&thread_id)
-                    .expect("close_frame: no stack found for given thread")
-                    .get(i as usize)
-                    .expect("close_frame: no node found in current stack")
-                    .borrow_mut();
-                n.update(ts);
-                n.sample_count = (n.duration_ns as f64 / (10 * 1_000_000) as f64).ceil() as u64;
-            };
+        let close_frame = |n: &mut Node, ts: u64| {
+            n.update(ts);
+            n.sample_count = (n.duration_ns as f64 / (10 * 1_000_000) as f64).ceil() as u64;
+        };
 
+        // frames shared by the nodes of the same method
+        let mut frames: HashMap<u64, Arc<Frame>> = HashMap::new();
         let mut max_timestamp_ns: u64 = 0;
-        let mut enter_per_method: HashMap<u64, i64> = HashMap::new();
-        let mut exit_per_method: HashMap<u64, i64> = HashMap::new();
+        let mut fixed_up_events: HashMap<u64, u64> = HashMap::new();
+        let mut truncated_stacks: u64 = 0;
 
         for event in &self.events {
             if event.thread_id != active_thread_id {
@@ -451,91 +575,214 @@ impl Android {
                         })
                     });
                     let depth = stack_depth.entry(event.thread_id).or_default();
+                    let stack = stacks.entry(event.thread_id).or_default();
+                    let recursion = recursions.entry(event.thread_id).or_default();
+                    if collapse_recursion && *depth == stack.len() as i64 {
+                        if let (Some(&top), Some(tree)) =
+                            (stack.last(), trees_by_thread_id.get_mut(&event.thread_id))
+                        {
+                            if tree[top].frame.method_id == Some(event.method_id) {
+                                let open = recursion.last_mut().expect("same length as stack");
+                                *open += 1;
+                                tree[top].recursion_count = tree[top].recursion_count.max(*open);
+                                continue;
+                            }
+                        }
+                    }
                     *depth += 1;
                     if *depth > max_depth as i64 {
-                        continue;
-                    }
-                    *enter_per_method.entry(event.method_id).or_default() += 1;
-                    let n = nodetree::Node::from_frame(&method.frame(), ts, 0, 0);
-                    if stacks.entry(event.thread_id).or_default().is_empty() {
-                        trees_by_thread_id
-                            .entry(event.thread_id)
-                            .or_default()
-                            .push(Rc::clone(&n));
-                    } else {
-                        let stack = stacks.get(&event.thread_id).unwrap();
-                        let i = stack.len() - 1;
-                        if let Some(node) = stack.get(i) {
-                            node.borrow_mut().children.push(Rc::clone(&n));
+                        if *depth == max_depth as i64 + 1 {
+                            truncated_stacks += 1;
                         }
+                        continue;
                     }
+                    let tree = trees_by_thread_id.entry(event.thread_id).or_default();
                     let stack = stacks.entry(event.thread_id).or_default();
-                    stack.push(Rc::clone(&n));
-                    n.borrow_mut().fingerprint = generate_fingerprint(stack);
+                    let n = tree.push(
+                        stack.last().copied(),
+                        Node::from_frame(
+                            frames
+                                .entry(event.method_id)
+                                .or_insert_with(|| Arc::new(method.frame())),
+                            ts,
+                            0,
+                            0,
+                        ),
+                    );
+                    stack.push(n);
+                    recursion.push(0);
+                    tree[n].fingerprint = generate_fingerprint(tree, stack);
                 } //end Action::Enter
                 Action::Exit | Action::Unwind => {
                     let depth = stack_depth.entry(event.thread_id).or_default();
-                    *depth -= 1;
-                    if *depth >= max_depth as i64 {
-                        continue;
-                    }
-                    if stacks
-                        .get(&event.thread_id)
-                        .is_none_or(|stack| stack.is_empty())
-                    {
+                    if *depth > max_depth as i64 {
+                        // the frame was cut from the tree
+                        *depth -= 1;
                         continue;
                     }
-                    let mut i = (stacks.get(&event.thread_id).unwrap().len() as i64) - 1;
-                    let mut event_skipped = false;
-                    while i >= 0 {
-                        let node_method_id = stacks.get(&event.thread_id).unwrap()[i as usize]
-                            .borrow()
-                            .frame
-                            .method_id;
-                        if let (Some(method_id), Some(method_enters), Some(method_exits)) = (
-                            node_method_id,
-                            enter_per_method.get(&event.method_id),
-                            exit_per_method.get(&event.method_id),
-                        ) {
-                            if method_id != event.method_id && method_enters < method_exits {
-                                event_skipped = true;
-                                break;
-                            }
+                    let stack = stacks.entry(event.thread_id).or_default();
+                    let recursion = recursions.entry(event.thread_id).or_default();
+                    let position = trees_by_thread_id.get(&event.thread_id).and_then(|tree| {
+                        stack
+                            .iter()
+                            .rposition(|&n| tree[n].frame.method_id == Some(event.method_id))
+                    });
+                    let fixed_up = fixed_up_events.entry(event.thread_id).or_default();
+                    let close_from = match (position, event.action) {
+                        (Some(position), _) if recursion[position] > 0 => {
+                            // exit of a collapsed recursive call, frames above
+                            // it are missing their exits
+                            recursion[position] -= 1;
+                            *fixed_up += (stack.len() - position - 1) as u64;
+                            position + 1
                         }
-                        close_frame(event.thread_id, ts, i, &stacks);
-                        exit_per_method
-                            .entry(event.method_id)
-                            .and_modify(|c| *c += 1);
-                        if node_method_id.is_some_and(|id| id == event.method_id) {
-                            break;
+                        (Some(position), _) => {
+                            // frames above the method are missing their exits
+                            *fixed_up += (stack.len() - position - 1) as u64;
+                            position
+                        }
+                        (None, Action::Unwind) => {
+                            // the exception unwound every frame we know of
+                            *fixed_up += stack.len().max(1) as u64;
+                            0
+                        }
+                        (None, _) => {
+                            // orphaned exit, its enter was never recorded
+                            *fixed_up += 1;
+                            continue;
+                        }
+                    };
+                    if let Some(tree) = trees_by_thread_id.get_mut(&event.thread_id) {
+                        for &n in stack[close_from..].iter().rev() {
+                            close_frame(&mut tree[n], ts);
                         }
-                        i -= 1;
-                    }
-                    // If we didn't skip the event, we should cut the stack accordingly.
-                    if !event_skipped {
-                        stacks
-                            .entry(event.thread_id)
-                            .and_modify(|stack| stack.truncate(i as usize));
                     }
+                    stack.truncate(close_from);
+                    recursion.truncate(close_from);
+                    *depth = close_from as i64;
                 } //end Action Exit | Unwind
             } //end match event action
         } //end events loop
-          // Close remaining open frames.
+          // Close remaining open frames, at the end of the chunk if known.
+        let end_ns = max_timestamp_ns.max(self.sdk_end_time.unwrap_or_default());
         for (thread_id, stack) in stacks.iter() {
-            let mut i = (stack.len() as i64) - 1;
-            while i >= 0 {
-                close_frame(*thread_id, max_timestamp_ns, i, &stacks);
-                i -= 1;
+            let Some(tree) = trees_by_thread_id.get_mut(thread_id) else {
+                continue;
+            };
+            for &n in stack.iter().rev() {
+                close_frame(&mut tree[n], end_ns);
+            }
+            if !stack.is_empty() {
+                *fixed_up_events.entry(*thread_id).or_default() += stack.len() as u64;
             }
         }
-        for trees in trees_by_thread_id.values() {
-            for root in trees {
-                root.borrow_mut().close(max_timestamp_ns);
+        for tree in trees_by_thread_id.values_mut() {
+            for i in 0..tree.roots().len() {
+                tree.close(tree.roots()[i], end_ns);
             }
         }
 
+        drop(build_timestamp);
+        drop(methods);
+        fixed_up_events.retain(|_, count| *count > 0);
+        self.fixed_up_events = fixed_up_events;
+        self.truncated_stacks = truncated_stacks;
         Ok(trees_by_thread_id)
     }
+
+    /// Builds the call trees of sampled profiles, each sample lasting until
+    /// the next one on its thread. Stacks deeper than `max_depth` are cut,
+    /// keeping their outermost frames.
+    fn sampled_call_trees(
+        &mut self,
+        max_depth: u64,
+        collapse_recursion: bool,
+    ) -> Result<CallTreesU64, CallTreeError> {
+        let active_thread_id = self.active_thread_id();
+        let build_timestamp = self.timestamp_getter();
+
+        let mut frames: HashMap<u64, Arc<Frame>> = self
+            .methods
+            .iter()
+            .map(|method| (method.id, Arc::new(method.frame())))
+            .collect();
+        let samples: Vec<&AndroidSample> = self
+            .samples
+            .iter()
+            .filter(|sample| sample.thread_id == active_thread_id)
+            .collect();
+
+        let mut tree = CallTree::new();
+        let mut truncated_stacks: u64 = 0;
+        for pair in samples.windows(2) {
+            let (sample, next) = (pair[0], pair[1]);
+            let stack = self
+                .stacks
+                .get(sample.stack_id)
+                .ok_or(CallTreeError::Sample(SampleError::InvalidStackId))?;
+            let start_ns = build_timestamp(&sample.time) + self.sdk_start_time.unwrap_or_default();
+            let end_ns = build_timestamp(&next.time) + self.sdk_start_time.unwrap_or_default();
+
+            if stack.len() as u64 > max_depth {
+                truncated_stacks += 1;
+            }
+            let mut hasher = FingerprintHasher::default();
+            let mut current: Option<NodeId> = None;
+            // the previous method, and the number of its recursive calls
+            // collapsed so far
+            let mut previous: Option<(u64, u64)> = None;
+            for &method_id in stack.iter().rev().take(max_depth as usize) {
+                if collapse_recursion {
+                    match (&mut previous, current) {
+                        (Some((previous_id, recursions)), Some(current))
+                            if *previous_id == method_id =>
+                        {
+                            *recursions += 1;
+                            let node = &mut tree[current];
+                            node.recursion_count = node.recursion_count.max(*recursions);
+                            continue;
+                        }
+                        _ => previous = Some((method_id, 0)),
+                    }
+                }
+                let frame = frames.entry(method_id).or_insert_with(|| {
+                    Arc::new(
+                        AndroidMethod {
+                            class_name: "unknown".to_string(),
+                            id: method_id,
+                            name: "unknown".to_string(),
+                            ..Default::default()
+                        }
+                        .frame(),
+                    )
+                });
+                // same fingerprints as the nodes of traced profiles
+                let package = frame.module_or_package();
+                let name = frame.function.as_deref().unwrap_or_default();
+                if package.is_empty() && name.is_empty() {
+                    hasher.write(b"-");
+                } else {
+                    hasher.write(package.as_bytes());
+                    hasher.write(name.as_bytes());
+                }
+                current = Some(tree.add_sampled_frame(
+                    current,
+                    frame,
+                    hasher.finish(),
+                    start_ns,
+                    end_ns,
+                    1,
+                ));
+            }
+        }
+
+        drop(build_timestamp);
+        self.truncated_stacks = truncated_stacks;
+        if tree.is_empty() {
+            return Ok(HashMap::new());
+        }
+        Ok(HashMap::from([(active_thread_id, tree)]))
+    }
 }
 
 // maxTimeNs: the highest time (in nanoseconds) in the sequence so far
@@ -549,18 +796,16 @@ fn get_adjusted_time(max_time_ns: u64, latest_ns: u64, current_ns: u64) -> u64 {
     }
 }
 
-fn generate_fingerprint(stack: &Vec<Rc<RefCell<Node>>>) -> u64 {
-    let mut hasher = Fnv64::default();
-    for node in stack {
-        node.borrow().write_to_hash(&mut hasher);
+fn generate_fingerprint(tree: &CallTree, stack: &[NodeId]) -> u64 {
+    let mut hasher = FingerprintHasher::default();
+    for &node in stack {
+        tree[node].write_to_hash(&mut hasher);
     }
     hasher.finish()
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{cell::RefCell, rc::Rc};
-
     use super::AndroidMethod;
     use crate::{
         android::{
@@ -568,7 +813,7 @@ mod tests {
             EventTime,
         },
         frame::Frame,
-        nodetree::Node,
+        nodetree::{CallTree, NestedNode, Node},
         types::CallTreesU64,
     };
 
@@ -1289,6 +1534,7 @@ mod tests {
                         name: "background".to_string(),
                     },
                 ],
+                repaired_timestamps: 3,
                 ..Default::default()
             },
         }]; // end test_cases
@@ -1376,7 +1622,7 @@ mod tests {
 
     #[test]
     fn test_call_trees() {
-        use crate::MAX_STACK_DEPTH;
+        use crate::limits::DEFAULT_MAX_STACK_DEPTH;
         struct TestStruct {
             name: String,
             trace: Android,
@@ -1388,64 +1634,75 @@ mod tests {
             TestStruct {
                 name: "Build call trees with missing exit events".to_string(),
                 trace: get_missing_exit_events_trace(),
-                max_depth: MAX_STACK_DEPTH,
+                max_depth: DEFAULT_MAX_STACK_DEPTH,
                 want: [(
                     1,
-                    vec![
-                        Rc::new(RefCell::new(Node {
-                            duration_ns: 1000,
-                            is_application: true,
-                            end_ns: 2000,
-                            start_ns: 1000,
-                            name: "class1.method1()".to_string(),
-                            package: "class1".to_string(),
-                            sample_count: 1,
-                            fingerprint: 8189722245693347360,
-                            frame: Frame {
-                                function: Some("class1.method1()".to_string()),
-                                in_app: Some(true),
-                                method_id: Some(1),
-                                package: Some("class1".to_string()),
-                                ..Default::default()
-                            },
-                            children: vec![Rc::new(RefCell::new(Node {
+                    CallTree::from_nested(vec![
+                        NestedNode(
+                            Node {
                                 duration_ns: 1000,
                                 is_application: true,
                                 end_ns: 2000,
                                 start_ns: 1000,
-                                name: "class2.method2()".to_string(),
-                                package: "class2".to_string(),
+                                name: "class1.method1()".to_string(),
+                                package: "class1".to_string(),
                                 sample_count: 1,
-                                fingerprint: 13109094123195830328,
+                                fingerprint: 8189722245693347360,
                                 frame: Frame {
-                                    function: Some("class2.method2()".to_string()),
+                                    function: Some("class1.method1()".to_string()),
                                     in_app: Some(true),
-                                    method_id: Some(2),
-                                    package: Some("class2".to_string()),
+                                    method_id: Some(1),
+                                    package: Some("class1".to_string()),
                                     ..Default::default()
-                                },
+                                }
+                                .into(),
                                 ..Default::default()
-                            }))],
-                            ..Default::default()
-                        })),
-                        Rc::new(RefCell::new(Node {
-                            duration_ns: 0,
-                            is_application: true,
-                            end_ns: 3000,
-                            start_ns: 3000,
-                            name: "class1.method1()".to_string(),
-                            package: "class1".to_string(),
-                            fingerprint: 8189722245693347360,
-                            frame: Frame {
-                                function: Some("class1.method1()".to_string()),
-                                in_app: Some(true),
-                                method_id: Some(1),
-                                package: Some("class1".to_string()),
+                            },
+                            vec![NestedNode(
+                                Node {
+                                    duration_ns: 1000,
+                                    is_application: true,
+                                    end_ns: 2000,
+                                    start_ns: 1000,
+                                    name: "class2.method2()".to_string(),
+                                    package: "class2".to_string(),
+                                    sample_count: 1,
+                                    fingerprint: 13109094123195830328,
+                                    frame: Frame {
+                                        function: Some("class2.method2()".to_string()),
+                                        in_app: Some(true),
+                                        method_id: Some(2),
+                                        package: Some("class2".to_string()),
+                                        ..Default::default()
+                                    }
+                                    .into(),
+                                    ..Default::default()
+                                },
+                                vec![],
+                            )],
+                        ),
+                        NestedNode(
+                            Node {
+                                duration_ns: 0,
+                                is_application: true,
+                                end_ns: 3000,
+                                start_ns: 3000,
+                                name: "class1.method1()".to_string(),
+                                package: "class1".to_string(),
+                                fingerprint: 8189722245693347360,
+                                frame: Frame {
+                                    function: Some("class1.method1()".to_string()),
+                                    in_app: Some(true),
+                                    method_id: Some(1),
+                                    package: Some("class1".to_string()),
+                                    ..Default::default()
+                                }
+                                .into(),
                                 ..Default::default()
                             },
-                            ..Default::default()
-                        })),
-                    ], //end trees of thread 1
+                            vec![],
+                        ),
+                    ]), //end trees of thread 1
                 )]
                 .iter()
                 .cloned()
@@ -1454,63 +1711,73 @@ mod tests {
             TestStruct {
                 name: "Build call trees with missing enter events".to_string(),
                 trace: get_missing_enter_events_trace(),
-                max_depth: MAX_STACK_DEPTH,
+                max_depth: DEFAULT_MAX_STACK_DEPTH,
                 want: [(
                     1,
-                    vec![Rc::new(RefCell::new(Node {
-                        duration_ns: 2000,
-                        is_application: true,
-                        start_ns: 1000,
-                        end_ns: 3000,
-                        sample_count: 1,
-                        package: "class1".to_string(),
-                        name: "class1.method1()".to_string(),
-                        fingerprint: 8189722245693347360,
-                        frame: Frame {
-                            function: Some("class1.method1()".to_string()),
-                            in_app: Some(true),
-                            method_id: Some(1),
-                            package: Some("class1".to_string()),
-                            ..Default::default()
-                        },
-                        children: vec![Rc::new(RefCell::new(Node {
-                            duration_ns: 1000,
+                    CallTree::from_nested(vec![NestedNode(
+                        Node {
+                            duration_ns: 2000,
                             is_application: true,
-                            start_ns: 1500,
-                            end_ns: 2500,
+                            start_ns: 1000,
+                            end_ns: 3000,
                             sample_count: 1,
-                            package: "class3".to_string(),
-                            name: "class3.method3()".to_string(),
-                            fingerprint: 12998937618057698167,
+                            package: "class1".to_string(),
+                            name: "class1.method1()".to_string(),
+                            fingerprint: 8189722245693347360,
                             frame: Frame {
-                                function: Some("class3.method3()".to_string()),
+                                function: Some("class1.method1()".to_string()),
                                 in_app: Some(true),
-                                method_id: Some(3),
-                                package: Some("class3".to_string()),
+                                method_id: Some(1),
+                                package: Some("class1".to_string()),
                                 ..Default::default()
-                            },
-                            children: vec![Rc::new(RefCell::new(Node {
-                                duration_ns: 500,
+                            }
+                            .into(),
+                            ..Default::default()
+                        },
+                        vec![NestedNode(
+                            Node {
+                                duration_ns: 1000,
                                 is_application: true,
-                                start_ns: 1750,
-                                end_ns: 2250,
+                                start_ns: 1500,
+                                end_ns: 2500,
                                 sample_count: 1,
-                                package: "class4".to_string(),
-                                name: "class4.method4()".to_string(),
-                                fingerprint: 10444418669734640285,
+                                package: "class3".to_string(),
+                                name: "class3.method3()".to_string(),
+                                fingerprint: 12998937618057698167,
                                 frame: Frame {
-                                    function: Some("class4.method4()".to_string()),
+                                    function: Some("class3.method3()".to_string()),
                                     in_app: Some(true),
-                                    method_id: Some(4),
-                                    package: Some("class4".to_string()),
+                                    method_id: Some(3),
+                                    package: Some("class3".to_string()),
                                     ..Default::default()
-                                },
+                                }
+                                .into(),
                                 ..Default::default()
-                            }))],
-                            ..Default::default()
-                        }))],
-                        ..Default::default()
-                    }))],
+                            },
+                            vec![NestedNode(
+                                Node {
+                                    duration_ns: 500,
+                                    is_application: true,
+                                    start_ns: 1750,
+                                    end_ns: 2250,
+                                    sample_count: 1,
+                                    package: "class4".to_string(),
+                                    name: "class4.method4()".to_string(),
+                                    fingerprint: 10444418669734640285,
+                                    frame: Frame {
+                                        function: Some("class4.method4()".to_string()),
+                                        in_app: Some(true),
+                                        method_id: Some(4),
+                                        package: Some("class4".to_string()),
+                                        ..Default::default()
+                                    }
+                                    .into(),
+                                    ..Default::default()
+                                },
+                                vec![],
+                            )],
+                        )],
+                    )]),
                 )]
                 .iter()
                 .cloned()
@@ -1522,24 +1789,28 @@ mod tests {
                 max_depth: 1,
                 want: [(
                     1,
-                    vec![Rc::new(RefCell::new(Node {
-                        duration_ns: 1000,
-                        is_application: true,
-                        start_ns: 1000,
-                        end_ns: 2000,
-                        sample_count: 1,
-                        package: "class1".to_string(),
-                        name: "class1.method1()".to_string(),
-                        fingerprint: 8189722245693347360,
-                        frame: Frame {
-                            function: Some("class1.method1()".to_string()),
-                            in_app: Some(true),
-                            method_id: Some(1),
-                            package: Some("class1".to_string()),
+                    CallTree::from_nested(vec![NestedNode(
+                        Node {
+                            duration_ns: 1000,
+                            is_application: true,
+                            start_ns: 1000,
+                            end_ns: 2000,
+                            sample_count: 1,
+                            package: "class1".to_string(),
+                            name: "class1.method1()".to_string(),
+                            fingerprint: 8189722245693347360,
+                            frame: Frame {
+                                function: Some("class1.method1()".to_string()),
+                                in_app: Some(true),
+                                method_id: Some(1),
+                                package: Some("class1".to_string()),
+                                ..Default::default()
+                            }
+                            .into(),
                             ..Default::default()
                         },
-                        ..Default::default()
-                    }))],
+                        vec![],
+                    )]),
                 )]
                 .iter()
                 .cloned()
@@ -1550,12 +1821,13 @@ mod tests {
         for test_case in test_cases.as_mut() {
             let call_trees = test_case
                 .trace
-                .call_trees_with_max_depth(test_case.max_depth)
+                .call_trees_with_options(test_case.max_depth, false)
                 .unwrap();
-            let call_json = serde_json::to_string(&call_trees).unwrap();
-            let want_json = serde_json::to_string(&test_case.want).unwrap();
-            //assert_eq!(test_case.want, call_trees, "test: `{}` failed", test_case.name)
-            assert_eq!(want_json, call_json, "test: `{}` failed", test_case.name)
+            assert_eq!(
+                test_case.want, call_trees,
+                "test: `{}` failed",
+                test_case.name
+            )
         }
     }
 }
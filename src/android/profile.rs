@@ -5,7 +5,9 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     android::AndroidError,
-    sample::v1::{Measurement, Profile, RuntimeMetadata, SampleProfile},
+    frame::{in_app_overrides::organization_overrides, Frame},
+    measurements::Measurements,
+    sample::v1::{Profile, RuntimeMetadata, SampleProfile},
     types::{
         CallTreeError, ClientSDK, DebugMeta, ProfileInterface, Transaction, TransactionMetadata,
     },
@@ -52,11 +54,16 @@ pub struct AndroidProfile {
     environment: Option<String>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    js_profile: Option<serde_json::Value>,
+    js_profile: Option<NestedProfile>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    measurements: Option<HashMap<String, Measurement>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "crate::measurements::profile_format"
+    )]
+    measurements: Option<Measurements>,
 
+    #[serde(default)]
     organization_id: u64,
 
     platform: String,
@@ -65,12 +72,15 @@ pub struct AndroidProfile {
 
     profile_id: String,
 
+    #[serde(default)]
     project_id: u64,
 
+    #[serde(default)]
     received: i64,
 
     release: Option<String>,
 
+    #[serde(default)]
     retention_days: i32,
 
     sampled: bool,
@@ -96,7 +106,7 @@ pub struct AndroidProfile {
 
 // NestedProfile is used to deserialize the js_profile
 // when one is present.
-#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
 pub struct NestedProfile {
     profile: Profile,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -151,20 +161,17 @@ impl ProfileInterface for AndroidProfile {
     }
 
     fn normalize(&mut self) {
-        if let Some(js_profile_json) = &mut self.js_profile {
-            let mut js_profile: NestedProfile = serde_json::from_value(js_profile_json.clone())
-                .expect("error while deserializing js_profile");
+        if let Some(js_profile) = &mut self.js_profile {
+            // the frames of the js profile are normalized with the
+            // javascript rules, as vroom does for react-native profiles
             let mut sample_profile = SampleProfile {
+                organization_id: self.organization_id,
                 platform: "javascript".to_string(),
-                profile: js_profile.profile,
+                profile: std::mem::take(&mut js_profile.profile),
                 ..Default::default()
             };
             sample_profile.normalize();
             js_profile.profile = sample_profile.profile;
-            let js_profile_value = serde_json::to_value(js_profile);
-            if let Ok(value) = js_profile_value {
-                self.js_profile = Some(value);
-            }
         }
         if self
             .build_id
@@ -180,6 +187,9 @@ impl ProfileInterface for AndroidProfile {
             }
         }
         self.build_id = None;
+        if let Some(overrides) = organization_overrides(self.organization_id) {
+            self.profile.apply_in_app_overrides(&overrides);
+        }
     }
 
     fn call_trees(&mut self) -> Result<crate::types::CallTreesU64, crate::types::CallTreeError> {
@@ -188,15 +198,14 @@ impl ProfileInterface for AndroidProfile {
         if self.duration_ns > MAX_PROFILE_DURATION_FOR_CALL_TREES {
             return Ok(HashMap::new());
         }
-        // this is to handle only the Reactnative (android + js)
-        // use case. If it's an Android profile but there is no
-        // js profile, we'll skip this entirely
-        if let Some(js_profile_json) = &mut self.js_profile {
-            let js_profile: NestedProfile = serde_json::from_value(js_profile_json.clone())
-                .expect("error while deserializing js_profile");
+        let mut trees = self.profile.call_trees()?;
+        // this is to handle the react-native (android + js) use case:
+        // the call trees of the js profile are merged with the android
+        // ones, by thread
+        if let Some(js_profile) = &self.js_profile {
             let mut sample_profile = SampleProfile {
                 platform: "javascript".to_string(),
-                profile: js_profile.profile,
+                profile: js_profile.profile.clone(),
                 ..Default::default()
             };
             // if we're in this branch we know for sure that here
@@ -209,16 +218,14 @@ impl ProfileInterface for AndroidProfile {
                 name: "hermes".to_string(),
                 ..Default::default()
             });
-            match fill_sample_profile_metadata(&mut sample_profile) {
-                Ok(_) => return sample_profile.call_trees(),
-                Err(err) => {
-                    return Err(CallTreeError::Android(
-                        AndroidError::FillSampleMetadataError(err),
-                    ))
-                }
+            fill_sample_profile_metadata(&mut sample_profile).map_err(|err| {
+                CallTreeError::Android(AndroidError::FillSampleMetadataError(err))
+            })?;
+            for (thread_id, tree) in sample_profile.call_trees()? {
+                trees.entry(thread_id).or_default().append(tree);
             }
-        } // end if js_profile
-        self.profile.call_trees()
+        }
+        Ok(trees)
     }
 
     fn storage_path(&self) -> String {
@@ -250,15 +257,24 @@ impl ProfileInterface for AndroidProfile {
             segment_id: self
                 .transaction_metadata
                 .as_ref()
-                .unwrap()
-                .segment_id
-                .as_ref()
-                .map_or("".to_string(), |segment| segment.clone()),
+                .and_then(|metadata| metadata.segment_id.clone())
+                .unwrap_or_default(),
         })
     }
 
     fn get_main_thread_id(&self) -> Option<u64> {
-        None
+        match self.profile.active_thread_id() {
+            0 => None,
+            thread_id => Some(thread_id),
+        }
+    }
+
+    fn thread_names(&self) -> HashMap<String, String> {
+        self.profile.thread_names()
+    }
+
+    fn frames(&self) -> Vec<Cow<'_, Frame>> {
+        self.profile.frames()
     }
 
     fn get_transaction_tags(&self) -> &HashMap<String, String> {
@@ -269,7 +285,7 @@ impl ProfileInterface for AndroidProfile {
         &self.debug_meta
     }
 
-    fn get_measurements(&self) -> Option<&HashMap<String, Measurement>> {
+    fn get_measurements(&self) -> Option<&Measurements> {
         self.measurements.as_ref()
     }
 
@@ -281,6 +297,22 @@ impl ProfileInterface for AndroidProfile {
         self.profile_id = profile_id
     }
 
+    fn set_organization_id(&mut self, organization_id: u64) {
+        self.organization_id = organization_id
+    }
+
+    fn set_project_id(&mut self, project_id: u64) {
+        self.project_id = project_id
+    }
+
+    fn set_received(&mut self, received: i64) {
+        self.received = received
+    }
+
+    fn set_retention_days(&mut self, retention_days: i32) {
+        self.retention_days = retention_days
+    }
+
     fn get_metadata(&self) -> crate::types::Metadata {
         crate::types::Metadata {
             android_api_level: self.android_api_level,
@@ -322,14 +354,12 @@ impl ProfileInterface for AndroidProfile {
 // Here we set it to the only thread that the js profile has.
 fn fill_sample_profile_metadata(
     sample_profile: &mut SampleProfile,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     if let Some((thread_id, _)) = sample_profile
         .profile
         .thread_metadata
         .as_ref()
-        .expect("cannot fill profile metadata: missing thread metadata")
-        .iter()
-        .next()
+        .and_then(|thread_metadata| thread_metadata.iter().next())
     {
         sample_profile.transaction = Transaction {
             active_thread_id: thread_id.parse::<u64>()?,
@@ -384,11 +414,11 @@ mod tests {
                                 [0]
                             ]
                         }
-                    }"#).expect("failed to parse JSON string into serde_json::Value")),
+                    }"#).expect("failed to parse js_profile")),
                     ..Default::default()
                 },
                 want: AndroidProfile {
-                    js_profile: Some(serde_json::from_str(r#"{"profile":{"frames":[{"data":null,"function":"[Native] functionPrototypeApply","in_app":false,"platform":"javascript"}],"samples":[{"elapsed_since_start_ns":1000,"stack_id":0,"thread_id":1}],"stacks":[[0]]}}"#).expect("failed to parse JSON string into serde_json::Value")),
+                    js_profile: Some(serde_json::from_str(r#"{"profile":{"frames":[{"category":"system","data":null,"function":"[Native] functionPrototypeApply","in_app":false,"platform":"javascript"}],"samples":[{"elapsed_since_start_ns":1000,"stack_id":0,"thread_id":1}],"stacks":[[0]]}}"#).expect("failed to parse js_profile")),
                     ..Default::default()
                 },
             },
@@ -398,7 +428,8 @@ mod tests {
             //let call_trees = test_case.chunk.call_trees(None).unwrap();
             test_case.profile.normalize();
             assert_eq!(
-                test_case.profile.js_profile, test_case.want.js_profile,
+                serde_json::to_value(&test_case.profile.js_profile).unwrap(),
+                serde_json::to_value(&test_case.want.js_profile).unwrap(),
                 "test: {} failed.",
                 test_case.name
             );
@@ -443,4 +474,102 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_find_view_inflation_on_main_thread() {
+        use crate::occurrence::{find_occurences, DetectorOptions};
+
+        let event = |action: &str, thread_id: u64, millis: u64| {
+            serde_json::json!({
+                "action": action,
+                "thread_id": thread_id,
+                "method_id": 1,
+                "time": {"Monotonic": {"wall": {"nanos": millis * 1_000_000}}},
+            })
+        };
+        let mut profile = AndroidProfile {
+            platform: "android".to_string(),
+            profile: serde_json::from_value(serde_json::json!({
+                "clock": "Wall",
+                "events": [
+                    event("Enter", 1, 0),
+                    event("Enter", 2, 0),
+                    event("Exit", 1, 20),
+                    event("Exit", 2, 20),
+                ],
+                "methods": [{"class_name": "android.view.LayoutInflater", "id": 1, "name": "inflate", "signature": "(I)Landroid/view/View;"}],
+                "start_time": 0,
+                "threads": [{"id": 1, "name": "main"}, {"id": 2, "name": "AsyncLayoutInflator"}],
+            }))
+            .unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(profile.get_main_thread_id(), Some(1));
+
+        let call_trees = profile.call_trees().unwrap();
+        let occurrences = find_occurences(&profile, &call_trees, &DetectorOptions::default());
+        let categories: Vec<&str> = occurrences
+            .iter()
+            .map(|occurrence| occurrence.category.as_str())
+            .collect();
+        // inflating off the main thread is fine
+        assert_eq!(categories, vec!["view_inflation"]);
+    }
+
+    #[test]
+    fn test_call_trees_with_js_profile() {
+        let event = |action: &str, nanos: u64| {
+            serde_json::json!({
+                "action": action,
+                "thread_id": 1,
+                "method_id": 1,
+                "time": {"Monotonic": {"wall": {"nanos": nanos}}},
+            })
+        };
+        let mut profile = AndroidProfile {
+            profile: serde_json::from_value(serde_json::json!({
+                "clock": "Wall",
+                "events": [event("Enter", 0), event("Exit", 20)],
+                "methods": [{"class_name": "com.example.MainActivity", "id": 1, "name": "onCreate", "signature": "()V"}],
+                "start_time": 0,
+                "threads": [{"id": 1, "name": "main"}],
+            }))
+            .unwrap(),
+            ..Default::default()
+        };
+        let android_trees = profile.call_trees().unwrap();
+        let active_thread_id = profile.profile.active_thread_id();
+        let android_roots = android_trees[&active_thread_id].roots().len();
+
+        for (js_thread_id, want_roots) in [(2, 1), (active_thread_id, android_roots + 1)] {
+            profile.js_profile = Some(
+                serde_json::from_value(serde_json::json!({
+                    "profile": {
+                        "frames": [{"function": "render", "abs_path": "app:///index.bundle"}],
+                        "samples": [
+                            {"stack_id": 0, "thread_id": js_thread_id, "elapsed_since_start_ns": 0},
+                            {"stack_id": 0, "thread_id": js_thread_id, "elapsed_since_start_ns": 10},
+                        ],
+                        "stacks": [[0]],
+                        "thread_metadata": {js_thread_id.to_string(): {"name": "mqt_js"}},
+                    }
+                }))
+                .unwrap(),
+            );
+            profile.normalize();
+            let trees = profile.call_trees().unwrap();
+
+            assert_eq!(
+                trees.len(),
+                android_trees.len() + usize::from(js_thread_id == 2)
+            );
+            let tree = &trees[&js_thread_id];
+            assert_eq!(tree.roots().len(), want_roots);
+            assert!(tree.roots().iter().any(|&root| tree[root].name == "render"));
+            assert!(tree
+                .roots()
+                .windows(2)
+                .all(|roots| tree[roots[0]].start_ns <= tree[roots[1]].start_ns));
+        }
+    }
 }
@@ -0,0 +1,217 @@
+//! Scores how anomalous the data of a profile chunk is, from signals of
+//! malformed payloads, so data-quality dashboards and filters can exclude
+//! junk chunks.
+
+use pyo3::{pyclass, pymethods};
+
+use crate::{
+    frame::Frame,
+    nodetree::{is_obfuscated_frame, OBFUSCATION_SUPPORTED_PLATFORMS},
+    symbolication::is_unsymbolicated_frame,
+    timestamp::nanos_from_seconds,
+    types::ChunkInterface,
+};
+
+/// Clock skew at which the clock skew signal saturates.
+const MAX_CLOCK_SKEW_NS: u64 = 60_000_000_000;
+
+/// The anomaly score of a profile chunk, along with the signals it combines.
+/// Each signal is a ratio between 0 (nothing anomalous) and 1.
+#[pyclass]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnomalyScore {
+    pub score: f64,
+    pub unknown_frames: f64,
+    pub repaired_events: f64,
+    pub truncated_stacks: f64,
+    pub obfuscated_frames: f64,
+    pub clock_skew: f64,
+}
+
+#[pymethods]
+impl AnomalyScore {
+    /// Returns the anomaly score, combining all the signals.
+    ///
+    /// Returns:
+    ///     float
+    ///         The score, from 0 (nothing anomalous) to 1 (junk).
+    pub fn get_score(&self) -> f64 {
+        self.score
+    }
+
+    /// Returns the ratio of frames without a function name, or which
+    /// couldn't be symbolicated.
+    ///
+    /// Returns:
+    ///     float
+    ///         The ratio of unknown frames.
+    pub fn get_unknown_frames(&self) -> f64 {
+        self.unknown_frames
+    }
+
+    /// Returns the ratio of samples (or events) whose timestamp was repaired,
+    /// or fixed up while building the call trees.
+    ///
+    /// Returns:
+    ///     float
+    ///         The ratio of repaired events.
+    pub fn get_repaired_events(&self) -> f64 {
+        self.repaired_events
+    }
+
+    /// Returns the ratio of samples whose stack was truncated at the
+    /// maximum depth.
+    ///
+    /// Returns:
+    ///     float
+    ///         The ratio of truncated stacks.
+    pub fn get_truncated_stacks(&self) -> f64 {
+        self.truncated_stacks
+    }
+
+    /// Returns the ratio of frames still obfuscated, on platforms supporting
+    /// deobfuscation.
+    ///
+    /// Returns:
+    ///     float
+    ///         The ratio of obfuscated frames.
+    pub fn get_obfuscated_frames(&self) -> f64 {
+        self.obfuscated_frames
+    }
+
+    /// Returns how far in the future the chunk ended when it was received,
+    /// the clock of the device being ahead.
+    ///
+    /// Returns:
+    ///     float
+    ///         The clock skew, saturating at 1 for a minute or more.
+    pub fn get_clock_skew(&self) -> f64 {
+        self.clock_skew
+    }
+}
+
+impl AnomalyScore {
+    fn signals(&self) -> [f64; 5] {
+        [
+            self.unknown_frames,
+            self.repaired_events,
+            self.truncated_stacks,
+            self.obfuscated_frames,
+            self.clock_skew,
+        ]
+    }
+}
+
+fn is_unknown_frame(frame: &Frame) -> bool {
+    frame.function.as_deref().is_none_or(str::is_empty) || is_unsymbolicated_frame(frame)
+}
+
+fn ratio(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    (count as f64 / total as f64).min(1.0)
+}
+
+/// Returns the anomaly score of a chunk whose call trees were built, the
+/// repairs being counted while building them.
+///
+/// Signals are combined as independent probabilities of the chunk being
+/// junk, so a single strong signal is enough to get a high score.
+pub fn anomaly_score(chunk: &dyn ChunkInterface) -> AnomalyScore {
+    let platform = chunk.get_platform();
+    let frames = chunk.frames();
+    let unknown_frames = frames
+        .iter()
+        .filter(|frame| is_unknown_frame(frame))
+        .count();
+    let obfuscated_frames = frames
+        .iter()
+        .filter(|frame| {
+            let frame_platform = frame.platform.as_deref().unwrap_or(&platform);
+            OBFUSCATION_SUPPORTED_PLATFORMS.contains(frame_platform) && is_obfuscated_frame(frame)
+        })
+        .count();
+
+    let sample_count = chunk.sample_count();
+    let repaired_events =
+        chunk.fixed_up_events().values().sum::<u64>() + chunk.repaired_timestamps();
+
+    let clock_skew_ns = match (
+        nanos_from_seconds(chunk.end_timestamp()),
+        nanos_from_seconds(chunk.get_received()),
+    ) {
+        (Some(end_ns), Some(received_ns)) if received_ns > 0 => end_ns.saturating_sub(received_ns),
+        _ => 0,
+    };
+
+    let mut score = AnomalyScore {
+        unknown_frames: ratio(unknown_frames, frames.len()),
+        repaired_events: ratio(repaired_events as usize, sample_count),
+        truncated_stacks: ratio(chunk.truncated_stacks() as usize, sample_count),
+        obfuscated_frames: ratio(obfuscated_frames, frames.len()),
+        clock_skew: (clock_skew_ns as f64 / MAX_CLOCK_SKEW_NS as f64).min(1.0),
+        ..Default::default()
+    };
+    score.score = 1.0
+        - score
+            .signals()
+            .iter()
+            .map(|signal| 1.0 - signal)
+            .product::<f64>();
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::anomaly_score;
+    use crate::{
+        android::chunk::AndroidChunkBuilder, frame::Frame, sample::v2::SampleChunkBuilder,
+        types::ChunkInterface,
+    };
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_anomaly_score_clean_chunk() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let main = builder.frame(Frame {
+            function: Some("main".to_string()),
+            module: Some("app".to_string()),
+            ..Default::default()
+        });
+        builder.sample("1", 1.0, &[main]).sample("1", 1.01, &[main]);
+        let mut chunk = builder.build();
+        chunk.set_received(2.0);
+        chunk.call_trees(None).unwrap();
+        assert_eq!(anomaly_score(&chunk).get_score(), 0.0);
+
+        // the chunk ended after it was received
+        chunk.set_received(0.5);
+        let score = anomaly_score(&chunk);
+        assert!(score.clock_skew > 0.0 && score.clock_skew < 1.0);
+        assert!((score.score - score.clock_skew).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_anomaly_score_android_chunk() {
+        let mut builder = AndroidChunkBuilder::new(1737465052.0);
+        let on_create = builder.method("com.example.MainActivity", "onCreate", "()V", "");
+        let obfuscated = builder.method("a", "b", "()V", "");
+        builder
+            .thread(1, "main")
+            .enter(1, on_create, 0)
+            .enter(1, obfuscated, 10_000_000)
+            .exit(1, on_create, 50_000_000);
+        let mut chunk = builder.build();
+        chunk.set_received(1737465052.0 + 30.0);
+        chunk.call_trees(None).unwrap();
+
+        let score = anomaly_score(&chunk);
+        assert_eq!(score.obfuscated_frames, 0.5);
+        // the obfuscated frame was closed without an exit, out of 3 events
+        assert_eq!(score.repaired_events, 1.0 / 3.0);
+        assert_eq!(score.clock_skew, 0.0);
+        assert!((score.score - 2.0 / 3.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,38 @@
+//! The stable Rust API of vroomrs, for services processing profiles
+//! natively rather than through the Python bindings.
+//!
+//! Items re-exported here follow semver: they only change in breaking ways
+//! along with the major version. Enums and structs other crates could match
+//! on or build are `#[non_exhaustive]`, so variants and fields can be added
+//! in minor versions; options are built from their default with the `with_*`
+//! methods. Everything outside of this module is internal and may change in
+//! any release.
+//!
+//! ```no_run
+//! use vroomrs::api::{FunctionsMetricsOptions, ProfileChunk};
+//!
+//! # fn process(payload: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+//! let mut chunk = ProfileChunk::from_json_vec_and_version(payload, "2")?;
+//! chunk.normalize(None)?;
+//! let options = FunctionsMetricsOptions::default()
+//!     .with_min_depth(1)
+//!     .with_filter_system_frames(true)
+//!     .with_max_unique_functions(100)
+//!     .with_max_examples(5);
+//! let functions = chunk.extract_functions_metrics(&options)?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub use crate::{
+    frame::{
+        in_app_overrides::InAppOverridesError, Frame, FrameCategory, FrameOverrideError,
+        InAppOverrides, InAppRules,
+    },
+    nodetree::{CallTreeFunction, FunctionsMetricsOptions, FunctionsSortKey, Node},
+    occurrence::{DetectorError, DetectorOptions},
+    profile::Profile,
+    profile_chunk::ProfileChunk,
+    sample::v2::MergeError,
+    types::{CallTreeError, CallTreeOptions, PayloadError},
+};
@@ -0,0 +1,156 @@
+//! Exports the samples of chunks as Arrow record batches, one row per frame
+//! of each sample, for offline analytics to run SQL over raw samples.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use arrow_array::{
+    builder::{
+        BooleanBuilder, StringBuilder, TimestampNanosecondBuilder, UInt32Builder, UInt64Builder,
+    },
+    ArrayRef, RecordBatch,
+};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{ArrowError, DataType, Field, Schema, TimeUnit};
+
+use crate::{
+    sample::v2::{Sample, SampleChunk},
+    types::CallTreeError,
+};
+
+/// Returns the schema of the record batches of samples.
+pub fn samples_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("thread_id", DataType::Utf8, false),
+        Field::new(
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into())),
+            false,
+        ),
+        Field::new("depth", DataType::UInt32, false),
+        Field::new("function", DataType::Utf8, true),
+        Field::new("package", DataType::Utf8, false),
+        Field::new("in_app", DataType::Boolean, false),
+        Field::new("duration_ns", DataType::UInt64, false),
+    ])
+}
+
+/// Flattens the samples of a chunk into a record batch with a row per frame
+/// of each sample, the root frame being at depth 0.
+///
+/// Rows are sorted by thread ID, then timestamp and depth. A sample lasts
+/// until the next sample of its thread, so the last sample of each thread,
+/// only marking the end of the previous one, has no rows (like in call
+/// trees).
+pub fn samples_record_batch(chunk: &SampleChunk) -> Result<RecordBatch, CallTreeError> {
+    let mut samples_by_thread_id: BTreeMap<&str, Vec<&Sample>> = BTreeMap::new();
+    for sample in &chunk.profile.samples {
+        samples_by_thread_id
+            .entry(sample.thread_id.as_ref())
+            .or_default()
+            .push(sample);
+    }
+
+    let mut thread_ids = StringBuilder::new();
+    let mut timestamps = TimestampNanosecondBuilder::new().with_timezone("UTC");
+    let mut depths = UInt32Builder::new();
+    let mut functions = StringBuilder::new();
+    let mut packages = StringBuilder::new();
+    let mut in_apps = BooleanBuilder::new();
+    let mut durations = UInt64Builder::new();
+
+    for (thread_id, mut samples) in samples_by_thread_id {
+        samples.sort_by_key(|sample| sample.timestamp_ns);
+        for pair in samples.windows(2) {
+            let (sample, next) = (pair[0], pair[1]);
+            let stack = chunk.profile.stack(sample.stack_id)?;
+            for (depth, &frame_id) in stack.iter().rev().enumerate() {
+                let frame = &chunk.profile.frames[frame_id as usize];
+                thread_ids.append_value(thread_id);
+                timestamps.append_value(sample.timestamp_ns as i64);
+                depths.append_value(depth as u32);
+                functions.append_option(frame.function.as_deref());
+                packages.append_value(frame.module_or_package());
+                in_apps.append_value(frame.in_app.unwrap_or(false));
+                durations.append_value(next.timestamp_ns - sample.timestamp_ns);
+            }
+        }
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(thread_ids.finish()),
+        Arc::new(timestamps.finish()),
+        Arc::new(depths.finish()),
+        Arc::new(functions.finish()),
+        Arc::new(packages.finish()),
+        Arc::new(in_apps.finish()),
+        Arc::new(durations.finish()),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(samples_schema()), columns)
+        .expect("columns match the samples schema"))
+}
+
+/// Serializes a record batch in the Arrow IPC streaming format.
+pub fn to_ipc_stream(batch: &RecordBatch) -> Result<Vec<u8>, ArrowError> {
+    let mut writer = StreamWriter::try_new(Vec::new(), &batch.schema())?;
+    writer.write(batch)?;
+    writer.into_inner()
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::{cast::AsArray, types::UInt64Type};
+    use arrow_ipc::reader::StreamReader;
+
+    use super::{samples_record_batch, to_ipc_stream};
+    use crate::{frame::Frame, sample::v2::SampleChunkBuilder};
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_samples_record_batch() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let function = |name: &str| Frame {
+            function: Some(name.to_string()),
+            module: Some("app".to_string()),
+            in_app: Some(true),
+            ..Default::default()
+        };
+        let main = builder.frame(function("main"));
+        let load = builder.frame(function("load"));
+        builder
+            .sample("2", 10.0, &[main])
+            .sample("1", 10.1, &[main])
+            .sample("1", 10.0, &[load, main])
+            .sample("2", 10.2, &[main]);
+        let batch = samples_record_batch(&builder.build()).unwrap();
+
+        assert_eq!(batch.num_rows(), 3);
+        let thread_ids: Vec<&str> = batch
+            .column(0)
+            .as_string::<i32>()
+            .iter()
+            .flatten()
+            .collect();
+        assert_eq!(thread_ids, vec!["1", "1", "2"]);
+        let functions: Vec<&str> = batch
+            .column(3)
+            .as_string::<i32>()
+            .iter()
+            .flatten()
+            .collect();
+        assert_eq!(functions, vec!["main", "load", "main"]);
+        let durations: Vec<u64> = batch
+            .column(6)
+            .as_primitive::<UInt64Type>()
+            .values()
+            .to_vec();
+        assert_eq!(durations, vec![100_000_000, 100_000_000, 200_000_000]);
+
+        let payload = to_ipc_stream(&batch).unwrap();
+        let batches: Vec<_> = StreamReader::try_new(payload.as_slice(), None)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(batches, vec![batch]);
+    }
+}
@@ -0,0 +1,161 @@
+//! Analyses supported by platform, so callers can route work and surface
+//! what's available without keeping their own copy of this matrix.
+
+use std::collections::BTreeMap;
+
+use pyo3::{pyclass, pymethods};
+
+use crate::{
+    frame::IN_APP_RULES_PLATFORMS,
+    nodetree::OBFUSCATION_SUPPORTED_PLATFORMS,
+    occurrence::{
+        detector_categories_by_platform, APP_START_PRELUDE_PLATFORMS, FRAME_DROP_PLATFORMS,
+    },
+};
+
+/// The analyses supported for profiles of a platform.
+#[pyclass]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlatformCapabilities {
+    pub in_app_rules: bool,
+    pub occurrence_detectors: Vec<String>,
+    pub frame_drop: bool,
+    pub deobfuscation: bool,
+    pub app_start_prelude: bool,
+}
+
+#[pymethods]
+impl PlatformCapabilities {
+    /// Returns whether frames are classified as application or system
+    /// frames by our rules when the SDK doesn't classify them.
+    ///
+    /// Returns:
+    ///     bool
+    ///         True if the platform has in_app rules.
+    pub fn get_in_app_rules(&self) -> bool {
+        self.in_app_rules
+    }
+
+    /// Returns the categories of the occurrences detected in profiles of the
+    /// platform by the enabled detectors, including the ones registered at
+    /// runtime.
+    ///
+    /// Returns:
+    ///     list[str]
+    ///         The occurrence categories, sorted (e.g. "file_read", "image_decode").
+    pub fn get_occurrence_detectors(&self) -> Vec<String> {
+        self.occurrence_detectors.clone()
+    }
+
+    /// Returns whether frame drop causes are detected, from frozen frame
+    /// measurements.
+    ///
+    /// Returns:
+    ///     bool
+    ///         True if the platform SDKs measure frozen frames.
+    pub fn get_frame_drop(&self) -> bool {
+        self.frame_drop
+    }
+
+    /// Returns whether frames can be deobfuscated, in which case partially
+    /// deobfuscated frames are left out of function metrics.
+    ///
+    /// Returns:
+    ///     bool
+    ///         True if the platform supports deobfuscation.
+    pub fn get_deobfuscation(&self) -> bool {
+        self.deobfuscation
+    }
+
+    /// Returns whether the app start prelude (dyld, `+load` methods and
+    /// static initializers) can be analyzed.
+    ///
+    /// Returns:
+    ///     bool
+    ///         True if the platform profiles can capture the app start prelude.
+    pub fn get_app_start_prelude(&self) -> bool {
+        self.app_start_prelude
+    }
+}
+
+/// Returns the capabilities of every platform supporting at least one
+/// analysis, by platform.
+pub fn platform_capabilities() -> BTreeMap<String, PlatformCapabilities> {
+    let mut capabilities: BTreeMap<String, PlatformCapabilities> = BTreeMap::new();
+    for &platform in IN_APP_RULES_PLATFORMS {
+        capabilities
+            .entry(platform.to_string())
+            .or_default()
+            .in_app_rules = true;
+    }
+    for (platform, categories) in detector_categories_by_platform() {
+        capabilities
+            .entry(platform)
+            .or_default()
+            .occurrence_detectors = categories.into_iter().collect();
+    }
+    for &platform in FRAME_DROP_PLATFORMS {
+        capabilities
+            .entry(platform.to_string())
+            .or_default()
+            .frame_drop = true;
+    }
+    for platform in OBFUSCATION_SUPPORTED_PLATFORMS.iter() {
+        capabilities
+            .entry(platform.clone())
+            .or_default()
+            .deobfuscation = true;
+    }
+    for &platform in APP_START_PRELUDE_PLATFORMS {
+        capabilities
+            .entry(platform.to_string())
+            .or_default()
+            .app_start_prelude = true;
+    }
+    capabilities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::platform_capabilities;
+    use crate::occurrence::register_frame_detector;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_platform_capabilities() {
+        let capabilities = platform_capabilities();
+
+        let android = &capabilities["android"];
+        assert!(android.in_app_rules && android.frame_drop && android.deobfuscation);
+        assert!(android
+            .occurrence_detectors
+            .contains(&"image_decode".to_string()));
+
+        let java = &capabilities["java"];
+        assert!(!java.in_app_rules && !java.frame_drop && java.deobfuscation);
+        assert!(java.occurrence_detectors.is_empty());
+
+        let python = &capabilities["python"];
+        assert!(python.in_app_rules && !python.frame_drop && !python.deobfuscation);
+
+        let cocoa = &capabilities["cocoa"];
+        assert!(cocoa.app_start_prelude && !android.app_start_prelude);
+        let mut sorted = cocoa.occurrence_detectors.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(cocoa.occurrence_detectors, sorted);
+    }
+
+    #[test]
+    fn test_platform_capabilities_registered_detectors() {
+        register_frame_detector("capabilities_test", "capabilities-test", &[], 0, false).unwrap();
+        let capabilities = platform_capabilities();
+        let platform = &capabilities["capabilities-test"];
+        assert_eq!(
+            platform.occurrence_detectors,
+            vec!["capabilities_test".to_string()]
+        );
+        assert!(!platform.in_app_rules);
+    }
+}
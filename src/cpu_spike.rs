@@ -0,0 +1,362 @@
+//! Locates CPU usage spikes in profile chunks and attributes them to the
+//! stacks sampled while they happened.
+
+use std::collections::HashMap;
+
+use pyo3::{pyclass, pymethods};
+
+use crate::{
+    measurements::MeasurementValue,
+    nodetree::{CallTree, NodeId},
+    types::CallTreesStr,
+};
+
+/// A window during which the CPU usage stayed above a threshold.
+#[pyclass]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CpuSpike {
+    pub start_ns: u64,
+    pub end_ns: u64,
+    pub average_cpu_usage: f64,
+    pub max_cpu_usage: f64,
+    pub stacks: Vec<SpikeStack>,
+}
+
+/// A stack sampled during a CPU spike, with the time spent in it.
+#[pyclass]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpikeStack {
+    pub thread_id: String,
+    pub frames: Vec<String>,
+    pub duration_ns: u64,
+}
+
+#[pymethods]
+impl CpuSpike {
+    /// Returns the start timestamp of the spike in nanoseconds.
+    ///
+    /// Returns:
+    ///     int
+    ///         The start timestamp of the spike window.
+    pub fn get_start_ns(&self) -> u64 {
+        self.start_ns
+    }
+
+    /// Returns the end timestamp of the spike in nanoseconds.
+    ///
+    /// Returns:
+    ///     int
+    ///         The end timestamp of the spike window.
+    pub fn get_end_ns(&self) -> u64 {
+        self.end_ns
+    }
+
+    /// Returns the average CPU usage during the spike.
+    ///
+    /// Returns:
+    ///     float
+    ///         The average of the cpu_usage values measured during the spike.
+    pub fn get_average_cpu_usage(&self) -> f64 {
+        self.average_cpu_usage
+    }
+
+    /// Returns the maximum CPU usage during the spike.
+    ///
+    /// Returns:
+    ///     float
+    ///         The maximum of the cpu_usage values measured during the spike.
+    pub fn get_max_cpu_usage(&self) -> f64 {
+        self.max_cpu_usage
+    }
+
+    /// Returns the dominant stacks during the spike.
+    ///
+    /// Returns:
+    ///     list[SpikeStack]
+    ///         The stacks the most time was spent in, sorted by descending duration.
+    pub fn get_stacks(&self) -> Vec<SpikeStack> {
+        self.stacks.clone()
+    }
+}
+
+#[pymethods]
+impl SpikeStack {
+    /// Returns the thread ID.
+    ///
+    /// Returns:
+    ///     str
+    ///         The ID of the thread the stack was sampled on.
+    pub fn get_thread_id(&self) -> &str {
+        &self.thread_id
+    }
+
+    /// Returns the function names of the stack.
+    ///
+    /// Returns:
+    ///     list[str]
+    ///         The function names, from the outermost frame to the innermost one.
+    pub fn get_frames(&self) -> Vec<String> {
+        self.frames.clone()
+    }
+
+    /// Returns the time spent in the stack during the spike in nanoseconds.
+    ///
+    /// Returns:
+    ///     int
+    ///         The duration in nanoseconds.
+    pub fn get_duration_ns(&self) -> u64 {
+        self.duration_ns
+    }
+}
+
+/// Slides a `window_ns` window over the CPU usage values and returns the
+/// windows whose average usage reaches `threshold`, overlapping windows
+/// being merged together. Stacks are left empty.
+pub fn find_cpu_spikes(
+    values: &[MeasurementValue],
+    window_ns: u64,
+    threshold: f64,
+) -> Vec<CpuSpike> {
+    let mut values: Vec<(u64, f64)> = values.iter().map(|v| (v.timestamp_ns, v.value)).collect();
+    values.sort_by_key(|(timestamp_ns, _)| *timestamp_ns);
+
+    // spike windows, along with the range of values they contain
+    let mut windows: Vec<(CpuSpike, std::ops::Range<usize>)> = Vec::new();
+    for (i, &(start_ns, _)) in values.iter().enumerate() {
+        let end_ns = start_ns.saturating_add(window_ns);
+        let len = values[i..]
+            .iter()
+            .take_while(|(timestamp_ns, _)| *timestamp_ns < end_ns)
+            .count();
+        let average = values[i..i + len].iter().map(|(_, v)| v).sum::<f64>() / len as f64;
+        if average < threshold {
+            continue;
+        }
+        match windows.last_mut() {
+            Some((spike, range)) if spike.end_ns >= start_ns => {
+                spike.end_ns = end_ns;
+                range.end = i + len;
+            }
+            _ => windows.push((
+                CpuSpike {
+                    start_ns,
+                    end_ns,
+                    ..Default::default()
+                },
+                i..i + len,
+            )),
+        }
+    }
+
+    windows
+        .into_iter()
+        .map(|(mut spike, range)| {
+            let spike_values = &values[range];
+            spike.average_cpu_usage =
+                spike_values.iter().map(|(_, v)| v).sum::<f64>() / spike_values.len() as f64;
+            spike.max_cpu_usage = spike_values
+                .iter()
+                .map(|(_, v)| *v)
+                .fold(f64::MIN, f64::max);
+            spike
+        })
+        .collect()
+}
+
+/// Returns the `max_stacks` stacks the most time was spent in between
+/// `start_ns` and `end_ns`, sorted by descending duration.
+pub fn dominant_stacks(
+    call_trees: &CallTreesStr,
+    start_ns: u64,
+    end_ns: u64,
+    max_stacks: usize,
+) -> Vec<SpikeStack> {
+    let mut durations: HashMap<(&str, Vec<String>), u64> = HashMap::new();
+    for (thread_id, call_tree) in call_trees {
+        for &root in call_tree.roots() {
+            let mut frames = Vec::new();
+            collect_stack_durations(
+                call_tree,
+                root,
+                start_ns,
+                end_ns,
+                &mut frames,
+                &mut |frames, duration_ns| {
+                    *durations
+                        .entry((thread_id.as_ref(), frames.to_vec()))
+                        .or_default() += duration_ns;
+                },
+            );
+        }
+    }
+
+    let mut stacks: Vec<SpikeStack> = durations
+        .into_iter()
+        .map(|((thread_id, frames), duration_ns)| SpikeStack {
+            thread_id: thread_id.to_string(),
+            frames,
+            duration_ns,
+        })
+        .collect();
+    stacks.sort_by(|a, b| {
+        b.duration_ns
+            .cmp(&a.duration_ns)
+            .then_with(|| a.thread_id.cmp(&b.thread_id))
+            .then_with(|| a.frames.cmp(&b.frames))
+    });
+    stacks.truncate(max_stacks);
+    stacks
+}
+
+/// Walks the node and reports, for each stack, the time spent in its
+/// innermost frame within the window. Returns the time spent in the node.
+fn collect_stack_durations(
+    call_tree: &CallTree,
+    id: NodeId,
+    start_ns: u64,
+    end_ns: u64,
+    frames: &mut Vec<String>,
+    report: &mut impl FnMut(&[String], u64),
+) -> u64 {
+    let node = &call_tree[id];
+    let overlap_ns = node
+        .end_ns
+        .min(end_ns)
+        .saturating_sub(node.start_ns.max(start_ns));
+    if overlap_ns == 0 {
+        return 0;
+    }
+
+    frames.push(node.name.clone());
+    let children_ns: u64 = call_tree
+        .children(id)
+        .iter()
+        .map(|&child| collect_stack_durations(call_tree, child, start_ns, end_ns, frames, report))
+        .sum();
+    if overlap_ns > children_ns {
+        report(frames, overlap_ns - children_ns);
+    }
+    frames.pop();
+    overlap_ns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dominant_stacks, find_cpu_spikes, SpikeStack};
+    use crate::{
+        frame::Frame,
+        measurements::{Measurement, MeasurementValue},
+        sample::v2::SampleChunkBuilder,
+        types::ChunkInterface,
+    };
+
+    use pretty_assertions::assert_eq;
+
+    fn values(values: &[(f64, f64)]) -> Vec<MeasurementValue> {
+        Measurement::from_seconds("percent", values).values
+    }
+
+    #[test]
+    fn test_find_cpu_spikes() {
+        struct TestStruct {
+            name: String,
+            values: Vec<MeasurementValue>,
+            window_ns: u64,
+            threshold: f64,
+            want: Vec<(u64, u64, f64, f64)>,
+        }
+
+        let test_cases = [
+            TestStruct {
+                name: "no spike".to_string(),
+                values: values(&[(1.0, 10.0), (1.1, 20.0), (1.2, 10.0)]),
+                window_ns: 200_000_000,
+                threshold: 80.0,
+                want: vec![],
+            },
+            TestStruct {
+                name: "single spike".to_string(),
+                values: values(&[(1.0, 10.0), (1.1, 90.0), (1.2, 100.0), (1.3, 10.0)]),
+                window_ns: 200_000_000,
+                threshold: 80.0,
+                want: vec![(1_100_000_000, 1_300_000_000, 95.0, 100.0)],
+            },
+            TestStruct {
+                name: "overlapping windows are merged".to_string(),
+                values: values(&[(1.0, 90.0), (1.1, 90.0), (1.2, 90.0), (1.3, 10.0)]),
+                window_ns: 200_000_000,
+                threshold: 80.0,
+                want: vec![(1_000_000_000, 1_300_000_000, 90.0, 90.0)],
+            },
+            TestStruct {
+                name: "separate spikes".to_string(),
+                values: values(&[(1.0, 90.0), (1.5, 10.0), (2.0, 100.0)]),
+                window_ns: 100_000_000,
+                threshold: 80.0,
+                want: vec![
+                    (1_000_000_000, 1_100_000_000, 90.0, 90.0),
+                    (2_000_000_000, 2_100_000_000, 100.0, 100.0),
+                ],
+            },
+            TestStruct {
+                name: "window past the last timestamp".to_string(),
+                values: values(&[(1.0, 90.0), (1.1, 100.0)]),
+                window_ns: u64::MAX,
+                threshold: 80.0,
+                want: vec![(1_000_000_000, u64::MAX, 95.0, 100.0)],
+            },
+        ];
+
+        for test in test_cases {
+            let spikes: Vec<(u64, u64, f64, f64)> =
+                find_cpu_spikes(&test.values, test.window_ns, test.threshold)
+                    .into_iter()
+                    .map(|s| (s.start_ns, s.end_ns, s.average_cpu_usage, s.max_cpu_usage))
+                    .collect();
+            assert_eq!(spikes, test.want, "test `{}` failed", test.name);
+        }
+    }
+
+    #[test]
+    fn test_cpu_spike_stacks() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let frame = |name: &str| Frame {
+            function: Some(name.to_string()),
+            ..Default::default()
+        };
+        let main = builder.frame(frame("main"));
+        let idle = builder.frame(frame("idle"));
+        let busy = builder.frame(frame("busy"));
+        builder
+            .thread("1", "main")
+            .sample("1", 1.0, &[idle, main])
+            .sample("1", 1.1, &[busy, main])
+            .sample("1", 1.2, &[busy, main])
+            .sample("1", 1.3, &[main])
+            .sample("1", 1.4, &[idle, main])
+            .sample("1", 1.5, &[idle, main])
+            .measurement(
+                "cpu_usage",
+                "percent",
+                &[(1.0, 5.0), (1.1, 100.0), (1.2, 100.0), (1.4, 5.0)],
+            );
+        let mut chunk = builder.build();
+
+        let cpu_usage = chunk.measurement("cpu_usage").unwrap();
+        let spikes = find_cpu_spikes(&cpu_usage.values, 200_000_000, 90.0);
+        assert_eq!(spikes.len(), 1);
+
+        let call_trees = chunk.call_trees(None).unwrap();
+        let spike = &spikes[0];
+        let stacks = dominant_stacks(&call_trees, spike.start_ns, spike.end_ns, 1);
+        assert_eq!(
+            stacks,
+            vec![SpikeStack {
+                thread_id: "1".to_string(),
+                frames: vec!["main".to_string(), "busy".to_string()],
+                duration_ns: 200_000_000,
+            }]
+        );
+        assert_eq!(spike.max_cpu_usage, 100.0);
+    }
+}
@@ -0,0 +1,39 @@
+//! Exceptions raised to Python, so consumers can tell payloads which will
+//! never be processed (to route to a dead letter queue) from transient
+//! failures worth a retry.
+//!
+//! They all derive from `ValueError`, which every error used to be raised
+//! as, so existing handlers keep catching them.
+
+use pyo3::{create_exception, exceptions::PyValueError};
+
+create_exception!(
+    vroomrs,
+    VroomError,
+    PyValueError,
+    "Base class of the errors raised processing profiles and chunks."
+);
+create_exception!(
+    vroomrs,
+    ParseError,
+    VroomError,
+    "The payload isn't valid JSON, or doesn't match the format of its version."
+);
+create_exception!(
+    vroomrs,
+    UnsupportedVersion,
+    VroomError,
+    "The payload is of a version of the profile format which isn't supported."
+);
+create_exception!(
+    vroomrs,
+    InvalidStack,
+    VroomError,
+    "Samples reference stacks or frames which don't exist."
+);
+create_exception!(
+    vroomrs,
+    CompressionError,
+    VroomError,
+    "The payload couldn't be decompressed."
+);
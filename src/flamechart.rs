@@ -0,0 +1,216 @@
+//! Builds flamechart datasets of profile chunks on a shared time axis, so
+//! chunks covering the same wall-clock interval (e.g. two pods, or react
+//! native JS and native code) can be displayed side by side.
+
+use std::collections::BTreeMap;
+
+use pyo3::{pyclass, pymethods, PyErr};
+use serde::Serialize;
+
+use crate::{
+    nodetree::{CallTree, NodeId},
+    profile_chunk::ProfileChunk,
+    timestamp::nanos_from_seconds,
+    types::{CallTreeError, CallTreeOptions},
+};
+
+/// A function call, positioned on the shared time axis.
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct FlamechartSpan {
+    pub depth: u16,
+    /// Offset from the start of the shared time axis
+    pub start_ns: u64,
+    pub duration_ns: u64,
+    pub name: String,
+    pub package: String,
+    pub is_application: bool,
+}
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct Flamechart {
+    pub chunk_id: String,
+    pub profiler_id: String,
+    pub platform: String,
+    /// Spans by thread ID, sorted by start then depth
+    pub threads: BTreeMap<String, Vec<FlamechartSpan>>,
+}
+
+/// A pair of flamecharts sharing the same time axis.
+#[pyclass]
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct FlamechartComparison {
+    /// Origin of the shared time axis, as a Unix timestamp in nanoseconds
+    pub start_ns: u64,
+    pub end_ns: u64,
+    pub left: Flamechart,
+    pub right: Flamechart,
+}
+
+#[pymethods]
+impl FlamechartComparison {
+    /// Returns the origin of the shared time axis.
+    ///
+    /// Returns:
+    ///     int
+    ///         The Unix timestamp in nanoseconds span offsets are relative to.
+    pub fn get_start_ns(&self) -> u64 {
+        self.start_ns
+    }
+
+    /// Returns the end of the shared time axis.
+    ///
+    /// Returns:
+    ///     int
+    ///         The Unix timestamp in nanoseconds of the end of the latest chunk.
+    pub fn get_end_ns(&self) -> u64 {
+        self.end_ns
+    }
+
+    /// Returns the duration of the shared time axis.
+    ///
+    /// Returns:
+    ///     int
+    ///         The duration in nanoseconds.
+    pub fn get_duration_ns(&self) -> u64 {
+        self.end_ns - self.start_ns
+    }
+
+    /// Serializes the comparison to a JSON string.
+    ///
+    /// Returns:
+    ///     str
+    ///         A JSON string with the time axis metadata and the `left` and
+    ///         `right` flamecharts.
+    ///
+    /// Raises:
+    ///     ValueError
+    ///         If the serialization fails due to invalid data.
+    pub fn to_json_str(&self) -> Result<String, PyErr> {
+        serde_json::to_string(self)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))
+    }
+}
+
+fn timestamp_ns(timestamp: f64) -> u64 {
+    nanos_from_seconds(timestamp).unwrap_or_default()
+}
+
+/// Builds the flamecharts of both chunks, their spans being offset from the
+/// start of the earliest chunk.
+pub fn compare_chunks(
+    left: &mut ProfileChunk,
+    right: &mut ProfileChunk,
+) -> Result<FlamechartComparison, CallTreeError> {
+    let start_ns = timestamp_ns(
+        left.profile
+            .start_timestamp()
+            .min(right.profile.start_timestamp()),
+    );
+    let end_ns = timestamp_ns(
+        left.profile
+            .end_timestamp()
+            .max(right.profile.end_timestamp()),
+    );
+    Ok(FlamechartComparison {
+        start_ns,
+        end_ns,
+        left: flamechart(left, start_ns)?,
+        right: flamechart(right, start_ns)?,
+    })
+}
+
+fn flamechart(chunk: &mut ProfileChunk, origin_ns: u64) -> Result<Flamechart, CallTreeError> {
+    let mut flamechart = Flamechart {
+        chunk_id: chunk.profile.get_chunk_id().to_string(),
+        profiler_id: chunk.profile.get_profiler_id().to_string(),
+        platform: chunk.profile.get_platform(),
+        ..Default::default()
+    };
+    let call_trees = chunk.call_trees(None, CallTreeOptions::default())?;
+    for (thread_id, call_tree) in call_trees.iter() {
+        let mut spans = Vec::new();
+        for &root in call_tree.roots() {
+            collect_spans(call_tree, root, 0, origin_ns, &mut spans);
+        }
+        spans.sort_by_key(|span| (span.start_ns, span.depth));
+        flamechart.threads.insert(thread_id.to_string(), spans);
+    }
+    Ok(flamechart)
+}
+
+fn collect_spans(
+    call_tree: &CallTree,
+    id: NodeId,
+    depth: u16,
+    origin_ns: u64,
+    spans: &mut Vec<FlamechartSpan>,
+) {
+    let node = &call_tree[id];
+    spans.push(FlamechartSpan {
+        depth,
+        start_ns: node.start_ns.saturating_sub(origin_ns),
+        duration_ns: node.duration_ns,
+        name: node.name.clone(),
+        package: node.package.clone(),
+        is_application: node.is_application,
+    });
+    for &child in call_tree.children(id) {
+        collect_spans(call_tree, child, depth + 1, origin_ns, spans);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare_chunks, FlamechartSpan};
+    use crate::{frame::Frame, profile_chunk::ProfileChunk, sample::v2::SampleChunkBuilder};
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_compare_chunks() {
+        let build_chunk = |start: f64| {
+            let mut builder = SampleChunkBuilder::new("python");
+            let frame = |function: &str| Frame {
+                function: Some(function.to_string()),
+                module: Some("app".to_string()),
+                in_app: Some(true),
+                ..Default::default()
+            };
+            let (main, work) = (builder.frame(frame("main")), builder.frame(frame("work")));
+            builder
+                .sample("1", start, &[work, main])
+                .sample("1", start + 0.5, &[main])
+                .sample("1", start + 1.0, &[main]);
+            ProfileChunk::new(Box::new(builder.build()))
+        };
+        let mut left = build_chunk(10.0);
+        let mut right = build_chunk(10.25);
+
+        let comparison = compare_chunks(&mut left, &mut right).unwrap();
+        assert_eq!(comparison.start_ns, 10_000_000_000);
+        assert_eq!(comparison.end_ns, 11_250_000_000);
+
+        let span = |depth: u16, start_ns: u64, duration_ns: u64, name: &str| FlamechartSpan {
+            depth,
+            start_ns,
+            duration_ns,
+            name: name.to_string(),
+            package: "app".to_string(),
+            is_application: true,
+        };
+        assert_eq!(
+            comparison.left.threads["1"],
+            vec![
+                span(0, 0, 1_000_000_000, "main"),
+                span(1, 0, 500_000_000, "work"),
+            ]
+        );
+        assert_eq!(
+            comparison.right.threads["1"],
+            vec![
+                span(0, 250_000_000, 1_000_000_000, "main"),
+                span(1, 250_000_000, 500_000_000, "work"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,238 @@
+use serde::{Deserialize, Serialize};
+
+use super::{runtime::is_scheduler_function, Frame};
+
+/// Semantic category of a frame, computed during normalization so every
+/// consumer (e.g. flamegraph coloring) classifies frames the same way
+/// across platforms.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum FrameCategory {
+    /// Application code
+    App,
+    /// System libraries, runtimes and third party dependencies
+    System,
+    /// Garbage collection
+    Gc,
+    /// Threads waiting for work
+    Idle,
+    /// Just-in-time compilation
+    Jit,
+    /// Sentry SDK instrumentation
+    Sdk,
+    /// Green thread schedulers (goroutines, tokio, asyncio, Kotlin coroutines)
+    Scheduler,
+}
+
+/// Function names (or prefixes thereof) of garbage collection frames.
+const GC_FUNCTION_PREFIXES: &[&str] = &[
+    "(garbage collector)",
+    "Garbage Collection",
+    "art::gc::",
+    "dalvik.system.VMRuntime.",
+    "gc.collect",
+    "hermes::vm::GCBase",
+    "hermes::vm::HadesGC",
+    "java.lang.System.gc",
+    "v8::internal::Heap::CollectGarbage",
+];
+
+/// Function names (or prefixes thereof) of threads waiting for work.
+const IDLE_FUNCTION_PREFIXES: &[&str] = &[
+    "(idle)",
+    "__psynch_cvwait",
+    "__semwait_signal",
+    "__workq_kernreturn",
+    "android.os.MessageQueue.nativePollOnce",
+    "epoll_wait",
+    "mach_msg2_trap",
+    "mach_msg_trap",
+];
+
+/// Function names (or prefixes thereof) of just-in-time compilation frames.
+const JIT_FUNCTION_PREFIXES: &[&str] = &[
+    "(JIT)",
+    "art::jit::",
+    "v8::internal::Compiler::",
+    "v8::internal::maglev::",
+    "v8::internal::compiler::",
+];
+
+/// Module (or package) prefixes of the Sentry SDKs.
+const SDK_MODULE_PREFIXES: &[&str] = &["io.sentry.", "sentry_sdk", "Sentry\\", "@sentry/"];
+
+impl Frame {
+    /// Returns the semantic category of the frame. It relies on `in_app`,
+    /// so it should only be called once the frame is normalized.
+    pub(crate) fn classify(&self) -> FrameCategory {
+        let function = self.function.as_deref().unwrap_or_default();
+        let matches = |prefixes: &[&str]| prefixes.iter().any(|p| function.starts_with(p));
+
+        if self.is_sdk_frame() {
+            FrameCategory::Sdk
+        } else if matches(GC_FUNCTION_PREFIXES) {
+            FrameCategory::Gc
+        } else if matches(IDLE_FUNCTION_PREFIXES) {
+            FrameCategory::Idle
+        } else if matches(JIT_FUNCTION_PREFIXES) {
+            FrameCategory::Jit
+        } else if self.is_scheduler_frame() {
+            FrameCategory::Scheduler
+        } else if self.in_app.unwrap_or(false) {
+            FrameCategory::App
+        } else {
+            FrameCategory::System
+        }
+    }
+
+    fn is_scheduler_frame(&self) -> bool {
+        let Some(function) = self.function.as_deref() else {
+            return false;
+        };
+        let platform = self.platform.as_deref().unwrap_or_default();
+        match self.module.as_deref() {
+            Some(module) if !module.is_empty() && !function.starts_with(module) => {
+                is_scheduler_function(platform, &format!("{module}.{function}"))
+            }
+            _ => is_scheduler_function(platform, function),
+        }
+    }
+
+    fn is_sdk_frame(&self) -> bool {
+        if self.module_or_package() == "Sentry" {
+            return true;
+        }
+        let module = self
+            .module
+            .as_deref()
+            .or(self.function.as_deref())
+            .unwrap_or_default();
+        SDK_MODULE_PREFIXES
+            .iter()
+            .any(|prefix| module.starts_with(prefix))
+            || self
+                .path
+                .as_deref()
+                .is_some_and(|path| path.contains("node_modules/@sentry/"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FrameCategory;
+    use crate::frame::Frame;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_frame_category() {
+        struct TestStruct {
+            name: String,
+            platform: &'static str,
+            frame: Frame,
+            want: FrameCategory,
+        }
+
+        let test_cases = [
+            TestStruct {
+                name: "cocoa application frame".to_string(),
+                platform: "cocoa",
+                frame: Frame {
+                    function: Some("-[ViewController viewDidLoad]".to_string()),
+                    package: Some("/private/var/containers/Bundle/Application/MyApp.app/MyApp".to_string()),
+                    ..Default::default()
+                },
+                want: FrameCategory::App,
+            },
+            TestStruct {
+                name: "cocoa system frame".to_string(),
+                platform: "cocoa",
+                frame: Frame {
+                    function: Some("objc_msgSend".to_string()),
+                    package: Some("/usr/lib/libobjc.A.dylib".to_string()),
+                    ..Default::default()
+                },
+                want: FrameCategory::System,
+            },
+            TestStruct {
+                name: "cocoa idle frame".to_string(),
+                platform: "cocoa",
+                frame: Frame {
+                    function: Some("mach_msg2_trap".to_string()),
+                    package: Some("/usr/lib/system/libsystem_kernel.dylib".to_string()),
+                    ..Default::default()
+                },
+                want: FrameCategory::Idle,
+            },
+            TestStruct {
+                name: "cocoa sdk frame".to_string(),
+                platform: "cocoa",
+                frame: Frame {
+                    function: Some("-[SentryHub captureEvent:]".to_string()),
+                    package: Some("/private/var/containers/Bundle/Application/MyApp.app/Frameworks/Sentry.framework/Sentry".to_string()),
+                    ..Default::default()
+                },
+                want: FrameCategory::Sdk,
+            },
+            TestStruct {
+                name: "python sdk frame".to_string(),
+                platform: "python",
+                frame: Frame {
+                    function: Some("capture_event".to_string()),
+                    module: Some("sentry_sdk.client".to_string()),
+                    ..Default::default()
+                },
+                want: FrameCategory::Sdk,
+            },
+            TestStruct {
+                name: "node gc frame".to_string(),
+                platform: "node",
+                frame: Frame {
+                    function: Some("(garbage collector)".to_string()),
+                    ..Default::default()
+                },
+                want: FrameCategory::Gc,
+            },
+            TestStruct {
+                name: "android jit frame".to_string(),
+                platform: "android",
+                frame: Frame {
+                    function: Some("art::jit::JitCompiler::CompileMethod".to_string()),
+                    package: Some("/apex/com.android.art/lib64/libart-compiler.so".to_string()),
+                    ..Default::default()
+                },
+                want: FrameCategory::Jit,
+            },
+            TestStruct {
+                name: "python scheduler frame".to_string(),
+                platform: "python",
+                frame: Frame {
+                    function: Some("_run_once".to_string()),
+                    module: Some("asyncio.base_events".to_string()),
+                    ..Default::default()
+                },
+                want: FrameCategory::Scheduler,
+            },
+            TestStruct {
+                name: "go scheduler frame".to_string(),
+                platform: "go",
+                frame: Frame {
+                    function: Some("runtime.gopark".to_string()),
+                    ..Default::default()
+                },
+                want: FrameCategory::Scheduler,
+            },
+        ];
+
+        for mut test in test_cases {
+            test.frame.normalize(test.platform);
+            assert_eq!(
+                test.frame.category,
+                Some(test.want),
+                "test `{}` failed",
+                test.name
+            );
+        }
+    }
+}
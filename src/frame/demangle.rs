@@ -0,0 +1,118 @@
+//! Demangling of the function names of native frames.
+//!
+//! C++ (`_ZN...`, `__ZN...` on Apple platforms) and Rust (legacy and v0)
+//! symbols reach us mangled when the SDK couldn't symbolicate them on
+//! device. Demangling is opt-in and process-wide, see
+//! [`set_demangle_options`], as it changes the fingerprint of the functions
+//! it renames.
+
+use std::sync::RwLock;
+
+use symbolic_common::{Language, Name};
+use symbolic_demangle::{Demangle, DemangleOptions as SymbolicDemangleOptions};
+
+use super::Frame;
+
+static DEMANGLE_OPTIONS: RwLock<DemangleOptions> = RwLock::new(DemangleOptions {
+    enabled: false,
+    preserve_mangled: false,
+});
+
+/// Whether function names are demangled when frames are normalized.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DemangleOptions {
+    pub enabled: bool,
+    /// Keeps the mangled name in the `symbol` of frames which have none.
+    pub preserve_mangled: bool,
+}
+
+pub fn set_demangle_options(options: DemangleOptions) {
+    *DEMANGLE_OPTIONS.write().unwrap_or_else(|e| e.into_inner()) = options;
+}
+
+pub(crate) fn demangle_options() -> DemangleOptions {
+    *DEMANGLE_OPTIONS.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Returns the demangled name of a mangled C++ or Rust symbol, along with
+/// its parameters for C++, or None if it isn't one of those or is malformed.
+pub(crate) fn demangle(symbol: &str) -> Option<String> {
+    let name = Name::from(symbol);
+    if !matches!(name.detect_language(), Language::Cpp | Language::Rust) {
+        return None;
+    }
+    name.demangle(SymbolicDemangleOptions::name_only().parameters(true))
+        .filter(|demangled| demangled != symbol)
+}
+
+impl Frame {
+    /// Demangles the function name of the frame, see [`demangle`].
+    pub(crate) fn demangle_function(&mut self, options: DemangleOptions) {
+        if !options.enabled {
+            return;
+        }
+        let Some(demangled) = self.function.as_deref().and_then(demangle) else {
+            return;
+        };
+        let mangled = self.function.replace(demangled);
+        if options.preserve_mangled && self.symbol.is_none() {
+            self.symbol = mangled;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{demangle, DemangleOptions};
+    use crate::frame::Frame;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_demangle() {
+        let test_cases = [
+            ("_ZN3foo3barEv", Some("foo::bar()")),
+            ("__ZN3foo3barEi", Some("foo::bar(int)")),
+            (
+                "_ZN4core3ptr13drop_in_place17h9a9c6d1b2c7f1e2aE",
+                Some("core::ptr::drop_in_place"),
+            ),
+            ("_RNvCs1234_7mycrate3foo", Some("mycrate::foo")),
+            ("foo::bar", None),
+            ("-[NSManagedObjectContext save:]", None),
+            ("_Znot_mangled", None),
+            ("main", None),
+        ];
+        for (symbol, want) in test_cases {
+            assert_eq!(demangle(symbol).as_deref(), want, "{symbol}");
+        }
+    }
+
+    #[test]
+    fn test_demangle_function() {
+        let frame = Frame {
+            function: Some("_ZN3foo3barEv".to_string()),
+            ..Default::default()
+        };
+
+        let mut disabled = frame.clone();
+        disabled.demangle_function(DemangleOptions::default());
+        assert_eq!(disabled, frame);
+
+        let mut demangled = frame.clone();
+        demangled.demangle_function(DemangleOptions {
+            enabled: true,
+            preserve_mangled: false,
+        });
+        assert_eq!(demangled.function.as_deref(), Some("foo::bar()"));
+        assert_eq!(demangled.symbol, None);
+
+        let mut preserved = frame.clone();
+        preserved.demangle_function(DemangleOptions {
+            enabled: true,
+            preserve_mangled: true,
+        });
+        assert_eq!(preserved.function.as_deref(), Some("foo::bar()"));
+        assert_eq!(preserved.symbol.as_deref(), Some("_ZN3foo3barEv"));
+    }
+}
@@ -0,0 +1,370 @@
+//! Organization-scoped overrides of the in_app classification, for internal
+//! frameworks our heuristics misclassify.
+//!
+//! An override document lists package prefixes along with the in_app value
+//! forced on matching frames:
+//!
+//! ```json
+//! {"rules": [{"prefix": "com.acme.", "in_app": true}, {"prefix": "vendored", "in_app": false}]}
+//! ```
+//!
+//! Prefixes are matched against the frame module, or its package if it has no
+//! module (see [`Frame::module_or_package`]), the longest matching prefix
+//! winning.
+//!
+//! Projects can also pass their own rules when normalizing a chunk, as lists
+//! of prefixes forced in_app (`include`) or not (`exclude`) per platform:
+//!
+//! ```json
+//! {"android": {"include": ["com.mycompany."], "exclude": ["com.thirdparty."]}}
+//! ```
+//!
+//! The rules of the project take precedence over those of its organization.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+use super::Frame;
+use crate::limits::limits;
+
+static ORGANIZATION_OVERRIDES: Lazy<RwLock<HashMap<u64, Arc<InAppOverrides>>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct InAppRule {
+    pub prefix: String,
+    pub in_app: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct InAppOverrides {
+    /// Sorted by descending prefix length, so the first match is the longest.
+    rules: Vec<InAppRule>,
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum InAppOverridesError {
+    Json(serde_json::Error),
+    EmptyPrefix(usize),
+    DuplicatePrefix(String),
+    TooManyRules(usize, usize),
+    UnknownRuleList(String),
+}
+
+impl fmt::Display for InAppOverridesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InAppOverridesError::Json(error) => write!(f, "invalid document: {error}"),
+            InAppOverridesError::EmptyPrefix(index) => {
+                write!(f, "rule {index} has an empty prefix")
+            }
+            InAppOverridesError::DuplicatePrefix(prefix) => {
+                write!(f, "prefix `{prefix}` appears in several rules")
+            }
+            InAppOverridesError::TooManyRules(count, max_rules) => {
+                write!(f, "{count} rules, at most {max_rules} are allowed")
+            }
+            InAppOverridesError::UnknownRuleList(list) => {
+                write!(
+                    f,
+                    "unknown rule list `{list}`, expected `include` or `exclude`"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for InAppOverridesError {}
+
+impl InAppOverrides {
+    /// Parses and validates an override document.
+    pub fn from_json_str(document: &str) -> Result<Self, InAppOverridesError> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Document {
+            rules: Vec<InAppRule>,
+        }
+
+        let document: Document =
+            serde_json::from_str(document).map_err(InAppOverridesError::Json)?;
+        Self::new(document.rules)
+    }
+
+    pub fn new(mut rules: Vec<InAppRule>) -> Result<Self, InAppOverridesError> {
+        let max_rules = limits().max_in_app_rules;
+        if rules.len() > max_rules {
+            return Err(InAppOverridesError::TooManyRules(rules.len(), max_rules));
+        }
+        let mut prefixes = HashSet::new();
+        for (index, rule) in rules.iter().enumerate() {
+            if rule.prefix.is_empty() {
+                return Err(InAppOverridesError::EmptyPrefix(index));
+            }
+            if !prefixes.insert(rule.prefix.as_str()) {
+                return Err(InAppOverridesError::DuplicatePrefix(rule.prefix.clone()));
+            }
+        }
+        rules.sort_by_key(|rule| std::cmp::Reverse(rule.prefix.len()));
+        Ok(InAppOverrides { rules })
+    }
+
+    /// Returns the in_app value forced on frames of the module (or package),
+    /// if any.
+    pub fn in_app(&self, module_or_package: &str) -> Option<bool> {
+        self.rules
+            .iter()
+            .find(|rule| module_or_package.starts_with(&rule.prefix))
+            .map(|rule| rule.in_app)
+    }
+}
+
+/// The `include` and `exclude` prefix lists of the in_app rules of a project,
+/// by platform.
+pub type InAppRuleLists = HashMap<String, HashMap<String, Vec<String>>>;
+
+/// The in_app rules of a project, by platform.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct InAppRules {
+    platforms: HashMap<String, InAppOverrides>,
+}
+
+impl InAppRules {
+    /// Validates the `include` and `exclude` prefix lists of each platform.
+    pub fn new(rules: InAppRuleLists) -> Result<Self, InAppOverridesError> {
+        let mut platforms = HashMap::with_capacity(rules.len());
+        for (platform, lists) in rules {
+            let mut platform_rules = Vec::new();
+            for (list, prefixes) in lists {
+                let in_app = match list.as_str() {
+                    "include" => true,
+                    "exclude" => false,
+                    _ => return Err(InAppOverridesError::UnknownRuleList(list)),
+                };
+                platform_rules.extend(
+                    prefixes
+                        .into_iter()
+                        .map(|prefix| InAppRule { prefix, in_app }),
+                );
+            }
+            platforms.insert(platform, InAppOverrides::new(platform_rules)?);
+        }
+        Ok(InAppRules { platforms })
+    }
+
+    /// Returns the overrides of the frames of a platform, if any.
+    pub fn overrides(&self, platform: &str) -> Option<&InAppOverrides> {
+        self.platforms.get(platform)
+    }
+}
+
+/// Sets the overrides applied to the profiles of the organization when
+/// they're normalized, replacing the previous ones.
+pub fn set_organization_overrides(organization_id: u64, overrides: InAppOverrides) {
+    ORGANIZATION_OVERRIDES
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(organization_id, Arc::new(overrides));
+}
+
+pub fn clear_organization_overrides(organization_id: u64) {
+    ORGANIZATION_OVERRIDES
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&organization_id);
+}
+
+pub(crate) fn organization_overrides(organization_id: u64) -> Option<Arc<InAppOverrides>> {
+    ORGANIZATION_OVERRIDES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&organization_id)
+        .cloned()
+}
+
+impl Frame {
+    /// Forces the in_app value of the frame if it matches an override.
+    pub(crate) fn apply_in_app_overrides(&mut self, overrides: &InAppOverrides) {
+        if let Some(in_app) = overrides.in_app(&self.module_or_package()) {
+            self.in_app = Some(in_app);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InAppOverrides, InAppRule, InAppRules};
+    use crate::frame::{Frame, FrameCategory};
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_from_json_str() {
+        struct TestStruct {
+            name: String,
+            document: &'static str,
+            want: Result<Vec<(&'static str, bool)>, &'static str>,
+        }
+
+        let test_cases = [
+            TestStruct {
+                name: "rules are sorted by descending prefix length".to_string(),
+                document: r#"{"rules": [{"prefix": "com.acme.", "in_app": true}, {"prefix": "com.acme.vendor.", "in_app": false}]}"#,
+                want: Ok(vec![("com.acme.vendor.", false), ("com.acme.", true)]),
+            },
+            TestStruct {
+                name: "empty prefix".to_string(),
+                document: r#"{"rules": [{"prefix": "", "in_app": true}]}"#,
+                want: Err("rule 0 has an empty prefix"),
+            },
+            TestStruct {
+                name: "duplicate prefix".to_string(),
+                document: r#"{"rules": [{"prefix": "a", "in_app": true}, {"prefix": "a", "in_app": false}]}"#,
+                want: Err("prefix `a` appears in several rules"),
+            },
+            TestStruct {
+                name: "unknown field".to_string(),
+                document: r#"{"rules": [{"prefix": "a", "in_app": true, "inapp": false}]}"#,
+                want: Err("invalid document: unknown field `inapp`, expected `prefix` or `in_app` at line 1 column 50"),
+            },
+            TestStruct {
+                name: "missing in_app".to_string(),
+                document: r#"{"rules": [{"prefix": "a"}]}"#,
+                want: Err("invalid document: missing field `in_app` at line 1 column 26"),
+            },
+        ];
+
+        for test in test_cases {
+            let got = InAppOverrides::from_json_str(test.document)
+                .map(|overrides| {
+                    overrides
+                        .rules
+                        .iter()
+                        .map(|rule| (rule.prefix.clone(), rule.in_app))
+                        .collect::<Vec<_>>()
+                })
+                .map_err(|e| e.to_string());
+            assert_eq!(
+                got,
+                test.want
+                    .map(|rules| rules
+                        .into_iter()
+                        .map(|(prefix, in_app)| (prefix.to_string(), in_app))
+                        .collect())
+                    .map_err(|e| e.to_string()),
+                "test `{}` failed",
+                test.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_apply_in_app_overrides() {
+        let overrides = InAppOverrides::new(vec![
+            InAppRule {
+                prefix: "acme".to_string(),
+                in_app: true,
+            },
+            InAppRule {
+                prefix: "acme.vendored".to_string(),
+                in_app: false,
+            },
+        ])
+        .unwrap();
+        let frame = |module: &str, in_app: bool| Frame {
+            function: Some("run".to_string()),
+            module: Some(module.to_string()),
+            in_app: Some(in_app),
+            platform: Some("python".to_string()),
+            ..Default::default()
+        };
+
+        let mut framework = frame("acme.jobs", false);
+        framework.normalize_with_overrides("python", Some(&overrides), None);
+        assert_eq!(framework.in_app, Some(true));
+        assert_eq!(framework.category, Some(FrameCategory::App));
+
+        let mut vendored = frame("acme.vendored.requests", true);
+        vendored.normalize_with_overrides("python", Some(&overrides), None);
+        assert_eq!(vendored.in_app, Some(false));
+
+        let mut other = frame("billing", true);
+        other.normalize_with_overrides("python", Some(&overrides), None);
+        assert_eq!(other.in_app, Some(true));
+    }
+
+    #[test]
+    fn test_apply_in_app_rules() {
+        let lists = |include: &[&str], exclude: &[&str]| {
+            HashMap::from([
+                (
+                    "include".to_string(),
+                    include.iter().map(|p| p.to_string()).collect(),
+                ),
+                (
+                    "exclude".to_string(),
+                    exclude.iter().map(|p| p.to_string()).collect(),
+                ),
+            ])
+        };
+        let rules = InAppRules::new(HashMap::from([(
+            "python".to_string(),
+            lists(&["acme.vendored"], &["acme"]),
+        )]))
+        .unwrap();
+        // the organization overrides are superseded by the project rules
+        let overrides = InAppOverrides::new(vec![InAppRule {
+            prefix: "acme".to_string(),
+            in_app: true,
+        }])
+        .unwrap();
+        let frame = |module: &str, platform: &str| Frame {
+            function: Some("run".to_string()),
+            module: Some(module.to_string()),
+            platform: Some(platform.to_string()),
+            ..Default::default()
+        };
+
+        let mut framework = frame("acme.jobs", "python");
+        framework.normalize_with_overrides("python", Some(&overrides), Some(&rules));
+        assert_eq!(framework.in_app, Some(false));
+        assert_eq!(framework.category, Some(FrameCategory::System));
+
+        let mut vendored = frame("acme.vendored.requests", "python");
+        vendored.normalize_with_overrides("python", Some(&overrides), Some(&rules));
+        assert_eq!(vendored.in_app, Some(true));
+
+        // rules only apply to the frames of their platform
+        let mut javascript = Frame {
+            path: Some("/app/node_modules/acme/vendored.js".to_string()),
+            ..frame("acme.vendored", "javascript")
+        };
+        javascript.normalize_with_overrides("python", None, Some(&rules));
+        assert_eq!(javascript.in_app, Some(false));
+
+        let errors = [
+            (
+                HashMap::from([(
+                    "python".to_string(),
+                    HashMap::from([("includes".to_string(), vec!["acme".to_string()])]),
+                )]),
+                "unknown rule list `includes`, expected `include` or `exclude`",
+            ),
+            (
+                HashMap::from([("python".to_string(), lists(&["acme"], &["acme"]))]),
+                "prefix `acme` appears in several rules",
+            ),
+        ];
+        for (rules, want) in errors {
+            assert_eq!(InAppRules::new(rules).unwrap_err().to_string(), want);
+        }
+    }
+}
@@ -1,12 +1,29 @@
+mod category;
+mod demangle;
+pub mod in_app_overrides;
+mod node;
+mod overrides;
 mod python_std_lib;
+mod runtime;
+mod swift;
+mod v8;
 
-use std::{collections::HashSet, hash::Hasher};
+pub use category::FrameCategory;
+pub use demangle::{set_demangle_options, DemangleOptions};
+pub use in_app_overrides::{InAppOverrides, InAppRules};
+pub use overrides::{FrameOverrideError, FrameOverrides};
+pub(crate) use runtime::skip_scheduler_frames;
+pub use runtime::GARBAGE_COLLECTION_FUNCTION;
+pub use swift::{set_swift_simplify_options, SwiftSimplifyOptions};
+
+use std::collections::HashSet;
 
-use fnv_rs::Fnv64;
 use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
+use crate::hashing::function_fingerprint;
+
 static WINDOWS_PATH_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)^([a-z]:\\|\\\\)").unwrap());
 static PACKAGE_EXTENSION_REGEX: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"\.(dylib|so|a|dll|exe)$").unwrap());
@@ -16,7 +33,11 @@ static COCOA_SYSTEM_PACKAGE: Lazy<HashSet<&'static str>> =
     Lazy::new(|| HashSet::from(["Sentry", "hermes"]));
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub struct Frame {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<FrameCategory>,
+
     #[serde(rename = "colno", skip_serializing_if = "Option::is_none")]
     pub column: Option<u32>,
 
@@ -81,6 +102,73 @@ pub(crate) fn is_android_system_package(package: &str) -> bool {
         || package.starts_with("[anon:dalvik-")
 }
 
+/// Platforms whose frames our rules classify as application or system
+/// frames when the SDK doesn't (see `set_in_app`).
+pub(crate) const IN_APP_RULES_PLATFORMS: &[&str] = &[
+    "android",
+    "cocoa",
+    "javascript",
+    "native",
+    "node",
+    "php",
+    "python",
+    "rust",
+];
+
+/// Directories the system libraries of linux are installed in.
+const LINUX_SYSTEM_LIBRARY_PREFIXES: &[&str] = &[
+    "/lib/",
+    "/lib32/",
+    "/lib64/",
+    "/usr/lib/",
+    "/usr/lib32/",
+    "/usr/lib64/",
+];
+
+/// Directories (lowercased, without their drive) the system libraries of
+/// windows are installed in.
+const WINDOWS_SYSTEM_LIBRARY_PREFIXES: &[&str] = &[
+    "\\windows\\",
+    "\\program files\\common files\\",
+    "\\program files (x86)\\common files\\",
+];
+
+/// File name prefixes (lowercased) of the runtime libraries of windows,
+/// often shipped along with the application.
+const WINDOWS_RUNTIME_LIBRARY_PREFIXES: &[&str] = &[
+    "api-ms-win-",
+    "concrt",
+    "msvcp",
+    "ucrtbase",
+    "vccorlib",
+    "vcruntime",
+];
+
+/// Determines whether a native frame's package is a library of the system
+/// (windows or linux) rather than of the application.
+fn is_native_system_package(package: &str) -> bool {
+    if package.starts_with('[') || package.starts_with("linux-vdso") {
+        // [vdso], [heap], ... mappings of the kernel
+        return true;
+    }
+    if WINDOWS_PATH_REGEX.is_match(package) {
+        let package = package.to_lowercase();
+        let directory = package
+            .split_once(':')
+            .map_or(package.as_str(), |(_, path)| path);
+        let filename = package.rsplit('\\').next().unwrap_or_default();
+        return WINDOWS_SYSTEM_LIBRARY_PREFIXES
+            .iter()
+            .any(|prefix| directory.starts_with(prefix))
+            || WINDOWS_RUNTIME_LIBRARY_PREFIXES
+                .iter()
+                .any(|prefix| filename.starts_with(prefix));
+    }
+    LINUX_SYSTEM_LIBRARY_PREFIXES
+        .iter()
+        .any(|prefix| package.starts_with(prefix))
+}
+
 /// Platform, runtime and SDK class-name namespaces for android/JVM frames. A
 /// frame whose class (module) starts with one of these is a system frame;
 /// everything else — the app's own code and its bundled libraries are application code.
@@ -136,11 +224,36 @@ pub struct Data {
 
     #[serde(rename = "symbolicated", skip_serializing_if = "Option::is_none")]
     pub js_symbolicated: Option<bool>,
+
+    /// The subsystem (io, network, crypto, ...) of the frames of runtime
+    /// modules, e.g. the builtin modules of Node.js.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+
+    /// Fields SDKs add that are not modeled above (e.g. experiments), kept
+    /// as is so they survive processing.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Returns the name of the Composer package (`vendor/package`) a file
+/// installed in a `vendor` directory belongs to.
+fn composer_package(path: &str) -> Option<String> {
+    let (_, installed) = path.split_once("/vendor/")?;
+    let mut segments = installed.splitn(3, '/');
+    match (segments.next(), segments.next(), segments.next()) {
+        (Some(vendor), Some(package), Some(file))
+            if !vendor.is_empty() && !package.is_empty() && !file.is_empty() =>
+        {
+            Some(format!("{vendor}/{package}"))
+        }
+        _ => None,
+    }
 }
 
 // Taken from https://github.com/getsentry/sentry/blob/1c9cf8bd92f65e933a407d8ee37fb90997c1c76c/static/app/components/events/interfaces/frame/utils.tsx#L8-L12
 // This takes a frame's package and formats it in such a way that is suitable for displaying/aggregation.
-fn trim_package(pkg: &str) -> String {
+pub(crate) fn trim_package(pkg: &str) -> String {
     let separator = if WINDOWS_PATH_REGEX.is_match(pkg) {
         '\\'
     } else {
@@ -191,6 +304,26 @@ impl Frame {
             .is_none_or(|path| !path.starts_with("node:") && !path.contains("node_modules"))
     }
 
+    /// Sets the category of the frame data of the frames of the builtin
+    /// modules of Node.js, unless the SDK set one.
+    fn set_node_data_category(&mut self) {
+        if self.platform.as_deref() != Some("node") {
+            return;
+        }
+        let Some(category) = self
+            .path
+            .as_deref()
+            .or(self.file.as_deref())
+            .and_then(node::builtin_module_category)
+        else {
+            return;
+        };
+        let data = self.data.get_or_insert_with(Data::default);
+        if data.category.is_none() {
+            data.category = Some(category.to_string());
+        }
+    }
+
     fn is_javascript_application_frame(&self) -> bool {
         if let Some(function) = &self.function {
             if function.starts_with('[') {
@@ -234,6 +367,12 @@ impl Frame {
             .is_some_and(|package| is_cocoa_application_package(package))
     }
 
+    fn is_native_application_frame(&self) -> bool {
+        self.package
+            .as_deref()
+            .is_some_and(|package| !package.is_empty() && !is_native_system_package(package))
+    }
+
     fn is_rust_application_frame(&self) -> bool {
         self.package.as_ref().is_some_and(|package| {
             !package.contains("/library/std/src/")
@@ -279,6 +418,19 @@ impl Frame {
             .is_none_or(|path| !path.contains("/vendor/"))
     }
 
+    /// Sets the package of PHP frames of Composer dependencies to the name of
+    /// their Composer package (`vendor/package`), taken from their path, so
+    /// their functions are grouped by package.
+    fn set_php_package(&mut self) {
+        if self.platform.as_deref() != Some("php") || self.package.is_some() {
+            return;
+        }
+        let Some(path) = self.path.as_deref().or(self.file.as_deref()) else {
+            return;
+        };
+        self.package = composer_package(&path.replace('\\', "/"));
+    }
+
     fn is_android_application_frame(&self) -> bool {
         if self
             .package
@@ -334,6 +486,7 @@ impl Frame {
             "java" | "native" | "android" if p == "android" => self
                 .in_app
                 .unwrap_or_else(|| self.is_android_application_frame()),
+            "native" => self.is_native_application_frame(),
             _ => false,
         };
 
@@ -351,6 +504,56 @@ impl Frame {
         }
     }
 
+    /// Strips the V8 optimization markers of node function names, so the
+    /// optimized and deoptimized variants of a function merge.
+    fn strip_v8_markers(&mut self) {
+        if self.platform.as_deref() != Some("node") {
+            return;
+        }
+        if let Some(function) = self
+            .function
+            .as_deref()
+            .and_then(v8::strip_optimization_markers)
+        {
+            self.function = Some(function);
+        }
+    }
+
+    /// Folds the garbage collection frames of language runtimes into a
+    /// synthetic frame, shared by all of them.
+    fn fold_runtime_frames(&mut self) {
+        let platform = self.platform.as_deref().unwrap_or_default();
+        let function = self.function.as_deref().unwrap_or_default();
+        if !runtime::is_gc_function(platform, function) {
+            return;
+        }
+        *self = Frame {
+            data: self.data.take(),
+            function: Some(GARBAGE_COLLECTION_FUNCTION.to_string()),
+            in_app: Some(false),
+            method_id: self.method_id,
+            platform: self.platform.take(),
+            status: self.status.take(),
+            is_react_native: self.is_react_native,
+            ..Default::default()
+        };
+    }
+
+    /// Returns whether the frame is the synthetic frame garbage collection
+    /// frames are folded into.
+    pub(crate) fn is_folded_gc_frame(&self) -> bool {
+        self.function.as_deref() == Some(GARBAGE_COLLECTION_FUNCTION)
+            && self.category == Some(FrameCategory::Gc)
+    }
+
+    /// Flags the javascript frames of native profiles as react-native
+    /// frames, which are only symbolicated through source maps.
+    fn set_react_native(&mut self, p: &str) {
+        if p != "javascript" && self.platform.as_deref() == Some("javascript") {
+            self.is_react_native = true;
+        }
+    }
+
     fn set_status(&mut self) {
         if let Some(data) = &self.data {
             if let Some(symbolicator_status) = &data.symbolicator_status {
@@ -362,10 +565,39 @@ impl Frame {
     }
 
     pub fn normalize(&mut self, p: &str) {
+        self.normalize_with_overrides(p, None, None);
+    }
+
+    /// Normalizes the frame, the in_app overrides of the organization taking
+    /// precedence over the in_app heuristics, and the in_app rules of the
+    /// project over both.
+    pub fn normalize_with_overrides(
+        &mut self,
+        p: &str,
+        overrides: Option<&InAppOverrides>,
+        in_app_rules: Option<&InAppRules>,
+    ) {
         // Call order is important since set_in_app uses status and platform
+        // and the category relies on in_app. Runtime frames are folded last
+        // so no rule marks them as application frames.
         self.set_status();
         self.set_platform(p);
+        self.demangle_function(demangle::demangle_options());
+        self.simplify_swift_function(swift::swift_simplify_options());
+        self.set_php_package();
+        self.set_node_data_category();
+        self.set_react_native(p);
+        self.strip_v8_markers();
         self.set_in_app(p);
+        if let Some(overrides) = overrides {
+            self.apply_in_app_overrides(overrides);
+        }
+        let platform = self.platform.as_deref().unwrap_or(p);
+        if let Some(overrides) = in_app_rules.and_then(|rules| rules.overrides(platform)) {
+            self.apply_in_app_overrides(overrides);
+        }
+        self.fold_runtime_frames();
+        self.category = Some(self.classify());
     }
 
     /// Returns the module name if present, otherwise returns the trimmed package name.
@@ -379,20 +611,30 @@ impl Frame {
 
         if let Some(package) = &self.package {
             if !package.is_empty() {
-                return trim_package(package);
+                return self.trimmed_package(package);
             }
         }
 
         String::new()
     }
 
+    /// Returns the package formatted for display and aggregation. Composer
+    /// package names of PHP frames are kept whole, they aren't paths.
+    fn trimmed_package(&self, package: &str) -> String {
+        if self.platform.as_deref() == Some("php") {
+            package.to_string()
+        } else {
+            trim_package(package)
+        }
+    }
+
     /// Writes frame data to the provided hash implementation.
     /// This is used to create a unique identifier for the frame.
     pub fn write_to_hash<H: std::hash::Hasher>(&self, h: &mut H) {
         let s = if let Some(module) = &self.module {
             module
         } else if let Some(package) = &self.package {
-            &trim_package(package)
+            &self.trimmed_package(package)
         } else if let Some(file) = &self.file {
             file
         } else {
@@ -404,26 +646,62 @@ impl Frame {
         let s = self.function.as_deref().unwrap_or("-");
         h.write(s.as_bytes());
 
-        // Important for native platforms to distinguish unknown frames
-        if let Some(addr) = &self.instruction_addr {
+        // Functions sharing a name in different files are different
+        // functions, unless the file was hashed already in the absence of
+        // module and package.
+        if self.module.is_some() || self.package.is_some() {
+            if let Some(filename) = self.filename() {
+                h.write(filename.as_bytes());
+            }
+        }
+
+        // Important for native platforms to distinguish unknown frames.
+        // The symbol address is the start of the function, shared by every
+        // instruction of it, so samples hitting the same function at
+        // different instructions are grouped together. Without it, the name
+        // of a symbolicated function identifies it already, and only the
+        // instruction address is left to tell unknown functions apart.
+        if let Some(addr) = self.sym_addr.as_ref().filter(|addr| !addr.is_empty()) {
+            h.write(addr.as_bytes());
+        } else if let (None, Some(addr)) = (&self.function, &self.instruction_addr) {
             h.write(addr.as_bytes());
         }
     }
 
+    /// Returns the name of the file of the frame, without its directory.
+    pub fn filename(&self) -> Option<&str> {
+        let path = [self.path.as_deref(), self.file.as_deref()]
+            .into_iter()
+            .flatten()
+            .find(|path| !path.is_empty())?;
+        path.rsplit(['/', '\\'])
+            .next()
+            .filter(|name| !name.is_empty())
+    }
+
+    /// Returns the fingerprint of the function of the frame, see
+    /// [`function_fingerprint`].
     pub fn fingerprint(&self, parent_fingerprint: Option<u32>) -> u32 {
-        let mut hasher = Fnv64::default();
-        hasher.write(self.module_or_package().as_bytes());
-        hasher.write(":".as_bytes());
-        hasher.write(self.function.as_deref().unwrap_or_default().as_bytes());
-        if let Some(parent_fingerprint) = parent_fingerprint {
-            hasher.write_u32(parent_fingerprint);
-        }
+        function_fingerprint(
+            self.function.as_deref().unwrap_or_default(),
+            &self.module_or_package(),
+            parent_fingerprint,
+        )
+    }
+
+    /// Returns a field of the frame data not modeled by [`Data`].
+    pub fn data_field(&self, key: &str) -> Option<&serde_json::Value> {
+        self.data.as_ref()?.extra.get(key)
+    }
+
+    /// Returns a string field of the frame data not modeled by [`Data`].
+    pub fn data_str(&self, key: &str) -> Option<&str> {
+        self.data_field(key)?.as_str()
+    }
 
-        // casting to an uint32 here because snuba does not handle uint64 values well
-        // as it is converted to a float somewhere not changing to the 32 bit hash
-        // function here to preserve backwards compatibility with existing fingerprints
-        // that we can cast
-        hasher.finish() as u32
+    /// Returns a boolean field of the frame data not modeled by [`Data`].
+    pub fn data_bool(&self, key: &str) -> Option<bool> {
+        self.data_field(key)?.as_bool()
     }
 }
 
@@ -431,7 +709,8 @@ impl Frame {
 mod tests {
     use std::hash::Hasher;
 
-    use super::Frame;
+    use super::{Data, Frame};
+    use crate::hashing::{function_fingerprint, FingerprintHasher};
 
     #[test]
     fn test_is_cocoa_application_frame() {
@@ -767,6 +1046,67 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_is_native_application_frame() {
+        let test_cases = [
+            ("C:\\Windows\\System32\\ntdll.dll", false),
+            ("c:\\windows\\SysWOW64\\KERNELBASE.dll", false),
+            ("C:\\Games\\MyGame\\VCRUNTIME140.dll", false),
+            ("C:\\Games\\MyGame\\MyGame.exe", true),
+            ("D:\\Games\\MyGame\\Engine.dll", true),
+            ("/usr/lib/x86_64-linux-gnu/libstdc++.so.6", false),
+            ("/lib64/ld-linux-x86-64.so.2", false),
+            ("linux-vdso.so.1", false),
+            ("/opt/mygame/bin/mygame", true),
+            ("/home/user/mygame/libengine.so", true),
+            ("", false),
+        ];
+        for (package, is_application) in test_cases {
+            let mut frame = Frame {
+                function: Some("update".to_string()),
+                package: Some(package.to_string()),
+                ..Default::default()
+            };
+            frame.normalize("native");
+            assert_eq!(frame.in_app, Some(is_application), "{package}");
+        }
+    }
+
+    #[test]
+    fn test_set_php_package() {
+        let test_cases = [
+            (
+                "/var/www/http/vendor/cakephp/cakephp/src/Http/Client.php",
+                Some("cakephp/cakephp"),
+            ),
+            (
+                "C:\\www\\vendor\\symfony\\http-kernel\\Kernel.php",
+                Some("symfony/http-kernel"),
+            ),
+            ("/var/www/http/src/Middleware/SentryMiddleware.php", None),
+            ("/var/www/http/vendor/autoload.php", None),
+        ];
+        for (path, want) in test_cases {
+            let mut frame = Frame {
+                function: Some("handle".to_string()),
+                path: Some(path.to_string()),
+                ..Default::default()
+            };
+            frame.normalize("php");
+            assert_eq!(frame.package.as_deref(), want, "{path}");
+        }
+
+        // package names are kept whole when grouping functions
+        let mut frame = Frame {
+            function: Some("Cake\\Http\\Client::send".to_string()),
+            path: Some("/var/www/http/vendor/cakephp/cakephp/src/Http/Client.php".to_string()),
+            ..Default::default()
+        };
+        frame.normalize("php");
+        assert_eq!(frame.module_or_package(), "cakephp/cakephp");
+        assert_eq!(frame.in_app, Some(false));
+    }
+
     #[test]
     fn test_is_php_application_frame() {
         struct TestStruct {
@@ -893,7 +1233,7 @@ mod tests {
             },
             TestStruct {
                 name: "prefers function module over package".to_string(),
-                bytes: "foo-".as_bytes(),
+                bytes: "foo-baz".as_bytes(),
                 frame: Frame {
                     module: Some("foo".to_string()),
                     package: Some("/bar/bar".to_string()),
@@ -903,7 +1243,7 @@ mod tests {
             },
             TestStruct {
                 name: "prefers package over file".to_string(),
-                bytes: "bar-".as_bytes(),
+                bytes: "bar-baz".as_bytes(),
                 frame: Frame {
                     package: Some("/bar/bar".to_string()),
                     file: Some("baz".to_string()),
@@ -918,6 +1258,15 @@ mod tests {
                     ..Default::default()
                 },
             },
+            TestStruct {
+                name: "uses the file name of the path".to_string(),
+                bytes: "foo-server.js".as_bytes(),
+                frame: Frame {
+                    module: Some("foo".to_string()),
+                    path: Some("/app/src/server.js".to_string()),
+                    ..Default::default()
+                },
+            },
             TestStruct {
                 name: "uses function name".to_string(),
                 bytes: "-qux".as_bytes(),
@@ -934,6 +1283,39 @@ mod tests {
                     ..Default::default()
                 },
             },
+            TestStruct {
+                name: "native unknown frame with a symbol address".to_string(),
+                bytes: "MyApp-0x123456000".as_bytes(),
+                frame: Frame {
+                    instruction_addr: Some("0x123456789".to_string()),
+                    package: Some(
+                        "/private/var/containers/Bundle/Application/MyApp.app/MyApp".to_string(),
+                    ),
+                    sym_addr: Some("0x123456000".to_string()),
+                    ..Default::default()
+                },
+            },
+            TestStruct {
+                name: "native unknown frame with an empty symbol address".to_string(),
+                bytes: "--0x123456789".as_bytes(),
+                frame: Frame {
+                    instruction_addr: Some("0x123456789".to_string()),
+                    sym_addr: Some(String::new()),
+                    ..Default::default()
+                },
+            },
+            TestStruct {
+                name: "native symbolicated frame".to_string(),
+                bytes: "MyApp-[Foo bar]".as_bytes(),
+                frame: Frame {
+                    function: Some("-[Foo bar]".to_string()),
+                    instruction_addr: Some("0x123456789".to_string()),
+                    package: Some(
+                        "/private/var/containers/Bundle/Application/MyApp.app/MyApp".to_string(),
+                    ),
+                    ..Default::default()
+                },
+            },
         ];
 
         for test_case in test_cases {
@@ -1293,4 +1675,173 @@ mod tests {
         sys.normalize("android");
         assert_eq!(sys.in_app, Some(true));
     }
+
+    #[test]
+    fn test_normalize_node_data_category() {
+        let mut frame = Frame {
+            function: Some("readFileSync".to_string()),
+            path: Some("node:fs".to_string()),
+            ..Default::default()
+        };
+        frame.normalize("node");
+        assert_eq!(
+            frame.data.and_then(|data| data.category).as_deref(),
+            Some("io")
+        );
+        assert_eq!(frame.in_app, Some(false));
+
+        // categories set by the SDK are kept
+        let mut frame = Frame {
+            function: Some("connect".to_string()),
+            path: Some("node:net".to_string()),
+            data: Some(Data {
+                category: Some("db".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        frame.normalize("node");
+        assert_eq!(
+            frame.data.and_then(|data| data.category).as_deref(),
+            Some("db")
+        );
+    }
+
+    #[test]
+    fn test_normalize_v8_markers() {
+        let names = [
+            "*handleRequest",
+            "~handleRequest",
+            "handleRequest (deoptimized)",
+        ];
+        let fingerprints: Vec<u32> = names
+            .iter()
+            .map(|name| {
+                let mut frame = Frame {
+                    function: Some(name.to_string()),
+                    path: Some("/app/server.js".to_string()),
+                    ..Default::default()
+                };
+                frame.normalize("node");
+                assert_eq!(frame.function.as_deref(), Some("handleRequest"));
+                frame.fingerprint(None)
+            })
+            .collect();
+        assert!(fingerprints.iter().all(|&f| f == fingerprints[0]));
+
+        let mut frame = Frame {
+            function: Some("*handleRequest".to_string()),
+            ..Default::default()
+        };
+        frame.normalize("cocoa");
+        assert_eq!(frame.function.as_deref(), Some("*handleRequest"));
+    }
+
+    #[test]
+    fn test_filename() {
+        let test_cases = [
+            (Some("/app/src/server.js"), None, Some("server.js")),
+            (Some("C:\\app\\Program.cs"), None, Some("Program.cs")),
+            (None, Some("views.py"), Some("views.py")),
+            (Some(""), Some("views.py"), Some("views.py")),
+            (Some("/app/src/"), None, None),
+            (None, None, None),
+        ];
+        for (path, file, want) in test_cases {
+            let frame = Frame {
+                path: path.map(str::to_string),
+                file: file.map(str::to_string),
+                ..Default::default()
+            };
+            assert_eq!(frame.filename(), want, "{path:?} {file:?}");
+        }
+    }
+
+    #[test]
+    fn test_write_to_hash_same_function() {
+        let hash = |function: Option<&str>, sym_addr: Option<&str>, instruction_addr: &str| {
+            let mut h = FingerprintHasher::default();
+            Frame {
+                function: function.map(str::to_string),
+                instruction_addr: Some(instruction_addr.to_string()),
+                package: Some("/usr/lib/libobjc.A.dylib".to_string()),
+                sym_addr: sym_addr.map(str::to_string),
+                ..Default::default()
+            }
+            .write_to_hash(&mut h);
+            h.finish()
+        };
+
+        // instructions of the same function, by their symbol address or name
+        assert_eq!(
+            hash(None, Some("0x100004000"), "0x100004010"),
+            hash(None, Some("0x100004000"), "0x100004020")
+        );
+        assert_eq!(
+            hash(Some("objc_msgSend"), None, "0x100004010"),
+            hash(Some("objc_msgSend"), None, "0x100004020")
+        );
+        // unknown functions are still told apart
+        assert_ne!(
+            hash(None, Some("0x100004000"), "0x100004010"),
+            hash(None, Some("0x100008000"), "0x100008010")
+        );
+        assert_ne!(
+            hash(None, None, "0x100004010"),
+            hash(None, None, "0x100008010")
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_filename() {
+        let frame = |path: Option<&str>, line: Option<u32>| Frame {
+            function: Some("handle".to_string()),
+            module: Some("app".to_string()),
+            path: path.map(str::to_string),
+            line,
+            ..Default::default()
+        };
+        let hash = |frame: Frame| {
+            let mut h = FingerprintHasher::default();
+            frame.write_to_hash(&mut h);
+            h.finish()
+        };
+
+        // frames of functions sharing a name in different files differ, not
+        // at different lines of the same file
+        let frame_hash = hash(frame(Some("/app/users.py"), Some(10)));
+        assert_eq!(frame_hash, hash(frame(Some("/srv/app/users.py"), Some(12))));
+        assert_ne!(frame_hash, hash(frame(Some("/app/orders.py"), Some(10))));
+        assert_ne!(frame_hash, hash(frame(None, Some(10))));
+
+        // function fingerprints only depend on the package and name, as vroom's
+        let fingerprint = frame(Some("/app/users.py"), Some(10)).fingerprint(None);
+        assert_eq!(fingerprint, function_fingerprint("handle", "app", None));
+        assert_eq!(
+            fingerprint,
+            frame(Some("/app/orders.py"), Some(12)).fingerprint(None)
+        );
+        assert_eq!(fingerprint, frame(None, None).fingerprint(None));
+    }
+
+    #[test]
+    fn test_data_passthrough() {
+        let payload = r#"{"function":"foo","data":{"symbolicator_status":"symbolicated","client_module":"app/Foo","is_hot_path":true}}"#;
+        let mut frame: Frame = serde_json::from_str(payload).unwrap();
+        frame.normalize("cocoa");
+
+        assert_eq!(frame.data_str("client_module"), Some("app/Foo"));
+        assert_eq!(frame.data_bool("is_hot_path"), Some(true));
+        assert_eq!(frame.data_bool("client_module"), None);
+        assert_eq!(frame.data_field("missing"), None);
+        assert_eq!(
+            frame.data.as_ref().unwrap().symbolicator_status.as_deref(),
+            Some("symbolicated")
+        );
+
+        let round_trip: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(&frame).unwrap()).unwrap();
+        assert_eq!(round_trip["data"]["client_module"], "app/Foo");
+        assert_eq!(round_trip["data"]["is_hot_path"], true);
+    }
 }
@@ -0,0 +1,70 @@
+//! Categorization of the frames of the builtin modules of Node.js.
+//!
+//! The frames of builtin modules (`node:fs`, `node:internal/fs/promises`,
+//! ...) are all system frames. Their category, stored in the frame data,
+//! tells which subsystem the time was spent in, so detectors and the UI can
+//! break system time down.
+
+/// Categories of the builtin modules, by module name. Internal modules
+/// (`internal/fs/utils`) and vendored dependencies (`internal/deps/undici`)
+/// are categorized by the first segment of their name.
+const BUILTIN_MODULE_CATEGORIES: &[(&str, &str)] = &[
+    ("child_process", "process"),
+    ("cluster", "process"),
+    ("crypto", "crypto"),
+    ("dgram", "network"),
+    ("dns", "network"),
+    ("fs", "io"),
+    ("http", "network"),
+    ("http2", "network"),
+    ("https", "network"),
+    ("module", "module"),
+    ("modules", "module"),
+    ("net", "network"),
+    ("readline", "io"),
+    ("stream", "io"),
+    ("streams", "io"),
+    ("timers", "timers"),
+    ("tls", "network"),
+    ("undici", "network"),
+    ("zlib", "compression"),
+];
+
+/// Returns the category of a frame of a builtin module, from its path.
+pub(crate) fn builtin_module_category(path: &str) -> Option<&'static str> {
+    let module = path.strip_prefix("node:")?;
+    let module = module.strip_prefix("internal/").unwrap_or(module);
+    let module = module.strip_prefix("deps/").unwrap_or(module);
+    let name = module.split('/').next()?.trim_end_matches(".js");
+    BUILTIN_MODULE_CATEGORIES
+        .iter()
+        .find(|(module, _)| *module == name)
+        .map(|(_, category)| *category)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::builtin_module_category;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_builtin_module_category() {
+        let test_cases = [
+            ("node:fs", Some("io")),
+            ("node:internal/fs/promises", Some("io")),
+            ("node:net", Some("network")),
+            ("node:internal/deps/undici/undici", Some("network")),
+            ("node:crypto", Some("crypto")),
+            ("node:internal/crypto/hash", Some("crypto")),
+            ("node:zlib", Some("compression")),
+            ("node:internal/modules/cjs/loader", Some("module")),
+            ("node:events", None),
+            ("/app/node_modules/fs-extra/lib/index.js", None),
+            ("fs", None),
+        ];
+        for (path, want) in test_cases {
+            assert_eq!(builtin_module_category(path), want, "{path}");
+        }
+    }
+}
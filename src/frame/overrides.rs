@@ -0,0 +1,176 @@
+use std::fmt;
+
+use pyo3::{
+    exceptions::PyValueError,
+    types::{PyAnyMethods, PyDict, PyDictMethods},
+    Bound, PyAny, PyErr, PyResult, Python,
+};
+
+use super::Frame;
+
+/// Frame fields overridden by enrichment pipelines, `None` meaning the field
+/// is left untouched.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FrameOverrides {
+    pub function: Option<String>,
+    pub module: Option<String>,
+    pub package: Option<String>,
+    pub path: Option<String>,
+    pub line: Option<u32>,
+    pub in_app: Option<bool>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FrameOverrideError {
+    /// The field can't be overridden for frames of this format.
+    UnsupportedField(&'static str),
+    InvalidFrameIndex(usize),
+}
+
+impl fmt::Display for FrameOverrideError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameOverrideError::UnsupportedField(field) => {
+                write!(f, "`{field}` can't be overridden for this profile format")
+            }
+            FrameOverrideError::InvalidFrameIndex(index) => {
+                write!(f, "invalid frame index {index}")
+            }
+        }
+    }
+}
+
+impl From<FrameOverrideError> for PyErr {
+    fn from(error: FrameOverrideError) -> Self {
+        PyValueError::new_err(error.to_string())
+    }
+}
+
+impl FrameOverrides {
+    pub fn is_empty(&self) -> bool {
+        *self == FrameOverrides::default()
+    }
+
+    /// Returns the first overridden field which isn't in `supported`.
+    pub(crate) fn unsupported_field(&self, supported: &[&str]) -> Option<&'static str> {
+        [
+            ("function", self.function.is_some()),
+            ("module", self.module.is_some()),
+            ("package", self.package.is_some()),
+            ("path", self.path.is_some()),
+            ("lineno", self.line.is_some()),
+            ("in_app", self.in_app.is_some()),
+        ]
+        .into_iter()
+        .find(|(field, overridden)| *overridden && !supported.contains(field))
+        .map(|(field, _)| field)
+    }
+
+    /// Reads the overrides returned by a Python callback: either None, or a
+    /// dict with some of the keys of [`Frame::to_py_dict`].
+    pub(crate) fn from_py(value: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let mut overrides = FrameOverrides::default();
+        if value.is_none() {
+            return Ok(overrides);
+        }
+        let dict = value.cast::<PyDict>()?;
+        for (key, value) in dict.iter() {
+            let key: String = key.extract()?;
+            match key.as_str() {
+                "function" => overrides.function = value.extract()?,
+                "module" => overrides.module = value.extract()?,
+                "package" => overrides.package = value.extract()?,
+                "path" => overrides.path = value.extract()?,
+                "lineno" => overrides.line = value.extract()?,
+                "in_app" => overrides.in_app = value.extract()?,
+                _ => {
+                    return Err(PyValueError::new_err(format!(
+                        "`{key}` can't be overridden"
+                    )))
+                }
+            }
+        }
+        Ok(overrides)
+    }
+}
+
+impl Frame {
+    /// Overrides the frame fields. The category is computed again if the
+    /// frame was already normalized, since it relies on `in_app`.
+    pub(crate) fn apply_overrides(&mut self, overrides: &FrameOverrides) {
+        let FrameOverrides {
+            function,
+            module,
+            package,
+            path,
+            line,
+            in_app,
+        } = overrides.clone();
+        self.function = function.or(self.function.take());
+        self.module = module.or(self.module.take());
+        self.package = package.or(self.package.take());
+        self.path = path.or(self.path.take());
+        self.line = line.or(self.line);
+        self.in_app = in_app.or(self.in_app);
+        if self.category.is_some() {
+            self.category = Some(self.classify());
+        }
+    }
+
+    /// Returns the fields exposed to Python callbacks.
+    pub(crate) fn to_py_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("function", &self.function)?;
+        dict.set_item("module", &self.module)?;
+        dict.set_item("package", &self.package)?;
+        dict.set_item("path", &self.path)?;
+        dict.set_item("lineno", self.line)?;
+        dict.set_item("in_app", self.in_app)?;
+        dict.set_item("platform", &self.platform)?;
+        Ok(dict)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FrameOverrides;
+    use crate::frame::{Frame, FrameCategory};
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_apply_overrides() {
+        let mut frame = Frame {
+            function: Some("handle".to_string()),
+            module: Some("vendored.http".to_string()),
+            in_app: Some(false),
+            line: Some(12),
+            ..Default::default()
+        };
+        frame.normalize("python");
+        assert_eq!(frame.category, Some(FrameCategory::System));
+
+        frame.apply_overrides(&FrameOverrides {
+            module: Some("app.http".to_string()),
+            in_app: Some(true),
+            ..Default::default()
+        });
+        assert_eq!(frame.function.as_deref(), Some("handle"));
+        assert_eq!(frame.module.as_deref(), Some("app.http"));
+        assert_eq!(frame.line, Some(12));
+        assert_eq!(frame.in_app, Some(true));
+        assert_eq!(frame.category, Some(FrameCategory::App));
+    }
+
+    #[test]
+    fn test_unsupported_field() {
+        let overrides = FrameOverrides {
+            path: Some("Foo.kt".to_string()),
+            function: Some("foo".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(overrides.unsupported_field(&["path"]), Some("function"));
+        assert_eq!(overrides.unsupported_field(&["path", "function"]), None);
+    }
+}
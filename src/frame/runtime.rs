@@ -0,0 +1,204 @@
+//! Normalization of the frames of language runtimes.
+//!
+//! Runtimes report their garbage collector under many names (`[GC Young
+//! Gen]` and `[GC Old Gen]` for Hermes, `(garbage collector)` for V8, the
+//! `GCDaemon` and `HeapTaskDaemon` threads for ART), scattering its time
+//! across leaf nodes. These frames are folded into a single synthetic
+//! frame, so the time spent collecting garbage adds up in one node.
+//!
+//! Green threads (goroutines, tokio tasks, asyncio tasks, Kotlin
+//! coroutines) run below the frames of their scheduler, which would
+//! otherwise be the hottest functions of every profile. Scheduler frames
+//! are dropped from the stacks running a task, attributing their time to
+//! the task frames instead.
+
+use super::FrameCategory;
+
+/// Function name of the synthetic frame garbage collection frames are
+/// folded into.
+pub const GARBAGE_COLLECTION_FUNCTION: &str = "Garbage Collection";
+
+/// Function names (or prefixes thereof) of the garbage collection frames
+/// of javascript runtimes.
+const JAVASCRIPT_GC_FUNCTION_PREFIXES: &[&str] =
+    &["(garbage collector)", "[GC Young Gen]", "[GC Old Gen]"];
+
+/// Function names (or prefixes thereof) of the garbage collection frames
+/// of the JVM and ART, whose function names are qualified by their class.
+const JAVA_GC_FUNCTION_PREFIXES: &[&str] = &[
+    "dalvik.system.VMRuntime.runHeapTasks",
+    "java.lang.Daemons$GCDaemon.",
+    "java.lang.Daemons$HeapTaskDaemon.",
+    "java.lang.Runtime.gc",
+    "java.lang.System.gc",
+];
+
+/// Qualified function names (or prefixes thereof) of the green thread
+/// schedulers of the go runtime.
+const GO_SCHEDULER_FUNCTION_PREFIXES: &[&str] = &[
+    "runtime.findRunnable",
+    "runtime.goexit",
+    "runtime.gopark",
+    "runtime.mcall",
+    "runtime.park_m",
+    "runtime.schedule",
+];
+
+/// Qualified function names (or prefixes thereof) of the asyncio event loop.
+const PYTHON_SCHEDULER_FUNCTION_PREFIXES: &[&str] = &[
+    "asyncio.base_events.",
+    "asyncio.events.",
+    "asyncio.runners.",
+    "selectors.",
+    "uvloop.",
+];
+
+/// Qualified function names (or prefixes thereof) of the tokio runtime.
+const RUST_SCHEDULER_FUNCTION_PREFIXES: &[&str] = &["<tokio::runtime::", "tokio::runtime::"];
+
+/// Qualified function names (or prefixes thereof) of the Kotlin coroutine
+/// dispatchers.
+const KOTLIN_SCHEDULER_FUNCTION_PREFIXES: &[&str] = &[
+    "kotlin.coroutines.jvm.internal.BaseContinuationImpl.resumeWith",
+    "kotlinx.coroutines.DispatchedTask.run",
+    "kotlinx.coroutines.EventLoopImplBase.processNextEvent",
+    "kotlinx.coroutines.internal.LimitedDispatcher",
+    "kotlinx.coroutines.scheduling.CoroutineScheduler",
+];
+
+/// Returns the garbage collection function names (or prefixes thereof) of
+/// the runtime of a frame platform.
+fn gc_function_prefixes(platform: &str) -> &'static [&'static str] {
+    match platform {
+        "javascript" | "node" => JAVASCRIPT_GC_FUNCTION_PREFIXES,
+        "android" | "java" => JAVA_GC_FUNCTION_PREFIXES,
+        _ => &[],
+    }
+}
+
+/// Returns whether a function of a frame platform belongs to the garbage
+/// collector of its runtime.
+pub(crate) fn is_gc_function(platform: &str, function: &str) -> bool {
+    gc_function_prefixes(platform)
+        .iter()
+        .any(|prefix| function.starts_with(prefix))
+}
+
+/// Returns the green thread scheduler function names (or prefixes thereof)
+/// of the runtime of a frame platform.
+fn scheduler_function_prefixes(platform: &str) -> &'static [&'static str] {
+    match platform {
+        "go" => GO_SCHEDULER_FUNCTION_PREFIXES,
+        "python" => PYTHON_SCHEDULER_FUNCTION_PREFIXES,
+        "rust" => RUST_SCHEDULER_FUNCTION_PREFIXES,
+        "android" | "java" => KOTLIN_SCHEDULER_FUNCTION_PREFIXES,
+        _ => &[],
+    }
+}
+
+/// Returns whether a function of a frame platform, qualified by its module,
+/// belongs to the green thread scheduler of its runtime.
+pub(crate) fn is_scheduler_function(platform: &str, qualified_function: &str) -> bool {
+    scheduler_function_prefixes(platform)
+        .iter()
+        .any(|prefix| qualified_function.starts_with(prefix))
+}
+
+/// Drops the scheduler frames of a stack, leaf first, found below a frame
+/// of the task they scheduled. Stacks of parked green threads keep their
+/// scheduler frames, idle frames not being part of any task.
+pub(crate) fn skip_scheduler_frames<I: Copy>(
+    stack: &mut Vec<I>,
+    category: impl Fn(I) -> Option<FrameCategory>,
+) {
+    let mut runs_task = false;
+    stack.retain(|&id| match category(id) {
+        Some(FrameCategory::Scheduler) => !runs_task,
+        Some(FrameCategory::Idle) => true,
+        _ => {
+            runs_task = true;
+            true
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_gc_function, is_scheduler_function, skip_scheduler_frames};
+    use crate::frame::FrameCategory;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_is_gc_function() {
+        let test_cases = [
+            ("node", "(garbage collector)", true),
+            ("javascript", "[GC Young Gen]", true),
+            ("javascript", "[GC Old Gen]", true),
+            ("android", "java.lang.Daemons$GCDaemon.runInternal()", true),
+            ("java", "java.lang.System.gc()", true),
+            (
+                "android",
+                "java.lang.Daemons$FinalizerDaemon.runInternal()",
+                false,
+            ),
+            ("node", "java.lang.System.gc()", false),
+            ("cocoa", "(garbage collector)", false),
+            ("node", "collectGarbage", false),
+        ];
+        for (platform, function, want) in test_cases {
+            assert_eq!(
+                is_gc_function(platform, function),
+                want,
+                "{platform} {function}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_scheduler_function() {
+        let test_cases = [
+            ("go", "runtime.gopark", true),
+            ("go", "runtime.goexit", true),
+            ("go", "main.handleRequest", false),
+            ("python", "asyncio.base_events._run_once", true),
+            ("python", "asyncio.tasks.sleep", false),
+            (
+                "rust",
+                "tokio::runtime::task::harness::Harness<T,S>::poll",
+                true,
+            ),
+            ("rust", "app::handle_request", false),
+            ("android", "kotlinx.coroutines.DispatchedTask.run()", true),
+            ("node", "runtime.gopark", false),
+        ];
+        for (platform, function, want) in test_cases {
+            assert_eq!(
+                is_scheduler_function(platform, function),
+                want,
+                "{platform} {function}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_skip_scheduler_frames() {
+        // 0: task, 1: scheduler, 2: idle, 3: thread entry point
+        let category = |id: usize| match id {
+            0 => Some(FrameCategory::App),
+            1 => Some(FrameCategory::Scheduler),
+            2 => Some(FrameCategory::Idle),
+            _ => Some(FrameCategory::System),
+        };
+        let test_cases = [
+            (vec![0, 1, 1, 3], vec![0, 3]),
+            (vec![2, 1, 1, 3], vec![2, 1, 1, 3]),
+            (vec![1, 0, 1, 3], vec![1, 0, 3]),
+            (vec![0, 3], vec![0, 3]),
+        ];
+        for (mut stack, want) in test_cases {
+            skip_scheduler_frames(&mut stack, category);
+            assert_eq!(stack, want);
+        }
+    }
+}
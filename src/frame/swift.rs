@@ -0,0 +1,226 @@
+//! Simplification of the Swift function names of cocoa frames.
+//!
+//! Fully qualified Swift symbols spell out their generic parameters
+//! (`MyApp.Cache<Swift.String, MyApp.User>.value(for: A) -> B?`), so every
+//! specialization of a generic function is a different function in the
+//! functions dataset. Simplification strips generic parameters and,
+//! optionally, module prefixes. Like demangling, it's opt-in and
+//! process-wide, see [`set_swift_simplify_options`], as it changes the
+//! fingerprint of the functions it renames.
+
+use std::sync::RwLock;
+
+use super::Frame;
+
+/// Module of the Swift standard library.
+const SWIFT_STANDARD_LIBRARY_MODULE: &str = "Swift";
+
+/// Prefix of the functions specialized for some generic parameters.
+const GENERIC_SPECIALIZATION_PREFIX: &str = "generic specialization ";
+
+static SWIFT_SIMPLIFY_OPTIONS: RwLock<SwiftSimplifyOptions> = RwLock::new(SwiftSimplifyOptions {
+    strip_generics: false,
+    strip_modules: false,
+});
+
+/// How the Swift function names of cocoa frames are simplified when frames
+/// are normalized.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SwiftSimplifyOptions {
+    /// Strips generic parameters (`<...>`) and generic specialization
+    /// prefixes.
+    pub strip_generics: bool,
+    /// Strips the prefixes of the module of the frame and of the Swift
+    /// standard library (`MyApp.`, `Swift.`).
+    pub strip_modules: bool,
+}
+
+impl SwiftSimplifyOptions {
+    fn is_enabled(&self) -> bool {
+        self.strip_generics || self.strip_modules
+    }
+}
+
+pub fn set_swift_simplify_options(options: SwiftSimplifyOptions) {
+    *SWIFT_SIMPLIFY_OPTIONS
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = options;
+}
+
+pub(crate) fn swift_simplify_options() -> SwiftSimplifyOptions {
+    *SWIFT_SIMPLIFY_OPTIONS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// Removes the generic parameters of a Swift function name, the `>` of
+/// function type arrows (`->`) not closing any.
+fn strip_generics(function: &str) -> String {
+    if let Some(specialized) = function
+        .strip_prefix(GENERIC_SPECIALIZATION_PREFIX)
+        .and_then(|rest| {
+            strip_generics(rest)
+                .strip_prefix(" of ")
+                .map(str::to_string)
+        })
+    {
+        return specialized;
+    }
+
+    let mut stripped = String::new();
+    let mut depth = 0usize;
+    let mut previous = None;
+    for c in function.chars() {
+        match c {
+            '<' => depth += 1,
+            '>' if depth > 0 && previous != Some('-') => depth -= 1,
+            _ if depth == 0 => stripped.push(c),
+            _ => {}
+        }
+        previous = Some(c);
+    }
+    stripped
+}
+
+/// Removes the `module.` prefixes of the identifiers of a function name.
+fn strip_module(function: &str, module: &str) -> String {
+    let prefix = format!("{module}.");
+    let mut stripped = String::with_capacity(function.len());
+    let mut rest = function;
+    while let Some(i) = rest.find(&prefix) {
+        let is_identifier_start = rest[..i]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !(c.is_alphanumeric() || c == '_' || c == '.'));
+        stripped.push_str(&rest[..i]);
+        if !is_identifier_start {
+            stripped.push_str(&prefix);
+        }
+        rest = &rest[i + prefix.len()..];
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+/// Returns the simplified Swift function name of a frame of a module, or
+/// None if it's left as is.
+pub(crate) fn simplify_swift_function(
+    function: &str,
+    module: &str,
+    options: SwiftSimplifyOptions,
+) -> Option<String> {
+    let mut simplified = function.to_string();
+    if options.strip_generics {
+        simplified = strip_generics(&simplified);
+    }
+    if options.strip_modules {
+        simplified = strip_module(&simplified, SWIFT_STANDARD_LIBRARY_MODULE);
+        if !module.is_empty() {
+            simplified = strip_module(&simplified, module);
+        }
+    }
+    (simplified != function).then_some(simplified)
+}
+
+impl Frame {
+    /// Simplifies the Swift function name of a cocoa frame, see
+    /// [`simplify_swift_function`].
+    pub(crate) fn simplify_swift_function(&mut self, options: SwiftSimplifyOptions) {
+        if !options.is_enabled() || self.platform.as_deref() != Some("cocoa") {
+            return;
+        }
+        let module = self.module_or_package();
+        if let Some(simplified) = self
+            .function
+            .as_deref()
+            .and_then(|function| simplify_swift_function(function, &module, options))
+        {
+            self.function = Some(simplified);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{simplify_swift_function, SwiftSimplifyOptions};
+    use crate::frame::Frame;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_simplify_swift_function() {
+        let generics = SwiftSimplifyOptions {
+            strip_generics: true,
+            strip_modules: false,
+        };
+        let modules = SwiftSimplifyOptions {
+            strip_generics: false,
+            strip_modules: true,
+        };
+        let both = SwiftSimplifyOptions {
+            strip_generics: true,
+            strip_modules: true,
+        };
+        let test_cases = [
+            (
+                "MyApp.Cache<Swift.String, MyApp.User>.value(for: A) -> B?",
+                generics,
+                Some("MyApp.Cache.value(for: A) -> B?"),
+            ),
+            (
+                "generic specialization <Swift.Int> of Swift.Array.append(__owned A) -> ()",
+                generics,
+                Some("Swift.Array.append(__owned A) -> ()"),
+            ),
+            (
+                "MyApp.map<A>(_: (A) -> Swift.String) -> [Swift.String]",
+                both,
+                Some("map(_: (A) -> String) -> [String]"),
+            ),
+            (
+                "closure #1 in MyApp.ViewModel.load(id: Swift.String) async throws -> ()",
+                modules,
+                Some("closure #1 in ViewModel.load(id: String) async throws -> ()"),
+            ),
+            ("SwiftUI.ViewGraph.updateOutputs() -> ()", modules, None),
+            ("MyAppKit.Store.save() -> ()", modules, None),
+            ("-[UIViewController viewDidLoad]", both, None),
+        ];
+        for (function, options, want) in test_cases {
+            assert_eq!(
+                simplify_swift_function(function, "MyApp", options).as_deref(),
+                want,
+                "{function}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_frame_simplify_swift_function() {
+        let frame = Frame {
+            function: Some("MyApp.Cache<Swift.String>.value() -> ()".to_string()),
+            package: Some("/private/var/containers/Bundle/Application/MyApp.app/MyApp".to_string()),
+            platform: Some("cocoa".to_string()),
+            ..Default::default()
+        };
+        let options = SwiftSimplifyOptions {
+            strip_generics: true,
+            strip_modules: true,
+        };
+
+        let mut simplified = frame.clone();
+        simplified.simplify_swift_function(options);
+        assert_eq!(simplified.function.as_deref(), Some("Cache.value() -> ()"));
+
+        let mut disabled = frame.clone();
+        disabled.simplify_swift_function(SwiftSimplifyOptions::default());
+        assert_eq!(disabled, frame);
+
+        let mut python = Frame {
+            platform: Some("python".to_string()),
+            ..frame.clone()
+        };
+        python.simplify_swift_function(options);
+        assert_eq!(python.function, frame.function);
+    }
+}
@@ -0,0 +1,67 @@
+//! Normalization of the function names of frames collected by V8.
+//!
+//! V8 decorates function names depending on how the function was compiled
+//! when sampled (`*foo` once optimized by TurboFan, `~foo` when interpreted,
+//! `LazyCompile: *foo` in logs) and appends ` (deoptimized)` after a
+//! deoptimization. The same function shows up under several names within a
+//! chunk as the optimizer kicks in, which splits its metrics, so the
+//! decorations are stripped.
+
+/// Prefixes of the code event tags found in V8 logs.
+const CODE_EVENT_TAGS: &[&str] = &["LazyCompile: ", "Function: ", "JS: "];
+
+/// Markers of the tier the function was compiled with: `~` for Ignition,
+/// `^` for Sparkplug, `+` for Maglev and `*` for TurboFan.
+const TIER_MARKERS: &[char] = &['~', '^', '+', '*'];
+
+/// Suffixes V8 appends to deoptimized functions.
+const DEOPTIMIZED_SUFFIXES: &[&str] = &[" (deoptimized)", " [deoptimized]"];
+
+/// Returns the function name without its V8 decorations, or `None` if it
+/// has none.
+pub(crate) fn strip_optimization_markers(function: &str) -> Option<String> {
+    let mut name = function;
+    if let Some(tag) = CODE_EVENT_TAGS.iter().find(|tag| name.starts_with(*tag)) {
+        name = &name[tag.len()..];
+    }
+    if let Some(suffix) = DEOPTIMIZED_SUFFIXES.iter().find(|s| name.ends_with(*s)) {
+        name = &name[..name.len() - suffix.len()];
+    }
+    // a single marker, and never the whole name, so operators are left alone
+    if name.len() > 1 && name.starts_with(TIER_MARKERS) {
+        name = &name[1..];
+    }
+    (name.len() != function.len()).then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::strip_optimization_markers;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_strip_optimization_markers() {
+        let test_cases = [
+            ("handleRequest", None),
+            ("*handleRequest", Some("handleRequest")),
+            ("~handleRequest", Some("handleRequest")),
+            ("^handleRequest", Some("handleRequest")),
+            ("+handleRequest", Some("handleRequest")),
+            ("handleRequest (deoptimized)", Some("handleRequest")),
+            ("*handleRequest [deoptimized]", Some("handleRequest")),
+            ("LazyCompile: *handleRequest", Some("handleRequest")),
+            ("Function: ~Module._compile", Some("Module._compile")),
+            ("*", None),
+            ("(garbage collector)", None),
+            ("", None),
+        ];
+        for (function, want) in test_cases {
+            assert_eq!(
+                strip_optimization_markers(function).as_deref(),
+                want,
+                "{function}"
+            );
+        }
+    }
+}
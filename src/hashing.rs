@@ -0,0 +1,142 @@
+//! Hash functions behind fingerprints and deduplication keys. Fingerprints
+//! are persisted and compared across workers, possibly running on different
+//! architectures, so hashes must only depend on their input: the algorithms
+//! are pinned, and integers are hashed as little-endian bytes.
+
+use std::hash::Hasher;
+
+use fnv_rs::Fnv64;
+
+/// Identifies the hashing scheme. It changes whenever the hash of a given
+/// input would, so fingerprints computed with different schemes are never
+/// compared with each other.
+///
+/// Frame and stack fingerprints use 64-bit FNV-1a over little-endian
+/// integers, occurrence fingerprints use MD5 over UTF-8 strings.
+///
+/// Schemes:
+/// - 1: frames hashed by module (or package), function and address.
+/// - 2: the file name of frames folded into frame hashes and function
+///   fingerprints.
+/// - 3: the file name of frames only folded into frame hashes, function
+///   fingerprints being vroom's again (see [`function_fingerprint`]).
+/// - 4: the instruction address of frames only hashed when neither their
+///   function nor its symbol address is known.
+pub const HASHING_SCHEME: &str = "fnv1a64-le+md5/4";
+
+/// 64-bit FNV-1a hasher, hashing integers as little-endian bytes regardless
+/// of the endianness and pointer width of the host.
+#[derive(Default)]
+pub struct FingerprintHasher(Fnv64);
+
+impl Hasher for FingerprintHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+}
+
+/// Returns the fingerprint of a function, computed exactly as vroom does,
+/// so function metrics keep matching the ones it stored: the 64-bit FNV-1a
+/// hash of `package:name`, truncated to 32 bits. `package` is the module of
+/// the function, or its trimmed package (see [`Frame::module_or_package`]).
+///
+/// With the fingerprint of the stack calling the function, returns the
+/// fingerprint of the stack down to the function instead.
+///
+/// [`Frame::module_or_package`]: crate::frame::Frame::module_or_package
+pub fn function_fingerprint(name: &str, package: &str, parent_fingerprint: Option<u32>) -> u32 {
+    let mut hasher = FingerprintHasher::default();
+    hasher.write(package.as_bytes());
+    hasher.write(":".as_bytes());
+    hasher.write(name.as_bytes());
+    if let Some(parent_fingerprint) = parent_fingerprint {
+        hasher.write_u32(parent_fingerprint);
+    }
+
+    // casting to an uint32 here because snuba does not handle uint64 values well
+    // as it is converted to a float somewhere not changing to the 32 bit hash
+    // function here to preserve backwards compatibility with existing fingerprints
+    // that we can cast
+    hasher.finish() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hasher;
+
+    use super::{function_fingerprint, FingerprintHasher};
+
+    use pretty_assertions::assert_eq;
+
+    fn hash(write: impl FnOnce(&mut FingerprintHasher)) -> u64 {
+        let mut hasher = FingerprintHasher::default();
+        write(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_fnv1a_64_vectors() {
+        assert_eq!(hash(|_| {}), 0xcbf29ce484222325);
+        assert_eq!(hash(|h| h.write(b"a")), 0xaf63dc4c8601ec8c);
+        assert_eq!(hash(|h| h.write(b"foobar")), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn test_function_fingerprint_golden_values() {
+        // pinned, as changing them would break the matching with the
+        // fingerprints stored by vroom
+        let test_cases = [
+            ("foo", "foo", 2655321105),
+            ("bar", "bar", 1766712469),
+            ("main", "", 73247328),
+            ("onCreate", "com.example.app.MainActivity", 3843770210),
+            ("-[UIApplication _run]", "UIKitCore", 2065175645),
+            ("", "", 2248256941),
+        ];
+        for (name, package, want) in test_cases {
+            assert_eq!(
+                function_fingerprint(name, package, None),
+                want,
+                "{package}:{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_integers_are_little_endian() {
+        assert_eq!(
+            hash(|h| h.write_u32(0x01020304)),
+            hash(|h| h.write(&[4, 3, 2, 1]))
+        );
+        assert_eq!(
+            hash(|h| h.write_u64(0x0102030405060708)),
+            hash(|h| h.write(&[8, 7, 6, 5, 4, 3, 2, 1]))
+        );
+        assert_eq!(hash(|h| h.write_i32(-1)), hash(|h| h.write(&[0xff; 4])));
+        assert_eq!(hash(|h| h.write_usize(42)), hash(|h| h.write_u64(42)));
+    }
+}
@@ -0,0 +1,90 @@
+//! JSON deserialization of profile payloads.
+//!
+//! With the `simd-json` feature, payloads are parsed with simd-json, which
+//! needs a mutable buffer: borrowed payloads are copied first. If simd-json
+//! fails, parsing falls back to serde_json, so errors are always reported
+//! the same way.
+
+use serde::de::DeserializeOwned;
+
+#[cfg(not(feature = "simd-json"))]
+pub(crate) fn from_slice<T: DeserializeOwned>(payload: &[u8]) -> Result<T, serde_json::Error> {
+    serde_json::from_slice(payload)
+}
+
+#[cfg(feature = "simd-json")]
+pub(crate) fn from_slice<T: DeserializeOwned>(payload: &[u8]) -> Result<T, serde_json::Error> {
+    let mut buffer = payload.to_vec();
+    simd_json::serde::from_slice(&mut buffer).or_else(|_| serde_json::from_slice(payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use crate::{
+        android::chunk::AndroidChunk,
+        sample::v2::SampleChunk,
+        synthetic::{generate_sample_chunk, SyntheticOptions},
+    };
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_from_slice_errors() {
+        let err = super::from_slice::<SampleChunk>(b"{\"version\": 2}").unwrap_err();
+        assert!(err.is_data(), "unexpected error: {err}");
+        let err = super::from_slice::<SampleChunk>(b"{\"version\":").unwrap_err();
+        assert!(err.is_eof(), "unexpected error: {err}");
+    }
+
+    #[test]
+    fn test_from_slice_matches_serde_json() {
+        let payload = include_bytes!("../tests/fixtures/sample/v2/valid_cocoa.json");
+        let got: SampleChunk = super::from_slice(payload).unwrap();
+        let want: SampleChunk = serde_json::from_slice(payload).unwrap();
+        assert_eq!(got, want);
+    }
+
+    fn bench<T: serde::de::DeserializeOwned>(name: &str, payload: &[u8]) {
+        let iterations = 20;
+        let start = Instant::now();
+        for _ in 0..iterations {
+            serde_json::from_slice::<T>(payload).unwrap();
+        }
+        let serde_json = start.elapsed() / iterations;
+        let start = Instant::now();
+        for _ in 0..iterations {
+            super::from_slice::<T>(payload).unwrap();
+        }
+        let from_slice = start.elapsed() / iterations;
+        println!("{name}: serde_json {serde_json:?}, from_slice {from_slice:?}");
+    }
+
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_from_slice() {
+        bench::<SampleChunk>(
+            "cocoa",
+            include_bytes!("../tests/fixtures/sample/v2/valid_cocoa.json"),
+        );
+        bench::<SampleChunk>(
+            "python",
+            include_bytes!("../tests/fixtures/sample/v2/valid_python.json"),
+        );
+        bench::<AndroidChunk>(
+            "android",
+            include_bytes!("../tests/fixtures/android/chunk/valid.json"),
+        );
+
+        // fixtures are small, a production sized chunk shows the throughput
+        let options = SyntheticOptions {
+            threads: 8,
+            duration_ms: 10_000,
+            seed: Some(1),
+            ..Default::default()
+        };
+        let chunk = generate_sample_chunk("python", &options).unwrap();
+        bench::<SampleChunk>("synthetic", &serde_json::to_vec(&chunk).unwrap());
+    }
+}
@@ -1,21 +1,61 @@
-use nodetree::CallTreeFunction;
-use profile::Profile;
-use profile_chunk::ProfileChunk;
+use activity::ActivityTimeline;
+use android::chunk::AndroidChunkBuilder;
+use anomaly::AnomalyScore;
+use capabilities::PlatformCapabilities;
+use cpu_spike::{CpuSpike, SpikeStack};
+use exceptions::{CompressionError, InvalidStack, ParseError, UnsupportedVersion, VroomError};
+use flamechart::FlamechartComparison;
+use frame::InAppOverrides;
+use limits::Limits;
+use measurement_metrics::MeasurementMetric;
+use memory_growth::{MemoryGrowth, MemoryGrowthSuspect};
+use nodetree::{CallTreeFunction, FunctionExample, FunctionsMetricsOptions, FunctionsSortKey};
+use occurrence::{AppStartPrelude, CalibrationStats, DetectorInfo, PreludeFrame};
+use otel::OtelSpan;
+use profile::{ProcessedProfile, Profile, ProfileSummary};
+use profile_chunk::{ProcessedProfileChunk, ProfileChunk};
 use pyo3::prelude::*;
+use sample::v2::SampleChunkBuilder;
+use stats::ProcessingStats;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use symbolication::SymbolicationStats;
+use top_frames::TopFrame;
 
+mod activity;
 mod android;
+mod anomaly;
+pub mod api;
+#[cfg(feature = "arrow")]
+mod arrow;
+mod capabilities;
+mod cpu_spike;
 mod debug_images;
+mod exceptions;
+mod flamechart;
 mod frame;
+mod hashing;
+mod json;
+mod limits;
+mod measurement_metrics;
+mod measurements;
+mod memory_growth;
+mod msgpack;
 mod nodetree;
+mod obfuscation;
 mod occurrence;
+mod otel;
 mod profile;
 mod profile_chunk;
 mod sample;
+mod sample_rate;
+mod stats;
+mod symbolication;
+mod synthetic;
+mod timestamp;
+mod top_frames;
 mod types;
 mod utils;
 
-const MAX_STACK_DEPTH: u64 = 128;
-
 /// Returns a `ProfileChunk` instance from a json string
 ///
 /// .. deprecated::
@@ -33,6 +73,10 @@ const MAX_STACK_DEPTH: u64 = 128;
 ///   more efficiently.
 ///   If the platform is known at the time this function is invoked, it's
 ///   recommended to always pass it.
+/// in_app_rules : Optional[dict[str, dict[str, list[str]]]]
+///   Package prefixes of the project forced in_app (`include`) or not
+///   (`exclude`), by platform, applied when the chunk is normalized (see
+///   :meth:`vroomrs.ProfileChunk.normalize`).
 ///
 /// Returns
 /// -------
@@ -41,19 +85,39 @@ const MAX_STACK_DEPTH: u64 = 128;
 ///
 /// Raises
 /// -------
-/// pyo3.exceptions.PyException
-///     If an error occurs during the extraction process.
+/// vroomrs.ParseError
+///     If the payload isn't valid JSON, or doesn't match the format of its version.
+/// vroomrs.UnsupportedVersion
+///     If the version of the payload isn't supported.
+/// ValueError
+///     If the in_app rules are invalid.
+///
+/// Example
+/// --------
+///     >>> chunk = vroomrs.profile_chunk_from_json_str(
+///     ...     payload, "android", in_app_rules={"android": {"include": ["com.mycompany."]}}
+///     ... )
+///     >>> chunk.normalize()
 ///
 #[pyfunction]
-#[pyo3(signature = (profile, platform=None))]
+#[pyo3(signature = (profile, platform=None, in_app_rules=None))]
 #[allow(deprecated)]
-fn profile_chunk_from_json_str(profile: &str, platform: Option<&str>) -> PyResult<ProfileChunk> {
-    match platform {
+fn profile_chunk_from_json_str(
+    profile: &str,
+    platform: Option<&str>,
+    in_app_rules: Option<HashMap<String, HashMap<String, Vec<String>>>>,
+) -> PyResult<ProfileChunk> {
+    let mut chunk = match platform {
         Some(platform) => ProfileChunk::from_json_vec_and_platform(profile.as_bytes(), platform)
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string())),
-        None => ProfileChunk::from_json_vec(profile.as_bytes())
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string())),
+            .map_err(|e| ParseError::new_err(e.to_string()))?,
+        None => ProfileChunk::from_json_vec(profile.as_bytes())?,
+    };
+    if let Some(in_app_rules) = in_app_rules {
+        chunk
+            .set_in_app_rules(in_app_rules)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
     }
+    Ok(chunk)
 }
 
 /// Returns a `ProfileChunk` instance from a json string, using the profile
@@ -67,7 +131,7 @@ fn profile_chunk_from_json_str(profile: &str, platform: Option<&str>) -> PyResul
 ///   A string representing the profile version. It is used to directly
 ///   deserialize to the right profile chunk more efficiently ("2.android-trace"
 ///   and, as a fallback to the legacy behavior, an empty string map to the
-///   legacy android trace format, any other version to the sample v2 format).
+///   legacy android trace format, other versions 2.x to the sample v2 format).
 ///
 /// Returns
 /// -------
@@ -76,13 +140,133 @@ fn profile_chunk_from_json_str(profile: &str, platform: Option<&str>) -> PyResul
 ///
 /// Raises
 /// -------
-/// pyo3.exceptions.PyException
-///     If an error occurs during the extraction process.
+/// vroomrs.ParseError
+///     If the payload isn't valid JSON, or doesn't match the format of its version.
+/// vroomrs.UnsupportedVersion
+///     If the version of the payload isn't supported.
 ///
 #[pyfunction]
 fn profile_chunk_from_json_str_and_version(profile: &str, version: &str) -> PyResult<ProfileChunk> {
-    ProfileChunk::from_json_vec_and_version(profile.as_bytes(), version)
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))
+    Ok(ProfileChunk::from_json_vec_and_version(
+        profile.as_bytes(),
+        version,
+    )?)
+}
+
+/// Returns `ProfileChunk` instances from a json string, splitting chunks
+/// over the limits rather than rejecting them.
+///
+/// Samples are split in timestamp order. Each chunk gets the frames and
+/// stacks its samples use, the measurements of its time range, and a chunk
+/// ID derived from the original one (the same every time the chunk is split).
+/// Chunks in the legacy android trace format are never split.
+///
+/// Arguments
+/// ---------
+/// profile : str
+///   A profile chunk serialized as json string
+/// max_samples : int
+///   The maximum number of samples of a chunk.
+/// max_bytes : Optional[int]
+///   An optional maximum size of a chunk serialized as json, in bytes.
+///   Chunks made of a single sample are never split further.
+///
+/// Returns
+/// -------
+/// list[:class:`vroomrs.ProfileChunk`]
+///   The chunk, or the chunks it was split into, in timestamp order.
+///
+/// Raises
+/// -------
+/// vroomrs.ParseError
+///     If the payload isn't valid JSON, or doesn't match the format of its version.
+/// vroomrs.UnsupportedVersion
+///     If the version of the payload isn't supported.
+///
+/// Example
+/// --------
+///     >>> chunks = vroomrs.profile_chunks_from_json_str(payload, max_samples=10000)
+///
+#[pyfunction]
+#[pyo3(signature = (profile, max_samples, max_bytes=None))]
+fn profile_chunks_from_json_str(
+    profile: &str,
+    max_samples: usize,
+    max_bytes: Option<usize>,
+) -> PyResult<Vec<ProfileChunk>> {
+    Ok(ProfileChunk::from_json_vec_split(
+        profile.as_bytes(),
+        max_samples,
+        max_bytes,
+    )?)
+}
+
+/// Extracts the function metrics of a profile chunk straight from its json
+/// payload, for very large chunks.
+///
+/// The chunk is normalized, and the call trees of sample chunks are built
+/// while their samples are parsed, so the samples are never all held in
+/// memory at once. This requires the samples of each thread to be sorted by
+/// timestamp, as SDKs send them: otherwise, or for android trace chunks, the
+/// chunk is parsed in full, as with :meth:`vroomrs.ProfileChunk.extract_functions_metrics`.
+///
+/// Arguments
+/// ---------
+/// profile : bytes
+///   A profile chunk serialized as json.
+/// min_depth, filter_system_frames, max_unique_functions, filter_non_leaf_functions, generate_stack_fingerprints, max_examples, active_thread_only, max_lines_per_function, min_sample_count, sort_by
+///   The function metrics options, see :meth:`vroomrs.ProfileChunk.extract_functions_metrics`.
+///
+/// Returns
+/// -------
+/// list[:class:`vroomrs.CallTreeFunction`]
+///   The metrics of the functions of the chunk.
+///
+/// Raises
+/// ------
+/// vroomrs.ParseError
+///     If the payload isn't valid JSON, or doesn't match the format of its version.
+/// vroomrs.UnsupportedVersion
+///     If the version of the payload isn't supported.
+/// vroomrs.InvalidStack
+///     If too many samples reference stacks or frames which don't exist.
+/// ValueError
+///     If `sort_by` is unknown.
+///
+/// Example
+/// --------
+///     >>> functions = vroomrs.stream_profile_chunk_functions_metrics(payload, min_depth=1, filter_system_frames=True)
+///
+#[pyfunction]
+#[pyo3(signature = (profile, min_depth, filter_system_frames, max_unique_functions=None, filter_non_leaf_functions=true, generate_stack_fingerprints=false, max_examples=0, active_thread_only=false, max_lines_per_function=0, min_sample_count=2, sort_by="self_time"))]
+#[allow(clippy::too_many_arguments)]
+fn stream_profile_chunk_functions_metrics(
+    profile: &[u8],
+    min_depth: u16,
+    filter_system_frames: bool,
+    max_unique_functions: Option<usize>,
+    filter_non_leaf_functions: bool,
+    generate_stack_fingerprints: bool,
+    max_examples: usize,
+    active_thread_only: bool,
+    max_lines_per_function: usize,
+    min_sample_count: u64,
+    sort_by: &str,
+) -> PyResult<Vec<CallTreeFunction>> {
+    let options = FunctionsMetricsOptions {
+        min_depth,
+        filter_system_frames,
+        max_unique_functions,
+        filter_non_leaf_functions,
+        generate_stack_fingerprints,
+        max_examples,
+        active_thread_only,
+        max_lines_per_function,
+        min_sample_count,
+        sort_key: FunctionsSortKey::from_name(sort_by)?,
+        ..Default::default()
+    };
+    ProfileChunk::stream_functions_metrics(profile, &options)
 }
 
 /// Returns a `Profile` instance from a json string
@@ -105,26 +289,27 @@ fn profile_chunk_from_json_str_and_version(profile: &str, version: &str) -> PyRe
 ///
 /// Raises
 /// -------
-/// pyo3.exceptions.PyException
-///     If an error occurs during the extraction process.
+/// vroomrs.ParseError
+///     If the payload isn't valid JSON, or doesn't match the format of its version.
+/// vroomrs.UnsupportedVersion
+///     If the version of the payload isn't supported.
 ///
 #[pyfunction]
 #[pyo3(signature = (profile, platform=None))]
 fn profile_from_json_str(profile: &str, platform: Option<&str>) -> PyResult<Profile> {
     match platform {
         Some(platform) => Profile::from_json_vec_and_platform(profile.as_bytes(), platform)
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string())),
-        None => Profile::from_json_vec(profile.as_bytes())
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string())),
+            .map_err(|e| ParseError::new_err(e.to_string())),
+        None => Ok(Profile::from_json_vec(profile.as_bytes())?),
     }
 }
 
-/// Returns a `ProfileChunk` instance from a lz4 encoded profile.
+/// Returns a `ProfileChunk` instance from a lz4 or zstd encoded profile.
 ///
 /// Arguments
 /// ---------
 /// profile : bytes
-///   A lz4 encoded profile.
+///   A lz4 or zstd encoded profile, the codec being detected from the payload.
 ///
 /// Returns
 /// -------
@@ -133,8 +318,12 @@ fn profile_from_json_str(profile: &str, platform: Option<&str>) -> PyResult<Prof
 ///
 /// Raises
 /// ------
-/// pyo3.exceptions.PyException
-///     If an error occurs during the extraction process.
+/// vroomrs.CompressionError
+///     If the payload couldn't be decompressed.
+/// vroomrs.ParseError
+///     If the payload isn't valid JSON, or doesn't match the format of its version.
+/// vroomrs.UnsupportedVersion
+///     If the version of the payload isn't supported.
 ///
 /// Example
 /// --------
@@ -144,8 +333,7 @@ fn profile_from_json_str(profile: &str, platform: Option<&str>) -> PyResult<Prof
 ///
 #[pyfunction]
 fn decompress_profile_chunk(profile: &[u8]) -> PyResult<ProfileChunk> {
-    ProfileChunk::decompress(profile)
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))
+    Ok(ProfileChunk::decompress(profile)?)
 }
 
 /// Returns a `Profile` instance from a lz4 encoded profile.
@@ -162,8 +350,12 @@ fn decompress_profile_chunk(profile: &[u8]) -> PyResult<ProfileChunk> {
 ///
 /// Raises
 /// ------
-/// pyo3.exceptions.PyException
-///     If an error occurs during the extraction process.
+/// vroomrs.CompressionError
+///     If the payload couldn't be decompressed.
+/// vroomrs.ParseError
+///     If the payload isn't valid JSON, or doesn't match the format of its version.
+/// vroomrs.UnsupportedVersion
+///     If the version of the payload isn't supported.
 ///
 /// Example
 /// --------
@@ -173,21 +365,858 @@ fn decompress_profile_chunk(profile: &[u8]) -> PyResult<ProfileChunk> {
 ///
 #[pyfunction]
 fn decompress_profile(profile: &[u8]) -> PyResult<Profile> {
-    Profile::decompress(profile)
+    Ok(Profile::decompress(profile)?)
+}
+
+/// Returns everything extracted from a lz4 encoded profile (its metadata,
+/// function metrics, occurrences and summary) in a single pass, the profile
+/// being decompressed, parsed, normalized and its call trees built only once.
+///
+/// Arguments
+/// ---------
+/// profile : bytes
+///   A lz4 encoded profile.
+/// min_depth, filter_system_frames, max_unique_functions, filter_non_leaf_functions, generate_stack_fingerprints, max_examples, active_thread_only, max_lines_per_function, min_sample_count, sort_by
+///   The function metrics options, see :meth:`vroomrs.Profile.extract_functions_metrics`.
+///
+/// Returns
+/// -------
+/// :class:`vroomrs.ProcessedProfile`
+///   The metadata, functions, occurrences and summary of the profile.
+///
+/// Raises
+/// ------
+/// vroomrs.CompressionError
+///     If the payload couldn't be decompressed.
+/// vroomrs.ParseError
+///     If the payload isn't valid JSON, or doesn't match the format of its version.
+/// vroomrs.UnsupportedVersion
+///     If the version of the payload isn't supported.
+/// vroomrs.VroomError
+///     If an error occurs while processing the profile.
+///
+/// Example
+/// --------
+///     >>> processed = vroomrs.process_profile(payload, min_depth=1, filter_system_frames=True)
+///     >>> functions = processed.get_functions()
+///     >>> occurrences = processed.get_occurrences()
+///
+#[pyfunction]
+#[pyo3(signature = (profile, min_depth, filter_system_frames, max_unique_functions=None, filter_non_leaf_functions=true, generate_stack_fingerprints=false, max_examples=0, active_thread_only=false, max_lines_per_function=0, min_sample_count=2, sort_by="self_time"))]
+#[allow(clippy::too_many_arguments)]
+fn process_profile(
+    profile: &[u8],
+    min_depth: u16,
+    filter_system_frames: bool,
+    max_unique_functions: Option<usize>,
+    filter_non_leaf_functions: bool,
+    generate_stack_fingerprints: bool,
+    max_examples: usize,
+    active_thread_only: bool,
+    max_lines_per_function: usize,
+    min_sample_count: u64,
+    sort_by: &str,
+) -> PyResult<ProcessedProfile> {
+    let options = FunctionsMetricsOptions {
+        min_depth,
+        filter_system_frames,
+        max_unique_functions,
+        filter_non_leaf_functions,
+        generate_stack_fingerprints,
+        max_examples,
+        active_thread_only,
+        max_lines_per_function,
+        min_sample_count,
+        sort_key: FunctionsSortKey::from_name(sort_by)?,
+        ..Default::default()
+    };
+    let mut profile = Profile::decompress(profile)?;
+    Ok(profile.process(&options)?)
+}
+
+/// Returns everything extracted from a lz4 encoded profile chunk (its
+/// function metrics, processing stats and summary) in a single pass, the
+/// chunk being decompressed, parsed, normalized and its call trees built
+/// only once.
+///
+/// Arguments
+/// ---------
+/// profile : bytes
+///   A lz4 encoded profile chunk.
+/// min_depth, filter_system_frames, max_unique_functions, filter_non_leaf_functions, generate_stack_fingerprints, max_examples, active_thread_only, max_lines_per_function, collapse_recursion, skip_idle_threads, min_sample_count, sort_by
+///   The function metrics options, see :meth:`vroomrs.ProfileChunk.extract_functions_metrics`.
+///
+/// Returns
+/// -------
+/// :class:`vroomrs.ProcessedProfileChunk`
+///   The functions, processing stats and summary of the chunk. Chunks aren't
+///   searched for occurrences, so the occurrence count of the summary is 0.
+///
+/// Raises
+/// ------
+/// vroomrs.CompressionError
+///     If the payload couldn't be decompressed.
+/// vroomrs.ParseError
+///     If the payload isn't valid JSON, or doesn't match the format of its version.
+/// vroomrs.UnsupportedVersion
+///     If the version of the payload isn't supported.
+/// vroomrs.VroomError
+///     If an error occurs while processing the chunk.
+/// ValueError
+///     If `sort_by` is unknown.
+///
+/// Example
+/// --------
+///     >>> processed = vroomrs.process_profile_chunk(payload, min_depth=1, filter_system_frames=True)
+///     >>> functions = processed.get_functions()
+///
+#[pyfunction]
+#[pyo3(signature = (profile, min_depth, filter_system_frames, max_unique_functions=None, filter_non_leaf_functions=true, generate_stack_fingerprints=false, max_examples=0, active_thread_only=false, max_lines_per_function=0, collapse_recursion=false, skip_idle_threads=false, min_sample_count=2, sort_by="self_time"))]
+#[allow(clippy::too_many_arguments)]
+fn process_profile_chunk(
+    profile: &[u8],
+    min_depth: u16,
+    filter_system_frames: bool,
+    max_unique_functions: Option<usize>,
+    filter_non_leaf_functions: bool,
+    generate_stack_fingerprints: bool,
+    max_examples: usize,
+    active_thread_only: bool,
+    max_lines_per_function: usize,
+    collapse_recursion: bool,
+    skip_idle_threads: bool,
+    min_sample_count: u64,
+    sort_by: &str,
+) -> PyResult<ProcessedProfileChunk> {
+    let options = FunctionsMetricsOptions {
+        min_depth,
+        filter_system_frames,
+        max_unique_functions,
+        filter_non_leaf_functions,
+        generate_stack_fingerprints,
+        max_examples,
+        active_thread_only,
+        max_lines_per_function,
+        collapse_recursion,
+        skip_idle_threads,
+        min_sample_count,
+        sort_key: FunctionsSortKey::from_name(sort_by)?,
+    };
+    let mut chunk = ProfileChunk::decompress(profile)?;
+    chunk.process(&options)
+}
+
+/// Registers a frame detector, run in addition to the built-in ones when
+/// looking for occurrences in profiles of the given platform.
+///
+/// The detector is named after its platform and category (e.g.
+/// "cocoa_json_decode"), see :func:`list_detectors`. Registering a detector
+/// of the same platform and category again replaces it.
+///
+/// Arguments
+/// ---------
+/// category : str
+///   The category of the occurrences created for the detected frames.
+/// platform : str
+///   The platform of the profiles the detector applies to.
+/// frame_names : list[str]
+///   The function names to detect. Names carrying a signature (e.g. android)
+///   are also matched up to their first '('.
+/// threshold_ns : int
+///   The minimum duration, in nanoseconds, a frame needs to be detected.
+/// only_main_thread : bool
+///   Whether to only look at the main thread or at all threads.
+///
+/// Raises
+/// -------
+/// ValueError
+///     If the detector would replace a built-in one.
+///
+/// Example
+/// --------
+///     >>> vroomrs.register_frame_detector(
+///     ...     "json_decode", "cocoa", ["MyJSONParser.parse(Data)"], 16_000_000, True
+///     ... )
+///
+#[pyfunction]
+#[pyo3(signature = (category, platform, frame_names, threshold_ns, only_main_thread=true))]
+fn register_frame_detector(
+    category: &str,
+    platform: &str,
+    frame_names: Vec<String>,
+    threshold_ns: u64,
+    only_main_thread: bool,
+) -> PyResult<()> {
+    occurrence::register_frame_detector(
+        category,
+        platform,
+        &frame_names,
+        threshold_ns,
+        only_main_thread,
+    )
+    .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))
+}
+
+/// Unregisters a detector registered with :func:`register_frame_detector`.
+///
+/// Arguments
+/// ---------
+/// name : str
+///   The name of the detector, see :func:`list_detectors`.
+///
+/// Raises
+/// -------
+/// ValueError
+///     If the detector is unknown or built-in.
+///
+/// Example
+/// --------
+///     >>> vroomrs.unregister_detector("cocoa_json_decode")
+///
+#[pyfunction]
+fn unregister_detector(name: &str) -> PyResult<()> {
+    occurrence::unregister_detector(name)
         .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))
 }
 
+/// Returns the detectors run when looking for occurrences, in the order they
+/// run: the built-in ones, then the ones registered at runtime.
+///
+/// Returns
+/// -------
+/// list[:class:`vroomrs.DetectorInfo`]
+///   The detectors, with their name, categories, platforms and whether
+///   they're enabled.
+///
+#[pyfunction]
+fn list_detectors() -> Vec<DetectorInfo> {
+    occurrence::detectors()
+}
+
+/// Enables or disables detectors, process-wide.
+///
+/// Arguments
+/// ---------
+/// names : list[str]
+///   The names of the detectors, see :func:`list_detectors`.
+/// enabled : bool
+///   Whether the detectors run when looking for occurrences.
+///
+/// Raises
+/// -------
+/// ValueError
+///     If a detector is unknown, in which case none are changed.
+///
+/// Example
+/// --------
+///     >>> vroomrs.set_detectors_enabled(["frame_drop"], False)
+///
+#[pyfunction]
+#[pyo3(signature = (names, enabled=true))]
+fn set_detectors_enabled(names: Vec<String>, enabled: bool) -> PyResult<()> {
+    occurrence::set_detectors_enabled(&names, enabled)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))
+}
+
+/// Returns a randomized but valid `ProfileChunk`, for load testing
+/// and fuzzing.
+///
+/// Arguments
+/// ---------
+/// platform : str
+///   The profile platform. "android" generates a chunk in the legacy android
+///   trace format, any other platform a sample v2 chunk.
+/// threads : int
+///   The number of threads sampled, the first one being the main thread.
+/// max_depth : int
+///   The maximum stack depth.
+/// sample_rate_hz : int
+///   The sampling frequency, in Hz.
+/// duration_ms : int
+///   The chunk duration, in milliseconds.
+/// unique_functions : int
+///   The number of distinct functions stacks are made of.
+/// seed : Optional[int]
+///   The seed of the random generator, to reproduce a given chunk.
+///
+/// Returns
+/// -------
+/// :class:`vroomrs.ProfileChunk`
+///   A `ProfileChunk` instance
+///
+/// Raises
+/// -------
+/// ValueError
+///     If the duration and sampling frequency overflow the sample count.
+///
+#[pyfunction]
+#[pyo3(signature = (platform, threads=1, max_depth=32, sample_rate_hz=100, duration_ms=1000, unique_functions=100, seed=None))]
+fn generate_synthetic_profile_chunk(
+    platform: &str,
+    threads: u64,
+    max_depth: usize,
+    sample_rate_hz: u64,
+    duration_ms: u64,
+    unique_functions: usize,
+    seed: Option<u64>,
+) -> PyResult<ProfileChunk> {
+    let options = synthetic::SyntheticOptions {
+        threads,
+        max_depth,
+        sample_rate_hz,
+        duration_ms,
+        unique_functions,
+        seed,
+    };
+    let to_value_error = |e: synthetic::SyntheticError| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string())
+    };
+    match platform {
+        "android" => Ok(ProfileChunk::new(Box::new(
+            synthetic::generate_android_chunk(&options).map_err(to_value_error)?,
+        ))),
+        _ => Ok(ProfileChunk::new(Box::new(
+            synthetic::generate_sample_chunk(platform, &options).map_err(to_value_error)?,
+        ))),
+    }
+}
+
+/// Merges profile chunks collected by the same profiler into a single chunk
+/// covering the whole session.
+///
+/// Samples are concatenated in timestamp order, their stacks and frames being
+/// re-indexed (and deduplicated) into those of the merged chunk, and thread
+/// metadata, measurements and debug images are merged. Other metadata is
+/// taken from the earliest chunk, and the chunk ID is derived from the IDs of
+/// the chunks merged (the same every time the same chunks are merged).
+///
+/// Arguments
+/// ---------
+/// chunks : list[:class:`vroomrs.ProfileChunk`]
+///   The chunks to merge, in any order.
+///
+/// Returns
+/// -------
+/// :class:`vroomrs.ProfileChunk`
+///   The merged chunk.
+///
+/// Raises
+/// -------
+/// pyo3.exceptions.ValueError
+///     If no chunks are given, if they were collected by different profilers
+///     or on different platforms, or if any is in the legacy android trace format.
+///
+/// Example
+/// --------
+///     >>> session = vroomrs.merge_profile_chunks(chunks)
+///     >>> metrics = session.extract_functions_metrics(min_depth=1, filter_system_frames=True)
+///
+#[pyfunction]
+fn merge_profile_chunks(chunks: Vec<PyRef<'_, ProfileChunk>>) -> PyResult<ProfileChunk> {
+    let chunks: Vec<&ProfileChunk> = chunks.iter().map(|chunk| &**chunk).collect();
+    ProfileChunk::merge(&chunks)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))
+}
+
+/// Aggregates the function metrics of several profile chunks in one call,
+/// e.g. for the hourly functions rollup, building the call trees of the chunks
+/// in parallel.
+///
+/// Functions are merged by fingerprint (or stack fingerprint) across chunks,
+/// their self and total times, sample counts, threads and examples being
+/// combined, before being filtered and sorted: `min_sample_count` applies to
+/// the samples of all the chunks.
+///
+/// Arguments
+/// ---------
+/// chunks : list[:class:`vroomrs.ProfileChunk`]
+///   The chunks to aggregate, normalized. A chunk given more than once is
+///   only aggregated once.
+/// min_depth, filter_system_frames, max_unique_functions, filter_non_leaf_functions, generate_stack_fingerprints, max_examples, min_sample_count, sort_by
+///   The function metrics options, see :meth:`vroomrs.ProfileChunk.extract_functions_metrics`.
+///
+/// Returns
+/// -------
+/// list[:class:`vroomrs.CallTreeFunction`]
+///   The metrics of the functions of all the chunks, sorted by `sort_by`.
+///
+/// Raises
+/// -------
+/// ValueError
+///     If `sort_by` is unknown.
+/// vroomrs.InvalidStack
+///     If too many samples reference stacks or frames which don't exist.
+///
+/// Example
+/// --------
+///     >>> functions = vroomrs.aggregate_functions_metrics(chunks, min_depth=1, filter_system_frames=True)
+///
+#[pyfunction]
+#[pyo3(signature = (chunks, min_depth, filter_system_frames, max_unique_functions=None, filter_non_leaf_functions=true, generate_stack_fingerprints=false, max_examples=0, min_sample_count=2, sort_by="self_time"))]
+#[allow(clippy::too_many_arguments)]
+fn aggregate_functions_metrics(
+    py: Python<'_>,
+    chunks: Vec<Bound<'_, ProfileChunk>>,
+    min_depth: u16,
+    filter_system_frames: bool,
+    max_unique_functions: Option<usize>,
+    filter_non_leaf_functions: bool,
+    generate_stack_fingerprints: bool,
+    max_examples: usize,
+    min_sample_count: u64,
+    sort_by: &str,
+) -> PyResult<Vec<CallTreeFunction>> {
+    let options = FunctionsMetricsOptions {
+        min_depth,
+        filter_system_frames,
+        max_unique_functions,
+        filter_non_leaf_functions,
+        generate_stack_fingerprints,
+        max_examples,
+        min_sample_count,
+        sort_key: FunctionsSortKey::from_name(sort_by)?,
+        ..Default::default()
+    };
+    // the chunks are copied rather than borrowed while the GIL is released,
+    // so other threads can keep using them in the meantime
+    let mut copies = Vec::with_capacity(chunks.len());
+    let mut seen = HashSet::with_capacity(chunks.len());
+    for chunk in &chunks {
+        if seen.insert(chunk.as_ptr()) {
+            copies.push(ProfileChunk::clone(&*chunk.try_borrow()?));
+        }
+    }
+    let mut copies: Vec<&mut ProfileChunk> = copies.iter_mut().collect();
+    Ok(py.detach(|| profile_chunk::aggregate_functions_metrics(&mut copies, &options))?)
+}
+
+/// Returns the flamecharts of two profile chunks covering the same wall-clock
+/// interval (e.g. two pods, or react native JS and native code), on a shared
+/// time axis for a synced dual view.
+///
+/// Arguments
+/// ---------
+/// left : :class:`vroomrs.ProfileChunk`
+///   The chunk displayed first.
+/// right : :class:`vroomrs.ProfileChunk`
+///   The chunk displayed second.
+///
+/// Returns
+/// -------
+/// :class:`vroomrs.FlamechartComparison`
+///   The time axis metadata, and both flamecharts with spans offset from
+///   the start of the earliest chunk.
+///
+/// Raises
+/// -------
+/// pyo3.exceptions.PyException
+///     If an error occurs while building the call trees.
+///
+/// Example
+/// --------
+///     >>> comparison = vroomrs.compare_profile_chunks(js_chunk, native_chunk)
+///     >>> payload = comparison.to_json_str()
+///
+#[pyfunction]
+fn compare_profile_chunks(
+    left: &mut ProfileChunk,
+    right: &mut ProfileChunk,
+) -> PyResult<FlamechartComparison> {
+    Ok(flamechart::compare_chunks(left, right)?)
+}
+
+/// Serializes a list of functions to MessagePack in one go, as an array of
+/// maps keyed by field name (see :meth:`vroomrs.CallTreeFunction.to_msgpack`).
+///
+/// Arguments
+/// ---------
+/// functions : list[:class:`vroomrs.CallTreeFunction`]
+///   The functions to serialize, e.g. extracted from a profile chunk.
+///
+/// Returns
+/// -------
+/// bytes
+///   The MessagePack encoding of the functions list.
+///
+/// Raises
+/// -------
+/// pyo3.exceptions.ValueError
+///     If the serialization fails due to invalid data.
+///
+/// Example
+/// --------
+///     >>> functions = chunk.extract_functions_metrics(min_depth=1, filter_system_frames=True)
+///     >>> producer.produce("profiles-call-tree-functions", vroomrs.serialize_functions_msgpack(functions))
+///
+#[pyfunction]
+fn serialize_functions_msgpack(functions: Vec<PyRef<'_, CallTreeFunction>>) -> PyResult<Vec<u8>> {
+    let functions: Vec<&CallTreeFunction> = functions.iter().map(|function| &**function).collect();
+    msgpack::to_vec(&functions)
+}
+
+/// Returns the identifier of the hashing scheme used for fingerprints.
+///
+/// Fingerprints only depend on their input, whatever the architecture of the
+/// worker computing them. The identifier changes whenever the fingerprint of
+/// a given input would, so fingerprints computed with different schemes are
+/// never compared with each other.
+///
+/// Returns
+/// -------
+/// str
+///   The hashing scheme identifier.
+///
+#[pyfunction]
+fn hashing_scheme() -> &'static str {
+    hashing::HASHING_SCHEME
+}
+
+/// Returns the fingerprint of a function, as found in function metrics.
+///
+/// The algorithm is the one of vroom (the 64-bit FNV-1a hash of
+/// ``package:name``, truncated to 32 bits), so fingerprints match the ones
+/// it stored.
+///
+/// Arguments
+/// ---------
+/// name : str
+///   The name of the function.
+/// package : str
+///   The module of the function, or its package if it has no module, as
+///   returned by :meth:`vroomrs.CallTreeFunction.get_package`.
+///
+/// Returns
+/// -------
+/// int
+///   The fingerprint of the function.
+///
+/// Example
+/// --------
+///     >>> vroomrs.fingerprint_function("foo", "foo")
+///     2655321105
+///
+#[pyfunction]
+fn fingerprint_function(name: &str, package: &str) -> u32 {
+    hashing::function_fingerprint(name, package, None)
+}
+
+/// Formats a package (e.g. the path of a library) for display and
+/// aggregation, as function metrics do: the file name without its
+/// directory and library extension.
+///
+/// Arguments
+/// ---------
+/// package : str
+///   The package, as sent by the SDK.
+///
+/// Returns
+/// -------
+/// str
+///   The trimmed package.
+///
+/// Example
+/// --------
+///     >>> vroomrs.trim_package("/usr/lib/system/libsystem_kernel.dylib")
+///     'libsystem_kernel'
+///
+#[pyfunction]
+fn trim_package(package: &str) -> String {
+    frame::trim_package(package)
+}
+
+/// Returns the module of a frame, or its trimmed package if it has no
+/// module (see :func:`trim_package`), as found in function metrics
+/// (:meth:`vroomrs.CallTreeFunction.get_package`).
+///
+/// Arguments
+/// ---------
+/// module : Optional[str]
+///   The module of the frame.
+/// package : Optional[str]
+///   The package of the frame.
+/// platform : Optional[str]
+///   The platform of the frame. Composer packages of PHP frames are kept
+///   whole.
+///
+/// Returns
+/// -------
+/// str
+///   The module or package, empty if the frame has neither.
+///
+/// Example
+/// --------
+///     >>> vroomrs.frame_module_or_package(package="/usr/lib/libc++.1.dylib")
+///     'libc++.1'
+///
+#[pyfunction]
+#[pyo3(signature = (module=None, package=None, platform=None))]
+fn frame_module_or_package(
+    module: Option<String>,
+    package: Option<String>,
+    platform: Option<String>,
+) -> String {
+    frame::Frame {
+        module,
+        package,
+        platform,
+        ..Default::default()
+    }
+    .module_or_package()
+}
+
+/// Sets the in_app overrides of an organization, applied to its profiles
+/// when they're normalized and taking precedence over the in_app heuristics.
+/// They replace the previous overrides of the organization.
+///
+/// The document lists package prefixes along with the in_app value forced on
+/// matching frames, e.g. ``{"rules": [{"prefix": "com.acme.", "in_app": true}]}``.
+/// Prefixes are matched against the frame module, or its package if it has
+/// no module, the longest matching prefix winning.
+///
+/// Arguments
+/// ---------
+/// organization_id : int
+///   The organization the overrides apply to.
+/// document : str
+///   The override document, as a json string.
+///
+/// Raises
+/// -------
+/// ValueError
+///     If the document is invalid, see :func:`validate_in_app_overrides`.
+///
+#[pyfunction]
+fn set_in_app_overrides(organization_id: u64, document: &str) -> PyResult<()> {
+    let overrides = InAppOverrides::from_json_str(document)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+    frame::in_app_overrides::set_organization_overrides(organization_id, overrides);
+    Ok(())
+}
+
+/// Removes the in_app overrides of an organization.
+///
+/// Arguments
+/// ---------
+/// organization_id : int
+///   The organization whose overrides are removed.
+///
+#[pyfunction]
+fn clear_in_app_overrides(organization_id: u64) {
+    frame::in_app_overrides::clear_organization_overrides(organization_id);
+}
+
+/// Sets whether the mangled C++ (``_ZN...``, ``__ZN...``) and Rust function
+/// names of frames are demangled when profiles are normalized, process-wide.
+///
+/// Demangling changes the fingerprint of the functions it renames, so it
+/// should be set before any profile is processed.
+///
+/// Arguments
+/// ---------
+/// enabled : bool
+///   Whether function names are demangled.
+/// preserve_mangled : bool
+///   Whether the mangled name is kept as the ``symbol`` of frames which
+///   have none. Defaults to False.
+///
+/// Example
+/// --------
+///     >>> vroomrs.set_demangling(True, preserve_mangled=True)
+///
+#[pyfunction]
+#[pyo3(signature = (enabled, preserve_mangled=false))]
+fn set_demangling(enabled: bool, preserve_mangled: bool) {
+    frame::set_demangle_options(frame::DemangleOptions {
+        enabled,
+        preserve_mangled,
+    });
+}
+
+/// Sets how the Swift function names of cocoa frames are simplified when
+/// profiles are normalized, process-wide, so the specializations of generic
+/// functions aren't different functions.
+///
+/// Simplification changes the fingerprint of the functions it renames, so it
+/// should be set before any profile is processed. Names are left as is unless
+/// it's set.
+///
+/// Arguments
+/// ---------
+/// strip_generics : bool
+///   Whether generic parameters (``MyApp.Cache<Swift.String>.value()`` becomes
+///   ``MyApp.Cache.value()``) and generic specialization prefixes are
+///   stripped. Defaults to True.
+/// strip_modules : bool
+///   Whether the prefixes of the module of the frame and of the Swift
+///   standard library (``MyApp.``, ``Swift.``) are stripped. Defaults to
+///   False.
+///
+/// Example
+/// --------
+///     >>> vroomrs.set_swift_simplification(strip_generics=True, strip_modules=True)
+///
+#[pyfunction]
+#[pyo3(signature = (strip_generics=true, strip_modules=false))]
+fn set_swift_simplification(strip_generics: bool, strip_modules: bool) {
+    frame::set_swift_simplify_options(frame::SwiftSimplifyOptions {
+        strip_generics,
+        strip_modules,
+    });
+}
+
+/// Validates an in_app override document, without applying it.
+///
+/// Arguments
+/// ---------
+/// document : str
+///   The override document, as a json string.
+///
+/// Raises
+/// -------
+/// ValueError
+///     If the document isn't valid json, has unknown fields, empty or
+///     duplicate prefixes, or more rules than allowed (1000 by default, see
+///     :func:`configure`).
+///
+#[pyfunction]
+fn validate_in_app_overrides(document: &str) -> PyResult<()> {
+    InAppOverrides::from_json_str(document)
+        .map(|_| ())
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))
+}
+
+/// Configures the limits applied while processing profiles, process-wide.
+///
+/// Limits not given are left as they are, so calling it without arguments
+/// returns the limits in effect. Limits are read when processing starts, so
+/// they should be configured before any profile is processed.
+///
+/// Arguments
+/// ---------
+/// max_stack_depth : Optional[int]
+///   The maximum depth of stacks (128 by default). Deeper stacks of android
+///   profiles are truncated when building call trees, and stack traces of
+///   occurrences only keep this many frames.
+/// max_in_app_rules : Optional[int]
+///   The maximum number of rules of an in_app override document (1000 by
+///   default).
+/// max_malformed_samples_ratio : Optional[float]
+///   The maximum ratio of the samples of a chunk which can be malformed (0.1
+///   by default). Up to it, malformed samples are skipped when building call
+///   trees rather than failing, 0 failing on any malformed sample.
+///
+/// Returns
+/// -------
+/// :class:`vroomrs.Limits`
+///   The limits in effect.
+///
+/// Raises
+/// -------
+/// ValueError
+///     If a limit is 0, or a ratio isn't between 0 and 1, in which case none are changed.
+///
+/// Example
+/// --------
+///     >>> vroomrs.configure(max_stack_depth=512).get_max_stack_depth()
+///     512
+///
+#[pyfunction]
+#[pyo3(signature = (max_stack_depth=None, max_in_app_rules=None, max_malformed_samples_ratio=None))]
+fn configure(
+    max_stack_depth: Option<u64>,
+    max_in_app_rules: Option<usize>,
+    max_malformed_samples_ratio: Option<f64>,
+) -> PyResult<Limits> {
+    limits::configure(
+        max_stack_depth,
+        max_in_app_rules,
+        max_malformed_samples_ratio,
+    )
+    .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))
+}
+
+/// Returns the analyses supported for each platform, so work can be routed
+/// and their availability surfaced without hardcoding them.
+///
+/// Platforms supporting no analysis are left out.
+///
+/// Returns
+/// -------
+/// dict[str, :class:`vroomrs.PlatformCapabilities`]
+///   The capabilities of each platform, by platform.
+///
+#[pyfunction]
+fn platform_capabilities() -> BTreeMap<String, PlatformCapabilities> {
+    capabilities::platform_capabilities()
+}
+
 #[pymodule]
 fn vroomrs(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<ProfileChunk>()?;
     m.add_class::<CallTreeFunction>()?;
+    m.add_class::<FunctionExample>()?;
+    m.add_class::<CpuSpike>()?;
+    m.add_class::<ActivityTimeline>()?;
+    m.add_class::<SymbolicationStats>()?;
+    m.add_class::<OtelSpan>()?;
+    m.add_class::<SpikeStack>()?;
+    m.add_class::<MeasurementMetric>()?;
+    m.add_class::<AnomalyScore>()?;
+    m.add_class::<ProcessingStats>()?;
+    m.add_class::<MemoryGrowth>()?;
+    m.add_class::<MemoryGrowthSuspect>()?;
+    m.add_class::<FlamechartComparison>()?;
+    m.add_class::<AppStartPrelude>()?;
+    m.add_class::<PreludeFrame>()?;
+    m.add_class::<SampleChunkBuilder>()?;
+    m.add_class::<AndroidChunkBuilder>()?;
+    m.add_class::<PlatformCapabilities>()?;
+    m.add_class::<DetectorInfo>()?;
+    m.add_class::<CalibrationStats>()?;
+    m.add_class::<Limits>()?;
+    m.add_class::<ProcessedProfile>()?;
+    m.add_class::<ProfileSummary>()?;
+    m.add_class::<ProcessedProfileChunk>()?;
+    m.add_class::<TopFrame>()?;
+    m.add("VroomError", m.py().get_type::<VroomError>())?;
+    m.add("ParseError", m.py().get_type::<ParseError>())?;
+    m.add(
+        "UnsupportedVersion",
+        m.py().get_type::<UnsupportedVersion>(),
+    )?;
+    m.add("InvalidStack", m.py().get_type::<InvalidStack>())?;
+    m.add("CompressionError", m.py().get_type::<CompressionError>())?;
     m.add_function(wrap_pyfunction!(profile_chunk_from_json_str, m)?)?;
     m.add_function(wrap_pyfunction!(
         profile_chunk_from_json_str_and_version,
         m
     )?)?;
+    m.add_function(wrap_pyfunction!(profile_chunks_from_json_str, m)?)?;
     m.add_function(wrap_pyfunction!(decompress_profile_chunk, m)?)?;
+    m.add_function(wrap_pyfunction!(stream_profile_chunk_functions_metrics, m)?)?;
     m.add_function(wrap_pyfunction!(profile_from_json_str, m)?)?;
     m.add_function(wrap_pyfunction!(decompress_profile, m)?)?;
+    m.add_function(wrap_pyfunction!(process_profile, m)?)?;
+    m.add_function(wrap_pyfunction!(process_profile_chunk, m)?)?;
+    m.add_function(wrap_pyfunction!(register_frame_detector, m)?)?;
+    m.add_function(wrap_pyfunction!(unregister_detector, m)?)?;
+    m.add_function(wrap_pyfunction!(list_detectors, m)?)?;
+    m.add_function(wrap_pyfunction!(set_detectors_enabled, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_synthetic_profile_chunk, m)?)?;
+    m.add_function(wrap_pyfunction!(merge_profile_chunks, m)?)?;
+    m.add_function(wrap_pyfunction!(aggregate_functions_metrics, m)?)?;
+    m.add_function(wrap_pyfunction!(compare_profile_chunks, m)?)?;
+    m.add_function(wrap_pyfunction!(serialize_functions_msgpack, m)?)?;
+    m.add_function(wrap_pyfunction!(hashing_scheme, m)?)?;
+    m.add_function(wrap_pyfunction!(fingerprint_function, m)?)?;
+    m.add_function(wrap_pyfunction!(trim_package, m)?)?;
+    m.add_function(wrap_pyfunction!(frame_module_or_package, m)?)?;
+    m.add_function(wrap_pyfunction!(set_in_app_overrides, m)?)?;
+    m.add_function(wrap_pyfunction!(clear_in_app_overrides, m)?)?;
+    m.add_function(wrap_pyfunction!(validate_in_app_overrides, m)?)?;
+    m.add_function(wrap_pyfunction!(set_demangling, m)?)?;
+    m.add_function(wrap_pyfunction!(set_swift_simplification, m)?)?;
+    m.add_function(wrap_pyfunction!(platform_capabilities, m)?)?;
+    m.add_function(wrap_pyfunction!(configure, m)?)?;
     Ok(())
 }
@@ -0,0 +1,173 @@
+//! Limits applied while processing profiles, configurable at runtime for
+//! workers whose customers legitimately exceed the defaults (e.g. the deep
+//! stacks of mobile game engines).
+//!
+//! Limits apply process-wide and are read by every module when processing
+//! starts, so they're meant to be configured once, before any profile is
+//! processed.
+
+use std::{fmt, sync::RwLock};
+
+use pyo3::{pyclass, pymethods};
+
+/// Default maximum depth of call tree stacks and occurrence stack traces.
+pub const DEFAULT_MAX_STACK_DEPTH: u64 = 128;
+
+/// Default maximum number of rules of an in_app override document.
+pub const DEFAULT_MAX_IN_APP_RULES: usize = 1000;
+
+/// Default maximum ratio of the samples of a chunk which can be malformed
+/// before building its call trees fails.
+pub const DEFAULT_MAX_MALFORMED_SAMPLES_RATIO: f64 = 0.1;
+
+static LIMITS: RwLock<Limits> = RwLock::new(Limits {
+    max_stack_depth: DEFAULT_MAX_STACK_DEPTH,
+    max_in_app_rules: DEFAULT_MAX_IN_APP_RULES,
+    max_malformed_samples_ratio: DEFAULT_MAX_MALFORMED_SAMPLES_RATIO,
+});
+
+/// The limits applied while processing profiles.
+#[pyclass]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    pub max_stack_depth: u64,
+    pub max_in_app_rules: usize,
+    pub max_malformed_samples_ratio: f64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_stack_depth: DEFAULT_MAX_STACK_DEPTH,
+            max_in_app_rules: DEFAULT_MAX_IN_APP_RULES,
+            max_malformed_samples_ratio: DEFAULT_MAX_MALFORMED_SAMPLES_RATIO,
+        }
+    }
+}
+
+#[pymethods]
+impl Limits {
+    /// Returns the maximum depth of stacks. Deeper stacks of android
+    /// profiles are truncated when building call trees, and stack traces of
+    /// occurrences only keep this many frames.
+    ///
+    /// Returns:
+    ///     int
+    ///         The maximum stack depth (128 by default).
+    pub fn get_max_stack_depth(&self) -> u64 {
+        self.max_stack_depth
+    }
+
+    /// Returns the maximum number of rules of an in_app override document.
+    ///
+    /// Returns:
+    ///     int
+    ///         The maximum number of rules (1000 by default).
+    pub fn get_max_in_app_rules(&self) -> usize {
+        self.max_in_app_rules
+    }
+
+    /// Returns the maximum ratio of the samples of a chunk which can be
+    /// malformed (referencing a stack or frame which doesn't exist). Up to
+    /// it, malformed samples are skipped when building call trees, and
+    /// counted in :meth:`ProcessingStats.get_malformed_samples`.
+    ///
+    /// Returns:
+    ///     float
+    ///         The maximum ratio of malformed samples (0.1 by default), 0
+    ///         failing on any malformed sample.
+    pub fn get_max_malformed_samples_ratio(&self) -> f64 {
+        self.max_malformed_samples_ratio
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LimitsError {
+    Zero(&'static str),
+    NotARatio(&'static str),
+}
+
+impl fmt::Display for LimitsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitsError::Zero(limit) => write!(f, "{limit} must be greater than 0"),
+            LimitsError::NotARatio(limit) => write!(f, "{limit} must be between 0 and 1"),
+        }
+    }
+}
+
+impl std::error::Error for LimitsError {}
+
+/// Returns the limits in effect.
+pub fn limits() -> Limits {
+    *LIMITS.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Returns the maximum depth of stacks in effect.
+pub fn max_stack_depth() -> u64 {
+    limits().max_stack_depth
+}
+
+/// Returns the maximum ratio of malformed samples in effect.
+pub fn max_malformed_samples_ratio() -> f64 {
+    limits().max_malformed_samples_ratio
+}
+
+/// Sets the limits given, leaving the others as they are, and returns the
+/// limits in effect. Limits are all left unchanged if any is invalid.
+pub fn configure(
+    max_stack_depth: Option<u64>,
+    max_in_app_rules: Option<usize>,
+    max_malformed_samples_ratio: Option<f64>,
+) -> Result<Limits, LimitsError> {
+    if max_stack_depth == Some(0) {
+        return Err(LimitsError::Zero("max_stack_depth"));
+    }
+    if max_in_app_rules == Some(0) {
+        return Err(LimitsError::Zero("max_in_app_rules"));
+    }
+    if max_malformed_samples_ratio.is_some_and(|ratio| !(0.0..=1.0).contains(&ratio)) {
+        return Err(LimitsError::NotARatio("max_malformed_samples_ratio"));
+    }
+    let mut limits = LIMITS.write().unwrap_or_else(|e| e.into_inner());
+    if let Some(max_stack_depth) = max_stack_depth {
+        limits.max_stack_depth = max_stack_depth;
+    }
+    if let Some(max_in_app_rules) = max_in_app_rules {
+        limits.max_in_app_rules = max_in_app_rules;
+    }
+    if let Some(max_malformed_samples_ratio) = max_malformed_samples_ratio {
+        limits.max_malformed_samples_ratio = max_malformed_samples_ratio;
+    }
+    Ok(*limits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{configure, limits, Limits, LimitsError};
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_configure() {
+        // other tests rely on the defaults, so they're left as they are
+        assert_eq!(configure(None, None, None), Ok(Limits::default()));
+        assert_eq!(
+            configure(Some(256), Some(0), None),
+            Err(LimitsError::Zero("max_in_app_rules"))
+        );
+        assert_eq!(
+            configure(Some(0), None, None).unwrap_err().to_string(),
+            "max_stack_depth must be greater than 0"
+        );
+        assert_eq!(
+            configure(Some(256), None, Some(1.5)),
+            Err(LimitsError::NotARatio("max_malformed_samples_ratio"))
+        );
+        assert_eq!(
+            configure(None, None, Some(f64::NAN)),
+            Err(LimitsError::NotARatio("max_malformed_samples_ratio"))
+        );
+        assert_eq!(limits(), Limits::default());
+    }
+}
@@ -0,0 +1,189 @@
+//! Aggregates the device impact measurements of profile chunks (CPU usage,
+//! memory footprint) into summary metrics, so they can be charted without
+//! reading raw measurements.
+
+use pyo3::{pyclass, pymethods};
+
+use crate::measurements::{MeasurementValue, Measurements};
+
+/// Measurements aggregated into metrics.
+pub const METRIC_MEASUREMENTS: &[&str] =
+    &["cpu_usage", "memory_footprint", "memory_native_footprint"];
+
+/// Summary of the values of a measurement over a profile chunk.
+#[pyclass]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeasurementMetric {
+    pub name: String,
+    pub unit: String,
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub p90: f64,
+}
+
+#[pymethods]
+impl MeasurementMetric {
+    /// Returns the name of the measurement.
+    ///
+    /// Returns:
+    ///     str
+    ///         The measurement name (e.g. "cpu_usage").
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the unit of the measurement.
+    ///
+    /// Returns:
+    ///     str
+    ///         The measurement unit (e.g. "percent", "byte").
+    pub fn get_unit(&self) -> &str {
+        &self.unit
+    }
+
+    /// Returns the number of values aggregated.
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of values of the measurement.
+    pub fn get_count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the minimum value of the measurement.
+    ///
+    /// Returns:
+    ///     float
+    ///         The minimum value.
+    pub fn get_min(&self) -> f64 {
+        self.min
+    }
+
+    /// Returns the maximum value of the measurement.
+    ///
+    /// Returns:
+    ///     float
+    ///         The maximum value.
+    pub fn get_max(&self) -> f64 {
+        self.max
+    }
+
+    /// Returns the mean value of the measurement.
+    ///
+    /// Returns:
+    ///     float
+    ///         The mean value.
+    pub fn get_mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Returns the 90th percentile of the values of the measurement.
+    ///
+    /// Returns:
+    ///     float
+    ///         The p90 value, using the nearest-rank method.
+    pub fn get_p90(&self) -> f64 {
+        self.p90
+    }
+}
+
+impl MeasurementMetric {
+    /// Aggregates the values, or returns `None` if there are none.
+    pub fn from_values(name: &str, unit: &str, values: &[MeasurementValue]) -> Option<Self> {
+        let mut sorted: Vec<f64> = values
+            .iter()
+            .map(|v| v.value)
+            .filter(|v| v.is_finite())
+            .collect();
+        if sorted.is_empty() {
+            return None;
+        }
+        sorted.sort_by(f64::total_cmp);
+        let rank = (0.9 * sorted.len() as f64).ceil() as usize;
+        Some(MeasurementMetric {
+            name: name.to_string(),
+            unit: unit.to_string(),
+            count: sorted.len(),
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            p90: sorted[rank.saturating_sub(1)],
+        })
+    }
+}
+
+/// Returns the metrics of the measurements listed in
+/// [`METRIC_MEASUREMENTS`], in that order, skipping missing or empty ones.
+pub fn measurement_metrics(measurements: Option<&Measurements>) -> Vec<MeasurementMetric> {
+    let Some(measurements) = measurements else {
+        return vec![];
+    };
+    METRIC_MEASUREMENTS
+        .iter()
+        .filter_map(|&name| {
+            let measurement = measurements.get(name)?;
+            MeasurementMetric::from_values(name, measurement.unit.as_str(), &measurement.values)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{measurement_metrics, MeasurementMetric};
+    use crate::{sample::v2::SampleChunkBuilder, types::ChunkInterface};
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_measurement_metrics() {
+        let mut builder = SampleChunkBuilder::new("cocoa");
+        builder
+            .measurement(
+                "memory_footprint",
+                "byte",
+                &[(1.0, 300.0), (1.1, 100.0), (1.2, 200.0)],
+            )
+            .measurement("frozen_frame_renders", "nanosecond", &[(1.0, 8e8)])
+            .measurement("cpu_usage", "percent", &[(1.0, 10.0), (1.1, f64::NAN)])
+            .measurement("memory_native_footprint", "byte", &[]);
+        let chunk = builder.build();
+
+        assert_eq!(
+            measurement_metrics(chunk.get_measurements()),
+            vec![
+                MeasurementMetric {
+                    name: "cpu_usage".to_string(),
+                    unit: "percent".to_string(),
+                    count: 1,
+                    min: 10.0,
+                    max: 10.0,
+                    mean: 10.0,
+                    p90: 10.0,
+                },
+                MeasurementMetric {
+                    name: "memory_footprint".to_string(),
+                    unit: "byte".to_string(),
+                    count: 3,
+                    min: 100.0,
+                    max: 300.0,
+                    mean: 200.0,
+                    p90: 300.0,
+                },
+            ]
+        );
+        assert_eq!(measurement_metrics(None), vec![]);
+    }
+
+    #[test]
+    fn test_p90() {
+        let values: Vec<(f64, f64)> = (1..=20).map(|i| (i as f64, i as f64)).collect();
+        let mut builder = SampleChunkBuilder::new("cocoa");
+        builder.measurement("cpu_usage", "percent", &values);
+        let chunk = builder.build();
+        let metrics = measurement_metrics(chunk.get_measurements());
+        assert_eq!(metrics[0].p90, 18.0);
+        assert_eq!(metrics[0].mean, 10.5);
+    }
+}
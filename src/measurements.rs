@@ -0,0 +1,358 @@
+//! Measurements collected alongside profiles and chunks (CPU usage, memory
+//! footprint, frame renders...), as series of timestamped values.
+//!
+//! Profiles and chunks only differ in how values are timestamped in
+//! payloads: relative to the start of the profile for profiles
+//! (`elapsed_since_start_ns`), as Unix timestamps in seconds for chunks
+//! (`timestamp`). Either way, values are timestamped in nanoseconds on the
+//! time axis of the call trees once parsed, so detectors can correlate
+//! measurements with call trees regardless of the format.
+
+use std::fmt;
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::timestamp::nanos_from_seconds;
+
+/// Measurements by name. Names are kept in their original order, so
+/// profiles round-trip unchanged.
+pub type Measurements = IndexMap<String, Measurement>;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Measurement {
+    pub unit: MeasurementUnit,
+    pub values: Vec<MeasurementValue>,
+    /// Fields we don't know about, passed through as is.
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MeasurementValue {
+    pub timestamp_ns: u64,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum MeasurementUnit {
+    /// The unit is missing.
+    #[default]
+    None,
+    Nanosecond,
+    Microsecond,
+    Millisecond,
+    Second,
+    Byte,
+    Percent,
+    Hertz,
+    /// Units we don't know about, or that aren't even strings, serialized
+    /// as JSON, so they don't fail the parsing of the whole profile.
+    Other(String),
+}
+
+impl MeasurementUnit {
+    pub fn as_str(&self) -> &str {
+        match self {
+            MeasurementUnit::None => "",
+            MeasurementUnit::Nanosecond => "nanosecond",
+            MeasurementUnit::Microsecond => "microsecond",
+            MeasurementUnit::Millisecond => "millisecond",
+            MeasurementUnit::Second => "second",
+            MeasurementUnit::Byte => "byte",
+            MeasurementUnit::Percent => "percent",
+            MeasurementUnit::Hertz => "hz",
+            MeasurementUnit::Other(unit) => unit,
+        }
+    }
+
+    /// Converts a duration to nanoseconds, or returns `None` if the unit
+    /// isn't a duration unit.
+    pub fn to_nanoseconds(&self, value: f64) -> Option<f64> {
+        match self {
+            MeasurementUnit::Nanosecond => Some(value),
+            MeasurementUnit::Microsecond => Some(value * 1e3),
+            MeasurementUnit::Millisecond => Some(value * 1e6),
+            MeasurementUnit::Second => Some(value * 1e9),
+            _ => None,
+        }
+    }
+}
+
+impl Measurement {
+    /// Builds a chunk measurement from `(timestamp, value)` pairs, with
+    /// timestamps in seconds.
+    ///
+    /// Panics if a timestamp is negative.
+    pub fn from_seconds(unit: &str, values: &[(f64, f64)]) -> Self {
+        Measurement {
+            unit: unit.into(),
+            values: values
+                .iter()
+                .map(|&(timestamp, value)| MeasurementValue {
+                    timestamp_ns: nanos_from_seconds(timestamp).expect("invalid timestamp"),
+                    value,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&str> for MeasurementUnit {
+    fn from(unit: &str) -> Self {
+        match unit {
+            "" => MeasurementUnit::None,
+            "nanosecond" => MeasurementUnit::Nanosecond,
+            "microsecond" => MeasurementUnit::Microsecond,
+            "millisecond" => MeasurementUnit::Millisecond,
+            "second" => MeasurementUnit::Second,
+            "byte" => MeasurementUnit::Byte,
+            "percent" => MeasurementUnit::Percent,
+            "hz" => MeasurementUnit::Hertz,
+            unit => MeasurementUnit::Other(unit.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for MeasurementUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl PartialEq<&str> for MeasurementUnit {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Serialize for MeasurementUnit {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for MeasurementUnit {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match serde_json::Value::deserialize(deserializer)? {
+            serde_json::Value::String(unit) => unit.as_str().into(),
+            serde_json::Value::Null => MeasurementUnit::None,
+            unit => MeasurementUnit::Other(unit.to_string()),
+        })
+    }
+}
+
+/// A measurement as found in payloads, `V` being the value format.
+#[derive(Serialize, Deserialize)]
+struct RawMeasurement<V> {
+    #[serde(default)]
+    unit: MeasurementUnit,
+    values: Vec<V>,
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Value format of profile measurements.
+#[derive(Serialize, Deserialize)]
+struct ElapsedValue {
+    elapsed_since_start_ns: u64,
+    value: f64,
+}
+
+/// Value format of chunk measurements.
+#[derive(Serialize, Deserialize)]
+struct UnixValue {
+    #[serde(with = "crate::timestamp::seconds")]
+    timestamp: u64,
+    value: f64,
+}
+
+impl From<ElapsedValue> for MeasurementValue {
+    fn from(value: ElapsedValue) -> Self {
+        MeasurementValue {
+            timestamp_ns: value.elapsed_since_start_ns,
+            value: value.value,
+        }
+    }
+}
+
+impl From<&MeasurementValue> for ElapsedValue {
+    fn from(value: &MeasurementValue) -> Self {
+        ElapsedValue {
+            elapsed_since_start_ns: value.timestamp_ns,
+            value: value.value,
+        }
+    }
+}
+
+impl From<UnixValue> for MeasurementValue {
+    fn from(value: UnixValue) -> Self {
+        MeasurementValue {
+            timestamp_ns: value.timestamp,
+            value: value.value,
+        }
+    }
+}
+
+impl From<&MeasurementValue> for UnixValue {
+    fn from(value: &MeasurementValue) -> Self {
+        UnixValue {
+            timestamp: value.timestamp_ns,
+            value: value.value,
+        }
+    }
+}
+
+impl<V: Into<MeasurementValue>> From<RawMeasurement<V>> for Measurement {
+    fn from(measurement: RawMeasurement<V>) -> Self {
+        Measurement {
+            unit: measurement.unit,
+            values: measurement.values.into_iter().map(Into::into).collect(),
+            extra: measurement.extra,
+        }
+    }
+}
+
+impl<'a, V: From<&'a MeasurementValue>> From<&'a Measurement> for RawMeasurement<V> {
+    fn from(measurement: &'a Measurement) -> Self {
+        RawMeasurement {
+            unit: measurement.unit.clone(),
+            values: measurement.values.iter().map(Into::into).collect(),
+            extra: measurement.extra.clone(),
+        }
+    }
+}
+
+fn serialize_measurements<S, V>(
+    measurements: &Option<Measurements>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    V: Serialize + for<'a> From<&'a MeasurementValue>,
+{
+    match measurements {
+        Some(measurements) => serializer.collect_map(
+            measurements
+                .iter()
+                .map(|(name, measurement)| (name, RawMeasurement::<V>::from(measurement))),
+        ),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Serde helpers for the measurements of profiles, to use with
+/// `#[serde(with)]`.
+pub(crate) mod profile_format {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        measurements: &Option<Measurements>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serialize_measurements::<S, ElapsedValue>(measurements, serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Measurements>, D::Error> {
+        let measurements: Option<IndexMap<String, RawMeasurement<ElapsedValue>>> =
+            Option::deserialize(deserializer)?;
+        Ok(measurements.map(|measurements| {
+            measurements
+                .into_iter()
+                .map(|(name, measurement)| (name, measurement.into()))
+                .collect()
+        }))
+    }
+}
+
+/// Serde helpers for the measurements of chunks, to use with
+/// `#[serde(with)]`.
+///
+/// Chunk measurements are best effort: malformed measurements are dropped
+/// rather than failing the parsing of the whole chunk.
+pub(crate) mod chunk_format {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        measurements: &Option<Measurements>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serialize_measurements::<S, UnixValue>(measurements, serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Measurements>, D::Error> {
+        let Some(serde_json::Value::Object(measurements)) = Option::deserialize(deserializer)?
+        else {
+            return Ok(None);
+        };
+        Ok(Some(
+            measurements
+                .into_iter()
+                .filter_map(|(name, measurement)| {
+                    let measurement = RawMeasurement::<UnixValue>::deserialize(measurement).ok()?;
+                    Some((name, measurement.into()))
+                })
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Measurement, MeasurementUnit, MeasurementValue, Measurements};
+
+    use pretty_assertions::assert_eq;
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Chunk {
+        #[serde(default, with = "super::chunk_format")]
+        measurements: Option<Measurements>,
+    }
+
+    #[test]
+    fn test_chunk_format() {
+        let payload = r#"{"measurements": {
+            "cpu_usage": {"unit": "percent", "values": [{"timestamp": 1737465052.42, "value": 12.5}]},
+            "frozen_frame_renders": {"unit": "millisecond", "values": [{"timestamp": 1737465052, "value": 800}]},
+            "malformed": {"unit": "byte", "values": [{"value": 1.0}]}
+        }}"#;
+        let chunk: Chunk = serde_json::from_str(payload).unwrap();
+        let measurements = chunk.measurements.unwrap();
+        assert_eq!(
+            measurements.keys().collect::<Vec<_>>(),
+            vec!["cpu_usage", "frozen_frame_renders"]
+        );
+        assert_eq!(
+            measurements["cpu_usage"],
+            Measurement {
+                unit: MeasurementUnit::Percent,
+                values: vec![MeasurementValue {
+                    timestamp_ns: 1_737_465_052_420_000_000,
+                    value: 12.5,
+                }],
+                ..Default::default()
+            }
+        );
+        let frozen = &measurements["frozen_frame_renders"];
+        assert_eq!(
+            frozen.unit.to_nanoseconds(frozen.values[0].value),
+            Some(8e8)
+        );
+    }
+
+    #[test]
+    fn test_chunk_format_is_lenient() {
+        for payload in [
+            r#"{}"#,
+            r#"{"measurements": null}"#,
+            r#"{"measurements": []}"#,
+        ] {
+            let chunk: Chunk = serde_json::from_str(payload).unwrap();
+            assert_eq!(chunk.measurements, None, "{payload}");
+        }
+    }
+}
@@ -0,0 +1,333 @@
+//! Correlates the growth of the memory footprint of a profile chunk with
+//! the frames allocating memory while it grew.
+
+use std::collections::HashMap;
+
+use pyo3::{pyclass, pymethods};
+
+use crate::{
+    measurements::MeasurementValue,
+    nodetree::{CallTree, NodeId},
+    types::CallTreesStr,
+};
+
+/// Measurements tracking the memory footprint, by order of preference.
+pub const MEMORY_FOOTPRINT_MEASUREMENTS: &[&str] = &["memory_footprint", "memory_native_footprint"];
+
+/// Function names (or prefixes thereof) of frames allocating memory.
+const ALLOCATION_FUNCTION_PREFIXES: &[&str] = &[
+    "PyMem_Malloc",
+    "PyMem_Realloc",
+    "PyObject_Malloc",
+    "PyObject_Realloc",
+    "_PyObject_Malloc",
+    "calloc",
+    "malloc",
+    "malloc_zone_calloc",
+    "malloc_zone_malloc",
+    "malloc_zone_realloc",
+    "operator new",
+    "posix_memalign",
+    "realloc",
+];
+
+fn is_allocation_function(name: &str) -> bool {
+    ALLOCATION_FUNCTION_PREFIXES.iter().any(|prefix| {
+        name.strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(['(', '[', ' ']))
+    })
+}
+
+/// An interval during which the memory footprint kept growing.
+#[pyclass]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemoryGrowth {
+    pub start_ns: u64,
+    pub end_ns: u64,
+    pub growth_bytes: f64,
+    pub suspects: Vec<MemoryGrowthSuspect>,
+}
+
+/// A function calling allocation functions while the memory footprint grew.
+#[pyclass]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryGrowthSuspect {
+    pub function: String,
+    pub package: String,
+    pub in_app: bool,
+    pub allocation_duration_ns: u64,
+}
+
+#[pymethods]
+impl MemoryGrowth {
+    /// Returns the start timestamp of the growth interval in nanoseconds.
+    ///
+    /// Returns:
+    ///     int
+    ///         The timestamp of the first measurement of the interval.
+    pub fn get_start_ns(&self) -> u64 {
+        self.start_ns
+    }
+
+    /// Returns the end timestamp of the growth interval in nanoseconds.
+    ///
+    /// Returns:
+    ///     int
+    ///         The timestamp of the last measurement of the interval.
+    pub fn get_end_ns(&self) -> u64 {
+        self.end_ns
+    }
+
+    /// Returns how much the memory footprint grew during the interval.
+    ///
+    /// Returns:
+    ///     float
+    ///         The growth, in the unit of the measurement (usually bytes).
+    pub fn get_growth_bytes(&self) -> f64 {
+        self.growth_bytes
+    }
+
+    /// Returns the functions suspected to cause the growth.
+    ///
+    /// Returns:
+    ///     list[MemoryGrowthSuspect]
+    ///         The suspects, sorted by descending time spent allocating.
+    pub fn get_suspects(&self) -> Vec<MemoryGrowthSuspect> {
+        self.suspects.clone()
+    }
+}
+
+#[pymethods]
+impl MemoryGrowthSuspect {
+    /// Returns the function name.
+    ///
+    /// Returns:
+    ///     str
+    ///         The function name.
+    pub fn get_function(&self) -> &str {
+        &self.function
+    }
+
+    /// Returns the package name.
+    ///
+    /// Returns:
+    ///     str
+    ///         The package name.
+    pub fn get_package(&self) -> &str {
+        &self.package
+    }
+
+    /// Returns whether the function is in an app or system one.
+    ///
+    /// Returns:
+    ///     bool
+    ///         True if the function is an app one, False otherwise.
+    pub fn get_in_app(&self) -> bool {
+        self.in_app
+    }
+
+    /// Returns the time spent in allocation functions called by the function.
+    ///
+    /// Returns:
+    ///     int
+    ///         The duration in nanoseconds, within the growth interval.
+    pub fn get_allocation_duration_ns(&self) -> u64 {
+        self.allocation_duration_ns
+    }
+}
+
+/// Returns the intervals during which the memory footprint monotonically
+/// grew by at least `min_growth`. Suspects are left empty.
+pub fn find_memory_growths(values: &[MeasurementValue], min_growth: f64) -> Vec<MemoryGrowth> {
+    let mut values: Vec<(u64, f64)> = values.iter().map(|v| (v.timestamp_ns, v.value)).collect();
+    values.sort_by_key(|(timestamp_ns, _)| *timestamp_ns);
+
+    let mut growths = Vec::new();
+    let mut start = 0;
+    for end in 1..=values.len() {
+        if end < values.len() && values[end].1 >= values[end - 1].1 {
+            continue;
+        }
+        // values[start..end] is a monotonic run
+        let growth = values[end - 1].1 - values[start].1;
+        if growth > 0.0 && growth >= min_growth {
+            growths.push(MemoryGrowth {
+                start_ns: values[start].0,
+                end_ns: values[end - 1].0,
+                growth_bytes: growth,
+                ..Default::default()
+            });
+        }
+        start = end;
+    }
+    growths
+}
+
+/// Returns the `max_suspects` functions that spent the most time calling
+/// allocation functions between `start_ns` and `end_ns`. Allocations are
+/// credited to the closest application caller, or to the direct caller if
+/// there is none.
+pub fn memory_growth_suspects(
+    call_trees: &CallTreesStr,
+    start_ns: u64,
+    end_ns: u64,
+    max_suspects: usize,
+) -> Vec<MemoryGrowthSuspect> {
+    let mut suspects: HashMap<(String, String), MemoryGrowthSuspect> = HashMap::new();
+    for call_tree in call_trees.values() {
+        for &root in call_tree.roots() {
+            collect_allocations(
+                call_tree,
+                root,
+                start_ns,
+                end_ns,
+                &mut vec![],
+                &mut suspects,
+            );
+        }
+    }
+
+    let mut suspects: Vec<MemoryGrowthSuspect> = suspects.into_values().collect();
+    suspects.sort_by(|a, b| {
+        b.allocation_duration_ns
+            .cmp(&a.allocation_duration_ns)
+            .then_with(|| a.package.cmp(&b.package))
+            .then_with(|| a.function.cmp(&b.function))
+    });
+    suspects.truncate(max_suspects);
+    suspects
+}
+
+fn collect_allocations(
+    call_tree: &CallTree,
+    id: NodeId,
+    start_ns: u64,
+    end_ns: u64,
+    callers: &mut Vec<NodeId>,
+    suspects: &mut HashMap<(String, String), MemoryGrowthSuspect>,
+) {
+    let n = &call_tree[id];
+    let overlap_ns = n
+        .end_ns
+        .min(end_ns)
+        .saturating_sub(n.start_ns.max(start_ns));
+    if overlap_ns == 0 {
+        return;
+    }
+
+    if is_allocation_function(&n.name) {
+        let suspect = &call_tree[callers
+            .iter()
+            .rev()
+            .find(|&&caller| call_tree[caller].is_application)
+            .or(callers.last())
+            .copied()
+            .unwrap_or(id)];
+        suspects
+            .entry((suspect.package.clone(), suspect.name.clone()))
+            .or_insert_with(|| MemoryGrowthSuspect {
+                function: suspect.name.clone(),
+                package: suspect.package.clone(),
+                in_app: suspect.is_application,
+                allocation_duration_ns: 0,
+            })
+            .allocation_duration_ns += overlap_ns;
+        // children of allocation functions are allocator internals
+        return;
+    }
+
+    callers.push(id);
+    for &child in call_tree.children(id) {
+        collect_allocations(call_tree, child, start_ns, end_ns, callers, suspects);
+    }
+    callers.pop();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_memory_growths, is_allocation_function, memory_growth_suspects};
+    use crate::{
+        frame::Frame, measurements::Measurement, sample::v2::SampleChunkBuilder,
+        types::ChunkInterface,
+    };
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_is_allocation_function() {
+        assert!(is_allocation_function("malloc"));
+        assert!(is_allocation_function("posix_memalign"));
+        assert!(is_allocation_function("operator new(unsigned long)"));
+        assert!(is_allocation_function("PyObject_Malloc"));
+        assert!(!is_allocation_function("malloc_size"));
+        assert!(!is_allocation_function("free"));
+    }
+
+    #[test]
+    fn test_find_memory_growths() {
+        let values = Measurement::from_seconds(
+            "byte",
+            &[
+                (1.0, 100.0),
+                (1.1, 200.0),
+                (1.2, 300.0),
+                (1.3, 250.0),
+                (1.4, 260.0),
+                (1.5, 240.0),
+            ],
+        )
+        .values;
+
+        let growths: Vec<(u64, u64, f64)> = find_memory_growths(&values, 50.0)
+            .into_iter()
+            .map(|g| (g.start_ns, g.end_ns, g.growth_bytes))
+            .collect();
+        assert_eq!(growths, vec![(1_000_000_000, 1_200_000_000, 200.0)]);
+
+        assert_eq!(find_memory_growths(&values, 0.0).len(), 2);
+    }
+
+    #[test]
+    fn test_memory_growth_suspects() {
+        let mut builder = SampleChunkBuilder::new("cocoa");
+        let frame = |name: &str, in_app: bool| Frame {
+            function: Some(name.to_string()),
+            package: Some(if in_app { "MyApp" } else { "libsystem" }.to_string()),
+            in_app: Some(in_app),
+            ..Default::default()
+        };
+        let main = builder.frame(frame("main", true));
+        let load = builder.frame(frame("loadImages", true));
+        let decode = builder.frame(frame("decode", false));
+        let malloc = builder.frame(frame("malloc", false));
+        let nano = builder.frame(frame("nanov2_malloc", false));
+        builder
+            .thread("1", "main")
+            .sample("1", 1.0, &[main])
+            .sample("1", 1.1, &[nano, malloc, decode, load, main])
+            .sample("1", 1.2, &[malloc, decode, load, main])
+            .sample("1", 1.3, &[malloc, main])
+            .sample("1", 1.4, &[main])
+            .measurement("memory_footprint", "byte", &[(1.0, 1000.0), (1.4, 5000.0)]);
+        let mut chunk = builder.build();
+
+        let footprint = chunk.measurement("memory_footprint").unwrap();
+        let growths = find_memory_growths(&footprint.values, 0.0);
+        assert_eq!(growths.len(), 1);
+
+        let call_trees = chunk.call_trees(None).unwrap();
+        let suspects =
+            memory_growth_suspects(&call_trees, growths[0].start_ns, growths[0].end_ns, 5);
+        let suspects: Vec<(&str, bool, u64)> = suspects
+            .iter()
+            .map(|s| (s.function.as_str(), s.in_app, s.allocation_duration_ns))
+            .collect();
+        assert_eq!(
+            suspects,
+            vec![
+                ("loadImages", true, 200_000_000),
+                ("main", true, 100_000_000)
+            ]
+        );
+    }
+}
@@ -0,0 +1,123 @@
+//! Serializes processed outputs (functions, occurrences, call trees) to
+//! MessagePack, for the Kafka producers to publish them as is instead of
+//! converting them to dicts and packing those in Python.
+
+use std::collections::BTreeMap;
+
+use pyo3::PyErr;
+use serde::Serialize;
+
+use crate::{
+    nodetree::{CallTree, Node, NodeId},
+    types::CallTreesStr,
+};
+
+/// Serializes a value to MessagePack, structs being encoded as maps keyed
+/// by field name, like in their JSON serialization.
+pub(crate) fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, PyErr> {
+    rmp_serde::to_vec_named(value)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))
+}
+
+/// A call tree node serialized along with its children.
+#[derive(Debug, Serialize)]
+pub(crate) struct SerializedNode<'a> {
+    #[serde(flatten)]
+    node: &'a Node,
+    children: Vec<SerializedNode<'a>>,
+}
+
+impl<'a> SerializedNode<'a> {
+    fn new(call_tree: &'a CallTree, id: NodeId) -> Self {
+        Self {
+            node: &call_tree[id],
+            children: call_tree
+                .children(id)
+                .iter()
+                .map(|&child| Self::new(call_tree, child))
+                .collect(),
+        }
+    }
+}
+
+/// Returns the root nodes of the call trees of each thread, nesting their
+/// children, sorted by thread ID so the serialization is deterministic.
+pub(crate) fn nested_call_trees<'a>(
+    call_trees: &'a CallTreesStr,
+) -> BTreeMap<&'a str, Vec<SerializedNode<'a>>> {
+    call_trees
+        .iter()
+        .map(|(thread_id, call_tree)| {
+            (
+                thread_id.as_ref(),
+                call_tree
+                    .roots()
+                    .iter()
+                    .map(|&root| SerializedNode::new(call_tree, root))
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{nested_call_trees, to_vec};
+    use crate::{
+        frame::Frame,
+        nodetree::{CallTreeFunction, FunctionExample},
+        sample::v2::SampleChunkBuilder,
+        types::ChunkInterface,
+    };
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_call_trees_to_msgpack() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let function = |name: &str| Frame {
+            function: Some(name.to_string()),
+            module: Some("app".to_string()),
+            ..Default::default()
+        };
+        let main = builder.frame(function("main"));
+        let load = builder.frame(function("load"));
+        builder
+            .sample("1", 10.0, &[load, main])
+            .sample("1", 10.1, &[main])
+            .sample("1", 10.2, &[main]);
+        let mut chunk = builder.build();
+        let call_trees = chunk.call_trees(None).unwrap();
+
+        let payload = to_vec(&nested_call_trees(&call_trees)).unwrap();
+        let decoded: serde_json::Value = rmp_serde::from_slice(&payload).unwrap();
+        let main = &decoded["1"][0];
+        assert_eq!(main["name"], "main");
+        assert_eq!(main["duration_ns"], 200_000_000);
+        assert_eq!(main["frame"]["function"], "main");
+        assert_eq!(main["children"][0]["name"], "load");
+        assert_eq!(main["children"][0]["children"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_functions_to_msgpack() {
+        let functions = vec![CallTreeFunction {
+            fingerprint: 1234,
+            function: "load".to_string(),
+            self_times_ns: vec![10, 20],
+            examples: vec![FunctionExample {
+                thread_id: "1".to_string(),
+                self_time_ns: 20,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }];
+        let payload = to_vec(&functions).unwrap();
+        let decoded: serde_json::Value = rmp_serde::from_slice(&payload).unwrap();
+        assert_eq!(decoded[0]["fingerprint"], 1234);
+        assert_eq!(decoded[0]["function"], "load");
+        assert_eq!(decoded[0]["self_times_ns"], serde_json::json!([10, 20]));
+        assert_eq!(decoded[0]["examples"][0]["self_time_ns"], 20);
+        assert_eq!(decoded[0]["stack_fingerprint"], serde_json::Value::Null);
+    }
+}
@@ -1,19 +1,23 @@
 use std::{
-    cell::RefCell,
     collections::{HashMap, HashSet},
     hash::Hasher,
-    rc::Rc,
+    ops::{Index, IndexMut},
+    sync::Arc,
 };
 
 use once_cell::sync::Lazy;
-use pyo3::{pyclass, pymethods};
+use pyo3::{pyclass, pymethods, types::PyAnyMethods, Bound, PyAny, PyErr, PyResult, Python};
 
-use crate::frame::Frame;
+use crate::{
+    frame::Frame,
+    msgpack,
+    profile_chunk::ProfileChunk,
+    types::{CallTreeOptions, ChunkInterface},
+};
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+#[non_exhaustive]
 pub struct Node {
-    pub children: Vec<Rc<RefCell<Node>>>,
-
     pub duration_ns: u64,
 
     pub fingerprint: u64,
@@ -30,23 +34,231 @@ pub struct Node {
 
     pub end_ns: u64,
 
-    pub frame: Frame,
+    pub frame: Arc<Frame>,
 
     pub sample_count: u64,
 
     pub start_ns: u64,
+
+    /// Number of consecutive recursive calls collapsed into the node, see
+    /// [`crate::types::CallTreeOptions::collapse_recursion`].
+    #[serde(skip_serializing_if = "is_zero")]
+    pub recursion_count: u64,
+
+    /// Whether the node is the outermost remaining frame of stacks truncated
+    /// at the maximum depth, its callers having been cut.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+fn is_zero(count: &u64) -> bool {
+    *count == 0
+}
+
+/// Index of a node in a [`CallTree`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(u32);
+
+/// The call trees of a thread, stored in an arena: nodes live in a single
+/// vector and refer to their children by index, instead of each node being
+/// a separate reference-counted allocation.
+#[derive(Debug, Clone, Default)]
+pub struct CallTree {
+    nodes: Vec<Node>,
+    children: Vec<Vec<NodeId>>,
+    roots: Vec<NodeId>,
+}
+
+impl CallTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of nodes in the arena.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the root nodes, in chronological order.
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    /// Returns the children of a node, in chronological order.
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.children[id.0 as usize]
+    }
+
+    /// Appends a node to the children of `parent`, or to the roots if there
+    /// is no parent, and returns its ID.
+    pub fn push(&mut self, parent: Option<NodeId>, node: Node) -> NodeId {
+        let id = NodeId(self.nodes.len() as u32);
+        self.nodes.push(node);
+        self.children.push(Vec::new());
+        match parent {
+            Some(parent) => self.children[parent.0 as usize].push(id),
+            None => self.roots.push(id),
+        }
+        id
+    }
+
+    /// Adds a sampled frame under `parent` (or as a root if there is no
+    /// parent). The last child is extended up to `end_ns` if it has the same
+    /// fingerprint and ends where the sample starts, otherwise a new node is
+    /// appended. Returns the ID of the node the frame was added to.
+    ///
+    /// The sample stands for `weight` samples: it counts `weight` times
+    /// towards the sample count and duration of the node.
+    pub fn add_sampled_frame(
+        &mut self,
+        parent: Option<NodeId>,
+        frame: &Arc<Frame>,
+        fingerprint: u64,
+        start_ns: u64,
+        end_ns: u64,
+        weight: u64,
+    ) -> NodeId {
+        let siblings = match parent {
+            Some(parent) => self.children(parent),
+            None => self.roots(),
+        };
+        if let Some(&last) = siblings.last() {
+            let node = &mut self[last];
+            if node.fingerprint == fingerprint && node.end_ns == start_ns {
+                node.add_sample(end_ns, weight);
+                return last;
+            }
+        }
+        let mut node = Node::from_frame(frame, start_ns, end_ns, fingerprint);
+        node.sample_count = weight;
+        node.duration_ns *= weight;
+        self.push(parent, node)
+    }
+
+    /// Sets the end of the node and its descendants still open to
+    /// `timestamp`, or to the end of their closest closed ancestor.
+    pub fn close(&mut self, id: NodeId, mut timestamp: u64) {
+        let node = &mut self[id];
+        if node.end_ns == 0 {
+            node.set_duration(timestamp);
+        } else {
+            timestamp = node.end_ns;
+        }
+        for i in 0..self.children(id).len() {
+            self.close(self.children(id)[i], timestamp);
+        }
+    }
+
+    /// Appends the call trees of `other`, keeping the roots in
+    /// chronological order.
+    pub fn append(&mut self, other: CallTree) {
+        let offset = self.nodes.len() as u32;
+        let rebase = |ids: Vec<NodeId>| -> Vec<NodeId> {
+            ids.into_iter().map(|id| NodeId(id.0 + offset)).collect()
+        };
+        self.nodes.extend(other.nodes);
+        self.children.extend(other.children.into_iter().map(rebase));
+        self.roots.extend(rebase(other.roots));
+        let nodes = &self.nodes;
+        self.roots.sort_by_key(|id| nodes[id.0 as usize].start_ns);
+    }
+
+    fn eq_subtree(&self, id: NodeId, other: &CallTree, other_id: NodeId) -> bool {
+        self[id] == other[other_id]
+            && self.children(id).len() == other.children(other_id).len()
+            && self
+                .children(id)
+                .iter()
+                .zip(other.children(other_id))
+                .all(|(&child, &other_child)| self.eq_subtree(child, other, other_child))
+    }
+}
+
+impl Index<NodeId> for CallTree {
+    type Output = Node;
+
+    fn index(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0 as usize]
+    }
+}
+
+impl IndexMut<NodeId> for CallTree {
+    fn index_mut(&mut self, id: NodeId) -> &mut Node {
+        &mut self.nodes[id.0 as usize]
+    }
+}
+
+/// Call trees are equal if their nodes are, regardless of the order they
+/// were added to the arena in.
+impl PartialEq for CallTree {
+    fn eq(&self, other: &Self) -> bool {
+        self.roots.len() == other.roots.len()
+            && self
+                .roots
+                .iter()
+                .zip(&other.roots)
+                .all(|(&root, &other_root)| self.eq_subtree(root, other, other_root))
+    }
+}
+
+impl Eq for CallTree {}
+
+/// A node along with its children, to spell out call trees in tests.
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub(crate) struct NestedNode(pub Node, pub Vec<NestedNode>);
+
+#[cfg(test)]
+impl CallTree {
+    pub(crate) fn from_nested(roots: Vec<NestedNode>) -> Self {
+        fn add(tree: &mut CallTree, parent: Option<NodeId>, node: NestedNode) {
+            let id = tree.push(parent, node.0);
+            for child in node.1 {
+                add(tree, Some(id), child);
+            }
+        }
+        let mut tree = CallTree::new();
+        for root in roots {
+            add(&mut tree, None, root);
+        }
+        tree
+    }
+}
+
+/// Shares the frames of a frame table between the nodes of call trees, so
+/// each frame is cloned once rather than once per node.
+pub struct FrameInterner<'a> {
+    frames: &'a [Frame],
+    interned: Vec<Option<Arc<Frame>>>,
+}
+
+impl<'a> FrameInterner<'a> {
+    pub fn new(frames: &'a [Frame]) -> Self {
+        Self {
+            frames,
+            interned: vec![None; frames.len()],
+        }
+    }
+
+    /// Returns the frame at `index` in the frame table.
+    pub fn get(&mut self, index: usize) -> &Arc<Frame> {
+        self.interned[index].get_or_insert_with(|| Arc::new(self.frames[index].clone()))
+    }
 }
 
 impl Node {
-    pub fn from_frame(f: &Frame, start: u64, end: u64, fingerprint: u64) -> Rc<RefCell<Node>> {
+    pub fn from_frame(f: &Arc<Frame>, start: u64, end: u64, fingerprint: u64) -> Node {
         let is_application = f.in_app.unwrap_or(true);
 
         let mut node = Node {
-            children: Vec::new(),
             duration_ns: 0,
             end_ns: end,
             fingerprint,
-            frame: f.clone(),
+            frame: Arc::clone(f),
             is_application,
             line: f.line,
             name: f.function.as_deref().unwrap_or_default().into(),
@@ -54,13 +266,15 @@ impl Node {
             path: f.path.clone(),
             sample_count: 1,
             start_ns: start,
+            recursion_count: 0,
+            truncated: false,
         };
 
         if end > 0 {
             node.duration_ns = node.end_ns - node.start_ns;
         }
 
-        Rc::new(RefCell::new(node))
+        node
     }
 
     pub fn update(&mut self, timestamp: u64) {
@@ -68,8 +282,16 @@ impl Node {
         self.set_duration(timestamp);
     }
 
+    /// Extends the node up to `timestamp` with a sample standing for
+    /// `weight` samples.
+    pub fn add_sample(&mut self, timestamp: u64, weight: u64) {
+        self.sample_count += weight;
+        self.duration_ns += (timestamp - self.end_ns) * weight;
+        self.end_ns = timestamp;
+    }
+
     pub fn to_frame(&self) -> Frame {
-        let mut frame = self.frame.clone();
+        let mut frame = Frame::clone(&self.frame);
         if let Some(mut data) = frame.data {
             data.symbolicator_status = frame.status.clone();
             frame.data = Some(data);
@@ -90,18 +312,9 @@ impl Node {
             h.write(self.name.as_bytes());
         }
     }
+}
 
-    pub fn close(&mut self, mut timestamp: u64) {
-        if self.end_ns == 0 {
-            self.set_duration(timestamp);
-        } else {
-            timestamp = self.end_ns;
-        }
-        for child in &self.children {
-            child.borrow_mut().close(timestamp);
-        }
-    }
-
+impl CallTree {
     // `collect_functions` walks the node tree and writes functions into the `results` parameter.
     // When `filter_non_leaf_functions` is true, only functions with non-zero self-time are collected.
     // When `filter_non_leaf_functions` is false, all functions are collected regardless of self-time.
@@ -121,9 +334,13 @@ impl Node {
     // children with durations 20ms, 30ms, and 40ms, and they are system, application, system
     // functions respectively, the self-time of `bar` will be 70ms because
     // 100ms - 30ms = 70ms.
+    //
+    // The total time of a function is the duration of its calls, a call nested in
+    // another call of the same function (recursion) being covered by the outer one.
     #[allow(clippy::too_many_arguments)]
     pub fn collect_functions(
         &self,
+        id: NodeId,
         results: &mut HashMap<u32, CallTreeFunction>,
         thread_id: &str,
         node_depth: u16,
@@ -131,34 +348,90 @@ impl Node {
         filter_system_frames: bool,
         filter_non_leaf_functions: bool,
         generate_stack_fingerprints: bool,
+        max_examples: usize,
+        aggregate_lines: bool,
         parent_fingerprint: Option<u32>,
     ) -> (u64, u64) {
+        self.collect_functions_with_total_times(
+            id,
+            results,
+            &mut HashMap::new(),
+            thread_id,
+            node_depth,
+            min_depth,
+            filter_system_frames,
+            filter_non_leaf_functions,
+            generate_stack_fingerprints,
+            max_examples,
+            aggregate_lines,
+            parent_fingerprint,
+        )
+    }
+
+    /// Collects the functions of the subtree of a node, see
+    /// [`CallTree::collect_functions`]. `total_times_ns` sums the total time
+    /// credited to each function while walking the call tree, so calls can
+    /// tell how much of it was credited to the calls nested into them.
+    #[allow(clippy::too_many_arguments)]
+    fn collect_functions_with_total_times(
+        &self,
+        id: NodeId,
+        results: &mut HashMap<u32, CallTreeFunction>,
+        total_times_ns: &mut HashMap<u32, u64>,
+        thread_id: &str,
+        node_depth: u16,
+        min_depth: u16,
+        filter_system_frames: bool,
+        filter_non_leaf_functions: bool,
+        generate_stack_fingerprints: bool,
+        max_examples: usize,
+        aggregate_lines: bool,
+        parent_fingerprint: Option<u32>,
+    ) -> (u64, u64) {
+        let node = &self[id];
+        // casting to an uint32 here because snuba does not handle uint64 values
+        // well as it is converted to a float somewhere
+        // not changing to the 32 bit hash function here to preserve backwards
+        // compatibility with existing fingerprints that we can cast
+        let fingerprint = node.frame.fingerprint(None);
+        let stack_fingerprint = if generate_stack_fingerprints {
+            Some(node.frame.fingerprint(parent_fingerprint))
+        } else {
+            None
+        };
+        let key = stack_fingerprint.unwrap_or(fingerprint);
+        let credited_total_time_ns = total_times_ns.get(&key).copied().unwrap_or_default();
         let mut children_application_duration_ns: u64 = 0;
         let mut children_system_duration_ns: u64 = 0;
 
         // determine the amount of time spent in application vs system functions in the children
-        for child in &self.children {
+        for &child in self.children(id) {
             let stack_fingerprint = if generate_stack_fingerprints {
-                if filter_system_frames && !self.is_application {
+                if filter_system_frames && !node.is_application {
                     // if filter_system_frames is enabled and the current frame is a system frame,
                     // pass the closest application frame's fingerprint
                     parent_fingerprint
                 } else {
-                    Some(self.frame.fingerprint(parent_fingerprint))
+                    Some(node.frame.fingerprint(parent_fingerprint))
                 }
             } else {
                 parent_fingerprint
             };
-            let (application_duration_ns, system_duration_ns) = child.borrow().collect_functions(
-                results,
-                thread_id,
-                node_depth + 1,
-                min_depth,
-                filter_system_frames,
-                filter_non_leaf_functions,
-                generate_stack_fingerprints,
-                stack_fingerprint,
-            );
+            let (application_duration_ns, system_duration_ns) = self
+                .collect_functions_with_total_times(
+                    child,
+                    results,
+                    total_times_ns,
+                    thread_id,
+                    node_depth + 1,
+                    min_depth,
+                    filter_system_frames,
+                    filter_non_leaf_functions,
+                    generate_stack_fingerprints,
+                    max_examples,
+                    aggregate_lines,
+                    stack_fingerprint,
+                );
             children_application_duration_ns += application_duration_ns;
             children_system_duration_ns += system_duration_ns;
         }
@@ -167,19 +440,19 @@ impl Node {
         let mut application_duration_ns = children_application_duration_ns;
         // in the event that the time spent in application functions in the descendents exceed
         // the frame duration, we cap it at the frame duration
-        if application_duration_ns > self.duration_ns {
-            application_duration_ns = self.duration_ns
+        if application_duration_ns > node.duration_ns {
+            application_duration_ns = node.duration_ns
         }
 
         let mut self_time_ns: u64 = 0;
 
-        if node_depth >= min_depth && should_aggregate_frame(&self.frame) {
-            if self.is_application {
+        if node_depth >= min_depth && should_aggregate_frame(&node.frame) {
+            if node.is_application {
                 // cannot use `node.duration_ns - children_application_duration_ns > 0` in case it underflows
-                if self.duration_ns > children_application_duration_ns {
+                if node.duration_ns > children_application_duration_ns {
                     // application function's self time only looks at the time
                     // spent in application function in its descendents
-                    self_time_ns = self.duration_ns - children_application_duration_ns;
+                    self_time_ns = node.duration_ns - children_application_duration_ns;
 
                     // credit the self time of this application function
                     // to the total time spent in application functions
@@ -187,38 +460,49 @@ impl Node {
                 }
             } else {
                 // cannot use `node.duration_ns - children_application_duration_ns - children_system_duration_ns` in case it underflows
-                if self.duration_ns > children_application_duration_ns + children_system_duration_ns
+                if node.duration_ns > children_application_duration_ns + children_system_duration_ns
                 {
                     // system function's self time looks at all descendents of its descendents
-                    self_time_ns = self.duration_ns
+                    self_time_ns = node.duration_ns
                         - children_application_duration_ns
                         - children_system_duration_ns
                 }
             }
 
             if self_time_ns > 0 || !filter_non_leaf_functions {
-                // casting to an uint32 here because snuba does not handle uint64 values
-                // well as it is converted to a float somewhere
-                // not changing to the 32 bit hash function here to preserve backwards
-                // compatibility with existing fingerprints that we can cast
-                let fingerprint = self.frame.fingerprint(None);
-                let stack_fingerprint = if generate_stack_fingerprints {
-                    Some(self.frame.fingerprint(parent_fingerprint))
-                } else {
-                    None
+                // the part of the duration of the call not credited to the
+                // calls of the same function nested into it
+                let nested_total_time_ns =
+                    total_times_ns.get(&key).copied().unwrap_or_default() - credited_total_time_ns;
+                let total_time_ns = node.duration_ns.saturating_sub(nested_total_time_ns);
+                total_times_ns.insert(key, credited_total_time_ns + node.duration_ns);
+                let example = FunctionExample {
+                    thread_id: thread_id.to_string(),
+                    start_ns: node.start_ns,
+                    end_ns: node.end_ns,
+                    self_time_ns,
+                    ..Default::default()
                 };
 
-                results
-                    .entry(if generate_stack_fingerprints {
-                        stack_fingerprint.unwrap()
-                    } else {
-                        fingerprint
-                    })
+                let function = results
+                    .entry(key)
                     .and_modify(|function| {
                         function.self_times_ns.push(self_time_ns);
                         function.sum_self_time_ns += self_time_ns;
-                        function.total_times_ns.push(self.duration_ns);
-                        function.sample_count += self.sample_count;
+                        function.total_times_ns.push(node.duration_ns);
+                        function.sum_total_time_ns += total_time_ns;
+                        function.sample_count += node.sample_count;
+                        if let Err(i) = function
+                            .thread_ids
+                            .binary_search_by(|id| id.as_str().cmp(thread_id))
+                        {
+                            function.thread_ids.insert(i, thread_id.to_string());
+                        }
+                        function.add_example(example.clone(), max_examples);
+                        function.line = match (function.line, node.line) {
+                            (Some(line), Some(node_line)) => Some(line.min(node_line)),
+                            (line, node_line) => line.or(node_line),
+                        };
                         if self_time_ns > function.max_duration {
                             function.max_duration = self_time_ns;
                             if thread_id != function.thread_id {
@@ -230,26 +514,41 @@ impl Node {
                         parent_fingerprint,
                         stack_fingerprint,
                         fingerprint,
-                        function: self
+                        function: node
                             .frame
                             .function
                             .as_ref()
                             .map(|f| f.into())
                             .unwrap_or_default(),
-                        package: self.frame.module_or_package(),
-                        in_app: self.is_application,
+                        package: node.frame.module_or_package(),
+                        filename: node.frame.filename().unwrap_or_default().to_string(),
+                        line: node.line,
+                        in_app: node.is_application,
                         self_times_ns: vec![self_time_ns],
                         sum_self_time_ns: self_time_ns,
-                        total_times_ns: vec![self.duration_ns],
-                        sample_count: self.sample_count,
+                        total_times_ns: vec![node.duration_ns],
+                        sum_total_time_ns: total_time_ns,
+                        sample_count: node.sample_count,
                         thread_id: thread_id.to_string(),
+                        thread_name: None,
+                        thread_ids: vec![thread_id.to_string()],
+                        main_thread: false,
                         max_duration: self_time_ns,
                         depth: if generate_stack_fingerprints {
                             Some(node_depth)
                         } else {
                             None
                         },
+                        examples: if max_examples > 0 {
+                            vec![example]
+                        } else {
+                            vec![]
+                        },
+                        line_self_times_ns: vec![],
                     });
+                if aggregate_lines {
+                    function.add_line_self_time(node.line, self_time_ns);
+                }
             }
         } // end node_depth >= min_depth && should_aggregate_frame
 
@@ -257,27 +556,247 @@ impl Node {
         // time spent in system functions by this function and all of its descendents
         (
             application_duration_ns,
-            self.duration_ns - application_duration_ns,
+            node.duration_ns - application_duration_ns,
         )
     }
 }
 
 #[pyclass]
-#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+#[non_exhaustive]
 pub struct CallTreeFunction {
     pub parent_fingerprint: Option<u32>,
     pub stack_fingerprint: Option<u32>,
     pub fingerprint: u32,
     pub function: String,
     pub package: String,
+    /// Name of the file of the function, without its directory.
+    pub filename: String,
+    /// Lowest line of the function sampled, the closest to its definition.
+    pub line: Option<u32>,
     pub in_app: bool,
     pub self_times_ns: Vec<u64>,
     pub total_times_ns: Vec<u64>,
     pub sum_self_time_ns: u64,
+    /// Sum of the durations of the calls of the function, calls nested in
+    /// another call of the function being counted once.
+    pub sum_total_time_ns: u64,
     pub sample_count: u64,
     pub thread_id: String,
+    /// Name of the thread of `thread_id`, if known.
+    pub thread_name: Option<String>,
+    pub thread_ids: Vec<String>,
+    /// Whether the function was sampled on the main thread.
+    pub main_thread: bool,
     pub max_duration: u64,
     pub depth: Option<u16>,
+    pub examples: Vec<FunctionExample>,
+    /// Self time by line, sorted by line, when aggregated per line.
+    pub line_self_times_ns: Vec<(u32, u64)>,
+}
+
+impl CallTreeFunction {
+    /// Records an example, only keeping the `max_examples` ones with the
+    /// longest self time.
+    fn add_example(&mut self, example: FunctionExample, max_examples: usize) {
+        let i = self
+            .examples
+            .partition_point(|e| e.self_time_ns >= example.self_time_ns);
+        if i < max_examples {
+            self.examples.insert(i, example);
+            self.examples.truncate(max_examples);
+        }
+    }
+
+    /// Merges the calls of the same function collected from another chunk,
+    /// whose threads and examples source were already set.
+    pub(crate) fn merge(&mut self, other: CallTreeFunction, max_examples: usize) {
+        self.self_times_ns.extend(other.self_times_ns);
+        self.total_times_ns.extend(other.total_times_ns);
+        self.sum_self_time_ns += other.sum_self_time_ns;
+        self.sum_total_time_ns += other.sum_total_time_ns;
+        self.sample_count += other.sample_count;
+        for thread_id in other.thread_ids {
+            if let Err(i) = self.thread_ids.binary_search(&thread_id) {
+                self.thread_ids.insert(i, thread_id);
+            }
+        }
+        self.main_thread |= other.main_thread;
+        for example in other.examples {
+            self.add_example(example, max_examples);
+        }
+        self.line = match (self.line, other.line) {
+            (Some(line), Some(other_line)) => Some(line.min(other_line)),
+            (line, other_line) => line.or(other_line),
+        };
+        for (line, self_time_ns) in other.line_self_times_ns {
+            self.add_line_self_time(Some(line), self_time_ns);
+        }
+        if other.max_duration > self.max_duration {
+            self.max_duration = other.max_duration;
+            self.thread_id = other.thread_id;
+            self.thread_name = other.thread_name;
+        }
+    }
+
+    /// Credits self time to the line of a call, if known.
+    fn add_line_self_time(&mut self, line: Option<u32>, self_time_ns: u64) {
+        let Some(line) = line.filter(|_| self_time_ns > 0) else {
+            return;
+        };
+        match self
+            .line_self_times_ns
+            .binary_search_by_key(&line, |&(line, _)| line)
+        {
+            Ok(i) => self.line_self_times_ns[i].1 += self_time_ns,
+            Err(i) => self.line_self_times_ns.insert(i, (line, self_time_ns)),
+        }
+    }
+
+    /// Only keeps the `max_lines` lines with the most self time.
+    pub(crate) fn truncate_lines(&mut self, max_lines: usize) {
+        if self.line_self_times_ns.len() <= max_lines {
+            return;
+        }
+        self.line_self_times_ns
+            .sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        self.line_self_times_ns.truncate(max_lines);
+        self.line_self_times_ns.sort_unstable();
+    }
+
+    /// Returns the self time at the given percentile (in `[0, 1]`), using the
+    /// nearest-rank method over every recorded self time.
+    pub fn self_time_percentile_ns(&self, percentile: f64) -> u64 {
+        if self.self_times_ns.is_empty() {
+            return 0;
+        }
+        let mut self_times_ns = self.self_times_ns.clone();
+        self_times_ns.sort_unstable();
+        let rank = (percentile.clamp(0.0, 1.0) * self_times_ns.len() as f64).ceil() as usize;
+        self_times_ns[rank.saturating_sub(1)]
+    }
+
+    /// Sets the name of the thread of the function and whether it was
+    /// sampled on the main thread, once every call was collected.
+    pub(crate) fn set_threads(
+        &mut self,
+        thread_names: &HashMap<String, String>,
+        main_thread_id: Option<&str>,
+    ) {
+        self.thread_name = thread_names.get(&self.thread_id).cloned();
+        self.main_thread = main_thread_id.is_some_and(|main_thread_id| {
+            self.thread_ids
+                .binary_search_by(|id| id.as_str().cmp(main_thread_id))
+                .is_ok()
+        });
+    }
+
+    /// Sets the ID of the profile (or chunk) the examples were taken from,
+    /// and their UTC timestamps using `absolute_timestamp_ns`.
+    pub(crate) fn set_examples_source(
+        &mut self,
+        profile_id: Option<&str>,
+        chunk_id: Option<&str>,
+        absolute_timestamp_ns: impl Fn(u64) -> u64,
+    ) {
+        for example in &mut self.examples {
+            example.profile_id = profile_id.map(str::to_string);
+            example.chunk_id = chunk_id.map(str::to_string);
+            example.start_timestamp_ns = absolute_timestamp_ns(example.start_ns);
+            example.end_timestamp_ns = absolute_timestamp_ns(example.end_ns);
+        }
+    }
+}
+
+/// An example of a function call, pointing to the sample where it was found.
+#[pyclass]
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct FunctionExample {
+    pub profile_id: Option<String>,
+    pub chunk_id: Option<String>,
+    pub thread_id: String,
+    pub start_ns: u64,
+    pub end_ns: u64,
+    pub start_timestamp_ns: u64,
+    pub end_timestamp_ns: u64,
+    pub self_time_ns: u64,
+}
+
+#[pymethods]
+impl FunctionExample {
+    /// Returns the ID of the transaction profile the example was found in.
+    ///
+    /// Returns:
+    ///     str
+    ///         The profile ID, or None if the example comes from a profile chunk.
+    pub fn get_profile_id(&self) -> Option<&str> {
+        self.profile_id.as_deref()
+    }
+
+    /// Returns the ID of the profile chunk the example was found in.
+    ///
+    /// Returns:
+    ///     str
+    ///         The chunk ID, or None if the example comes from a transaction profile.
+    pub fn get_chunk_id(&self) -> Option<&str> {
+        self.chunk_id.as_deref()
+    }
+
+    /// Returns the thread ID.
+    ///
+    /// Returns:
+    ///     str
+    ///         The ID of the thread the function was sampled on.
+    pub fn get_thread_id(&self) -> &str {
+        &self.thread_id
+    }
+
+    /// Returns the start timestamp of the function call in nanoseconds.
+    ///
+    /// Returns:
+    ///     int
+    ///         The start timestamp, as found in the call tree.
+    pub fn get_start_ns(&self) -> u64 {
+        self.start_ns
+    }
+
+    /// Returns the end timestamp of the function call in nanoseconds.
+    ///
+    /// Returns:
+    ///     int
+    ///         The end timestamp, as found in the call tree.
+    pub fn get_end_ns(&self) -> u64 {
+        self.end_ns
+    }
+
+    /// Returns the start timestamp of the function call, as a UTC Unix
+    /// timestamp in nanoseconds.
+    ///
+    /// Returns:
+    ///     int
+    ///         The start timestamp, to line the call up with logs and spans.
+    pub fn get_start_timestamp_ns(&self) -> u64 {
+        self.start_timestamp_ns
+    }
+
+    /// Returns the end timestamp of the function call, as a UTC Unix
+    /// timestamp in nanoseconds.
+    ///
+    /// Returns:
+    ///     int
+    ///         The end timestamp, to line the call up with logs and spans.
+    pub fn get_end_timestamp_ns(&self) -> u64 {
+        self.end_timestamp_ns
+    }
+
+    /// Returns the self time of the function call in nanoseconds.
+    ///
+    /// Returns:
+    ///     int
+    ///         The self time in nanoseconds.
+    pub fn get_self_time_ns(&self) -> u64 {
+        self.self_time_ns
+    }
 }
 
 #[pymethods]
@@ -331,6 +850,25 @@ impl CallTreeFunction {
         &self.package
     }
 
+    /// Returns the name of the file of the function.
+    ///
+    /// Returns:
+    ///     str
+    ///         The file name, without its directory, or an empty string if unknown.
+    pub fn get_filename(&self) -> &str {
+        &self.filename
+    }
+
+    /// Returns the line of the function.
+    ///
+    /// Returns:
+    ///     int
+    ///         The lowest line of the function found in the samples, the closest
+    ///         to its definition, or None if unknown.
+    pub fn get_line(&self) -> Option<u32> {
+        self.line
+    }
+
     /// Returns whether the function is in an app or system one.
     ///
     /// Returns:
@@ -340,7 +878,9 @@ impl CallTreeFunction {
         self.in_app
     }
 
-    /// Returns the self times in nanoseconds.
+    /// Returns the self times in nanoseconds, one per call of the function
+    /// aggregated, in no particular order: the distribution of its durations
+    /// in the chunk, which the p75, p95 and p99 are computed from.
     ///
     /// Returns:
     ///     list[int]
@@ -358,6 +898,47 @@ impl CallTreeFunction {
         self.sum_self_time_ns
     }
 
+    /// Returns the sum of total (inclusive) times in nanoseconds, the time
+    /// spent in the function and its callees, e.g. to compute the share of
+    /// the profile spent in the function.
+    ///
+    /// Calls nested in another call of the function (recursion) are covered
+    /// by the outer call, so they aren't counted twice.
+    ///
+    /// Returns:
+    ///     int
+    ///         The sum of total times in nanoseconds.
+    pub fn get_sum_total_time_ns(&self) -> u64 {
+        self.sum_total_time_ns
+    }
+
+    /// Returns the 75th percentile of self times in nanoseconds.
+    ///
+    /// Returns:
+    ///     int
+    ///         The p75 self time in nanoseconds.
+    pub fn get_p75_self_time_ns(&self) -> u64 {
+        self.self_time_percentile_ns(0.75)
+    }
+
+    /// Returns the 95th percentile of self times in nanoseconds.
+    ///
+    /// Returns:
+    ///     int
+    ///         The p95 self time in nanoseconds.
+    pub fn get_p95_self_time_ns(&self) -> u64 {
+        self.self_time_percentile_ns(0.95)
+    }
+
+    /// Returns the 99th percentile of self times in nanoseconds.
+    ///
+    /// Returns:
+    ///     int
+    ///         The p99 self time in nanoseconds.
+    pub fn get_p99_self_time_ns(&self) -> u64 {
+        self.self_time_percentile_ns(0.99)
+    }
+
     /// Returns the total times in nanoseconds.
     ///
     /// Returns:
@@ -385,6 +966,58 @@ impl CallTreeFunction {
         &self.thread_id
     }
 
+    /// Returns the IDs of the threads the function was sampled on.
+    ///
+    /// Returns:
+    ///     list[str]
+    ///         The sorted thread IDs.
+    pub fn get_thread_ids(&self) -> Vec<String> {
+        self.thread_ids.clone()
+    }
+
+    /// Returns the name of the thread of :meth:`get_thread_id`.
+    ///
+    /// Returns:
+    ///     str
+    ///         The thread name, or None if the thread is unnamed.
+    pub fn get_thread_name(&self) -> Option<&str> {
+        self.thread_name.as_deref()
+    }
+
+    /// Returns whether the function was sampled on the main thread, to
+    /// filter main thread functions without joining on thread metadata.
+    ///
+    /// Returns:
+    ///     bool
+    ///         True if the main thread is among :meth:`get_thread_ids`.
+    pub fn get_main_thread(&self) -> bool {
+        self.main_thread
+    }
+
+    /// Returns examples of the function calls with the longest self time.
+    ///
+    /// Returns:
+    ///     list[FunctionExample]
+    ///         Up to `max_examples` examples, sorted by descending self time.
+    pub fn get_examples(&self) -> Vec<FunctionExample> {
+        self.examples.clone()
+    }
+
+    /// Returns the self time of the function by line, for line-level
+    /// heatmaps.
+    ///
+    /// Each call is credited to the line of its frame. Lines are only
+    /// aggregated if `max_lines_per_function` was set when extracting the
+    /// metrics.
+    ///
+    /// Returns:
+    ///     list[tuple[int, int]]
+    ///         Up to `max_lines_per_function` ``(line, self_time_ns)`` pairs, the
+    ///         lines with the most self time, sorted by line.
+    pub fn get_line_self_times_ns(&self) -> Vec<(u32, u64)> {
+        self.line_self_times_ns.clone()
+    }
+
     /// Returns the maximum duration in nanoseconds.
     ///
     /// Returns:
@@ -402,6 +1035,285 @@ impl CallTreeFunction {
     pub fn get_depth(&self) -> Option<u16> {
         self.depth
     }
+
+    /// Serializes the function to MessagePack, as a map keyed by field name.
+    ///
+    /// Returns:
+    ///     bytes
+    ///         The MessagePack encoding of the function.
+    ///
+    /// Raises:
+    ///     ValueError
+    ///         If the serialization fails due to invalid data.
+    ///
+    /// Example:
+    ///     >>> producer.produce("profiles-call-tree-functions", function.to_msgpack())
+    pub fn to_msgpack(&self) -> Result<Vec<u8>, PyErr> {
+        msgpack::to_vec(self)
+    }
+
+    /// Returns the row of the function in the ClickHouse functions table,
+    /// with the fields of the profile chunk it was extracted from.
+    ///
+    /// Args:
+    ///     chunk (:class:`ProfileChunk`): The profile chunk the function was extracted from.
+    ///
+    /// Returns:
+    ///     dict[str, Any]
+    ///         The row: ``project_id``, ``timestamp`` (the start of the chunk, a Unix
+    ///         timestamp in whole seconds), ``fingerprint``, ``name``, ``package``,
+    ///         ``is_application``, ``durations`` (the self times in nanoseconds),
+    ///         ``platform``, ``environment``, ``release`` and ``retention_days``.
+    ///
+    /// Raises:
+    ///     ValueError
+    ///         If the serialization fails due to invalid data.
+    ///
+    /// Example:
+    ///     >>> functions = chunk.extract_functions_metrics(min_depth=1, filter_system_frames=True)
+    ///     >>> rows = [function.to_row(chunk) for function in functions]
+    pub fn to_row<'py>(
+        &self,
+        py: Python<'py>,
+        chunk: &ProfileChunk,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let row = serde_json::to_string(&self.row(chunk.profile.as_ref()))
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+        py.import("json")?.call_method1("loads", (row,))
+    }
+}
+
+impl CallTreeFunction {
+    /// Returns the row of the function in the ClickHouse functions table.
+    pub fn row<'a>(&'a self, chunk: &'a dyn ChunkInterface) -> FunctionRow<'a> {
+        FunctionRow {
+            project_id: chunk.get_project_id(),
+            timestamp: chunk.start_timestamp() as u64,
+            fingerprint: self.fingerprint,
+            name: &self.function,
+            package: &self.package,
+            is_application: self.in_app,
+            durations: &self.self_times_ns,
+            platform: chunk.get_platform(),
+            environment: chunk.get_environment(),
+            release: chunk.get_release(),
+            retention_days: chunk.get_retention_days(),
+        }
+    }
+}
+
+/// A row of the ClickHouse functions table, see [`CallTreeFunction::row`].
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct FunctionRow<'a> {
+    pub project_id: u64,
+    pub timestamp: u64,
+    pub fingerprint: u32,
+    pub name: &'a str,
+    pub package: &'a str,
+    pub is_application: bool,
+    pub durations: &'a [u64],
+    pub platform: String,
+    pub environment: Option<&'a str>,
+    pub release: Option<&'a str>,
+    pub retention_days: i32,
+}
+
+/// Options of the extraction of function metrics from the call trees of
+/// profiles and chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FunctionsMetricsOptions {
+    /// Ignores the nodes shallower than this depth in the call trees, e.g.
+    /// 1 to ignore the root frames.
+    pub min_depth: u16,
+    /// Drops the system functions (e.g. of the standard library).
+    pub filter_system_frames: bool,
+    /// Keeps the top functions only, all of them if `None`.
+    pub max_unique_functions: Option<usize>,
+    /// Drops the functions without self time (non-leaf functions).
+    pub filter_non_leaf_functions: bool,
+    /// Fingerprints the stack up to each function too, functions being
+    /// aggregated by stack rather than by function.
+    pub generate_stack_fingerprints: bool,
+    /// Number of examples, the calls with the longest self time, recorded
+    /// for each function.
+    pub max_examples: usize,
+    /// Only aggregates the functions sampled on the active thread (the main
+    /// thread of chunks, the active thread of the transaction of profiles).
+    pub active_thread_only: bool,
+    /// Number of lines self time is aggregated for, per function, the lines
+    /// with the most self time being kept. 0 doesn't aggregate per line.
+    pub max_lines_per_function: usize,
+    /// Collapses recursion when building the call trees of chunks, see
+    /// [`CallTreeOptions::collapse_recursion`]. The call trees of profiles
+    /// are always built in full.
+    pub collapse_recursion: bool,
+    /// Skips the idle threads when building the call trees of chunks, see
+    /// [`CallTreeOptions::skip_idle_threads`].
+    pub skip_idle_threads: bool,
+    /// Drops the functions sampled fewer times, to reduce the amount of
+    /// data. Lower it to keep the short functions of platforms sampled at a
+    /// low frequency (e.g. PHP at 10Hz).
+    pub min_sample_count: u64,
+    /// What the functions are ranked by before keeping the top ones, ties
+    /// being broken by fingerprint.
+    pub sort_key: FunctionsSortKey,
+}
+
+impl Default for FunctionsMetricsOptions {
+    fn default() -> Self {
+        FunctionsMetricsOptions {
+            min_depth: 0,
+            filter_system_frames: false,
+            max_unique_functions: None,
+            filter_non_leaf_functions: true,
+            generate_stack_fingerprints: false,
+            max_examples: 0,
+            active_thread_only: false,
+            max_lines_per_function: 0,
+            collapse_recursion: false,
+            skip_idle_threads: false,
+            min_sample_count: 2,
+            sort_key: FunctionsSortKey::SelfTime,
+        }
+    }
+}
+
+impl FunctionsMetricsOptions {
+    pub fn with_min_depth(mut self, min_depth: u16) -> Self {
+        self.min_depth = min_depth;
+        self
+    }
+
+    pub fn with_filter_system_frames(mut self, filter_system_frames: bool) -> Self {
+        self.filter_system_frames = filter_system_frames;
+        self
+    }
+
+    pub fn with_max_unique_functions(mut self, max_unique_functions: usize) -> Self {
+        self.max_unique_functions = Some(max_unique_functions);
+        self
+    }
+
+    pub fn with_filter_non_leaf_functions(mut self, filter_non_leaf_functions: bool) -> Self {
+        self.filter_non_leaf_functions = filter_non_leaf_functions;
+        self
+    }
+
+    pub fn with_generate_stack_fingerprints(mut self, generate_stack_fingerprints: bool) -> Self {
+        self.generate_stack_fingerprints = generate_stack_fingerprints;
+        self
+    }
+
+    pub fn with_max_examples(mut self, max_examples: usize) -> Self {
+        self.max_examples = max_examples;
+        self
+    }
+
+    pub fn with_active_thread_only(mut self, active_thread_only: bool) -> Self {
+        self.active_thread_only = active_thread_only;
+        self
+    }
+
+    pub fn with_max_lines_per_function(mut self, max_lines_per_function: usize) -> Self {
+        self.max_lines_per_function = max_lines_per_function;
+        self
+    }
+
+    pub fn with_collapse_recursion(mut self, collapse_recursion: bool) -> Self {
+        self.collapse_recursion = collapse_recursion;
+        self
+    }
+
+    pub fn with_skip_idle_threads(mut self, skip_idle_threads: bool) -> Self {
+        self.skip_idle_threads = skip_idle_threads;
+        self
+    }
+
+    pub fn with_min_sample_count(mut self, min_sample_count: u64) -> Self {
+        self.min_sample_count = min_sample_count;
+        self
+    }
+
+    pub fn with_sort_key(mut self, sort_key: FunctionsSortKey) -> Self {
+        self.sort_key = sort_key;
+        self
+    }
+
+    /// Returns the options the call trees of chunks are built with.
+    pub(crate) fn call_tree_options(&self) -> CallTreeOptions {
+        CallTreeOptions::default()
+            .with_collapse_recursion(self.collapse_recursion)
+            .with_skip_idle_threads(self.skip_idle_threads)
+    }
+}
+
+/// What function metrics are ranked by, in decreasing order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FunctionsSortKey {
+    #[default]
+    SelfTime,
+    SampleCount,
+    TotalTime,
+}
+
+impl FunctionsSortKey {
+    pub(crate) fn from_name(name: &str) -> Result<Self, PyErr> {
+        match name {
+            "self_time" => Ok(FunctionsSortKey::SelfTime),
+            "sample_count" => Ok(FunctionsSortKey::SampleCount),
+            "total_time" => Ok(FunctionsSortKey::TotalTime),
+            _ => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "unknown sort key {name:?}, expected \"self_time\", \"sample_count\" or \"total_time\""
+            ))),
+        }
+    }
+
+    fn value(self, function: &CallTreeFunction) -> u64 {
+        match self {
+            FunctionsSortKey::SelfTime => function.sum_self_time_ns,
+            FunctionsSortKey::SampleCount => function.sample_count,
+            FunctionsSortKey::TotalTime => function.sum_total_time_ns,
+        }
+    }
+}
+
+/// Sorts functions in decreasing order of the sort key, ties being broken by
+/// increasing fingerprint (then stack fingerprint), so the order doesn't
+/// depend on the order functions were collected in.
+pub(crate) fn sort_functions(functions: &mut [CallTreeFunction], sort_key: FunctionsSortKey) {
+    functions.sort_by(|a, b| {
+        sort_key
+            .value(b)
+            .cmp(&sort_key.value(a))
+            .then_with(|| a.fingerprint.cmp(&b.fingerprint))
+            .then_with(|| a.stack_fingerprint.cmp(&b.stack_fingerprint))
+    });
+}
+
+/// Drops the functions sampled fewer than `min_sample_count` times, to reduce
+/// the amount of data, and the system functions if `filter_system_frames` is
+/// set. Returns the functions kept, along with the number of functions
+/// dropped for having too few samples.
+pub(crate) fn filter_functions(
+    functions: HashMap<u32, CallTreeFunction>,
+    filter_system_frames: bool,
+    min_sample_count: u64,
+) -> (Vec<CallTreeFunction>, u64) {
+    let mut functions_list: Vec<CallTreeFunction> = Vec::with_capacity(functions.len());
+    let mut filtered_functions = 0;
+    for (_fingerprint, function) in functions {
+        if filter_system_frames && !function.in_app {
+            continue;
+        }
+        if function.sample_count < min_sample_count {
+            filtered_functions += 1;
+            continue;
+        }
+        functions_list.push(function);
+    }
+    (functions_list, filtered_functions)
 }
 
 fn should_aggregate_frame(frame: &Frame) -> bool {
@@ -422,33 +1334,10 @@ fn should_aggregate_frame(frame: &Frame) -> bool {
     }
 
     if let Some(platform) = frame.platform.as_ref() {
-        if OBFUSCATION_SUPPORTED_PLATFORMS.contains(platform) {
-            /*
-                There are 4 possible deobfuscation statuses
-                1. deobfuscated	- The frame was successfully deobfuscated.
-                2. partial			- The frame was only partially deobfuscated.
-                                                    (likely just the class name and not the method name)
-                3. missing			- The frame could not be deobfuscated, not found in the mapping file.
-                                                    (likely to be a system library that should not be obfuscated)
-                4. <no status>	- The frame did not go through deobfuscation. No mapping file specified.
-
-                Only the `partial` status should not be aggregated because only having a deobfuscated
-                class names makes grouping ineffective.
-            */
-            if frame
-                .data
-                .as_ref()
-                .and_then(|data| data.deobfuscation_status.as_deref())
-                == Some("partial")
-            {
-                return false;
-            }
-
-            // obfuscated package names often don't contain a dot (`.`)
-            let frame_package = frame.module_or_package();
-            if !frame_package.contains('.') {
-                return false;
-            }
+        if OBFUSCATION_SUPPORTED_PLATFORMS.contains(platform)
+            && (is_obfuscated_frame(frame) || deobfuscation_status(frame) == Some("missing"))
+        {
+            return false;
         }
 
         if SYMBOLICATION_SUPPORTED_PLATFORMS.contains(platform) {
@@ -460,6 +1349,51 @@ fn should_aggregate_frame(frame: &Frame) -> bool {
     true
 }
 
+/// Returns whether a frame of a platform supporting deobfuscation is still
+/// (at least partially) obfuscated.
+pub(crate) fn is_obfuscated_frame(frame: &Frame) -> bool {
+    /*
+        There are 4 possible deobfuscation statuses
+        1. deobfuscated	- The frame was successfully deobfuscated.
+        2. partial			- The frame was only partially deobfuscated.
+                                            (likely just the class name and not the method name)
+        3. missing			- The frame could not be deobfuscated, not found in the mapping file.
+                                            (likely to be a system library that should not be obfuscated)
+        4. <no status>	- The frame did not go through deobfuscation. No mapping file specified.
+
+        Only the `partial` status means the frame is still obfuscated, only having a
+        deobfuscated class names makes grouping ineffective. `missing` frames aren't
+        aggregated either, as their names are too noisy, but aren't obfuscated.
+    */
+    if deobfuscation_status(frame) == Some("partial") {
+        return true;
+    }
+
+    // obfuscated package names often don't contain a dot (`.`)
+    !frame.module_or_package().contains('.')
+}
+
+fn deobfuscation_status(frame: &Frame) -> Option<&str> {
+    frame
+        .data
+        .as_ref()
+        .and_then(|data| data.deobfuscation_status.as_deref())
+}
+
+/// Returns the ratio of the frames of a chunk which went through
+/// deobfuscation that were found in the mapping file, or None if none went
+/// through deobfuscation (no mapping file specified).
+pub fn deobfuscation_ratio(chunk: &dyn ChunkInterface) -> Option<f64> {
+    let (deobfuscated, total) = chunk
+        .frames()
+        .iter()
+        .filter_map(|frame| deobfuscation_status(frame))
+        .fold((0usize, 0usize), |(deobfuscated, total), status| {
+            (deobfuscated + usize::from(status != "missing"), total + 1)
+        });
+    (total > 0).then(|| deobfuscated as f64 / total as f64)
+}
+
 fn is_symbolicated_frame(frame: &Frame) -> bool {
     if let Some(platform) = frame.platform.as_ref() {
         if platform.as_str() == "javascript" && frame.is_react_native {
@@ -499,11 +1433,15 @@ pub(crate) static SYMBOLICATION_SUPPORTED_PLATFORMS: Lazy<HashSet<String>> = Laz
 mod tests {
 
     use pretty_assertions::assert_eq;
-    use std::{cell::RefCell, collections::HashMap, rc::Rc};
+    use std::collections::HashMap;
 
     use crate::{
         frame::{Data, Frame},
-        nodetree::{is_symbolicated_frame, CallTreeFunction, Node},
+        nodetree::{
+            deobfuscation_ratio, is_symbolicated_frame, should_aggregate_frame, CallTree,
+            CallTreeFunction, FunctionExample, FunctionRow, NestedNode, Node,
+        },
+        sample::v2::SampleChunkBuilder,
     };
 
     #[test]
@@ -578,7 +1516,7 @@ mod tests {
     fn test_node_collect_functions() {
         struct TestStruct {
             name: String,
-            node: Node,
+            node: NestedNode,
             want: HashMap<u32, CallTreeFunction>,
         }
 
@@ -591,20 +1529,25 @@ mod tests {
         let test_cases: Vec<TestStruct> = vec![
             TestStruct {
                 name: "single application node".to_string(),
-                node: Node {
-                    duration_ns: 10,
-                    is_application: true,
-                    frame: Frame {
-                        platform: Some("python".to_string()),
-                        function: Some("foo".to_string()),
-                        package: Some("foo".to_string()),
+                node: NestedNode(
+                    Node {
+                        duration_ns: 10,
+                        is_application: true,
+                        frame: Frame {
+                            platform: Some("python".to_string()),
+                            function: Some("foo".to_string()),
+                            package: Some("foo".to_string()),
+                            ..Default::default()
+                        }
+                        .into(),
                         ..Default::default()
                     },
-                    ..Default::default()
-                },
+                    vec![],
+                ),
                 want: [(
                     FINGERPRINT_FOO,
                     CallTreeFunction {
+                        thread_ids: vec!["".to_string()],
                         fingerprint: FINGERPRINT_FOO,
                         in_app: true,
                         function: "foo".to_string(),
@@ -612,6 +1555,7 @@ mod tests {
                         self_times_ns: vec![10],
                         sum_self_time_ns: 10,
                         total_times_ns: vec![10],
+                        sum_total_time_ns: 10,
                         max_duration: 10,
                         ..Default::default()
                     },
@@ -622,20 +1566,25 @@ mod tests {
             }, // end first test case
             TestStruct {
                 name: "single system node".to_string(),
-                node: Node {
-                    duration_ns: 10,
-                    is_application: false,
-                    frame: Frame {
-                        platform: Some("python".to_string()),
-                        function: Some("foo".to_string()),
-                        package: Some("foo".to_string()),
+                node: NestedNode(
+                    Node {
+                        duration_ns: 10,
+                        is_application: false,
+                        frame: Frame {
+                            platform: Some("python".to_string()),
+                            function: Some("foo".to_string()),
+                            package: Some("foo".to_string()),
+                            ..Default::default()
+                        }
+                        .into(),
                         ..Default::default()
                     },
-                    ..Default::default()
-                },
+                    vec![],
+                ),
                 want: [(
                     FINGERPRINT_FOO,
                     CallTreeFunction {
+                        thread_ids: vec!["".to_string()],
                         fingerprint: FINGERPRINT_FOO,
                         in_app: false,
                         function: "foo".to_string(),
@@ -643,6 +1592,7 @@ mod tests {
                         self_times_ns: vec![10],
                         sum_self_time_ns: 10,
                         total_times_ns: vec![10],
+                        sum_total_time_ns: 10,
                         max_duration: 10,
                         ..Default::default()
                     },
@@ -653,32 +1603,40 @@ mod tests {
             }, // end second test case
             TestStruct {
                 name: "non leaf node with non zero self time".to_string(),
-                node: Node {
-                    duration_ns: 20,
-                    is_application: true,
-                    frame: Frame {
-                        platform: Some("python".to_string()),
-                        function: Some("foo".to_string()),
-                        package: Some("foo".to_string()),
-                        ..Default::default()
-                    },
-                    children: vec![Rc::new(RefCell::new(Node {
-                        duration_ns: 10,
+                node: NestedNode(
+                    Node {
+                        duration_ns: 20,
                         is_application: true,
                         frame: Frame {
                             platform: Some("python".to_string()),
-                            function: Some("bar".to_string()),
-                            package: Some("bar".to_string()),
+                            function: Some("foo".to_string()),
+                            package: Some("foo".to_string()),
                             ..Default::default()
-                        },
+                        }
+                        .into(),
                         ..Default::default()
-                    }))],
-                    ..Default::default()
-                },
+                    },
+                    vec![NestedNode(
+                        Node {
+                            duration_ns: 10,
+                            is_application: true,
+                            frame: Frame {
+                                platform: Some("python".to_string()),
+                                function: Some("bar".to_string()),
+                                package: Some("bar".to_string()),
+                                ..Default::default()
+                            }
+                            .into(),
+                            ..Default::default()
+                        },
+                        vec![],
+                    )],
+                ),
                 want: [
                     (
                         FINGERPRINT_FOO,
                         CallTreeFunction {
+                            thread_ids: vec!["".to_string()],
                             fingerprint: FINGERPRINT_FOO,
                             in_app: true,
                             function: "foo".to_string(),
@@ -686,6 +1644,7 @@ mod tests {
                             self_times_ns: vec![10],
                             sum_self_time_ns: 10,
                             total_times_ns: vec![20],
+                            sum_total_time_ns: 20,
                             max_duration: 10,
                             ..Default::default()
                         },
@@ -693,6 +1652,7 @@ mod tests {
                     (
                         FINGERPRINT_BAR,
                         CallTreeFunction {
+                            thread_ids: vec!["".to_string()],
                             fingerprint: FINGERPRINT_BAR,
                             in_app: true,
                             function: "bar".to_string(),
@@ -700,6 +1660,7 @@ mod tests {
                             self_times_ns: vec![10],
                             sum_self_time_ns: 10,
                             total_times_ns: vec![10],
+                            sum_total_time_ns: 10,
                             max_duration: 10,
                             ..Default::default()
                         },
@@ -711,54 +1672,68 @@ mod tests {
             }, // end third test case
             TestStruct {
                 name: "application node wrapping system nodes of same duration".to_string(),
-                node: Node {
-                    duration_ns: 10,
-                    is_application: true,
-                    frame: Frame {
-                        platform: Some("python".to_string()),
-                        function: Some("main".to_string()),
-                        package: Some("main".to_string()),
-                        ..Default::default()
-                    },
-                    children: vec![Rc::new(RefCell::new(Node {
+                node: NestedNode(
+                    Node {
                         duration_ns: 10,
                         is_application: true,
                         frame: Frame {
                             platform: Some("python".to_string()),
-                            function: Some("foo".to_string()),
-                            package: Some("foo".to_string()),
+                            function: Some("main".to_string()),
+                            package: Some("main".to_string()),
                             ..Default::default()
-                        },
-                        children: vec![Rc::new(RefCell::new(Node {
+                        }
+                        .into(),
+                        ..Default::default()
+                    },
+                    vec![NestedNode(
+                        Node {
                             duration_ns: 10,
-                            is_application: false,
+                            is_application: true,
                             frame: Frame {
                                 platform: Some("python".to_string()),
-                                function: Some("bar".to_string()),
-                                package: Some("bar".to_string()),
+                                function: Some("foo".to_string()),
+                                package: Some("foo".to_string()),
                                 ..Default::default()
-                            },
-                            children: vec![Rc::new(RefCell::new(Node {
+                            }
+                            .into(),
+                            ..Default::default()
+                        },
+                        vec![NestedNode(
+                            Node {
                                 duration_ns: 10,
                                 is_application: false,
                                 frame: Frame {
                                     platform: Some("python".to_string()),
-                                    function: Some("baz".to_string()),
-                                    package: Some("baz".to_string()),
+                                    function: Some("bar".to_string()),
+                                    package: Some("bar".to_string()),
                                     ..Default::default()
-                                },
+                                }
+                                .into(),
                                 ..Default::default()
-                            }))],
-                            ..Default::default()
-                        }))],
-                        ..Default::default()
-                    }))],
-                    ..Default::default()
-                },
+                            },
+                            vec![NestedNode(
+                                Node {
+                                    duration_ns: 10,
+                                    is_application: false,
+                                    frame: Frame {
+                                        platform: Some("python".to_string()),
+                                        function: Some("baz".to_string()),
+                                        package: Some("baz".to_string()),
+                                        ..Default::default()
+                                    }
+                                    .into(),
+                                    ..Default::default()
+                                },
+                                vec![],
+                            )],
+                        )],
+                    )],
+                ),
                 want: [
                     (
                         FINGERPRINT_FOO,
                         CallTreeFunction {
+                            thread_ids: vec!["".to_string()],
                             fingerprint: FINGERPRINT_FOO,
                             in_app: true,
                             function: "foo".to_string(),
@@ -766,6 +1741,7 @@ mod tests {
                             self_times_ns: vec![10],
                             sum_self_time_ns: 10,
                             total_times_ns: vec![10],
+                            sum_total_time_ns: 10,
                             max_duration: 10,
                             ..Default::default()
                         },
@@ -773,6 +1749,7 @@ mod tests {
                     (
                         FINGERPRINT_BAZ,
                         CallTreeFunction {
+                            thread_ids: vec!["".to_string()],
                             fingerprint: FINGERPRINT_BAZ,
                             in_app: false,
                             function: "baz".to_string(),
@@ -780,6 +1757,7 @@ mod tests {
                             self_times_ns: vec![10],
                             sum_self_time_ns: 10,
                             total_times_ns: vec![10],
+                            sum_total_time_ns: 10,
                             max_duration: 10,
                             ..Default::default()
                         },
@@ -791,100 +1769,128 @@ mod tests {
             }, // end fourth test case
             TestStruct {
                 name: "multitple occurrences of same functions".to_string(),
-                node: Node {
-                    duration_ns: 40,
-                    is_application: true,
-                    frame: Frame {
-                        platform: Some("python".to_string()),
-                        function: Some("main".to_string()),
-                        package: Some("main".to_string()),
+                node: NestedNode(
+                    Node {
+                        duration_ns: 40,
+                        is_application: true,
+                        frame: Frame {
+                            platform: Some("python".to_string()),
+                            function: Some("main".to_string()),
+                            package: Some("main".to_string()),
+                            ..Default::default()
+                        }
+                        .into(),
                         ..Default::default()
                     },
-                    children: vec![
-                        Rc::new(RefCell::new(Node {
-                            duration_ns: 10,
-                            is_application: true,
-                            frame: Frame {
-                                platform: Some("python".to_string()),
-                                function: Some("foo".to_string()),
-                                package: Some("foo".to_string()),
-                                ..Default::default()
-                            },
-                            children: vec![Rc::new(RefCell::new(Node {
+                    vec![
+                        NestedNode(
+                            Node {
                                 duration_ns: 10,
-                                is_application: false,
+                                is_application: true,
                                 frame: Frame {
                                     platform: Some("python".to_string()),
-                                    function: Some("bar".to_string()),
-                                    package: Some("bar".to_string()),
+                                    function: Some("foo".to_string()),
+                                    package: Some("foo".to_string()),
                                     ..Default::default()
-                                },
-                                children: vec![Rc::new(RefCell::new(Node {
+                                }
+                                .into(),
+                                ..Default::default()
+                            },
+                            vec![NestedNode(
+                                Node {
                                     duration_ns: 10,
                                     is_application: false,
                                     frame: Frame {
                                         platform: Some("python".to_string()),
-                                        function: Some("baz".to_string()),
-                                        package: Some("baz".to_string()),
+                                        function: Some("bar".to_string()),
+                                        package: Some("bar".to_string()),
+                                        ..Default::default()
+                                    }
+                                    .into(),
+                                    ..Default::default()
+                                },
+                                vec![NestedNode(
+                                    Node {
+                                        duration_ns: 10,
+                                        is_application: false,
+                                        frame: Frame {
+                                            platform: Some("python".to_string()),
+                                            function: Some("baz".to_string()),
+                                            package: Some("baz".to_string()),
+                                            ..Default::default()
+                                        }
+                                        .into(),
                                         ..Default::default()
                                     },
+                                    vec![],
+                                )],
+                            )],
+                        ),
+                        NestedNode(
+                            Node {
+                                duration_ns: 10,
+                                is_application: false,
+                                frame: Frame {
+                                    platform: Some("python".to_string()),
+                                    function: Some("qux".to_string()),
+                                    package: Some("qux".to_string()),
                                     ..Default::default()
-                                }))],
-                                ..Default::default()
-                            }))],
-                            ..Default::default()
-                        })),
-                        Rc::new(RefCell::new(Node {
-                            duration_ns: 10,
-                            is_application: false,
-                            frame: Frame {
-                                platform: Some("python".to_string()),
-                                function: Some("qux".to_string()),
-                                package: Some("qux".to_string()),
-                                ..Default::default()
-                            },
-                            ..Default::default()
-                        })),
-                        Rc::new(RefCell::new(Node {
-                            duration_ns: 20,
-                            is_application: true,
-                            frame: Frame {
-                                platform: Some("python".to_string()),
-                                function: Some("foo".to_string()),
-                                package: Some("foo".to_string()),
+                                }
+                                .into(),
                                 ..Default::default()
                             },
-                            children: vec![Rc::new(RefCell::new(Node {
+                            vec![],
+                        ),
+                        NestedNode(
+                            Node {
                                 duration_ns: 20,
-                                is_application: false,
+                                is_application: true,
                                 frame: Frame {
                                     platform: Some("python".to_string()),
-                                    function: Some("bar".to_string()),
-                                    package: Some("bar".to_string()),
+                                    function: Some("foo".to_string()),
+                                    package: Some("foo".to_string()),
                                     ..Default::default()
-                                },
-                                children: vec![Rc::new(RefCell::new(Node {
+                                }
+                                .into(),
+                                ..Default::default()
+                            },
+                            vec![NestedNode(
+                                Node {
                                     duration_ns: 20,
                                     is_application: false,
                                     frame: Frame {
                                         platform: Some("python".to_string()),
-                                        function: Some("baz".to_string()),
-                                        package: Some("baz".to_string()),
+                                        function: Some("bar".to_string()),
+                                        package: Some("bar".to_string()),
                                         ..Default::default()
-                                    },
+                                    }
+                                    .into(),
                                     ..Default::default()
-                                }))],
-                                ..Default::default()
-                            }))],
-                            ..Default::default()
-                        })),
+                                },
+                                vec![NestedNode(
+                                    Node {
+                                        duration_ns: 20,
+                                        is_application: false,
+                                        frame: Frame {
+                                            platform: Some("python".to_string()),
+                                            function: Some("baz".to_string()),
+                                            package: Some("baz".to_string()),
+                                            ..Default::default()
+                                        }
+                                        .into(),
+                                        ..Default::default()
+                                    },
+                                    vec![],
+                                )],
+                            )],
+                        ),
                     ],
-                    ..Default::default()
-                },
+                ),
                 want: [
                     (
                         FINGERPRINT_FOO,
                         CallTreeFunction {
+                            thread_ids: vec!["".to_string()],
                             fingerprint: FINGERPRINT_FOO,
                             in_app: true,
                             function: "foo".to_string(),
@@ -892,6 +1898,7 @@ mod tests {
                             self_times_ns: vec![10, 20],
                             sum_self_time_ns: 30,
                             total_times_ns: vec![10, 20],
+                            sum_total_time_ns: 30,
                             max_duration: 20,
                             ..Default::default()
                         },
@@ -899,6 +1906,7 @@ mod tests {
                     (
                         FINGERPRINT_BAZ,
                         CallTreeFunction {
+                            thread_ids: vec!["".to_string()],
                             fingerprint: FINGERPRINT_BAZ,
                             in_app: false,
                             function: "baz".to_string(),
@@ -906,6 +1914,7 @@ mod tests {
                             self_times_ns: vec![10, 20],
                             sum_self_time_ns: 30,
                             total_times_ns: vec![10, 20],
+                            sum_total_time_ns: 30,
                             max_duration: 20,
                             ..Default::default()
                         },
@@ -913,6 +1922,7 @@ mod tests {
                     (
                         FINGERPRINT_QUX,
                         CallTreeFunction {
+                            thread_ids: vec!["".to_string()],
                             fingerprint: FINGERPRINT_QUX,
                             in_app: false,
                             function: "qux".to_string(),
@@ -920,6 +1930,7 @@ mod tests {
                             self_times_ns: vec![10],
                             sum_self_time_ns: 10,
                             total_times_ns: vec![10],
+                            sum_total_time_ns: 10,
                             max_duration: 10,
                             ..Default::default()
                         },
@@ -927,6 +1938,7 @@ mod tests {
                     (
                         FINGERPRINT_MAIN,
                         CallTreeFunction {
+                            thread_ids: vec!["".to_string()],
                             fingerprint: FINGERPRINT_MAIN,
                             in_app: true,
                             function: "main".to_string(),
@@ -934,6 +1946,7 @@ mod tests {
                             self_times_ns: vec![10],
                             sum_self_time_ns: 10,
                             total_times_ns: vec![40],
+                            sum_total_time_ns: 40,
                             max_duration: 10,
                             ..Default::default()
                         },
@@ -945,56 +1958,68 @@ mod tests {
             }, // end fifth test case
             TestStruct {
                 name: "obfuscated android frames".to_string(),
-                node: Node {
-                    duration_ns: 20,
-                    is_application: true,
-                    frame: Frame {
-                        platform: Some("android".to_string()),
-                        function: Some("a.B()".to_string()),
-                        package: Some("a".to_string()),
-                        data: Some(Data {
-                            deobfuscation_status: Some("missing".to_string()),
+                node: NestedNode(
+                    Node {
+                        duration_ns: 20,
+                        is_application: true,
+                        frame: Frame {
+                            platform: Some("android".to_string()),
+                            function: Some("a.B()".to_string()),
+                            package: Some("a".to_string()),
+                            data: Some(Data {
+                                deobfuscation_status: Some("missing".to_string()),
+                                ..Default::default()
+                            }),
                             ..Default::default()
-                        }),
+                        }
+                        .into(),
                         ..Default::default()
                     },
-                    children: vec![
-                        Rc::new(RefCell::new(Node {
-                            duration_ns: 10,
-                            is_application: true,
-                            frame: Frame {
-                                platform: Some("android".to_string()),
-                                function: Some("com.example.Thing.doStuff()".to_string()),
-                                package: Some("com.example".to_string()),
-                                data: Some(Data {
-                                    deobfuscation_status: Some("deobfuscated".to_string()),
+                    vec![
+                        NestedNode(
+                            Node {
+                                duration_ns: 10,
+                                is_application: true,
+                                frame: Frame {
+                                    platform: Some("android".to_string()),
+                                    function: Some("com.example.Thing.doStuff()".to_string()),
+                                    package: Some("com.example".to_string()),
+                                    data: Some(Data {
+                                        deobfuscation_status: Some("deobfuscated".to_string()),
+                                        ..Default::default()
+                                    }),
                                     ..Default::default()
-                                }),
+                                }
+                                .into(),
                                 ..Default::default()
                             },
-                            ..Default::default()
-                        })),
-                        Rc::new(RefCell::new(Node {
-                            duration_ns: 10,
-                            is_application: true,
-                            frame: Frame {
-                                platform: Some("android".to_string()),
-                                function: Some("com.example.Thing.a()".to_string()),
-                                package: Some("com.example".to_string()),
-                                data: Some(Data {
-                                    deobfuscation_status: Some("partial".to_string()),
+                            vec![],
+                        ),
+                        NestedNode(
+                            Node {
+                                duration_ns: 10,
+                                is_application: true,
+                                frame: Frame {
+                                    platform: Some("android".to_string()),
+                                    function: Some("com.example.Thing.a()".to_string()),
+                                    package: Some("com.example".to_string()),
+                                    data: Some(Data {
+                                        deobfuscation_status: Some("partial".to_string()),
+                                        ..Default::default()
+                                    }),
                                     ..Default::default()
-                                }),
+                                }
+                                .into(),
                                 ..Default::default()
                             },
-                            ..Default::default()
-                        })),
+                            vec![],
+                        ),
                     ],
-                    ..Default::default()
-                },
+                ),
                 want: [(
                     1902388659,
                     CallTreeFunction {
+                        thread_ids: vec!["".to_string()],
                         fingerprint: 1902388659,
                         in_app: true,
                         function: "com.example.Thing.doStuff()".to_string(),
@@ -1002,6 +2027,7 @@ mod tests {
                         self_times_ns: vec![10],
                         sum_self_time_ns: 10,
                         total_times_ns: vec![10],
+                        sum_total_time_ns: 10,
                         max_duration: 10,
                         ..Default::default()
                     },
@@ -1012,31 +2038,39 @@ mod tests {
             }, // end sixth test case
             TestStruct {
                 name: "obfuscated java frames".to_string(),
-                node: Node {
-                    duration_ns: 20,
-                    is_application: true,
-                    frame: Frame {
-                        platform: Some("java".to_string()),
-                        function: Some("a.B()".to_string()),
-                        package: Some("a".to_string()),
-                        ..Default::default()
-                    },
-                    children: vec![Rc::new(RefCell::new(Node {
-                        duration_ns: 10,
+                node: NestedNode(
+                    Node {
+                        duration_ns: 20,
                         is_application: true,
                         frame: Frame {
                             platform: Some("java".to_string()),
-                            function: Some("com.example.Thing.doStuff()".to_string()),
-                            package: Some("com.example".to_string()),
+                            function: Some("a.B()".to_string()),
+                            package: Some("a".to_string()),
                             ..Default::default()
-                        },
+                        }
+                        .into(),
                         ..Default::default()
-                    }))],
-                    ..Default::default()
-                },
+                    },
+                    vec![NestedNode(
+                        Node {
+                            duration_ns: 10,
+                            is_application: true,
+                            frame: Frame {
+                                platform: Some("java".to_string()),
+                                function: Some("com.example.Thing.doStuff()".to_string()),
+                                package: Some("com.example".to_string()),
+                                ..Default::default()
+                            }
+                            .into(),
+                            ..Default::default()
+                        },
+                        vec![],
+                    )],
+                ),
                 want: [(
                     1902388659,
                     CallTreeFunction {
+                        thread_ids: vec!["".to_string()],
                         fingerprint: 1902388659,
                         in_app: true,
                         function: "com.example.Thing.doStuff()".to_string(),
@@ -1044,6 +2078,7 @@ mod tests {
                         self_times_ns: vec![10],
                         sum_self_time_ns: 10,
                         total_times_ns: vec![10],
+                        sum_total_time_ns: 10,
                         max_duration: 10,
                         ..Default::default()
                     },
@@ -1054,25 +2089,41 @@ mod tests {
             }, // end seventh test case
             TestStruct {
                 name: "cocoa main frame".to_string(),
-                node: Node {
-                    duration_ns: 10,
-                    is_application: true,
-                    frame: Frame {
-                        platform: Some("cocoa".to_string()),
-                        function: Some("main".to_string()),
-                        package: Some("iOS-Swift".to_string()),
+                node: NestedNode(
+                    Node {
+                        duration_ns: 10,
+                        is_application: true,
+                        frame: Frame {
+                            platform: Some("cocoa".to_string()),
+                            function: Some("main".to_string()),
+                            package: Some("iOS-Swift".to_string()),
+                            ..Default::default()
+                        }
+                        .into(),
                         ..Default::default()
                     },
-                    ..Default::default()
-                },
+                    vec![],
+                ),
                 want: HashMap::new(),
             }, // end eighth test case
         ];
 
         for test in &test_cases {
             let mut results: HashMap<u32, CallTreeFunction> = HashMap::new();
-            test.node
-                .collect_functions(&mut results, "", 0, 0, false, true, false, None);
+            let call_tree = CallTree::from_nested(vec![test.node.clone()]);
+            call_tree.collect_functions(
+                call_tree.roots()[0],
+                &mut results,
+                "",
+                0,
+                0,
+                false,
+                true,
+                false,
+                0,
+                false,
+                None,
+            );
 
             assert_eq!(results, test.want, "test `{}` failed", test.name);
         }
@@ -1082,7 +2133,7 @@ mod tests {
     fn test_node_collect_non_leaf_functions() {
         struct TestStruct {
             name: String,
-            node: Node,
+            node: NestedNode,
             want: HashMap<u32, CallTreeFunction>,
         }
 
@@ -1092,32 +2143,40 @@ mod tests {
         let test_cases: Vec<TestStruct> = vec![
             TestStruct {
                 name: "single application node".to_string(),
-                node: Node {
-                    duration_ns: 10,
-                    is_application: true,
-                    frame: Frame {
-                        platform: Some("python".to_string()),
-                        function: Some("foo".to_string()),
-                        package: Some("foo".to_string()),
-                        ..Default::default()
-                    },
-                    children: vec![Rc::new(RefCell::new(Node {
+                node: NestedNode(
+                    Node {
                         duration_ns: 10,
                         is_application: true,
                         frame: Frame {
                             platform: Some("python".to_string()),
-                            function: Some("bar".to_string()),
-                            package: Some("bar".to_string()),
+                            function: Some("foo".to_string()),
+                            package: Some("foo".to_string()),
                             ..Default::default()
-                        },
+                        }
+                        .into(),
                         ..Default::default()
-                    }))],
-                    ..Default::default()
-                },
+                    },
+                    vec![NestedNode(
+                        Node {
+                            duration_ns: 10,
+                            is_application: true,
+                            frame: Frame {
+                                platform: Some("python".to_string()),
+                                function: Some("bar".to_string()),
+                                package: Some("bar".to_string()),
+                                ..Default::default()
+                            }
+                            .into(),
+                            ..Default::default()
+                        },
+                        vec![],
+                    )],
+                ),
                 want: [
                     (
                         FINGERPRINT_FOO,
                         CallTreeFunction {
+                            thread_ids: vec!["".to_string()],
                             fingerprint: FINGERPRINT_FOO,
                             in_app: true,
                             function: "foo".to_string(),
@@ -1125,6 +2184,7 @@ mod tests {
                             self_times_ns: vec![0],
                             sum_self_time_ns: 0,
                             total_times_ns: vec![10],
+                            sum_total_time_ns: 10,
                             max_duration: 0,
                             ..Default::default()
                         },
@@ -1132,6 +2192,7 @@ mod tests {
                     (
                         FINGERPRINT_BAR,
                         CallTreeFunction {
+                            thread_ids: vec!["".to_string()],
                             fingerprint: FINGERPRINT_BAR,
                             in_app: true,
                             function: "bar".to_string(),
@@ -1139,6 +2200,7 @@ mod tests {
                             self_times_ns: vec![10],
                             sum_self_time_ns: 10,
                             total_times_ns: vec![10],
+                            sum_total_time_ns: 10,
                             max_duration: 10,
                             ..Default::default()
                         },
@@ -1152,8 +2214,20 @@ mod tests {
 
         for test in &test_cases {
             let mut results: HashMap<u32, CallTreeFunction> = HashMap::new();
-            test.node
-                .collect_functions(&mut results, "", 0, 0, false, false, false, None);
+            let call_tree = CallTree::from_nested(vec![test.node.clone()]);
+            call_tree.collect_functions(
+                call_tree.roots()[0],
+                &mut results,
+                "",
+                0,
+                0,
+                false,
+                false,
+                false,
+                0,
+                false,
+                None,
+            );
 
             assert_eq!(results, test.want, "test `{}` failed", test.name);
         }
@@ -1163,50 +2237,61 @@ mod tests {
     fn test_node_collect_functions_stack_fingerprints_all_frames() {
         struct TestStruct {
             name: String,
-            node: Node,
+            node: NestedNode,
             want: HashMap<u32, CallTreeFunction>,
         }
 
         let test_cases: Vec<TestStruct> = vec![
             TestStruct {
                 name: "all frames with stack fingerprint".to_string(),
-                node: Node {
-                    duration_ns: 10,
-                    is_application: true,
-                    frame: Frame {
-                        platform: Some("python".to_string()),
-                        function: Some("foo".to_string()),
-                        package: Some("foo".to_string()),
-                        ..Default::default()
-                    },
-                    children: vec![Rc::new(RefCell::new(Node {
+                node: NestedNode(
+                    Node {
                         duration_ns: 10,
                         is_application: true,
                         frame: Frame {
                             platform: Some("python".to_string()),
-                            function: Some("bar".to_string()),
-                            package: Some("bar".to_string()),
+                            function: Some("foo".to_string()),
+                            package: Some("foo".to_string()),
                             ..Default::default()
-                        },
-                        children: vec![Rc::new(RefCell::new(Node {
+                        }
+                        .into(),
+                        ..Default::default()
+                    },
+                    vec![NestedNode(
+                        Node {
                             duration_ns: 10,
                             is_application: true,
                             frame: Frame {
                                 platform: Some("python".to_string()),
-                                function: Some("baz".to_string()),
-                                package: Some("baz".to_string()),
+                                function: Some("bar".to_string()),
+                                package: Some("bar".to_string()),
                                 ..Default::default()
-                            },
+                            }
+                            .into(),
                             ..Default::default()
-                        }))],
-                        ..Default::default()
-                    }))],
-                    ..Default::default()
-                },
+                        },
+                        vec![NestedNode(
+                            Node {
+                                duration_ns: 10,
+                                is_application: true,
+                                frame: Frame {
+                                    platform: Some("python".to_string()),
+                                    function: Some("baz".to_string()),
+                                    package: Some("baz".to_string()),
+                                    ..Default::default()
+                                }
+                                .into(),
+                                ..Default::default()
+                            },
+                            vec![],
+                        )],
+                    )],
+                ),
                 want: [
                     (
                         333499442,
                         CallTreeFunction {
+                            thread_ids: vec!["".to_string()],
                             stack_fingerprint: Some(333499442),
                             fingerprint: 509004053,
                             in_app: true,
@@ -1215,6 +2300,7 @@ mod tests {
                             self_times_ns: vec![10],
                             sum_self_time_ns: 10,
                             total_times_ns: vec![10],
+                            sum_total_time_ns: 10,
                             max_duration: 10,
                             parent_fingerprint: Some(1806052038),
                             depth: Some(2),
@@ -1224,37 +2310,53 @@ mod tests {
                     (
                         2655321105,
                         CallTreeFunction {
+                            thread_ids: vec!["".to_string()],
                             parent_fingerprint: None,
                             stack_fingerprint: Some(2655321105),
                             fingerprint: 2655321105,
                             function: "foo".to_string(),
                             package: "foo".to_string(),
+                            filename: "".to_string(),
+                            line: None,
                             in_app: true,
                             self_times_ns: vec![0],
                             sum_self_time_ns: 0,
                             total_times_ns: vec![10],
+                            sum_total_time_ns: 10,
                             sample_count: 0,
                             thread_id: "".to_string(),
+                            thread_name: None,
+                            main_thread: false,
                             max_duration: 0,
                             depth: Some(0),
+                            examples: vec![],
+                            line_self_times_ns: vec![],
                         },
                     ),
                     (
                         1806052038,
                         CallTreeFunction {
+                            thread_ids: vec!["".to_string()],
                             parent_fingerprint: Some(2655321105),
                             stack_fingerprint: Some(1806052038),
                             fingerprint: 1766712469,
                             function: "bar".to_string(),
                             package: "bar".to_string(),
+                            filename: "".to_string(),
+                            line: None,
                             in_app: true,
                             self_times_ns: vec![0],
                             sum_self_time_ns: 0,
                             total_times_ns: vec![10],
+                            sum_total_time_ns: 10,
                             sample_count: 0,
                             thread_id: "".to_string(),
+                            thread_name: None,
+                            main_thread: false,
                             max_duration: 0,
                             depth: Some(1),
+                            examples: vec![],
+                            line_self_times_ns: vec![],
                         },
                     ),
                 ]
@@ -1266,8 +2368,20 @@ mod tests {
 
         for test in &test_cases {
             let mut results: HashMap<u32, CallTreeFunction> = HashMap::new();
-            test.node
-                .collect_functions(&mut results, "", 0, 0, false, false, true, None);
+            let call_tree = CallTree::from_nested(vec![test.node.clone()]);
+            call_tree.collect_functions(
+                call_tree.roots()[0],
+                &mut results,
+                "",
+                0,
+                0,
+                false,
+                false,
+                true,
+                0,
+                false,
+                None,
+            );
 
             assert_eq!(results, test.want, "test `{}` failed", test.name);
         }
@@ -1277,99 +2391,133 @@ mod tests {
     fn test_node_collect_functions_stack_fingerprints_application_frames() {
         struct TestStruct {
             name: String,
-            node: Node,
+            node: NestedNode,
             want: HashMap<u32, CallTreeFunction>,
         }
 
         let test_cases: Vec<TestStruct> = vec![
             TestStruct {
                 name: "application frames with stack fingerprint".to_string(),
-                node: Node {
-                    duration_ns: 10,
-                    is_application: true,
-                    frame: Frame {
-                        platform: Some("python".to_string()),
-                        function: Some("foo".to_string()),
-                        package: Some("foo".to_string()),
-                        ..Default::default()
-                    },
-                    children: vec![Rc::new(RefCell::new(Node {
+                node: NestedNode(
+                    Node {
                         duration_ns: 10,
-                        is_application: false,
+                        is_application: true,
                         frame: Frame {
                             platform: Some("python".to_string()),
-                            function: Some("bar".to_string()),
-                            package: Some("bar".to_string()),
+                            function: Some("foo".to_string()),
+                            package: Some("foo".to_string()),
                             ..Default::default()
-                        },
-                        children: vec![Rc::new(RefCell::new(Node {
+                        }
+                        .into(),
+                        ..Default::default()
+                    },
+                    vec![NestedNode(
+                        Node {
                             duration_ns: 10,
-                            is_application: true,
+                            is_application: false,
                             frame: Frame {
                                 platform: Some("python".to_string()),
-                                function: Some("baz".to_string()),
-                                package: Some("baz".to_string()),
+                                function: Some("bar".to_string()),
+                                package: Some("bar".to_string()),
                                 ..Default::default()
-                            },
+                            }
+                            .into(),
                             ..Default::default()
-                        }))],
-                        ..Default::default()
-                    }))],
-                    ..Default::default()
-                },
+                        },
+                        vec![NestedNode(
+                            Node {
+                                duration_ns: 10,
+                                is_application: true,
+                                frame: Frame {
+                                    platform: Some("python".to_string()),
+                                    function: Some("baz".to_string()),
+                                    package: Some("baz".to_string()),
+                                    ..Default::default()
+                                }
+                                .into(),
+                                ..Default::default()
+                            },
+                            vec![],
+                        )],
+                    )],
+                ),
                 want: [
                     (
                         2655321105,
                         CallTreeFunction {
+                            thread_ids: vec!["".to_string()],
                             parent_fingerprint: None,
                             stack_fingerprint: Some(2655321105),
                             fingerprint: 2655321105,
                             function: "foo".to_string(),
                             package: "foo".to_string(),
+                            filename: "".to_string(),
+                            line: None,
                             in_app: true,
                             self_times_ns: vec![0],
                             sum_self_time_ns: 0,
                             total_times_ns: vec![10],
+                            sum_total_time_ns: 10,
                             sample_count: 0,
                             thread_id: "".to_string(),
+                            thread_name: None,
+                            main_thread: false,
                             max_duration: 0,
                             depth: Some(0),
+                            examples: vec![],
+                            line_self_times_ns: vec![],
                         },
                     ),
                     (
                         1806052038,
                         CallTreeFunction {
+                            thread_ids: vec!["".to_string()],
                             parent_fingerprint: Some(2655321105),
                             stack_fingerprint: Some(1806052038),
                             fingerprint: 1766712469,
                             function: "bar".to_string(),
                             package: "bar".to_string(),
+                            filename: "".to_string(),
+                            line: None,
                             in_app: false,
                             self_times_ns: vec![0],
                             sum_self_time_ns: 0,
                             total_times_ns: vec![10],
+                            sum_total_time_ns: 10,
                             sample_count: 0,
                             thread_id: "".to_string(),
+                            thread_name: None,
+                            main_thread: false,
                             max_duration: 0,
                             depth: Some(1),
+                            examples: vec![],
+                            line_self_times_ns: vec![],
                         },
                     ),
                     (
                         3825246022,
                         CallTreeFunction {
+                            thread_ids: vec!["".to_string()],
                             parent_fingerprint: Some(2655321105),
                             stack_fingerprint: Some(3825246022),
                             fingerprint: 509004053,
                             function: "baz".to_string(),
                             package: "baz".to_string(),
+                            filename: "".to_string(),
+                            line: None,
                             in_app: true,
                             self_times_ns: vec![10],
                             sum_self_time_ns: 10,
                             total_times_ns: vec![10],
+                            sum_total_time_ns: 10,
                             sample_count: 0,
                             thread_id: "".to_string(),
+                            thread_name: None,
+                            main_thread: false,
                             max_duration: 10,
                             depth: Some(2),
+                            examples: vec![],
+                            line_self_times_ns: vec![],
                         },
                     ),
                 ]
@@ -1381,10 +2529,330 @@ mod tests {
 
         for test in &test_cases {
             let mut results: HashMap<u32, CallTreeFunction> = HashMap::new();
-            test.node
-                .collect_functions(&mut results, "", 0, 0, true, false, true, None);
+            let call_tree = CallTree::from_nested(vec![test.node.clone()]);
+            call_tree.collect_functions(
+                call_tree.roots()[0],
+                &mut results,
+                "",
+                0,
+                0,
+                true,
+                false,
+                true,
+                0,
+                false,
+                None,
+            );
 
             assert_eq!(results, test.want, "test `{}` failed", test.name);
         }
     }
+
+    #[test]
+    fn test_node_collect_functions_thread_ids() {
+        let node = Node {
+            duration_ns: 10,
+            is_application: true,
+            frame: Frame {
+                platform: Some("python".to_string()),
+                function: Some("foo".to_string()),
+                package: Some("foo".to_string()),
+                ..Default::default()
+            }
+            .into(),
+            ..Default::default()
+        };
+
+        let mut call_tree = CallTree::new();
+        let root = call_tree.push(None, node);
+
+        let mut results: HashMap<u32, CallTreeFunction> = HashMap::new();
+        for thread_id in ["2", "1", "2"] {
+            call_tree.collect_functions(
+                root,
+                &mut results,
+                thread_id,
+                0,
+                0,
+                false,
+                true,
+                false,
+                0,
+                false,
+                None,
+            );
+        }
+
+        let function = results.values().next().unwrap();
+        assert_eq!(function.thread_ids, vec!["1".to_string(), "2".to_string()]);
+        assert_eq!(function.self_times_ns, vec![10, 10, 10]);
+    }
+
+    #[test]
+    fn test_node_collect_functions_sum_total_time() {
+        let node = |function: &str, duration_ns: u64, children: Vec<NestedNode>| {
+            NestedNode(
+                Node {
+                    duration_ns,
+                    is_application: true,
+                    frame: Frame {
+                        platform: Some("python".to_string()),
+                        function: Some(function.to_string()),
+                        package: Some("app".to_string()),
+                        ..Default::default()
+                    }
+                    .into(),
+                    ..Default::default()
+                },
+                children,
+            )
+        };
+        // foo recurses through bar, then calls baz
+        let call_tree = CallTree::from_nested(vec![node(
+            "foo",
+            150,
+            vec![
+                node("bar", 80, vec![node("foo", 50, vec![])]),
+                node("baz", 40, vec![]),
+            ],
+        )]);
+
+        let mut results: HashMap<u32, CallTreeFunction> = HashMap::new();
+        call_tree.collect_functions(
+            call_tree.roots()[0],
+            &mut results,
+            "1",
+            0,
+            0,
+            false,
+            true,
+            false,
+            0,
+            false,
+            None,
+        );
+
+        let mut totals: Vec<(&str, u64, u64)> = results
+            .values()
+            .map(|f| (f.function.as_str(), f.sum_self_time_ns, f.sum_total_time_ns))
+            .collect();
+        totals.sort();
+        assert_eq!(
+            totals,
+            vec![("bar", 30, 80), ("baz", 40, 40), ("foo", 80, 150)]
+        );
+    }
+
+    #[test]
+    fn test_node_collect_functions_examples() {
+        let node = |start_ns: u64, end_ns: u64| Node {
+            start_ns,
+            end_ns,
+            duration_ns: end_ns - start_ns,
+            is_application: true,
+            frame: Frame {
+                platform: Some("python".to_string()),
+                function: Some("foo".to_string()),
+                package: Some("foo".to_string()),
+                ..Default::default()
+            }
+            .into(),
+            ..Default::default()
+        };
+
+        let mut results: HashMap<u32, CallTreeFunction> = HashMap::new();
+        for (thread_id, start_ns, end_ns) in [("1", 0, 10), ("2", 10, 40), ("1", 40, 60)] {
+            let mut call_tree = CallTree::new();
+            let root = call_tree.push(None, node(start_ns, end_ns));
+            call_tree.collect_functions(
+                root,
+                &mut results,
+                thread_id,
+                0,
+                0,
+                false,
+                true,
+                false,
+                2,
+                false,
+                None,
+            );
+        }
+
+        let function = results.values_mut().next().unwrap();
+        function.set_examples_source(None, Some("chunk"), |ts| 1_000 + ts);
+        assert_eq!(
+            function.examples,
+            vec![
+                FunctionExample {
+                    chunk_id: Some("chunk".to_string()),
+                    thread_id: "2".to_string(),
+                    start_ns: 10,
+                    end_ns: 40,
+                    start_timestamp_ns: 1_010,
+                    end_timestamp_ns: 1_040,
+                    self_time_ns: 30,
+                    ..Default::default()
+                },
+                FunctionExample {
+                    chunk_id: Some("chunk".to_string()),
+                    thread_id: "1".to_string(),
+                    start_ns: 40,
+                    end_ns: 60,
+                    start_timestamp_ns: 1_040,
+                    end_timestamp_ns: 1_060,
+                    self_time_ns: 20,
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_node_collect_functions_lines() {
+        let node = |line: Option<u32>, start_ns: u64, end_ns: u64| Node {
+            start_ns,
+            end_ns,
+            duration_ns: end_ns - start_ns,
+            is_application: true,
+            line,
+            frame: Frame {
+                platform: Some("python".to_string()),
+                function: Some("foo".to_string()),
+                package: Some("foo".to_string()),
+                ..Default::default()
+            }
+            .into(),
+            ..Default::default()
+        };
+
+        for (aggregate_lines, max_lines, want) in [
+            (false, 0, vec![]),
+            (true, 10, vec![(3, 40), (7, 20), (12, 10)]),
+            (true, 2, vec![(3, 40), (7, 20)]),
+        ] {
+            let mut results: HashMap<u32, CallTreeFunction> = HashMap::new();
+            for (line, start_ns, end_ns) in [
+                (Some(7), 0, 20),
+                (Some(3), 20, 50),
+                (None, 50, 55),
+                (Some(12), 55, 65),
+                (Some(3), 65, 75),
+            ] {
+                let mut call_tree = CallTree::new();
+                let root = call_tree.push(None, node(line, start_ns, end_ns));
+                call_tree.collect_functions(
+                    root,
+                    &mut results,
+                    "1",
+                    0,
+                    0,
+                    false,
+                    true,
+                    false,
+                    0,
+                    aggregate_lines,
+                    None,
+                );
+            }
+
+            let function = results.values_mut().next().unwrap();
+            function.truncate_lines(max_lines);
+            assert_eq!(function.line_self_times_ns, want, "max_lines={max_lines}");
+            assert_eq!(function.sum_self_time_ns, 75);
+        }
+    }
+
+    #[test]
+    fn test_call_tree_function_self_time_percentiles() {
+        let function = CallTreeFunction {
+            self_times_ns: (1..=100).rev().collect(),
+            ..Default::default()
+        };
+        assert_eq!(function.get_p75_self_time_ns(), 75);
+        assert_eq!(function.get_p95_self_time_ns(), 95);
+        assert_eq!(function.get_p99_self_time_ns(), 99);
+        assert_eq!(function.self_time_percentile_ns(1.0), 100);
+        assert_eq!(function.self_time_percentile_ns(0.0), 1);
+
+        let function = CallTreeFunction {
+            self_times_ns: vec![10, 1000],
+            ..Default::default()
+        };
+        assert_eq!(function.get_p75_self_time_ns(), 1000);
+
+        assert_eq!(CallTreeFunction::default().get_p99_self_time_ns(), 0);
+    }
+
+    #[test]
+    fn test_call_tree_function_row() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let main = builder.frame(Frame {
+            function: Some("main".to_string()),
+            ..Default::default()
+        });
+        builder
+            .project_id(42)
+            .release("1.0")
+            .sample("1", 1737465052.5, &[main])
+            .sample("1", 1737465052.6, &[main]);
+        let chunk = builder.build();
+        let function = CallTreeFunction {
+            fingerprint: 1234,
+            function: "main".to_string(),
+            package: "app".to_string(),
+            in_app: true,
+            self_times_ns: vec![10, 20],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            function.row(&chunk),
+            FunctionRow {
+                project_id: 42,
+                timestamp: 1737465052,
+                fingerprint: 1234,
+                name: "main",
+                package: "app",
+                is_application: true,
+                durations: &[10, 20],
+                platform: "python".to_string(),
+                environment: None,
+                release: Some("1.0"),
+                retention_days: chunk.retention_days,
+            }
+        );
+    }
+
+    #[test]
+    fn test_deobfuscation_ratio() {
+        let frame = |function: &str, status: Option<&str>| Frame {
+            function: Some(function.to_string()),
+            package: Some("com.example".to_string()),
+            platform: Some("android".to_string()),
+            data: status.map(|status| Data {
+                deobfuscation_status: Some(status.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut builder = SampleChunkBuilder::new("android");
+        builder.frame(frame("com.example.Thing.run()", None));
+        assert_eq!(deobfuscation_ratio(&builder.build()), None);
+
+        let mut builder = SampleChunkBuilder::new("android");
+        let load = frame("com.example.Thing.load()", Some("deobfuscated"));
+        let missing = frame("com.example.Thing.b()", Some("missing"));
+        assert!(should_aggregate_frame(&load));
+        assert!(!should_aggregate_frame(&missing));
+        let load = builder.frame(load);
+        let missing = builder.frame(missing);
+        let partial = builder.frame(frame("com.example.Thing.a()", Some("partial")));
+        let run = builder.frame(frame("com.example.Thing.run()", None));
+        builder
+            .sample("1", 1.0, &[missing, load, run])
+            .sample("1", 1.1, &[partial, run])
+            .sample("1", 1.2, &[run]);
+        assert_eq!(deobfuscation_ratio(&builder.build()), Some(2.0 / 3.0));
+    }
 }
@@ -0,0 +1,125 @@
+//! Detects Android profiles whose frames look ProGuard-obfuscated, so ingest
+//! can tag them and the UI can suggest uploading mapping files.
+//!
+//! ProGuard and R8 rename classes to sequences of lowercase letters (`a`,
+//! `b`, ..., `aa`, `ab`, ...), so a profile whose app classes are mostly
+//! named that way most likely wasn't deobfuscated.
+
+use std::borrow::Cow;
+
+use crate::{frame::Frame, nodetree::OBFUSCATION_SUPPORTED_PLATFORMS};
+
+/// Maximum length of the class names generated by ProGuard considered,
+/// longer ones being rare enough to be confused with real names.
+const MAX_OBFUSCATED_CLASS_NAME_LEN: usize = 2;
+
+/// Ratio of the app frames above which a profile is considered obfuscated.
+const MIN_OBFUSCATED_FRAMES_RATIO: f64 = 0.5;
+
+/// Returns the simple name of the class of a Java method name
+/// (`com.example.a.b$c.d()` is in class `b`), or None if it has no class.
+fn simple_class_name(function: &str) -> Option<&str> {
+    let method = function
+        .split_once('(')
+        .map_or(function, |(method, _)| method);
+    let (class_name, _) = method.rsplit_once('.')?;
+    let simple_class_name = class_name.rsplit('.').next()?;
+    simple_class_name.split('$').next()
+}
+
+/// Returns whether the class of a frame looks renamed by ProGuard.
+fn has_obfuscated_class_name(frame: &Frame) -> bool {
+    frame
+        .function
+        .as_deref()
+        .and_then(simple_class_name)
+        .is_some_and(|class_name| {
+            !class_name.is_empty()
+                && class_name.len() <= MAX_OBFUSCATED_CLASS_NAME_LEN
+                && class_name.bytes().all(|b| b.is_ascii_lowercase())
+        })
+}
+
+/// Returns the number of app frames of a platform supporting deobfuscation,
+/// and how many of them have a class name looking renamed by ProGuard.
+pub(crate) fn obfuscated_frames(platform: &str, frames: &[Cow<'_, Frame>]) -> (usize, usize) {
+    frames
+        .iter()
+        .filter(|frame| {
+            OBFUSCATION_SUPPORTED_PLATFORMS.contains(frame.platform.as_deref().unwrap_or(platform))
+                // system frames are never obfuscated
+                && frame.in_app != Some(false)
+        })
+        .fold((0, 0), |(obfuscated, total), frame| {
+            (
+                obfuscated + usize::from(has_obfuscated_class_name(frame)),
+                total + 1,
+            )
+        })
+}
+
+/// Returns whether most app frames have class names looking renamed by
+/// ProGuard.
+pub fn is_obfuscated(platform: &str, frames: &[Cow<'_, Frame>]) -> bool {
+    let (obfuscated, total) = obfuscated_frames(platform, frames);
+    total > 0 && obfuscated as f64 / total as f64 > MIN_OBFUSCATED_FRAMES_RATIO
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::{is_obfuscated, simple_class_name};
+    use crate::frame::Frame;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_simple_class_name() {
+        let test_cases = [
+            (
+                "com.example.MainActivity.onCreate(Bundle)",
+                Some("MainActivity"),
+            ),
+            ("a.b.c()", Some("b")),
+            ("com.example.a$b.c", Some("a")),
+            ("main", None),
+        ];
+        for (function, want) in test_cases {
+            assert_eq!(simple_class_name(function), want, "{function}");
+        }
+    }
+
+    #[test]
+    fn test_is_obfuscated() {
+        let frame = |function: &str, in_app: bool| {
+            Cow::Owned(Frame {
+                function: Some(function.to_string()),
+                in_app: Some(in_app),
+                ..Default::default()
+            })
+        };
+        let system = [
+            frame("android.app.Activity.performCreate(Bundle)", false),
+            frame("java.lang.a.b()", false),
+        ];
+
+        let mut frames = system.to_vec();
+        frames.extend([
+            frame("com.example.a.b()", true),
+            frame("com.example.ab$c.d(a)", true),
+            frame("com.example.MainActivity.onCreate(Bundle)", true),
+        ]);
+        assert!(is_obfuscated("android", &frames));
+        // only platforms supporting deobfuscation
+        assert!(!is_obfuscated("python", &frames));
+
+        let mut frames = system.to_vec();
+        frames.extend([
+            frame("com.example.a.b()", true),
+            frame("com.example.MainActivity.onCreate(Bundle)", true),
+        ]);
+        assert!(!is_obfuscated("android", &frames));
+        assert!(!is_obfuscated("android", &system));
+    }
+}
@@ -0,0 +1,423 @@
+//! Detection of the app start prelude of Cocoa profiles: the work done on
+//! the main thread before `main` runs, by dyld loading and binding images
+//! and by the Objective-C runtime running `+load` methods and static
+//! initializers. It doesn't show up in app functions, yet apps can reduce
+//! it (fewer dylibs, lazy `+initialize` instead of `+load`, ...).
+
+use std::collections::HashMap;
+
+use pyo3::{pyclass, pymethods};
+
+use crate::{
+    nodetree::{CallTree, Node, NodeId},
+    types::{CallTreesU64, ProfileInterface},
+};
+
+use super::{NodeInfo, Occurrence};
+
+pub const APP_START_PRELUDE: &str = "app_start_prelude";
+/// Platforms whose profiles can capture the app start prelude.
+pub const APP_START_PRELUDE_PLATFORMS: &[&str] = &["cocoa"];
+
+/// Kinds of prelude frames.
+const KIND_DYLD: &str = "dyld";
+const KIND_OBJC_LOAD: &str = "objc_load";
+const KIND_STATIC_INITIALIZER: &str = "static_initializer";
+
+/// Functions calling into the app, which end the prelude.
+const MAIN_FUNCTIONS: &[&str] = &["main", "UIApplicationMain", "NSApplicationMain"];
+
+/// Package of dyld frames, once trimmed.
+const DYLD_PACKAGE: &str = "dyld";
+const DYLD_FUNCTION_PREFIXES: &[&str] = &[
+    "_dyld_start",
+    "dyldbootstrap::",
+    "dyld::",
+    "dyld3::",
+    "dyld4::",
+];
+/// Functions of the Objective-C runtime loading images and running their
+/// `+load` methods.
+const OBJC_LOAD_FUNCTIONS: &[&str] = &[
+    "_objc_init",
+    "call_load_methods",
+    "load_images",
+    "map_images",
+    "map_images_nolock",
+];
+const STATIC_INITIALIZER_PREFIXES: &[&str] = &["__cxx_global_var_init", "_GLOBAL__sub_I_"];
+
+/// The time spent in the app start prelude of a profile.
+#[pyclass]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AppStartPrelude {
+    pub window_ns: u64,
+    pub duration_ns: u64,
+    pub frames: Vec<PreludeFrame>,
+    pub occurrence: Option<Occurrence>,
+}
+
+/// A frame of the app start prelude, with the time spent in it.
+#[pyclass]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreludeFrame {
+    pub function: String,
+    pub package: String,
+    pub kind: String,
+    pub duration_ns: u64,
+}
+
+#[pymethods]
+impl AppStartPrelude {
+    /// Returns the duration of the app start window the prelude was looked
+    /// for in, from the start of the profile.
+    ///
+    /// Returns:
+    ///     int
+    ///         The duration of the window in nanoseconds.
+    pub fn get_window_ns(&self) -> u64 {
+        self.window_ns
+    }
+
+    /// Returns the time spent in the prelude on the main thread.
+    ///
+    /// Returns:
+    ///     int
+    ///         The duration in nanoseconds.
+    pub fn get_duration_ns(&self) -> u64 {
+        self.duration_ns
+    }
+
+    /// Returns the frames of the prelude.
+    ///
+    /// Returns:
+    ///     list[PreludeFrame]
+    ///         The frames, sorted by descending duration.
+    pub fn get_frames(&self) -> Vec<PreludeFrame> {
+        self.frames.clone()
+    }
+
+    /// Returns the "slow app start prelude" occurrence, if one was requested
+    /// and the prelude was slow enough.
+    ///
+    /// Returns:
+    ///     Occurrence | None
+    ///         The occurrence, blaming the frame the most time was spent in.
+    pub fn get_occurrence(&self) -> Option<Occurrence> {
+        self.occurrence.clone()
+    }
+}
+
+#[pymethods]
+impl PreludeFrame {
+    /// Returns the function name.
+    ///
+    /// Returns:
+    ///     str
+    ///         The function name (e.g. "+[MyClass load]").
+    pub fn get_function(&self) -> &str {
+        &self.function
+    }
+
+    /// Returns the package name.
+    ///
+    /// Returns:
+    ///     str
+    ///         The package name.
+    pub fn get_package(&self) -> &str {
+        &self.package
+    }
+
+    /// Returns the kind of work done in the frame.
+    ///
+    /// Returns:
+    ///     str
+    ///         "dyld", "objc_load" or "static_initializer".
+    pub fn get_kind(&self) -> &str {
+        &self.kind
+    }
+
+    /// Returns the time spent in the frame, and in the frames it called
+    /// which aren't prelude frames themselves.
+    ///
+    /// Returns:
+    ///     int
+    ///         The duration in nanoseconds, within the app start window.
+    pub fn get_duration_ns(&self) -> u64 {
+        self.duration_ns
+    }
+}
+
+fn is_objc_load_method(name: &str) -> bool {
+    name.starts_with("+[") && name.ends_with(" load]")
+}
+
+/// Returns the kind of prelude work the node does, if any.
+fn prelude_kind(node: &Node) -> Option<&'static str> {
+    let name = node.name.as_str();
+    if is_objc_load_method(name) || OBJC_LOAD_FUNCTIONS.contains(&name) {
+        return Some(KIND_OBJC_LOAD);
+    }
+    if STATIC_INITIALIZER_PREFIXES
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+    {
+        return Some(KIND_STATIC_INITIALIZER);
+    }
+    if node.package == DYLD_PACKAGE
+        || DYLD_FUNCTION_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+    {
+        return Some(KIND_DYLD);
+    }
+    None
+}
+
+#[derive(Default)]
+struct PreludeWalk {
+    /// Time attributed to each prelude frame, by function and package.
+    durations: HashMap<(String, String), (&'static str, u64)>,
+    /// Longest node of each prelude frame, along with its stack.
+    nodes: HashMap<(String, String), (u64, Vec<NodeId>)>,
+}
+
+impl PreludeWalk {
+    /// Walks the node, attributing the time spent in the window to the
+    /// innermost prelude frame of the stack. Returns the time spent in the
+    /// node within the window.
+    fn walk(
+        &mut self,
+        call_tree: &CallTree,
+        id: NodeId,
+        window_ns: u64,
+        prelude: Option<(&'static str, NodeId)>,
+        stack: &mut Vec<NodeId>,
+    ) -> u64 {
+        let node = &call_tree[id];
+        let overlap_ns = node.end_ns.min(window_ns).saturating_sub(node.start_ns);
+        // the prelude is over once the app is running
+        if overlap_ns == 0 || MAIN_FUNCTIONS.contains(&node.name.as_str()) {
+            return overlap_ns;
+        }
+
+        stack.push(id);
+        let prelude = match prelude_kind(node) {
+            Some(kind) => {
+                let key = (node.name.clone(), node.package.clone());
+                let longest = self.nodes.entry(key).or_default();
+                if overlap_ns > longest.0 {
+                    *longest = (overlap_ns, stack.clone());
+                }
+                Some((kind, id))
+            }
+            None => prelude,
+        };
+        let children_ns: u64 = call_tree
+            .children(id)
+            .iter()
+            .map(|&child| self.walk(call_tree, child, window_ns, prelude, stack))
+            .sum();
+        if let Some((kind, prelude_id)) = prelude {
+            let prelude_node = &call_tree[prelude_id];
+            let key = (prelude_node.name.clone(), prelude_node.package.clone());
+            self.durations.entry(key).or_insert((kind, 0)).1 +=
+                overlap_ns.saturating_sub(children_ns);
+        }
+        stack.pop();
+        overlap_ns
+    }
+}
+
+/// Returns the app start prelude of the main thread within the first
+/// `window_ns` of the profile, or `None` if the platform isn't supported or
+/// the main thread is unknown.
+///
+/// With `min_duration_ns`, an occurrence is generated if the prelude lasted
+/// at least that long.
+pub fn find_app_start_prelude(
+    profile: &dyn ProfileInterface,
+    call_trees: &CallTreesU64,
+    window_ns: u64,
+    min_duration_ns: Option<u64>,
+) -> Option<AppStartPrelude> {
+    if !APP_START_PRELUDE_PLATFORMS.contains(&profile.get_platform().as_str()) {
+        return None;
+    }
+    let call_tree = call_trees.get(&profile.get_main_thread_id()?)?;
+
+    let mut walk = PreludeWalk::default();
+    for &root in call_tree.roots() {
+        walk.walk(call_tree, root, window_ns, None, &mut Vec::new());
+    }
+
+    let mut frames: Vec<PreludeFrame> = walk
+        .durations
+        .into_iter()
+        .filter(|(_, (_, duration_ns))| *duration_ns > 0)
+        .map(|((function, package), (kind, duration_ns))| PreludeFrame {
+            function,
+            package,
+            kind: kind.to_string(),
+            duration_ns,
+        })
+        .collect();
+    frames.sort_by(|a, b| {
+        b.duration_ns
+            .cmp(&a.duration_ns)
+            .then_with(|| a.function.cmp(&b.function))
+            .then_with(|| a.package.cmp(&b.package))
+    });
+    let duration_ns = frames.iter().map(|frame| frame.duration_ns).sum();
+
+    let occurrence = match (min_duration_ns, frames.first()) {
+        (Some(min_duration_ns), Some(frame)) if duration_ns >= min_duration_ns => {
+            let (_, stack) = &walk.nodes[&(frame.function.clone(), frame.package.clone())];
+            let node = call_tree[*stack.last()?].clone();
+            Some(super::new_occurrence(
+                profile,
+                NodeInfo {
+                    category: APP_START_PRELUDE.to_string(),
+                    node,
+                    stack_trace: stack.iter().map(|&id| call_tree[id].to_frame()).collect(),
+                },
+            ))
+        }
+        _ => None,
+    };
+
+    Some(AppStartPrelude {
+        window_ns,
+        duration_ns,
+        frames,
+        occurrence,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{find_app_start_prelude, PreludeFrame, APP_START_PRELUDE};
+    use crate::{
+        frame::Frame,
+        sample::v1::{self, Sample, SampleProfile},
+        types::{ProfileInterface, Transaction},
+    };
+
+    use pretty_assertions::assert_eq;
+
+    fn profile(platform: &str, stacks: Vec<Vec<usize>>) -> SampleProfile {
+        let frame = |function: &str, package: &str| Frame {
+            function: Some(function.to_string()),
+            package: Some(package.to_string()),
+            ..Default::default()
+        };
+        SampleProfile {
+            platform: platform.to_string(),
+            transaction: Transaction {
+                active_thread_id: 1,
+                ..Default::default()
+            },
+            profile: v1::Profile {
+                samples: (0..stacks.len())
+                    .map(|i| Sample {
+                        stack_id: i,
+                        thread_id: 1,
+                        elapsed_since_start_ns: i as u64 * 10_000_000,
+                        ..Default::default()
+                    })
+                    .collect(),
+                stacks,
+                frames: vec![
+                    frame("start", "/usr/lib/dyld"),
+                    frame("dyld4::prepare(dyld4::APIs&)", "/usr/lib/dyld"),
+                    frame("load_images", "/usr/lib/libobjc.A.dylib"),
+                    frame("+[Analytics load]", "/private/var/MyApp.app/MyApp"),
+                    frame("main", "/private/var/MyApp.app/MyApp"),
+                    frame("-[AppDelegate setup]", "/private/var/MyApp.app/MyApp"),
+                ],
+                thread_metadata: Some(HashMap::from([(
+                    "1".to_string(),
+                    serde_json::from_str(r#"{"name": "main"}"#).unwrap(),
+                )])),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_find_app_start_prelude() {
+        // stacks are leaf first
+        let mut profile = profile(
+            "cocoa",
+            vec![
+                vec![1, 0],
+                vec![3, 2, 1, 0],
+                vec![3, 2, 1, 0],
+                vec![2, 1, 0],
+                vec![5, 4, 0],
+                vec![5, 4, 0],
+            ],
+        );
+        let call_trees = profile.call_trees().unwrap();
+
+        let prelude = find_app_start_prelude(&profile, &call_trees, 1_000_000_000, None).unwrap();
+        let frame = |function: &str, package: &str, kind: &str, duration_ms: u64| PreludeFrame {
+            function: function.to_string(),
+            package: package.to_string(),
+            kind: kind.to_string(),
+            duration_ns: duration_ms * 1_000_000,
+        };
+        assert_eq!(
+            prelude.frames,
+            vec![
+                frame("+[Analytics load]", "MyApp", "objc_load", 20),
+                frame("dyld4::prepare(dyld4::APIs&)", "dyld", "dyld", 10),
+                frame("load_images", "libobjc.A", "objc_load", 10),
+            ]
+        );
+        assert_eq!(prelude.duration_ns, 40_000_000);
+        assert_eq!(prelude.occurrence, None);
+
+        // the window cuts the prelude short
+        let prelude = find_app_start_prelude(&profile, &call_trees, 25_000_000, None).unwrap();
+        assert_eq!(prelude.duration_ns, 25_000_000);
+
+        let prelude =
+            find_app_start_prelude(&profile, &call_trees, 1_000_000_000, Some(40_000_000)).unwrap();
+        let occurrence = prelude.occurrence.unwrap();
+        assert_eq!(occurrence.category, APP_START_PRELUDE);
+        assert_eq!(occurrence.issue_title, "Slow App Start Prelude");
+        assert_eq!(occurrence.subtitle, "+[Analytics load]");
+        assert_eq!(
+            occurrence
+                .event
+                .stacktrace
+                .frames
+                .iter()
+                .map(|f| f.function.clone().unwrap())
+                .collect::<Vec<_>>(),
+            vec![
+                "start",
+                "dyld4::prepare(dyld4::APIs&)",
+                "load_images",
+                "+[Analytics load]"
+            ]
+        );
+        let prelude =
+            find_app_start_prelude(&profile, &call_trees, 1_000_000_000, Some(50_000_000)).unwrap();
+        assert_eq!(prelude.occurrence, None);
+    }
+
+    #[test]
+    fn test_find_app_start_prelude_unsupported() {
+        let mut profile = profile("python", vec![vec![1, 0]]);
+        let call_trees = profile.call_trees().unwrap();
+        assert_eq!(
+            find_app_start_prelude(&profile, &call_trees, 1_000_000_000, None),
+            None
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! Detects runaway recursion: functions calling themselves over and over,
+//! which never overflow the stack but burn CPU.
+
+use std::collections::HashSet;
+
+use crate::{
+    nodetree::{CallTree, NodeId},
+    types::{CallTreesU64, ProfileInterface},
+};
+
+use super::{
+    detector::{Detector, DetectorOptions},
+    NodeInfo, Occurrence,
+};
+
+pub const DEEP_RECURSION: &str = "deep_recursion";
+
+/// Platforms of the profiles deep recursion is looked for in.
+pub const DEEP_RECURSION_PLATFORMS: &[&str] = &[
+    "android",
+    "cocoa",
+    "javascript",
+    "node",
+    "php",
+    "python",
+    "ruby",
+    "rust",
+];
+
+/// Number of consecutive calls of a function past which its recursion is
+/// reported, unless set by [`DetectorOptions::recursion_depth_threshold`].
+pub const DEFAULT_RECURSION_DEPTH_THRESHOLD: u64 = 100;
+
+pub struct DeepRecursionDetector;
+
+impl Detector for DeepRecursionDetector {
+    fn name(&self) -> &str {
+        DEEP_RECURSION
+    }
+
+    fn categories(&self) -> Vec<&str> {
+        vec![DEEP_RECURSION]
+    }
+
+    fn platforms(&self) -> Vec<&str> {
+        DEEP_RECURSION_PLATFORMS.to_vec()
+    }
+
+    fn run(
+        &self,
+        call_trees: &CallTreesU64,
+        profile: &dyn ProfileInterface,
+        options: &DetectorOptions,
+        occurrences: &mut Vec<Occurrence>,
+    ) {
+        let threshold = options
+            .recursion_depth_threshold
+            .unwrap_or(DEFAULT_RECURSION_DEPTH_THRESHOLD);
+        let mut thread_ids: Vec<&u64> = call_trees.keys().collect();
+        thread_ids.sort();
+        for thread_id in thread_ids {
+            for node_info in find_deep_recursions(&call_trees[thread_id], threshold) {
+                occurrences.push(super::new_occurrence(profile, node_info));
+            }
+        }
+    }
+}
+
+/// Finds the calls of a function directly followed by more than `threshold`
+/// consecutive calls of the same function (frames with the same function
+/// fingerprint), counting the calls collapsed into the nodes of trees built
+/// with `collapse_recursion`.
+///
+/// The node reported is the outermost call of the recursion, lasting as long
+/// as the whole recursion, with the stack leading to it.
+pub(crate) fn find_deep_recursions(call_tree: &CallTree, threshold: u64) -> Vec<NodeInfo> {
+    let mut recursions = Vec::new();
+    let mut reported: HashSet<NodeId> = HashSet::new();
+    // the calls from the root to the current node: the node, its function
+    // fingerprint, the consecutive calls of its function up to it, and the
+    // depth of the outermost of these calls
+    let mut path: Vec<(NodeId, u32, u64, usize)> = Vec::new();
+    // nodes to visit, with their depth, iteratively as recursion is deep by
+    // definition here
+    let mut stack: Vec<(NodeId, usize)> = call_tree
+        .roots()
+        .iter()
+        .rev()
+        .map(|&root| (root, 0))
+        .collect();
+    while let Some((id, depth)) = stack.pop() {
+        path.truncate(depth);
+        let node = &call_tree[id];
+        let function = node.frame.fingerprint(None);
+        let mut calls = 1 + node.recursion_count;
+        let mut outermost = depth;
+        if let Some(&(_, parent_function, parent_calls, parent_outermost)) = path.last() {
+            if parent_function == function {
+                calls += parent_calls;
+                outermost = parent_outermost;
+            }
+        }
+        path.push((id, function, calls, outermost));
+
+        let outermost_id = path[outermost].0;
+        if calls > threshold && reported.insert(outermost_id) {
+            recursions.push(NodeInfo {
+                category: DEEP_RECURSION.to_string(),
+                node: call_tree[outermost_id].clone(),
+                stack_trace: path[..=outermost]
+                    .iter()
+                    .map(|&(id, ..)| call_tree[id].to_frame())
+                    .collect(),
+            });
+        }
+        stack.extend(
+            call_tree
+                .children(id)
+                .iter()
+                .rev()
+                .map(|&child| (child, depth + 1)),
+        );
+    }
+    recursions
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::find_deep_recursions;
+    use crate::{
+        frame::Frame,
+        nodetree::{CallTree, NestedNode, Node},
+    };
+
+    use pretty_assertions::assert_eq;
+
+    fn node(function: &str, duration_ns: u64, children: Vec<NestedNode>) -> NestedNode {
+        NestedNode(
+            Node {
+                duration_ns,
+                name: function.to_string(),
+                frame: Arc::new(Frame {
+                    function: Some(function.to_string()),
+                    module: Some("app".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            children,
+        )
+    }
+
+    /// Returns `depth` nested calls of `function`, the innermost one calling
+    /// `leaf`.
+    fn recursion(function: &str, depth: usize, leaf: Vec<NestedNode>) -> NestedNode {
+        let mut nested = node(function, 10, leaf);
+        for _ in 1..depth {
+            nested = node(function, 10, vec![nested]);
+        }
+        nested
+    }
+
+    #[test]
+    fn test_find_deep_recursions() {
+        let call_tree = CallTree::from_nested(vec![node(
+            "main",
+            100,
+            vec![
+                recursion("walk", 5, vec![recursion("visit", 2, vec![])]),
+                recursion("parse", 3, vec![]),
+            ],
+        )]);
+
+        let recursions = find_deep_recursions(&call_tree, 3);
+        let found: Vec<(&str, Vec<Option<&str>>)> = recursions
+            .iter()
+            .map(|recursion| {
+                (
+                    recursion.node.name.as_str(),
+                    recursion
+                        .stack_trace
+                        .iter()
+                        .map(|frame| frame.function.as_deref())
+                        .collect(),
+                )
+            })
+            .collect();
+        // 3 consecutive calls of parse aren't more than the threshold
+        assert_eq!(found, vec![("walk", vec![Some("main"), Some("walk")])]);
+        assert!(recursions
+            .iter()
+            .all(|recursion| recursion.category == "deep_recursion"));
+
+        assert_eq!(find_deep_recursions(&call_tree, 2).len(), 2);
+        assert!(find_deep_recursions(&call_tree, 5).is_empty());
+    }
+
+    #[test]
+    fn test_find_deep_recursions_collapsed() {
+        let mut collapsed = node("walk", 10, vec![]);
+        collapsed.0.recursion_count = 9;
+        let call_tree = CallTree::from_nested(vec![node("main", 10, vec![collapsed])]);
+        assert_eq!(find_deep_recursions(&call_tree, 9).len(), 1);
+        assert!(find_deep_recursions(&call_tree, 10).is_empty());
+    }
+}
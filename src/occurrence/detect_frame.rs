@@ -1,13 +1,19 @@
 use once_cell::sync::Lazy;
-use std::{cell::RefCell, collections::HashMap, rc::Rc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 
 use crate::{
     frame::Frame,
-    nodetree::Node,
+    limits::max_stack_depth,
+    nodetree::{CallTree, Node, NodeId},
     types::{CallTreesU64, ProfileInterface},
-    MAX_STACK_DEPTH,
 };
 
+use super::detector::{register_detector, Detector, DetectorError, DetectorOptions};
+
 pub(crate) const BASE64_DECODE: &str = "base64_decode";
 pub(crate) const BASE64_ENCODE: &str = "base64_encode";
 pub(crate) const COMPRESSION: &str = "compression";
@@ -15,6 +21,7 @@ pub(crate) const CORE_DATA_BLOCK: &str = "core_data_block";
 pub(crate) const CORE_DATA_MERGE: &str = "core_data_merge";
 pub(crate) const CORE_DATA_READ: &str = "core_data_read";
 pub(crate) const CORE_DATA_WRITE: &str = "core_data_write";
+pub(crate) const DB_MAIN_THREAD: &str = "db_main_thread";
 pub(crate) const DECOMPRESSION: &str = "decompression";
 pub(crate) const FILE_READ: &str = "file_read";
 pub(crate) const FILE_WRITE: &str = "file_write";
@@ -25,6 +32,7 @@ pub(crate) const JSON_DECODE: &str = "json_decode";
 pub(crate) const JSON_ENCODE: &str = "json_encode";
 pub(crate) const ML_MODEL_INFERENCE: &str = "ml_model_inference";
 pub(crate) const ML_MODEL_LOAD: &str = "ml_model_load";
+pub(crate) const NETWORK_MAIN_THREAD: &str = "network_main_thread";
 pub(crate) const REGEX: &str = "regex";
 pub(crate) const SQL: &str = "sql";
 pub(crate) const SOURCE_CONTEXT: &str = "source_context";
@@ -55,6 +63,9 @@ pub trait DetectFrameOptions {
     /// Checks a node and returns information about it if it matches detection criteria.
     /// Returns None if the node doesn't match the criteria.
     fn check_node(&self, node: &Node) -> Option<NodeInfo>;
+
+    /// Returns the categories of the occurrences the job can create.
+    fn categories(&self) -> Vec<&str>;
 }
 
 /// Options for detecting exact frames in profiling data.
@@ -91,6 +102,24 @@ pub struct DetectAndroidFrameOptions {
     pub sample_threshold: u32,
 }
 
+/// Options for detecting frames registered at runtime, through
+/// [`register_frame_detector`].
+#[derive(Debug, Clone)]
+pub struct DetectRegisteredFrameOptions {
+    /// Category assigned to the detected frames
+    pub category: String,
+
+    /// Which threads to consider for detection
+    pub detection_thread: DetectionThread,
+
+    /// Minimum duration threshold for frame detection
+    pub duration_threshold: Duration,
+
+    /// Function names to detect. Names are matched either exactly or,
+    /// for frames carrying a signature (e.g. android), up to the first '('.
+    pub frame_names: HashSet<String>,
+}
+
 /// Key for identifying a specific node in the call tree.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct NodeKey {
@@ -137,16 +166,20 @@ impl DetectFrameOptions for DetectExactFrameOptions {
             return None;
         }
 
-        // Create NodeInfo with the category and a copy of the node (without children).
-        let mut node_copy = node.clone();
-        node_copy.children.clear();
-
+        // Create NodeInfo with the category and a copy of the node.
         Some(NodeInfo {
             category: category.to_string(),
-            node: node_copy,
+            node: node.clone(),
             stack_trace: Vec::new(), // Initialize empty stack trace
         })
     }
+
+    fn categories(&self) -> Vec<&str> {
+        self.functions_by_package
+            .values()
+            .flat_map(|functions| functions.values().copied())
+            .collect()
+    }
 }
 
 impl DetectFrameOptions for DetectAndroidFrameOptions {
@@ -181,26 +214,68 @@ impl DetectFrameOptions for DetectAndroidFrameOptions {
             return None;
         }
 
-        // Create NodeInfo with the category and a copy of the node (without children).
-        let mut node_copy = node.clone();
-        node_copy.children.clear();
-
+        // Create NodeInfo with the category and a copy of the node.
         Some(NodeInfo {
             category: category.to_string(),
-            node: node_copy,
+            node: node.clone(),
             stack_trace: Vec::new(), // Initialize empty stack trace
         })
     }
+
+    fn categories(&self) -> Vec<&str> {
+        self.functions_by_package
+            .values()
+            .flat_map(|functions| functions.values().copied())
+            .collect()
+    }
+}
+
+impl DetectFrameOptions for DetectRegisteredFrameOptions {
+    fn detection_thread(&self) -> DetectionThread {
+        self.detection_thread
+    }
+
+    fn check_node(&self, node: &Node) -> Option<NodeInfo> {
+        // Check if we need to detect that function.
+        if !self.frame_names.contains(node.name.as_str()) {
+            let paren_pos = node.name.find('(')?;
+            if !self.frame_names.contains(&node.name[..paren_pos]) {
+                return None;
+            }
+        }
+
+        // Check if it's above the duration threshold.
+        let duration_threshold_ns = self.duration_threshold.as_nanos() as u64;
+        if node.duration_ns < duration_threshold_ns {
+            return None;
+        }
+
+        // Create NodeInfo with the category and a copy of the node.
+        Some(NodeInfo {
+            category: self.category.clone(),
+            node: node.clone(),
+            stack_trace: Vec::new(),
+        })
+    }
+
+    fn categories(&self) -> Vec<&str> {
+        vec![self.category.as_str()]
+    }
 }
 
-/// Platform-specific frame detection job configurations.
-pub static DETECT_FRAME_JOBS: Lazy<
-    HashMap<String, Vec<Box<dyn DetectFrameOptions + Send + Sync>>>,
-> = Lazy::new(|| {
+/// A frame detection job, shared by the detector running it.
+pub type FrameJob = Arc<dyn DetectFrameOptions + Send + Sync>;
+
+/// A frame detection job and the name of its detector.
+pub type NamedFrameJob = (&'static str, FrameJob);
+
+/// Platform-specific frame detection job configurations, named after their
+/// platform and what they detect.
+pub static DETECT_FRAME_JOBS: Lazy<HashMap<String, Vec<NamedFrameJob>>> = Lazy::new(|| {
     HashMap::from([
         // Node.js platform
         ("node".to_string(), vec![
-            Box::new(DetectExactFrameOptions {
+            ("node_file_read", Arc::new(DetectExactFrameOptions {
                 detection_thread: DetectionThread::ActiveThread,
                 duration_threshold: Duration::from_millis(0),
                 sample_threshold: 1,
@@ -250,8 +325,8 @@ pub static DETECT_FRAME_JOBS: Lazy<
                         ("writevSync", FILE_READ),
                     ]))
                 ]),
-            }) as Box<dyn DetectFrameOptions + Send + Sync>,
-            Box::new(DetectExactFrameOptions {
+            }) as FrameJob),
+            ("node_source_context", Arc::new(DetectExactFrameOptions {
                 detection_thread: DetectionThread::AllThreads,
                 duration_threshold: Duration::from_millis(100),
                 sample_threshold: 1,
@@ -261,11 +336,11 @@ pub static DETECT_FRAME_JOBS: Lazy<
                         ("addSourceContextToFrames", SOURCE_CONTEXT),
                     ]))
                 ]),
-            }) as Box<dyn DetectFrameOptions + Send + Sync>,
+            }) as FrameJob),
         ]),
         // Cocoa platform
         ("cocoa".to_string(), vec![
-            Box::new(DetectExactFrameOptions {
+            ("cocoa_main_thread", Arc::new(DetectExactFrameOptions {
                 detection_thread: DetectionThread::MainThread,
                 duration_threshold: Duration::from_millis(16),
                 sample_threshold: 4,
@@ -299,14 +374,6 @@ pub static DETECT_FRAME_JOBS: Lazy<
                         ("-[MLNeuralNetworkEngine predictionFromFeatures:options:error:]", ML_MODEL_INFERENCE),
                     ])),
                     ("Foundation", HashMap::from([
-                        ("+[NSJSONSerialization JSONObjectWithStream:options:error:]", JSON_DECODE),
-                        ("+[NSJSONSerialization writeJSONObject:toStream:options:error:]", JSON_ENCODE),
-                        ("+[NSRegularExpression regularExpressionWithPattern:options:error:]", REGEX),
-                        ("-[NSRegularExpression initWithPattern:options:error:]", REGEX),
-                        ("-[NSRegularExpression(NSMatching) enumerateMatchesInString:options:range:usingBlock:]", REGEX),
-                        ("Regex.firstMatch(in: String)", REGEX),
-                        ("Regex.wholeMatch(in: String)", REGEX),
-                        ("Regex.prefixMatch(in: String)", REGEX),
                         ("+[NSURLConnection sendSynchronousRequest:returningResponse:error:]", HTTP),
                         ("-[NSData(NSData) initWithContentsOfMappedFile:]", FILE_READ),
                         ("-[NSData(NSData) initWithContentsOfURL:]", FILE_READ),
@@ -321,14 +388,11 @@ pub static DETECT_FRAME_JOBS: Lazy<
                         ("-[NSISEngine performModifications:withUnsatisfiableConstraintsHandler:]", VIEW_LAYOUT),
                         ("@nonobjc NSData.init(contentsOf: URL, options: NSDataReadingOptions)", FILE_READ),
                         ("Data.init(contentsOf: __shared URL, options: NSDataReadingOptions)", FILE_READ),
-                        ("JSONDecoder.decode<A>(_: A.Type, from: Any)", JSON_DECODE),
-                        ("JSONDecoder.decode<A>(_: A.Type, from: Data)", JSON_DECODE),
-                        ("JSONDecoder.decode<A>(_: A.Type, jsonData: Data, logErrors: Bool)", JSON_DECODE),
-                        ("-[_NSJSONReader parseData:options:error:]", JSON_ENCODE),
-                        ("JSONEncoder.encode<A>(A)", JSON_ENCODE),
                         ("NSFileManager.contents(atURL: URL)", FILE_READ),
                     ])),
                     ("ImageIO", HashMap::from([
+                        ("CGImageSourceCreateImageAtIndex", IMAGE_DECODE),
+                        ("CGImageSourceCreateThumbnailAtIndex", IMAGE_DECODE),
                         ("DecodeImageData", IMAGE_DECODE),
                         ("DecodeImageStream", IMAGE_DECODE),
                         ("GIFReadPlugin::DoDecodeImageData(IIOImageReadSession*, GlobalGIFInfo*, ReadPluginData const&, GIFPluginData const&, unsigned char*, unsigned long, std::__1::shared_ptr<GIFBufferInfo>, long*)", IMAGE_DECODE),
@@ -341,23 +405,6 @@ pub static DETECT_FRAME_JOBS: Lazy<
                         ("WebPDecode", IMAGE_DECODE),
                         ("jpeg_huff_decode", IMAGE_DECODE),
                     ])),
-                    ("libcompression.dylib", HashMap::from([
-                        ("BrotliDecoderDecompress", COMPRESSION),
-                        ("brotli_encode_buffer", COMPRESSION),
-                        ("lz4_decode", COMPRESSION),
-                        ("lz4_decode_asm", COMPRESSION),
-                        ("lzfseDecode", COMPRESSION),
-                        ("lzfseEncode", COMPRESSION),
-                        ("lzfseStreamDecode", COMPRESSION),
-                        ("lzfseStreamEncode", COMPRESSION),
-                        ("lzvnDecode", COMPRESSION),
-                        ("lzvnEncode", COMPRESSION),
-                        ("lzvnStreamDecode", COMPRESSION),
-                        ("lzvnStreamEncode", COMPRESSION),
-                        ("zlibDecodeBuffer", COMPRESSION),
-                        ("zlib_decode_buffer", COMPRESSION),
-                        ("zlib_encode_buffer", COMPRESSION),
-                    ])),
                     ("libsqlite3.dylib", HashMap::from([
                         ("sqlite3_blob_read", SQL),
                         ("sqlite3_column_blob", SQL),
@@ -384,10 +431,6 @@ pub static DETECT_FRAME_JOBS: Lazy<
                         ("NSManagedObjectContext.fetch<A>(NSFetchRequest<A>)", CORE_DATA_READ),
                         ("NSManagedObjectContext.perform<A>(schedule: NSManagedObjectContext.ScheduledTaskType, _: ())", CORE_DATA_BLOCK),
                     ])),
-                    ("libswiftFoundation.dylib", HashMap::from([
-                        ("__JSONDecoder.decode<A>(A.Type)", JSON_DECODE),
-                        ("__JSONEncoder.encode<A>(A)", JSON_ENCODE),
-                    ])),
                     ("libsystem_c.dylib", HashMap::from([
                         ("__fread", FILE_READ),
                         ("fread", FILE_READ),
@@ -401,36 +444,152 @@ pub static DETECT_FRAME_JOBS: Lazy<
                         ("ViewRendererHost.updateViewGraph<A>(body: (ViewGraph))", VIEW_UPDATE),
                     ])),
                     ("UIKit", HashMap::from([
+                        ("+[UIImage imageNamed:]", IMAGE_DECODE),
+                        ("+[UIImage imageWithContentsOfFile:]", IMAGE_DECODE),
+                        ("+[UIImage imageWithData:]", IMAGE_DECODE),
+                        ("-[UIImage initWithContentsOfFile:]", IMAGE_DECODE),
+                        ("-[UIImage initWithData:]", IMAGE_DECODE),
                         ("-[_UIPathLazyImageAsset imageWithConfiguration:]", IMAGE_DECODE),
                         ("-[UINib instantiateWithOwner:options:]", VIEW_INFLATION),
                     ])),
                 ]),
-            }) as Box<dyn DetectFrameOptions + Send + Sync>,
+            }) as FrameJob),
+            // JSON serialization on the main thread
+            ("cocoa_json", Arc::new(DetectExactFrameOptions {
+                detection_thread: DetectionThread::MainThread,
+                duration_threshold: Duration::from_millis(16),
+                sample_threshold: 4,
+                functions_by_package: HashMap::from([
+                    ("Foundation", HashMap::from([
+                        ("+[NSJSONSerialization JSONObjectWithData:options:error:]", JSON_DECODE),
+                        ("+[NSJSONSerialization JSONObjectWithStream:options:error:]", JSON_DECODE),
+                        ("+[NSJSONSerialization dataWithJSONObject:options:error:]", JSON_ENCODE),
+                        ("+[NSJSONSerialization writeJSONObject:toStream:options:error:]", JSON_ENCODE),
+                        ("-[_NSJSONReader parseData:options:error:]", JSON_DECODE),
+                        ("JSONDecoder.decode<A>(_: A.Type, from: Any)", JSON_DECODE),
+                        ("JSONDecoder.decode<A>(_: A.Type, from: Data)", JSON_DECODE),
+                        ("JSONDecoder.decode<A>(_: A.Type, jsonData: Data, logErrors: Bool)", JSON_DECODE),
+                        ("JSONEncoder.encode<A>(A)", JSON_ENCODE),
+                    ])),
+                    ("libswiftFoundation.dylib", HashMap::from([
+                        ("__JSONDecoder.decode<A>(A.Type)", JSON_DECODE),
+                        ("__JSONEncoder.encode<A>(A)", JSON_ENCODE),
+                    ])),
+                ]),
+            }) as FrameJob),
+            // Synchronous network requests on the main thread
+            ("cocoa_network_main_thread", Arc::new(DetectExactFrameOptions {
+                detection_thread: DetectionThread::MainThread,
+                duration_threshold: Duration::from_millis(16),
+                sample_threshold: 2,
+                functions_by_package: HashMap::from([
+                    ("CoreFoundation", HashMap::from([
+                        ("CFURLConnectionSendSynchronousRequest", NETWORK_MAIN_THREAD),
+                    ])),
+                    ("Foundation", HashMap::from([
+                        ("+[NSURLConnection sendSynchronousRequest:returningResponse:error:]", NETWORK_MAIN_THREAD),
+                    ])),
+                    ("libcurl.4.dylib", HashMap::from([
+                        ("curl_easy_perform", NETWORK_MAIN_THREAD),
+                    ])),
+                ]),
+            }) as FrameJob),
+            // Database queries and writes on the main thread
+            ("cocoa_db_main_thread", Arc::new(DetectExactFrameOptions {
+                detection_thread: DetectionThread::MainThread,
+                duration_threshold: Duration::from_millis(16),
+                sample_threshold: 2,
+                functions_by_package: HashMap::from([
+                    ("CoreData", HashMap::from([
+                        ("-[NSManagedObjectContext save:]", DB_MAIN_THREAD),
+                    ])),
+                    ("libsqlite3.dylib", HashMap::from([
+                        ("sqlite3_step", DB_MAIN_THREAD),
+                    ])),
+                ]),
+            }) as FrameJob),
+            // Compression and decompression on the main thread
+            ("cocoa_compression", Arc::new(DetectExactFrameOptions {
+                detection_thread: DetectionThread::MainThread,
+                duration_threshold: Duration::from_millis(16),
+                sample_threshold: 4,
+                functions_by_package: HashMap::from([
+                    ("libbz2.1.0.dylib", HashMap::from([
+                        ("BZ2_bzBuffToBuffCompress", COMPRESSION),
+                        ("BZ2_bzBuffToBuffDecompress", DECOMPRESSION),
+                        ("BZ2_bzCompress", COMPRESSION),
+                        ("BZ2_bzDecompress", DECOMPRESSION),
+                    ])),
+                    ("libcompression.dylib", HashMap::from([
+                        ("BrotliDecoderDecompress", DECOMPRESSION),
+                        ("brotli_encode_buffer", COMPRESSION),
+                        ("compression_decode_buffer", DECOMPRESSION),
+                        ("compression_encode_buffer", COMPRESSION),
+                        ("lz4_decode", DECOMPRESSION),
+                        ("lz4_decode_asm", DECOMPRESSION),
+                        ("lzfseDecode", DECOMPRESSION),
+                        ("lzfseEncode", COMPRESSION),
+                        ("lzfseStreamDecode", DECOMPRESSION),
+                        ("lzfseStreamEncode", COMPRESSION),
+                        ("lzvnDecode", DECOMPRESSION),
+                        ("lzvnEncode", COMPRESSION),
+                        ("lzvnStreamDecode", DECOMPRESSION),
+                        ("lzvnStreamEncode", COMPRESSION),
+                        ("zlibDecodeBuffer", DECOMPRESSION),
+                        ("zlib_decode_buffer", DECOMPRESSION),
+                        ("zlib_encode_buffer", COMPRESSION),
+                    ])),
+                    ("libz.1.dylib", HashMap::from([
+                        ("compress", COMPRESSION),
+                        ("compress2", COMPRESSION),
+                        ("deflate", COMPRESSION),
+                        ("inflate", DECOMPRESSION),
+                        ("uncompress", DECOMPRESSION),
+                        ("uncompress2", DECOMPRESSION),
+                    ])),
+                ]),
+            }) as FrameJob),
+            // Base64 encoding and decoding on the main thread
+            ("cocoa_base64", Arc::new(DetectExactFrameOptions {
+                detection_thread: DetectionThread::MainThread,
+                duration_threshold: Duration::from_millis(8),
+                sample_threshold: 2,
+                functions_by_package: HashMap::from([
+                    ("Foundation", HashMap::from([
+                        ("-[NSData(NSDataBase64Encoding) base64EncodedDataWithOptions:]", BASE64_ENCODE),
+                        ("-[NSData(NSDataBase64Encoding) base64EncodedStringWithOptions:]", BASE64_ENCODE),
+                        ("-[NSData(NSDataBase64Encoding) initWithBase64EncodedData:options:]", BASE64_DECODE),
+                        ("-[NSData(NSDataBase64Encoding) initWithBase64EncodedString:options:]", BASE64_DECODE),
+                    ])),
+                ]),
+            }) as FrameJob),
+            // Regex compilation and matching on the main thread
+            ("cocoa_regex", Arc::new(DetectExactFrameOptions {
+                detection_thread: DetectionThread::MainThread,
+                duration_threshold: Duration::from_millis(8),
+                sample_threshold: 2,
+                functions_by_package: HashMap::from([
+                    ("Foundation", HashMap::from([
+                        ("+[NSRegularExpression regularExpressionWithPattern:options:error:]", REGEX),
+                        ("-[NSRegularExpression initWithPattern:options:error:]", REGEX),
+                        ("-[NSRegularExpression(NSMatching) enumerateMatchesInString:options:range:usingBlock:]", REGEX),
+                        ("-[NSRegularExpression(NSMatching) firstMatchInString:options:range:]", REGEX),
+                        ("-[NSRegularExpression(NSMatching) matchesInString:options:range:]", REGEX),
+                        ("-[NSRegularExpression(NSMatching) numberOfMatchesInString:options:range:]", REGEX),
+                        ("Regex.firstMatch(in: String)", REGEX),
+                        ("Regex.wholeMatch(in: String)", REGEX),
+                        ("Regex.prefixMatch(in: String)", REGEX),
+                    ])),
+                ]),
+            }) as FrameJob),
         ]),
         // Android platform
         ("android".to_string(), vec![
-            Box::new(DetectAndroidFrameOptions {
+            ("android_main_thread", Arc::new(DetectAndroidFrameOptions {
                 detection_thread: DetectionThread::ActiveThread,
                 duration_threshold: Duration::from_millis(40),
                 sample_threshold: 1,
                 functions_by_package: HashMap::from([
-                    ("com.google.gson", HashMap::from([
-                        ("com.google.gson.Gson.fromJson", JSON_DECODE),
-                        ("com.google.gson.Gson.toJson", JSON_ENCODE),
-                        ("com.google.gson.Gson.toJsonTree", JSON_ENCODE),
-                    ])),
-                    ("org.json", HashMap::from([
-                        ("org.json.JSONArray.get", JSON_DECODE),
-                        ("org.json.JSONArray.opt", JSON_DECODE),
-                        ("org.json.JSONArray.writeTo", JSON_ENCODE),
-                        ("org.json.JSONObject.checkName", JSON_DECODE),
-                        ("org.json.JSONObject.get", JSON_DECODE),
-                        ("org.json.JSONObject.opt", JSON_DECODE),
-                        ("org.json.JSONObject.put", JSON_ENCODE),
-                        ("org.json.JSONObject.putOpt", JSON_ENCODE),
-                        ("org.json.JSONObject.remove", JSON_ENCODE),
-                        ("org.json.JSONObject.writeTo", JSON_ENCODE),
-                    ])),
                     ("android.content.res", HashMap::from([
                         ("android.content.res.AssetManager.open", FILE_READ),
                         ("android.content.res.AssetManager.openFd", FILE_READ),
@@ -464,7 +623,11 @@ pub static DETECT_FRAME_JOBS: Lazy<
                         ("android.graphics.BitmapFactory.decodeByteArray", IMAGE_DECODE),
                         ("android.graphics.BitmapFactory.decodeFile", IMAGE_DECODE),
                         ("android.graphics.BitmapFactory.decodeFileDescriptor", IMAGE_DECODE),
+                        ("android.graphics.BitmapFactory.decodeResource", IMAGE_DECODE),
+                        ("android.graphics.BitmapFactory.decodeResourceStream", IMAGE_DECODE),
                         ("android.graphics.BitmapFactory.decodeStream", IMAGE_DECODE),
+                        ("android.graphics.ImageDecoder.decodeBitmap", IMAGE_DECODE),
+                        ("android.graphics.ImageDecoder.decodeDrawable", IMAGE_DECODE),
                     ])),
                     ("android.database.sqlite", HashMap::from([
                         ("android.database.sqlite.SQLiteDatabase.insertWithOnConflict", SQL),
@@ -479,63 +642,289 @@ pub static DETECT_FRAME_JOBS: Lazy<
                     ("androidx.room", HashMap::from([
                         ("androidx.room.RoomDatabase.query", SQL),
                     ])),
+                    ("kotlinx.coroutines", HashMap::from([
+                        ("kotlinx.coroutines.AwaitAll.await", THREAD_WAIT),
+                        ("kotlinx.coroutines.AwaitKt.awaitAll", THREAD_WAIT),
+                        ("kotlinx.coroutines.BlockingCoroutine.joinBlocking", THREAD_WAIT),
+                        ("kotlinx.coroutines.JobSupport.join", THREAD_WAIT),
+                        ("kotlinx.coroutines.JobSupport.joinSuspend", THREAD_WAIT),
+                    ])),
+                ]),
+            }) as FrameJob),
+            // JSON serialization on the main thread
+            ("android_json", Arc::new(DetectAndroidFrameOptions {
+                detection_thread: DetectionThread::ActiveThread,
+                duration_threshold: Duration::from_millis(40),
+                sample_threshold: 1,
+                functions_by_package: HashMap::from([
+                    ("com.google.gson", HashMap::from([
+                        ("com.google.gson.Gson.fromJson", JSON_DECODE),
+                        ("com.google.gson.Gson.toJson", JSON_ENCODE),
+                        ("com.google.gson.Gson.toJsonTree", JSON_ENCODE),
+                    ])),
+                    ("org.json", HashMap::from([
+                        // constructors parse the whole document
+                        ("org.json.JSONArray", JSON_DECODE),
+                        ("org.json.JSONArray.get", JSON_DECODE),
+                        ("org.json.JSONArray.opt", JSON_DECODE),
+                        ("org.json.JSONArray.writeTo", JSON_ENCODE),
+                        ("org.json.JSONObject", JSON_DECODE),
+                        ("org.json.JSONObject.checkName", JSON_DECODE),
+                        ("org.json.JSONObject.get", JSON_DECODE),
+                        ("org.json.JSONObject.opt", JSON_DECODE),
+                        ("org.json.JSONObject.put", JSON_ENCODE),
+                        ("org.json.JSONObject.putOpt", JSON_ENCODE),
+                        ("org.json.JSONObject.remove", JSON_ENCODE),
+                        ("org.json.JSONObject.toString", JSON_ENCODE),
+                        ("org.json.JSONObject.writeTo", JSON_ENCODE),
+                        ("org.json.JSONTokener.nextValue", JSON_DECODE),
+                    ])),
+                ]),
+            }) as FrameJob),
+            // Synchronous network requests on the main thread
+            ("android_network_main_thread", Arc::new(DetectAndroidFrameOptions {
+                detection_thread: DetectionThread::ActiveThread,
+                duration_threshold: Duration::from_millis(40),
+                sample_threshold: 1,
+                functions_by_package: HashMap::from([
+                    // implementation of java.net.HttpURLConnection on android
+                    ("com.android.okhttp.internal.huc", HashMap::from([
+                        ("com.android.okhttp.internal.huc.DelegatingHttpsURLConnection.getInputStream", NETWORK_MAIN_THREAD),
+                        ("com.android.okhttp.internal.huc.HttpURLConnectionImpl.getInputStream", NETWORK_MAIN_THREAD),
+                        ("com.android.okhttp.internal.huc.HttpURLConnectionImpl.getResponseCode", NETWORK_MAIN_THREAD),
+                    ])),
+                    ("java.net", HashMap::from([
+                        ("java.net.HttpURLConnection.getResponseCode", NETWORK_MAIN_THREAD),
+                        ("java.net.URL.openStream", NETWORK_MAIN_THREAD),
+                    ])),
+                ]),
+            }) as FrameJob),
+            // Database queries and writes on the main thread
+            ("android_db_main_thread", Arc::new(DetectAndroidFrameOptions {
+                detection_thread: DetectionThread::ActiveThread,
+                duration_threshold: Duration::from_millis(40),
+                sample_threshold: 1,
+                functions_by_package: HashMap::from([
+                    ("android.database.sqlite", HashMap::from([
+                        ("android.database.sqlite.SQLiteDatabase.delete", DB_MAIN_THREAD),
+                        ("android.database.sqlite.SQLiteDatabase.execSQL", DB_MAIN_THREAD),
+                        ("android.database.sqlite.SQLiteDatabase.insertWithOnConflict", DB_MAIN_THREAD),
+                        ("android.database.sqlite.SQLiteDatabase.open", DB_MAIN_THREAD),
+                        ("android.database.sqlite.SQLiteDatabase.query", DB_MAIN_THREAD),
+                        ("android.database.sqlite.SQLiteDatabase.rawQueryWithFactory", DB_MAIN_THREAD),
+                        ("android.database.sqlite.SQLiteDatabase.updateWithOnConflict", DB_MAIN_THREAD),
+                        ("android.database.sqlite.SQLiteStatement.execute", DB_MAIN_THREAD),
+                        ("android.database.sqlite.SQLiteStatement.executeInsert", DB_MAIN_THREAD),
+                        ("android.database.sqlite.SQLiteStatement.executeUpdateDelete", DB_MAIN_THREAD),
+                        ("android.database.sqlite.SQLiteStatement.simpleQueryForLong", DB_MAIN_THREAD),
+                    ])),
+                ]),
+            }) as FrameJob),
+            // Layouts inflated and measured on the main thread
+            ("android_view_inflation", Arc::new(DetectAndroidFrameOptions {
+                detection_thread: DetectionThread::MainThread,
+                duration_threshold: Duration::from_millis(16),
+                sample_threshold: 1,
+                functions_by_package: HashMap::from([
+                    ("android.content.res", HashMap::from([
+                        ("android.content.res.Resources.getLayout", VIEW_INFLATION),
+                    ])),
+                    ("android.view", HashMap::from([
+                        ("android.view.LayoutInflater.inflate", VIEW_INFLATION),
+                        ("android.view.View.layout", VIEW_INFLATION),
+                        ("android.view.View.measure", VIEW_INFLATION),
+                    ])),
+                ]),
+            }) as FrameJob),
+            // Compression and decompression on the main thread
+            ("android_compression", Arc::new(DetectAndroidFrameOptions {
+                detection_thread: DetectionThread::ActiveThread,
+                duration_threshold: Duration::from_millis(40),
+                sample_threshold: 1,
+                functions_by_package: HashMap::from([
                     ("java.util.zip", HashMap::from([
                         ("java.util.zip.Deflater.deflate", COMPRESSION),
                         ("java.util.zip.Deflater.deflateBytes", COMPRESSION),
                         ("java.util.zip.DeflaterOutputStream.write", COMPRESSION),
-                        ("java.util.zip.GZIPInputStream.read", COMPRESSION),
+                        ("java.util.zip.GZIPInputStream.read", DECOMPRESSION),
                         ("java.util.zip.GZIPOutputStream.write", COMPRESSION),
-                        ("java.util.zip.Inflater.inflate", COMPRESSION),
-                        ("java.util.zip.Inflater.inflateBytes", COMPRESSION),
+                        ("java.util.zip.Inflater.inflate", DECOMPRESSION),
+                        ("java.util.zip.Inflater.inflateBytes", DECOMPRESSION),
+                        ("java.util.zip.InflaterInputStream.read", DECOMPRESSION),
+                    ])),
+                    ("org.brotli.dec", HashMap::from([
+                        ("org.brotli.dec.BrotliInputStream.read", DECOMPRESSION),
+                    ])),
+                ]),
+            }) as FrameJob),
+            // Base64 encoding and decoding on the main thread
+            ("android_base64", Arc::new(DetectAndroidFrameOptions {
+                detection_thread: DetectionThread::ActiveThread,
+                duration_threshold: Duration::from_millis(20),
+                sample_threshold: 1,
+                functions_by_package: HashMap::from([
+                    ("android.util", HashMap::from([
+                        ("android.util.Base64.decode", BASE64_DECODE),
+                        ("android.util.Base64.encode", BASE64_ENCODE),
+                        ("android.util.Base64.encodeToString", BASE64_ENCODE),
                     ])),
                     ("java.util", HashMap::from([
                         ("java.util.Base64$Decoder.decode", BASE64_DECODE),
                         ("java.util.Base64$Decoder.decode0", BASE64_DECODE),
+                        ("java.util.Base64$Encoder.encode", BASE64_ENCODE),
+                        ("java.util.Base64$Encoder.encode0", BASE64_ENCODE),
+                        ("java.util.Base64$Encoder.encodeToString", BASE64_ENCODE),
                     ])),
+                ]),
+            }) as FrameJob),
+            // Regex compilation and matching on the main thread
+            ("android_regex", Arc::new(DetectAndroidFrameOptions {
+                detection_thread: DetectionThread::ActiveThread,
+                duration_threshold: Duration::from_millis(20),
+                sample_threshold: 1,
+                functions_by_package: HashMap::from([
                     ("java.util.regex", HashMap::from([
-                        ("java.util.regex.Matcher.matches", REGEX),
                         ("java.util.regex.Matcher.find", REGEX),
                         ("java.util.regex.Matcher.lookingAt", REGEX),
-                    ])),
-                    ("kotlinx.coroutines", HashMap::from([
-                        ("kotlinx.coroutines.AwaitAll.await", THREAD_WAIT),
-                        ("kotlinx.coroutines.AwaitKt.awaitAll", THREAD_WAIT),
-                        ("kotlinx.coroutines.BlockingCoroutine.joinBlocking", THREAD_WAIT),
-                        ("kotlinx.coroutines.JobSupport.join", THREAD_WAIT),
-                        ("kotlinx.coroutines.JobSupport.joinSuspend", THREAD_WAIT),
+                        ("java.util.regex.Matcher.matches", REGEX),
+                        ("java.util.regex.Pattern.compile", REGEX),
+                        ("java.util.regex.Pattern.matches", REGEX),
                     ])),
                 ]),
-            }) as Box<dyn DetectFrameOptions + Send + Sync>,
+            }) as FrameJob),
         ])
     ])
 });
 
+/// A detector running a frame detection job over the profiles of a platform.
+pub struct FrameDetector {
+    name: String,
+    platform: String,
+    job: FrameJob,
+}
+
+impl FrameDetector {
+    pub fn new(name: &str, platform: &str, job: FrameJob) -> Self {
+        FrameDetector {
+            name: name.to_string(),
+            platform: platform.to_string(),
+            job,
+        }
+    }
+}
+
+impl Detector for FrameDetector {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn categories(&self) -> Vec<&str> {
+        self.job.categories()
+    }
+
+    fn platforms(&self) -> Vec<&str> {
+        vec![self.platform.as_str()]
+    }
+
+    fn run(
+        &self,
+        call_trees: &CallTreesU64,
+        profile: &dyn ProfileInterface,
+        _options: &DetectorOptions,
+        occurrences: &mut Vec<super::Occurrence>,
+    ) {
+        detect_frame(profile, call_trees, self.job.as_ref(), occurrences);
+    }
+}
+
+/// Returns the detectors of the built-in frame detection jobs, sorted by
+/// platform then in the order of [`DETECT_FRAME_JOBS`].
+pub(crate) fn frame_detectors() -> Vec<FrameDetector> {
+    let mut platforms: Vec<&String> = DETECT_FRAME_JOBS.keys().collect();
+    platforms.sort();
+    platforms
+        .into_iter()
+        .flat_map(|platform| {
+            DETECT_FRAME_JOBS[platform]
+                .iter()
+                .map(|(name, job)| FrameDetector::new(name, platform, job.clone()))
+        })
+        .collect()
+}
+
+/// Returns a detector of the given frames, named after its platform and
+/// category (e.g. "cocoa_custom_parse").
+pub(crate) fn registered_frame_detector(
+    category: &str,
+    platform: &str,
+    frame_names: &[String],
+    threshold_ns: u64,
+    only_main_thread: bool,
+) -> FrameDetector {
+    let options = DetectRegisteredFrameOptions {
+        category: category.to_string(),
+        detection_thread: if only_main_thread {
+            DetectionThread::MainThread
+        } else {
+            DetectionThread::AllThreads
+        },
+        duration_threshold: Duration::from_nanos(threshold_ns),
+        frame_names: frame_names.iter().cloned().collect(),
+    };
+    FrameDetector::new(
+        &format!("{platform}_{category}"),
+        platform,
+        Arc::new(options),
+    )
+}
+
+/// Registers a frame detector for the given platform, so detection can be
+/// tuned without shipping a new release. Registering it again replaces it.
+pub(crate) fn register_frame_detector(
+    category: &str,
+    platform: &str,
+    frame_names: &[String],
+    threshold_ns: u64,
+    only_main_thread: bool,
+) -> Result<(), DetectorError> {
+    register_detector(Box::new(registered_frame_detector(
+        category,
+        platform,
+        frame_names,
+        threshold_ns,
+        only_main_thread,
+    )))
+}
+
 /// Detects frames in a call tree starting from the root node.
 pub(crate) fn detect_frame_in_call_tree(
-    node: &Rc<RefCell<Node>>,
+    call_tree: &CallTree,
+    root: NodeId,
     options: &dyn DetectFrameOptions,
     nodes: &mut HashMap<NodeKey, NodeInfo>,
 ) {
-    let mut stack_trace: Vec<Frame> = Vec::with_capacity(MAX_STACK_DEPTH as usize);
-    detect_frame_in_node(node, options, nodes, &mut stack_trace);
+    let mut stack_trace: Vec<Frame> = Vec::with_capacity(max_stack_depth() as usize);
+    detect_frame_in_node(call_tree, root, options, nodes, &mut stack_trace);
 }
 
 /// Recursively detects frames in a node and its children, building up a stack trace.
 /// Returns Some(NodeInfo) if a matching node is found, None otherwise.
 fn detect_frame_in_node(
-    node: &Rc<RefCell<Node>>,
+    call_tree: &CallTree,
+    id: NodeId,
     options: &dyn DetectFrameOptions,
     nodes: &mut HashMap<NodeKey, NodeInfo>,
     stack_trace: &mut Vec<Frame>,
 ) -> Option<NodeInfo> {
-    let borrowed_node = node.borrow();
+    let node = &call_tree[id];
 
     // Add current node's frame to stack trace
-    stack_trace.push(borrowed_node.to_frame());
+    stack_trace.push(node.to_frame());
 
     // Recursively check all children first
-    for child in &borrowed_node.children {
-        if let Some(node_info) = detect_frame_in_node(child, options, nodes, stack_trace) {
+    for &child in call_tree.children(id) {
+        if let Some(node_info) = detect_frame_in_node(call_tree, child, options, nodes, stack_trace)
+        {
             // Pop the current frame before returning (mimicking defer)
             stack_trace.pop();
             return Some(node_info);
@@ -543,7 +932,7 @@ fn detect_frame_in_node(
     }
 
     // Check if current node matches criteria after children
-    let result = if let Some(mut node_info) = options.check_node(&borrowed_node) {
+    let result = if let Some(mut node_info) = options.check_node(node) {
         let key = NodeKey {
             package: node_info.node.package.clone(),
             function: node_info.node.name.clone(),
@@ -577,9 +966,9 @@ pub fn detect_frame(
     let mut nodes: HashMap<NodeKey, NodeInfo> = HashMap::new();
 
     let detect_in_thread = |thread_id: u64, nodes: &mut HashMap<NodeKey, NodeInfo>| {
-        if let Some(call_trees) = call_trees_per_thread_id.get(&thread_id) {
-            for root in call_trees {
-                detect_frame_in_call_tree(root, options, nodes);
+        if let Some(call_tree) = call_trees_per_thread_id.get(&thread_id) {
+            for &root in call_tree.roots() {
+                detect_frame_in_call_tree(call_tree, root, options, nodes);
             }
         }
     };
@@ -597,9 +986,9 @@ pub fn detect_frame(
             detect_in_thread(thread_id, &mut nodes);
         }
         DetectionThread::AllThreads => {
-            for call_trees in call_trees_per_thread_id.values() {
-                for root in call_trees {
-                    detect_frame_in_call_tree(root, options, &mut nodes);
+            for call_tree in call_trees_per_thread_id.values() {
+                for &root in call_tree.roots() {
+                    detect_frame_in_call_tree(call_tree, root, options, &mut nodes);
                 }
             }
         }
@@ -613,14 +1002,16 @@ pub fn detect_frame(
 
 #[cfg(test)]
 mod tests {
-    use std::{cell::RefCell, collections::HashMap, rc::Rc, time::Duration};
+    use std::{collections::HashMap, time::Duration};
 
     use crate::{
         frame::Frame,
-        nodetree::Node,
+        nodetree::{CallTree, NestedNode, Node},
         occurrence::detect_frame::{
             detect_frame_in_call_tree, DetectAndroidFrameOptions, DetectExactFrameOptions,
-            DetectFrameOptions, DetectionThread, NodeInfo, NodeKey, FILE_READ, IMAGE_DECODE,
+            DetectFrameOptions, DetectionThread, NodeInfo, NodeKey, BASE64_ENCODE, COMPRESSION,
+            DB_MAIN_THREAD, DECOMPRESSION, FILE_READ, HTTP, IMAGE_DECODE, JSON_DECODE,
+            NETWORK_MAIN_THREAD, REGEX,
         },
     };
 
@@ -631,7 +1022,7 @@ mod tests {
         struct TestStruct {
             name: String,
             job: Box<dyn DetectFrameOptions>,
-            node: Rc<RefCell<Node>>,
+            node: NestedNode,
             want: HashMap<NodeKey, NodeInfo>,
         }
 
@@ -647,54 +1038,29 @@ mod tests {
                     ]),
                     ..Default::default()
                 }),
-                node: Rc::new(RefCell::new(Node {
-                    children: vec![
-                        Rc::new(RefCell::new(Node {
-                            children: vec![
-                                Rc::new(RefCell::new(Node {
-                                    children: vec![
-                                        Rc::new(RefCell::new(Node {
-                                            children: vec![],
-                                            duration_ns: 20_000_000, // 20 * time.Millisecond
-                                            end_ns: 20_000_000,
-                                            fingerprint: 0,
-                                            is_application: false,
-                                            line: Some(0),
-                                            name: "CFReadStreamRead".to_string(),
-                                            package: "CoreFoundation".to_string(),
-                                            path: Some("path".to_string()),
-                                            sample_count: 4,
-                                            start_ns: 0,
-                                            frame: Frame {
-                                                function: Some("CFReadStreamRead".to_string()),
-                                                in_app: Some(false),
-                                                line: Some(0),
-                                                package: Some("CoreFoundation".to_string()),
-                                                path: Some("path".to_string()),
-                                                ..Default::default()
-                                            },
-                                        }))
-                                    ],
-                                    duration_ns: 20_000_000,
-                                    end_ns: 20_000_000,
-                                    fingerprint: 0,
-                                    is_application: true,
-                                    line: Some(0),
-                                    name: "child2-1".to_string(),
-                                    package: "package".to_string(),
-                                    path: Some("path".to_string()),
-                                    sample_count: 1,
-                                    start_ns: 0,
-                                    frame: Frame {
-                                        function: Some("child2-1".to_string()),
-                                        in_app: Some(true),
-                                        line: Some(0),
-                                        package: Some("package".to_string()),
-                                        path: Some("path".to_string()),
-                                        ..Default::default()
-                                    },
-                                }))
-                            ],
+                node: NestedNode(Node {
+                    duration_ns: 30_000_000, // 30 * time.Millisecond
+                    end_ns: 30_000_000,
+                    fingerprint: 0,
+                    is_application: true,
+                    line: Some(0),
+                    name: "root".to_string(),
+                    package: "package".to_string(),
+                    path: Some("path".to_string()),
+                    sample_count: 1,
+                    start_ns: 0,
+                    recursion_count: 0,
+                    truncated: false,
+                    frame: Frame {
+                        function: Some("root".to_string()),
+                        in_app: Some(true),
+                        line: Some(0),
+                        package: Some("package".to_string()),
+                        path: Some("path".to_string()),
+                        ..Default::default()
+                    }.into(),
+                }, vec![
+                        NestedNode(Node {
                             duration_ns: 20_000_000,
                             end_ns: 20_000_000,
                             fingerprint: 0,
@@ -705,6 +1071,8 @@ mod tests {
                             path: Some("path".to_string()),
                             sample_count: 1,
                             start_ns: 0,
+                            recursion_count: 0,
+                            truncated: false,
                             frame: Frame {
                                 function: Some("child1-1".to_string()),
                                 in_app: Some(false),
@@ -712,36 +1080,11 @@ mod tests {
                                 package: Some("package".to_string()),
                                 path: Some("path".to_string()),
                                 ..Default::default()
-                            },
-                        })),
-                        Rc::new(RefCell::new(Node {
-                            children: vec![
-                                Rc::new(RefCell::new(Node {
-                                    children: vec![
-                                        Rc::new(RefCell::new(Node {
-                                            children: vec![],
-                                            duration_ns: 5,
-                                            end_ns: 10,
-                                            fingerprint: 0,
-                                            is_application: false,
-                                            line: Some(0),
-                                            name: "child3-1".to_string(),
-                                            package: "package".to_string(),
-                                            path: Some("path".to_string()),
-                                            sample_count: 1,
-                                            start_ns: 5,
-                                            frame: Frame {
-                                                function: Some("child3-1".to_string()),
-                                                in_app: Some(false),
-                                                line: Some(0),
-                                                package: Some("package".to_string()),
-                                                path: Some("path".to_string()),
-                                                ..Default::default()
-                                            },
-                                        }))
-                                    ],
-                                    duration_ns: 5,
-                                    end_ns: 10,
+                            }.into(),
+                        }, vec![
+                                NestedNode(Node {
+                                    duration_ns: 20_000_000,
+                                    end_ns: 20_000_000,
                                     fingerprint: 0,
                                     is_application: true,
                                     line: Some(0),
@@ -749,7 +1092,9 @@ mod tests {
                                     package: "package".to_string(),
                                     path: Some("path".to_string()),
                                     sample_count: 1,
-                                    start_ns: 5,
+                                    start_ns: 0,
+                                    recursion_count: 0,
+                                    truncated: false,
                                     frame: Frame {
                                         function: Some("child2-1".to_string()),
                                         in_app: Some(true),
@@ -757,9 +1102,33 @@ mod tests {
                                         package: Some("package".to_string()),
                                         path: Some("path".to_string()),
                                         ..Default::default()
-                                    },
-                                }))
-                            ],
+                                    }.into(),
+                                }, vec![
+                                        NestedNode(Node {
+                                            duration_ns: 20_000_000, // 20 * time.Millisecond
+                                            end_ns: 20_000_000,
+                                            fingerprint: 0,
+                                            is_application: false,
+                                            line: Some(0),
+                                            name: "CFReadStreamRead".to_string(),
+                                            package: "CoreFoundation".to_string(),
+                                            path: Some("path".to_string()),
+                                            sample_count: 4,
+                                            start_ns: 0,
+                                            recursion_count: 0,
+                                            truncated: false,
+                                            frame: Frame {
+                                                function: Some("CFReadStreamRead".to_string()),
+                                                in_app: Some(false),
+                                                line: Some(0),
+                                                package: Some("CoreFoundation".to_string()),
+                                                path: Some("path".to_string()),
+                                                ..Default::default()
+                                            }.into(),
+                                        }, vec![])
+                                    ])
+                            ]),
+                        NestedNode(Node {
                             duration_ns: 5,
                             end_ns: 10,
                             fingerprint: 0,
@@ -770,6 +1139,8 @@ mod tests {
                             path: Some("path".to_string()),
                             sample_count: 1,
                             start_ns: 5,
+                            recursion_count: 0,
+                            truncated: false,
                             frame: Frame {
                                 function: Some("child1-2".to_string()),
                                 in_app: Some(false),
@@ -777,28 +1148,55 @@ mod tests {
                                 package: Some("package".to_string()),
                                 path: Some("path".to_string()),
                                 ..Default::default()
-                            },
-                        }))
-                    ],
-                    duration_ns: 30_000_000, // 30 * time.Millisecond
-                    end_ns: 30_000_000,
-                    fingerprint: 0,
-                    is_application: true,
-                    line: Some(0),
-                    name: "root".to_string(),
-                    package: "package".to_string(),
-                    path: Some("path".to_string()),
-                    sample_count: 1,
-                    start_ns: 0,
-                    frame: Frame {
-                        function: Some("root".to_string()),
-                        in_app: Some(true),
-                        line: Some(0),
-                        package: Some("package".to_string()),
-                        path: Some("path".to_string()),
-                        ..Default::default()
-                    },
-                })),
+                            }.into(),
+                        }, vec![
+                                NestedNode(Node {
+                                    duration_ns: 5,
+                                    end_ns: 10,
+                                    fingerprint: 0,
+                                    is_application: true,
+                                    line: Some(0),
+                                    name: "child2-1".to_string(),
+                                    package: "package".to_string(),
+                                    path: Some("path".to_string()),
+                                    sample_count: 1,
+                                    start_ns: 5,
+                                    recursion_count: 0,
+                                    truncated: false,
+                                    frame: Frame {
+                                        function: Some("child2-1".to_string()),
+                                        in_app: Some(true),
+                                        line: Some(0),
+                                        package: Some("package".to_string()),
+                                        path: Some("path".to_string()),
+                                        ..Default::default()
+                                    }.into(),
+                                }, vec![
+                                        NestedNode(Node {
+                                            duration_ns: 5,
+                                            end_ns: 10,
+                                            fingerprint: 0,
+                                            is_application: false,
+                                            line: Some(0),
+                                            name: "child3-1".to_string(),
+                                            package: "package".to_string(),
+                                            path: Some("path".to_string()),
+                                            sample_count: 1,
+                                            start_ns: 5,
+                                            recursion_count: 0,
+                                            truncated: false,
+                                            frame: Frame {
+                                                function: Some("child3-1".to_string()),
+                                                in_app: Some(false),
+                                                line: Some(0),
+                                                package: Some("package".to_string()),
+                                                path: Some("path".to_string()),
+                                                ..Default::default()
+                                            }.into(),
+                                        }, vec![])
+                                    ])
+                            ])
+                    ]),
                 want: HashMap::from([
                     (
                         NodeKey {
@@ -808,7 +1206,6 @@ mod tests {
                         NodeInfo {
                             category: FILE_READ.to_string(),
                             node: Node {
-                                children: vec![], // children cleared as per the logic
                                 duration_ns: 20_000_000,
                                 end_ns: 20_000_000,
                                 fingerprint: 0,
@@ -819,6 +1216,8 @@ mod tests {
                                 path: Some("path".to_string()),
                                 sample_count: 4,
                                 start_ns: 0,
+                                recursion_count: 0,
+                                truncated: false,
                                 frame: Frame {
                                     function: Some("CFReadStreamRead".to_string()),
                                     in_app: Some(false),
@@ -826,7 +1225,7 @@ mod tests {
                                     package: Some("CoreFoundation".to_string()),
                                     path: Some("path".to_string()),
                                     ..Default::default()
-                                },
+                                }.into(),
                             },
                             stack_trace: vec![
                                 Frame {
@@ -877,34 +1276,51 @@ mod tests {
                     ]),
                     ..Default::default()
                 }),
-                node: Rc::new(RefCell::new(Node {
-                    children: vec![
-                        Rc::new(RefCell::new(Node {
-                            children: vec![
-                                Rc::new(RefCell::new(Node {
-                                    children: vec![
-                                        Rc::new(RefCell::new(Node {
-                                            children: vec![],
-                                            duration_ns: 10_000_000, // 10 * time.Millisecond - below threshold
-                                            end_ns: 10_000_000,
-                                            fingerprint: 0,
-                                            is_application: false,
-                                            line: Some(0),
-                                            name: "SuperShortFunction".to_string(),
-                                            package: "vroom".to_string(),
-                                            path: Some("path".to_string()),
-                                            sample_count: 1,
-                                            start_ns: 0,
-                                            frame: Frame {
-                                                function: Some("SuperShortFunction".to_string()),
-                                                in_app: Some(false),
-                                                line: Some(0),
-                                                package: Some("vroom".to_string()),
-                                                path: Some("path".to_string()),
-                                                ..Default::default()
-                                            },
-                                        }))
-                                    ],
+                node: NestedNode(Node {
+                    duration_ns: 30_000_000, // 30 * time.Millisecond
+                    end_ns: 30_000_000,
+                    fingerprint: 0,
+                    is_application: true,
+                    line: Some(0),
+                    name: "root".to_string(),
+                    package: "package".to_string(),
+                    path: Some("path".to_string()),
+                    sample_count: 1,
+                    start_ns: 0,
+                    recursion_count: 0,
+                    truncated: false,
+                    frame: Frame {
+                        function: Some("root".to_string()),
+                        in_app: Some(true),
+                        line: Some(0),
+                        package: Some("package".to_string()),
+                        path: Some("path".to_string()),
+                        ..Default::default()
+                    }.into(),
+                }, vec![
+                        NestedNode(Node {
+                            duration_ns: 20_000_000,
+                            end_ns: 20_000_000,
+                            fingerprint: 0,
+                            is_application: false,
+                            line: Some(0),
+                            name: "child1-1".to_string(),
+                            package: "package".to_string(),
+                            path: Some("path".to_string()),
+                            sample_count: 1,
+                            start_ns: 0,
+                            recursion_count: 0,
+                            truncated: false,
+                            frame: Frame {
+                                function: Some("child1-1".to_string()),
+                                in_app: Some(false),
+                                line: Some(0),
+                                package: Some("package".to_string()),
+                                path: Some("path".to_string()),
+                                ..Default::default()
+                            }.into(),
+                        }, vec![
+                                NestedNode(Node {
                                     duration_ns: 20_000_000,
                                     end_ns: 20_000_000,
                                     fingerprint: 0,
@@ -915,6 +1331,8 @@ mod tests {
                                     path: Some("path".to_string()),
                                     sample_count: 1,
                                     start_ns: 0,
+                                    recursion_count: 0,
+                                    truncated: false,
                                     frame: Frame {
                                         function: Some("child2-1".to_string()),
                                         in_app: Some(true),
@@ -922,9 +1340,71 @@ mod tests {
                                         package: Some("package".to_string()),
                                         path: Some("path".to_string()),
                                         ..Default::default()
-                                    },
-                                }))
-                            ],
+                                    }.into(),
+                                }, vec![
+                                        NestedNode(Node {
+                                            duration_ns: 10_000_000, // 10 * time.Millisecond - below threshold
+                                            end_ns: 10_000_000,
+                                            fingerprint: 0,
+                                            is_application: false,
+                                            line: Some(0),
+                                            name: "SuperShortFunction".to_string(),
+                                            package: "vroom".to_string(),
+                                            path: Some("path".to_string()),
+                                            sample_count: 1,
+                                            start_ns: 0,
+                                            recursion_count: 0,
+                                            truncated: false,
+                                            frame: Frame {
+                                                function: Some("SuperShortFunction".to_string()),
+                                                in_app: Some(false),
+                                                line: Some(0),
+                                                package: Some("vroom".to_string()),
+                                                path: Some("path".to_string()),
+                                                ..Default::default()
+                                            }.into(),
+                                        }, vec![])
+                                    ])
+                            ])
+                    ]),
+                want: HashMap::new(), // Empty - no nodes should be detected
+            },
+            TestStruct {
+                name: "Do not detect frame in call tree under sample threshold".to_string(),
+                job: Box::new(DetectExactFrameOptions {
+                    duration_threshold: Duration::from_millis(16),
+                    sample_threshold: 4,
+                    functions_by_package: HashMap::from([
+                        ("vroom", HashMap::from([
+                            ("FunctionWithOneSample", FILE_READ),
+                            ("FunctionWithManySamples", FILE_READ),
+                        ]))
+                    ]),
+                    ..Default::default()
+                }),
+                node: NestedNode(Node {
+                    duration_ns: 30_000_000,
+                    end_ns: 30_000_000,
+                    fingerprint: 0,
+                    is_application: true,
+                    line: Some(0),
+                    name: "root".to_string(),
+                    package: "package".to_string(),
+                    path: Some("path".to_string()),
+                    sample_count: 1,
+                    start_ns: 0,
+                    recursion_count: 0,
+                    truncated: false,
+                    frame: Frame {
+                        function: Some("root".to_string()),
+                        in_app: Some(true),
+                        line: Some(0),
+                        package: Some("package".to_string()),
+                        path: Some("path".to_string()),
+                        ..Default::default()
+                    }.into(),
+                }, vec![
+                        NestedNode(Node {
                             duration_ns: 20_000_000,
                             end_ns: 20_000_000,
                             fingerprint: 0,
@@ -935,6 +1415,8 @@ mod tests {
                             path: Some("path".to_string()),
                             sample_count: 1,
                             start_ns: 0,
+                            recursion_count: 0,
+                            truncated: false,
                             frame: Frame {
                                 function: Some("child1-1".to_string()),
                                 in_app: Some(false),
@@ -942,51 +1424,31 @@ mod tests {
                                 package: Some("package".to_string()),
                                 path: Some("path".to_string()),
                                 ..Default::default()
-                            },
-                        }))
-                    ],
-                    duration_ns: 30_000_000, // 30 * time.Millisecond
-                    end_ns: 30_000_000,
-                    fingerprint: 0,
-                    is_application: true,
-                    line: Some(0),
-                    name: "root".to_string(),
-                    package: "package".to_string(),
-                    path: Some("path".to_string()),
-                    sample_count: 1,
-                    start_ns: 0,
-                    frame: Frame {
-                        function: Some("root".to_string()),
-                        in_app: Some(true),
-                        line: Some(0),
-                        package: Some("package".to_string()),
-                        path: Some("path".to_string()),
-                        ..Default::default()
-                    },
-                })),
-                want: HashMap::new(), // Empty - no nodes should be detected
-            },
-            TestStruct {
-                name: "Do not detect frame in call tree under sample threshold".to_string(),
-                job: Box::new(DetectExactFrameOptions {
-                    duration_threshold: Duration::from_millis(16),
-                    sample_threshold: 4,
-                    functions_by_package: HashMap::from([
-                        ("vroom", HashMap::from([
-                            ("FunctionWithOneSample", FILE_READ),
-                            ("FunctionWithManySamples", FILE_READ),
-                        ]))
-                    ]),
-                    ..Default::default()
-                }),
-                node: Rc::new(RefCell::new(Node {
-                    children: vec![
-                        Rc::new(RefCell::new(Node {
-                            children: vec![
-                                Rc::new(RefCell::new(Node {
-                                    children: vec![
-                                        Rc::new(RefCell::new(Node {
-                                            children: vec![],
+                            }.into(),
+                        }, vec![
+                                NestedNode(Node {
+                                    duration_ns: 20_000_000,
+                                    end_ns: 20_000_000,
+                                    fingerprint: 0,
+                                    is_application: true,
+                                    line: Some(0),
+                                    name: "child2-1".to_string(),
+                                    package: "package".to_string(),
+                                    path: Some("path".to_string()),
+                                    sample_count: 1,
+                                    start_ns: 0,
+                                    recursion_count: 0,
+                                    truncated: false,
+                                    frame: Frame {
+                                        function: Some("child2-1".to_string()),
+                                        in_app: Some(true),
+                                        line: Some(0),
+                                        package: Some("package".to_string()),
+                                        path: Some("path".to_string()),
+                                        ..Default::default()
+                                    }.into(),
+                                }, vec![
+                                        NestedNode(Node {
                                             duration_ns: 20_000_000,
                                             end_ns: 20_000_000,
                                             fingerprint: 0,
@@ -997,6 +1459,8 @@ mod tests {
                                             path: Some("path".to_string()),
                                             sample_count: 1, // Below threshold of 4
                                             start_ns: 0,
+                                            recursion_count: 0,
+                                            truncated: false,
                                             frame: Frame {
                                                 function: Some("FunctionWithOneSample".to_string()),
                                                 in_app: Some(false),
@@ -1004,32 +1468,9 @@ mod tests {
                                                 package: Some("vroom".to_string()),
                                                 path: Some("path".to_string()),
                                                 ..Default::default()
-                                            },
-                                        })),
-                                        Rc::new(RefCell::new(Node {
-                                            children: vec![
-                                                Rc::new(RefCell::new(Node {
-                                                    children: vec![],
-                                                    duration_ns: 20_000_000,
-                                                    end_ns: 20_000_000,
-                                                    fingerprint: 0,
-                                                    is_application: false,
-                                                    line: Some(0),
-                                                    name: "FunctionWithManySamples".to_string(),
-                                                    package: "vroom".to_string(),
-                                                    path: Some("path".to_string()),
-                                                    sample_count: 4, // Meets threshold of 4
-                                                    start_ns: 0,
-                                                    frame: Frame {
-                                                        function: Some("FunctionWithManySamples".to_string()),
-                                                        in_app: Some(false),
-                                                        line: Some(0),
-                                                        package: Some("vroom".to_string()),
-                                                        path: Some("path".to_string()),
-                                                        ..Default::default()
-                                                    },
-                                                }))
-                                            ],
+                                            }.into(),
+                                        }, vec![]),
+                                        NestedNode(Node {
                                             duration_ns: 20_000_000,
                                             end_ns: 20_000_000,
                                             fingerprint: 0,
@@ -1040,6 +1481,8 @@ mod tests {
                                             path: Some("path".to_string()),
                                             sample_count: 1,
                                             start_ns: 0,
+                                            recursion_count: 0,
+                                            truncated: false,
                                             frame: Frame {
                                                 function: Some("child3-1".to_string()),
                                                 in_app: Some(true),
@@ -1047,68 +1490,34 @@ mod tests {
                                                 package: Some("package".to_string()),
                                                 path: Some("path".to_string()),
                                                 ..Default::default()
-                                            },
-                                        }))
-                                    ],
-                                    duration_ns: 20_000_000,
-                                    end_ns: 20_000_000,
-                                    fingerprint: 0,
-                                    is_application: true,
-                                    line: Some(0),
-                                    name: "child2-1".to_string(),
-                                    package: "package".to_string(),
-                                    path: Some("path".to_string()),
-                                    sample_count: 1,
-                                    start_ns: 0,
-                                    frame: Frame {
-                                        function: Some("child2-1".to_string()),
-                                        in_app: Some(true),
-                                        line: Some(0),
-                                        package: Some("package".to_string()),
-                                        path: Some("path".to_string()),
-                                        ..Default::default()
-                                    },
-                                }))
-                            ],
-                            duration_ns: 20_000_000,
-                            end_ns: 20_000_000,
-                            fingerprint: 0,
-                            is_application: false,
-                            line: Some(0),
-                            name: "child1-1".to_string(),
-                            package: "package".to_string(),
-                            path: Some("path".to_string()),
-                            sample_count: 1,
-                            start_ns: 0,
-                            frame: Frame {
-                                function: Some("child1-1".to_string()),
-                                in_app: Some(false),
-                                line: Some(0),
-                                package: Some("package".to_string()),
-                                path: Some("path".to_string()),
-                                ..Default::default()
-                            },
-                        }))
-                    ],
-                    duration_ns: 30_000_000,
-                    end_ns: 30_000_000,
-                    fingerprint: 0,
-                    is_application: true,
-                    line: Some(0),
-                    name: "root".to_string(),
-                    package: "package".to_string(),
-                    path: Some("path".to_string()),
-                    sample_count: 1,
-                    start_ns: 0,
-                    frame: Frame {
-                        function: Some("root".to_string()),
-                        in_app: Some(true),
-                        line: Some(0),
-                        package: Some("package".to_string()),
-                        path: Some("path".to_string()),
-                        ..Default::default()
-                    },
-                })),
+                                            }.into(),
+                                        }, vec![
+                                                NestedNode(Node {
+                                                    duration_ns: 20_000_000,
+                                                    end_ns: 20_000_000,
+                                                    fingerprint: 0,
+                                                    is_application: false,
+                                                    line: Some(0),
+                                                    name: "FunctionWithManySamples".to_string(),
+                                                    package: "vroom".to_string(),
+                                                    path: Some("path".to_string()),
+                                                    sample_count: 4, // Meets threshold of 4
+                                                    start_ns: 0,
+                                                    recursion_count: 0,
+                                                    truncated: false,
+                                                    frame: Frame {
+                                                        function: Some("FunctionWithManySamples".to_string()),
+                                                        in_app: Some(false),
+                                                        line: Some(0),
+                                                        package: Some("vroom".to_string()),
+                                                        path: Some("path".to_string()),
+                                                        ..Default::default()
+                                                    }.into(),
+                                                }, vec![])
+                                            ])
+                                    ])
+                            ])
+                    ]),
                 want: HashMap::from([
                     (
                         NodeKey {
@@ -1118,7 +1527,6 @@ mod tests {
                         NodeInfo {
                             category: FILE_READ.to_string(),
                             node: Node {
-                                children: vec![], // children cleared as per the logic
                                 duration_ns: 20_000_000,
                                 end_ns: 20_000_000,
                                 fingerprint: 0,
@@ -1129,6 +1537,8 @@ mod tests {
                                 path: Some("path".to_string()),
                                 sample_count: 4,
                                 start_ns: 0,
+                                recursion_count: 0,
+                                truncated: false,
                                 frame: Frame {
                                     function: Some("FunctionWithManySamples".to_string()),
                                     in_app: Some(false),
@@ -1136,7 +1546,7 @@ mod tests {
                                     package: Some("vroom".to_string()),
                                     path: Some("path".to_string()),
                                     ..Default::default()
-                                },
+                                }.into(),
                             },
                             stack_trace: vec![
                                 Frame {
@@ -1175,35 +1585,94 @@ mod tests {
                                     function: Some("FunctionWithManySamples".to_string()),
                                     in_app: Some(false),
                                     line: Some(0),
-                                    package: Some("vroom".to_string()),
+                                    package: Some("vroom".to_string()),
+                                    path: Some("path".to_string()),
+                                    ..Default::default()
+                                },
+                            ],
+                        }
+                                         )
+                 ]),
+            },
+            TestStruct {
+                name: "Detect deeper frame in call tree".to_string(),
+                job: Box::new(DetectExactFrameOptions {
+                    duration_threshold: Duration::from_millis(16),
+                    functions_by_package: HashMap::from([
+                        ("CoreFoundation", HashMap::from([
+                            ("LeafFunction", FILE_READ),
+                            ("RandomFunction", FILE_READ),
+                        ]))
+                    ]),
+                    ..Default::default()
+                }),
+                node: NestedNode(Node {
+                    duration_ns: 30_000_000,
+                    end_ns: 30_000_000,
+                    fingerprint: 0,
+                    is_application: true,
+                    line: Some(0),
+                    name: "root".to_string(),
+                    package: "package".to_string(),
+                    path: Some("path".to_string()),
+                    sample_count: 1,
+                    start_ns: 0,
+                    recursion_count: 0,
+                    truncated: false,
+                    frame: Frame {
+                        function: Some("root".to_string()),
+                        in_app: Some(true),
+                        line: Some(0),
+                        package: Some("package".to_string()),
+                        path: Some("path".to_string()),
+                        ..Default::default()
+                    }.into(),
+                }, vec![
+                        NestedNode(Node {
+                            duration_ns: 20_000_000,
+                            end_ns: 20_000_000,
+                            fingerprint: 0,
+                            is_application: false,
+                            line: Some(0),
+                            name: "child1-1".to_string(),
+                            package: "package".to_string(),
+                            path: Some("path".to_string()),
+                            sample_count: 1,
+                            start_ns: 0,
+                            recursion_count: 0,
+                            truncated: false,
+                            frame: Frame {
+                                function: Some("child1-1".to_string()),
+                                in_app: Some(false),
+                                line: Some(0),
+                                package: Some("package".to_string()),
+                                path: Some("path".to_string()),
+                                ..Default::default()
+                            }.into(),
+                        }, vec![
+                                NestedNode(Node {
+                                    duration_ns: 20_000_000,
+                                    end_ns: 20_000_000,
+                                    fingerprint: 0,
+                                    is_application: true,
+                                    line: Some(0),
+                                    name: "RandomFunction".to_string(),
+                                    package: "CoreFoundation".to_string(),
                                     path: Some("path".to_string()),
-                                    ..Default::default()
-                                },
-                            ],
-                        }
-                                         )
-                 ]),
-            },
-            TestStruct {
-                name: "Detect deeper frame in call tree".to_string(),
-                job: Box::new(DetectExactFrameOptions {
-                    duration_threshold: Duration::from_millis(16),
-                    functions_by_package: HashMap::from([
-                        ("CoreFoundation", HashMap::from([
-                            ("LeafFunction", FILE_READ),
-                            ("RandomFunction", FILE_READ),
-                        ]))
-                    ]),
-                    ..Default::default()
-                }),
-                node: Rc::new(RefCell::new(Node {
-                    children: vec![
-                        Rc::new(RefCell::new(Node {
-                            children: vec![
-                                Rc::new(RefCell::new(Node {
-                                    children: vec![
-                                        Rc::new(RefCell::new(Node {
-                                            children: vec![],
+                                    sample_count: 1,
+                                    start_ns: 0,
+                                    recursion_count: 0,
+                                    truncated: false,
+                                    frame: Frame {
+                                        function: Some("RandomFunction".to_string()),
+                                        in_app: Some(true),
+                                        line: Some(0),
+                                        package: Some("CoreFoundation".to_string()),
+                                        path: Some("path".to_string()),
+                                        ..Default::default()
+                                    }.into(),
+                                }, vec![
+                                        NestedNode(Node {
                                             duration_ns: 20_000_000,
                                             end_ns: 20_000_000,
                                             fingerprint: 0,
@@ -1214,6 +1683,8 @@ mod tests {
                                             path: Some("path".to_string()),
                                             sample_count: 1,
                                             start_ns: 0,
+                                            recursion_count: 0,
+                                            truncated: false,
                                             frame: Frame {
                                                 function: Some("LeafFunction".to_string()),
                                                 in_app: Some(false),
@@ -1221,68 +1692,11 @@ mod tests {
                                                 package: Some("CoreFoundation".to_string()),
                                                 path: Some("path".to_string()),
                                                 ..Default::default()
-                                            },
-                                        }))
-                                    ],
-                                    duration_ns: 20_000_000,
-                                    end_ns: 20_000_000,
-                                    fingerprint: 0,
-                                    is_application: true,
-                                    line: Some(0),
-                                    name: "RandomFunction".to_string(),
-                                    package: "CoreFoundation".to_string(),
-                                    path: Some("path".to_string()),
-                                    sample_count: 1,
-                                    start_ns: 0,
-                                    frame: Frame {
-                                        function: Some("RandomFunction".to_string()),
-                                        in_app: Some(true),
-                                        line: Some(0),
-                                        package: Some("CoreFoundation".to_string()),
-                                        path: Some("path".to_string()),
-                                        ..Default::default()
-                                    },
-                                }))
-                            ],
-                            duration_ns: 20_000_000,
-                            end_ns: 20_000_000,
-                            fingerprint: 0,
-                            is_application: false,
-                            line: Some(0),
-                            name: "child1-1".to_string(),
-                            package: "package".to_string(),
-                            path: Some("path".to_string()),
-                            sample_count: 1,
-                            start_ns: 0,
-                            frame: Frame {
-                                function: Some("child1-1".to_string()),
-                                in_app: Some(false),
-                                line: Some(0),
-                                package: Some("package".to_string()),
-                                path: Some("path".to_string()),
-                                ..Default::default()
-                            },
-                        }))
-                    ],
-                    duration_ns: 30_000_000,
-                    end_ns: 30_000_000,
-                    fingerprint: 0,
-                    is_application: true,
-                    line: Some(0),
-                    name: "root".to_string(),
-                    package: "package".to_string(),
-                    path: Some("path".to_string()),
-                    sample_count: 1,
-                    start_ns: 0,
-                    frame: Frame {
-                        function: Some("root".to_string()),
-                        in_app: Some(true),
-                        line: Some(0),
-                        package: Some("package".to_string()),
-                        path: Some("path".to_string()),
-                        ..Default::default()
-                    },
-                })),
+                                            }.into(),
+                                        }, vec![])
+                                    ])
+                            ])
+                    ]),
                 want: HashMap::from([
                     (
                         NodeKey {
@@ -1292,7 +1706,6 @@ mod tests {
                         NodeInfo {
                             category: FILE_READ.to_string(),
                             node: Node {
-                                children: vec![], // children cleared as per the logic
                                 duration_ns: 20_000_000,
                                 end_ns: 20_000_000,
                                 fingerprint: 0,
@@ -1303,6 +1716,8 @@ mod tests {
                                 path: Some("path".to_string()),
                                 sample_count: 1,
                                 start_ns: 0,
+                                recursion_count: 0,
+                                truncated: false,
                                 frame: Frame {
                                     function: Some("LeafFunction".to_string()),
                                     in_app: Some(false),
@@ -1310,7 +1725,7 @@ mod tests {
                                     package: Some("CoreFoundation".to_string()),
                                     path: Some("path".to_string()),
                                     ..Default::default()
-                                },
+                                }.into(),
                             },
                             stack_trace: vec![
                                 Frame {
@@ -1361,10 +1776,29 @@ mod tests {
                     ]),
                     ..Default::default()
                 }),
-                node: Rc::new(RefCell::new(Node {
-                    children: vec![
-                        Rc::new(RefCell::new(Node {
-                            children: vec![],
+                node: NestedNode(Node {
+                    duration_ns: 30_000_000,
+                    end_ns: 30_000_000,
+                    fingerprint: 0,
+                    is_application: true,
+                    line: Some(0),
+                    name: "RandomFunction".to_string(),
+                    package: "CoreFoundation".to_string(),
+                    path: Some("path".to_string()),
+                    sample_count: 1,
+                    start_ns: 0,
+                    recursion_count: 0,
+                    truncated: false,
+                    frame: Frame {
+                        function: Some("RandomFunction".to_string()),
+                        in_app: Some(true),
+                        line: Some(0),
+                        package: Some("CoreFoundation".to_string()),
+                        path: Some("path".to_string()),
+                        ..Default::default()
+                    }.into(),
+                }, vec![
+                        NestedNode(Node {
                             duration_ns: 20_000_000,
                             end_ns: 20_000_000,
                             fingerprint: 0,
@@ -1375,6 +1809,8 @@ mod tests {
                             path: Some("path".to_string()),
                             sample_count: 1,
                             start_ns: 0,
+                            recursion_count: 0,
+                            truncated: false,
                             frame: Frame {
                                 function: Some("child1-1".to_string()),
                                 in_app: Some(false),
@@ -1382,10 +1818,9 @@ mod tests {
                                 package: Some("package".to_string()),
                                 path: Some("path".to_string()),
                                 ..Default::default()
-                            },
-                        })),
-                        Rc::new(RefCell::new(Node {
-                            children: vec![],
+                            }.into(),
+                        }, vec![]),
+                        NestedNode(Node {
                             duration_ns: 20_000_000,
                             end_ns: 20_000_000,
                             fingerprint: 0,
@@ -1396,6 +1831,8 @@ mod tests {
                             path: Some("path".to_string()),
                             sample_count: 1,
                             start_ns: 0,
+                            recursion_count: 0,
+                            truncated: false,
                             frame: Frame {
                                 function: Some("child1-2".to_string()),
                                 in_app: Some(false),
@@ -1403,28 +1840,9 @@ mod tests {
                                 package: Some("package".to_string()),
                                 path: Some("path".to_string()),
                                 ..Default::default()
-                            },
-                        }))
-                    ],
-                    duration_ns: 30_000_000,
-                    end_ns: 30_000_000,
-                    fingerprint: 0,
-                    is_application: true,
-                    line: Some(0),
-                    name: "RandomFunction".to_string(),
-                    package: "CoreFoundation".to_string(),
-                    path: Some("path".to_string()),
-                    sample_count: 1,
-                    start_ns: 0,
-                    frame: Frame {
-                        function: Some("RandomFunction".to_string()),
-                        in_app: Some(true),
-                        line: Some(0),
-                        package: Some("CoreFoundation".to_string()),
-                        path: Some("path".to_string()),
-                        ..Default::default()
-                    },
-                })),
+                            }.into(),
+                        }, vec![])
+                    ]),
                 want: HashMap::from([
                     (
                         NodeKey {
@@ -1434,7 +1852,6 @@ mod tests {
                         NodeInfo {
                             category: FILE_READ.to_string(),
                             node: Node {
-                                children: vec![], // children cleared as per the logic
                                 duration_ns: 30_000_000,
                                 end_ns: 30_000_000,
                                 fingerprint: 0,
@@ -1445,6 +1862,8 @@ mod tests {
                                 path: Some("path".to_string()),
                                 sample_count: 1,
                                 start_ns: 0,
+                                recursion_count: 0,
+                                truncated: false,
                                 frame: Frame {
                                     function: Some("RandomFunction".to_string()),
                                     in_app: Some(true),
@@ -1452,7 +1871,7 @@ mod tests {
                                     package: Some("CoreFoundation".to_string()),
                                     path: Some("path".to_string()),
                                     ..Default::default()
-                                },
+                                }.into(),
                             },
                             stack_trace: vec![
                                 Frame {
@@ -1480,8 +1899,7 @@ mod tests {
                         ]))
                     ]),
                 }),
-                node: Rc::new(RefCell::new(Node {
-                    children: vec![],
+                node: NestedNode(Node {
                     duration_ns: 30_000_000,
                     end_ns: 30_000_000,
                     fingerprint: 0,
@@ -1492,6 +1910,8 @@ mod tests {
                     path: Some("path".to_string()),
                     sample_count: 1,
                     start_ns: 0,
+                    recursion_count: 0,
+                    truncated: false,
                     frame: Frame {
                         function: Some("android.graphics.BitmapFactory.decodeStream(java.io.InputStream, android.graphics.Rect, android.graphics.BitmapFactory$Options): android.graphics.Bitmap".to_string()),
                         in_app: Some(true),
@@ -1499,8 +1919,8 @@ mod tests {
                         package: Some("android.graphics".to_string()),
                         path: Some("path".to_string()),
                         ..Default::default()
-                    },
-                })),
+                    }.into(),
+                }, vec![]),
                 want: HashMap::from([
                     (
                         NodeKey {
@@ -1510,7 +1930,6 @@ mod tests {
                         NodeInfo {
                             category: IMAGE_DECODE.to_string(),
                             node: Node {
-                                children: vec![], // children cleared as per the logic
                                 duration_ns: 30_000_000,
                                 end_ns: 30_000_000,
                                 fingerprint: 0,
@@ -1521,6 +1940,8 @@ mod tests {
                                 path: Some("path".to_string()),
                                 sample_count: 1,
                                 start_ns: 0,
+                                recursion_count: 0,
+                                truncated: false,
                                 frame: Frame {
                                     function: Some("android.graphics.BitmapFactory.decodeStream(java.io.InputStream, android.graphics.Rect, android.graphics.BitmapFactory$Options): android.graphics.Bitmap".to_string()),
                                     in_app: Some(true),
@@ -1528,7 +1949,7 @@ mod tests {
                                     package: Some("android.graphics".to_string()),
                                     path: Some("path".to_string()),
                                     ..Default::default()
-                                },
+                                }.into(),
                             },
                             stack_trace: vec![
                                 Frame {
@@ -1548,7 +1969,13 @@ mod tests {
 
         for test in test_cases {
             let mut nodes = HashMap::new();
-            detect_frame_in_call_tree(&test.node, test.job.as_ref(), &mut nodes);
+            let call_tree = CallTree::from_nested(vec![test.node]);
+            detect_frame_in_call_tree(
+                &call_tree,
+                call_tree.roots()[0],
+                test.job.as_ref(),
+                &mut nodes,
+            );
 
             assert_eq!(nodes, test.want, "test '{}' failed", test.name);
         }
@@ -1561,14 +1988,18 @@ mod tests {
         };
 
         // A matching node living on the active thread (id 1). There is no main thread.
-        let matching_node = Rc::new(RefCell::new(Node {
-            duration_ns: 20_000_000,
-            sample_count: 4,
-            name: "func".to_string(),
-            package: "pkg".to_string(),
-            ..Default::default()
-        }));
-        let call_trees: crate::types::CallTreesU64 = HashMap::from([(1u64, vec![matching_node])]);
+        let matching_node = NestedNode(
+            Node {
+                duration_ns: 20_000_000,
+                sample_count: 4,
+                name: "func".to_string(),
+                package: "pkg".to_string(),
+                ..Default::default()
+            },
+            vec![],
+        );
+        let call_trees: crate::types::CallTreesU64 =
+            HashMap::from([(1u64, CallTree::from_nested(vec![matching_node]))]);
 
         let options = DetectExactFrameOptions {
             detection_thread: DetectionThread::MainThread,
@@ -1615,4 +2046,462 @@ mod tests {
             "ActiveThread detection should find the matching node"
         );
     }
+
+    #[test]
+    fn test_detect_image_decode_jobs() {
+        use crate::occurrence::detect_frame::DETECT_FRAME_JOBS;
+
+        struct TestStruct {
+            name: String,
+            platform: &'static str,
+            node: Node,
+        }
+
+        let test_cases = [
+            TestStruct {
+                name: "cocoa CGImageSourceCreateImageAtIndex".to_string(),
+                platform: "cocoa",
+                node: Node {
+                    duration_ns: 20_000_000,
+                    sample_count: 4,
+                    name: "CGImageSourceCreateImageAtIndex".to_string(),
+                    package: "ImageIO".to_string(),
+                    ..Default::default()
+                },
+            },
+            TestStruct {
+                name: "cocoa UIImage".to_string(),
+                platform: "cocoa",
+                node: Node {
+                    duration_ns: 20_000_000,
+                    sample_count: 4,
+                    name: "+[UIImage imageNamed:]".to_string(),
+                    package: "UIKit".to_string(),
+                    ..Default::default()
+                },
+            },
+            TestStruct {
+                name: "android BitmapFactory.decodeResource".to_string(),
+                platform: "android",
+                node: Node {
+                    duration_ns: 50_000_000,
+                    sample_count: 5,
+                    name: "android.graphics.BitmapFactory.decodeResource(android.content.res.Resources, int): android.graphics.Bitmap".to_string(),
+                    package: "android.graphics".to_string(),
+                    ..Default::default()
+                },
+            },
+        ];
+
+        for test in test_cases {
+            let categories: Vec<String> = DETECT_FRAME_JOBS
+                .get(test.platform)
+                .unwrap()
+                .iter()
+                .filter_map(|(_, job)| job.check_node(&test.node))
+                .map(|node_info| node_info.category)
+                .collect();
+            assert_eq!(
+                categories,
+                vec![IMAGE_DECODE.to_string()],
+                "test '{}' failed",
+                test.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_detect_json_and_regex_jobs() {
+        use crate::occurrence::detect_frame::DETECT_FRAME_JOBS;
+
+        struct TestStruct {
+            name: String,
+            platform: &'static str,
+            node: Node,
+            want: Vec<String>,
+        }
+
+        let test_cases = [
+            TestStruct {
+                name: "cocoa NSJSONSerialization".to_string(),
+                platform: "cocoa",
+                node: Node {
+                    duration_ns: 20_000_000,
+                    sample_count: 4,
+                    name: "+[NSJSONSerialization JSONObjectWithData:options:error:]".to_string(),
+                    package: "Foundation".to_string(),
+                    ..Default::default()
+                },
+                want: vec![JSON_DECODE.to_string()],
+            },
+            TestStruct {
+                name: "cocoa JSONDecoder".to_string(),
+                platform: "cocoa",
+                node: Node {
+                    duration_ns: 20_000_000,
+                    sample_count: 4,
+                    name: "JSONDecoder.decode<A>(_: A.Type, from: Data)".to_string(),
+                    package: "Foundation".to_string(),
+                    ..Default::default()
+                },
+                want: vec![JSON_DECODE.to_string()],
+            },
+            TestStruct {
+                name: "cocoa NSRegularExpression".to_string(),
+                platform: "cocoa",
+                node: Node {
+                    duration_ns: 10_000_000,
+                    sample_count: 2,
+                    name: "-[NSRegularExpression(NSMatching) matchesInString:options:range:]"
+                        .to_string(),
+                    package: "Foundation".to_string(),
+                    ..Default::default()
+                },
+                want: vec![REGEX.to_string()],
+            },
+            TestStruct {
+                name: "cocoa NSJSONSerialization under threshold".to_string(),
+                platform: "cocoa",
+                node: Node {
+                    duration_ns: 10_000_000,
+                    sample_count: 2,
+                    name: "+[NSJSONSerialization JSONObjectWithData:options:error:]".to_string(),
+                    package: "Foundation".to_string(),
+                    ..Default::default()
+                },
+                want: vec![],
+            },
+            TestStruct {
+                name: "android org.json.JSONObject constructor".to_string(),
+                platform: "android",
+                node: Node {
+                    duration_ns: 50_000_000,
+                    sample_count: 5,
+                    name: "org.json.JSONObject(java.lang.String): void".to_string(),
+                    package: "org.json".to_string(),
+                    ..Default::default()
+                },
+                want: vec![JSON_DECODE.to_string()],
+            },
+            TestStruct {
+                name: "android Pattern.compile".to_string(),
+                platform: "android",
+                node: Node {
+                    duration_ns: 25_000_000,
+                    sample_count: 3,
+                    name:
+                        "java.util.regex.Pattern.compile(java.lang.String): java.util.regex.Pattern"
+                            .to_string(),
+                    package: "java.util.regex".to_string(),
+                    ..Default::default()
+                },
+                want: vec![REGEX.to_string()],
+            },
+        ];
+
+        for test in test_cases {
+            let categories: Vec<String> = DETECT_FRAME_JOBS
+                .get(test.platform)
+                .unwrap()
+                .iter()
+                .filter_map(|(_, job)| job.check_node(&test.node))
+                .map(|node_info| node_info.category)
+                .collect();
+            assert_eq!(categories, test.want, "test '{}' failed", test.name);
+        }
+    }
+
+    #[test]
+    fn test_detect_db_main_thread_jobs() {
+        use crate::{
+            occurrence::{
+                detect_frame::{CORE_DATA_WRITE, DETECT_FRAME_JOBS, SQL},
+                new_occurrence, CORE_DATA_TYPE, NONE_TYPE,
+            },
+            sample::v1::SampleProfile,
+        };
+
+        struct TestStruct {
+            name: String,
+            platform: &'static str,
+            node: Node,
+            want: Vec<(String, u64)>,
+        }
+
+        let test_cases = [
+            TestStruct {
+                name: "cocoa NSManagedObjectContext save".to_string(),
+                platform: "cocoa",
+                node: Node {
+                    duration_ns: 20_000_000,
+                    sample_count: 4,
+                    name: "-[NSManagedObjectContext save:]".to_string(),
+                    package: "CoreData".to_string(),
+                    ..Default::default()
+                },
+                // the Core Data issue keeps being reported as is
+                want: vec![
+                    (CORE_DATA_WRITE.to_string(), CORE_DATA_TYPE),
+                    (DB_MAIN_THREAD.to_string(), CORE_DATA_TYPE),
+                ],
+            },
+            TestStruct {
+                name: "cocoa sqlite3_step".to_string(),
+                platform: "cocoa",
+                node: Node {
+                    duration_ns: 20_000_000,
+                    sample_count: 4,
+                    name: "sqlite3_step".to_string(),
+                    package: "libsqlite3.dylib".to_string(),
+                    ..Default::default()
+                },
+                want: vec![
+                    (SQL.to_string(), NONE_TYPE),
+                    (DB_MAIN_THREAD.to_string(), CORE_DATA_TYPE),
+                ],
+            },
+            TestStruct {
+                name: "cocoa sqlite3_step under threshold".to_string(),
+                platform: "cocoa",
+                node: Node {
+                    duration_ns: 20_000_000,
+                    sample_count: 2,
+                    name: "sqlite3_step".to_string(),
+                    package: "libsqlite3.dylib".to_string(),
+                    ..Default::default()
+                },
+                want: vec![(DB_MAIN_THREAD.to_string(), CORE_DATA_TYPE)],
+            },
+            TestStruct {
+                name: "cocoa sqlite3_step under both thresholds".to_string(),
+                platform: "cocoa",
+                node: Node {
+                    duration_ns: 10_000_000,
+                    sample_count: 1,
+                    name: "sqlite3_step".to_string(),
+                    package: "libsqlite3.dylib".to_string(),
+                    ..Default::default()
+                },
+                want: vec![],
+            },
+            TestStruct {
+                name: "android SQLiteDatabase.query".to_string(),
+                platform: "android",
+                node: Node {
+                    duration_ns: 50_000_000,
+                    sample_count: 5,
+                    name: "android.database.sqlite.SQLiteDatabase.query(java.lang.String, java.lang.String[], java.lang.String, java.lang.String[], java.lang.String, java.lang.String, java.lang.String): android.database.Cursor".to_string(),
+                    package: "android.database.sqlite".to_string(),
+                    ..Default::default()
+                },
+                want: vec![
+                    (SQL.to_string(), NONE_TYPE),
+                    (DB_MAIN_THREAD.to_string(), CORE_DATA_TYPE),
+                ],
+            },
+        ];
+
+        for test in test_cases {
+            let profile = SampleProfile {
+                platform: test.platform.to_string(),
+                ..Default::default()
+            };
+            let occurrences: Vec<(String, u64)> = DETECT_FRAME_JOBS
+                .get(test.platform)
+                .unwrap()
+                .iter()
+                .filter_map(|(_, job)| job.check_node(&test.node))
+                .map(|node_info| {
+                    let occurrence = new_occurrence(&profile, node_info);
+                    (occurrence.category, occurrence.r#type)
+                })
+                .collect();
+            assert_eq!(occurrences, test.want, "test '{}' failed", test.name);
+        }
+    }
+
+    #[test]
+    fn test_detect_network_main_thread_jobs() {
+        use crate::occurrence::detect_frame::DETECT_FRAME_JOBS;
+
+        let function = |name: &str, package: &str| Node {
+            duration_ns: 80_000_000,
+            sample_count: 8,
+            name: name.to_string(),
+            package: package.to_string(),
+            frame: std::sync::Arc::new(Frame {
+                function: Some(name.to_string()),
+                package: Some(package.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        // the calls already reported as http keep being reported as such
+        let test_cases = [
+            (
+                "cocoa",
+                function(
+                    "+[NSURLConnection sendSynchronousRequest:returningResponse:error:]",
+                    "Foundation",
+                ),
+                vec![HTTP, NETWORK_MAIN_THREAD],
+            ),
+            (
+                "cocoa",
+                function("CFURLConnectionSendSynchronousRequest", "CoreFoundation"),
+                vec![HTTP, NETWORK_MAIN_THREAD],
+            ),
+            (
+                "cocoa",
+                function("curl_easy_perform", "libcurl.4.dylib"),
+                vec![NETWORK_MAIN_THREAD],
+            ),
+            (
+                "android",
+                function(
+                    "com.android.okhttp.internal.huc.HttpURLConnectionImpl.getInputStream(): java.io.InputStream",
+                    "com.android.okhttp.internal.huc",
+                ),
+                vec![NETWORK_MAIN_THREAD],
+            ),
+        ];
+
+        for (platform, node, want) in test_cases {
+            let jobs = &DETECT_FRAME_JOBS[platform];
+            let (_, job) = jobs
+                .iter()
+                .find(|(name, _)| *name == format!("{platform}_network_main_thread"))
+                .unwrap();
+            let call_tree = CallTree::from_nested(vec![NestedNode(
+                function("main", "app"),
+                vec![NestedNode(
+                    function("fetchConfig", "app"),
+                    vec![NestedNode(node.clone(), vec![])],
+                )],
+            )]);
+            let mut nodes = HashMap::new();
+            detect_frame_in_call_tree(&call_tree, call_tree.roots()[0], job.as_ref(), &mut nodes);
+            let node_infos: Vec<&NodeInfo> = nodes.values().collect();
+            let [node_info] = &node_infos[..] else {
+                panic!("expected an occurrence for {}", node.name)
+            };
+            assert_eq!(node_info.category, NETWORK_MAIN_THREAD);
+            // the offending stack, root first
+            assert_eq!(
+                node_info.stack_trace,
+                vec![
+                    function("main", "app").to_frame(),
+                    function("fetchConfig", "app").to_frame(),
+                    node.to_frame(),
+                ]
+            );
+            let categories: Vec<String> = jobs
+                .iter()
+                .filter_map(|(_, job)| job.check_node(&node))
+                .map(|node_info| node_info.category)
+                .collect();
+            assert_eq!(categories, want, "{}", node.name);
+        }
+    }
+
+    #[test]
+    fn test_detect_compression_and_base64_jobs() {
+        use crate::occurrence::detect_frame::DETECT_FRAME_JOBS;
+
+        let node = |duration_ms: u64, name: &str, package: &str| Node {
+            duration_ns: duration_ms * 1_000_000,
+            sample_count: duration_ms / 4,
+            name: name.to_string(),
+            package: package.to_string(),
+            ..Default::default()
+        };
+        let test_cases = [
+            (
+                "cocoa",
+                node(20, "deflate", "libz.1.dylib"),
+                vec![("cocoa_compression", COMPRESSION)],
+            ),
+            (
+                "cocoa",
+                node(40, "BZ2_bzCompress", "libbz2.1.0.dylib"),
+                vec![("cocoa_compression", COMPRESSION)],
+            ),
+            (
+                "cocoa",
+                node(40, "compression_decode_buffer", "libcompression.dylib"),
+                vec![("cocoa_compression", DECOMPRESSION)],
+            ),
+            (
+                "cocoa",
+                node(
+                    10,
+                    "-[NSData(NSDataBase64Encoding) base64EncodedStringWithOptions:]",
+                    "Foundation",
+                ),
+                vec![("cocoa_base64", BASE64_ENCODE)],
+            ),
+            // under the threshold of the compression job, not of the base64 one
+            ("cocoa", node(10, "deflate", "libz.1.dylib"), vec![]),
+            (
+                "android",
+                node(
+                    50,
+                    "java.util.zip.Inflater.inflate(byte[], int, int): int",
+                    "java.util.zip",
+                ),
+                vec![("android_compression", DECOMPRESSION)],
+            ),
+            (
+                "android",
+                node(
+                    30,
+                    "android.util.Base64.encodeToString(byte[], int): java.lang.String",
+                    "android.util",
+                ),
+                vec![("android_base64", BASE64_ENCODE)],
+            ),
+        ];
+
+        for (platform, node, want) in test_cases {
+            let got: Vec<(&str, String)> = DETECT_FRAME_JOBS[platform]
+                .iter()
+                .filter_map(|(name, job)| Some((*name, job.check_node(&node)?.category)))
+                .collect();
+            let want: Vec<(&str, String)> = want
+                .into_iter()
+                .map(|(name, category)| (name, category.to_string()))
+                .collect();
+            assert_eq!(got, want, "{}", node.name);
+        }
+    }
+
+    #[test]
+    fn test_register_frame_detector() {
+        use crate::occurrence::{detect_frame::registered_frame_detector, detector::Detector};
+
+        let detector = registered_frame_detector(
+            "custom_parse",
+            "test_register_frame_detector",
+            &["com.example.Parser.parse".to_string()],
+            10_000_000,
+            true,
+        );
+        assert_eq!(detector.name(), "test_register_frame_detector_custom_parse");
+        assert_eq!(detector.platforms(), vec!["test_register_frame_detector"]);
+        let job = &detector.job;
+        assert_eq!(job.detection_thread(), DetectionThread::MainThread);
+
+        let mut node = Node {
+            duration_ns: 20_000_000,
+            sample_count: 2,
+            name: "com.example.Parser.parse(java.lang.String): void".to_string(),
+            package: "com.example".to_string(),
+            ..Default::default()
+        };
+        let node_info = job.check_node(&node).unwrap();
+        assert_eq!(node_info.category, "custom_parse");
+
+        node.duration_ns = 5_000_000;
+        assert!(job.check_node(&node).is_none());
+    }
 }
@@ -0,0 +1,543 @@
+//! The registry of the detectors finding occurrences in profiles. Detectors
+//! are run in the order they were registered, the built-in ones first, and
+//! can be disabled at runtime. Detectors registered at runtime can be
+//! replaced or unregistered, the built-in ones can't.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+    sync::RwLock,
+};
+
+use once_cell::sync::Lazy;
+use pyo3::{pyclass, pymethods};
+
+use crate::types::{CallTreesU64, ProfileInterface};
+
+use super::{
+    deep_recursion::DeepRecursionDetector, detect_frame::frame_detectors,
+    frame_drop::FrameDropDetector, Occurrence,
+};
+
+/// Options of a detection run, passed to every detector.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct DetectorOptions {
+    /// Whether to only keep an occurrence per fingerprint, the longest,
+    /// when the same issue is detected more than once in a profile (e.g. on
+    /// several threads, or by several detectors).
+    pub detect_once_per_profile: bool,
+
+    /// Whether to run every detector of the platform, enabled or not, to
+    /// calibrate them (see [`calibrate_detectors`]).
+    pub dry_run: bool,
+
+    /// Number of consecutive calls of a function past which its recursion
+    /// is reported as a `deep_recursion` occurrence. Defaults to
+    /// [`super::deep_recursion::DEFAULT_RECURSION_DEPTH_THRESHOLD`].
+    pub recursion_depth_threshold: Option<u64>,
+}
+
+impl DetectorOptions {
+    pub fn with_detect_once_per_profile(mut self, detect_once_per_profile: bool) -> Self {
+        self.detect_once_per_profile = detect_once_per_profile;
+        self
+    }
+
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn with_recursion_depth_threshold(mut self, recursion_depth_threshold: u64) -> Self {
+        self.recursion_depth_threshold = Some(recursion_depth_threshold);
+        self
+    }
+}
+
+/// Upper bounds of the buckets of the duration histograms of calibration
+/// stats, in milliseconds.
+const CALIBRATION_BUCKETS_MS: &[u64] = &[1, 2, 4, 8, 16, 32, 64, 128, 256, 512, 1024, 2048, 4096];
+
+/// A detector of occurrences in the call trees of a profile.
+pub trait Detector: Send + Sync {
+    /// Returns the name of the detector, used to enable or disable it.
+    fn name(&self) -> &str;
+
+    /// Returns the categories of the occurrences the detector can create.
+    fn categories(&self) -> Vec<&str>;
+
+    /// Returns the platforms of the profiles the detector runs on.
+    fn platforms(&self) -> Vec<&str>;
+
+    /// Detects occurrences in the call trees of the profile, adding them to
+    /// `occurrences`.
+    fn run(
+        &self,
+        call_trees: &CallTreesU64,
+        profile: &dyn ProfileInterface,
+        options: &DetectorOptions,
+        occurrences: &mut Vec<Occurrence>,
+    );
+}
+
+struct Registration {
+    detector: Box<dyn Detector>,
+    enabled: bool,
+    builtin: bool,
+}
+
+static DETECTORS: Lazy<RwLock<Vec<Registration>>> = Lazy::new(|| {
+    let mut detectors: Vec<Box<dyn Detector>> = frame_detectors()
+        .into_iter()
+        .map(|detector| Box::new(detector) as Box<dyn Detector>)
+        .collect();
+    detectors.push(Box::new(FrameDropDetector));
+    detectors.push(Box::new(DeepRecursionDetector));
+    RwLock::new(
+        detectors
+            .into_iter()
+            .map(|detector| Registration {
+                detector,
+                enabled: true,
+                builtin: true,
+            })
+            .collect(),
+    )
+});
+
+#[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DetectorError {
+    UnknownDetector(String),
+    BuiltinDetector(String),
+}
+
+impl fmt::Display for DetectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DetectorError::UnknownDetector(name) => write!(f, "unknown detector: {name}"),
+            DetectorError::BuiltinDetector(name) => {
+                write!(f, "{name} is a built-in detector")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DetectorError {}
+
+/// A detector of the registry.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectorInfo {
+    pub name: String,
+    pub categories: Vec<String>,
+    pub platforms: Vec<String>,
+    pub enabled: bool,
+}
+
+#[pymethods]
+impl DetectorInfo {
+    /// Returns the name of the detector.
+    ///
+    /// Returns:
+    ///     str
+    ///         The name of the detector (e.g. "cocoa_main_thread", "frame_drop").
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the categories of the occurrences the detector can create.
+    ///
+    /// Returns:
+    ///     list[str]
+    ///         The occurrence categories, sorted.
+    pub fn get_categories(&self) -> Vec<String> {
+        self.categories.clone()
+    }
+
+    /// Returns the platforms of the profiles the detector runs on.
+    ///
+    /// Returns:
+    ///     list[str]
+    ///         The platforms of the detector.
+    pub fn get_platforms(&self) -> Vec<String> {
+        self.platforms.clone()
+    }
+
+    /// Returns whether the detector runs when finding occurrences.
+    ///
+    /// Returns:
+    ///     bool
+    ///         True unless the detector was disabled.
+    pub fn get_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+/// What the detectors would have reported, to tune their thresholds before
+/// enabling them.
+#[pyclass]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CalibrationStats {
+    /// Occurrences which would have been created, by category.
+    pub occurrences: BTreeMap<String, u64>,
+    /// Durations of the occurrences, by category, counted in the buckets of
+    /// [`CALIBRATION_BUCKETS_MS`] and a last bucket for longer durations.
+    pub duration_histograms: BTreeMap<String, Vec<u64>>,
+}
+
+impl CalibrationStats {
+    fn from_occurrences(occurrences: &[Occurrence]) -> Self {
+        let mut stats = CalibrationStats::default();
+        for occurrence in occurrences {
+            *stats
+                .occurrences
+                .entry(occurrence.category.clone())
+                .or_default() += 1;
+            let histogram = stats
+                .duration_histograms
+                .entry(occurrence.category.clone())
+                .or_insert_with(|| vec![0; CALIBRATION_BUCKETS_MS.len() + 1]);
+            let bucket = CALIBRATION_BUCKETS_MS
+                .iter()
+                .position(|&bound_ms| occurrence.duration_ns < bound_ms * 1_000_000)
+                .unwrap_or(CALIBRATION_BUCKETS_MS.len());
+            histogram[bucket] += 1;
+        }
+        stats
+    }
+}
+
+#[pymethods]
+impl CalibrationStats {
+    /// Returns the number of occurrences the detectors would have created.
+    ///
+    /// Returns:
+    ///     dict[str, int]
+    ///         The number of occurrences, by category.
+    pub fn get_occurrences(&self) -> BTreeMap<String, u64> {
+        self.occurrences.clone()
+    }
+
+    /// Returns the upper bounds of the buckets of the duration histograms.
+    ///
+    /// Returns:
+    ///     list[int]
+    ///         The bounds, in nanoseconds, from 1ms to 4096ms by powers of 2.
+    pub fn get_duration_buckets_ns(&self) -> Vec<u64> {
+        CALIBRATION_BUCKETS_MS
+            .iter()
+            .map(|bound_ms| bound_ms * 1_000_000)
+            .collect()
+    }
+
+    /// Returns the histograms of the durations of the occurrences the
+    /// detectors would have created. The i-th count is of the durations
+    /// under the i-th bound of :meth:`get_duration_buckets_ns` and not under
+    /// the previous one, the last count of the durations over every bound.
+    ///
+    /// Returns:
+    ///     dict[str, list[int]]
+    ///         The duration histograms, by category.
+    pub fn get_duration_histograms(&self) -> BTreeMap<String, Vec<u64>> {
+        self.duration_histograms.clone()
+    }
+}
+
+/// Adds a detector to the registry, run after the ones already registered.
+/// A detector registered under the same name is replaced, keeping its place
+/// and whether it's enabled, but built-in detectors can't be.
+pub(crate) fn register_detector(detector: Box<dyn Detector>) -> Result<(), DetectorError> {
+    let mut detectors = DETECTORS.write().unwrap_or_else(|e| e.into_inner());
+    match detectors
+        .iter_mut()
+        .find(|registration| registration.detector.name() == detector.name())
+    {
+        Some(registration) if registration.builtin => {
+            Err(DetectorError::BuiltinDetector(detector.name().to_string()))
+        }
+        Some(registration) => {
+            registration.detector = detector;
+            Ok(())
+        }
+        None => {
+            detectors.push(Registration {
+                detector,
+                enabled: true,
+                builtin: false,
+            });
+            Ok(())
+        }
+    }
+}
+
+/// Removes a detector registered at runtime from the registry.
+pub fn unregister_detector(name: &str) -> Result<(), DetectorError> {
+    let mut detectors = DETECTORS.write().unwrap_or_else(|e| e.into_inner());
+    let Some(i) = detectors
+        .iter()
+        .position(|registration| registration.detector.name() == name)
+    else {
+        return Err(DetectorError::UnknownDetector(name.to_string()));
+    };
+    if detectors[i].builtin {
+        return Err(DetectorError::BuiltinDetector(name.to_string()));
+    }
+    detectors.remove(i);
+    Ok(())
+}
+
+/// Returns the detectors of the registry, in the order they run.
+pub fn detectors() -> Vec<DetectorInfo> {
+    DETECTORS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|registration| {
+            let detector = &registration.detector;
+            let categories: BTreeSet<&str> = detector.categories().into_iter().collect();
+            DetectorInfo {
+                name: detector.name().to_string(),
+                categories: categories.into_iter().map(str::to_string).collect(),
+                platforms: detector
+                    .platforms()
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect(),
+                enabled: registration.enabled,
+            }
+        })
+        .collect()
+}
+
+/// Enables or disables the detectors with the given names. Detectors are all
+/// left unchanged if any name is unknown.
+pub fn set_detectors_enabled(names: &[String], enabled: bool) -> Result<(), DetectorError> {
+    let mut detectors = DETECTORS.write().unwrap_or_else(|e| e.into_inner());
+    if let Some(name) = names.iter().find(|name| {
+        !detectors
+            .iter()
+            .any(|registration| registration.detector.name() == name.as_str())
+    }) {
+        return Err(DetectorError::UnknownDetector(name.clone()));
+    }
+    for registration in detectors.iter_mut() {
+        if names
+            .iter()
+            .any(|name| name.as_str() == registration.detector.name())
+        {
+            registration.enabled = enabled;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the categories of the occurrences the enabled detectors can
+/// create, by platform.
+pub(crate) fn detector_categories_by_platform() -> BTreeMap<String, BTreeSet<String>> {
+    let mut categories: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let detectors = DETECTORS.read().unwrap_or_else(|e| e.into_inner());
+    for registration in detectors.iter().filter(|registration| registration.enabled) {
+        for platform in registration.detector.platforms() {
+            categories.entry(platform.to_string()).or_default().extend(
+                registration
+                    .detector
+                    .categories()
+                    .into_iter()
+                    .map(str::to_string),
+            );
+        }
+    }
+    categories
+}
+
+/// Runs the enabled detectors of the platform of the profile, or all of them
+/// on a dry run.
+pub(crate) fn run_detectors(
+    profile: &dyn ProfileInterface,
+    call_trees: &CallTreesU64,
+    options: &DetectorOptions,
+) -> Vec<Occurrence> {
+    let platform = profile.get_platform();
+    let mut occurrences = Vec::new();
+    let detectors = DETECTORS.read().unwrap_or_else(|e| e.into_inner());
+    for registration in detectors
+        .iter()
+        .filter(|registration| registration.enabled || options.dry_run)
+    {
+        let detector = &registration.detector;
+        if detector.platforms().contains(&platform.as_str()) {
+            detector.run(call_trees, profile, options, &mut occurrences);
+        }
+    }
+    if options.detect_once_per_profile {
+        occurrences = once_per_fingerprint(occurrences);
+    }
+    occurrences
+}
+
+/// Runs every detector of the platform of the profile, enabled or not, and
+/// returns the stats of the occurrences they would have created.
+pub(crate) fn calibrate_detectors(
+    profile: &dyn ProfileInterface,
+    call_trees: &CallTreesU64,
+    options: &DetectorOptions,
+) -> CalibrationStats {
+    let options = DetectorOptions {
+        dry_run: true,
+        ..options.clone()
+    };
+    CalibrationStats::from_occurrences(&run_detectors(profile, call_trees, &options))
+}
+
+/// Keeps the longest occurrence of each fingerprint, in the order they were
+/// first detected.
+fn once_per_fingerprint(occurrences: Vec<Occurrence>) -> Vec<Occurrence> {
+    let mut deduplicated: Vec<Occurrence> = Vec::with_capacity(occurrences.len());
+    let mut indices: HashMap<Vec<String>, usize> = HashMap::new();
+    for occurrence in occurrences {
+        match indices.get(&occurrence.fingerprint) {
+            Some(&i) => {
+                if occurrence.duration_ns > deduplicated[i].duration_ns {
+                    deduplicated[i] = occurrence;
+                }
+            }
+            None => {
+                indices.insert(occurrence.fingerprint.clone(), deduplicated.len());
+                deduplicated.push(occurrence);
+            }
+        }
+    }
+    deduplicated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        detector_categories_by_platform, detectors, once_per_fingerprint, set_detectors_enabled,
+        unregister_detector, CalibrationStats, DetectorError,
+    };
+    use crate::occurrence::{register_frame_detector, Occurrence};
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_set_detectors_enabled() {
+        let builtin = detectors();
+        assert!(builtin
+            .iter()
+            .any(|detector| detector.name == "cocoa_main_thread"
+                && detector.categories.contains(&"file_read".to_string())));
+        assert!(builtin.iter().all(|detector| detector.enabled));
+
+        // other tests rely on the built-in detectors, so only the one
+        // registered here is disabled
+        register_frame_detector("custom_parse", "detector-test", &[], 0, false).unwrap();
+        let name = "detector-test_custom_parse".to_string();
+        assert_eq!(
+            set_detectors_enabled(&[name.clone(), "unknown".to_string()], false),
+            Err(DetectorError::UnknownDetector("unknown".to_string()))
+        );
+        assert!(detector_categories_by_platform().contains_key("detector-test"));
+
+        assert_eq!(
+            set_detectors_enabled(std::slice::from_ref(&name), false),
+            Ok(())
+        );
+        let detector = detectors()
+            .into_iter()
+            .find(|detector| detector.name == name)
+            .unwrap();
+        assert_eq!(detector.platforms, vec!["detector-test".to_string()]);
+        assert!(!detector.enabled);
+        assert!(!detector_categories_by_platform().contains_key("detector-test"));
+    }
+
+    #[test]
+    fn test_register_detector_twice() {
+        let count = |name: &str| {
+            detectors()
+                .iter()
+                .filter(|detector| detector.name == name)
+                .count()
+        };
+        let name = "detector-twice-test_custom_parse";
+        register_frame_detector("custom_parse", "detector-twice-test", &[], 0, false).unwrap();
+        set_detectors_enabled(&[name.to_string()], false).unwrap();
+        // retuning the detector replaces it, still disabled
+        register_frame_detector("custom_parse", "detector-twice-test", &[], 10, true).unwrap();
+        assert_eq!(count(name), 1);
+        assert!(
+            !detectors()
+                .into_iter()
+                .find(|detector| detector.name == name)
+                .unwrap()
+                .enabled
+        );
+
+        assert_eq!(unregister_detector(name), Ok(()));
+        assert_eq!(count(name), 0);
+        assert_eq!(
+            unregister_detector(name),
+            Err(DetectorError::UnknownDetector(name.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_builtin_detectors_are_kept() {
+        let builtin = detectors();
+        assert_eq!(
+            register_frame_detector("main_thread", "cocoa", &[], 0, true),
+            Err(DetectorError::BuiltinDetector(
+                "cocoa_main_thread".to_string()
+            ))
+        );
+        assert_eq!(
+            unregister_detector("frame_drop"),
+            Err(DetectorError::BuiltinDetector("frame_drop".to_string()))
+        );
+        let detectors = detectors();
+        for detector in builtin {
+            assert!(detectors.contains(&detector), "{}", detector.name);
+        }
+    }
+
+    #[test]
+    fn test_once_per_fingerprint() {
+        let occurrence = |fingerprint: &str, duration_ns: u64| Occurrence {
+            fingerprint: vec![fingerprint.to_string()],
+            duration_ns,
+            ..Default::default()
+        };
+        let occurrences = once_per_fingerprint(vec![
+            occurrence("a", 10),
+            occurrence("b", 10),
+            occurrence("a", 30),
+            occurrence("a", 20),
+        ]);
+        assert_eq!(occurrences, vec![occurrence("a", 30), occurrence("b", 10)]);
+    }
+
+    #[test]
+    fn test_calibration_stats() {
+        let occurrence = |category: &str, duration_ns: u64| Occurrence {
+            category: category.to_string(),
+            duration_ns,
+            ..Default::default()
+        };
+        let stats = CalibrationStats::from_occurrences(&[
+            occurrence("file_read", 500_000),
+            occurrence("file_read", 20_000_000),
+            occurrence("file_read", 32_000_000),
+            occurrence("frame_drop", 10_000_000_000),
+        ]);
+        assert_eq!(stats.occurrences["file_read"], 3);
+        let mut want = vec![0; 14];
+        want[0] = 1;
+        want[5] = 1;
+        want[6] = 1;
+        assert_eq!(stats.duration_histograms["file_read"], want);
+        assert_eq!(stats.duration_histograms["frame_drop"][13], 1);
+    }
+}
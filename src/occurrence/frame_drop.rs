@@ -1,12 +1,15 @@
-use crate::nodetree::Node;
+use crate::limits::max_stack_depth;
+use crate::measurements::Measurement;
+use crate::nodetree::{CallTree, Node, NodeId};
 use crate::types::{CallTreesU64, ProfileInterface};
-use crate::MAX_STACK_DEPTH;
-use std::cell::RefCell;
-use std::rc::Rc;
+
+use super::detector::{Detector, DetectorOptions};
 use std::time::Duration;
 
 // Constants
 pub const FRAME_DROP: &str = "frame_drop";
+/// Platforms whose SDKs measure frozen frame renders.
+pub const FRAME_DROP_PLATFORMS: &[&str] = &["android", "cocoa"];
 const MARGIN_PERCENT: f64 = 0.05;
 const MIN_FRAME_DURATION_PERCENT: f64 = 0.5;
 const START_LIMIT_PERCENT: f64 = 0.2;
@@ -71,18 +74,19 @@ impl FrozenFrameStats {
     /// and greater depth.
     pub fn find_frame_drop_cause_frame(
         &self,
-        n: &Rc<RefCell<Node>>,
-        st: &mut Vec<Rc<RefCell<Node>>>,
+        call_tree: &CallTree,
+        n: NodeId,
+        st: &mut Vec<NodeId>,
         depth: i32,
     ) -> Option<NodeStack> {
         // Add current node to stack trace
-        st.push(n.clone());
+        st.push(n);
 
         let mut longest: Option<NodeStack> = None;
 
         // Explore each branch to find the deepest valid node
-        for child in &n.borrow().children {
-            if let Some(cause) = self.find_frame_drop_cause_frame(child, st, depth + 1) {
+        for &child in call_tree.children(n) {
+            if let Some(cause) = self.find_frame_drop_cause_frame(call_tree, child, st, depth + 1) {
                 match &longest {
                     Some(longest_ref) => {
                         // Only keep the longest node
@@ -103,8 +107,8 @@ impl FrozenFrameStats {
         // Create a nodeStack of the current node
         let ns = NodeStack {
             depth,
-            n: n.borrow().clone(), // Clone the node data
-            st: Vec::new(),        // Will be filled later if needed
+            n: call_tree[n].clone(), // Clone the node data
+            st: Vec::new(),          // Will be filled later if needed
         };
 
         // Check if current node is valid
@@ -119,7 +123,7 @@ impl FrozenFrameStats {
             (None, Some(mut current)) => {
                 // If we didn't find any valid node downstream, we return the current
                 // Copy the stack trace
-                current.st = st.iter().map(|node| node.borrow().clone()).collect();
+                current.st = st.iter().map(|&node| call_tree[node].clone()).collect();
                 Some(current)
             }
             (Some(longest), None) => Some(longest),
@@ -130,7 +134,7 @@ impl FrozenFrameStats {
                     Some(longest)
                 } else {
                     // Copy the stack trace
-                    current.st = st.iter().map(|node| node.borrow().clone()).collect();
+                    current.st = st.iter().map(|&node| call_tree[node].clone()).collect();
                     Some(current)
                 }
             }
@@ -161,18 +165,67 @@ pub fn find_frame_drop_cause(
 
     // Get call trees for the active thread
     let active_thread_id = profile.get_transaction().active_thread_id;
-    let Some(call_trees) = call_trees_per_thread_id.get(&active_thread_id) else {
+    let Some(call_tree) = call_trees_per_thread_id.get(&active_thread_id) else {
         return;
     };
 
+    for node_info in find_frame_drop_cause_nodes(call_tree, frame_drops) {
+        occurrences.push(super::new_occurrence(profile, node_info));
+    }
+}
+
+/// The detector of frame drop causes.
+pub struct FrameDropDetector;
+
+impl Detector for FrameDropDetector {
+    fn name(&self) -> &str {
+        FRAME_DROP
+    }
+
+    fn categories(&self) -> Vec<&str> {
+        vec![FRAME_DROP]
+    }
+
+    fn platforms(&self) -> Vec<&str> {
+        FRAME_DROP_PLATFORMS.to_vec()
+    }
+
+    fn run(
+        &self,
+        call_trees: &CallTreesU64,
+        profile: &dyn ProfileInterface,
+        _options: &DetectorOptions,
+        occurrences: &mut Vec<super::Occurrence>,
+    ) {
+        find_frame_drop_cause(profile, call_trees, occurrences);
+    }
+}
+
+/// Finds the nodes of the call tree responsible for the frozen frames of a
+/// `frozen_frame_renders` measurement, at most one per frozen frame.
+///
+/// Measurement values are timestamped on the time axis of the call trees,
+/// so this works with the call trees of profiles and chunks alike.
+pub fn find_frame_drop_cause_nodes(
+    call_tree: &CallTree,
+    frame_drops: &Measurement,
+) -> Vec<super::NodeInfo> {
+    let mut causes = Vec::new();
+
     // Process each measurement value
     for mv in &frame_drops.values {
-        let stats = FrozenFrameStats::new(mv.elapsed_since_start_ns, mv.value);
+        // SDKs report frame render durations in nanoseconds, which we assume
+        // if the unit is missing
+        let duration_ns = frame_drops
+            .unit
+            .to_nanoseconds(mv.value)
+            .unwrap_or(mv.value);
+        let stats = FrozenFrameStats::new(mv.timestamp_ns, duration_ns);
 
         // Check each root in call trees
-        for root in call_trees {
-            let mut st = Vec::with_capacity(MAX_STACK_DEPTH as usize);
-            if let Some(cause) = stats.find_frame_drop_cause_frame(root, &mut st, 0) {
+        for &root in call_tree.roots() {
+            let mut st = Vec::with_capacity(max_stack_depth() as usize);
+            if let Some(cause) = stats.find_frame_drop_cause_frame(call_tree, root, &mut st, 0) {
                 // We found a potential stacktrace responsible for this frozen frame
                 let mut stack_trace = Vec::with_capacity(cause.st.len());
                 let mut unknown_frames_count = 0.0;
@@ -197,20 +250,16 @@ pub fn find_frame_drop_cause(
                     continue;
                 }
 
-                // Create NodeInfo for the found cause
-                let node_info = super::NodeInfo {
+                causes.push(super::NodeInfo {
                     category: FRAME_DROP.to_string(),
                     node: cause.n,
                     stack_trace,
-                };
-
-                // Create new occurrence and add it to the occurrences vector
-                let occurrence = super::new_occurrence(profile, node_info);
-                occurrences.push(occurrence);
+                });
                 break; // Found a cause for this measurement, move to next one
             }
         }
     }
+    causes
 }
 
 #[cfg(test)]
@@ -219,7 +268,10 @@ mod tests {
 
     use crate::{
         frame::Frame,
-        occurrence::{Event, Evidence, EvidenceData, Occurrence, StackTrace, ISSUE_TITLES},
+        occurrence::{
+            Event, Evidence, EvidenceData, Occurrence, StackTrace, FINGERPRINT_VERSION,
+            ISSUE_TITLES,
+        },
     };
 
     use chrono::DateTime;
@@ -255,7 +307,8 @@ mod tests {
                     frame: Frame {
                         function: Some("test_function".to_string()),
                         ..Default::default()
-                    },
+                    }
+                    .into(),
                     ..Default::default()
                 },
                 st: Vec::new(),
@@ -276,10 +329,10 @@ mod tests {
     #[test]
     fn test_find_frame_drop_cause() {
         use super::*;
+        use crate::measurements::{MeasurementUnit, MeasurementValue, Measurements};
+        use crate::nodetree::NestedNode;
         use crate::sample::v1::Sample;
-        use crate::sample::v1::{
-            Measurement, MeasurementValue, Profile as SampleProfileData, SampleProfile,
-        };
+        use crate::sample::v1::{Profile as SampleProfileData, SampleProfile};
         use crate::types::{ProfileInterface, Transaction};
 
         struct TestCase {
@@ -294,14 +347,15 @@ mod tests {
                 name: "Find a basic cause of a frame drop".to_string(),
                 profile: Box::new(SampleProfile {
                     event_id: "1234567890".to_string(),
-                    measurements: Some(HashMap::from_iter([(
+                    measurements: Some(Measurements::from_iter([(
                         "frozen_frame_renders".to_string(),
                         Measurement {
-                            unit: "nanosecond".to_string(),
+                            unit: MeasurementUnit::Nanosecond,
                             values: vec![MeasurementValue {
-                                elapsed_since_start_ns: 400_000_000, // 400ms
-                                value: 200_000_000.0,                // 200ms
+                                timestamp_ns: 400_000_000, // 400ms
+                                value: 200_000_000.0,      // 200ms
                             }],
+                            ..Default::default()
                         },
                     )])),
                     transaction: Transaction {
@@ -335,78 +389,93 @@ mod tests {
                 call_trees: HashMap::from_iter([
                     (
                         1_u64,
-                        Vec::from_iter([Rc::new(RefCell::new(Node {
-                            duration_ns: 500_000_000, // 500ms
-                            end_ns: 500_000_000,
-                            is_application: false,
-                            name: "root".to_string(),
-                            package: "package".to_string(),
-                            path: Some("path".to_string()),
-                            frame: Frame {
-                                function: Some("root".to_string()),
-                                in_app: Some(false),
-                                package: Some("package".to_string()),
+                        CallTree::from_nested(Vec::from_iter([NestedNode(
+                            Node {
+                                duration_ns: 500_000_000, // 500ms
+                                end_ns: 500_000_000,
+                                is_application: false,
+                                name: "root".to_string(),
+                                package: "package".to_string(),
                                 path: Some("path".to_string()),
-                                ..Default::default()
-                            },
-                            children: vec![
-                                Rc::new(RefCell::new(Node {
-                                    duration_ns: 200_000_000, // 200ms
-                                    end_ns: 200_000_000,
-                                    is_application: true,
-                                    name: "child1".to_string(),
-                                    package: "package".to_string(),
+                                frame: Frame {
+                                    function: Some("root".to_string()),
+                                    in_app: Some(false),
+                                    package: Some("package".to_string()),
                                     path: Some("path".to_string()),
-                                    frame: Frame {
-                                        function: Some("child1".to_string()),
-                                        in_app: Some(true),
-                                        package: Some("package".to_string()),
-                                        path: Some("path".to_string()),
-                                        ..Default::default()
-                                    },
                                     ..Default::default()
-                                })),
-                                Rc::new(RefCell::new(Node {
-                                    duration_ns: 100_000_000, // 100ms
-                                    end_ns: 300_000_000,
-                                    start_ns: 200_000_000,
-                                    is_application: true,
-                                    name: "child2".to_string(),
-                                    package: "package".to_string(),
-                                    path: Some("path".to_string()),
-                                    frame: Frame {
-                                        function: Some("child2".to_string()),
-                                        in_app: Some(true),
-                                        package: Some("package".to_string()),
+                                }
+                                .into(),
+                                ..Default::default()
+                            },
+                            vec![
+                                NestedNode(
+                                    Node {
+                                        duration_ns: 200_000_000, // 200ms
+                                        end_ns: 200_000_000,
+                                        is_application: true,
+                                        name: "child1".to_string(),
+                                        package: "package".to_string(),
                                         path: Some("path".to_string()),
+                                        frame: Frame {
+                                            function: Some("child1".to_string()),
+                                            in_app: Some(true),
+                                            package: Some("package".to_string()),
+                                            path: Some("path".to_string()),
+                                            ..Default::default()
+                                        }
+                                        .into(),
                                         ..Default::default()
                                     },
-                                    children: vec![Rc::new(RefCell::new(Node {
-                                        duration_ns: 50_000_000, // 50ms
-                                        end_ns: 250_000_000,
+                                    vec![],
+                                ),
+                                NestedNode(
+                                    Node {
+                                        duration_ns: 100_000_000, // 100ms
+                                        end_ns: 300_000_000,
                                         start_ns: 200_000_000,
                                         is_application: true,
-                                        name: "child2-1".to_string(),
+                                        name: "child2".to_string(),
                                         package: "package".to_string(),
                                         path: Some("path".to_string()),
                                         frame: Frame {
-                                            function: Some("child2-1".to_string()),
-                                            in_app: Some(false),
+                                            function: Some("child2".to_string()),
+                                            in_app: Some(true),
                                             package: Some("package".to_string()),
                                             path: Some("path".to_string()),
                                             ..Default::default()
-                                        },
+                                        }
+                                        .into(),
                                         ..Default::default()
-                                    }))],
-                                    ..Default::default()
-                                })),
+                                    },
+                                    vec![NestedNode(
+                                        Node {
+                                            duration_ns: 50_000_000, // 50ms
+                                            end_ns: 250_000_000,
+                                            start_ns: 200_000_000,
+                                            is_application: true,
+                                            name: "child2-1".to_string(),
+                                            package: "package".to_string(),
+                                            path: Some("path".to_string()),
+                                            frame: Frame {
+                                                function: Some("child2-1".to_string()),
+                                                in_app: Some(false),
+                                                package: Some("package".to_string()),
+                                                path: Some("path".to_string()),
+                                                ..Default::default()
+                                            }
+                                            .into(),
+                                            ..Default::default()
+                                        },
+                                        vec![],
+                                    )],
+                                ),
                             ],
-                            ..Default::default()
-                        }))]),
+                        )])),
                     ), // end call_tree for tid 1
                 ]),
                 want: vec![Occurrence {
                     culprit: "some".to_string(),
+                    fingerprint_version: FINGERPRINT_VERSION,
                     issue_title: ISSUE_TITLES[FRAME_DROP].issue_title.to_string(),
                     level: "info".to_string(),
                     payload_type: "occurrence".to_string(),
@@ -417,22 +486,13 @@ mod tests {
                     event: Event {
                         platform: "cocoa".to_string(),
                         stacktrace: StackTrace {
-                            frames: vec![
-                                Frame {
-                                    function: Some("root".to_string()),
-                                    in_app: Some(false),
-                                    package: Some("package".to_string()),
-                                    path: Some("path".to_string()),
-                                    ..Default::default()
-                                },
-                                Frame {
-                                    function: Some("child2".to_string()),
-                                    in_app: Some(true),
-                                    package: Some("package".to_string()),
-                                    path: Some("path".to_string()),
-                                    ..Default::default()
-                                },
-                            ],
+                            frames: vec![Frame {
+                                function: Some("child2".to_string()),
+                                in_app: Some(true),
+                                package: Some("package".to_string()),
+                                path: Some("path".to_string()),
+                                ..Default::default()
+                            }],
                         },
                         ..Default::default()
                     },
@@ -446,6 +506,9 @@ mod tests {
                         transaction_id: "1234".to_string(),
                         transaction_name: "some".to_string(),
                         profile_duration_ns: 500000000,
+                        profile_duration_ms: 500,
+                        offender_duration_ms: 100,
+                        profile_percentage: 20.0,
                         ..Default::default()
                     },
                     evidence_display: vec![
@@ -467,14 +530,15 @@ mod tests {
                 name: "Find a deeper frame than expected".to_string(),
                 profile: Box::new(SampleProfile {
                     event_id: "1234567890".to_string(),
-                    measurements: Some(HashMap::from_iter([(
+                    measurements: Some(Measurements::from_iter([(
                         "frozen_frame_renders".to_string(),
                         Measurement {
-                            unit: "nanosecond".to_string(),
+                            unit: MeasurementUnit::Nanosecond,
                             values: vec![MeasurementValue {
-                                elapsed_since_start_ns: 400_000_000, // 400ms
-                                value: 200_000_000.0,                // 200ms
+                                timestamp_ns: 400_000_000, // 400ms
+                                value: 200_000_000.0,      // 200ms
                             }],
+                            ..Default::default()
                         },
                     )])),
                     transaction: Transaction {
@@ -508,131 +572,155 @@ mod tests {
                 call_trees: HashMap::from_iter([
                     (
                         1_u64,
-                        Vec::from_iter([Rc::new(RefCell::new(Node {
-                            duration_ns: 500_000_000, // 500ms
-                            end_ns: 500_000_000,
-                            is_application: false,
-                            name: "root".to_string(),
-                            package: "package".to_string(),
-                            path: Some("path".to_string()),
-                            frame: Frame {
-                                function: Some("root".to_string()),
-                                in_app: Some(false),
-                                package: Some("package".to_string()),
+                        CallTree::from_nested(Vec::from_iter([NestedNode(
+                            Node {
+                                duration_ns: 500_000_000, // 500ms
+                                end_ns: 500_000_000,
+                                is_application: false,
+                                name: "root".to_string(),
+                                package: "package".to_string(),
                                 path: Some("path".to_string()),
-                                ..Default::default()
-                            },
-                            children: vec![
-                                Rc::new(RefCell::new(Node {
-                                    duration_ns: 20_000_000, // 20ms
-                                    end_ns: 20_000_000,
-                                    is_application: true,
-                                    name: "child1".to_string(),
-                                    package: "package".to_string(),
+                                frame: Frame {
+                                    function: Some("root".to_string()),
+                                    in_app: Some(false),
+                                    package: Some("package".to_string()),
                                     path: Some("path".to_string()),
-                                    frame: Frame {
-                                        function: Some("child1-1".to_string()),
-                                        in_app: Some(true),
-                                        package: Some("package".to_string()),
-                                        path: Some("path".to_string()),
-                                        ..Default::default()
-                                    },
                                     ..Default::default()
-                                })),
-                                Rc::new(RefCell::new(Node {
-                                    duration_ns: 100_000_000, // 100ms
-                                    end_ns: 300_000_000,
-                                    start_ns: 200_000_000,
-                                    is_application: true,
-                                    name: "child2".to_string(),
-                                    package: "package".to_string(),
-                                    path: Some("path".to_string()),
-                                    frame: Frame {
-                                        function: Some("child2".to_string()),
-                                        in_app: Some(true),
-                                        package: Some("package".to_string()),
+                                }
+                                .into(),
+                                ..Default::default()
+                            },
+                            vec![
+                                NestedNode(
+                                    Node {
+                                        duration_ns: 20_000_000, // 20ms
+                                        end_ns: 20_000_000,
+                                        is_application: true,
+                                        name: "child1".to_string(),
+                                        package: "package".to_string(),
                                         path: Some("path".to_string()),
+                                        frame: Frame {
+                                            function: Some("child1-1".to_string()),
+                                            in_app: Some(true),
+                                            package: Some("package".to_string()),
+                                            path: Some("path".to_string()),
+                                            ..Default::default()
+                                        }
+                                        .into(),
                                         ..Default::default()
                                     },
-                                    children: vec![Rc::new(RefCell::new(Node {
+                                    vec![],
+                                ),
+                                NestedNode(
+                                    Node {
                                         duration_ns: 100_000_000, // 100ms
                                         end_ns: 300_000_000,
                                         start_ns: 200_000_000,
-                                        is_application: false,
-                                        name: "child2-1".to_string(),
+                                        is_application: true,
+                                        name: "child2".to_string(),
                                         package: "package".to_string(),
                                         path: Some("path".to_string()),
                                         frame: Frame {
-                                            function: Some("child2-1".to_string()),
-                                            in_app: Some(false),
+                                            function: Some("child2".to_string()),
+                                            in_app: Some(true),
                                             package: Some("package".to_string()),
                                             path: Some("path".to_string()),
                                             ..Default::default()
-                                        },
-                                        children: vec![Rc::new(RefCell::new(Node {
+                                        }
+                                        .into(),
+                                        ..Default::default()
+                                    },
+                                    vec![NestedNode(
+                                        Node {
                                             duration_ns: 100_000_000, // 100ms
                                             end_ns: 300_000_000,
                                             start_ns: 200_000_000,
-                                            is_application: true,
-                                            name: "child2-1-1".to_string(),
+                                            is_application: false,
+                                            name: "child2-1".to_string(),
                                             package: "package".to_string(),
                                             path: Some("path".to_string()),
                                             frame: Frame {
-                                                function: Some("child2-1-1".to_string()),
-                                                in_app: Some(true),
+                                                function: Some("child2-1".to_string()),
+                                                in_app: Some(false),
                                                 package: Some("package".to_string()),
                                                 path: Some("path".to_string()),
                                                 ..Default::default()
-                                            },
-                                            children: vec![Rc::new(RefCell::new(Node {
+                                            }
+                                            .into(),
+                                            ..Default::default()
+                                        },
+                                        vec![NestedNode(
+                                            Node {
                                                 duration_ns: 100_000_000, // 100ms
                                                 end_ns: 300_000_000,
                                                 start_ns: 200_000_000,
-                                                is_application: false,
-                                                name: "child2-1-1-1".to_string(),
+                                                is_application: true,
+                                                name: "child2-1-1".to_string(),
                                                 package: "package".to_string(),
                                                 path: Some("path".to_string()),
                                                 frame: Frame {
-                                                    function: Some("child2-1-1-1".to_string()),
-                                                    in_app: Some(false),
+                                                    function: Some("child2-1-1".to_string()),
+                                                    in_app: Some(true),
                                                     package: Some("package".to_string()),
                                                     path: Some("path".to_string()),
                                                     ..Default::default()
-                                                },
-                                                children: vec![Rc::new(RefCell::new(Node {
+                                                }
+                                                .into(),
+                                                ..Default::default()
+                                            },
+                                            vec![NestedNode(
+                                                Node {
                                                     duration_ns: 100_000_000, // 100ms
                                                     end_ns: 300_000_000,
                                                     start_ns: 200_000_000,
                                                     is_application: false,
-                                                    name: "child2-1-1-1-1".to_string(),
+                                                    name: "child2-1-1-1".to_string(),
                                                     package: "package".to_string(),
                                                     path: Some("path".to_string()),
                                                     frame: Frame {
-                                                        function: Some(
-                                                            "child2-1-1-1-1".to_string(),
-                                                        ),
+                                                        function: Some("child2-1-1-1".to_string()),
                                                         in_app: Some(false),
                                                         package: Some("package".to_string()),
                                                         path: Some("path".to_string()),
                                                         ..Default::default()
-                                                    },
+                                                    }
+                                                    .into(),
                                                     ..Default::default()
-                                                }))],
-                                                ..Default::default()
-                                            }))],
-                                            ..Default::default()
-                                        }))],
-                                        ..Default::default()
-                                    }))],
-                                    ..Default::default()
-                                })),
+                                                },
+                                                vec![NestedNode(
+                                                    Node {
+                                                        duration_ns: 100_000_000, // 100ms
+                                                        end_ns: 300_000_000,
+                                                        start_ns: 200_000_000,
+                                                        is_application: false,
+                                                        name: "child2-1-1-1-1".to_string(),
+                                                        package: "package".to_string(),
+                                                        path: Some("path".to_string()),
+                                                        frame: Frame {
+                                                            function: Some(
+                                                                "child2-1-1-1-1".to_string(),
+                                                            ),
+                                                            in_app: Some(false),
+                                                            package: Some("package".to_string()),
+                                                            path: Some("path".to_string()),
+                                                            ..Default::default()
+                                                        }
+                                                        .into(),
+                                                        ..Default::default()
+                                                    },
+                                                    vec![],
+                                                )],
+                                            )],
+                                        )],
+                                    )],
+                                ),
                             ],
-                            ..Default::default()
-                        }))]),
+                        )])),
                     ), // end call_tree for tid 1
                 ]),
                 want: vec![Occurrence {
                     culprit: "some".to_string(),
+                    fingerprint_version: FINGERPRINT_VERSION,
                     issue_title: ISSUE_TITLES[FRAME_DROP].issue_title.to_string(),
                     level: "info".to_string(),
                     payload_type: "occurrence".to_string(),
@@ -644,13 +732,6 @@ mod tests {
                         platform: "cocoa".to_string(),
                         stacktrace: StackTrace {
                             frames: vec![
-                                Frame {
-                                    function: Some("root".to_string()),
-                                    in_app: Some(false),
-                                    package: Some("package".to_string()),
-                                    path: Some("path".to_string()),
-                                    ..Default::default()
-                                },
                                 Frame {
                                     function: Some("child2".to_string()),
                                     in_app: Some(true),
@@ -686,6 +767,9 @@ mod tests {
                         transaction_id: "1234".to_string(),
                         transaction_name: "some".to_string(),
                         profile_duration_ns: 500000000,
+                        profile_duration_ms: 500,
+                        offender_duration_ms: 100,
+                        profile_percentage: 20.0,
                         ..Default::default()
                     },
                     evidence_display: vec![
@@ -707,14 +791,15 @@ mod tests {
                 name: "Find a deeper and longer frame in shorter parent system frame".to_string(),
                 profile: Box::new(SampleProfile {
                     event_id: "1234567890".to_string(),
-                    measurements: Some(HashMap::from_iter([(
+                    measurements: Some(Measurements::from_iter([(
                         "frozen_frame_renders".to_string(),
                         Measurement {
-                            unit: "nanosecond".to_string(),
+                            unit: MeasurementUnit::Nanosecond,
                             values: vec![MeasurementValue {
-                                elapsed_since_start_ns: 400_000_000, // 400ms
-                                value: 300_000_000.0,                // 300ms
+                                timestamp_ns: 400_000_000, // 400ms
+                                value: 300_000_000.0,      // 300ms
                             }],
+                            ..Default::default()
                         },
                     )])),
                     transaction: Transaction {
@@ -748,128 +833,153 @@ mod tests {
                 call_trees: HashMap::from_iter([
                     (
                         1_u64,
-                        Vec::from_iter([Rc::new(RefCell::new(Node {
-                            duration_ns: 500_000_000, // 500ms
-                            end_ns: 500_000_000,
-                            is_application: false,
-                            name: "root".to_string(),
-                            package: "package".to_string(),
-                            path: Some("path".to_string()),
-                            frame: Frame {
-                                function: Some("root".to_string()),
-                                in_app: Some(false),
-                                package: Some("package".to_string()),
+                        CallTree::from_nested(Vec::from_iter([NestedNode(
+                            Node {
+                                duration_ns: 500_000_000, // 500ms
+                                end_ns: 500_000_000,
+                                is_application: false,
+                                name: "root".to_string(),
+                                package: "package".to_string(),
                                 path: Some("path".to_string()),
+                                frame: Frame {
+                                    function: Some("root".to_string()),
+                                    in_app: Some(false),
+                                    package: Some("package".to_string()),
+                                    path: Some("path".to_string()),
+                                    ..Default::default()
+                                }
+                                .into(),
                                 ..Default::default()
                             },
-                            children: vec![
-                                Rc::new(RefCell::new(Node {
-                                    duration_ns: 50_000_000, // 50ms
-                                    end_ns: 50_000_000,
-                                    is_application: true,
-                                    name: "child1".to_string(),
-                                    package: "package".to_string(),
-                                    path: Some("path".to_string()),
-                                    frame: Frame {
-                                        function: Some("child1".to_string()),
-                                        in_app: Some(true),
-                                        package: Some("package".to_string()),
-                                        path: Some("path".to_string()),
-                                        ..Default::default()
-                                    },
-                                    children: vec![Rc::new(RefCell::new(Node {
+                            vec![
+                                NestedNode(
+                                    Node {
                                         duration_ns: 50_000_000, // 50ms
                                         end_ns: 50_000_000,
                                         is_application: true,
-                                        name: "child1-1".to_string(),
+                                        name: "child1".to_string(),
                                         package: "package".to_string(),
                                         path: Some("path".to_string()),
                                         frame: Frame {
-                                            function: Some("child1-1".to_string()),
+                                            function: Some("child1".to_string()),
                                             in_app: Some(true),
                                             package: Some("package".to_string()),
                                             path: Some("path".to_string()),
                                             ..Default::default()
-                                        },
-                                        ..Default::default()
-                                    }))],
-                                    ..Default::default()
-                                })),
-                                Rc::new(RefCell::new(Node {
-                                    duration_ns: 200_000_000, // 200ms
-                                    end_ns: 250_000_000,
-                                    start_ns: 50_000_000,
-                                    is_application: false,
-                                    name: "child2".to_string(),
-                                    package: "package".to_string(),
-                                    path: Some("path".to_string()),
-                                    frame: Frame {
-                                        function: Some("child2".to_string()),
-                                        in_app: Some(false),
-                                        package: Some("package".to_string()),
-                                        path: Some("path".to_string()),
+                                        }
+                                        .into(),
                                         ..Default::default()
                                     },
-                                    children: vec![Rc::new(RefCell::new(Node {
-                                        duration_ns: 150_000_000, // 150ms
+                                    vec![NestedNode(
+                                        Node {
+                                            duration_ns: 50_000_000, // 50ms
+                                            end_ns: 50_000_000,
+                                            is_application: true,
+                                            name: "child1-1".to_string(),
+                                            package: "package".to_string(),
+                                            path: Some("path".to_string()),
+                                            frame: Frame {
+                                                function: Some("child1-1".to_string()),
+                                                in_app: Some(true),
+                                                package: Some("package".to_string()),
+                                                path: Some("path".to_string()),
+                                                ..Default::default()
+                                            }
+                                            .into(),
+                                            ..Default::default()
+                                        },
+                                        vec![],
+                                    )],
+                                ),
+                                NestedNode(
+                                    Node {
+                                        duration_ns: 200_000_000, // 200ms
                                         end_ns: 250_000_000,
-                                        start_ns: 100_000_000,
-                                        is_application: true,
-                                        name: "child2-1".to_string(),
+                                        start_ns: 50_000_000,
+                                        is_application: false,
+                                        name: "child2".to_string(),
                                         package: "package".to_string(),
                                         path: Some("path".to_string()),
                                         frame: Frame {
-                                            function: Some("child2-1".to_string()),
-                                            in_app: Some(true),
+                                            function: Some("child2".to_string()),
+                                            in_app: Some(false),
                                             package: Some("package".to_string()),
                                             path: Some("path".to_string()),
                                             ..Default::default()
-                                        },
-                                        ..Default::default()
-                                    }))],
-                                    ..Default::default()
-                                })),
-                                Rc::new(RefCell::new(Node {
-                                    duration_ns: 250_000_000, // 250ms
-                                    end_ns: 500_000_000,
-                                    start_ns: 250_000_000,
-                                    is_application: false,
-                                    name: "child3".to_string(),
-                                    package: "package".to_string(),
-                                    path: Some("path".to_string()),
-                                    frame: Frame {
-                                        function: Some("child3".to_string()),
-                                        in_app: Some(false),
-                                        package: Some("package".to_string()),
-                                        path: Some("path".to_string()),
+                                        }
+                                        .into(),
                                         ..Default::default()
                                     },
-                                    children: vec![Rc::new(RefCell::new(Node {
-                                        duration_ns: 50_000_000, // 50ms
-                                        end_ns: 300_000_000,
+                                    vec![NestedNode(
+                                        Node {
+                                            duration_ns: 150_000_000, // 150ms
+                                            end_ns: 250_000_000,
+                                            start_ns: 100_000_000,
+                                            is_application: true,
+                                            name: "child2-1".to_string(),
+                                            package: "package".to_string(),
+                                            path: Some("path".to_string()),
+                                            frame: Frame {
+                                                function: Some("child2-1".to_string()),
+                                                in_app: Some(true),
+                                                package: Some("package".to_string()),
+                                                path: Some("path".to_string()),
+                                                ..Default::default()
+                                            }
+                                            .into(),
+                                            ..Default::default()
+                                        },
+                                        vec![],
+                                    )],
+                                ),
+                                NestedNode(
+                                    Node {
+                                        duration_ns: 250_000_000, // 250ms
+                                        end_ns: 500_000_000,
                                         start_ns: 250_000_000,
-                                        is_application: true,
-                                        name: "child3-1".to_string(),
+                                        is_application: false,
+                                        name: "child3".to_string(),
                                         package: "package".to_string(),
                                         path: Some("path".to_string()),
                                         frame: Frame {
-                                            function: Some("child3-1".to_string()),
-                                            in_app: Some(true),
+                                            function: Some("child3".to_string()),
+                                            in_app: Some(false),
                                             package: Some("package".to_string()),
                                             path: Some("path".to_string()),
                                             ..Default::default()
-                                        },
+                                        }
+                                        .into(),
                                         ..Default::default()
-                                    }))],
-                                    ..Default::default()
-                                })),
+                                    },
+                                    vec![NestedNode(
+                                        Node {
+                                            duration_ns: 50_000_000, // 50ms
+                                            end_ns: 300_000_000,
+                                            start_ns: 250_000_000,
+                                            is_application: true,
+                                            name: "child3-1".to_string(),
+                                            package: "package".to_string(),
+                                            path: Some("path".to_string()),
+                                            frame: Frame {
+                                                function: Some("child3-1".to_string()),
+                                                in_app: Some(true),
+                                                package: Some("package".to_string()),
+                                                path: Some("path".to_string()),
+                                                ..Default::default()
+                                            }
+                                            .into(),
+                                            ..Default::default()
+                                        },
+                                        vec![],
+                                    )],
+                                ),
                             ],
-                            ..Default::default()
-                        }))]),
+                        )])),
                     ), // end call_tree for tid 1
                 ]),
                 want: vec![Occurrence {
                     culprit: "some".to_string(),
+                    fingerprint_version: FINGERPRINT_VERSION,
                     issue_title: ISSUE_TITLES[FRAME_DROP].issue_title.to_string(),
                     level: "info".to_string(),
                     payload_type: "occurrence".to_string(),
@@ -880,29 +990,13 @@ mod tests {
                     event: Event {
                         platform: "cocoa".to_string(),
                         stacktrace: StackTrace {
-                            frames: vec![
-                                Frame {
-                                    function: Some("root".to_string()),
-                                    in_app: Some(false),
-                                    package: Some("package".to_string()),
-                                    path: Some("path".to_string()),
-                                    ..Default::default()
-                                },
-                                Frame {
-                                    function: Some("child2".to_string()),
-                                    in_app: Some(false),
-                                    package: Some("package".to_string()),
-                                    path: Some("path".to_string()),
-                                    ..Default::default()
-                                },
-                                Frame {
-                                    function: Some("child2-1".to_string()),
-                                    in_app: Some(true),
-                                    package: Some("package".to_string()),
-                                    path: Some("path".to_string()),
-                                    ..Default::default()
-                                },
-                            ],
+                            frames: vec![Frame {
+                                function: Some("child2-1".to_string()),
+                                in_app: Some(true),
+                                package: Some("package".to_string()),
+                                path: Some("path".to_string()),
+                                ..Default::default()
+                            }],
                         },
                         ..Default::default()
                     },
@@ -916,6 +1010,9 @@ mod tests {
                         transaction_id: "1234".to_string(),
                         transaction_name: "some".to_string(),
                         profile_duration_ns: 500000000,
+                        profile_duration_ms: 500,
+                        offender_duration_ms: 150,
+                        profile_percentage: 30.0,
                         ..Default::default()
                     },
                     evidence_display: vec![
@@ -937,14 +1034,15 @@ mod tests {
                 name: "Make sure we're biased towards earlier frames".to_string(),
                 profile: Box::new(SampleProfile {
                     event_id: "1234567890".to_string(),
-                    measurements: Some(HashMap::from_iter([(
+                    measurements: Some(Measurements::from_iter([(
                         "frozen_frame_renders".to_string(),
                         Measurement {
-                            unit: "nanosecond".to_string(),
+                            unit: MeasurementUnit::Nanosecond,
                             values: vec![MeasurementValue {
-                                elapsed_since_start_ns: 500_000_000, // 500ms
-                                value: 500_000_000.0,                // 500ms
+                                timestamp_ns: 500_000_000, // 500ms
+                                value: 500_000_000.0,      // 500ms
                             }],
+                            ..Default::default()
                         },
                     )])),
                     transaction: Transaction {
@@ -978,95 +1076,114 @@ mod tests {
                 call_trees: HashMap::from_iter([
                     (
                         1_u64,
-                        Vec::from_iter([Rc::new(RefCell::new(Node {
-                            duration_ns: 1_000_000_000, // 1000ms
-                            end_ns: 1_000_000_000,
-                            is_application: false,
-                            name: "root".to_string(),
-                            package: "package".to_string(),
-                            path: Some("path".to_string()),
-                            frame: Frame {
-                                function: Some("root".to_string()),
-                                in_app: Some(false),
-                                package: Some("package".to_string()),
+                        CallTree::from_nested(Vec::from_iter([NestedNode(
+                            Node {
+                                duration_ns: 1_000_000_000, // 1000ms
+                                end_ns: 1_000_000_000,
+                                is_application: false,
+                                name: "root".to_string(),
+                                package: "package".to_string(),
                                 path: Some("path".to_string()),
+                                frame: Frame {
+                                    function: Some("root".to_string()),
+                                    in_app: Some(false),
+                                    package: Some("package".to_string()),
+                                    path: Some("path".to_string()),
+                                    ..Default::default()
+                                }
+                                .into(),
                                 ..Default::default()
                             },
-                            children: vec![
-                                Rc::new(RefCell::new(Node {
-                                    duration_ns: 50_000_000, // 50ms
-                                    end_ns: 50_000_000,
-                                    is_application: true,
-                                    name: "child1".to_string(),
-                                    package: "package".to_string(),
-                                    path: Some("path".to_string()),
-                                    frame: Frame {
-                                        function: Some("child1".to_string()),
-                                        in_app: Some(true),
-                                        package: Some("package".to_string()),
+                            vec![
+                                NestedNode(
+                                    Node {
+                                        duration_ns: 50_000_000, // 50ms
+                                        end_ns: 50_000_000,
+                                        is_application: true,
+                                        name: "child1".to_string(),
+                                        package: "package".to_string(),
                                         path: Some("path".to_string()),
+                                        frame: Frame {
+                                            function: Some("child1".to_string()),
+                                            in_app: Some(true),
+                                            package: Some("package".to_string()),
+                                            path: Some("path".to_string()),
+                                            ..Default::default()
+                                        }
+                                        .into(),
                                         ..Default::default()
                                     },
-                                    ..Default::default()
-                                })),
-                                Rc::new(RefCell::new(Node {
-                                    duration_ns: 200_000_000, // 200ms
-                                    end_ns: 300_000_000,
-                                    start_ns: 100_000_000,
-                                    is_application: false,
-                                    name: "child2".to_string(),
-                                    package: "package".to_string(),
-                                    path: Some("path".to_string()),
-                                    frame: Frame {
-                                        function: Some("child2".to_string()),
-                                        in_app: Some(false),
-                                        package: Some("package".to_string()),
+                                    vec![],
+                                ),
+                                NestedNode(
+                                    Node {
+                                        duration_ns: 200_000_000, // 200ms
+                                        end_ns: 300_000_000,
+                                        start_ns: 100_000_000,
+                                        is_application: false,
+                                        name: "child2".to_string(),
+                                        package: "package".to_string(),
                                         path: Some("path".to_string()),
+                                        frame: Frame {
+                                            function: Some("child2".to_string()),
+                                            in_app: Some(false),
+                                            package: Some("package".to_string()),
+                                            path: Some("path".to_string()),
+                                            ..Default::default()
+                                        }
+                                        .into(),
                                         ..Default::default()
                                     },
-                                    children: vec![Rc::new(RefCell::new(Node {
+                                    vec![NestedNode(
+                                        Node {
+                                            duration_ns: 250_000_000, // 250ms
+                                            end_ns: 350_000_000,
+                                            start_ns: 100_000_000,
+                                            is_application: true,
+                                            name: "child2-1".to_string(),
+                                            package: "package".to_string(),
+                                            path: Some("path".to_string()),
+                                            frame: Frame {
+                                                function: Some("child2-1".to_string()),
+                                                in_app: Some(true),
+                                                package: Some("package".to_string()),
+                                                path: Some("path".to_string()),
+                                                ..Default::default()
+                                            }
+                                            .into(),
+                                            ..Default::default()
+                                        },
+                                        vec![],
+                                    )],
+                                ),
+                                NestedNode(
+                                    Node {
                                         duration_ns: 250_000_000, // 250ms
-                                        end_ns: 350_000_000,
-                                        start_ns: 100_000_000,
+                                        end_ns: 500_000_000,
+                                        start_ns: 250_000_000,
                                         is_application: true,
-                                        name: "child2-1".to_string(),
+                                        name: "child3".to_string(),
                                         package: "package".to_string(),
                                         path: Some("path".to_string()),
                                         frame: Frame {
-                                            function: Some("child2-1".to_string()),
+                                            function: Some("child3".to_string()),
                                             in_app: Some(true),
                                             package: Some("package".to_string()),
                                             path: Some("path".to_string()),
                                             ..Default::default()
-                                        },
-                                        ..Default::default()
-                                    }))],
-                                    ..Default::default()
-                                })),
-                                Rc::new(RefCell::new(Node {
-                                    duration_ns: 250_000_000, // 250ms
-                                    end_ns: 500_000_000,
-                                    start_ns: 250_000_000,
-                                    is_application: true,
-                                    name: "child3".to_string(),
-                                    package: "package".to_string(),
-                                    path: Some("path".to_string()),
-                                    frame: Frame {
-                                        function: Some("child3".to_string()),
-                                        in_app: Some(true),
-                                        package: Some("package".to_string()),
-                                        path: Some("path".to_string()),
+                                        }
+                                        .into(),
                                         ..Default::default()
                                     },
-                                    ..Default::default()
-                                })),
+                                    vec![],
+                                ),
                             ],
-                            ..Default::default()
-                        }))]),
+                        )])),
                     ), // end call_tree for tid 1
                 ]),
                 want: vec![Occurrence {
                     culprit: "some".to_string(),
+                    fingerprint_version: FINGERPRINT_VERSION,
                     issue_title: ISSUE_TITLES[FRAME_DROP].issue_title.to_string(),
                     level: "info".to_string(),
                     payload_type: "occurrence".to_string(),
@@ -1077,29 +1194,13 @@ mod tests {
                     event: Event {
                         platform: "cocoa".to_string(),
                         stacktrace: StackTrace {
-                            frames: vec![
-                                Frame {
-                                    function: Some("root".to_string()),
-                                    in_app: Some(false),
-                                    package: Some("package".to_string()),
-                                    path: Some("path".to_string()),
-                                    ..Default::default()
-                                },
-                                Frame {
-                                    function: Some("child2".to_string()),
-                                    in_app: Some(false),
-                                    package: Some("package".to_string()),
-                                    path: Some("path".to_string()),
-                                    ..Default::default()
-                                },
-                                Frame {
-                                    function: Some("child2-1".to_string()),
-                                    in_app: Some(true),
-                                    package: Some("package".to_string()),
-                                    path: Some("path".to_string()),
-                                    ..Default::default()
-                                },
-                            ],
+                            frames: vec![Frame {
+                                function: Some("child2-1".to_string()),
+                                in_app: Some(true),
+                                package: Some("package".to_string()),
+                                path: Some("path".to_string()),
+                                ..Default::default()
+                            }],
                         },
                         ..Default::default()
                     },
@@ -1113,6 +1214,9 @@ mod tests {
                         transaction_id: "1234".to_string(),
                         transaction_name: "some".to_string(),
                         profile_duration_ns: 500000000,
+                        profile_duration_ms: 500,
+                        offender_duration_ms: 250,
+                        profile_percentage: 50.0,
                         ..Default::default()
                     },
                     evidence_display: vec![
@@ -1148,4 +1252,47 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_find_frame_drop_cause_nodes_in_chunk() {
+        use super::find_frame_drop_cause_nodes;
+        use crate::sample::v2::SampleChunkBuilder;
+        use crate::types::ChunkInterface;
+
+        let mut builder = SampleChunkBuilder::new("cocoa");
+        let main = builder.frame(Frame {
+            function: Some("main".to_string()),
+            package: Some("MyApp".to_string()),
+            in_app: Some(true),
+            ..Default::default()
+        });
+        let decode = builder.frame(Frame {
+            function: Some("decodeImage".to_string()),
+            package: Some("MyApp".to_string()),
+            in_app: Some(true),
+            ..Default::default()
+        });
+        builder
+            .sample("1", 10.0, &[main])
+            .sample("1", 10.1, &[decode, main])
+            .sample("1", 10.4, &[main])
+            .sample("1", 10.5, &[main])
+            // a 300ms frozen frame, ending at 10.4s
+            .measurement("frozen_frame_renders", "millisecond", &[(10.4, 300.0)]);
+        let mut chunk = builder.build();
+        let frame_drops = chunk.measurement("frozen_frame_renders").unwrap().clone();
+
+        let call_trees = chunk.call_trees(None).unwrap();
+        let causes = find_frame_drop_cause_nodes(&call_trees["1"], &frame_drops);
+        assert_eq!(causes.len(), 1);
+        assert_eq!(causes[0].node.name, "decodeImage");
+        assert_eq!(
+            causes[0]
+                .stack_trace
+                .iter()
+                .map(|f| f.function.as_deref().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["main", "decodeImage"]
+        );
+    }
 }
@@ -3,29 +3,45 @@ use std::time::Duration;
 
 use chrono::{DateTime, Utc};
 use once_cell::sync::Lazy;
-use pyo3::{pyclass, pymethods, PyErr};
+use pyo3::{pyclass, pymethods, types::PyAnyMethods, Bound, PyAny, PyErr, PyResult, Python};
 use serde::Serialize;
 use uuid::Uuid;
 
 use crate::{
     android, frame,
+    limits::max_stack_depth,
+    msgpack,
     types::{CallTreesU64, DebugMeta, ProfileInterface},
 };
 
+mod app_start;
+mod deep_recursion;
 mod detect_frame;
+mod detector;
 mod frame_drop;
 
+pub(crate) use app_start::{
+    find_app_start_prelude, AppStartPrelude, PreludeFrame, APP_START_PRELUDE_PLATFORMS,
+};
+pub(crate) use detect_frame::register_frame_detector;
+pub(crate) use detector::detector_categories_by_platform;
+pub(crate) use detector::{
+    calibrate_detectors, detectors, set_detectors_enabled, unregister_detector, CalibrationStats,
+    DetectorInfo,
+};
+pub use detector::{DetectorError, DetectorOptions};
+pub(crate) use frame_drop::FRAME_DROP_PLATFORMS;
+
+use deep_recursion::DEEP_RECURSION;
 // Import category constants from detect_frame module
 use detect_frame::{
     NodeInfo, BASE64_DECODE, BASE64_ENCODE, COMPRESSION, CORE_DATA_BLOCK, CORE_DATA_MERGE,
-    CORE_DATA_READ, CORE_DATA_WRITE, DECOMPRESSION, DETECT_FRAME_JOBS, FILE_READ, FILE_WRITE, HTTP,
-    IMAGE_DECODE, IMAGE_ENCODE, JSON_DECODE, JSON_ENCODE, ML_MODEL_INFERENCE, ML_MODEL_LOAD, REGEX,
-    SOURCE_CONTEXT, SQL, THREAD_WAIT, VIEW_INFLATION, VIEW_LAYOUT, VIEW_RENDER, VIEW_UPDATE, XPC,
+    CORE_DATA_READ, CORE_DATA_WRITE, DB_MAIN_THREAD, DECOMPRESSION, FILE_READ, FILE_WRITE, HTTP,
+    IMAGE_DECODE, IMAGE_ENCODE, JSON_DECODE, JSON_ENCODE, ML_MODEL_INFERENCE, ML_MODEL_LOAD,
+    NETWORK_MAIN_THREAD, REGEX, SOURCE_CONTEXT, SQL, THREAD_WAIT, VIEW_INFLATION, VIEW_LAYOUT,
+    VIEW_RENDER, VIEW_UPDATE, XPC,
 };
 
-// Import frame drop detection function
-use frame_drop::find_frame_drop_cause;
-
 // Type constants: DO NOT REMOVE COMMENTED TYPES!
 pub const NONE_TYPE: u64 = 0;
 pub const CORE_DATA_TYPE: u64 = 2004;
@@ -49,6 +65,17 @@ pub const EVIDENCE_NAME_PACKAGE: &str = "Package";
 // Other constants
 pub const OCCURRENCE_PAYLOAD: &str = "occurrence";
 
+/// Version of the occurrence fingerprint scheme, see [`occurrence_fingerprint`].
+/// Bumping it regroups every issue, so it has to be a deliberate change.
+pub const FINGERPRINT_VERSION: u32 = 2;
+
+// the versions occurrence_fingerprint can compute
+const _: () = assert!(matches!(FINGERPRINT_VERSION, 1 | 2));
+
+/// Salt of the version 2 fingerprint scheme. It's a constant so fingerprints
+/// don't depend on anything specific to the profile.
+const FINGERPRINT_SALT_V2: &str = "vroomrs:occurrence:v2";
+
 // FRAME_DROP constant (not defined in detect_frame.rs)
 const FRAME_DROP: &str = "frame_drop";
 
@@ -108,6 +135,11 @@ pub struct EvidenceData {
     transaction_name: String,
     profile_id: String,
     sample_count: Option<u64>,
+    /// Durations in milliseconds, and the share of the profile the offending
+    /// frame took, for alert rules to threshold on.
+    profile_duration_ms: u64,
+    offender_duration_ms: u64,
+    profile_percentage: f64,
 }
 
 #[pymethods]
@@ -201,6 +233,34 @@ impl EvidenceData {
     pub fn get_sample_count(&self) -> Option<u64> {
         self.sample_count
     }
+
+    /// Returns the profile duration in milliseconds.
+    ///
+    /// Returns:
+    ///     int
+    ///         Total duration of the profile in milliseconds.
+    pub fn get_profile_duration_ms(&self) -> u64 {
+        self.profile_duration_ms
+    }
+
+    /// Returns the duration of the offending frame in milliseconds.
+    ///
+    /// Returns:
+    ///     int
+    ///         Duration of the frame in milliseconds.
+    pub fn get_offender_duration_ms(&self) -> u64 {
+        self.offender_duration_ms
+    }
+
+    /// Returns the share of the profile the offending frame took.
+    ///
+    /// Returns:
+    ///     float
+    ///         The frame duration, as a percentage of the profile duration
+    ///         (e.g. 12.5 for 12.5%), or 0 if the profile has no duration.
+    pub fn get_profile_percentage(&self) -> f64 {
+        self.profile_percentage
+    }
 }
 
 #[pyclass]
@@ -212,6 +272,7 @@ pub struct Occurrence {
     pub evidence_data: EvidenceData,
     pub evidence_display: Vec<Evidence>,
     pub fingerprint: Vec<String>,
+    pub fingerprint_version: u32,
     pub id: String,
     pub issue_title: String,
     pub level: String,
@@ -283,6 +344,15 @@ impl Occurrence {
         &self.fingerprint
     }
 
+    /// Returns the version of the scheme used to compute the fingerprint.
+    ///
+    /// Returns:
+    ///     int
+    ///         The fingerprint scheme version.
+    pub fn get_fingerprint_version(&self) -> u32 {
+        self.fingerprint_version
+    }
+
     /// Returns the occurrence ID.
     ///
     /// Returns:
@@ -400,6 +470,88 @@ impl Occurrence {
         serde_json::to_string(self)
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))
     }
+
+    /// Serializes the occurrence to MessagePack, with the same fields as
+    /// :meth:`to_json_str`.
+    ///
+    /// Returns:
+    ///     bytes
+    ///         The MessagePack encoding of the occurrence.
+    ///
+    /// Raises:
+    ///     ValueError
+    ///         If the serialization fails due to invalid data.
+    ///
+    /// Example:
+    ///     >>> payload = occurrence.to_msgpack()
+    pub fn to_msgpack(&self) -> Result<Vec<u8>, PyErr> {
+        msgpack::to_vec(self)
+    }
+
+    /// Returns the occurrence as an issue platform payload, to be published
+    /// as is to the occurrences topic.
+    ///
+    /// The payload holds the fields the issue platform expects (evidence,
+    /// fingerprint, subtitle, culprit, and the event with its stack trace and
+    /// tags), leaving out the ones only used for stats (category, duration and
+    /// sample count).
+    ///
+    /// Returns:
+    ///     dict[str, Any]
+    ///         The issue platform payload.
+    ///
+    /// Raises:
+    ///     ValueError
+    ///         If the serialization fails due to invalid data.
+    ///
+    /// Example:
+    ///     >>> for occurrence in occurrences.occurrences:
+    ///     ...     producer.produce("ingest-occurrences", json.dumps(occurrence.to_dict()))
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let payload = serde_json::to_string(&self.issue_platform_payload())
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+        py.import("json")?.call_method1("loads", (payload,))
+    }
+}
+
+/// The fields of an occurrence sent to the issue platform.
+#[derive(Debug, Serialize)]
+pub struct IssuePlatformPayload<'a> {
+    culprit: &'a str,
+    detection_time: DateTime<Utc>,
+    event: &'a Event,
+    evidence_data: &'a EvidenceData,
+    evidence_display: &'a [Evidence],
+    fingerprint: &'a [String],
+    id: &'a str,
+    issue_title: &'a str,
+    level: &'a str,
+    payload_type: &'a str,
+    project_id: u64,
+    resource_id: Option<&'a str>,
+    subtitle: &'a str,
+    r#type: u64,
+}
+
+impl Occurrence {
+    pub fn issue_platform_payload(&self) -> IssuePlatformPayload<'_> {
+        IssuePlatformPayload {
+            culprit: &self.culprit,
+            detection_time: self.detection_time,
+            event: &self.event,
+            evidence_data: &self.evidence_data,
+            evidence_display: &self.evidence_display,
+            fingerprint: &self.fingerprint,
+            id: &self.id,
+            issue_title: &self.issue_title,
+            level: &self.level,
+            payload_type: &self.payload_type,
+            project_id: self.project_id,
+            resource_id: self.resource_id.as_deref(),
+            subtitle: &self.subtitle,
+            r#type: self.r#type,
+        }
+    }
 }
 
 pub struct CategoryMetadata {
@@ -426,6 +578,13 @@ pub struct Event {
 // Static lazy HashMap for issue titles
 pub static ISSUE_TITLES: Lazy<HashMap<&'static str, CategoryMetadata>> = Lazy::new(|| {
     HashMap::from([
+        (
+            app_start::APP_START_PRELUDE,
+            CategoryMetadata {
+                issue_title: "Slow App Start Prelude",
+                r#type: NONE_TYPE,
+            },
+        ),
         (
             BASE64_DECODE,
             CategoryMetadata {
@@ -475,6 +634,20 @@ pub static ISSUE_TITLES: Lazy<HashMap<&'static str, CategoryMetadata>> = Lazy::n
                 r#type: CORE_DATA_TYPE,
             },
         ),
+        (
+            DB_MAIN_THREAD,
+            CategoryMetadata {
+                issue_title: "Database operation on Main Thread",
+                r#type: CORE_DATA_TYPE,
+            },
+        ),
+        (
+            DEEP_RECURSION,
+            CategoryMetadata {
+                issue_title: "Deep Recursion",
+                r#type: NONE_TYPE,
+            },
+        ),
         (
             DECOMPRESSION,
             CategoryMetadata {
@@ -552,6 +725,13 @@ pub static ISSUE_TITLES: Lazy<HashMap<&'static str, CategoryMetadata>> = Lazy::n
                 r#type: NONE_TYPE,
             },
         ),
+        (
+            NETWORK_MAIN_THREAD,
+            CategoryMetadata {
+                issue_title: "Synchronous Network Request on Main Thread",
+                r#type: NONE_TYPE,
+            },
+        ),
         (
             REGEX,
             CategoryMetadata {
@@ -583,7 +763,7 @@ pub static ISSUE_TITLES: Lazy<HashMap<&'static str, CategoryMetadata>> = Lazy::n
         (
             VIEW_INFLATION,
             CategoryMetadata {
-                issue_title: "SwiftUI View Inflation is slow",
+                issue_title: "View Inflation is slow",
                 r#type: NONE_TYPE,
             },
         ),
@@ -625,6 +805,12 @@ pub fn generate_evidence_data(
     node_info: &NodeInfo,
 ) -> EvidenceData {
     let transaction = profile.get_transaction();
+    let profile_duration_ns = profile.duration_ns();
+    let profile_percentage = if profile_duration_ns > 0 {
+        (node_info.node.duration_ns as f64 * 100.0) / profile_duration_ns as f64
+    } else {
+        0.0
+    };
 
     let mut evidence_data = EvidenceData {
         frame_duration_ns: node_info.node.duration_ns,
@@ -643,12 +829,15 @@ pub fn generate_evidence_data(
             .as_deref()
             .unwrap_or("")
             .to_string(),
-        profile_duration_ns: profile.duration_ns(),
+        profile_duration_ns,
         template_name: "profile".to_string(),
         transaction_id: transaction.id.clone(),
         transaction_name: transaction.name.clone(),
         profile_id: profile.get_profile_id().to_string(),
         sample_count: None,
+        profile_duration_ms: profile_duration_ns / 1_000_000,
+        offender_duration_ms: node_info.node.duration_ns / 1_000_000,
+        profile_percentage,
     };
 
     // Special handling based on category and platform
@@ -758,11 +947,90 @@ pub fn normalize_android_stack_trace(st: &mut Vec<frame::Frame>) {
     }
 }
 
+/// Trims an evidence stack trace (ordered from the root frame) the same way
+/// the issue platform trims crash stacks, so performance issues group
+/// consistently with error issues:
+/// - the runtime prelude below the deepest `main` function is dropped,
+/// - the system frames above the outermost in_app frame are dropped, the
+///   frames from it down to the detected frame being kept,
+/// - consecutive repeated frames (recursion) are collapsed into one,
+/// - only the [`max_stack_depth`] frames closest to the detected frame are kept.
+pub fn trim_evidence_stack_trace(st: &mut Vec<frame::Frame>) {
+    if let Some(main_index) = st.iter().rposition(is_main_frame) {
+        st.drain(..main_index);
+    }
+
+    if let Some(in_app_index) = st.iter().position(|frame| frame.in_app == Some(true)) {
+        st.drain(..in_app_index);
+    }
+
+    st.dedup_by(|a, b| a.function == b.function && a.module_or_package() == b.module_or_package());
+
+    let max_depth = max_stack_depth() as usize;
+    if st.len() > max_depth {
+        st.drain(..st.len() - max_depth);
+    }
+}
+
+/// Returns whether the frame is a program entry point, e.g. `main` or
+/// `ActivityThread.main(java.lang.String[])` once android frames are normalized.
+fn is_main_frame(frame: &frame::Frame) -> bool {
+    let Some(function) = frame.function.as_deref() else {
+        return false;
+    };
+    let name = function.split('(').next().unwrap_or_default();
+    name == "main" || name.ends_with(".main")
+}
+
 /// Generates a unique event ID.
 fn event_id() -> String {
     Uuid::new_v4().to_string().replace("-", "")
 }
 
+/// Computes the fingerprint used to group occurrences into issues.
+///
+/// Versions:
+/// - 1: MD5 of the project ID, issue title, issue type, frame module (or
+///   package) and function name. Renaming an issue title regroups it.
+/// - 2: MD5 of a constant salt, the category, the function fingerprint of
+///   the detected frame (as in function metrics) and the node package,
+///   separated by NUL bytes. The function fingerprint is computed on the
+///   frame as sent by the SDK, so the same function detected in different
+///   chunks of a profiler session, or with a differently normalized name,
+///   is grouped into the same issue.
+///
+/// The frame identity of version 1 is normalized beforehand (e.g. android
+/// function names are stripped from their package).
+fn occurrence_fingerprint(
+    version: u32,
+    project_id: u64,
+    title: &str,
+    issue_type: u64,
+    ni: &NodeInfo,
+) -> String {
+    let mut hasher = md5::Context::new();
+    match version {
+        1 => {
+            hasher.consume(project_id.to_string().as_bytes());
+            hasher.consume(title.as_bytes());
+            hasher.consume(issue_type.to_string().as_bytes());
+            hasher.consume(ni.node.frame.module_or_package().as_bytes());
+            hasher.consume(ni.node.name.as_bytes());
+        }
+        2 => {
+            hasher.consume(FINGERPRINT_SALT_V2.as_bytes());
+            hasher.consume(b"\0");
+            hasher.consume(ni.category.as_bytes());
+            hasher.consume(b"\0");
+            hasher.consume(ni.node.frame.fingerprint(None).to_string().as_bytes());
+            hasher.consume(b"\0");
+            hasher.consume(ni.node.package.as_bytes());
+        }
+        _ => unreachable!("unsupported fingerprint version {version}"),
+    }
+    format!("{:x}", hasher.compute())
+}
+
 /// Creates a new occurrence from profile data and node information.
 /// This is the Rust equivalent of the Go NewOccurrence function.
 pub fn new_occurrence(profile: &dyn ProfileInterface, mut ni: NodeInfo) -> Occurrence {
@@ -785,14 +1053,15 @@ pub fn new_occurrence(profile: &dyn ProfileInterface, mut ni: NodeInfo) -> Occur
             android::strip_package_name_from_full_method_name(&ni.node.name, &ni.node.package);
     }
 
-    // Create fingerprint using MD5 hash
-    let mut hasher = md5::Context::new();
-    hasher.consume(profile.get_project_id().to_string().as_bytes());
-    hasher.consume(title.as_bytes());
-    hasher.consume(issue_type.to_string().as_bytes());
-    hasher.consume(ni.node.frame.module_or_package().as_bytes());
-    hasher.consume(ni.node.name.as_bytes());
-    let fingerprint = format!("{:x}", hasher.compute());
+    trim_evidence_stack_trace(&mut ni.stack_trace);
+
+    let fingerprint = occurrence_fingerprint(
+        FINGERPRINT_VERSION,
+        profile.get_project_id(),
+        &title,
+        issue_type,
+        &ni,
+    );
 
     // Get transaction tags or create empty map
     let tags = profile.get_transaction_tags().clone();
@@ -820,6 +1089,7 @@ pub fn new_occurrence(profile: &dyn ProfileInterface, mut ni: NodeInfo) -> Occur
         evidence_data: generate_evidence_data(profile, &ni),
         evidence_display: generate_evidence_display(profile, &ni),
         fingerprint: vec![fingerprint],
+        fingerprint_version: FINGERPRINT_VERSION,
         id: event_id(),
         issue_title: title,
         level: "info".to_string(),
@@ -836,24 +1106,14 @@ pub fn new_occurrence(profile: &dyn ProfileInterface, mut ni: NodeInfo) -> Occur
     }
 }
 
-/// Finds occurrences in a profile by detecting frames and frame drop causes.
+/// Finds occurrences in a profile by running the enabled detectors of its
+/// platform.
 pub fn find_occurences(
     profile: &dyn ProfileInterface,
     call_trees: &CallTreesU64,
+    options: &DetectorOptions,
 ) -> Vec<Occurrence> {
-    let mut occurrences = Vec::new();
-
-    // Check if there are detection jobs for this platform
-    if let Some(jobs) = DETECT_FRAME_JOBS.get(&profile.get_platform()) {
-        for options in jobs {
-            detect_frame::detect_frame(profile, call_trees, options.as_ref(), &mut occurrences);
-        }
-    }
-
-    // Find frame drop causes
-    find_frame_drop_cause(profile, call_trees, &mut occurrences);
-
-    occurrences
+    detector::run_detectors(profile, call_trees, options)
 }
 
 #[cfg(test)]
@@ -957,4 +1217,216 @@ mod tests {
             assert_eq!(tt.input, tt.output, "Test '{}' failed", tt.name);
         }
     }
+
+    #[test]
+    fn test_occurrence_fingerprint() {
+        let node_info = |category: &str| NodeInfo {
+            category: category.to_string(),
+            node: crate::nodetree::Node {
+                name: "-[NSData(NSData) initWithContentsOfURL:]".to_string(),
+                package: "Foundation".to_string(),
+                frame: frame::Frame {
+                    package: Some("Foundation".to_string()),
+                    ..Default::default()
+                }
+                .into(),
+                ..Default::default()
+            },
+            stack_trace: vec![],
+        };
+
+        // version 2 depends on the category, the function and the package
+        let v2 = occurrence_fingerprint(2, 1, "File I/O on Main Thread", 0, &node_info(FILE_READ));
+        assert_eq!(
+            v2,
+            occurrence_fingerprint(2, 2, "Another title", 2001, &node_info(FILE_READ))
+        );
+        assert_ne!(
+            v2,
+            occurrence_fingerprint(2, 1, "File I/O on Main Thread", 0, &node_info(FILE_WRITE))
+        );
+        let mut renamed = node_info(FILE_READ);
+        renamed.node.name = "initWithContentsOfURL:".to_string();
+        assert_eq!(
+            v2,
+            occurrence_fingerprint(2, 1, "File I/O on Main Thread", 0, &renamed)
+        );
+        let mut other_package = node_info(FILE_READ);
+        other_package.node.package = "CoreFoundation".to_string();
+        assert_ne!(
+            v2,
+            occurrence_fingerprint(2, 1, "File I/O on Main Thread", 0, &other_package)
+        );
+
+        // version 1 depends on the project and the issue title
+        let v1 = occurrence_fingerprint(1, 1, "File I/O on Main Thread", 0, &node_info(FILE_READ));
+        assert_ne!(v1, v2);
+        assert_ne!(
+            v1,
+            occurrence_fingerprint(1, 1, "Another title", 0, &node_info(FILE_READ))
+        );
+        assert_eq!(
+            v1,
+            occurrence_fingerprint(1, 1, "File I/O on Main Thread", 0, &node_info(FILE_WRITE))
+        );
+    }
+
+    #[test]
+    fn test_issue_platform_payload() {
+        let occurrence = Occurrence {
+            culprit: "MainActivity".to_string(),
+            fingerprint: vec!["fingerprint".to_string()],
+            issue_title: "File I/O on Main Thread".to_string(),
+            payload_type: OCCURRENCE_PAYLOAD.to_string(),
+            r#type: IMAGE_DECODE_TYPE,
+            category: FILE_READ.to_string(),
+            duration_ns: 10,
+            ..Default::default()
+        };
+        let payload = serde_json::to_value(occurrence.issue_platform_payload()).unwrap();
+        let mut keys: Vec<&str> = payload
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec![
+                "culprit",
+                "detection_time",
+                "event",
+                "evidence_data",
+                "evidence_display",
+                "fingerprint",
+                "id",
+                "issue_title",
+                "level",
+                "payload_type",
+                "project_id",
+                "resource_id",
+                "subtitle",
+                "type",
+            ]
+        );
+        assert_eq!(payload["type"], IMAGE_DECODE_TYPE);
+        assert_eq!(
+            payload["event"]["stacktrace"]["frames"],
+            serde_json::json!([])
+        );
+    }
+
+    #[test]
+    fn test_trim_evidence_stack_trace() {
+        struct TestCase {
+            name: String,
+            input: Vec<&'static str>,
+            output: Vec<&'static str>,
+        }
+
+        let test_cases = [
+            TestCase {
+                name: "drop runtime prelude".to_string(),
+                input: vec!["start", "main", "run", "work"],
+                output: vec!["main", "run", "work"],
+            },
+            TestCase {
+                name: "drop prelude below the deepest android main".to_string(),
+                input: vec![
+                    "ZygoteInit.main(java.lang.String[])",
+                    "RuntimeInit$MethodAndArgsCaller.run()",
+                    "ActivityThread.main(java.lang.String[])",
+                    "Looper.loop()",
+                ],
+                output: vec!["ActivityThread.main(java.lang.String[])", "Looper.loop()"],
+            },
+            TestCase {
+                name: "collapse repeated frames".to_string(),
+                input: vec!["main", "visit", "visit", "visit", "work", "work"],
+                output: vec!["main", "visit", "work"],
+            },
+            TestCase {
+                name: "no main function".to_string(),
+                input: vec!["thread_start", "run", "work"],
+                output: vec!["thread_start", "run", "work"],
+            },
+        ];
+
+        let to_frames = |functions: &[&str]| -> Vec<frame::Frame> {
+            functions
+                .iter()
+                .map(|function| frame::Frame {
+                    function: Some(function.to_string()),
+                    ..Default::default()
+                })
+                .collect()
+        };
+
+        for tt in test_cases {
+            let mut st = to_frames(&tt.input);
+            trim_evidence_stack_trace(&mut st);
+            assert_eq!(st, to_frames(&tt.output), "Test '{}' failed", tt.name);
+        }
+
+        // cap the depth, keeping the frames closest to the detected one
+        let mut st: Vec<frame::Frame> = (0..crate::limits::DEFAULT_MAX_STACK_DEPTH + 10)
+            .map(|i| frame::Frame {
+                function: Some(format!("function_{i}")),
+                ..Default::default()
+            })
+            .collect();
+        trim_evidence_stack_trace(&mut st);
+        assert_eq!(st.len(), crate::limits::DEFAULT_MAX_STACK_DEPTH as usize);
+        assert_eq!(st[0].function.as_deref(), Some("function_10"));
+    }
+
+    #[test]
+    fn test_trim_evidence_stack_trace_in_app() {
+        let to_frames = |functions: &[(&str, bool)]| -> Vec<frame::Frame> {
+            functions
+                .iter()
+                .map(|&(function, in_app)| frame::Frame {
+                    function: Some(function.to_string()),
+                    in_app: Some(in_app),
+                    ..Default::default()
+                })
+                .collect()
+        };
+
+        // keep the app code and the system frames it calls into, down to
+        // the detected frame
+        let mut st = to_frames(&[
+            ("main", false),
+            ("UIApplicationMain", false),
+            ("-[UIApplication sendEvent:]", false),
+            ("-[AppDelegate load]", true),
+            ("-[Store read]", true),
+            ("-[NSData initWithContentsOfFile:]", false),
+            ("read", false),
+        ]);
+        trim_evidence_stack_trace(&mut st);
+        assert_eq!(
+            st,
+            to_frames(&[
+                ("-[AppDelegate load]", true),
+                ("-[Store read]", true),
+                ("-[NSData initWithContentsOfFile:]", false),
+                ("read", false),
+            ])
+        );
+
+        // without app code, only the runtime prelude is dropped
+        let mut st = to_frames(&[
+            ("start", false),
+            ("main", false),
+            ("CFRunLoopRun", false),
+            ("read", false),
+        ]);
+        trim_evidence_stack_trace(&mut st);
+        assert_eq!(
+            st,
+            to_frames(&[("main", false), ("CFRunLoopRun", false), ("read", false)])
+        );
+    }
 }
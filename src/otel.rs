@@ -0,0 +1,323 @@
+//! Exports the heaviest call tree nodes of profile chunks as OpenTelemetry
+//! spans, for customers piping profiling insights into their OTel pipelines.
+
+use std::collections::{BTreeMap, HashSet};
+
+use pyo3::{pyclass, pymethods};
+use serde_json::json;
+
+use crate::{
+    nodetree::{CallTree, NodeId},
+    types::{CallTreesStr, ChunkInterface},
+};
+
+/// Value of the `sentry.origin` attribute of the exported spans.
+pub const SPAN_ORIGIN: &str = "auto.profiling";
+
+/// OTLP span kind of the exported spans, SPAN_KIND_INTERNAL.
+const SPAN_KIND_INTERNAL: u8 = 1;
+
+/// A call tree node exported as an OpenTelemetry span.
+#[pyclass]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OtelSpan {
+    pub trace_id: String,
+    pub span_id: String,
+    /// The span of the closest ancestor of the node also exported.
+    pub parent_span_id: Option<String>,
+    pub name: String,
+    pub start_time_unix_nano: u64,
+    pub end_time_unix_nano: u64,
+    pub attributes: BTreeMap<String, String>,
+}
+
+#[pymethods]
+impl OtelSpan {
+    /// Returns the trace ID of the span, derived from the profiler ID so
+    /// the spans of all the chunks of a profiler session share it.
+    ///
+    /// Returns:
+    ///     str
+    ///         The trace ID, 32 hexadecimal characters.
+    pub fn get_trace_id(&self) -> &str {
+        &self.trace_id
+    }
+
+    /// Returns the span ID, stable across exports of the same chunk.
+    ///
+    /// Returns:
+    ///     str
+    ///         The span ID, 16 hexadecimal characters.
+    pub fn get_span_id(&self) -> &str {
+        &self.span_id
+    }
+
+    /// Returns the span of the closest ancestor of the node which was also
+    /// exported.
+    ///
+    /// Returns:
+    ///     str
+    ///         The parent span ID, or None if no ancestor was exported.
+    pub fn get_parent_span_id(&self) -> Option<&str> {
+        self.parent_span_id.as_deref()
+    }
+
+    /// Returns the name of the span, the function of the node.
+    ///
+    /// Returns:
+    ///     str
+    ///         The function name.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the start of the span.
+    ///
+    /// Returns:
+    ///     int
+    ///         The UTC Unix timestamp in nanoseconds the node started at.
+    pub fn get_start_time_unix_nano(&self) -> u64 {
+        self.start_time_unix_nano
+    }
+
+    /// Returns the end of the span.
+    ///
+    /// Returns:
+    ///     int
+    ///         The UTC Unix timestamp in nanoseconds the node ended at.
+    pub fn get_end_time_unix_nano(&self) -> u64 {
+        self.end_time_unix_nano
+    }
+
+    /// Returns the attributes of the span: the profile it comes from
+    /// (`sentry.origin`, `sentry.profiler_id`, `sentry.chunk_id`), the thread
+    /// (`thread.id`) and the code (`code.function`, `code.namespace`,
+    /// `code.filepath`, `code.lineno`) of the node, and its number of samples
+    /// (`profile.sample_count`).
+    ///
+    /// Returns:
+    ///     dict[str, str]
+    ///         The attributes, by key.
+    pub fn get_attributes(&self) -> BTreeMap<String, String> {
+        self.attributes.clone()
+    }
+
+    /// Serializes the span in the OTLP JSON encoding, to be added to the
+    /// `spans` of a `ScopeSpans`.
+    ///
+    /// Returns:
+    ///     str
+    ///         A JSON string of the span.
+    pub fn to_json_str(&self) -> String {
+        self.to_otlp_json().to_string()
+    }
+}
+
+impl OtelSpan {
+    /// Returns the span in the OTLP JSON encoding, where 64-bit integers
+    /// are encoded as strings.
+    pub fn to_otlp_json(&self) -> serde_json::Value {
+        let mut span = json!({
+            "traceId": self.trace_id,
+            "spanId": self.span_id,
+            "name": self.name,
+            "kind": SPAN_KIND_INTERNAL,
+            "startTimeUnixNano": self.start_time_unix_nano.to_string(),
+            "endTimeUnixNano": self.end_time_unix_nano.to_string(),
+            "attributes": self
+                .attributes
+                .iter()
+                .map(|(key, value)| json!({"key": key, "value": {"stringValue": value}}))
+                .collect::<Vec<_>>(),
+        });
+        if let Some(parent_span_id) = &self.parent_span_id {
+            span["parentSpanId"] = json!(parent_span_id);
+        }
+        span
+    }
+}
+
+/// Returns the trace ID of the spans of a profiler session: the profiler ID
+/// when it's a UUID, or its MD5 otherwise.
+fn trace_id(profiler_id: &str) -> String {
+    let id = profiler_id.replace('-', "").to_lowercase();
+    if id.len() == 32 && id.chars().all(|c| c.is_ascii_hexdigit()) {
+        id
+    } else {
+        format!("{:x}", md5::compute(profiler_id))
+    }
+}
+
+/// Exports the `top_n` longest nodes of the call trees of a chunk as spans,
+/// sorted by start time. Spans are parented to the span of the closest
+/// exported ancestor of their node.
+pub fn otel_spans(
+    chunk: &dyn ChunkInterface,
+    call_trees: &CallTreesStr,
+    top_n: usize,
+) -> Vec<OtelSpan> {
+    let mut nodes: Vec<(&str, NodeId, u64, u64)> = Vec::new();
+    for (thread_id, call_tree) in call_trees {
+        let mut stack: Vec<NodeId> = call_tree.roots().iter().rev().copied().collect();
+        while let Some(id) = stack.pop() {
+            let node = &call_tree[id];
+            nodes.push((thread_id, id, node.duration_ns, node.start_ns));
+            stack.extend(call_tree.children(id).iter().rev());
+        }
+    }
+    nodes.sort_by(|a, b| {
+        b.2.cmp(&a.2)
+            .then_with(|| a.3.cmp(&b.3))
+            .then_with(|| a.0.cmp(b.0))
+    });
+    let exported: HashSet<(&str, NodeId)> = nodes
+        .into_iter()
+        .take(top_n)
+        .map(|(thread_id, id, _, _)| (thread_id, id))
+        .collect();
+
+    let trace_id = trace_id(chunk.get_profiler_id());
+    let mut spans = Vec::with_capacity(exported.len());
+    for (thread_id, call_tree) in call_trees {
+        for &root in call_tree.roots() {
+            export_node(
+                chunk, call_tree, thread_id, root, None, &exported, &trace_id, &mut spans,
+            );
+        }
+    }
+    spans.sort_by(|a, b| {
+        a.start_time_unix_nano
+            .cmp(&b.start_time_unix_nano)
+            .then_with(|| b.end_time_unix_nano.cmp(&a.end_time_unix_nano))
+            .then_with(|| a.span_id.cmp(&b.span_id))
+    });
+    spans
+}
+
+#[allow(clippy::too_many_arguments)]
+fn export_node(
+    chunk: &dyn ChunkInterface,
+    call_tree: &CallTree,
+    thread_id: &str,
+    id: NodeId,
+    parent_span_id: Option<&str>,
+    exported: &HashSet<(&str, NodeId)>,
+    trace_id: &str,
+    spans: &mut Vec<OtelSpan>,
+) {
+    let node = &call_tree[id];
+    let mut span_id = parent_span_id.map(str::to_string);
+    if exported.contains(&(thread_id, id)) {
+        let hash = format!(
+            "{:x}",
+            md5::compute(format!(
+                "{}/{thread_id}/{}/{}",
+                chunk.get_chunk_id(),
+                node.start_ns,
+                node.fingerprint
+            ))
+        );
+        let mut attributes = BTreeMap::from([
+            ("sentry.origin".to_string(), SPAN_ORIGIN.to_string()),
+            (
+                "sentry.profiler_id".to_string(),
+                chunk.get_profiler_id().to_string(),
+            ),
+            (
+                "sentry.chunk_id".to_string(),
+                chunk.get_chunk_id().to_string(),
+            ),
+            ("thread.id".to_string(), thread_id.to_string()),
+            ("code.function".to_string(), node.name.clone()),
+            (
+                "profile.sample_count".to_string(),
+                node.sample_count.to_string(),
+            ),
+        ]);
+        if !node.package.is_empty() {
+            attributes.insert("code.namespace".to_string(), node.package.clone());
+        }
+        if let Some(path) = node.path.as_ref().filter(|path| !path.is_empty()) {
+            attributes.insert("code.filepath".to_string(), path.clone());
+        }
+        if let Some(line) = node.line {
+            attributes.insert("code.lineno".to_string(), line.to_string());
+        }
+        spans.push(OtelSpan {
+            trace_id: trace_id.to_string(),
+            span_id: hash[..16].to_string(),
+            parent_span_id: parent_span_id.map(str::to_string),
+            name: node.name.clone(),
+            start_time_unix_nano: chunk.absolute_timestamp_ns(node.start_ns),
+            end_time_unix_nano: chunk.absolute_timestamp_ns(node.end_ns),
+            attributes,
+        });
+        span_id = Some(hash[..16].to_string());
+    }
+    for &child in call_tree.children(id) {
+        export_node(
+            chunk,
+            call_tree,
+            thread_id,
+            child,
+            span_id.as_deref(),
+            exported,
+            trace_id,
+            spans,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{otel_spans, trace_id};
+    use crate::{frame::Frame, sample::v2::SampleChunkBuilder, types::ChunkInterface};
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_otel_spans() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let function = |name: &str| Frame {
+            function: Some(name.to_string()),
+            module: Some("app".to_string()),
+            ..Default::default()
+        };
+        let main = builder.frame(function("main"));
+        let load = builder.frame(function("load"));
+        let parse = builder.frame(function("parse"));
+        builder
+            .sample("1", 10.0, &[parse, load, main])
+            .sample("1", 10.1, &[parse, load, main])
+            .sample("1", 10.2, &[load, main])
+            .sample("1", 10.3, &[main])
+            .sample("1", 10.4, &[main]);
+        let chunk = builder.build();
+        let mut trees_chunk = chunk.clone();
+        let call_trees = trees_chunk.call_trees(None).unwrap();
+
+        let spans = otel_spans(&chunk, &call_trees, 2);
+        let names: Vec<&str> = spans.iter().map(|span| span.name.as_str()).collect();
+        assert_eq!(names, vec!["main", "load"]);
+        let [main, load] = &spans[..] else {
+            panic!("expected 2 spans")
+        };
+        assert_eq!(main.parent_span_id, None);
+        assert_eq!(load.parent_span_id.as_ref(), Some(&main.span_id));
+        assert_eq!(main.trace_id, trace_id(chunk.get_profiler_id()));
+        assert_eq!(main.start_time_unix_nano, 10_000_000_000);
+        assert_eq!(load.end_time_unix_nano, 10_300_000_000);
+        assert_eq!(load.attributes["code.namespace"], "app");
+        assert_eq!(load.attributes["sentry.origin"], "auto.profiling");
+
+        let json = main.to_otlp_json();
+        assert_eq!(json["startTimeUnixNano"], "10000000000");
+        assert!(json.get("parentSpanId").is_none());
+
+        assert_eq!(
+            trace_id("4D229F1D-3ADF-4D3C-B79D-2B5FD6F5B2D2"),
+            "4d229f1d3adf4d3cb79d2b5fd6f5b2d2"
+        );
+        assert_eq!(trace_id("session").len(), 32);
+    }
+}
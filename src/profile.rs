@@ -1,19 +1,35 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use pyo3::{pyclass, pymethods, PyErr, PyResult};
 
 use crate::{
     android::profile::AndroidProfile,
-    nodetree::CallTreeFunction,
-    occurrence::{self, Occurrence},
+    json, msgpack,
+    nodetree::{
+        filter_functions, sort_functions, CallTreeFunction, FunctionsMetricsOptions,
+        FunctionsSortKey,
+    },
+    obfuscation::is_obfuscated,
+    occurrence::{self, AppStartPrelude, CalibrationStats, DetectorOptions, Occurrence},
     sample::v1::SampleProfile,
-    types::{CallTreeError, CallTreesU64, Metadata, ProfileInterface, Transaction},
-    utils::{compress_lz4, decompress_lz4},
+    top_frames::{top_frames, TopFrame},
+    types::{CallTreeError, CallTreesU64, Metadata, PayloadError, ProfileInterface, Transaction},
+    utils::{compress, decompress, Codec},
 };
 
+/// Major version of profiles in the sample format, android profiles having
+/// no version.
+const SAMPLE_FORMAT_MAJOR_VERSION: &str = "1";
+
 #[pyclass]
 pub struct Profile {
-    pub profile: Box<dyn ProfileInterface + Send + Sync>,
+    pub(crate) profile: Box<dyn ProfileInterface + Send + Sync>,
+    /// The call trees of the profile, built on first use and dropped
+    /// whenever the profile is modified.
+    call_trees: Option<Arc<CallTreesU64>>,
+    /// The number of functions dropped by the last extraction of function
+    /// metrics for having too few samples.
+    filtered_functions: u64,
 }
 
 #[derive(serde::Deserialize)]
@@ -22,21 +38,41 @@ struct MinimumProfile {
 }
 
 impl Profile {
-    pub(crate) fn from_json_vec(profile: &[u8]) -> Result<Self, serde_json::Error> {
-        let min_prof: MinimumProfile = serde_json::from_slice(profile)?;
-        match min_prof.version {
+    pub(crate) fn new(profile: Box<dyn ProfileInterface + Send + Sync>) -> Self {
+        Profile {
+            profile,
+            call_trees: None,
+            filtered_functions: 0,
+        }
+    }
+
+    /// Returns the call trees of the profile, building them on first use so
+    /// extracting metadata, functions and occurrences only builds them once.
+    pub(crate) fn call_trees(&mut self) -> Result<Arc<CallTreesU64>, CallTreeError> {
+        if let Some(call_trees) = &self.call_trees {
+            return Ok(Arc::clone(call_trees));
+        }
+        let call_trees = Arc::new(self.profile.call_trees()?);
+        self.call_trees = Some(Arc::clone(&call_trees));
+        Ok(call_trees)
+    }
+
+    pub fn from_json_vec(profile: &[u8]) -> Result<Self, PayloadError> {
+        let min_prof: MinimumProfile = json::from_slice(profile)?;
+        match min_prof.version.as_deref() {
             None => {
-                let android: AndroidProfile = serde_json::from_slice(profile)?;
-                Ok(Profile {
-                    profile: Box::new(android),
-                })
+                let android: AndroidProfile = json::from_slice(profile)?;
+                Ok(Profile::new(Box::new(android)))
             }
-            Some(_) => {
-                let sample: SampleProfile = serde_json::from_slice(profile)?;
-                Ok(Profile {
-                    profile: Box::new(sample),
-                })
+            // sample profiles built without a version have an empty one
+            Some(version)
+                if version.is_empty()
+                    || version.split('.').next() == Some(SAMPLE_FORMAT_MAJOR_VERSION) =>
+            {
+                let sample: SampleProfile = json::from_slice(profile)?;
+                Ok(Profile::new(Box::new(sample)))
             }
+            Some(version) => Err(PayloadError::UnsupportedVersion(version.to_string())),
         }
     }
 
@@ -46,24 +82,121 @@ impl Profile {
     ) -> Result<Self, serde_json::Error> {
         match platform {
             "android" => {
-                let android: AndroidProfile = serde_json::from_slice(profile)?;
-                Ok(Profile {
-                    profile: Box::new(android),
-                })
+                let android: AndroidProfile = json::from_slice(profile)?;
+                Ok(Profile::new(Box::new(android)))
             }
             _ => {
-                let sample: SampleProfile = serde_json::from_slice(profile)?;
-                Ok(Profile {
-                    profile: Box::new(sample),
-                })
+                let sample: SampleProfile = json::from_slice(profile)?;
+                Ok(Profile::new(Box::new(sample)))
             }
         }
     }
 
-    pub(crate) fn decompress(source: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
-        let bytes = decompress_lz4(source)?;
+    pub fn decompress(source: &[u8]) -> Result<Self, PayloadError> {
+        let bytes = decompress(source).map_err(PayloadError::Decompression)?;
         Self::from_json_vec(bytes.as_ref())
-            .map_err(|err| Box::new(err) as Box<dyn std::error::Error>)
+    }
+
+    /// Normalizes the profile then extracts its metadata, function metrics,
+    /// occurrences and summary, building its call trees only once.
+    pub(crate) fn process(
+        &mut self,
+        options: &FunctionsMetricsOptions,
+    ) -> Result<ProcessedProfile, CallTreeError> {
+        self.normalize();
+        let call_trees = self.call_trees()?;
+        let functions = self.functions_metrics(&call_trees, options);
+        let occurrences = occurrence::find_occurences(
+            self.profile.as_ref(),
+            &call_trees,
+            &DetectorOptions::default(),
+        );
+        let summary = ProfileSummary {
+            duration_ns: self.profile.duration_ns(),
+            thread_count: call_trees.len(),
+            sample_count: call_trees
+                .values()
+                .flat_map(|call_tree| {
+                    call_tree
+                        .roots()
+                        .iter()
+                        .map(|&root| call_tree[root].sample_count)
+                })
+                .sum(),
+            function_count: functions.len(),
+            occurrence_count: occurrences.len(),
+        };
+        Ok(ProcessedProfile {
+            metadata: self.profile.get_metadata(),
+            functions,
+            occurrences,
+            summary,
+        })
+    }
+
+    /// Extracts the function metrics of the profile, building its call
+    /// trees on first use.
+    pub fn extract_functions_metrics(
+        &mut self,
+        options: &FunctionsMetricsOptions,
+    ) -> Result<Vec<CallTreeFunction>, CallTreeError> {
+        let call_trees = self.call_trees()?;
+        Ok(self.functions_metrics(&call_trees, options))
+    }
+
+    /// Aggregates the functions of the call trees, see
+    /// [`Profile::extract_functions_metrics`].
+    fn functions_metrics(
+        &mut self,
+        call_trees: &CallTreesU64,
+        options: &FunctionsMetricsOptions,
+    ) -> Vec<CallTreeFunction> {
+        let mut functions: HashMap<u32, CallTreeFunction> = HashMap::new();
+
+        let active_thread_id = self.profile.get_transaction().active_thread_id;
+
+        for (tid, call_tree) in call_trees {
+            if options.active_thread_only && *tid != active_thread_id {
+                continue;
+            }
+            for &root in call_tree.roots() {
+                call_tree.collect_functions(
+                    root,
+                    &mut functions,
+                    tid.to_string().as_ref(),
+                    0,
+                    options.min_depth,
+                    options.filter_system_frames,
+                    options.filter_non_leaf_functions,
+                    options.generate_stack_fingerprints,
+                    options.max_examples,
+                    options.max_lines_per_function > 0,
+                    None,
+                );
+            }
+        }
+
+        let (mut functions_list, filtered_functions) = filter_functions(
+            functions,
+            options.filter_system_frames,
+            options.min_sample_count,
+        );
+        self.filtered_functions = filtered_functions;
+
+        // sort the list in descending order, and take the top N results
+        sort_functions(&mut functions_list, options.sort_key);
+
+        functions_list.truncate(options.max_unique_functions.unwrap_or(functions_list.len()));
+        let thread_names = self.profile.thread_names();
+        let main_thread_id = self.profile.get_main_thread_id().map(|id| id.to_string());
+        for function in &mut functions_list {
+            function.truncate_lines(options.max_lines_per_function);
+            function.set_threads(&thread_names, main_thread_id.as_deref());
+            function.set_examples_source(Some(self.profile.get_profile_id()), None, |ts| {
+                self.profile.absolute_timestamp_ns(ts)
+            });
+        }
+        functions_list
     }
 }
 
@@ -94,6 +227,24 @@ impl Occurrences {
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))
     }
 
+    /// Serializes the occurrences to MessagePack, as an array with the same
+    /// fields as :meth:`to_json_str`.
+    ///
+    /// Returns:
+    ///     bytes
+    ///         The MessagePack encoding of the occurrences list.
+    ///
+    /// Raises:
+    ///     ValueError
+    ///         If the serialization fails due to invalid data.
+    ///
+    /// Example:
+    ///     >>> occurrences = profile.find_occurrences()
+    ///     >>> payload = occurrences.to_msgpack()
+    pub fn to_msgpack(&self) -> Result<Vec<u8>, PyErr> {
+        msgpack::to_vec(&self.occurrences)
+    }
+
     /// Filters occurrences to remove those with NONE_TYPE.
     ///
     /// This method removes all occurrences that have a type of NONE_TYPE,
@@ -108,12 +259,124 @@ impl Occurrences {
     }
 }
 
+/// Everything the profile consumer emits for a profile, computed in a
+/// single pass over the profile (see :func:`process_profile`).
+#[pyclass]
+pub struct ProcessedProfile {
+    pub metadata: Metadata,
+    pub functions: Vec<CallTreeFunction>,
+    pub occurrences: Vec<Occurrence>,
+    pub summary: ProfileSummary,
+}
+
+/// Aggregates describing a profile or a chunk as a whole.
+#[pyclass]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileSummary {
+    pub duration_ns: u64,
+    pub thread_count: usize,
+    pub sample_count: u64,
+    pub function_count: usize,
+    pub occurrence_count: usize,
+}
+
+#[pymethods]
+impl ProcessedProfile {
+    /// Returns the metadata of the profile.
+    ///
+    /// Returns:
+    ///     Metadata
+    ///         The metadata message of the profile, see :meth:`Profile.get_metadata`.
+    pub fn get_metadata(&self) -> Metadata {
+        self.metadata.clone()
+    }
+
+    /// Returns the function metrics of the profile.
+    ///
+    /// Returns:
+    ///     list[:class:`CallTreeFunction`]
+    ///         The functions, see :meth:`Profile.extract_functions_metrics`.
+    pub fn get_functions(&self) -> Vec<CallTreeFunction> {
+        self.functions.clone()
+    }
+
+    /// Returns the occurrences found in the profile.
+    ///
+    /// Returns:
+    ///     :class:`Occurrences`
+    ///         The occurrences, see :meth:`Profile.find_occurrences`.
+    pub fn get_occurrences(&self) -> Occurrences {
+        Occurrences {
+            occurrences: self.occurrences.clone(),
+        }
+    }
+
+    /// Returns the summary of the profile.
+    ///
+    /// Returns:
+    ///     :class:`ProfileSummary`
+    ///         The aggregates describing the profile as a whole.
+    pub fn get_summary(&self) -> ProfileSummary {
+        self.summary.clone()
+    }
+}
+
+#[pymethods]
+impl ProfileSummary {
+    /// Returns the duration of the profile in nanoseconds.
+    ///
+    /// Returns:
+    ///     int
+    ///         The duration in nanoseconds.
+    pub fn get_duration_ns(&self) -> u64 {
+        self.duration_ns
+    }
+
+    /// Returns the number of threads sampled.
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of threads with at least one sample.
+    pub fn get_thread_count(&self) -> usize {
+        self.thread_count
+    }
+
+    /// Returns the number of samples of the profile.
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of samples aggregated in the call trees, across all threads.
+    pub fn get_sample_count(&self) -> u64 {
+        self.sample_count
+    }
+
+    /// Returns the number of function metrics extracted.
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of functions returned by :meth:`ProcessedProfile.get_functions`.
+    pub fn get_function_count(&self) -> usize {
+        self.function_count
+    }
+
+    /// Returns the number of occurrences found.
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of occurrences returned by :meth:`ProcessedProfile.get_occurrences`,
+    ///         0 for chunks, which aren't searched for occurrences.
+    pub fn get_occurrence_count(&self) -> usize {
+        self.occurrence_count
+    }
+}
+
 #[pymethods]
 impl Profile {
     /// Applies the various normalization steps,
     /// depending on the profile's platform.
     pub fn normalize(&mut self) {
         self.profile.normalize();
+        self.call_trees = None;
     }
 
     /// Returns the environment.
@@ -209,6 +472,25 @@ impl Profile {
         self.profile.get_timestamp().timestamp_micros() as f64 / 1_000_000.0
     }
 
+    /// Converts a call tree timestamp to a UTC Unix timestamp.
+    ///
+    /// Node timestamps are relative to the profile timestamp, this lines
+    /// them up with logs and spans from other tools.
+    ///
+    /// Args:
+    ///     timestamp_ns (int): The call tree timestamp, in nanoseconds.
+    ///
+    /// Returns:
+    ///     float
+    ///         The Unix timestamp, in seconds.
+    ///
+    /// Example:
+    ///     >>> example = profile.extract_functions_metrics(1, False, max_examples=1)[0].get_examples()[0]
+    ///     >>> profile.absolute_timestamp(example.get_start_ns())
+    pub fn absolute_timestamp(&self, timestamp_ns: u64) -> f64 {
+        self.profile.absolute_timestamp_ns(timestamp_ns) as f64 / 1e9
+    }
+
     /// Returns the SDK name.
     ///
     /// Returns:
@@ -259,7 +541,7 @@ impl Profile {
             .profile
             .to_json_vec()
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
-        compress_lz4(&mut prof.as_slice())
+        compress(&prof, Codec::Lz4, None, None)
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))
     }
 
@@ -280,62 +562,68 @@ impl Profile {
     ///         If `False`, all functions including non-leaf functions with zero self-time will be included.
     ///         Defaults to `True`.
     ///     generate_stack_fingerprints (bool): If `True`, the fingerprint of the stack up to the current function and the parent function's fingerprint will be generated.
+    ///     max_examples (int): The maximum number of examples, the function calls with the longest self time,
+    ///         to record for each function.
+    ///         Defaults to `0`.
+    ///     active_thread_only (bool): If `True`, only functions sampled on the transaction's active thread are aggregated,
+    ///         so background workers don't skew application metrics.
+    ///         Defaults to `False`.
+    ///     max_lines_per_function (int): The maximum number of lines self time is aggregated for, per function
+    ///         (see :meth:`CallTreeFunction.get_line_self_times_ns`), the lines with the most self time being kept.
+    ///         If `0`, self time isn't aggregated per line.
+    ///         Defaults to `0`.
+    ///     min_sample_count (int): The minimum number of samples of the functions returned, to reduce the amount of
+    ///         data. Lower it to keep short functions of platforms sampled at a low frequency (e.g. PHP at 10Hz).
+    ///         The number of functions dropped is returned by :meth:`get_filtered_functions`.
+    ///         Defaults to `2`.
+    ///     sort_by (str): What the functions are ranked by, in decreasing order, before keeping the top
+    ///         `max_unique_functions`: "self_time" (their sum of self times), "sample_count" or "total_time" (their
+    ///         sum of total times, see :meth:`CallTreeFunction.get_sum_total_time_ns`). Ties are broken by increasing
+    ///         fingerprint, so the order is stable.
+    ///         Defaults to `"self_time"`.
     ///
     /// Returns:
     ///     list[:class:`CallTreeFunction`]
-    ///         A list of :class:`CallTreeFunction` objects, each containing metrics for a function in the call tree.
+    ///         A list of :class:`CallTreeFunction` objects, each containing metrics for a function in the call tree,
+    ///         sorted by `sort_by`.
     ///
     /// Raises:
+    ///     pyo3.exceptions.ValueError: If `sort_by` is unknown.
     ///     pyo3.exceptions.PyException: If an error occurs during the extraction process.
     ///
     /// Example:
     ///     >>> metrics = profile.extract_functions_metrics(min_depth=2, filter_system_frames=True, max_unique_functions=10, filter_non_leaf_functions=False)
     ///     >>> for function_metric in metrics:
     ///     ...     do_something(function_metric)
-    #[pyo3(signature = (min_depth, filter_system_frames, max_unique_functions=None, filter_non_leaf_functions=true, generate_stack_fingerprints=false))]
-    pub fn extract_functions_metrics(
+    #[pyo3(name = "extract_functions_metrics", signature = (min_depth, filter_system_frames, max_unique_functions=None, filter_non_leaf_functions=true, generate_stack_fingerprints=false, max_examples=0, active_thread_only=false, max_lines_per_function=0, min_sample_count=2, sort_by="self_time"))]
+    #[allow(clippy::too_many_arguments)]
+    fn py_extract_functions_metrics(
         &mut self,
         min_depth: u16,
         filter_system_frames: bool,
         max_unique_functions: Option<usize>,
         filter_non_leaf_functions: bool,
         generate_stack_fingerprints: bool,
+        max_examples: usize,
+        active_thread_only: bool,
+        max_lines_per_function: usize,
+        min_sample_count: u64,
+        sort_by: &str,
     ) -> PyResult<Vec<CallTreeFunction>> {
-        let call_trees: CallTreesU64 = self.profile.call_trees()?;
-        let mut functions: HashMap<u32, CallTreeFunction> = HashMap::new();
-
-        for (tid, call_trees_for_thread) in &call_trees {
-            for call_tree in call_trees_for_thread {
-                call_tree.borrow_mut().collect_functions(
-                    &mut functions,
-                    tid.to_string().as_ref(),
-                    0,
-                    min_depth,
-                    filter_system_frames,
-                    filter_non_leaf_functions,
-                    generate_stack_fingerprints,
-                    None,
-                );
-            }
-        }
-
-        let mut functions_list: Vec<CallTreeFunction> = Vec::with_capacity(functions.len());
-        for (_fingerprint, function) in functions {
-            if function.sample_count <= 1 || (filter_system_frames && !function.in_app) {
-                // if there's only ever a single sample for this function in
-                // the profile, or the function represents a system frame, and we
-                // decided to ignore system frames, we skip over it to reduce the
-                //amount of data
-                continue;
-            }
-            functions_list.push(function);
-        }
-
-        // sort the list in descending order, and take the top N results
-        functions_list.sort_by_key(|f| std::cmp::Reverse(f.sum_self_time_ns));
-
-        functions_list.truncate(max_unique_functions.unwrap_or(functions_list.len()));
-        Ok(functions_list)
+        let options = FunctionsMetricsOptions {
+            min_depth,
+            filter_system_frames,
+            max_unique_functions,
+            filter_non_leaf_functions,
+            generate_stack_fingerprints,
+            max_examples,
+            active_thread_only,
+            max_lines_per_function,
+            min_sample_count,
+            sort_key: FunctionsSortKey::from_name(sort_by)?,
+            ..Default::default()
+        };
+        Ok(self.extract_functions_metrics(&options)?)
     }
 
     /// Finds performance issues (occurrences) in the profile.
@@ -345,21 +633,176 @@ impl Profile {
     /// - Slow operations on the main thread (e.g., I/O, compression, database operations)
     /// - SwiftUI performance issues (view inflation, layout, rendering)
     /// - Machine learning model operations
+    /// - Deep recursion, burning CPU without overflowing the stack
     /// - And other platform-specific performance patterns
     ///
+    /// Args:
+    ///     detect_once_per_profile (bool): Whether to only report an issue
+    ///         once, its longest occurrence, when it's detected more than once
+    ///         in the profile (e.g. on several threads). Defaults to False.
+    ///     recursion_depth_threshold (int): The number of consecutive calls of a
+    ///         function past which its recursion is reported as a `deep_recursion`
+    ///         occurrence. Defaults to 100.
+    ///
     /// Returns:
     ///     :class:`Occurrence`
     ///         An :class:`Occurrences` object, a wrapper containing a list of :class:`Occurrences`, each representing a detected performance issue.
     ///
     /// Raises:
     ///     pyo3.exceptions.PyException: If an error occurs during the detection process.
-    pub fn find_occurrences(&mut self) -> Result<Occurrences, CallTreeError> {
-        let call_trees = self.profile.call_trees()?;
+    #[pyo3(signature = (detect_once_per_profile=false, recursion_depth_threshold=None))]
+    pub fn find_occurrences(
+        &mut self,
+        detect_once_per_profile: bool,
+        recursion_depth_threshold: Option<u64>,
+    ) -> Result<Occurrences, CallTreeError> {
+        let call_trees = self.call_trees()?;
+        let options = DetectorOptions {
+            detect_once_per_profile,
+            recursion_depth_threshold,
+            ..Default::default()
+        };
         Ok(Occurrences {
-            occurrences: occurrence::find_occurences(self.profile.as_ref(), &call_trees),
+            occurrences: occurrence::find_occurences(self.profile.as_ref(), &call_trees, &options),
         })
     }
 
+    /// Runs every detector of the platform of the profile, including the
+    /// disabled ones, without reporting occurrences: only the stats of the
+    /// occurrences they would have created are returned, to tune thresholds
+    /// on production traffic before enabling a detector.
+    ///
+    /// Args:
+    ///     detect_once_per_profile (bool): Whether to only count an issue
+    ///         once when it's detected more than once in the profile, as in
+    ///         :meth:`find_occurrences`. Defaults to False.
+    ///     recursion_depth_threshold (int): The number of consecutive calls of a
+    ///         function past which its recursion is counted, as in
+    ///         :meth:`find_occurrences`. Defaults to 100.
+    ///
+    /// Returns:
+    ///     :class:`CalibrationStats`
+    ///         The number of occurrences and the histogram of their durations,
+    ///         by category.
+    ///
+    /// Raises:
+    ///     pyo3.exceptions.PyException: If an error occurs during the detection process.
+    #[pyo3(signature = (detect_once_per_profile=false, recursion_depth_threshold=None))]
+    pub fn calibrate_detectors(
+        &mut self,
+        detect_once_per_profile: bool,
+        recursion_depth_threshold: Option<u64>,
+    ) -> Result<CalibrationStats, CallTreeError> {
+        let call_trees = self.call_trees()?;
+        let options = DetectorOptions {
+            detect_once_per_profile,
+            recursion_depth_threshold,
+            ..Default::default()
+        };
+        Ok(occurrence::calibrate_detectors(
+            self.profile.as_ref(),
+            &call_trees,
+            &options,
+        ))
+    }
+
+    /// Analyzes the app start prelude of the profile: the work done on the
+    /// main thread before `main` runs, by dyld loading images and by the
+    /// Objective-C runtime running `+load` methods and static initializers.
+    ///
+    /// Time is attributed to the innermost prelude frame of each stack, within
+    /// the first `window_ms` of the profile.
+    ///
+    /// Args:
+    ///     window_ms (int): The duration of the app start window, from the start of the profile, in milliseconds.
+    ///         Defaults to `2000`.
+    ///     min_duration_ms (int): If provided, a "slow app start prelude" occurrence is generated
+    ///         when the prelude lasted at least this long (see :meth:`AppStartPrelude.get_occurrence`).
+    ///
+    /// Returns:
+    ///     :class:`AppStartPrelude` | None
+    ///         The prelude, or `None` if the platform isn't supported (only cocoa is)
+    ///         or the main thread is unknown.
+    ///
+    /// Raises:
+    ///     pyo3.exceptions.PyException: If an error occurs while building the call trees.
+    ///
+    /// Example:
+    ///     >>> prelude = profile.app_start_prelude(min_duration_ms=400)
+    ///     >>> if prelude is not None and prelude.get_occurrence() is not None:
+    ///     ...     send_occurrence(prelude.get_occurrence())
+    #[pyo3(signature = (window_ms=2000, min_duration_ms=None))]
+    pub fn app_start_prelude(
+        &mut self,
+        window_ms: u64,
+        min_duration_ms: Option<u64>,
+    ) -> Result<Option<AppStartPrelude>, CallTreeError> {
+        let call_trees = self.call_trees()?;
+        Ok(occurrence::find_app_start_prelude(
+            self.profile.as_ref(),
+            &call_trees,
+            window_ms * 1_000_000,
+            min_duration_ms.map(|ms| ms * 1_000_000),
+        ))
+    }
+
+    /// Returns the frames the profile spent the most self time in, across
+    /// threads.
+    ///
+    /// Unlike :meth:`extract_functions_metrics`, frames are ranked whatever
+    /// their depth and whether they're app frames or not, making it a
+    /// lighter-weight alternative for the header of the profile summary.
+    ///
+    /// Args:
+    ///     n (int): The number of frames to return. Defaults to `10`.
+    ///
+    /// Returns:
+    ///     list[:class:`TopFrame`]
+    ///         The frames, by decreasing self time.
+    ///
+    /// Raises:
+    ///     pyo3.exceptions.PyException: If an error occurs while building the call trees.
+    ///
+    /// Example:
+    ///     >>> for frame in profile.top_frames(5):
+    ///     ...     print(frame.get_function(), frame.get_self_time_ns())
+    #[pyo3(signature = (n=10))]
+    pub fn top_frames(&mut self, n: usize) -> Result<Vec<TopFrame>, CallTreeError> {
+        let call_trees = self.call_trees()?;
+        Ok(top_frames(call_trees.values(), n))
+    }
+
+    /// Returns whether the profile looks ProGuard-obfuscated, most of its app
+    /// frames having class names renamed to a letter or two (`a`, `b`, ...,
+    /// `aa`, ...), so ingest can tag it and the UI can suggest uploading a
+    /// mapping file.
+    ///
+    /// Only frames of platforms supporting deobfuscation (android, java)
+    /// are considered.
+    ///
+    /// Returns:
+    ///     bool
+    ///         True if the profile looks obfuscated, False otherwise.
+    ///
+    /// Example:
+    ///     >>> if profile.is_obfuscated():
+    ///     ...     tags["obfuscated"] = "true"
+    pub fn is_obfuscated(&self) -> bool {
+        is_obfuscated(&self.profile.get_platform(), &self.profile.frames())
+    }
+
+    /// Returns the number of functions dropped by the last call to
+    /// :meth:`extract_functions_metrics` for having fewer samples than its
+    /// `min_sample_count`, to monitor the data lost.
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of functions dropped. 0 if no function metrics were
+    ///         extracted yet.
+    pub fn get_filtered_functions(&self) -> u64 {
+        self.filtered_functions
+    }
+
     /// Returns whether the profile is sampled.
     ///
     /// Returns:
@@ -382,6 +825,50 @@ impl Profile {
         self.profile.set_profile_id(profile_id);
     }
 
+    /// Sets the organization ID.
+    ///
+    /// Args:
+    ///     organization_id (int): The ID of the organization to which the profile belongs.
+    ///
+    /// Example:
+    ///     >>> profile.set_organization_id(1)
+    pub fn set_organization_id(&mut self, organization_id: u64) {
+        self.profile.set_organization_id(organization_id);
+    }
+
+    /// Sets the project ID.
+    ///
+    /// Args:
+    ///     project_id (int): The ID of the project to which the profile belongs.
+    ///
+    /// Example:
+    ///     >>> profile.set_project_id(1)
+    pub fn set_project_id(&mut self, project_id: u64) {
+        self.profile.set_project_id(project_id);
+    }
+
+    /// Sets the received timestamp.
+    ///
+    /// Args:
+    ///     received (int): The Unix timestamp, in seconds, at which the profile was received.
+    ///
+    /// Example:
+    ///     >>> profile.set_received(1710805688)
+    pub fn set_received(&mut self, received: i64) {
+        self.profile.set_received(received);
+    }
+
+    /// Sets the retention days.
+    ///
+    /// Args:
+    ///     retention_days (int): The number of days the profile is retained.
+    ///
+    /// Example:
+    ///     >>> profile.set_retention_days(90)
+    pub fn set_retention_days(&mut self, retention_days: i32) {
+        self.profile.set_retention_days(retention_days);
+    }
+
     /// Returns the transaction information associated with the profile.
     ///
     /// Returns:
@@ -408,7 +895,16 @@ impl Profile {
 
 #[cfg(test)]
 mod tests {
-    use crate::{android::profile::AndroidProfile, profile::Profile, sample::v1::SampleProfile};
+    use std::collections::HashMap;
+
+    use crate::{
+        android::profile::AndroidProfile,
+        frame::{Data, Frame},
+        nodetree::{CallTreeFunction, FunctionsMetricsOptions},
+        profile::{Profile, ProfileSummary},
+        sample::v1::{self, Sample, SampleProfile},
+        types::{PayloadError, Transaction},
+    };
 
     #[test]
     fn test_from_json_vec() {
@@ -448,6 +944,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_from_json_vec_unsupported_version() {
+        // a chunk rather than a transaction profile
+        let profile_json = include_bytes!("../tests/fixtures/sample/v2/valid_python.json");
+        assert!(matches!(
+            Profile::from_json_vec(profile_json),
+            Err(PayloadError::UnsupportedVersion(version)) if version == "2"
+        ));
+    }
+
     #[test]
     fn test_from_json_vec_and_platform() {
         struct TestStruct<'a> {
@@ -548,4 +1054,134 @@ mod tests {
             assert!(equals, "test `{}` failed", test.name);
         }
     }
+
+    #[test]
+    fn test_setters() {
+        for fixture in [
+            &include_bytes!("../tests/fixtures/sample/v1/valid_cocoa.json")[..],
+            &include_bytes!("../tests/fixtures/android/profile/valid.json")[..],
+        ] {
+            let mut profile = Profile::from_json_vec(fixture).unwrap();
+
+            profile.set_organization_id(2);
+            profile.set_project_id(3);
+            profile.set_received(1710805690);
+            profile.set_retention_days(30);
+
+            let profile = Profile::decompress(&profile.compress().unwrap()).unwrap();
+            assert_eq!(profile.get_organization_id(), 2);
+            assert_eq!(profile.get_project_id(), 3);
+            assert_eq!(profile.get_received(), 1710805690);
+            assert_eq!(profile.get_retention_days(), 30);
+        }
+    }
+
+    #[test]
+    fn test_absolute_timestamp() {
+        let profile = Profile::from_json_vec(include_bytes!(
+            "../tests/fixtures/sample/v1/valid_cocoa.json"
+        ))
+        .unwrap();
+        // the profile timestamp is 2025-05-20T06:36:46.395Z
+        assert_eq!(
+            profile.profile.absolute_timestamp_ns(5_000_000),
+            1_747_723_006_400_000_000
+        );
+        assert_eq!(profile.absolute_timestamp(0), 1747723006.395);
+    }
+
+    #[test]
+    fn test_process() {
+        let sample = |elapsed_since_start_ns: u64| Sample {
+            stack_id: 0,
+            thread_id: 1,
+            elapsed_since_start_ns,
+            ..Default::default()
+        };
+        let frame = |function: &str, package: &str| Frame {
+            function: Some(function.to_string()),
+            package: Some(package.to_string()),
+            data: Some(Data {
+                symbolicator_status: Some("symbolicated".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut profile = Profile::new(Box::new(SampleProfile {
+            platform: "cocoa".to_string(),
+            event_id: "ba06cbd52dfe4d5699958cbe1cb0acc8".to_string(),
+            transaction: Transaction {
+                active_thread_id: 1,
+                ..Default::default()
+            },
+            profile: v1::Profile {
+                samples: (0..=6).map(|i| sample(i * 10_000_000)).collect(),
+                stacks: vec![vec![1, 0]],
+                frames: vec![
+                    frame(
+                        "-[AppDelegate load]",
+                        "/private/var/containers/Bundle/Application/A/MyApp.app/MyApp",
+                    ),
+                    frame(
+                        "CFReadStreamRead",
+                        "/System/Library/Frameworks/CoreFoundation.framework/CoreFoundation",
+                    ),
+                ],
+                thread_metadata: Some(HashMap::from([(
+                    "1".to_string(),
+                    serde_json::from_str(r#"{"name": "main"}"#).unwrap(),
+                )])),
+                ..Default::default()
+            },
+            ..Default::default()
+        }));
+        let options = FunctionsMetricsOptions::default()
+            .with_filter_non_leaf_functions(false)
+            .with_filter_system_frames(true);
+        let mut processed = Profile::decompress(&profile.compress().unwrap())
+            .unwrap()
+            .process(&options)
+            .unwrap();
+
+        // Without in_app set, every frame counts as an application frame
+        // until the profile is normalized.
+        assert_eq!(
+            profile.extract_functions_metrics(&options).unwrap().len(),
+            2
+        );
+        profile.normalize();
+        let functions = profile.extract_functions_metrics(&options).unwrap();
+        let occurrences = profile.find_occurrences(false, None).unwrap().occurrences;
+        let key = |f: &CallTreeFunction| (f.fingerprint, f.sum_self_time_ns, f.sample_count);
+        processed.functions.sort_by_key(key);
+        let mut want_functions: Vec<_> = functions.iter().map(key).collect();
+        want_functions.sort();
+
+        assert_eq!(processed.metadata, profile.get_metadata());
+        assert_eq!(
+            processed.functions.iter().map(key).collect::<Vec<_>>(),
+            want_functions
+        );
+        assert_eq!(
+            processed
+                .occurrences
+                .iter()
+                .map(|o| o.fingerprint.clone())
+                .collect::<Vec<_>>(),
+            occurrences
+                .iter()
+                .map(|o| o.fingerprint.clone())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            processed.summary,
+            ProfileSummary {
+                duration_ns: profile.duration_ns(),
+                thread_count: 1,
+                sample_count: 6,
+                function_count: 1,
+                occurrence_count: 1,
+            }
+        );
+    }
 }
@@ -1,22 +1,96 @@
-use std::collections::HashMap;
+use std::{
+    borrow::Cow,
+    collections::{hash_map::Entry, HashMap},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use pyo3::{pyclass, pymethods, PyErr, PyResult};
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    pyclass, pymethods,
+    types::PyAnyMethods,
+    Bound, PyAny, PyErr, PyResult, Python,
+};
 
 use crate::{
+    activity::{activity_timeline, ActivityTimeline},
     android::chunk::AndroidChunk,
-    nodetree::CallTreeFunction,
-    sample::v2::SampleChunk,
-    types::{CallTreesStr, ChunkInterface},
-    utils::{compress_lz4, decompress_lz4},
+    anomaly::{anomaly_score, AnomalyScore},
+    cpu_spike::{dominant_stacks, find_cpu_spikes, CpuSpike},
+    frame::{
+        in_app_overrides::{InAppOverridesError, InAppRuleLists},
+        FrameOverrides, InAppRules,
+    },
+    json,
+    measurement_metrics::{measurement_metrics, MeasurementMetric},
+    memory_growth::{
+        find_memory_growths, memory_growth_suspects, MemoryGrowth, MEMORY_FOOTPRINT_MEASUREMENTS,
+    },
+    msgpack::{self, nested_call_trees},
+    nodetree::{
+        deobfuscation_ratio, filter_functions, sort_functions, CallTreeFunction,
+        FunctionsMetricsOptions, FunctionsSortKey,
+    },
+    otel::{otel_spans, OtelSpan},
+    profile::ProfileSummary,
+    sample::{
+        stream::stream_call_trees,
+        v2::{MergeError, SampleChunk},
+    },
+    sample_rate::{is_off_nominal_interval, sampling_interval_ns},
+    stats::{processing_stats, ProcessingStats},
+    symbolication::{symbolication_stats, SymbolicationStats},
+    timestamp::nanos_from_seconds,
+    types::{CallTreeError, CallTreeOptions, CallTreesStr, ChunkInterface, PayloadError},
+    utils::{compress, decompress, Codec},
 };
 
 /// Version of profile chunks in the legacy android trace format.
 pub(crate) const ANDROID_TRACE_FORMAT_VERSION: &str = "2.android-trace";
 
+/// Major version of profile chunks in the sample format.
+const SAMPLE_FORMAT_MAJOR_VERSION: &str = "2";
+
 /// This is a :class:`ProfileChunk` class
+///
+/// Profile chunks can be shared across Python threads. Methods building the
+/// call trees (e.g. :meth:`extract_functions_metrics`) update the chunk as
+/// they go (sorting its samples, counting repairs), so they borrow it
+/// exclusively: a chunk is never modified while a method reads it, and
+/// :meth:`map_frames` only borrows it once its callback returned for every
+/// frame. To process the same chunk from several threads at once, give each
+/// its own copy with :meth:`clone`.
+///
+/// Call trees are built on first use and reused by the following methods
+/// building them with the same options, until the chunk is modified (see
+/// :meth:`normalize` and :meth:`map_frames`).
 #[pyclass]
 pub struct ProfileChunk {
-    pub profile: Box<dyn ChunkInterface + Send + Sync>,
+    /// The chunk, shared with its copies (see [`ProfileChunk::clone`])
+    /// until either is modified.
+    pub(crate) profile: Arc<dyn ChunkInterface + Send + Sync>,
+    /// Incremented whenever the frames of the chunk are modified, for
+    /// [`ProfileChunk::map_frames`] to tell if they were while its callback ran.
+    frames_generation: u64,
+    /// The call trees last built, dropped whenever the chunk is modified.
+    call_trees: Option<CachedCallTrees>,
+    /// The in_app rules of the project, applied when the chunk is normalized.
+    in_app_rules: Option<Arc<InAppRules>>,
+    /// The number of functions dropped by the last extraction of function
+    /// metrics for having too few samples.
+    filtered_functions: u64,
+    /// The time it took to parse the payload of the chunk.
+    parse_duration: Duration,
+    /// The time it took to build the call trees last built.
+    build_duration: Duration,
+}
+
+/// Call trees of a chunk, along with the options they were built with.
+#[derive(Clone)]
+struct CachedCallTrees {
+    active_thread_id: Option<String>,
+    options: CallTreeOptions,
+    call_trees: Arc<CallTreesStr<'static>>,
 }
 
 #[derive(serde::Deserialize)]
@@ -24,47 +98,132 @@ struct MinimumProfile {
     version: Option<String>,
 }
 
+/// Returns whether chunks of a version are in the legacy android trace
+/// format rather than the sample format, or an error if the version is of
+/// neither.
+fn is_android_trace_format(version: Option<&str>) -> Result<bool, PayloadError> {
+    match version {
+        // Legacy android trace format chunks were originally sent
+        // without a version, newer ones carry an explicit version.
+        None | Some("") | Some(ANDROID_TRACE_FORMAT_VERSION) => Ok(true),
+        Some(version) if version.split('.').next() == Some(SAMPLE_FORMAT_MAJOR_VERSION) => {
+            Ok(false)
+        }
+        Some(version) => Err(PayloadError::UnsupportedVersion(version.to_string())),
+    }
+}
+
 impl ProfileChunk {
-    pub(crate) fn from_json_vec(profile: &[u8]) -> Result<Self, serde_json::Error> {
-        let min_prof: MinimumProfile = serde_json::from_slice(profile)?;
-        match min_prof.version.as_deref() {
-            // Legacy android trace format chunks were originally sent
-            // without a version, newer ones carry an explicit version.
-            None | Some("") | Some(ANDROID_TRACE_FORMAT_VERSION) => {
-                let android: AndroidChunk = serde_json::from_slice(profile)?;
-                Ok(ProfileChunk {
-                    profile: Box::new(android),
-                })
-            }
-            Some(_) => {
-                let sample: SampleChunk = serde_json::from_slice(profile)?;
-                Ok(ProfileChunk {
-                    profile: Box::new(sample),
-                })
-            }
+    pub(crate) fn new(profile: Box<dyn ChunkInterface + Send + Sync>) -> Self {
+        ProfileChunk {
+            profile: Arc::from(profile),
+            frames_generation: 0,
+            call_trees: None,
+            in_app_rules: None,
+            filtered_functions: 0,
+            parse_duration: Duration::ZERO,
+            build_duration: Duration::ZERO,
         }
     }
 
-    pub(crate) fn from_json_vec_and_version(
-        profile: &[u8],
-        version: &str,
-    ) -> Result<Self, serde_json::Error> {
-        match version {
-            // As a fallback to the legacy behavior, an empty version
-            // is treated as the android trace format as well.
-            "" | ANDROID_TRACE_FORMAT_VERSION => {
-                let android: AndroidChunk = serde_json::from_slice(profile)?;
-                Ok(ProfileChunk {
-                    profile: Box::new(android),
-                })
-            }
-            _ => {
-                let sample: SampleChunk = serde_json::from_slice(profile)?;
-                Ok(ProfileChunk {
-                    profile: Box::new(sample),
-                })
+    /// Returns the chunk to modify it, copying it first if it's shared with
+    /// copies of the chunk.
+    fn profile_mut(&mut self) -> &mut (dyn ChunkInterface + Send + Sync) {
+        if Arc::get_mut(&mut self.profile).is_none() {
+            self.profile = Arc::from(self.profile.clone_box());
+        }
+        Arc::get_mut(&mut self.profile).expect("the chunk isn't shared once copied")
+    }
+
+    /// Applies overrides to the frames of the chunk, see
+    /// [`ChunkInterface::override_frames`], unless the frames were modified
+    /// since they were at `frames_generation`, the overrides being indexed
+    /// by the frames they were computed from.
+    fn override_frames(
+        &mut self,
+        overrides: &[(usize, FrameOverrides)],
+        frames_generation: u64,
+    ) -> PyResult<()> {
+        if self.frames_generation != frames_generation {
+            return Err(PyRuntimeError::new_err(
+                "the frames of the chunk were modified while mapping them",
+            ));
+        }
+        self.profile_mut().override_frames(overrides)?;
+        self.frames_generation += 1;
+        self.call_trees = None;
+        Ok(())
+    }
+
+    /// Records the time it took to parse the payload of the chunk, see
+    /// [`ProcessingStats::parse_time_ns`].
+    fn with_parse_duration(mut self, parse_duration: Duration) -> Self {
+        self.parse_duration = parse_duration;
+        self
+    }
+
+    /// Sets the in_app rules of the project applied when the chunk is
+    /// normalized, see [`InAppRules::new`].
+    pub(crate) fn set_in_app_rules(
+        &mut self,
+        in_app_rules: InAppRuleLists,
+    ) -> Result<(), InAppOverridesError> {
+        self.in_app_rules = Some(Arc::new(InAppRules::new(in_app_rules)?));
+        Ok(())
+    }
+
+    /// Returns the call trees of the chunk, only building them if they
+    /// weren't built yet with the same options. Repair counters of the chunk
+    /// (e.g. [`ChunkInterface::truncated_stacks`]) are those of the call
+    /// trees returned.
+    pub(crate) fn call_trees(
+        &mut self,
+        active_thread_id: Option<&str>,
+        options: CallTreeOptions,
+    ) -> Result<Arc<CallTreesStr<'static>>, CallTreeError> {
+        if let Some(cached) = &self.call_trees {
+            if cached.active_thread_id.as_deref() == active_thread_id && cached.options == options {
+                return Ok(Arc::clone(&cached.call_trees));
             }
         }
+        let start = Instant::now();
+        let call_trees: CallTreesStr<'static> = self
+            .profile_mut()
+            .call_trees_with_options(active_thread_id, options)?
+            .into_iter()
+            .map(|(thread_id, call_tree)| (Cow::Owned(thread_id.into_owned()), call_tree))
+            .collect();
+        self.build_duration = start.elapsed();
+        let call_trees = Arc::new(call_trees);
+        self.call_trees = Some(CachedCallTrees {
+            active_thread_id: active_thread_id.map(str::to_string),
+            options,
+            call_trees: Arc::clone(&call_trees),
+        });
+        Ok(call_trees)
+    }
+
+    pub fn from_json_vec(profile: &[u8]) -> Result<Self, PayloadError> {
+        let start = Instant::now();
+        let min_prof: MinimumProfile = json::from_slice(profile)?;
+        if is_android_trace_format(min_prof.version.as_deref())? {
+            let android: AndroidChunk = json::from_slice(profile)?;
+            return Ok(ProfileChunk::new(Box::new(android)).with_parse_duration(start.elapsed()));
+        }
+        let sample: SampleChunk = json::from_slice(profile)?;
+        Ok(ProfileChunk::new(Box::new(sample)).with_parse_duration(start.elapsed()))
+    }
+
+    pub fn from_json_vec_and_version(profile: &[u8], version: &str) -> Result<Self, PayloadError> {
+        let start = Instant::now();
+        // As a fallback to the legacy behavior, an empty version is treated
+        // as the android trace format as well.
+        if is_android_trace_format(Some(version))? {
+            let android: AndroidChunk = json::from_slice(profile)?;
+            return Ok(ProfileChunk::new(Box::new(android)).with_parse_duration(start.elapsed()));
+        }
+        let sample: SampleChunk = json::from_slice(profile)?;
+        Ok(ProfileChunk::new(Box::new(sample)).with_parse_duration(start.elapsed()))
     }
 
     #[deprecated(
@@ -74,26 +233,166 @@ impl ProfileChunk {
         profile: &[u8],
         platform: &str,
     ) -> Result<Self, serde_json::Error> {
+        let start = Instant::now();
         match platform {
             "android" => {
-                let android: AndroidChunk = serde_json::from_slice(profile)?;
-                Ok(ProfileChunk {
-                    profile: Box::new(android),
-                })
+                let android: AndroidChunk = json::from_slice(profile)?;
+                Ok(ProfileChunk::new(Box::new(android)).with_parse_duration(start.elapsed()))
             }
             _ => {
-                let sample: SampleChunk = serde_json::from_slice(profile)?;
-                Ok(ProfileChunk {
-                    profile: Box::new(sample),
-                })
+                let sample: SampleChunk = json::from_slice(profile)?;
+                Ok(ProfileChunk::new(Box::new(sample)).with_parse_duration(start.elapsed()))
             }
         }
     }
 
-    pub(crate) fn decompress(source: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
-        let bytes = decompress_lz4(source)?;
+    /// Parses a chunk, splitting sample chunks over the limits, see
+    /// [`SampleChunk::split`]. Android trace chunks are never split, their
+    /// events being nested.
+    pub(crate) fn from_json_vec_split(
+        profile: &[u8],
+        max_samples: usize,
+        max_bytes: Option<usize>,
+    ) -> Result<Vec<Self>, PayloadError> {
+        let start = Instant::now();
+        let min_prof: MinimumProfile = json::from_slice(profile)?;
+        if is_android_trace_format(min_prof.version.as_deref())? {
+            return Ok(vec![Self::from_json_vec(profile)?]);
+        }
+        let sample: SampleChunk = json::from_slice(profile)?;
+        let parse_duration = start.elapsed();
+        // chunks within the size limit as sent are within it once split too
+        let max_bytes = max_bytes.filter(|&max_bytes| profile.len() > max_bytes);
+        Ok(sample
+            .split(max_samples, max_bytes)?
+            .into_iter()
+            .map(|chunk| ProfileChunk::new(Box::new(chunk)).with_parse_duration(parse_duration))
+            .collect())
+    }
+
+    /// Merges chunks collected by the same profiler, see
+    /// [`SampleChunk::merge`]. Android trace chunks can't be merged.
+    pub(crate) fn merge(chunks: &[&ProfileChunk]) -> Result<Self, MergeError> {
+        let chunks = chunks
+            .iter()
+            .map(|chunk| {
+                chunk
+                    .profile
+                    .as_any()
+                    .downcast_ref::<SampleChunk>()
+                    .ok_or(MergeError::UnsupportedFormat)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ProfileChunk::new(Box::new(SampleChunk::merge(&chunks)?)))
+    }
+
+    /// Extracts the function metrics of the chunk, building its call trees
+    /// if they weren't built yet with the same options.
+    pub fn extract_functions_metrics(
+        &mut self,
+        options: &FunctionsMetricsOptions,
+    ) -> Result<Vec<CallTreeFunction>, CallTreeError> {
+        let main_thread_id = if options.active_thread_only {
+            Some(self.profile.get_main_thread_id().unwrap_or_default())
+        } else {
+            None
+        };
+        let call_trees = self.call_trees(main_thread_id.as_deref(), options.call_tree_options())?;
+        let (mut functions, filtered_functions) = functions_metrics(&call_trees, options);
+        self.filtered_functions = filtered_functions;
+        set_examples_source(self.profile.as_ref(), &mut functions);
+        Ok(functions)
+    }
+
+    /// Extracts the function metrics of a chunk straight from its payload,
+    /// normalizing it first. The call trees of sample chunks are built while
+    /// their samples are parsed, see [`stream_call_trees`], while android
+    /// trace chunks are parsed in full.
+    pub(crate) fn stream_functions_metrics(
+        payload: &[u8],
+        options: &FunctionsMetricsOptions,
+    ) -> PyResult<Vec<CallTreeFunction>> {
+        let min_prof: MinimumProfile = json::from_slice(payload).map_err(PayloadError::from)?;
+        if is_android_trace_format(min_prof.version.as_deref())? {
+            let mut chunk = Self::from_json_vec(payload)?;
+            chunk.normalize(None)?;
+            return Ok(chunk.extract_functions_metrics(options)?);
+        }
+        let streamed = stream_call_trees(payload, options.active_thread_only)?;
+        let (mut functions, _) = functions_metrics(&streamed.call_trees, options);
+        set_examples_source(&streamed.chunk, &mut functions);
+        Ok(functions)
+    }
+
+    pub fn decompress(source: &[u8]) -> Result<Self, PayloadError> {
+        let bytes = decompress(source).map_err(PayloadError::Decompression)?;
         Self::from_json_vec(bytes.as_ref())
-            .map_err(|err| Box::new(err) as Box<dyn std::error::Error>)
+    }
+
+    /// Normalizes the chunk then extracts its function metrics, processing
+    /// stats and summary, building its call trees only once.
+    pub(crate) fn process(
+        &mut self,
+        options: &FunctionsMetricsOptions,
+    ) -> PyResult<ProcessedProfileChunk> {
+        self.normalize(None)?;
+        let functions = self.extract_functions_metrics(options)?;
+        let stats = processing_stats(
+            self.profile.as_ref(),
+            self.parse_duration,
+            self.build_duration,
+        );
+        let summary = ProfileSummary {
+            duration_ns: self.profile.duration_ms().saturating_mul(1_000_000),
+            thread_count: stats.threads,
+            sample_count: stats.samples as u64,
+            function_count: functions.len(),
+            occurrence_count: 0,
+        };
+        Ok(ProcessedProfileChunk {
+            functions,
+            stats,
+            summary,
+        })
+    }
+}
+
+/// Everything the profile consumer emits for a chunk, computed in a single
+/// pass over the chunk (see :func:`process_profile_chunk`).
+#[pyclass]
+pub struct ProcessedProfileChunk {
+    pub functions: Vec<CallTreeFunction>,
+    pub stats: ProcessingStats,
+    pub summary: ProfileSummary,
+}
+
+#[pymethods]
+impl ProcessedProfileChunk {
+    /// Returns the function metrics of the chunk.
+    ///
+    /// Returns:
+    ///     list[:class:`CallTreeFunction`]
+    ///         The functions, see :meth:`ProfileChunk.extract_functions_metrics`.
+    pub fn get_functions(&self) -> Vec<CallTreeFunction> {
+        self.functions.clone()
+    }
+
+    /// Returns the processing stats of the chunk.
+    ///
+    /// Returns:
+    ///     :class:`ProcessingStats`
+    ///         The processing stats, see :meth:`ProfileChunk.stats`.
+    pub fn get_stats(&self) -> ProcessingStats {
+        self.stats.clone()
+    }
+
+    /// Returns the summary of the chunk.
+    ///
+    /// Returns:
+    ///     :class:`ProfileSummary`
+    ///         The aggregates describing the chunk as a whole.
+    pub fn get_summary(&self) -> ProfileSummary {
+        self.summary.clone()
     }
 }
 
@@ -101,8 +400,36 @@ impl ProfileChunk {
 impl ProfileChunk {
     /// Applies the various normalization steps,
     /// depending on the profile's platform.
-    pub fn normalize(&mut self) {
-        self.profile.normalize();
+    ///
+    /// Args:
+    ///     in_app_rules (dict[str, dict[str, list[str]]], optional): Package prefixes
+    ///         forced in_app (`include`) or not (`exclude`), by platform, taking
+    ///         precedence over the in_app heuristics and the overrides of the
+    ///         organization. Defaults to the rules the chunk was parsed with.
+    ///
+    /// Raises:
+    ///     ValueError: If the rules are invalid (an unknown list, an empty prefix,
+    ///         a prefix both included and excluded).
+    ///
+    /// Example:
+    ///     >>> profile_chunk.normalize(in_app_rules={
+    ///     ...     "android": {"include": ["com.mycompany."], "exclude": ["com.thirdparty."]},
+    ///     ... })
+    #[pyo3(signature = (in_app_rules=None))]
+    pub fn normalize(
+        &mut self,
+        in_app_rules: Option<HashMap<String, HashMap<String, Vec<String>>>>,
+    ) -> PyResult<()> {
+        if let Some(in_app_rules) = in_app_rules {
+            self.set_in_app_rules(in_app_rules)
+                .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        }
+        let in_app_rules = self.in_app_rules.clone();
+        self.profile_mut()
+            .normalize_with_rules(in_app_rules.as_deref());
+        self.frames_generation += 1;
+        self.call_trees = None;
+        Ok(())
     }
 
     /// Returns the environment.
@@ -123,6 +450,36 @@ impl ProfileChunk {
         self.profile.get_chunk_id()
     }
 
+    /// Returns the ID of the trace the profile chunk was collected during,
+    /// if the SDK sent it.
+    ///
+    /// Returns:
+    ///     str
+    ///         The trace ID, or None.
+    pub fn get_trace_id(&self) -> Option<&str> {
+        self.profile.get_trace_context().trace_id.as_deref()
+    }
+
+    /// Returns the ID of the transaction the profile chunk was collected
+    /// during, if the SDK sent it.
+    ///
+    /// Returns:
+    ///     str
+    ///         The transaction ID, or None.
+    pub fn get_transaction_id(&self) -> Option<&str> {
+        self.profile.get_trace_context().transaction_id.as_deref()
+    }
+
+    /// Returns the IDs of the segments (root spans) overlapping the profile
+    /// chunk, to join it to its spans.
+    ///
+    /// Returns:
+    ///     list[str]
+    ///         The segment IDs, empty if the SDK sent none.
+    pub fn get_segment_ids(&self) -> Vec<String> {
+        self.profile.get_trace_context().segment_ids.clone()
+    }
+
     /// Returns the organization ID.
     ///
     /// Returns:
@@ -186,6 +543,50 @@ impl ProfileChunk {
         self.profile.get_retention_days()
     }
 
+    /// Sets the organization ID.
+    ///
+    /// Args:
+    ///     organization_id (int): The ID of the organization to which the profile belongs.
+    ///
+    /// Example:
+    ///     >>> profile_chunk.set_organization_id(1)
+    pub fn set_organization_id(&mut self, organization_id: u64) {
+        self.profile_mut().set_organization_id(organization_id);
+    }
+
+    /// Sets the project ID.
+    ///
+    /// Args:
+    ///     project_id (int): The ID of the project to which the profile belongs.
+    ///
+    /// Example:
+    ///     >>> profile_chunk.set_project_id(1)
+    pub fn set_project_id(&mut self, project_id: u64) {
+        self.profile_mut().set_project_id(project_id);
+    }
+
+    /// Sets the received timestamp.
+    ///
+    /// Args:
+    ///     received (float): The Unix timestamp, in seconds, at which the profile was received.
+    ///
+    /// Example:
+    ///     >>> profile_chunk.set_received(1710805688.237)
+    pub fn set_received(&mut self, received: f64) {
+        self.profile_mut().set_received(received);
+    }
+
+    /// Sets the retention days.
+    ///
+    /// Args:
+    ///     retention_days (int): The number of days the profile is retained.
+    ///
+    /// Example:
+    ///     >>> profile_chunk.set_retention_days(90)
+    pub fn set_retention_days(&mut self, retention_days: i32) {
+        self.profile_mut().set_retention_days(retention_days);
+    }
+
     /// Returns the duration of the profile in ms.
     ///
     /// Returns:
@@ -207,154 +608,1018 @@ impl ProfileChunk {
         self.profile.start_timestamp()
     }
 
-    /// Returns the end timestamp of the profile.
+    /// Returns the end timestamp of the profile.
+    ///
+    /// The timestamp is a Unix timestamp in seconds
+    /// with millisecond precision.
+    ///
+    /// Returns:
+    ///     float
+    ///         The end timestamp of the profile.
+    pub fn end_timestamp(&self) -> f64 {
+        self.profile.end_timestamp()
+    }
+
+    /// Converts a call tree timestamp to a UTC Unix timestamp.
+    ///
+    /// Chunk call trees are already timestamped from the Unix epoch, this
+    /// lines node timestamps up with logs and spans from other tools.
+    ///
+    /// Args:
+    ///     timestamp_ns (int): The call tree timestamp, in nanoseconds.
+    ///
+    /// Returns:
+    ///     float
+    ///         The Unix timestamp, in seconds.
+    ///
+    /// Example:
+    ///     >>> example = profile_chunk.extract_functions_metrics(1, False, max_examples=1)[0].get_examples()[0]
+    ///     >>> profile_chunk.absolute_timestamp(example.get_start_ns())
+    pub fn absolute_timestamp(&self, timestamp_ns: u64) -> f64 {
+        self.profile.absolute_timestamp_ns(timestamp_ns) as f64 / 1e9
+    }
+
+    /// Returns the SDK name.
+    ///
+    /// Returns:
+    ///     str
+    ///         The name of the SDK used to collect this profile,
+    ///         or None, if version is not available.
+    pub fn sdk_name(&self) -> Option<&str> {
+        self.profile.sdk_name()
+    }
+
+    /// Returns the SDK version.
+    ///
+    /// Returns:
+    ///     str
+    ///         The version of the SDK used to collect this profile,
+    ///         or None, if version is not available.
+    pub fn sdk_version(&self) -> Option<&str> {
+        self.profile.sdk_version()
+    }
+
+    /// Returns the storage path of the profile.
+    ///
+    /// Returns:
+    ///     str
+    ///         The storage path of the profile.
+    pub fn storage_path(&self) -> String {
+        self.profile.storage_path()
+    }
+
+    /// Compresses the profile with lz4.
+    ///
+    /// This method serializes the profile to json and then compresses it with lz4,
+    /// or zstd, returning the bytes representing the encoded profile.
+    ///
+    /// Profiles compressed with either codec are decompressed by
+    /// :func:`vroomrs.decompress_profile_chunk`.
+    ///
+    /// Args:
+    ///     codec (str): The codec to compress the profile with, "lz4" (default) or "zstd".
+    ///     level (int, optional): The compression level, from 0 to 16 for lz4 (9 by default)
+    ///         and from 1 to 22 for zstd (3 by default). Higher levels trade CPU for space.
+    ///     compressed_size_hint (int, optional): The expected size of the encoded profile,
+    ///         in bytes, used to preallocate the output buffer.
+    ///
+    /// Returns:
+    ///     bytes
+    ///         A bytes object representing the encoded profile.
+    ///
+    /// Raises:
+    ///     pyo3.exceptions.PyException: If the codec or the level are invalid, or an error
+    ///         occurs during the extraction process.
+    ///
+    /// Example:
+    ///     >>> compressed_profile = profile.compress()
+    ///     >>> with open("profile_compressed.lz4", "wb+") as binary_file:
+    ///     ...     binary_file.write(compressed_profile)
+    ///     >>> compressed_profile = profile.compress(codec="zstd", level=19)
+    #[pyo3(signature = (codec="lz4", level=None, compressed_size_hint=None))]
+    pub fn compress(
+        &self,
+        codec: &str,
+        level: Option<i32>,
+        compressed_size_hint: Option<usize>,
+    ) -> PyResult<Vec<u8>> {
+        let codec = Codec::from_name(codec)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+        let prof = self
+            .profile
+            .to_json_vec()
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+        compress(&prof, codec, level, compressed_size_hint)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))
+    }
+
+    /// Extracts function metrics from the profile chunk.
+    ///
+    /// This method analyzes the call tree and extracts metrics for each function,
+    /// returning a list of `CallTreeFunction` objects.
+    ///
+    /// Args:
+    ///     min_depth (int): The minimum depth of the node in the call tree.
+    ///         When computing slowest functions, ignore frames/node whose depth in the callTree
+    ///         is less than min_depth (i.e. if min_depth=1, we'll ignore root frames).
+    ///     filter_system_frames (bool): If `True`, system frames (e.g., standard library calls) will be filtered out.
+    ///     max_unique_functions (int): An optional maximum number of unique functions to extract.
+    ///         If provided, only the top `max_unique_functions` slowest functions will be returned.
+    ///         If `None`, all functions will be returned.
+    ///     filter_non_leaf_functions (bool): If `True`, functions with zero self-time (non-leaf functions) will be filtered out.
+    ///         If `False`, all functions including non-leaf functions with zero self-time will be included.
+    ///         Defaults to `True`.
+    ///     generate_stack_fingerprints (bool): If `True`, the fingerprint of the stack up to the current function and the parent function's fingerprint will be generated.
+    ///         If `False`, only the fingerprint of the current function will be generated.
+    ///         Defaults to `False`.
+    ///     max_examples (int): The maximum number of examples, the function calls with the longest self time,
+    ///         to record for each function.
+    ///         Defaults to `0`.
+    ///     active_thread_only (bool): If `True`, only functions sampled on the main thread are aggregated,
+    ///         so background workers don't skew application metrics.
+    ///         If the main thread can't be determined, no function is returned.
+    ///         Defaults to `False`.
+    ///     max_lines_per_function (int): The maximum number of lines self time is aggregated for, per function
+    ///         (see :meth:`CallTreeFunction.get_line_self_times_ns`), the lines with the most self time being kept.
+    ///         If `0`, self time isn't aggregated per line.
+    ///         Defaults to `0`.
+    ///     collapse_recursion (bool): If `True`, consecutive calls of the same function are collapsed into a single
+    ///         call when building the call trees, so recursive functions aren't counted once per level of recursion.
+    ///         Defaults to `False`.
+    ///     skip_idle_threads (bool): If `True`, threads whose samples all have the same stack (e.g. parked worker
+    ///         pools) are skipped when building the call trees, see :meth:`get_skipped_idle_threads`.
+    ///         Defaults to `False`.
+    ///     min_sample_count (int): The minimum number of samples of the functions returned, to reduce the amount of
+    ///         data. Lower it to keep short functions of platforms sampled at a low frequency (e.g. PHP at 10Hz).
+    ///         The number of functions dropped is returned by :meth:`get_filtered_functions`.
+    ///         Defaults to `2`.
+    ///     sort_by (str): What the functions are ranked by, in decreasing order, before keeping the top
+    ///         `max_unique_functions`: "self_time" (their sum of self times), "sample_count" or "total_time" (their
+    ///         sum of total times, see :meth:`CallTreeFunction.get_sum_total_time_ns`). Ties are broken by increasing
+    ///         fingerprint, so the order is stable.
+    ///         Defaults to `"self_time"`.
+    ///
+    /// Returns:
+    ///     list[:class:`CallTreeFunction`]
+    ///         A list of :class:`CallTreeFunction` objects, each containing metrics for a function in the call tree,
+    ///         including the self time of each of its calls (see :meth:`CallTreeFunction.get_self_times_ns`),
+    ///         sorted by `sort_by`.
+    ///
+    /// Raises:
+    ///     pyo3.exceptions.ValueError: If `sort_by` is unknown.
+    ///     pyo3.exceptions.PyException: If an error occurs during the extraction process.
+    ///
+    /// Example:
+    ///     >>> metrics = profile_chunk.extract_functions_metrics(min_depth=2, filter_system_frames=True, max_unique_functions=10, filter_non_leaf_functions=False)
+    ///     >>> for function_metric in metrics:
+    ///     ...     do_something(function_metric)
+    #[pyo3(name = "extract_functions_metrics", signature = (min_depth, filter_system_frames, max_unique_functions=None, filter_non_leaf_functions=true, generate_stack_fingerprints=false, max_examples=0, active_thread_only=false, max_lines_per_function=0, collapse_recursion=false, skip_idle_threads=false, min_sample_count=2, sort_by="self_time"))]
+    #[allow(clippy::too_many_arguments)]
+    fn py_extract_functions_metrics(
+        &mut self,
+        min_depth: u16,
+        filter_system_frames: bool,
+        max_unique_functions: Option<usize>,
+        filter_non_leaf_functions: bool,
+        generate_stack_fingerprints: bool,
+        max_examples: usize,
+        active_thread_only: bool,
+        max_lines_per_function: usize,
+        collapse_recursion: bool,
+        skip_idle_threads: bool,
+        min_sample_count: u64,
+        sort_by: &str,
+    ) -> PyResult<Vec<CallTreeFunction>> {
+        let options = FunctionsMetricsOptions {
+            min_depth,
+            filter_system_frames,
+            max_unique_functions,
+            filter_non_leaf_functions,
+            generate_stack_fingerprints,
+            max_examples,
+            active_thread_only,
+            max_lines_per_function,
+            collapse_recursion,
+            skip_idle_threads,
+            min_sample_count,
+            sort_key: FunctionsSortKey::from_name(sort_by)?,
+        };
+        Ok(self.extract_functions_metrics(&options)?)
+    }
+
+    /// Extracts metrics from the device impact measurements of the profile
+    /// chunk, to be emitted alongside its function metrics.
+    ///
+    /// The values of the `cpu_usage`, `memory_footprint` and
+    /// `memory_native_footprint` measurements are aggregated into their
+    /// minimum, maximum, mean and 90th percentile.
+    ///
+    /// Returns:
+    ///     list[:class:`MeasurementMetric`]
+    ///         A metric per measurement, missing or empty measurements being
+    ///         left out.
+    ///
+    /// Example:
+    ///     >>> for metric in profile_chunk.extract_measurement_metrics():
+    ///     ...     print(metric.get_name(), metric.get_p90())
+    pub fn extract_measurement_metrics(&self) -> Vec<MeasurementMetric> {
+        measurement_metrics(self.profile.get_measurements())
+    }
+
+    /// Returns the number of events fixed up while building the call trees
+    /// of the profile chunk, by thread ID, to monitor malformed traces.
+    ///
+    /// Android traces can have exit events without a matching enter event,
+    /// which are ignored, and frames without an exit event (or unwound by an
+    /// exception past them), which are closed at the next exit of one of their
+    /// callers, or at the end of the chunk.
+    ///
+    /// Returns:
+    ///     dict[str, int]
+    ///         The number of events fixed up, by thread ID. Empty if the call
+    ///         trees weren't built yet, or if nothing needed fixing up.
+    pub fn get_fixed_up_events(&self) -> HashMap<String, u64> {
+        self.profile.fixed_up_events()
+    }
+
+    /// Returns the number of stacks truncated at the maximum depth (see
+    /// :func:`vroomrs.configure`) while building the call trees of the
+    /// profile chunk, to alert on pathological payloads.
+    ///
+    /// Sample stacks are truncated from the root side, keeping their
+    /// innermost frames, and android traces drop the frames entered past
+    /// the maximum depth.
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of stacks truncated. 0 if the call trees weren't
+    ///         built yet.
+    pub fn get_truncated_stacks(&self) -> u64 {
+        self.profile.truncated_stacks()
+    }
+
+    /// Returns the number of functions dropped by the last call to
+    /// :meth:`extract_functions_metrics` for having fewer samples than its
+    /// `min_sample_count`, to monitor the data lost.
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of functions dropped. 0 if no function metrics were
+    ///         extracted yet.
+    pub fn get_filtered_functions(&self) -> u64 {
+        self.filtered_functions
+    }
+
+    /// Returns the number of idle threads, whose samples all have the same
+    /// stack, skipped while building the call trees of the profile chunk
+    /// with `skip_idle_threads` (see :meth:`extract_functions_metrics`).
+    ///
+    /// Android traces, whose threads aren't sampled, never skip any.
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of threads skipped. 0 if the call trees weren't
+    ///         built yet, or were built without skipping idle threads.
+    pub fn get_skipped_idle_threads(&self) -> u64 {
+        self.profile.skipped_idle_threads()
+    }
+
+    /// Scores how anomalous the data of the profile chunk is, so
+    /// data-quality dashboards and filters can exclude junk chunks. The
+    /// score is meant to be stored along with the chunk metadata.
+    ///
+    /// The score combines the ratio of unknown frames, of events repaired or
+    /// fixed up while building the call trees, of stacks truncated at the
+    /// maximum depth, of frames still obfuscated, and the skew between the
+    /// clock of the device and the time the chunk was received.
+    ///
+    /// Returns:
+    ///     :class:`AnomalyScore`
+    ///         The score, from 0 (nothing anomalous) to 1, and its signals.
+    ///
+    /// Raises:
+    ///     pyo3.exceptions.PyException: If an error occurs while building the call trees.
+    ///
+    /// Example:
+    ///     >>> if profile_chunk.anomaly_score().get_score() > 0.5:
+    ///     ...     print("junk chunk", profile_chunk.get_chunk_id())
+    pub fn anomaly_score(&mut self) -> PyResult<AnomalyScore> {
+        self.call_trees(None, CallTreeOptions::default())?;
+        Ok(anomaly_score(self.profile.as_ref()))
+    }
+
+    /// Returns statistics on the processing of the profile chunk (its sizes,
+    /// the repairs made building its call trees, and how long parsing and
+    /// building took), to log and monitor them.
+    ///
+    /// The call trees are built if they weren't yet, the repair counters and
+    /// build time being those of the call trees last built.
+    ///
+    /// Returns:
+    ///     :class:`ProcessingStats`
+    ///         The processing stats of the chunk.
+    ///
+    /// Raises:
+    ///     pyo3.exceptions.PyException: If an error occurs while building the call trees.
+    ///
+    /// Example:
+    ///     >>> logger.info("chunk processed", extra=json.loads(profile_chunk.stats().to_json_str()))
+    pub fn stats(&mut self) -> PyResult<ProcessingStats> {
+        if self.call_trees.is_none() {
+            self.call_trees(None, CallTreeOptions::default())?;
+        }
+        Ok(processing_stats(
+            self.profile.as_ref(),
+            self.parse_duration,
+            self.build_duration,
+        ))
+    }
+
+    /// Returns the ratio of the frames of the profile chunk which went through
+    /// deobfuscation that were found in the ProGuard mapping file, to measure
+    /// how many mappings are missing.
+    ///
+    /// Frames not found in the mapping file (whose deobfuscation status is
+    /// `missing`) are excluded from the function metrics.
+    ///
+    /// Returns:
+    ///     float
+    ///         The ratio of deobfuscated frames, from 0 to 1, or None if no frame went
+    ///         through deobfuscation (no mapping file specified, or not an android chunk).
+    ///
+    /// Example:
+    ///     >>> if (ratio := profile_chunk.deobfuscation_ratio()) is not None:
+    ///     ...     record_deobfuscation_ratio(profile_chunk.get_project_id(), ratio)
+    pub fn deobfuscation_ratio(&self) -> Option<f64> {
+        deobfuscation_ratio(self.profile.as_ref())
+    }
+
+    /// Counts the samples of each thread of the profile chunk per time bucket,
+    /// for overviews of which thread was busy when (e.g. the minimap of the UI).
+    ///
+    /// Android traces without samples count the events entering methods instead.
+    ///
+    /// Args:
+    ///     bucket_ms (int): The duration of the buckets, in milliseconds.
+    ///
+    /// Returns:
+    ///     :class:`ActivityTimeline`
+    ///         The sample counts of each thread per bucket, buckets covering the chunk from its start.
+    ///
+    /// Raises:
+    ///     pyo3.exceptions.ValueError: If `bucket_ms` is 0.
+    ///
+    /// Example:
+    ///     >>> timeline = profile_chunk.activity_timeline(bucket_ms=100)
+    ///     >>> for thread_id, counts in timeline.get_threads().items():
+    ///     ...     draw_minimap_row(thread_id, counts)
+    pub fn activity_timeline(&self, bucket_ms: u64) -> PyResult<ActivityTimeline> {
+        if bucket_ms == 0 {
+            return Err(PyValueError::new_err("bucket_ms must be greater than 0"));
+        }
+        Ok(activity_timeline(
+            self.profile.as_ref(),
+            bucket_ms * 1_000_000,
+        ))
+    }
+
+    /// Returns the sampling interval of the profile chunk, estimated from the
+    /// timestamps of its samples: the median of the medians of the deltas
+    /// between the consecutive samples of each thread.
+    ///
+    /// Returns:
+    ///     int
+    ///         The estimated sampling interval in nanoseconds, or None for chunks with
+    ///         fewer than 2 samples per thread and for android traces, whose events aren't sampled.
+    pub fn sampling_interval_ns(&self) -> Option<u64> {
+        sampling_interval_ns(self.profile.as_ref())
+    }
+
+    /// Returns the effective sampling frequency of the profile chunk, derived
+    /// from its estimated sampling interval.
+    ///
+    /// Returns:
+    ///     float
+    ///         The estimated sampling frequency in Hz, or None if the sampling interval
+    ///         can't be estimated.
+    ///
+    /// Example:
+    ///     >>> if (sample_rate := profile_chunk.sample_rate()) is not None:
+    ///     ...     record_sample_rate(profile_chunk.sdk_name(), sample_rate)
+    pub fn sample_rate(&self) -> Option<f64> {
+        self.sampling_interval_ns()
+            .map(|interval_ns| 1e9 / interval_ns as f64)
+    }
+
+    /// Returns whether the estimated sampling interval of the profile chunk
+    /// deviates by a factor of more than 2 from the interval of the nominal
+    /// sampling frequency of the SDKs, 101Hz.
+    ///
+    /// Returns:
+    ///     bool
+    ///         True if the chunk wasn't sampled at the nominal frequency, False if it was
+    ///         or if its sampling interval can't be estimated.
+    pub fn has_off_nominal_sample_rate(&self) -> bool {
+        self.sampling_interval_ns()
+            .is_some_and(is_off_nominal_interval)
+    }
+
+    /// Returns how far symbolication of the frames of the profile chunk went,
+    /// to decide whether native (cocoa, windows and linux) chunks should be
+    /// withheld from function metrics until symbolication completes.
+    ///
+    /// Frames pending symbolication are native frames only known by their
+    /// instruction address, which symbolicator didn't process yet. Frames it
+    /// failed to symbolicate aren't pending, as waiting won't fix them.
+    ///
+    /// Args:
+    ///     max_pending_ratio (float): The maximum ratio of frames pending symbolication
+    ///         of a chunk whose function metrics are extracted right away.
+    ///         Defaults to `0`, withholding chunks with any frame pending symbolication.
+    ///
+    /// Returns:
+    ///     :class:`SymbolicationStats`
+    ///         The stats, whose `resubmit_after_symbolication` marker is set if the chunk
+    ///         should be withheld, and resubmitted once symbolicated.
+    ///
+    /// Example:
+    ///     >>> stats = profile_chunk.symbolication_stats()
+    ///     >>> if stats.get_resubmit_after_symbolication():
+    ///     ...     store_metadata(profile_chunk, stats.to_json_str())
+    ///     ... else:
+    ///     ...     metrics = profile_chunk.extract_functions_metrics(min_depth=1, filter_system_frames=True)
+    #[pyo3(signature = (max_pending_ratio=0.0))]
+    pub fn symbolication_stats(&self, max_pending_ratio: f64) -> SymbolicationStats {
+        symbolication_stats(self.profile.as_ref(), max_pending_ratio)
+    }
+
+    /// Calls `callback` once per unique frame of the profile chunk and
+    /// applies the overrides it returns, to enrich frames (e.g. from an
+    /// internal symbol server, or with ownership information) in place.
+    ///
+    /// The callback receives a dict with the `function`, `module`, `package`,
+    /// `path`, `lineno`, `in_app` and `platform` of the frame, and returns
+    /// either None to leave the frame untouched, or a dict of the fields to
+    /// override (any of the above but `platform`). Only `path`, `lineno` and
+    /// `in_app` can be overridden on android trace chunks, their function and
+    /// package names being derived from the method signatures.
+    ///
+    /// Frames are only modified once the callback returned for all of them,
+    /// so the chunk is left untouched if it raises or returns invalid overrides.
+    ///
+    /// Args:
+    ///     callback (Callable[[dict], Optional[dict]]): The function called for each frame.
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of frames modified.
+    ///
+    /// Raises:
+    ///     ValueError: If the callback returns a field which can't be overridden.
+    ///     RuntimeError: If the chunk is being modified by another thread, or
+    ///         was modified (e.g. normalized) while the callback ran.
+    ///
+    /// Example:
+    ///     >>> def own_vendored(frame):
+    ///     ...     if (frame["module"] or "").startswith("vendored."):
+    ///     ...         return {"in_app": False}
+    ///     >>> profile_chunk.map_frames(own_vendored)
+    pub fn map_frames(
+        slf: &Bound<'_, Self>,
+        py: Python<'_>,
+        callback: &Bound<'_, PyAny>,
+    ) -> PyResult<usize> {
+        // the chunk isn't borrowed while the callback runs, so it can be used
+        // by other threads (or by the callback itself) in the meantime
+        let (frames, frames_generation) = {
+            let chunk = slf.try_borrow()?;
+            let frames = chunk
+                .profile
+                .frames()
+                .iter()
+                .map(|frame| frame.to_py_dict(py))
+                .collect::<PyResult<Vec<_>>>()?;
+            (frames, chunk.frames_generation)
+        };
+        let mut overrides = Vec::new();
+        for (index, frame) in frames.into_iter().enumerate() {
+            let returned = callback.call1((frame,))?;
+            let frame_overrides = FrameOverrides::from_py(&returned)?;
+            if !frame_overrides.is_empty() {
+                overrides.push((index, frame_overrides));
+            }
+        }
+        if overrides.is_empty() {
+            return Ok(0);
+        }
+        slf.try_borrow_mut()?
+            .override_frames(&overrides, frames_generation)?;
+        Ok(overrides.len())
+    }
+
+    /// Returns a copy of the profile chunk, to process it from several
+    /// threads at once, or to modify it while keeping the original.
+    ///
+    /// Copies are cheap: they share the payload of the chunk, only copied
+    /// once either is modified, as building call trees which weren't built
+    /// yet does.
+    ///
+    /// Returns:
+    ///     :class:`ProfileChunk`
+    ///         A copy of the profile chunk.
+    ///
+    /// Example:
+    ///     >>> copies = [profile_chunk.clone() for _ in range(workers)]
+    #[allow(clippy::should_implement_trait)]
+    pub fn clone(&self) -> ProfileChunk {
+        ProfileChunk {
+            profile: Arc::clone(&self.profile),
+            frames_generation: self.frames_generation,
+            call_trees: self.call_trees.clone(),
+            in_app_rules: self.in_app_rules.clone(),
+            filtered_functions: self.filtered_functions,
+            parse_duration: self.parse_duration,
+            build_duration: self.build_duration,
+        }
+    }
+
+    /// Returns a copy of the profile chunk, see :meth:`clone`.
+    ///
+    /// Returns:
+    ///     :class:`ProfileChunk`
+    ///         A copy of the profile chunk.
+    pub fn __copy__(&self) -> ProfileChunk {
+        self.clone()
+    }
+
+    /// Returns a copy of the profile chunk, see :meth:`clone`.
+    ///
+    /// Args:
+    ///     memo (dict): The memo of :func:`copy.deepcopy`, unused as chunks
+    ///         don't reference Python objects.
+    ///
+    /// Returns:
+    ///     :class:`ProfileChunk`
+    ///         A copy of the profile chunk.
+    pub fn __deepcopy__(&self, _memo: &Bound<'_, PyAny>) -> ProfileChunk {
+        self.clone()
+    }
+
+    /// Finds the CPU usage spikes of the profile chunk.
+    ///
+    /// A window of `window_ms` is slid over the `cpu_usage` measurement, and
+    /// windows whose average usage reaches `threshold` are reported as spikes,
+    /// overlapping ones being merged. Each spike is attributed to the stacks
+    /// the most time was spent in while it happened.
+    ///
+    /// Args:
+    ///     window_ms (int): The size of the sliding window, in milliseconds.
+    ///     threshold (float): The minimum average CPU usage, in percent, of a spike window.
+    ///     max_stacks (int): The maximum number of stacks to attribute each spike to.
+    ///         Defaults to `5`.
+    ///
+    /// Returns:
+    ///     list[:class:`CpuSpike`]
+    ///         The spikes, sorted by start timestamp. The list is empty if
+    ///         the chunk has no `cpu_usage` measurement.
+    ///
+    /// Raises:
+    ///     ValueError: If the window doesn't fit in nanoseconds.
+    ///     pyo3.exceptions.PyException: If an error occurs while building the call trees.
+    ///
+    /// Example:
+    ///     >>> for spike in profile_chunk.cpu_spikes(window_ms=500, threshold=90.0):
+    ///     ...     print(spike.get_start_ns(), spike.get_stacks()[0].get_frames())
+    #[pyo3(signature = (window_ms, threshold, max_stacks=5))]
+    pub fn cpu_spikes(
+        &mut self,
+        window_ms: u64,
+        threshold: f64,
+        max_stacks: usize,
+    ) -> PyResult<Vec<CpuSpike>> {
+        let Some(cpu_usage) = self.profile.measurement("cpu_usage") else {
+            return Ok(vec![]);
+        };
+        let window_ns = window_ms
+            .checked_mul(1_000_000)
+            .ok_or_else(|| PyValueError::new_err(format!("window_ms too large: {window_ms}")))?;
+        let mut spikes = find_cpu_spikes(&cpu_usage.values, window_ns, threshold);
+        if spikes.is_empty() {
+            return Ok(spikes);
+        }
+
+        let call_trees = self.call_trees(None, CallTreeOptions::default())?;
+        for spike in &mut spikes {
+            spike.stacks = dominant_stacks(&call_trees, spike.start_ns, spike.end_ns, max_stacks);
+        }
+        Ok(spikes)
+    }
+
+    /// Finds the intervals during which the memory footprint of the profile
+    /// chunk grew, along with the functions suspected to cause the growth.
+    ///
+    /// Growth intervals are runs of monotonically increasing values of the
+    /// `memory_footprint` measurement (or `memory_native_footprint`, on android).
+    /// Suspects are the functions calling allocation functions (`malloc`,
+    /// `posix_memalign`, `PyObject_Malloc`, `operator new`, ...) during the
+    /// interval, credited to their closest application caller.
+    ///
+    /// Args:
+    ///     min_growth_bytes (float): The minimum growth of an interval to be reported.
+    ///         Defaults to `0`.
+    ///     max_suspects (int): The maximum number of suspects to report per interval.
+    ///         Defaults to `5`.
+    ///
+    /// Returns:
+    ///     list[:class:`MemoryGrowth`]
+    ///         The growth intervals, sorted by start timestamp. The list is empty if
+    ///         the chunk has no memory footprint measurement.
+    ///
+    /// Raises:
+    ///     pyo3.exceptions.PyException: If an error occurs while building the call trees.
+    ///
+    /// Example:
+    ///     >>> for growth in profile_chunk.memory_growth_suspects(min_growth_bytes=10_000_000):
+    ///     ...     print(growth.get_growth_bytes(), growth.get_suspects()[0].get_function())
+    #[pyo3(signature = (min_growth_bytes=0.0, max_suspects=5))]
+    pub fn memory_growth_suspects(
+        &mut self,
+        min_growth_bytes: f64,
+        max_suspects: usize,
+    ) -> PyResult<Vec<MemoryGrowth>> {
+        let Some(footprint) = MEMORY_FOOTPRINT_MEASUREMENTS
+            .iter()
+            .find_map(|name| self.profile.measurement(name))
+        else {
+            return Ok(vec![]);
+        };
+        let mut growths = find_memory_growths(&footprint.values, min_growth_bytes);
+        if growths.is_empty() {
+            return Ok(growths);
+        }
+
+        let call_trees = self.call_trees(None, CallTreeOptions::default())?;
+        for growth in &mut growths {
+            growth.suspects =
+                memory_growth_suspects(&call_trees, growth.start_ns, growth.end_ns, max_suspects);
+        }
+        Ok(growths)
+    }
+
+    /// Exports the longest call tree nodes of the profile chunk as
+    /// OpenTelemetry spans, for profiling insights to be piped into OTel
+    /// pipelines.
     ///
-    /// The timestamp is a Unix timestamp in seconds
-    /// with millisecond precision.
+    /// Spans of the same profiler session share a trace ID, derived from the
+    /// profiler ID. A span is parented to the span of the closest ancestor of
+    /// its node which was also exported, and carries attributes identifying
+    /// the chunk it comes from (see :meth:`OtelSpan.get_attributes`).
+    ///
+    /// Args:
+    ///     top_n (int): The number of nodes to export, the longest ones.
+    ///         Defaults to `10`.
     ///
     /// Returns:
-    ///     float
-    ///         The end timestamp of the profile.
-    pub fn end_timestamp(&self) -> f64 {
-        self.profile.end_timestamp()
+    ///     list[:class:`OtelSpan`]
+    ///         The spans, sorted by start time.
+    ///
+    /// Raises:
+    ///     pyo3.exceptions.PyException: If an error occurs while building the call trees.
+    ///
+    /// Example:
+    ///     >>> spans = [json.loads(span.to_json_str()) for span in profile_chunk.export_otel_spans(top_n=20)]
+    ///     >>> export_request = {"resourceSpans": [{"scopeSpans": [{"spans": spans}]}]}
+    #[pyo3(signature = (top_n=10))]
+    pub fn export_otel_spans(&mut self, top_n: usize) -> PyResult<Vec<OtelSpan>> {
+        let call_trees = self.call_trees(None, CallTreeOptions::default())?;
+        Ok(otel_spans(self.profile.as_ref(), &call_trees, top_n))
     }
 
-    /// Returns the SDK name.
+    /// Returns the time each thread of the profile chunk spent collecting
+    /// garbage, for runtimes whose garbage collection frames are folded into
+    /// the synthetic `Garbage Collection` frame on normalization (javascript,
+    /// node, android and java).
     ///
     /// Returns:
-    ///     str
-    ///         The name of the SDK used to collect this profile,
-    ///         or None, if version is not available.
-    pub fn sdk_name(&self) -> Option<&str> {
-        self.profile.sdk_name()
+    ///     dict[str, int]
+    ///         The garbage collection time in nanoseconds, by thread ID, for the
+    ///         threads which spent any.
+    ///
+    /// Raises:
+    ///     pyo3.exceptions.PyException: If an error occurs while building the call trees.
+    ///
+    /// Example:
+    ///     >>> profile_chunk.normalize()
+    ///     >>> gc_ns = sum(profile_chunk.gc_durations_ns().values())
+    pub fn gc_durations_ns(&mut self) -> PyResult<HashMap<String, u64>> {
+        let call_trees = self.call_trees(None, CallTreeOptions::default())?;
+        Ok(gc_durations_ns(&call_trees))
     }
 
-    /// Returns the SDK version.
+    /// Returns a new profile chunk with only the samples within a time window,
+    /// e.g. to build the flamegraph of a span.
+    ///
+    /// The chunk only keeps the frames and stacks used by the samples of the
+    /// window, and the measurement values within it. As in any chunk, the last
+    /// sample of each thread has no duration.
+    ///
+    /// Args:
+    ///     start_ts (float): The start of the window (inclusive), a Unix timestamp in seconds.
+    ///     end_ts (float): The end of the window (exclusive), a Unix timestamp in seconds.
     ///
     /// Returns:
-    ///     str
-    ///         The version of the SDK used to collect this profile,
-    ///         or None, if version is not available.
-    pub fn sdk_version(&self) -> Option<&str> {
-        self.profile.sdk_version()
+    ///     :class:`ProfileChunk`
+    ///         The chunk of the samples within the window, with the same chunk ID.
+    ///
+    /// Raises:
+    ///     ValueError: If a timestamp is negative or not finite, or if the chunk is in the legacy
+    ///         android trace format, whose events can't be sliced.
+    ///
+    /// Example:
+    ///     >>> span_chunk = profile_chunk.slice(span["start_timestamp"], span["timestamp"])
+    pub fn slice(&self, start_ts: f64, end_ts: f64) -> PyResult<ProfileChunk> {
+        let chunk = self
+            .profile
+            .as_any()
+            .downcast_ref::<SampleChunk>()
+            .ok_or_else(|| PyValueError::new_err("android trace chunks can't be sliced"))?;
+        let (Some(start_ns), Some(end_ns)) =
+            (nanos_from_seconds(start_ts), nanos_from_seconds(end_ts))
+        else {
+            return Err(PyValueError::new_err("invalid timestamp"));
+        };
+        let mut slice = ProfileChunk::new(Box::new(chunk.slice(start_ns, end_ns)));
+        slice.in_app_rules = self.in_app_rules.clone();
+        Ok(slice)
     }
 
-    /// Returns the storage path of the profile.
+    /// Returns the call tree of a thread during a time interval, e.g. to show
+    /// what was running during a span, without transferring the whole chunk.
+    ///
+    /// The tree is built from the samples of the thread within the interval
+    /// (see :meth:`slice`), each node holding its fields (``name``,
+    /// ``package``, ``start_ns``, ``duration_ns``, ``frame``...) and its
+    /// ``children`` nodes.
+    ///
+    /// Args:
+    ///     thread_id (str): The ID of the thread.
+    ///     start_ns (int): The start of the interval (inclusive), a Unix timestamp in nanoseconds.
+    ///     end_ns (int): The end of the interval (exclusive), a Unix timestamp in nanoseconds.
     ///
     /// Returns:
-    ///     str
-    ///         The storage path of the profile.
-    pub fn storage_path(&self) -> String {
-        self.profile.storage_path()
+    ///     list[dict[str, Any]]
+    ///         The root nodes of the call tree, in chronological order, empty if the thread
+    ///         wasn't sampled during the interval.
+    ///
+    /// Raises:
+    ///     ValueError: If the chunk is in the legacy android trace format, whose events can't be
+    ///         sliced, or if a sample references a missing stack or frame.
+    ///
+    /// Example:
+    ///     >>> roots = profile_chunk.call_trees_for_interval(span["thread_id"], start_ns, end_ns)
+    pub fn call_trees_for_interval<'py>(
+        &self,
+        py: Python<'py>,
+        thread_id: &str,
+        start_ns: u64,
+        end_ns: u64,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let chunk = self
+            .profile
+            .as_any()
+            .downcast_ref::<SampleChunk>()
+            .ok_or_else(|| PyValueError::new_err("android trace chunks can't be sliced"))?;
+        let mut slice = chunk.thread_slice(thread_id, start_ns, end_ns);
+        let call_trees = slice.call_trees(Some(thread_id))?;
+        let roots = nested_call_trees(&call_trees)
+            .remove(thread_id)
+            .unwrap_or_default();
+        let roots =
+            serde_json::to_string(&roots).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        py.import("json")?.call_method1("loads", (roots,))
     }
 
-    /// Compresses the profile with lz4.
+    /// Exports the samples of the profile chunk as an Arrow record batch, in
+    /// the Arrow IPC streaming format.
     ///
-    /// This method serializes the profile to json and then compresses it with lz4,
-    /// returning the bytes representing the lz4 encoded profile.
+    /// Samples are flattened into a row per frame, with the columns
+    /// ``thread_id``, ``timestamp`` (UTC, in nanoseconds), ``depth`` (0 for
+    /// the root frame), ``function``, ``package``, ``in_app`` and
+    /// ``duration_ns`` (the time until the next sample of the thread).
     ///
     /// Returns:
     ///     bytes
-    ///         A bytes object representing the lz4 encoded profile.
+    ///         The record batch, in the Arrow IPC streaming format.
     ///
     /// Raises:
-    ///     pyo3.exceptions.PyException: If an error occurs during the extraction process.
+    ///     ValueError: If the chunk is in the legacy android trace format, which has no samples,
+    ///         or if a sample references a missing stack or frame.
     ///
     /// Example:
-    ///     >>> compressed_profile = profile.compress()
-    ///     >>> with open("profile_compressed.lz4", "wb+") as binary_file:
-    ///     ...     binary_file.write(compressed_profile)
-    pub fn compress(&self) -> PyResult<Vec<u8>> {
-        let prof = self
+    ///     >>> table = pyarrow.ipc.open_stream(profile_chunk.to_arrow()).read_all()
+    ///     >>> pyarrow.parquet.write_table(table, "samples.parquet")
+    #[cfg(feature = "arrow")]
+    pub fn to_arrow(&self) -> PyResult<Vec<u8>> {
+        let chunk = self
             .profile
-            .to_json_vec()
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
-        compress_lz4(&mut prof.as_slice())
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))
+            .as_any()
+            .downcast_ref::<SampleChunk>()
+            .ok_or_else(|| PyValueError::new_err("android trace chunks have no samples"))?;
+        let batch = crate::arrow::samples_record_batch(chunk)?;
+        crate::arrow::to_ipc_stream(&batch).map_err(|e| PyValueError::new_err(e.to_string()))
     }
 
-    /// Extracts function metrics from the profile chunk.
+    /// Serializes the call trees of the profile chunk to MessagePack.
     ///
-    /// This method analyzes the call tree and extracts metrics for each function,
-    /// returning a list of `CallTreeFunction` objects.
-    ///
-    /// Args:
-    ///     min_depth (int): The minimum depth of the node in the call tree.
-    ///         When computing slowest functions, ignore frames/node whose depth in the callTree
-    ///         is less than min_depth (i.e. if min_depth=1, we'll ignore root frames).
-    ///     filter_system_frames (bool): If `True`, system frames (e.g., standard library calls) will be filtered out.
-    ///     max_unique_functions (int): An optional maximum number of unique functions to extract.
-    ///         If provided, only the top `max_unique_functions` slowest functions will be returned.
-    ///         If `None`, all functions will be returned.
-    ///     filter_non_leaf_functions (bool): If `True`, functions with zero self-time (non-leaf functions) will be filtered out.
-    ///         If `False`, all functions including non-leaf functions with zero self-time will be included.
-    ///         Defaults to `True`.
-    ///     generate_stack_fingerprints (bool): If `True`, the fingerprint of the stack up to the current function and the parent function's fingerprint will be generated.
-    ///         If `False`, only the fingerprint of the current function will be generated.
-    ///         Defaults to `False`.
+    /// The payload is a map of the root nodes of each thread, by thread ID,
+    /// each node holding its fields (``name``, ``package``, ``start_ns``,
+    /// ``duration_ns``, ``frame``...) and its ``children`` nodes.
     ///
     /// Returns:
-    ///     list[:class:`CallTreeFunction`]
-    ///         A list of :class:`CallTreeFunction` objects, each containing metrics for a function in the call tree.
+    ///     bytes
+    ///         The MessagePack encoding of the call trees.
     ///
     /// Raises:
-    ///     pyo3.exceptions.PyException: If an error occurs during the extraction process.
+    ///     pyo3.exceptions.PyException: If an error occurs while building the call trees.
+    ///     ValueError: If the serialization fails due to invalid data.
     ///
     /// Example:
-    ///     >>> metrics = profile_chunk.extract_functions_metrics(min_depth=2, filter_system_frames=True, max_unique_functions=10, filter_non_leaf_functions=False)
-    ///     >>> for function_metric in metrics:
-    ///     ...     do_something(function_metric)
-    #[pyo3(signature = (min_depth, filter_system_frames, max_unique_functions=None, filter_non_leaf_functions=true, generate_stack_fingerprints=false))]
-    pub fn extract_functions_metrics(
-        &mut self,
-        min_depth: u16,
-        filter_system_frames: bool,
-        max_unique_functions: Option<usize>,
-        filter_non_leaf_functions: bool,
-        generate_stack_fingerprints: bool,
-    ) -> PyResult<Vec<CallTreeFunction>> {
-        let call_trees: CallTreesStr = self.profile.call_trees(None)?;
-        let mut functions: HashMap<u32, CallTreeFunction> = HashMap::new();
+    ///     >>> payload = profile_chunk.call_trees_to_msgpack()
+    pub fn call_trees_to_msgpack(&mut self) -> PyResult<Vec<u8>> {
+        let call_trees = self.call_trees(None, CallTreeOptions::default())?;
+        msgpack::to_vec(&nested_call_trees(&call_trees))
+    }
+}
+
+/// Aggregates the functions of the call trees of a chunk, see
+/// [`ProfileChunk::extract_functions_metrics`]. Returns the functions, along
+/// with the number of functions dropped for having too few samples.
+fn functions_metrics(
+    call_trees: &CallTreesStr,
+    options: &FunctionsMetricsOptions,
+) -> (Vec<CallTreeFunction>, u64) {
+    let functions = collect_functions(call_trees, options);
+    top_functions(functions, options)
+}
+
+/// Collects the functions of the call trees of a chunk, by fingerprint (or
+/// stack fingerprint).
+fn collect_functions(
+    call_trees: &CallTreesStr,
+    options: &FunctionsMetricsOptions,
+) -> HashMap<u32, CallTreeFunction> {
+    let mut functions: HashMap<u32, CallTreeFunction> = HashMap::new();
+
+    for (tid, call_tree) in call_trees {
+        for &root in call_tree.roots() {
+            call_tree.collect_functions(
+                root,
+                &mut functions,
+                tid,
+                0,
+                options.min_depth,
+                options.filter_system_frames,
+                options.filter_non_leaf_functions,
+                options.generate_stack_fingerprints,
+                options.max_examples,
+                options.max_lines_per_function > 0,
+                None,
+            );
+        }
+    }
+    functions
+}
+
+/// Filters and sorts the functions collected, keeping the top
+/// `max_unique_functions`. Returns the functions, along with the number of
+/// functions dropped for having too few samples.
+fn top_functions(
+    functions: HashMap<u32, CallTreeFunction>,
+    options: &FunctionsMetricsOptions,
+) -> (Vec<CallTreeFunction>, u64) {
+    let (mut functions_list, filtered_functions) = filter_functions(
+        functions,
+        options.filter_system_frames,
+        options.min_sample_count,
+    );
+
+    // sort the list in descending order, and take the top N results
+    sort_functions(&mut functions_list, options.sort_key);
 
-        for (tid, call_trees_for_thread) in &call_trees {
-            for call_tree in call_trees_for_thread {
-                call_tree.borrow_mut().collect_functions(
-                    &mut functions,
-                    tid,
-                    0,
-                    min_depth,
-                    filter_system_frames,
-                    filter_non_leaf_functions,
-                    generate_stack_fingerprints,
-                    None,
-                );
+    functions_list.truncate(options.max_unique_functions.unwrap_or(functions_list.len()));
+    for function in &mut functions_list {
+        function.truncate_lines(options.max_lines_per_function);
+    }
+    (functions_list, filtered_functions)
+}
+
+/// Aggregates the function metrics of several chunks, building the call
+/// trees of the chunks in parallel, see [`ProfileChunk::extract_functions_metrics`].
+///
+/// Functions are merged by fingerprint (or stack fingerprint) across chunks
+/// before being filtered and sorted, so the minimum sample count applies to
+/// the samples of all the chunks.
+pub(crate) fn aggregate_functions_metrics(
+    chunks: &mut [&mut ProfileChunk],
+    options: &FunctionsMetricsOptions,
+) -> Result<Vec<CallTreeFunction>, CallTreeError> {
+    let merge = |functions: &mut HashMap<u32, CallTreeFunction>, function: CallTreeFunction| {
+        let key = function.stack_fingerprint.unwrap_or(function.fingerprint);
+        match functions.entry(key) {
+            Entry::Occupied(mut entry) => entry.get_mut().merge(function, options.max_examples),
+            Entry::Vacant(entry) => {
+                entry.insert(function);
+            }
+        }
+    };
+    let aggregate = |chunks: &mut [&mut ProfileChunk]| {
+        let mut functions: HashMap<u32, CallTreeFunction> = HashMap::new();
+        for chunk in chunks {
+            let call_trees = chunk.call_trees(None, options.call_tree_options())?;
+            let mut chunk_functions: Vec<CallTreeFunction> =
+                collect_functions(&call_trees, options)
+                    .into_values()
+                    .collect();
+            set_examples_source(chunk.profile.as_ref(), &mut chunk_functions);
+            for function in chunk_functions {
+                merge(&mut functions, function);
             }
         }
+        Ok::<_, CallTreeError>(functions)
+    };
 
-        let mut functions_list: Vec<CallTreeFunction> = Vec::with_capacity(functions.len());
-        for (_fingerprint, function) in functions {
-            if function.sample_count <= 1 || (filter_system_frames && !function.in_app) {
-                // if there's only ever a single sample for this function in
-                // the profile, or the function represents a system frame, and we
-                // decided to ignore system frames, we skip over it to reduce the
-                //amount of data
-                continue;
+    let workers = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunks_per_worker = chunks.len().div_ceil(workers).max(1);
+    let mut functions: HashMap<u32, CallTreeFunction> = HashMap::new();
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = chunks
+            .chunks_mut(chunks_per_worker)
+            .map(|chunks| scope.spawn(|| aggregate(chunks)))
+            .collect();
+        // merged in the order of the chunks, so the result is deterministic
+        for handle in handles {
+            let worker_functions = handle.join().expect("aggregating chunks doesn't panic")?;
+            for function in worker_functions.into_values() {
+                merge(&mut functions, function);
             }
-            functions_list.push(function);
         }
+        Ok::<_, CallTreeError>(())
+    })?;
 
-        // sort the list in descending order, and take the top N results
-        functions_list.sort_by_key(|f| std::cmp::Reverse(f.sum_self_time_ns));
+    let (functions, _) = top_functions(functions, options);
+    Ok(functions)
+}
+
+/// Sums the durations of the garbage collection nodes of each thread, the
+/// nodes nested into another being counted once.
+fn gc_durations_ns(call_trees: &CallTreesStr) -> HashMap<String, u64> {
+    let mut durations = HashMap::new();
+    for (thread_id, call_tree) in call_trees {
+        let mut duration_ns = 0;
+        let mut stack = call_tree.roots().to_vec();
+        while let Some(id) = stack.pop() {
+            let node = &call_tree[id];
+            if node.frame.is_folded_gc_frame() {
+                duration_ns += node.duration_ns;
+            } else {
+                stack.extend(call_tree.children(id));
+            }
+        }
+        if duration_ns > 0 {
+            durations.insert(thread_id.to_string(), duration_ns);
+        }
+    }
+    durations
+}
 
-        functions_list.truncate(max_unique_functions.unwrap_or(functions_list.len()));
-        Ok(functions_list)
+/// Points the examples of the functions to the chunk they were found in, and
+/// names their threads.
+fn set_examples_source(chunk: &dyn ChunkInterface, functions: &mut [CallTreeFunction]) {
+    let thread_names = chunk.thread_names();
+    let main_thread_id = chunk.get_main_thread_id();
+    for function in functions {
+        function.set_threads(&thread_names, main_thread_id.as_deref());
+        function.set_examples_source(None, Some(chunk.get_chunk_id()), |ts| {
+            chunk.absolute_timestamp_ns(ts)
+        });
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
     use crate::{
-        android::chunk::AndroidChunk, profile_chunk::ProfileChunk, sample::v2::SampleChunk,
+        android::chunk::{AndroidChunk, AndroidChunkBuilder},
+        frame::{
+            Data, Frame, FrameCategory, FrameOverrideError, FrameOverrides,
+            GARBAGE_COLLECTION_FUNCTION,
+        },
+        nodetree::{CallTreeFunction, FunctionsMetricsOptions, FunctionsSortKey},
+        profile::ProfileSummary,
+        profile_chunk::{aggregate_functions_metrics, ProfileChunk},
+        sample::v2::{SampleChunk, SampleChunkBuilder},
+        types::{CallTreeOptions, ChunkInterface, PayloadError, TraceContext},
     };
 
     #[test]
@@ -512,6 +1777,29 @@ mod tests {
             .is_some());
     }
 
+    #[test]
+    fn test_from_json_vec_unsupported_version() {
+        // a transaction profile rather than a chunk
+        let profile_json = include_bytes!("../tests/fixtures/sample/v1/valid_python.json");
+        assert!(matches!(
+            ProfileChunk::from_json_vec(profile_json),
+            Err(PayloadError::UnsupportedVersion(version)) if version == "1"
+        ));
+        let profile_json = include_bytes!("../tests/fixtures/sample/v2/valid_python.json");
+        assert!(matches!(
+            ProfileChunk::from_json_vec_and_version(profile_json, "3"),
+            Err(PayloadError::UnsupportedVersion(version)) if version == "3"
+        ));
+        assert!(matches!(
+            ProfileChunk::from_json_vec(b"{\"version\": \"2\"}"),
+            Err(PayloadError::Json(_))
+        ));
+        assert!(matches!(
+            ProfileChunk::decompress(b"not compressed"),
+            Err(PayloadError::Decompression(_))
+        ));
+    }
+
     #[test]
     #[allow(deprecated)]
     fn test_from_json_vec_and_platform() {
@@ -555,6 +1843,98 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_from_json_vec_split() {
+        let payload = include_bytes!("../tests/fixtures/sample/v2/valid_cocoa.json");
+        let chunk = ProfileChunk::from_json_vec(payload).unwrap();
+        let chunks = ProfileChunk::from_json_vec_split(payload, 2, None).unwrap();
+        assert_eq!(chunks.len(), chunk.profile.sample_count().div_ceil(2));
+        assert_eq!(chunks[0].start_timestamp(), chunk.start_timestamp());
+
+        // android trace chunks aren't split
+        let payload = include_bytes!("../tests/fixtures/android/chunk/valid.json");
+        let chunks = ProfileChunk::from_json_vec_split(payload, 2, Some(1)).unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].get_chunk_id(), "7fcbc6ebc1944f44933e373aabe07806");
+    }
+
+    #[test]
+    fn test_call_trees_cached() {
+        let payload = include_bytes!("../tests/fixtures/sample/v2/valid_cocoa.json");
+        let mut chunk = ProfileChunk::from_json_vec(payload).unwrap();
+        let options = CallTreeOptions::default();
+        let call_trees = chunk.call_trees(None, options).unwrap();
+        assert!(Arc::ptr_eq(
+            &call_trees,
+            &chunk.call_trees(None, options).unwrap()
+        ));
+
+        // other options rebuild the call trees
+        let collapsed = CallTreeOptions {
+            collapse_recursion: true,
+            ..Default::default()
+        };
+        assert!(!Arc::ptr_eq(
+            &call_trees,
+            &chunk.call_trees(None, collapsed).unwrap()
+        ));
+
+        // modifying the chunk drops the cached call trees
+        let call_trees = chunk.call_trees(None, options).unwrap();
+        chunk.normalize(None).unwrap();
+        let normalized = chunk.call_trees(None, options).unwrap();
+        assert!(!Arc::ptr_eq(&call_trees, &normalized));
+        assert_eq!(*normalized, chunk.profile_mut().call_trees(None).unwrap());
+    }
+
+    #[test]
+    fn test_clone() {
+        for payload in [
+            &include_bytes!("../tests/fixtures/sample/v2/valid_cocoa.json")[..],
+            &include_bytes!("../tests/fixtures/android/chunk/valid.json")[..],
+        ] {
+            let mut chunk = ProfileChunk::from_json_vec(payload).unwrap();
+            let mut copy = chunk.clone();
+            // the copy shares the chunk until either is modified
+            assert!(Arc::ptr_eq(&copy.profile, &chunk.profile));
+            copy.set_project_id(42);
+            assert!(!Arc::ptr_eq(&copy.profile, &chunk.profile));
+            assert_ne!(chunk.get_project_id(), 42);
+            assert_eq!(copy.get_project_id(), 42);
+            assert_eq!(
+                copy.profile_mut().call_trees(None).unwrap(),
+                chunk.profile_mut().call_trees(None).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_override_frames_generation() {
+        let mut chunk = ProfileChunk::from_json_vec(include_bytes!(
+            "../tests/fixtures/sample/v2/valid_cocoa.json"
+        ))
+        .unwrap();
+        let overrides = [(
+            0,
+            FrameOverrides {
+                in_app: Some(true),
+                ..Default::default()
+            },
+        )];
+        let frames_generation = chunk.frames_generation;
+        // normalized while the overrides were computed
+        chunk.normalize(None).unwrap();
+        assert!(chunk
+            .override_frames(&overrides, frames_generation)
+            .is_err());
+        assert_ne!(chunk.profile.frames()[0].in_app, Some(true));
+
+        chunk
+            .override_frames(&overrides, chunk.frames_generation)
+            .unwrap();
+        assert_eq!(chunk.profile.frames()[0].in_app, Some(true));
+    }
+
     #[test]
     fn test_compress_decompress() {
         struct TestStruct {
@@ -590,34 +1970,676 @@ mod tests {
         for test in test_cases {
             let profile = ProfileChunk::from_json_vec(test.payload).unwrap();
 
-            let compressed_profile_bytes = profile.compress().unwrap();
-            let decompressed_profile =
-                ProfileChunk::decompress(compressed_profile_bytes.as_slice()).unwrap();
+            for (codec, level) in [("lz4", None), ("zstd", None), ("zstd", Some(19))] {
+                let compressed_profile_bytes = profile.compress(codec, level, None).unwrap();
+                let decompressed_profile =
+                    ProfileChunk::decompress(compressed_profile_bytes.as_slice()).unwrap();
 
-            let equals = if let Some(original_android) =
-                profile.profile.as_any().downcast_ref::<AndroidChunk>()
-            {
-                let final_android = decompressed_profile
-                    .profile
-                    .as_any()
-                    .downcast_ref::<AndroidChunk>()
-                    .unwrap();
-                original_android == final_android
-            } else {
-                let original_sample = profile
-                    .profile
-                    .as_any()
-                    .downcast_ref::<SampleChunk>()
-                    .unwrap();
-                let final_sample = decompressed_profile
-                    .profile
-                    .as_any()
-                    .downcast_ref::<SampleChunk>()
-                    .unwrap();
-                original_sample == final_sample
+                let equals = if let Some(original_android) =
+                    profile.profile.as_any().downcast_ref::<AndroidChunk>()
+                {
+                    let final_android = decompressed_profile
+                        .profile
+                        .as_any()
+                        .downcast_ref::<AndroidChunk>()
+                        .unwrap();
+                    original_android == final_android
+                } else {
+                    let original_sample = profile
+                        .profile
+                        .as_any()
+                        .downcast_ref::<SampleChunk>()
+                        .unwrap();
+                    let final_sample = decompressed_profile
+                        .profile
+                        .as_any()
+                        .downcast_ref::<SampleChunk>()
+                        .unwrap();
+                    original_sample == final_sample
+                };
+
+                assert!(equals, "test `{}` failed with {codec}", test.name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_compress_options() {
+        let profile = ProfileChunk::from_json_vec(include_bytes!(
+            "../tests/fixtures/sample/v2/valid_cocoa.json"
+        ))
+        .unwrap();
+        assert!(profile.compress("gzip", None, None).is_err());
+        assert!(profile.compress("lz4", Some(17), None).is_err());
+        assert!(profile.compress("zstd", Some(23), None).is_err());
+
+        let lz4 = profile.compress("lz4", None, None).unwrap();
+        let zstd = profile.compress("zstd", Some(19), Some(lz4.len())).unwrap();
+        assert!(zstd.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]));
+        assert!(zstd.len() < lz4.len());
+    }
+
+    #[test]
+    fn test_process() {
+        let build_chunk = || {
+            let mut builder = SampleChunkBuilder::new("cocoa");
+            let frame = |function: &str, package: &str| Frame {
+                function: Some(function.to_string()),
+                package: Some(package.to_string()),
+                data: Some(Data {
+                    symbolicator_status: Some("symbolicated".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            let app = builder.frame(frame(
+                "-[AppDelegate load]",
+                "/private/var/containers/Bundle/Application/A/MyApp.app/MyApp",
+            ));
+            let system = builder.frame(frame(
+                "CFReadStreamRead",
+                "/System/Library/Frameworks/CoreFoundation.framework/CoreFoundation",
+            ));
+            for i in 0..5 {
+                builder.sample("1", i as f64 * 0.01, &[system, app]);
+            }
+            builder.py_build()
+        };
+        let options = FunctionsMetricsOptions::default()
+            .with_filter_non_leaf_functions(false)
+            .with_filter_system_frames(true);
+        let mut processed = build_chunk().process(&options).unwrap();
+
+        // Without in_app set, every frame counts as an application frame
+        // until the chunk is normalized.
+        let mut chunk = build_chunk();
+        assert_eq!(chunk.extract_functions_metrics(&options).unwrap().len(), 2);
+        chunk.normalize(None).unwrap();
+        let functions = chunk.extract_functions_metrics(&options).unwrap();
+        let key = |f: &CallTreeFunction| (f.fingerprint, f.sum_self_time_ns, f.sample_count);
+        processed.functions.sort_by_key(key);
+        let mut want_functions: Vec<_> = functions.iter().map(key).collect();
+        want_functions.sort();
+
+        assert_eq!(
+            processed.functions.iter().map(key).collect::<Vec<_>>(),
+            want_functions
+        );
+        assert_eq!(processed.stats.samples, 5);
+        assert_eq!(
+            processed.summary,
+            ProfileSummary {
+                duration_ns: chunk.duration_ms() * 1_000_000,
+                thread_count: 1,
+                sample_count: 5,
+                function_count: 1,
+                occurrence_count: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_extract_functions_metrics_stack_fingerprints() {
+        let build_chunk = || {
+            let mut builder = SampleChunkBuilder::new("cocoa");
+            let frames: Vec<i32> = ["main", "run", "work"]
+                .iter()
+                .map(|function| {
+                    builder.frame(Frame {
+                        function: Some(function.to_string()),
+                        package: Some("MyApp".to_string()),
+                        in_app: Some(true),
+                        ..Default::default()
+                    })
+                })
+                .collect();
+            for i in 0..5 {
+                builder.sample("1", i as f64 * 0.01, &[frames[2], frames[1], frames[0]]);
+            }
+            builder.py_build()
+        };
+
+        let functions = build_chunk()
+            .extract_functions_metrics(&FunctionsMetricsOptions::default())
+            .unwrap();
+        assert_eq!(functions.len(), 1);
+        assert_eq!(functions[0].stack_fingerprint, None);
+        assert_eq!(functions[0].parent_fingerprint, None);
+
+        let functions = build_chunk()
+            .extract_functions_metrics(
+                &FunctionsMetricsOptions::default().with_generate_stack_fingerprints(true),
+            )
+            .unwrap();
+        assert_eq!(functions.len(), 1);
+        assert_eq!(functions[0].function, "work");
+        assert_eq!(functions[0].depth, Some(2));
+        assert!(functions[0].stack_fingerprint.is_some());
+        assert!(functions[0].parent_fingerprint.is_some());
+        assert_ne!(
+            functions[0].stack_fingerprint,
+            Some(functions[0].fingerprint)
+        );
+    }
+
+    #[test]
+    fn test_extract_functions_metrics_filename_and_line() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let frame = |path: &str, line: u32| Frame {
+            function: Some("handle".to_string()),
+            module: Some(path.trim_end_matches(".py").replace('/', ".")),
+            path: Some(format!("/app/{path}")),
+            line: Some(line),
+            in_app: Some(true),
+            ..Default::default()
+        };
+        let users = [
+            builder.frame(frame("views/users.py", 12)),
+            builder.frame(frame("views/users.py", 10)),
+        ];
+        let orders = builder.frame(frame("views/orders.py", 30));
+        for (i, &frame) in [users[0], orders, users[1], orders, users[0]]
+            .iter()
+            .enumerate()
+        {
+            builder.sample("1", i as f64 * 0.01, &[frame]);
+        }
+        let mut functions: Vec<(String, String, Option<u32>)> = builder
+            .py_build()
+            .extract_functions_metrics(&FunctionsMetricsOptions::default())
+            .unwrap()
+            .into_iter()
+            .map(|f| (f.package, f.filename, f.line))
+            .collect();
+        functions.sort();
+        assert_eq!(
+            functions,
+            vec![
+                (
+                    "views.orders".to_string(),
+                    "orders.py".to_string(),
+                    Some(30)
+                ),
+                ("views.users".to_string(), "users.py".to_string(), Some(10)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stream_functions_metrics() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let frames: Vec<i32> = ["main", "run", "work", "wait"]
+            .iter()
+            .map(|function| {
+                builder.frame(Frame {
+                    function: Some(function.to_string()),
+                    module: Some("app".to_string()),
+                    in_app: Some(true),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        builder.thread("1", "MainThread").thread("2", "worker");
+        for i in 0..10 {
+            let leaf = frames[2 + i % 2];
+            builder
+                .sample("1", i as f64 * 0.01, &[leaf, frames[1], frames[0]])
+                .sample("2", i as f64 * 0.01, &[frames[2]]);
+        }
+        let payload = builder.build().to_json_vec().unwrap();
+
+        let sort = |mut functions: Vec<CallTreeFunction>| {
+            functions.sort_by_key(|function| function.fingerprint);
+            functions
+        };
+        for active_thread_only in [false, true] {
+            let mut chunk = ProfileChunk::from_json_vec(&payload).unwrap();
+            chunk.normalize(None).unwrap();
+            let want = sort(
+                chunk
+                    .extract_functions_metrics(
+                        &FunctionsMetricsOptions::default()
+                            .with_filter_non_leaf_functions(false)
+                            .with_generate_stack_fingerprints(true)
+                            .with_max_examples(1)
+                            .with_active_thread_only(active_thread_only),
+                    )
+                    .unwrap(),
+            );
+            let got = sort(
+                ProfileChunk::stream_functions_metrics(
+                    &payload,
+                    &FunctionsMetricsOptions::default()
+                        .with_filter_non_leaf_functions(false)
+                        .with_generate_stack_fingerprints(true)
+                        .with_max_examples(1)
+                        .with_active_thread_only(active_thread_only),
+                )
+                .unwrap(),
+            );
+            assert!(!got.is_empty());
+            assert_eq!(got, want);
+        }
+    }
+
+    #[test]
+    fn test_extract_functions_metrics_min_sample_count() {
+        let mut builder = SampleChunkBuilder::new("php");
+        let frame = |function: &str| Frame {
+            function: Some(function.to_string()),
+            module: Some("app".to_string()),
+            in_app: Some(true),
+            ..Default::default()
+        };
+        let (main, query) = (builder.frame(frame("main")), builder.frame(frame("query")));
+        builder
+            .sample("1", 0.0, &[query, main])
+            .sample("1", 0.1, &[main])
+            .sample("1", 0.2, &[main])
+            .sample("1", 0.3, &[main]);
+        let mut chunk = builder.py_build();
+
+        let functions = |chunk: &mut ProfileChunk, min_sample_count| {
+            let mut functions: Vec<String> = chunk
+                .extract_functions_metrics(
+                    &FunctionsMetricsOptions::default().with_min_sample_count(min_sample_count),
+                )
+                .unwrap()
+                .into_iter()
+                .map(|f| f.function)
+                .collect();
+            functions.sort();
+            functions
+        };
+        assert_eq!(chunk.get_filtered_functions(), 0);
+        assert_eq!(functions(&mut chunk, 2), vec!["main"]);
+        assert_eq!(chunk.get_filtered_functions(), 1);
+        assert_eq!(functions(&mut chunk, 1), vec!["main", "query"]);
+        assert_eq!(chunk.get_filtered_functions(), 0);
+    }
+
+    #[test]
+    fn test_aggregate_functions_metrics() {
+        let chunk = |start: f64, queries: usize| {
+            let mut builder = SampleChunkBuilder::new("php");
+            let frame = |function: &str| Frame {
+                function: Some(function.to_string()),
+                module: Some("app".to_string()),
+                in_app: Some(true),
+                ..Default::default()
+            };
+            let (main, query) = (builder.frame(frame("main")), builder.frame(frame("query")));
+            for i in 0..queries {
+                builder.sample("1", start + i as f64 * 0.1, &[query, main]);
+            }
+            builder
+                .sample("1", start + queries as f64 * 0.1, &[main])
+                .sample("1", start + queries as f64 * 0.1 + 0.1, &[main]);
+            builder.py_build()
+        };
+        let (mut first, mut second) = (chunk(0.0, 1), chunk(10.0, 2));
+
+        let functions = aggregate_functions_metrics(
+            &mut [&mut first, &mut second],
+            &FunctionsMetricsOptions::default().with_sort_key(FunctionsSortKey::SampleCount),
+        )
+        .unwrap();
+        let functions: Vec<(&str, u64, u64)> = functions
+            .iter()
+            .map(|f| (f.function.as_str(), f.sample_count, f.sum_self_time_ns))
+            .collect();
+        // the first chunk only has one sample of query, filtered out on its own
+        assert_eq!(
+            functions,
+            vec![("main", 5, 200_000_000), ("query", 3, 300_000_000)]
+        );
+    }
+
+    #[test]
+    fn test_extract_functions_metrics_sort_by() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let frame = |function: &str| Frame {
+            function: Some(function.to_string()),
+            module: Some("app".to_string()),
+            in_app: Some(true),
+            ..Default::default()
+        };
+        let main = builder.frame(frame("main"));
+        let (load, parse, save) = (
+            builder.frame(frame("load")),
+            builder.frame(frame("parse")),
+            builder.frame(frame("save")),
+        );
+        builder
+            .sample("1", 0.0, &[load, main])
+            .sample("1", 0.1, &[load, main])
+            .sample("1", 0.2, &[parse, main])
+            .sample("1", 0.3, &[parse, main])
+            .sample("1", 0.4, &[save, main])
+            .sample("1", 0.5, &[main])
+            .sample("1", 0.6, &[main]);
+        let mut chunk = builder.py_build();
+
+        // through the Python method, parsing the sort key
+        let mut functions = |sort_by| {
+            chunk
+                .py_extract_functions_metrics(
+                    0, false, None, true, false, 0, false, 0, false, false, 1, sort_by,
+                )
+                .map(|functions| {
+                    functions
+                        .into_iter()
+                        .map(|f| (f.function, f.fingerprint))
+                        .collect::<Vec<(String, u32)>>()
+                })
+        };
+        let by_self_time = functions("self_time").unwrap();
+        let mut tied: Vec<&str> = by_self_time[..2].iter().map(|f| f.0.as_str()).collect();
+        tied.sort();
+        assert_eq!(tied, vec!["load", "parse"]);
+        // ties are broken by fingerprint
+        assert!(by_self_time[0].1 < by_self_time[1].1);
+        assert!(by_self_time[2].1 < by_self_time[3].1);
+        assert_eq!(by_self_time, functions("self_time").unwrap());
+
+        assert_eq!(functions("total_time").unwrap()[0].0, "main");
+        assert_eq!(functions("sample_count").unwrap()[0].0, "main");
+        assert!(functions("duration").is_err());
+    }
+
+    #[test]
+    fn test_extract_functions_metrics_active_thread_only() {
+        let build_chunk = |with_thread_metadata: bool| {
+            let mut builder = SampleChunkBuilder::new("python");
+            let frame = |function: &str| Frame {
+                function: Some(function.to_string()),
+                module: Some("app".to_string()),
+                in_app: Some(true),
+                ..Default::default()
             };
+            let (ui, worker) = (builder.frame(frame("ui")), builder.frame(frame("worker")));
+            if with_thread_metadata {
+                builder.thread("1", "MainThread").thread("2", "worker");
+            }
+            for i in 0..5 {
+                builder
+                    .sample("1", i as f64 * 0.01, &[ui])
+                    .sample("2", i as f64 * 0.01, &[worker]);
+            }
+            builder.py_build()
+        };
+
+        let mut functions: Vec<String> = build_chunk(true)
+            .extract_functions_metrics(&FunctionsMetricsOptions::default())
+            .unwrap()
+            .into_iter()
+            .map(|f| f.function)
+            .collect();
+        functions.sort();
+        assert_eq!(functions, vec!["ui", "worker"]);
+
+        // functions know their thread without joining on thread metadata
+        let mut threads: Vec<(String, Option<String>, bool)> = build_chunk(true)
+            .extract_functions_metrics(&FunctionsMetricsOptions::default())
+            .unwrap()
+            .into_iter()
+            .map(|f| (f.function, f.thread_name, f.main_thread))
+            .collect();
+        threads.sort();
+        assert_eq!(
+            threads,
+            vec![
+                ("ui".to_string(), Some("MainThread".to_string()), true),
+                ("worker".to_string(), Some("worker".to_string()), false)
+            ]
+        );
+
+        let functions: Vec<String> = build_chunk(true)
+            .extract_functions_metrics(
+                &FunctionsMetricsOptions::default().with_active_thread_only(true),
+            )
+            .unwrap()
+            .into_iter()
+            .map(|f| f.function)
+            .collect();
+        assert_eq!(functions, vec!["ui"]);
+
+        // without thread metadata, the main thread can't be determined
+        let functions = build_chunk(false)
+            .extract_functions_metrics(
+                &FunctionsMetricsOptions::default().with_active_thread_only(true),
+            )
+            .unwrap();
+        assert!(functions.is_empty());
+    }
+
+    #[test]
+    fn test_gc_durations_ns() {
+        let mut builder = SampleChunkBuilder::new("android");
+        let frame = |function: &str| Frame {
+            function: Some(function.to_string()),
+            ..Default::default()
+        };
+        let run = builder.frame(frame("java.lang.Daemons$GCDaemon.run()"));
+        let run_internal = builder.frame(frame("java.lang.Daemons$GCDaemon.runInternal()"));
+        let heap_tasks = builder.frame(frame("dalvik.system.VMRuntime.runHeapTasks()"));
+        let young_gen = builder.frame(Frame {
+            platform: Some("javascript".to_string()),
+            ..frame("[GC Young Gen]")
+        });
+        let render = builder.frame(Frame {
+            platform: Some("javascript".to_string()),
+            ..frame("render")
+        });
+        builder
+            .sample("1", 10.0, &[run_internal, run])
+            .sample("1", 10.1, &[heap_tasks, run])
+            .sample("1", 10.2, &[run])
+            .sample("2", 10.0, &[young_gen, render])
+            .sample("2", 10.1, &[render])
+            .sample("2", 10.2, &[render]);
+        let mut chunk = builder.py_build();
+        chunk.normalize(None).unwrap();
+
+        let durations = chunk.gc_durations_ns().unwrap();
+        assert_eq!(
+            durations,
+            HashMap::from([
+                ("1".to_string(), 200_000_000),
+                ("2".to_string(), 100_000_000)
+            ])
+        );
+
+        // the GCDaemon frames are folded into a single node
+        let call_trees = chunk.call_trees(None, CallTreeOptions::default()).unwrap();
+        let call_tree = &call_trees["1"];
+        let [root] = call_tree.roots() else {
+            panic!("expected a single root")
+        };
+        assert_eq!(call_tree[*root].name, GARBAGE_COLLECTION_FUNCTION);
+        assert!(call_tree.children(*root).is_empty());
+        assert_eq!(call_tree[*root].frame.category, Some(FrameCategory::Gc));
+    }
+
+    #[test]
+    fn test_trace_context() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let main = builder.frame(Frame {
+            function: Some("main".to_string()),
+            ..Default::default()
+        });
+        builder.sample("1", 10.0, &[main]);
+        let mut chunk = builder.build();
+        let payload = serde_json::to_vec(&chunk).unwrap();
+        assert!(!String::from_utf8_lossy(&payload).contains("trace_context"));
+        let prof = ProfileChunk::from_json_vec(&payload).unwrap();
+        assert_eq!(prof.get_trace_id(), None);
+        assert!(prof.get_segment_ids().is_empty());
+
+        chunk.trace_context = TraceContext {
+            trace_id: Some("4d229f1d3adf4d3cb79d2b5fd6f5b2d2".to_string()),
+            transaction_id: Some("e2a5a8d1b4c34e0b9b3a1d2c3e4f5a6b".to_string()),
+            segment_ids: vec!["a1b2c3d4e5f60718".to_string()],
+        };
+        let prof = ProfileChunk::from_json_vec(&serde_json::to_vec(&chunk).unwrap()).unwrap();
+        assert_eq!(
+            prof.get_trace_id(),
+            Some("4d229f1d3adf4d3cb79d2b5fd6f5b2d2")
+        );
+        assert_eq!(
+            prof.get_transaction_id(),
+            Some("e2a5a8d1b4c34e0b9b3a1d2c3e4f5a6b")
+        );
+        assert_eq!(prof.get_segment_ids(), vec!["a1b2c3d4e5f60718"]);
+    }
+
+    #[test]
+    fn test_metadata_accessors() {
+        let prof = ProfileChunk::from_json_vec(include_bytes!(
+            "../tests/fixtures/sample/v2/valid_cocoa.json"
+        ))
+        .unwrap();
+        assert_eq!(prof.get_chunk_id(), "0432a0a4c25f4697bf9f0a2fcbe6a814");
+        assert_eq!(prof.get_profiler_id(), "4d229f1d3807421ba62a5f8bc295d836");
+        assert_eq!(prof.get_organization_id(), 1);
+        assert_eq!(prof.get_project_id(), 1);
+        assert_eq!(prof.get_received(), 1710805688.237);
+        assert_eq!(prof.get_retention_days(), 90);
+        assert_eq!(prof.get_environment(), None);
+        assert_eq!(prof.get_release(), Some("0.1 (199)"));
+        assert_eq!(prof.get_platform(), "cocoa");
+        assert_eq!(prof.sdk_name(), Some("sentry-cocoa"));
+        assert_eq!(prof.sdk_version(), Some("7.6.1"));
+        assert_eq!(
+            prof.storage_path(),
+            "1/1/4d229f1d3807421ba62a5f8bc295d836/0432a0a4c25f4697bf9f0a2fcbe6a814"
+        );
+
+        let prof = ProfileChunk::from_json_vec(include_bytes!(
+            "../tests/fixtures/android/chunk/valid.json"
+        ))
+        .unwrap();
+        assert_eq!(prof.get_chunk_id(), "7fcbc6ebc1944f44933e373aabe07806");
+        assert_eq!(prof.get_organization_id(), 1);
+        assert_eq!(prof.get_project_id(), 5);
+        assert_eq!(prof.get_received(), 1737465065.0);
+        assert_eq!(prof.get_environment(), Some("debug"));
+        assert_eq!(prof.get_platform(), "android");
+        assert_eq!(prof.sdk_name(), Some("sentry.java.android"));
+        assert_eq!(prof.start_timestamp(), 1737465052.423);
+        assert_eq!(prof.duration_ms(), 10105);
+        assert_eq!(
+            prof.end_timestamp(),
+            prof.start_timestamp() + 10105087000.0 * 1e-9
+        );
+    }
+
+    #[test]
+    fn test_setters() {
+        for fixture in [
+            &include_bytes!("../tests/fixtures/sample/v2/valid_android.json")[..],
+            &include_bytes!("../tests/fixtures/sample/v2/valid_cocoa.json")[..],
+        ] {
+            // chunks may arrive without the fields the ingest consumer stamps
+            let mut payload: serde_json::Value = serde_json::from_slice(fixture).unwrap();
+            let object = payload.as_object_mut().unwrap();
+            for field in [
+                "organization_id",
+                "project_id",
+                "received",
+                "retention_days",
+            ] {
+                object.remove(field);
+            }
+            let mut prof =
+                ProfileChunk::from_json_vec(&serde_json::to_vec(&payload).unwrap()).unwrap();
+            assert_eq!(prof.get_organization_id(), 0);
+
+            prof.set_organization_id(2);
+            prof.set_project_id(3);
+            prof.set_received(1710805690.5);
+            prof.set_retention_days(30);
 
-            assert!(equals, "test `{}` failed", test.name);
+            let prof =
+                ProfileChunk::decompress(&prof.compress("lz4", None, None).unwrap()).unwrap();
+            assert_eq!(prof.get_organization_id(), 2);
+            assert_eq!(prof.get_project_id(), 3);
+            assert_eq!(prof.get_received(), 1710805690.5);
+            assert_eq!(prof.get_retention_days(), 30);
         }
     }
+
+    #[test]
+    fn test_override_frames() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let frame = |function: &str| Frame {
+            function: Some(function.to_string()),
+            module: Some("app".to_string()),
+            ..Default::default()
+        };
+        let (main, work) = (builder.frame(frame("main")), builder.frame(frame("work")));
+        builder.sample("1", 1.0, &[work, main]);
+        let mut chunk = builder.build();
+
+        let overrides = FrameOverrides {
+            module: Some("app.jobs".to_string()),
+            in_app: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(
+            chunk.override_frames(&[(2, overrides.clone())]),
+            Err(FrameOverrideError::InvalidFrameIndex(2))
+        );
+        assert_eq!(
+            chunk.profile.frames[work as usize].module.as_deref(),
+            Some("app")
+        );
+
+        chunk
+            .override_frames(&[(work as usize, overrides)])
+            .unwrap();
+        let frames = chunk.frames();
+        let frames: Vec<(Option<&str>, Option<bool>)> = frames
+            .iter()
+            .map(|f| (f.module.as_deref(), f.in_app))
+            .collect();
+        assert_eq!(
+            frames,
+            vec![(Some("app"), None), (Some("app.jobs"), Some(true))]
+        );
+    }
+
+    #[test]
+    fn test_override_android_frames() {
+        let mut builder = AndroidChunkBuilder::new(1.0);
+        builder.method("com.example.Foo", "bar", "()V", "");
+        builder.method("com.example.Foo", "baz", "()V", "");
+        let mut chunk = builder.build();
+
+        let overrides = |function: Option<&str>| FrameOverrides {
+            function: function.map(str::to_string),
+            path: Some("Foo.kt".to_string()),
+            line: Some(42),
+            in_app: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(
+            chunk.override_frames(&[(0, overrides(None)), (1, overrides(Some("qux")))]),
+            Err(FrameOverrideError::UnsupportedField("function"))
+        );
+        assert_eq!(chunk.frames()[0].path, None);
+
+        chunk.override_frames(&[(0, overrides(None))]).unwrap();
+        let frames = chunk.frames();
+        assert_eq!(
+            frames[0].function.as_deref(),
+            Some("com.example.Foo.bar()V")
+        );
+        assert_eq!(
+            (frames[0].path.as_deref(), frames[0].file.as_deref()),
+            (Some("Foo.kt"), Some("Foo.kt"))
+        );
+        assert_eq!((frames[0].line, frames[0].in_app), (Some(42), Some(false)));
+        assert_eq!(frames[1].in_app, Some(true));
+    }
 }
@@ -1,5 +1,8 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
+pub mod stream;
 pub mod v1;
 pub mod v2;
 
@@ -16,3 +19,17 @@ pub struct ThreadMetadata {
     #[serde(skip_serializing_if = "Option::is_none")]
     priority: Option<i32>,
 }
+
+/// Returns the names of the named threads of the metadata, by thread ID.
+pub(crate) fn thread_names(
+    thread_metadata: Option<&HashMap<String, ThreadMetadata>>,
+) -> HashMap<String, String> {
+    thread_metadata
+        .into_iter()
+        .flatten()
+        .filter_map(|(thread_id, metadata)| {
+            let name = metadata.name.as_deref().filter(|name| !name.is_empty())?;
+            Some((thread_id.clone(), name.to_string()))
+        })
+        .collect()
+}
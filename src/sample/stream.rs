@@ -0,0 +1,474 @@
+//! Streaming parse of sample v2 chunks, building their call trees while
+//! their samples are parsed rather than collecting all of them first, to
+//! cut the peak memory of very large chunks.
+//!
+//! Payloads are read twice: once skipping the samples, to get the frames
+//! and stacks (and the platform and organization normalization depends on)
+//! wherever they are in the payload, then once only looking at the samples.
+//! Each sample is added to the call tree of its thread as soon as the next
+//! sample of the thread is parsed, which requires the samples of each thread
+//! to be sorted by timestamp, as SDKs send them. If they aren't, the chunk is
+//! parsed in full and its samples sorted, as usual.
+
+use std::{borrow::Cow, collections::HashMap, fmt};
+
+use pyo3::PyErr;
+use serde::{
+    de::{DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+use super::{
+    v2::{add_stack, MalformedSamples, RawSample, SampleChunk, SampleData},
+    ThreadMetadata,
+};
+use crate::{
+    exceptions::ParseError,
+    limits::max_stack_depth,
+    measurements::Measurements,
+    nodetree::{CallTree, FrameInterner},
+    types::{CallTreeError, CallTreesStr, ChunkInterface, ClientSDK, DebugMeta, TraceContext},
+};
+
+#[derive(Debug)]
+pub(crate) enum StreamError {
+    Json(serde_json::Error),
+    CallTree(CallTreeError),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::Json(error) => write!(f, "{error}"),
+            StreamError::CallTree(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl From<StreamError> for PyErr {
+    fn from(error: StreamError) -> Self {
+        match error {
+            StreamError::Json(error) => ParseError::new_err(error.to_string()),
+            StreamError::CallTree(error) => error.into(),
+        }
+    }
+}
+
+/// A normalized chunk along with its call trees. The samples of the chunk
+/// are left out when they were streamed.
+pub(crate) struct StreamedChunk {
+    pub chunk: SampleChunk,
+    pub call_trees: CallTreesStr<'static>,
+}
+
+/// A sample chunk without its samples, see [`SampleChunk`].
+#[derive(Deserialize)]
+struct ChunkHeader {
+    chunk_id: String,
+    profiler_id: String,
+    #[serde(default)]
+    debug_meta: DebugMeta,
+    #[serde(default)]
+    trace_context: TraceContext,
+    client_sdk: Option<ClientSDK>,
+    environment: Option<String>,
+    platform: String,
+    release: Option<String>,
+    version: String,
+    profile: ProfileHeader,
+    #[serde(default)]
+    organization_id: u64,
+    #[serde(default)]
+    project_id: u64,
+    #[serde(default)]
+    received: f64,
+    #[serde(default)]
+    retention_days: i32,
+    #[serde(default, with = "crate::measurements::chunk_format")]
+    measurements: Option<Measurements>,
+}
+
+/// The profile of a chunk, without its samples.
+#[derive(Deserialize)]
+struct ProfileHeader {
+    frames: Vec<crate::frame::Frame>,
+    stacks: Vec<Vec<i32>>,
+    #[serde(default)]
+    thread_metadata: Option<HashMap<String, ThreadMetadata>>,
+}
+
+impl From<ChunkHeader> for SampleChunk {
+    fn from(header: ChunkHeader) -> Self {
+        SampleChunk {
+            chunk_id: header.chunk_id,
+            profiler_id: header.profiler_id,
+            debug_meta: header.debug_meta,
+            trace_context: header.trace_context,
+            client_sdk: header.client_sdk,
+            environment: header.environment,
+            platform: header.platform,
+            release: header.release,
+            version: header.version,
+            profile: SampleData {
+                frames: header.profile.frames,
+                samples: vec![],
+                stacks: header.profile.stacks,
+                thread_metadata: header.profile.thread_metadata,
+            },
+            organization_id: header.organization_id,
+            project_id: header.project_id,
+            received: header.received,
+            retention_days: header.retention_days,
+            measurements: header.measurements,
+            truncated_stacks: 0,
+            skipped_idle_threads: 0,
+            malformed_samples: 0,
+        }
+    }
+}
+
+/// A sample waiting for the next one of its thread, which it lasts until.
+struct PendingSample {
+    stack_id: i32,
+    timestamp_ns: u64,
+    weight: u64,
+}
+
+/// Adds the samples to the call trees of their thread as they're parsed.
+struct TreeBuilder<'a> {
+    data: &'a SampleData,
+    frames: FrameInterner<'a>,
+    active_thread_id: Option<&'a str>,
+    threads: HashMap<String, (Option<PendingSample>, CallTree)>,
+    malformed: MalformedSamples,
+    unsorted: bool,
+    max_depth: usize,
+}
+
+impl<'a> TreeBuilder<'a> {
+    fn new(data: &'a SampleData, active_thread_id: Option<&'a str>) -> Self {
+        TreeBuilder {
+            data,
+            frames: FrameInterner::new(&data.frames),
+            active_thread_id,
+            threads: HashMap::new(),
+            malformed: MalformedSamples::default(),
+            unsorted: false,
+            max_depth: max_stack_depth() as usize,
+        }
+    }
+
+    fn add(&mut self, sample: RawSample<'_>) {
+        if self.unsorted {
+            return;
+        }
+        if self
+            .active_thread_id
+            .is_some_and(|active_thread_id| sample.thread_id != active_thread_id)
+        {
+            return;
+        }
+        if !self.threads.contains_key(sample.thread_id.as_ref()) {
+            self.threads
+                .insert(sample.thread_id.to_string(), (None, CallTree::new()));
+        }
+        let Some((pending, tree)) = self.threads.get_mut(sample.thread_id.as_ref()) else {
+            return;
+        };
+        if let Some(previous) = pending.as_ref() {
+            if sample.timestamp < previous.timestamp_ns {
+                self.unsorted = true;
+                return;
+            }
+            if let Some(stack) = self.malformed.check(self.data.stack(previous.stack_id)) {
+                add_stack(
+                    tree,
+                    &mut self.frames,
+                    stack,
+                    previous.timestamp_ns,
+                    sample.timestamp,
+                    previous.weight,
+                    false,
+                    self.max_depth,
+                );
+            }
+        }
+        *pending = Some(PendingSample {
+            stack_id: sample.stack_id,
+            timestamp_ns: sample.timestamp,
+            weight: sample.weight.map_or(1, u64::from),
+        });
+    }
+
+    /// Returns the call trees along with the number of malformed samples
+    /// skipped, or None if the samples weren't sorted.
+    fn finish(self) -> Result<Option<(CallTreesStr<'static>, u64)>, CallTreeError> {
+        if self.unsorted {
+            return Ok(None);
+        }
+        let malformed_samples = self.malformed.finish()?;
+        Ok(Some((
+            self.threads
+                .into_iter()
+                .filter(|(_, (_, tree))| !tree.is_empty())
+                .map(|(thread_id, (_, tree))| (Cow::Owned(thread_id), tree))
+                .collect(),
+            malformed_samples,
+        )))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "snake_case")]
+enum ChunkField {
+    Profile,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "snake_case")]
+enum ProfileField {
+    Samples,
+    #[serde(other)]
+    Other,
+}
+
+/// Visits a chunk, only looking at its samples.
+struct ChunkSeed<'b, 'a>(&'b mut TreeBuilder<'a>);
+
+impl<'de> DeserializeSeed<'de> for ChunkSeed<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ChunkSeed<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a profile chunk")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(field) = map.next_key::<ChunkField>()? {
+            match field {
+                ChunkField::Profile => map.next_value_seed(ProfileSeed(&mut *self.0))?,
+                ChunkField::Other => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Visits the profile of a chunk, only looking at its samples.
+struct ProfileSeed<'b, 'a>(&'b mut TreeBuilder<'a>);
+
+impl<'de> DeserializeSeed<'de> for ProfileSeed<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ProfileSeed<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a profile")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(field) = map.next_key::<ProfileField>()? {
+            match field {
+                ProfileField::Samples => map.next_value_seed(SamplesSeed(&mut *self.0))?,
+                ProfileField::Other => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Visits the samples of a chunk, adding them to the call trees one by one.
+struct SamplesSeed<'b, 'a>(&'b mut TreeBuilder<'a>);
+
+impl<'de> DeserializeSeed<'de> for SamplesSeed<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for SamplesSeed<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of samples")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(sample) = seq.next_element::<RawSample<'de>>()? {
+            self.0.add(sample);
+        }
+        Ok(())
+    }
+}
+
+/// Parses and normalizes a sample v2 chunk, building the call trees of its
+/// threads (of its main thread only if `active_thread_only` is set) while
+/// its samples are parsed.
+pub(crate) fn stream_call_trees(
+    payload: &[u8],
+    active_thread_only: bool,
+) -> Result<StreamedChunk, StreamError> {
+    let header: ChunkHeader = serde_json::from_slice(payload).map_err(StreamError::Json)?;
+    let mut chunk = SampleChunk::from(header);
+    chunk.normalize();
+    let main_thread_id = active_thread_only.then(|| chunk.get_main_thread_id().unwrap_or_default());
+
+    let mut builder = TreeBuilder::new(&chunk.profile, main_thread_id.as_deref());
+    let mut deserializer = serde_json::Deserializer::from_slice(payload);
+    ChunkSeed(&mut builder)
+        .deserialize(&mut deserializer)
+        .map_err(StreamError::Json)?;
+    if let Some((call_trees, malformed_samples)) =
+        builder.finish().map_err(StreamError::CallTree)?
+    {
+        chunk.malformed_samples = malformed_samples;
+        return Ok(StreamedChunk { chunk, call_trees });
+    }
+
+    // the samples aren't sorted, they need to be collected
+    let mut chunk: SampleChunk = serde_json::from_slice(payload).map_err(StreamError::Json)?;
+    chunk.normalize();
+    let call_trees = chunk
+        .call_trees(main_thread_id.as_deref())
+        .map_err(StreamError::CallTree)?
+        .into_iter()
+        .map(|(thread_id, tree)| (Cow::Owned(thread_id.into_owned()), tree))
+        .collect();
+    Ok(StreamedChunk { chunk, call_trees })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stream_call_trees;
+    use crate::{sample::v2::SampleChunk, types::ChunkInterface};
+
+    use pretty_assertions::assert_eq;
+
+    fn buffered_call_trees(payload: &[u8]) -> Vec<(String, crate::nodetree::CallTree)> {
+        let mut chunk: SampleChunk = serde_json::from_slice(payload).unwrap();
+        chunk.normalize();
+        let mut call_trees: Vec<_> = chunk
+            .call_trees(None)
+            .unwrap()
+            .into_iter()
+            .map(|(thread_id, tree)| (thread_id.into_owned(), tree))
+            .collect();
+        call_trees.sort_by(|a, b| a.0.cmp(&b.0));
+        call_trees
+    }
+
+    #[test]
+    fn test_stream_call_trees() {
+        for payload in [
+            &include_bytes!("../../tests/fixtures/sample/v2/valid_cocoa.json")[..],
+            &include_bytes!("../../tests/fixtures/sample/v2/valid_python.json")[..],
+            &include_bytes!("../../tests/fixtures/sample/v2/valid_android.json")[..],
+        ] {
+            let streamed = stream_call_trees(payload, false).unwrap();
+            let mut call_trees: Vec<_> = streamed
+                .call_trees
+                .into_iter()
+                .map(|(thread_id, tree)| (thread_id.into_owned(), tree))
+                .collect();
+            call_trees.sort_by(|a, b| a.0.cmp(&b.0));
+            assert_eq!(call_trees, buffered_call_trees(payload));
+        }
+    }
+
+    #[test]
+    fn test_stream_call_trees_unsorted_samples() {
+        let payload = br#"{
+            "chunk_id": "1", "profiler_id": "1", "platform": "python", "version": "2",
+            "profile": {
+                "samples": [
+                    {"stack_id": 0, "thread_id": "1", "timestamp": 10.02},
+                    {"stack_id": 0, "thread_id": "1", "timestamp": 10.0},
+                    {"stack_id": 1, "thread_id": "1", "timestamp": 10.01}
+                ],
+                "stacks": [[0], [1, 0]],
+                "frames": [{"function": "main"}, {"function": "work"}]
+            }
+        }"#;
+        let streamed = stream_call_trees(payload, false).unwrap();
+        // the chunk was parsed in full
+        assert_eq!(streamed.chunk.profile.samples.len(), 3);
+        let mut call_trees: Vec<_> = streamed
+            .call_trees
+            .into_iter()
+            .map(|(thread_id, tree)| (thread_id.into_owned(), tree))
+            .collect();
+        call_trees.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(call_trees, buffered_call_trees(payload));
+    }
+
+    #[test]
+    fn test_stream_call_trees_invalid_stack() {
+        let payload = br#"{
+            "chunk_id": "1", "profiler_id": "1", "platform": "python", "version": "2",
+            "profile": {
+                "frames": [{"function": "main"}],
+                "stacks": [[0]],
+                "samples": [
+                    {"stack_id": 3, "thread_id": "1", "timestamp": 10.0},
+                    {"stack_id": 0, "thread_id": "1", "timestamp": 10.01}
+                ]
+            }
+        }"#;
+        let err = stream_call_trees(payload, false).err().unwrap();
+        assert_eq!(err.to_string(), "invalid stack id");
+    }
+
+    #[test]
+    fn test_stream_call_trees_malformed_samples() {
+        let samples: Vec<String> = (0..21)
+            .map(|i| {
+                let stack_id = if i == 3 { 3 } else { 0 };
+                format!(
+                    r#"{{"stack_id": {stack_id}, "thread_id": "1", "timestamp": {}}}"#,
+                    10.0 + i as f64 * 0.01
+                )
+            })
+            .collect();
+        let payload = format!(
+            r#"{{
+                "chunk_id": "1", "profiler_id": "1", "platform": "python", "version": "2",
+                "profile": {{
+                    "frames": [{{"function": "main"}}],
+                    "stacks": [[0]],
+                    "samples": [{}]
+                }}
+            }}"#,
+            samples.join(",")
+        );
+        let streamed = stream_call_trees(payload.as_bytes(), false).unwrap();
+        assert_eq!(streamed.chunk.malformed_samples(), 1);
+        assert_eq!(
+            streamed.call_trees.into_iter().collect::<Vec<_>>(),
+            buffered_call_trees(payload.as_bytes())
+                .into_iter()
+                .map(|(thread_id, tree)| (thread_id.into(), tree))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -1,6 +1,10 @@
 use crate::{
-    frame::Frame,
-    nodetree::Node,
+    frame::{
+        in_app_overrides::organization_overrides, skip_scheduler_frames, Frame, FrameCategory,
+    },
+    hashing::FingerprintHasher,
+    measurements::Measurements,
+    nodetree::{FrameInterner, NodeId},
     sample::SampleError,
     types::{
         CallTreeError, CallTreesU64, ClientSDK, DebugMeta, ProfileInterface, Transaction,
@@ -10,9 +14,8 @@ use crate::{
 
 use super::ThreadMetadata;
 use chrono::{DateTime, Utc};
-use fnv_rs::Fnv64;
 use serde::{Deserialize, Serialize};
-use std::{borrow::Cow, cell::RefCell, collections::HashMap, hash::Hasher, rc::Rc};
+use std::{borrow::Cow, collections::HashMap, hash::Hasher};
 
 type FrameTuple<'a> = (usize, &'a Frame);
 
@@ -25,18 +28,6 @@ pub struct OSMetadata {
     build_number: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
-pub struct Measurement {
-    pub unit: String,
-    pub values: Vec<MeasurementValue>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
-pub struct MeasurementValue {
-    pub elapsed_since_start_ns: u64,
-    pub value: f64,
-}
-
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Device {
     architecture: String,
@@ -61,11 +52,23 @@ pub struct QueueMetadata {
     label: String,
 }
 
+impl QueueMetadata {
+    /// Returns whether the queue is the main dispatch queue, only ever
+    /// serviced by the main thread.
+    pub fn is_main_thread(&self) -> bool {
+        self.label == "com.apple.main-thread"
+    }
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Sample {
     pub stack_id: usize,
     pub thread_id: u64,
     pub elapsed_since_start_ns: u64,
+    /// Number of samples the sample stands for, set by profilers sampling
+    /// adaptively. Defaults to 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u32>,
 
     // cocoa only
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -74,6 +77,12 @@ pub struct Sample {
     pub state: Option<String>,
 }
 
+impl Sample {
+    pub fn weight(&self) -> u64 {
+        self.weight.map_or(1, u64::from)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
 pub struct Profile {
     pub frames: Vec<Frame>,
@@ -98,22 +107,30 @@ pub struct SampleProfile {
 
     pub event_id: String,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub measurements: Option<HashMap<String, Measurement>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "crate::measurements::profile_format"
+    )]
+    pub measurements: Option<Measurements>,
 
     pub os: OSMetadata,
 
+    #[serde(default)]
     pub organization_id: u64,
 
     pub platform: String,
 
+    #[serde(default)]
     pub project_id: u64,
 
+    #[serde(default)]
     pub received: i64,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub release: Option<String>,
 
+    #[serde(default)]
     pub retention_days: i32,
 
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -196,6 +213,32 @@ impl SampleProfile {
 }
 
 impl Profile {
+    /// Folds the consecutive garbage collection frames of stacks, normalized
+    /// to the same synthetic frame, so it isn't nested into itself.
+    fn fold_gc_stacks(&mut self) {
+        let is_gc: Vec<bool> = self.frames.iter().map(Frame::is_folded_gc_frame).collect();
+        if !is_gc.contains(&true) {
+            return;
+        }
+        let is_gc_frame = |id: &usize| is_gc.get(*id).copied().unwrap_or(false);
+        for stack in &mut self.stacks {
+            stack.dedup_by(|a, b| is_gc_frame(a) && is_gc_frame(b));
+        }
+    }
+
+    /// Drops the green thread scheduler frames of the stacks running a task,
+    /// attributing their time to the task frames.
+    fn skip_scheduler_stacks(&mut self) {
+        let categories: Vec<Option<FrameCategory>> =
+            self.frames.iter().map(|frame| frame.category).collect();
+        if !categories.contains(&Some(FrameCategory::Scheduler)) {
+            return;
+        }
+        for stack in &mut self.stacks {
+            skip_scheduler_frames(stack, |id| categories.get(id).copied().flatten());
+        }
+    }
+
     fn trim_python_stacks(&mut self) {
         // Find the module frame index in frames
         let module_frame_index = self.frames.iter().position(|f| {
@@ -389,9 +432,12 @@ impl ProfileInterface for SampleProfile {
     }
 
     fn normalize(&mut self) {
+        let overrides = organization_overrides(self.organization_id);
         for frame in &mut self.profile.frames {
-            frame.normalize(&self.platform);
+            frame.normalize_with_overrides(&self.platform, overrides.as_deref(), None);
         }
+        self.profile.fold_gc_stacks();
+        self.profile.skip_scheduler_stacks();
         if self.platform.as_str() == "cocoa" {
             self.trim_cocoa_stacks();
         } else if self.platform.as_str() == "python" {
@@ -418,7 +464,7 @@ impl ProfileInterface for SampleProfile {
         // Cocoa frame detection targets the main thread, which may differ from the
         // active thread, so build call trees for it as well when one is resolved.
         let main_thread_id = self.get_main_thread_id();
-        let mut trees_by_thread_id: HashMap<u64, Vec<Rc<RefCell<Node>>>> = HashMap::new();
+        let mut trees_by_thread_id: CallTreesU64 = HashMap::new();
         let mut samples_by_thread_id: HashMap<u64, Vec<&Sample>> = HashMap::new();
 
         for sample in &self.profile.samples {
@@ -428,7 +474,8 @@ impl ProfileInterface for SampleProfile {
                 .push(sample);
         }
 
-        let mut hasher = Fnv64::default();
+        let mut frames = FrameInterner::new(&self.profile.frames);
+        let mut hasher = FingerprintHasher::default();
 
         for (thread_id, samples) in samples_by_thread_id {
             if thread_id != active_thread_id && Some(thread_id) != main_thread_id {
@@ -458,63 +505,30 @@ impl ProfileInterface for SampleProfile {
                 let next_timestamp = samples[sample_index + 1].elapsed_since_start_ns;
                 let sample_timestamp = sample.elapsed_since_start_ns;
 
-                let mut current: Option<Rc<RefCell<Node>>> = None;
+                if stack.is_empty() {
+                    continue;
+                }
+                let tree = trees_by_thread_id.entry(thread_id).or_default();
+                let mut current: Option<NodeId> = None;
 
                 // Process stack frames from bottom to top
                 for &frame_id in stack.iter().rev() {
-                    let frame = &self.profile.frames[frame_id];
+                    let frame = frames.get(frame_id);
 
                     // Calculate fingerprint
                     frame.write_to_hash(&mut hasher);
                     let fingerprint = hasher.finish();
 
-                    match current {
-                        None => {
-                            let trees = trees_by_thread_id.entry(thread_id).or_default();
-
-                            if let Some(last_tree) = trees.last() {
-                                if last_tree.borrow().fingerprint == fingerprint
-                                    && last_tree.borrow().end_ns == sample_timestamp
-                                {
-                                    last_tree.borrow_mut().update(next_timestamp);
-                                    current = Some(Rc::clone(last_tree));
-                                    continue;
-                                }
-                            }
-
-                            let new_node = Node::from_frame(
-                                frame,
-                                sample_timestamp,
-                                next_timestamp,
-                                fingerprint,
-                            );
-                            trees.push(Rc::clone(&new_node));
-                            current = Some(new_node);
-                        }
-                        Some(node) => {
-                            let i = node.borrow().children.len();
-                            if !node.borrow().children.is_empty()
-                                && node.borrow().children[i - 1].borrow().fingerprint == fingerprint
-                                && node.borrow().children[i - 1].borrow().end_ns == sample_timestamp
-                            {
-                                let last_child = &node.borrow().children[i - 1];
-                                last_child.borrow_mut().update(next_timestamp);
-                                current = Some(Rc::clone(last_child));
-                                continue;
-                            } else {
-                                let new_node = Node::from_frame(
-                                    frame,
-                                    sample_timestamp,
-                                    next_timestamp,
-                                    fingerprint,
-                                );
-                                node.borrow_mut().children.push(Rc::clone(&new_node));
-                                current = Some(new_node);
-                            }
-                        } // end Some
-                    } // end match
+                    current = Some(tree.add_sampled_frame(
+                        current,
+                        frame,
+                        fingerprint,
+                        sample_timestamp,
+                        next_timestamp,
+                        sample.weight(),
+                    ));
                 } // end stack loop
-                hasher = Fnv64::default();
+                hasher = FingerprintHasher::default();
             }
         }
         Ok(trees_by_thread_id)
@@ -545,20 +559,38 @@ impl ProfileInterface for SampleProfile {
             "cocoa" => self
                 .profile
                 .thread_metadata
-                .as_ref()?
-                .iter()
-                .find_map(|(id, meta)| {
-                    let thread_id = id.parse::<u64>().ok()?;
-                    if meta.name.as_deref() == Some("main") {
-                        Some(thread_id)
-                    } else {
-                        None
-                    }
+                .as_ref()
+                .and_then(|thread_metadata| {
+                    thread_metadata.iter().find_map(|(id, meta)| {
+                        let thread_id = id.parse::<u64>().ok()?;
+                        if meta.name.as_deref() == Some("main") {
+                            Some(thread_id)
+                        } else {
+                            None
+                        }
+                    })
+                })
+                // Threads aren't always named, but only the main thread runs
+                // the main dispatch queue.
+                .or_else(|| {
+                    let queue_metadata = self.profile.queue_metadata.as_ref()?;
+                    self.profile.samples.iter().find_map(|sample| {
+                        let queue = queue_metadata.get(sample.queue_address.as_deref()?)?;
+                        queue.is_main_thread().then_some(sample.thread_id)
+                    })
                 }),
             _ => None,
         }
     }
 
+    fn thread_names(&self) -> HashMap<String, String> {
+        super::thread_names(self.profile.thread_metadata.as_ref())
+    }
+
+    fn frames(&self) -> Vec<Cow<'_, Frame>> {
+        self.profile.frames.iter().map(Cow::Borrowed).collect()
+    }
+
     fn get_transaction_tags(&self) -> &HashMap<String, String> {
         &self.transaction_tags
     }
@@ -567,7 +599,7 @@ impl ProfileInterface for SampleProfile {
         &self.debug_meta
     }
 
-    fn get_measurements(&self) -> Option<&HashMap<String, Measurement>> {
+    fn get_measurements(&self) -> Option<&Measurements> {
         self.measurements.as_ref()
     }
 
@@ -579,6 +611,22 @@ impl ProfileInterface for SampleProfile {
         self.event_id = profile_id
     }
 
+    fn set_organization_id(&mut self, organization_id: u64) {
+        self.organization_id = organization_id
+    }
+
+    fn set_project_id(&mut self, project_id: u64) {
+        self.project_id = project_id
+    }
+
+    fn set_received(&mut self, received: i64) {
+        self.received = received
+    }
+
+    fn set_retention_days(&mut self, retention_days: i32) {
+        self.retention_days = retention_days
+    }
+
     fn get_metadata(&self) -> crate::types::Metadata {
         crate::types::Metadata {
             android_api_level: None, // Not available in v1 sample profiles
@@ -607,8 +655,6 @@ impl ProfileInterface for SampleProfile {
 #[cfg(test)]
 mod tests {
 
-    use std::{cell::RefCell, rc::Rc};
-
     use serde_path_to_error::Error;
 
     use crate::{
@@ -1874,7 +1920,7 @@ mod tests {
 
     #[test]
     fn test_call_trees() {
-        use crate::nodetree::Node;
+        use crate::nodetree::{CallTree, NestedNode, Node};
         use pretty_assertions::assert_eq;
         struct TestStruct {
             name: String,
@@ -1932,50 +1978,60 @@ mod tests {
                 }, //end chucnk
                 want: [(
                     1,
-                    vec![Rc::new(RefCell::new(Node {
-                        duration_ns: 40,
-                        end_ns: 50,
-                        fingerprint: 6903369137866438128,
-                        is_application: true,
-                        name: "function0".to_string(),
-                        sample_count: 2,
-                        start_ns: 10,
-                        frame: Frame {
-                            function: Some("function0".to_string()),
+                    CallTree::from_nested(vec![NestedNode(
+                        Node {
+                            duration_ns: 40,
+                            end_ns: 50,
+                            fingerprint: 6903369137866438128,
+                            is_application: true,
+                            name: "function0".to_string(),
+                            sample_count: 2,
+                            start_ns: 10,
+                            frame: Frame {
+                                function: Some("function0".to_string()),
+                                ..Default::default()
+                            }
+                            .into(),
                             ..Default::default()
                         },
-                        children: vec![
-                            Rc::new(RefCell::new(Node {
-                                duration_ns: 40,
-                                end_ns: 50,
-                                start_ns: 10,
-                                fingerprint: 17095743776245828002,
-                                is_application: true,
-                                name: "function1".to_string(),
-                                sample_count: 2,
-                                frame: Frame {
-                                    function: Some("function1".to_string()),
-                                    ..Default::default()
-                                },
-                                children: vec![Rc::new(RefCell::new(Node {
-                                    duration_ns: 10,
+                        vec![
+                            NestedNode(
+                                Node {
+                                    duration_ns: 40,
                                     end_ns: 50,
-                                    fingerprint: 16529420490907277225,
+                                    start_ns: 10,
+                                    fingerprint: 17095743776245828002,
                                     is_application: true,
-                                    name: "function2".to_string(),
-                                    sample_count: 1,
-                                    start_ns: 40,
+                                    name: "function1".to_string(),
+                                    sample_count: 2,
                                     frame: Frame {
-                                        function: Some("function2".to_string()),
+                                        function: Some("function1".to_string()),
                                         ..Default::default()
-                                    },
+                                    }
+                                    .into(),
                                     ..Default::default()
-                                }))],
-                                ..Default::default()
-                            })), // TODO finish
+                                },
+                                vec![NestedNode(
+                                    Node {
+                                        duration_ns: 10,
+                                        end_ns: 50,
+                                        fingerprint: 16529420490907277225,
+                                        is_application: true,
+                                        name: "function2".to_string(),
+                                        sample_count: 1,
+                                        start_ns: 40,
+                                        frame: Frame {
+                                            function: Some("function2".to_string()),
+                                            ..Default::default()
+                                        }
+                                        .into(),
+                                        ..Default::default()
+                                    },
+                                    vec![],
+                                )],
+                            ), // TODO finish
                         ],
-                        ..Default::default()
-                    }))],
+                    )]),
                 )]
                 .iter()
                 .cloned()
@@ -2024,34 +2080,41 @@ mod tests {
                 }, //end chucnk
                 want: [(
                     1,
-                    vec![Rc::new(RefCell::new(Node {
-                        duration_ns: 30,
-                        end_ns: 40,
-                        fingerprint: 6903369137866438128,
-                        is_application: true,
-                        name: "function0".to_string(),
-                        sample_count: 1,
-                        start_ns: 10,
-                        frame: Frame {
-                            function: Some("function0".to_string()),
-                            ..Default::default()
-                        },
-                        children: vec![Rc::new(RefCell::new(Node {
+                    CallTree::from_nested(vec![NestedNode(
+                        Node {
                             duration_ns: 30,
                             end_ns: 40,
-                            fingerprint: 17095743776245828002,
+                            fingerprint: 6903369137866438128,
                             is_application: true,
-                            name: "function1".to_string(),
+                            name: "function0".to_string(),
                             sample_count: 1,
                             start_ns: 10,
                             frame: Frame {
-                                function: Some("function1".to_string()),
+                                function: Some("function0".to_string()),
                                 ..Default::default()
-                            },
+                            }
+                            .into(),
                             ..Default::default()
-                        }))],
-                        ..Default::default()
-                    }))],
+                        },
+                        vec![NestedNode(
+                            Node {
+                                duration_ns: 30,
+                                end_ns: 40,
+                                fingerprint: 17095743776245828002,
+                                is_application: true,
+                                name: "function1".to_string(),
+                                sample_count: 1,
+                                start_ns: 10,
+                                frame: Frame {
+                                    function: Some("function1".to_string()),
+                                    ..Default::default()
+                                }
+                                .into(),
+                                ..Default::default()
+                            },
+                            vec![],
+                        )],
+                    )]),
                 )]
                 .iter()
                 .cloned()
@@ -2106,36 +2169,44 @@ mod tests {
                 }, //end chucnk
                 want: [(
                     1,
-                    vec![
-                        Rc::new(RefCell::new(Node {
-                            duration_ns: 10,
-                            end_ns: 20,
-                            fingerprint: 6903369137866438128,
-                            is_application: true,
-                            name: "function0".to_string(),
-                            sample_count: 1,
-                            start_ns: 10,
-                            frame: Frame {
-                                function: Some("function0".to_string()),
+                    CallTree::from_nested(vec![
+                        NestedNode(
+                            Node {
+                                duration_ns: 10,
+                                end_ns: 20,
+                                fingerprint: 6903369137866438128,
+                                is_application: true,
+                                name: "function0".to_string(),
+                                sample_count: 1,
+                                start_ns: 10,
+                                frame: Frame {
+                                    function: Some("function0".to_string()),
+                                    ..Default::default()
+                                }
+                                .into(),
                                 ..Default::default()
                             },
-                            ..Default::default()
-                        })),
-                        Rc::new(RefCell::new(Node {
-                            duration_ns: 10,
-                            end_ns: 30,
-                            fingerprint: 6903370237378066339,
-                            is_application: true,
-                            name: "function1".to_string(),
-                            sample_count: 1,
-                            start_ns: 20,
-                            frame: Frame {
-                                function: Some("function1".to_string()),
+                            vec![],
+                        ),
+                        NestedNode(
+                            Node {
+                                duration_ns: 10,
+                                end_ns: 30,
+                                fingerprint: 6903370237378066339,
+                                is_application: true,
+                                name: "function1".to_string(),
+                                sample_count: 1,
+                                start_ns: 20,
+                                frame: Frame {
+                                    function: Some("function1".to_string()),
+                                    ..Default::default()
+                                }
+                                .into(),
                                 ..Default::default()
                             },
-                            ..Default::default()
-                        })),
-                    ],
+                            vec![],
+                        ),
+                    ]),
                 )]
                 .iter()
                 .cloned()
@@ -2221,4 +2292,84 @@ mod tests {
             "missing call trees for the main thread"
         );
     }
+
+    #[test]
+    fn test_call_trees_main_thread_from_queue() {
+        use crate::sample::v1::QueueMetadata;
+        use std::collections::HashMap;
+
+        // Cocoa profile without thread metadata, whose thread 2 runs the main queue.
+        let sample = |thread_id: u64, elapsed_since_start_ns: u64| Sample {
+            stack_id: 0,
+            thread_id,
+            elapsed_since_start_ns,
+            queue_address: (thread_id == 2).then(|| "0x1".to_string()),
+            ..Default::default()
+        };
+        let mut profile = SampleProfile {
+            platform: "cocoa".to_string(),
+            transaction: Transaction {
+                active_thread_id: 1,
+                ..Default::default()
+            },
+            profile: Profile {
+                samples: vec![sample(1, 10), sample(1, 20), sample(2, 10), sample(2, 20)],
+                stacks: vec![vec![0]],
+                frames: vec![Frame {
+                    function: Some("function0".to_string()),
+                    ..Default::default()
+                }],
+                queue_metadata: Some(HashMap::from([(
+                    "0x1".to_string(),
+                    QueueMetadata {
+                        label: "com.apple.main-thread".to_string(),
+                    },
+                )])),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(profile.get_main_thread_id(), Some(2));
+        let call_trees = profile.call_trees().unwrap();
+        assert!(call_trees.contains_key(&1) && call_trees.contains_key(&2));
+    }
+
+    #[test]
+    fn test_measurements_round_trip() {
+        let mut payload: serde_json::Value = serde_json::from_slice(include_bytes!(
+            "../../tests/fixtures/sample/v1/valid_cocoa.json"
+        ))
+        .unwrap();
+        let measurements = serde_json::json!({
+            "screen_frame_rates": {"unit": "hz", "values": [{"elapsed_since_start_ns": 1, "value": 60.0}]},
+            "custom_gauge": {"unit": {"name": "widgets"}, "values": []},
+            "cpu_usage": {"unit": null, "values": [{"elapsed_since_start_ns": 2, "value": 12.5}]},
+        });
+        payload["measurements"] = measurements.clone();
+
+        let profile: SampleProfile = serde_json::from_value(payload).unwrap();
+        let parsed = profile.get_measurements().unwrap();
+        assert_eq!(
+            parsed.keys().collect::<Vec<_>>(),
+            vec!["screen_frame_rates", "custom_gauge", "cpu_usage"]
+        );
+        assert_eq!(parsed["custom_gauge"].unit, r#"{"name":"widgets"}"#);
+        assert_eq!(parsed["cpu_usage"].unit, "");
+
+        let round_trip: serde_json::Value =
+            serde_json::from_slice(&profile.to_json_vec().unwrap()).unwrap();
+        assert_eq!(
+            round_trip["measurements"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            vec!["screen_frame_rates", "custom_gauge", "cpu_usage"]
+        );
+        assert_eq!(
+            round_trip["measurements"]["screen_frame_rates"],
+            measurements["screen_frame_rates"]
+        );
+    }
 }
@@ -1,19 +1,36 @@
-use fnv_rs::Fnv64;
+use serde::de::{Deserializer, SeqAccess, Visitor};
 use serde::{Deserialize, Serialize};
 use std::any::Any;
 use std::borrow::Cow;
-use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt;
 use std::hash::Hasher;
-use std::rc::Rc;
+use std::sync::Arc;
 
-use super::{SampleError, ThreadMetadata};
-use crate::frame::Frame;
-use crate::nodetree::Node;
-use crate::types::{CallTreeError, CallTreesStr, ChunkInterface};
-use crate::types::{ClientSDK, DebugMeta};
+use pyo3::{exceptions::PyValueError, pyclass, pymethods, PyResult};
+use uuid::Uuid;
 
-#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+use super::{SampleError, ThreadMetadata};
+use crate::frame::in_app_overrides::organization_overrides;
+use crate::frame::{
+    skip_scheduler_frames, Frame, FrameCategory, FrameOverrideError, FrameOverrides, InAppRules,
+};
+use crate::hashing::FingerprintHasher;
+use crate::limits::{max_malformed_samples_ratio, max_stack_depth};
+use crate::measurements::{Measurement, Measurements};
+use crate::nodetree::{CallTree, FrameInterner, NodeId};
+use crate::profile_chunk::ProfileChunk;
+use crate::timestamp::{nanos_from_seconds, seconds_from_nanos};
+use crate::types::{CallTreeError, CallTreeOptions, CallTreesStr, ChunkInterface};
+use crate::types::{ClientSDK, DebugMeta, TraceContext};
+
+/// Hashed before the frames of truncated stacks.
+const TRUNCATED_STACK_MARKER: &[u8] = b"<truncated>";
+
+/// Names SDKs give to the main thread (android, cocoa and python respectively).
+const MAIN_THREAD_NAMES: &[&str] = &["main", "com.apple.main-thread", "MainThread"];
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Clone)]
 pub struct SampleChunk {
     pub chunk_id: String,
 
@@ -22,6 +39,9 @@ pub struct SampleChunk {
     #[serde(default, skip_serializing_if = "DebugMeta::is_empty")]
     pub debug_meta: DebugMeta,
 
+    #[serde(default, skip_serializing_if = "TraceContext::is_empty")]
+    pub trace_context: TraceContext,
+
     pub client_sdk: Option<ClientSDK>,
 
     pub environment: Option<String>,
@@ -35,22 +55,44 @@ pub struct SampleChunk {
 
     pub profile: SampleData,
 
+    #[serde(default)]
     pub organization_id: u64,
 
+    #[serde(default)]
     pub project_id: u64,
 
+    #[serde(default)]
     pub received: f64,
 
+    #[serde(default)]
     pub retention_days: i32,
 
     // `measurements` contains CPU/memory measurements we do during the capture of the chunk.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub measurements: Option<serde_json::Value>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "crate::measurements::chunk_format"
+    )]
+    pub measurements: Option<Measurements>,
+
+    // Number of stacks truncated at the maximum depth while building the
+    // call trees.
+    #[serde(skip)]
+    pub truncated_stacks: u64,
+
+    // Number of idle threads skipped while building the call trees.
+    #[serde(skip)]
+    pub skipped_idle_threads: u64,
+
+    // Number of malformed samples skipped while building the call trees.
+    #[serde(skip)]
+    pub malformed_samples: u64,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Clone)]
 pub struct SampleData {
     pub frames: Vec<Frame>,
+    #[serde(deserialize_with = "deserialize_samples")]
     pub samples: Vec<Sample>,
     pub stacks: Vec<Vec<i32>>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -100,6 +142,72 @@ impl SampleData {
             .retain(|_| frame_indices.next().unwrap().is_some());
     }
 
+    /// Folds the consecutive garbage collection frames of stacks, normalized
+    /// to the same synthetic frame, so it isn't nested into itself.
+    fn fold_gc_stacks(&mut self) {
+        let is_gc: Vec<bool> = self.frames.iter().map(Frame::is_folded_gc_frame).collect();
+        if !is_gc.contains(&true) {
+            return;
+        }
+        let is_gc_frame = |id: &i32| is_gc.get(*id as usize).copied().unwrap_or(false);
+        for stack in &mut self.stacks {
+            stack.dedup_by(|a, b| is_gc_frame(a) && is_gc_frame(b));
+        }
+    }
+
+    /// Drops the green thread scheduler frames of the stacks running a task,
+    /// attributing their time to the task frames.
+    fn skip_scheduler_stacks(&mut self) {
+        let categories: Vec<Option<FrameCategory>> =
+            self.frames.iter().map(|frame| frame.category).collect();
+        if !categories.contains(&Some(FrameCategory::Scheduler)) {
+            return;
+        }
+        for stack in &mut self.stacks {
+            skip_scheduler_frames(stack, |id| categories.get(id as usize).copied().flatten());
+        }
+    }
+
+    /// Returns the stack of a sample, checking that it and its frames exist.
+    /// Returns whether the samples of a thread, sorted by timestamp, all
+    /// have the same non-empty stack, the thread never doing anything but
+    /// waiting. Threads with a single sample have no call tree to skip, and
+    /// threads with malformed samples are left for them to be counted.
+    fn is_idle_thread(&self, samples: &[&Sample]) -> bool {
+        let [first, rest @ ..] = samples else {
+            return false;
+        };
+        if rest.is_empty() {
+            return false;
+        }
+        let Ok(stack) = self.stack(first.stack_id) else {
+            return false;
+        };
+        if stack.is_empty() {
+            return false;
+        }
+        rest.iter().all(|sample| {
+            sample.stack_id == first.stack_id
+                || self
+                    .stack(sample.stack_id)
+                    .is_ok_and(|other| other == stack)
+        })
+    }
+
+    pub(crate) fn stack(&self, stack_id: i32) -> Result<&[i32], CallTreeError> {
+        let stack = usize::try_from(stack_id)
+            .ok()
+            .and_then(|stack_id| self.stacks.get(stack_id))
+            .ok_or(CallTreeError::Sample(SampleError::InvalidStackId))?;
+        if stack
+            .iter()
+            .any(|&frame_id| self.frames.len() <= frame_id as usize)
+        {
+            return Err(CallTreeError::Sample(SampleError::InvalidFrameId));
+        }
+        Ok(stack)
+    }
+
     fn trim_python_stacks(&mut self) {
         // Find the module frame index in frames
         let module_frame_index = self.frames.iter().position(|f| {
@@ -128,23 +236,577 @@ impl SampleData {
 pub struct Sample {
     #[serde(rename = "stack_id")]
     pub stack_id: i32,
+    /// Shared by all the samples of a thread.
     #[serde(rename = "thread_id")]
-    pub thread_id: String,
-    #[serde(rename = "timestamp")]
-    pub timestamp: f64,
+    pub thread_id: Arc<str>,
+    #[serde(rename = "timestamp", with = "crate::timestamp::seconds")]
+    pub timestamp_ns: u64,
+    /// Number of samples the sample stands for, set by profilers sampling
+    /// adaptively. Defaults to 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u32>,
+}
+
+impl Sample {
+    pub fn weight(&self) -> u64 {
+        self.weight.map_or(1, u64::from)
+    }
+}
+
+/// Adds a stack, sampled from `start_ns` to `end_ns`, to a call tree.
+///
+/// Stacks deeper than `max_depth` are truncated from the root side, keeping
+/// the innermost frames, and the node of their outermost remaining frame is
+/// marked as truncated. Returns whether the stack was truncated.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn add_stack(
+    tree: &mut CallTree,
+    frames: &mut FrameInterner,
+    stack: &[i32],
+    start_ns: u64,
+    end_ns: u64,
+    weight: u64,
+    collapse_recursion: bool,
+    max_depth: usize,
+) -> bool {
+    let mut hasher = FingerprintHasher::default();
+    let mut current: Option<NodeId> = None;
+    let truncated = stack.len() > max_depth;
+    if truncated {
+        // truncated stacks are never merged with stacks starting at the
+        // same frame for real
+        hasher.write(TRUNCATED_STACK_MARKER);
+    }
+    // function fingerprint of the previous frame, and the number of its
+    // recursive calls collapsed so far
+    let mut previous: Option<(u32, u64)> = None;
+
+    // Process stack frames from bottom to top
+    for &frame_id in stack[..stack.len().min(max_depth)].iter().rev() {
+        let frame = frames.get(frame_id as usize);
+
+        if collapse_recursion {
+            let function_fingerprint = frame.fingerprint(None);
+            match (&mut previous, current) {
+                (Some((fingerprint, recursions)), Some(current))
+                    if *fingerprint == function_fingerprint =>
+                {
+                    *recursions += 1;
+                    let node = &mut tree[current];
+                    node.recursion_count = node.recursion_count.max(*recursions);
+                    continue;
+                }
+                _ => previous = Some((function_fingerprint, 0)),
+            }
+        }
+
+        // Calculate fingerprint
+        frame.write_to_hash(&mut hasher);
+        let fingerprint = hasher.finish();
+
+        let id = tree.add_sampled_frame(current, frame, fingerprint, start_ns, end_ns, weight);
+        if truncated && current.is_none() {
+            tree[id].truncated = true;
+        }
+        current = Some(id);
+    }
+    truncated
+}
+
+/// A sample as found in payloads, its thread ID borrowed from the payload
+/// when possible.
+#[derive(Deserialize)]
+pub(crate) struct RawSample<'a> {
+    pub stack_id: i32,
+    #[serde(borrow)]
+    pub thread_id: Cow<'a, str>,
+    #[serde(with = "crate::timestamp::seconds")]
+    pub timestamp: u64,
+    #[serde(default)]
+    pub weight: Option<u32>,
+}
+
+/// Deserializes samples with a single thread ID allocation per thread rather
+/// than per sample, thread IDs being borrowed from the payload until they're
+/// first seen.
+fn deserialize_samples<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Sample>, D::Error> {
+    struct SamplesVisitor;
+
+    impl<'de> Visitor<'de> for SamplesVisitor {
+        type Value = Vec<Sample>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a sequence of samples")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut thread_ids: HashMap<Cow<'de, str>, Arc<str>> = HashMap::new();
+            let mut samples = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+            while let Some(sample) = seq.next_element::<RawSample<'de>>()? {
+                let thread_id = match thread_ids.get(&sample.thread_id) {
+                    Some(thread_id) => thread_id.clone(),
+                    None => {
+                        let thread_id: Arc<str> = sample.thread_id.as_ref().into();
+                        thread_ids.insert(sample.thread_id, thread_id.clone());
+                        thread_id
+                    }
+                };
+                samples.push(Sample {
+                    stack_id: sample.stack_id,
+                    thread_id,
+                    timestamp_ns: sample.timestamp,
+                    weight: sample.weight,
+                });
+            }
+            Ok(samples)
+        }
+    }
+
+    deserializer.deserialize_seq(SamplesVisitor)
+}
+
+impl SampleChunk {
+    /// Splits the chunk into chunks of at most `max_samples` samples and, if
+    /// `max_bytes` is set, of at most `max_bytes` bytes once serialized, so
+    /// oversized chunks can still be ingested.
+    ///
+    /// Samples are split in timestamp order, each chunk getting the frames
+    /// and stacks its samples use and the measurements of its time range, and
+    /// a chunk ID derived from the original one. The last sample of each
+    /// thread of a chunk has no duration, as in any chunk.
+    pub fn split(
+        self,
+        max_samples: usize,
+        max_bytes: Option<usize>,
+    ) -> Result<Vec<SampleChunk>, serde_json::Error> {
+        let parts = self.profile.samples.len().div_ceil(max_samples.max(1));
+        let mut chunks = if parts > 1 {
+            self.split_into(parts)
+        } else {
+            vec![self]
+        };
+        if let Some(max_bytes) = max_bytes {
+            let mut i = 0;
+            while i < chunks.len() {
+                if chunks[i].profile.samples.len() > 1
+                    && serde_json::to_vec(&chunks[i])?.len() > max_bytes
+                {
+                    let halves = chunks.remove(i).split_into(2);
+                    chunks.splice(i..i, halves);
+                } else {
+                    i += 1;
+                }
+            }
+        }
+        if chunks.len() > 1 {
+            for (index, chunk) in chunks.iter_mut().enumerate() {
+                chunk.chunk_id = derived_chunk_id(&chunk.chunk_id, index);
+            }
+        }
+        Ok(chunks)
+    }
+
+    /// Returns a chunk of the samples between `start_ns` (inclusive) and
+    /// `end_ns` (exclusive), with only the frames and stacks they use and the
+    /// measurements of the window, keeping the chunk ID. As in any chunk, the
+    /// last sample of each thread has no duration.
+    pub fn slice(&self, start_ns: u64, end_ns: u64) -> SampleChunk {
+        let samples = self
+            .profile
+            .samples
+            .iter()
+            .filter(|sample| (start_ns..end_ns).contains(&sample.timestamp_ns))
+            .cloned()
+            .collect();
+        self.part(samples, Some(start_ns), Some(end_ns))
+    }
+
+    /// Returns a chunk of the samples of a thread between `start_ns`
+    /// (inclusive) and `end_ns` (exclusive), see [`SampleChunk::slice`]. The
+    /// first sample of the thread at or after `end_ns` is kept to end the
+    /// last sample of the interval, so it lasts as long as in the chunk.
+    pub fn thread_slice(&self, thread_id: &str, start_ns: u64, end_ns: u64) -> SampleChunk {
+        let mut samples: Vec<Sample> = self
+            .profile
+            .samples
+            .iter()
+            .filter(|sample| {
+                sample.thread_id.as_ref() == thread_id && sample.timestamp_ns >= start_ns
+            })
+            .cloned()
+            .collect();
+        samples.sort_by_key(|sample| sample.timestamp_ns);
+        let end = samples.partition_point(|sample| sample.timestamp_ns < end_ns);
+        samples.truncate(end + 1);
+        self.part(samples, Some(start_ns), Some(end_ns))
+    }
+
+    /// Splits the samples into `parts` chunks of about the same number of
+    /// samples, keeping the chunk ID.
+    fn split_into(mut self, parts: usize) -> Vec<SampleChunk> {
+        let mut samples = std::mem::take(&mut self.profile.samples);
+        samples.sort_by_key(|sample| sample.timestamp_ns);
+        let part_size = samples.len().div_ceil(parts).max(1);
+        let mut chunks = Vec::with_capacity(parts);
+        let mut start_ns = None;
+        while !samples.is_empty() {
+            let rest = samples.split_off(part_size.min(samples.len()));
+            let end_ns = rest.first().map(|sample| sample.timestamp_ns);
+            chunks.push(self.part(samples, start_ns, end_ns));
+            start_ns = end_ns;
+            samples = rest;
+        }
+        chunks
+    }
+
+    /// Returns a chunk of the samples, keeping only the frames and stacks
+    /// they use and the measurements between `start_ns` (inclusive) and
+    /// `end_ns` (exclusive), unbounded if `None`.
+    fn part(&self, mut samples: Vec<Sample>, start_ns: Option<u64>, end_ns: Option<u64>) -> Self {
+        let mut stack_ids: HashMap<i32, i32> = HashMap::new();
+        let mut frame_ids: HashMap<i32, i32> = HashMap::new();
+        let mut stacks: Vec<Vec<i32>> = Vec::new();
+        let mut frames: Vec<Frame> = Vec::new();
+        for sample in &mut samples {
+            if let Some(&stack_id) = stack_ids.get(&sample.stack_id) {
+                sample.stack_id = stack_id;
+                continue;
+            }
+            // invalid ids stay invalid, for call_trees to reject
+            let Some(stack) = usize::try_from(sample.stack_id)
+                .ok()
+                .and_then(|stack_id| self.profile.stacks.get(stack_id))
+            else {
+                sample.stack_id = -1;
+                continue;
+            };
+            let stack = stack
+                .iter()
+                .map(|&frame_id| {
+                    let Some(frame) = usize::try_from(frame_id)
+                        .ok()
+                        .and_then(|frame_id| self.profile.frames.get(frame_id))
+                    else {
+                        return -1;
+                    };
+                    *frame_ids.entry(frame_id).or_insert_with(|| {
+                        frames.push(frame.clone());
+                        frames.len() as i32 - 1
+                    })
+                })
+                .collect();
+            stacks.push(stack);
+            let stack_id = stacks.len() as i32 - 1;
+            stack_ids.insert(sample.stack_id, stack_id);
+            sample.stack_id = stack_id;
+        }
+
+        let measurements = self.measurements.as_ref().map(|measurements| {
+            measurements
+                .iter()
+                .map(|(name, measurement)| {
+                    let values = measurement
+                        .values
+                        .iter()
+                        .filter(|value| {
+                            start_ns.is_none_or(|start_ns| value.timestamp_ns >= start_ns)
+                                && end_ns.is_none_or(|end_ns| value.timestamp_ns < end_ns)
+                        })
+                        .copied()
+                        .collect();
+                    let measurement = Measurement {
+                        values,
+                        ..measurement.clone()
+                    };
+                    (name.clone(), measurement)
+                })
+                .collect()
+        });
+
+        SampleChunk {
+            chunk_id: self.chunk_id.clone(),
+            profiler_id: self.profiler_id.clone(),
+            debug_meta: self.debug_meta.clone(),
+            trace_context: self.trace_context.clone(),
+            client_sdk: self.client_sdk.clone(),
+            environment: self.environment.clone(),
+            platform: self.platform.clone(),
+            release: self.release.clone(),
+            version: self.version.clone(),
+            profile: SampleData {
+                frames,
+                samples,
+                stacks,
+                thread_metadata: self.profile.thread_metadata.clone(),
+            },
+            organization_id: self.organization_id,
+            project_id: self.project_id,
+            received: self.received,
+            retention_days: self.retention_days,
+            measurements,
+            truncated_stacks: 0,
+            skipped_idle_threads: 0,
+            malformed_samples: 0,
+        }
+    }
+}
+
+/// Errors merging chunks, see [`SampleChunk::merge`].
+#[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MergeError {
+    NoChunks,
+    /// Android trace chunks have no samples to merge.
+    UnsupportedFormat,
+    ProfilerMismatch,
+    PlatformMismatch,
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeError::NoChunks => write!(f, "no chunks to merge"),
+            MergeError::UnsupportedFormat => {
+                write!(f, "android trace chunks can't be merged")
+            }
+            MergeError::ProfilerMismatch => {
+                write!(f, "chunks were collected by different profilers")
+            }
+            MergeError::PlatformMismatch => write!(f, "chunks have different platforms"),
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+impl SampleChunk {
+    /// Merges chunks collected by the same profiler into a single chunk
+    /// covering the whole session.
+    ///
+    /// Sample timestamps are absolute, so samples are concatenated in
+    /// timestamp order as they are, their stacks and frames being re-indexed
+    /// (and deduplicated) into those of the merged chunk. Thread metadata,
+    /// measurements and debug images are merged too. Other metadata is taken
+    /// from the earliest chunk, and the chunk ID is derived from the IDs of
+    /// the chunks merged.
+    pub fn merge(chunks: &[&SampleChunk]) -> Result<SampleChunk, MergeError> {
+        let mut chunks = chunks.to_vec();
+        chunks.sort_by_key(|chunk| {
+            chunk
+                .profile
+                .samples
+                .iter()
+                .map(|sample| sample.timestamp_ns)
+                .min()
+        });
+        let Some(&first) = chunks.first() else {
+            return Err(MergeError::NoChunks);
+        };
+        if chunks
+            .iter()
+            .any(|chunk| chunk.profiler_id != first.profiler_id)
+        {
+            return Err(MergeError::ProfilerMismatch);
+        }
+        if chunks.iter().any(|chunk| chunk.platform != first.platform) {
+            return Err(MergeError::PlatformMismatch);
+        }
+
+        let mut merged = SampleChunk {
+            chunk_id: format!(
+                "{:x}",
+                md5::compute(
+                    chunks
+                        .iter()
+                        .map(|chunk| chunk.chunk_id.as_str())
+                        .collect::<Vec<_>>()
+                        .join("/")
+                )
+            ),
+            profiler_id: first.profiler_id.clone(),
+            debug_meta: DebugMeta::default(),
+            trace_context: TraceContext {
+                trace_id: chunks
+                    .iter()
+                    .find_map(|chunk| chunk.trace_context.trace_id.clone()),
+                transaction_id: chunks
+                    .iter()
+                    .find_map(|chunk| chunk.trace_context.transaction_id.clone()),
+                segment_ids: chunks
+                    .iter()
+                    .flat_map(|chunk| &chunk.trace_context.segment_ids)
+                    .fold(Vec::new(), |mut segment_ids, segment_id| {
+                        if !segment_ids.contains(segment_id) {
+                            segment_ids.push(segment_id.clone());
+                        }
+                        segment_ids
+                    }),
+            },
+            client_sdk: first.client_sdk.clone(),
+            environment: first.environment.clone(),
+            platform: first.platform.clone(),
+            release: first.release.clone(),
+            version: first.version.clone(),
+            profile: SampleData::default(),
+            organization_id: first.organization_id,
+            project_id: first.project_id,
+            received: first.received,
+            retention_days: first.retention_days,
+            measurements: None,
+            truncated_stacks: 0,
+            skipped_idle_threads: 0,
+            malformed_samples: 0,
+        };
+        // frames aren't hashable, so they're deduplicated by their JSON
+        let mut frame_ids: HashMap<Vec<u8>, i32> = HashMap::new();
+        let mut stack_ids: HashMap<Vec<i32>, i32> = HashMap::new();
+        for chunk in &chunks {
+            let frame_index: Vec<i32> = chunk
+                .profile
+                .frames
+                .iter()
+                .map(|frame| {
+                    let key = serde_json::to_vec(frame).unwrap_or_default();
+                    *frame_ids.entry(key).or_insert_with(|| {
+                        merged.profile.frames.push(frame.clone());
+                        merged.profile.frames.len() as i32 - 1
+                    })
+                })
+                .collect();
+            let stack_index: Vec<i32> = chunk
+                .profile
+                .stacks
+                .iter()
+                .map(|stack| {
+                    // invalid ids stay invalid, for call_trees to reject
+                    let stack: Vec<i32> = stack
+                        .iter()
+                        .map(|&frame_id| {
+                            usize::try_from(frame_id)
+                                .ok()
+                                .and_then(|frame_id| frame_index.get(frame_id).copied())
+                                .unwrap_or(-1)
+                        })
+                        .collect();
+                    *stack_ids.entry(stack.clone()).or_insert_with(|| {
+                        merged.profile.stacks.push(stack);
+                        merged.profile.stacks.len() as i32 - 1
+                    })
+                })
+                .collect();
+            merged
+                .profile
+                .samples
+                .extend(chunk.profile.samples.iter().map(|sample| {
+                    Sample {
+                        stack_id: usize::try_from(sample.stack_id)
+                            .ok()
+                            .and_then(|stack_id| stack_index.get(stack_id).copied())
+                            .unwrap_or(-1),
+                        ..sample.clone()
+                    }
+                }));
+
+            if let Some(thread_metadata) = &chunk.profile.thread_metadata {
+                let merged_metadata = merged.profile.thread_metadata.get_or_insert_default();
+                for (thread_id, metadata) in thread_metadata {
+                    merged_metadata
+                        .entry(thread_id.clone())
+                        .or_insert_with(|| metadata.clone());
+                }
+            }
+            if let Some(measurements) = &chunk.measurements {
+                let merged_measurements = merged.measurements.get_or_insert_default();
+                for (name, measurement) in measurements {
+                    merged_measurements
+                        .entry(name.clone())
+                        .and_modify(|merged| merged.values.extend(&measurement.values))
+                        .or_insert_with(|| measurement.clone());
+                }
+            }
+            if let Some(images) = &chunk.debug_meta.images {
+                let merged_images = merged.debug_meta.images.get_or_insert_default();
+                for image in images {
+                    if !merged_images.contains(image) {
+                        merged_images.push(image.clone());
+                    }
+                }
+            }
+        }
+        merged
+            .profile
+            .samples
+            .sort_by_key(|sample| sample.timestamp_ns);
+        for measurement in merged.measurements.iter_mut().flat_map(|m| m.values_mut()) {
+            measurement.values.sort_by_key(|value| value.timestamp_ns);
+        }
+        Ok(merged)
+    }
+}
+
+/// Counts the malformed samples (referencing a stack or frame which doesn't
+/// exist) skipped while building call trees, which only fails if more than
+/// [`max_malformed_samples_ratio`] of the samples are malformed.
+#[derive(Debug, Default)]
+pub(crate) struct MalformedSamples {
+    samples: u64,
+    malformed_samples: u64,
+    error: Option<CallTreeError>,
+}
+
+impl MalformedSamples {
+    /// Returns the stack of a sample, or None if the sample is malformed
+    /// and should be skipped.
+    pub(crate) fn check<'a>(
+        &mut self,
+        stack: Result<&'a [i32], CallTreeError>,
+    ) -> Option<&'a [i32]> {
+        self.samples += 1;
+        match stack {
+            Ok(stack) => Some(stack),
+            Err(error) => {
+                self.malformed_samples += 1;
+                self.error.get_or_insert(error);
+                None
+            }
+        }
+    }
+
+    /// Returns the number of malformed samples skipped, or the error of the
+    /// first one if there are too many.
+    pub(crate) fn finish(self) -> Result<u64, CallTreeError> {
+        match self.error {
+            Some(error)
+                if self.malformed_samples as f64
+                    > max_malformed_samples_ratio() * self.samples as f64 =>
+            {
+                Err(error)
+            }
+            _ => Ok(self.malformed_samples),
+        }
+    }
+}
+
+/// Derives the ID of the `index`-th chunk split from a chunk, so splitting
+/// the same chunk again yields the same IDs.
+fn derived_chunk_id(chunk_id: &str, index: usize) -> String {
+    format!("{:x}", md5::compute(format!("{chunk_id}/{index}")))
 }
 
 impl ChunkInterface for SampleChunk {
-    fn call_trees(
+    fn call_trees_with_options(
         &mut self,
         active_thread_id: Option<&str>,
+        options: CallTreeOptions,
     ) -> Result<CallTreesStr<'_>, CallTreeError> {
         // Sort samples by timestamp
         self.profile
             .samples
-            .sort_by(|a, b| a.timestamp.partial_cmp(&b.timestamp).unwrap());
+            .sort_by_key(|sample| sample.timestamp_ns);
 
-        let mut trees_by_thread_id: HashMap<Cow<str>, Vec<Rc<RefCell<Node>>>> = HashMap::new();
+        let mut trees_by_thread_id: CallTreesStr = HashMap::new();
         let mut samples_by_thread_id: HashMap<&str, Vec<&Sample>> = HashMap::new();
 
         for sample in &self.profile.samples {
@@ -154,7 +816,11 @@ impl ChunkInterface for SampleChunk {
                 .push(sample);
         }
 
-        let mut hasher = Fnv64::default();
+        let mut frames = FrameInterner::new(&self.profile.frames);
+        let max_depth = max_stack_depth() as usize;
+        let mut truncated_stacks: u64 = 0;
+        let mut skipped_idle_threads: u64 = 0;
+        let mut malformed = MalformedSamples::default();
 
         for (thread_id, samples) in samples_by_thread_id {
             // Skip if we have an active_thread_id and the sample
@@ -164,101 +830,52 @@ impl ChunkInterface for SampleChunk {
                     continue;
                 }
             }
+            if options.skip_idle_threads && self.profile.is_idle_thread(&samples) {
+                skipped_idle_threads += 1;
+                continue;
+            }
 
             // Skip last sample as it's only used for timestamp
             for sample_index in 0..samples.len() - 1 {
                 let sample = &samples[sample_index];
-
-                // Validate stack ID
-                if self.profile.stacks.len() <= (sample.stack_id as usize) {
-                    return Err(CallTreeError::Sample(SampleError::InvalidStackId));
-                }
-
-                let stack = &self.profile.stacks[sample.stack_id as usize];
-
-                // Validate frame IDs
-                for &frame_id in stack.iter().rev() {
-                    if self.profile.frames.len() <= (frame_id as usize) {
-                        return Err(CallTreeError::Sample(SampleError::InvalidFrameId));
-                    }
+                let Some(stack) = malformed.check(self.profile.stack(sample.stack_id)) else {
+                    continue;
+                };
+                if stack.is_empty() {
+                    continue;
                 }
-
-                // Here while we save the nextTimestamp val, we convert it to nanosecond
-                // since the Node struct and utilities use uint64 ns values
-                let next_timestamp = (&samples[sample_index + 1].timestamp * 1e9) as u64;
-                let sample_timestamp = (sample.timestamp * 1e9) as u64;
-
-                let mut current: Option<Rc<RefCell<Node>>> = None;
-
-                // Process stack frames from bottom to top
-                for &frame_id in stack.iter().rev() {
-                    let frame = &self.profile.frames[frame_id as usize];
-
-                    // Calculate fingerprint
-                    frame.write_to_hash(&mut hasher);
-                    let fingerprint = hasher.finish();
-
-                    match current {
-                        None => {
-                            let trees = trees_by_thread_id
-                                .entry(Cow::Borrowed(thread_id))
-                                .or_default();
-
-                            if let Some(last_tree) = trees.last() {
-                                if last_tree.borrow().fingerprint == fingerprint
-                                    && last_tree.borrow().end_ns == sample_timestamp
-                                {
-                                    last_tree.borrow_mut().update(next_timestamp);
-                                    current = Some(Rc::clone(last_tree));
-                                    continue;
-                                }
-                            }
-
-                            let new_node = Node::from_frame(
-                                frame,
-                                sample_timestamp,
-                                next_timestamp,
-                                fingerprint,
-                            );
-                            trees.push(Rc::clone(&new_node));
-                            current = Some(new_node);
-                        }
-                        Some(node) => {
-                            let i = node.borrow().children.len();
-                            if !node.borrow().children.is_empty()
-                                && node.borrow().children[i - 1].borrow().fingerprint == fingerprint
-                                && node.borrow().children[i - 1].borrow().end_ns == sample_timestamp
-                            {
-                                let last_child = &node.borrow().children[i - 1];
-                                last_child.borrow_mut().update(next_timestamp);
-                                current = Some(Rc::clone(last_child));
-                                continue;
-                            } else {
-                                let new_node = Node::from_frame(
-                                    frame,
-                                    sample_timestamp,
-                                    next_timestamp,
-                                    fingerprint,
-                                );
-                                node.borrow_mut().children.push(Rc::clone(&new_node));
-                                current = Some(new_node);
-                            }
-                        } // end Some
-                    } // end match
-                } // end stack loop
-                hasher = Fnv64::default();
+                let tree = trees_by_thread_id
+                    .entry(Cow::Borrowed(thread_id))
+                    .or_default();
+                let truncated = add_stack(
+                    tree,
+                    &mut frames,
+                    stack,
+                    sample.timestamp_ns,
+                    samples[sample_index + 1].timestamp_ns,
+                    sample.weight(),
+                    options.collapse_recursion,
+                    max_depth,
+                );
+                truncated_stacks += truncated as u64;
             }
         }
+        self.truncated_stacks = truncated_stacks;
+        self.skipped_idle_threads = skipped_idle_threads;
+        self.malformed_samples = malformed.finish()?;
         Ok(trees_by_thread_id)
     }
 
-    fn normalize(&mut self) {
+    fn normalize_with_rules(&mut self, in_app_rules: Option<&InAppRules>) {
         if self.platform.as_str() == "android" {
             self.profile.trim_android_stacks();
         }
+        let overrides = organization_overrides(self.organization_id);
         for frame in &mut self.profile.frames {
-            frame.normalize(&self.platform);
+            frame.normalize_with_overrides(&self.platform, overrides.as_deref(), in_app_rules);
         }
+        self.profile.fold_gc_stacks();
+        self.profile.skip_scheduler_stacks();
         if self.platform.as_str() == "python" {
             self.profile.trim_python_stacks();
         }
@@ -268,6 +885,10 @@ impl ChunkInterface for SampleChunk {
         self.environment.as_deref()
     }
 
+    fn get_trace_context(&self) -> &TraceContext {
+        &self.trace_context
+    }
+
     fn get_chunk_id(&self) -> &str {
         &self.chunk_id
     }
@@ -300,6 +921,36 @@ impl ChunkInterface for SampleChunk {
         self.retention_days
     }
 
+    fn get_main_thread_id(&self) -> Option<String> {
+        self.profile
+            .thread_metadata
+            .as_ref()?
+            .iter()
+            .find(|(_, metadata)| {
+                metadata
+                    .name
+                    .as_deref()
+                    .is_some_and(|name| MAIN_THREAD_NAMES.contains(&name))
+            })
+            .map(|(thread_id, _)| thread_id.clone())
+    }
+
+    fn set_organization_id(&mut self, organization_id: u64) {
+        self.organization_id = organization_id
+    }
+
+    fn set_project_id(&mut self, project_id: u64) {
+        self.project_id = project_id
+    }
+
+    fn set_received(&mut self, received: f64) {
+        self.received = received
+    }
+
+    fn set_retention_days(&mut self, retention_days: i32) {
+        self.retention_days = retention_days
+    }
+
     fn duration_ms(&self) -> u64 {
         ((self.end_timestamp() - self.start_timestamp()).round() * 1e3) as u64
     }
@@ -308,7 +959,7 @@ impl ChunkInterface for SampleChunk {
         if self.profile.samples.is_empty() {
             0.0
         } else {
-            self.profile.samples[0].timestamp
+            seconds_from_nanos(self.profile.samples[0].timestamp_ns)
         }
     }
 
@@ -316,7 +967,7 @@ impl ChunkInterface for SampleChunk {
         if self.profile.samples.is_empty() {
             0.0
         } else {
-            self.profile.samples.last().unwrap().timestamp
+            seconds_from_nanos(self.profile.samples.last().unwrap().timestamp_ns)
         }
     }
 
@@ -340,22 +991,340 @@ impl ChunkInterface for SampleChunk {
         serde_json::to_vec(&self)
     }
 
+    fn get_measurements(&self) -> Option<&Measurements> {
+        self.measurements.as_ref()
+    }
+
+    fn sample_count(&self) -> usize {
+        self.profile.samples.len()
+    }
+
+    fn stack_count(&self) -> usize {
+        self.profile.stacks.len()
+    }
+
+    fn malformed_samples(&self) -> u64 {
+        self.malformed_samples
+    }
+
+    fn truncated_stacks(&self) -> u64 {
+        self.truncated_stacks
+    }
+
+    fn skipped_idle_threads(&self) -> u64 {
+        self.skipped_idle_threads
+    }
+
+    fn frames(&self) -> Vec<Cow<'_, Frame>> {
+        self.profile.frames.iter().map(Cow::Borrowed).collect()
+    }
+
+    fn override_frames(
+        &mut self,
+        overrides: &[(usize, FrameOverrides)],
+    ) -> Result<(), FrameOverrideError> {
+        if let Some((index, _)) = overrides
+            .iter()
+            .find(|(index, _)| *index >= self.profile.frames.len())
+        {
+            return Err(FrameOverrideError::InvalidFrameIndex(*index));
+        }
+        for (index, frame_overrides) in overrides {
+            self.profile.frames[*index].apply_overrides(frame_overrides);
+        }
+        Ok(())
+    }
+
+    fn thread_names(&self) -> HashMap<String, String> {
+        super::thread_names(self.profile.thread_metadata.as_ref())
+    }
+
+    fn sample_timestamps(&self) -> HashMap<String, Vec<u64>> {
+        let mut timestamps: HashMap<String, Vec<u64>> = HashMap::new();
+        for sample in &self.profile.samples {
+            let thread_timestamps = timestamps.entry(sample.thread_id.to_string()).or_default();
+            // weighted samples stand for several samples at the same time
+            thread_timestamps.extend((0..sample.weight()).map(|_| sample.timestamp_ns));
+        }
+        timestamps
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    fn clone_box(&self) -> Box<dyn ChunkInterface + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+/// Builds a valid [`SampleChunk`] programmatically (threads, stacks, samples
+/// and measurements), so tests don't need to rely on large JSON fixtures.
+///
+/// Frames and stacks are deduplicated as they're added. Stacks are listed
+/// from the innermost (leaf) frame to the outermost one, like in the sample
+/// format.
+#[pyclass]
+#[derive(Debug, Default)]
+pub struct SampleChunkBuilder {
+    chunk: SampleChunk,
+    stack_ids: HashMap<Vec<i32>, i32>,
+    measurements: Measurements,
+}
+
+impl SampleChunkBuilder {
+    pub fn new(platform: &str) -> Self {
+        SampleChunkBuilder {
+            chunk: SampleChunk {
+                chunk_id: Uuid::new_v4().simple().to_string(),
+                profiler_id: Uuid::new_v4().simple().to_string(),
+                platform: platform.to_string(),
+                version: "2".to_string(),
+                organization_id: 1,
+                project_id: 1,
+                retention_days: 90,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    pub fn chunk_id(&mut self, chunk_id: &str) -> &mut Self {
+        self.chunk.chunk_id = chunk_id.to_string();
+        self
+    }
+
+    pub fn profiler_id(&mut self, profiler_id: &str) -> &mut Self {
+        self.chunk.profiler_id = profiler_id.to_string();
+        self
+    }
+
+    pub fn organization_id(&mut self, organization_id: u64) -> &mut Self {
+        self.chunk.organization_id = organization_id;
+        self
+    }
+
+    pub fn project_id(&mut self, project_id: u64) -> &mut Self {
+        self.chunk.project_id = project_id;
+        self
+    }
+
+    pub fn release(&mut self, release: &str) -> &mut Self {
+        self.chunk.release = Some(release.to_string());
+        self
+    }
+
+    pub fn environment(&mut self, environment: &str) -> &mut Self {
+        self.chunk.environment = Some(environment.to_string());
+        self
+    }
+
+    pub fn thread(&mut self, thread_id: &str, name: &str) -> &mut Self {
+        self.chunk
+            .profile
+            .thread_metadata
+            .get_or_insert_with(HashMap::new)
+            .insert(
+                thread_id.to_string(),
+                ThreadMetadata {
+                    name: Some(name.to_string()),
+                    priority: None,
+                },
+            );
+        self
+    }
+
+    /// Adds a frame and returns its index, reusing an identical frame
+    /// if one was already added.
+    pub fn frame(&mut self, frame: Frame) -> i32 {
+        let frames = &mut self.chunk.profile.frames;
+        match frames.iter().position(|f| *f == frame) {
+            Some(index) => index as i32,
+            None => {
+                frames.push(frame);
+                (frames.len() - 1) as i32
+            }
+        }
+    }
+
+    /// Adds a sample on the given thread, at a timestamp in seconds. Since
+    /// the last sample of a thread only marks the end of the previous one,
+    /// its stack is ignored when computing call trees.
+    ///
+    /// Panics if the timestamp is negative.
+    pub fn sample(&mut self, thread_id: &str, timestamp: f64, stack: &[i32]) -> &mut Self {
+        let next_id = self.chunk.profile.stacks.len() as i32;
+        let stack_id = *self.stack_ids.entry(stack.to_vec()).or_insert(next_id);
+        if stack_id == next_id {
+            self.chunk.profile.stacks.push(stack.to_vec());
+        }
+        self.chunk.profile.samples.push(Sample {
+            stack_id,
+            thread_id: thread_id.into(),
+            timestamp_ns: nanos_from_seconds(timestamp).expect("invalid timestamp"),
+            weight: None,
+        });
+        self
+    }
+
+    /// Adds a measurement, values being `(timestamp, value)` pairs, with
+    /// timestamps in seconds.
+    ///
+    /// Panics if a timestamp is negative.
+    pub fn measurement(&mut self, name: &str, unit: &str, values: &[(f64, f64)]) -> &mut Self {
+        self.measurements
+            .insert(name.to_string(), Measurement::from_seconds(unit, values));
+        self
+    }
+
+    pub fn build(self) -> SampleChunk {
+        let mut chunk = self.chunk;
+        if !self.measurements.is_empty() {
+            chunk.measurements = Some(self.measurements);
+        }
+        if let Some(sample) = chunk.profile.samples.last() {
+            chunk.received = seconds_from_nanos(sample.timestamp_ns);
+        }
+        chunk
+    }
+}
+
+#[pymethods]
+impl SampleChunkBuilder {
+    #[new]
+    #[pyo3(signature = (platform, chunk_id=None, profiler_id=None, organization_id=1, project_id=1, release=None, environment=None))]
+    fn py_new(
+        platform: &str,
+        chunk_id: Option<&str>,
+        profiler_id: Option<&str>,
+        organization_id: u64,
+        project_id: u64,
+        release: Option<&str>,
+        environment: Option<&str>,
+    ) -> Self {
+        let mut builder = SampleChunkBuilder::new(platform);
+        builder
+            .organization_id(organization_id)
+            .project_id(project_id);
+        if let Some(chunk_id) = chunk_id {
+            builder.chunk_id(chunk_id);
+        }
+        if let Some(profiler_id) = profiler_id {
+            builder.profiler_id(profiler_id);
+        }
+        if let Some(release) = release {
+            builder.release(release);
+        }
+        if let Some(environment) = environment {
+            builder.environment(environment);
+        }
+        builder
+    }
+
+    /// Adds a frame to the profile.
+    ///
+    /// Identical frames are only stored once.
+    ///
+    /// Args:
+    ///     function (str): The function name.
+    ///     package (str, optional): The package (or image) the function belongs to.
+    ///     module (str, optional): The module the function belongs to.
+    ///     path (str, optional): The absolute path of the source file.
+    ///     lineno (int, optional): The line number.
+    ///     in_app (bool, optional): Whether the frame belongs to the application.
+    ///
+    /// Returns:
+    ///     int
+    ///         The index of the frame, to be used in stacks.
+    #[pyo3(signature = (function, package=None, module=None, path=None, lineno=None, in_app=None))]
+    pub fn add_frame(
+        &mut self,
+        function: &str,
+        package: Option<String>,
+        module: Option<String>,
+        path: Option<String>,
+        lineno: Option<u32>,
+        in_app: Option<bool>,
+    ) -> i32 {
+        self.frame(Frame {
+            function: Some(function.to_string()),
+            package,
+            module,
+            path,
+            line: lineno,
+            in_app,
+            ..Default::default()
+        })
+    }
+
+    /// Adds a thread name to the thread metadata.
+    ///
+    /// Args:
+    ///     thread_id (str): The thread ID.
+    ///     name (str): The thread name.
+    pub fn add_thread(&mut self, thread_id: &str, name: &str) {
+        self.thread(thread_id, name);
+    }
+
+    /// Adds a sample to the profile.
+    ///
+    /// The last sample of a thread only marks the end of the previous one.
+    ///
+    /// Args:
+    ///     thread_id (str): The thread ID.
+    ///     timestamp (float): The sample timestamp, in seconds.
+    ///     stack (list[int]): Frame indices, from the innermost (leaf) frame
+    ///         to the outermost one.
+    ///
+    /// Raises:
+    ///     ValueError
+    ///         If the timestamp is negative.
+    pub fn add_sample(&mut self, thread_id: &str, timestamp: f64, stack: Vec<i32>) -> PyResult<()> {
+        if nanos_from_seconds(timestamp).is_none() {
+            return Err(PyValueError::new_err(format!(
+                "invalid timestamp {timestamp}"
+            )));
+        }
+        self.sample(thread_id, timestamp, &stack);
+        Ok(())
+    }
+
+    /// Adds a measurement to the profile.
+    ///
+    /// Args:
+    ///     name (str): The measurement name (e.g. "cpu_usage").
+    ///     unit (str): The measurement unit (e.g. "percent").
+    ///     values (list[tuple[float, float]]): ``(timestamp, value)`` pairs.
+    pub fn add_measurement(&mut self, name: &str, unit: &str, values: Vec<(f64, f64)>) {
+        self.measurement(name, unit, &values);
+    }
+
+    /// Builds the profile chunk.
+    ///
+    /// The builder is left empty afterwards.
+    ///
+    /// Returns:
+    ///     :class:`ProfileChunk`
+    ///         The profile chunk built.
+    #[pyo3(name = "build")]
+    pub fn py_build(&mut self) -> ProfileChunk {
+        ProfileChunk::new(Box::new(std::mem::take(self).build()))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{borrow::Cow, cell::RefCell, rc::Rc};
+    use std::borrow::Cow;
+    use std::sync::Arc;
 
     use serde_path_to_error::Error;
 
-    use super::SampleChunk;
+    use super::{MergeError, SampleChunk};
     use crate::{
-        frame::Frame,
-        sample::v2::{Sample, SampleData},
-        types::{CallTreesStr, ChunkInterface},
+        frame::{Frame, FrameCategory},
+        nodetree::{CallTree, NodeId},
+        sample::v2::{Sample, SampleChunkBuilder, SampleData},
+        types::{CallTreeOptions, CallTreesStr, ChunkInterface},
     };
 
     use pretty_assertions::assert_eq;
@@ -386,7 +1355,7 @@ mod tests {
 
     #[test]
     fn test_call_trees() {
-        use crate::nodetree::Node;
+        use crate::nodetree::{CallTree, NestedNode, Node};
         struct TestStruct<'a> {
             name: String,
             chunk: SampleChunk,
@@ -401,18 +1370,21 @@ mod tests {
                         samples: vec![
                             Sample {
                                 stack_id: 0,
-                                thread_id: "1".to_string(),
-                                timestamp: 0.010,
+                                thread_id: "1".into(),
+                                timestamp_ns: 10_000_000,
+                                weight: None,
                             },
                             Sample {
                                 stack_id: 1,
-                                thread_id: "1".to_string(),
-                                timestamp: 0.040,
+                                thread_id: "1".into(),
+                                timestamp_ns: 40_000_000,
+                                weight: None,
                             },
                             Sample {
                                 stack_id: 1,
-                                thread_id: "1".to_string(),
-                                timestamp: 0.050,
+                                thread_id: "1".into(),
+                                timestamp_ns: 50_000_000,
+                                weight: None,
                             },
                         ],
                         stacks: vec![vec![1, 0], vec![2, 1, 0]],
@@ -436,50 +1408,60 @@ mod tests {
                 }, //end chucnk
                 want: [(
                     Cow::Borrowed("1"),
-                    vec![Rc::new(RefCell::new(Node {
-                        duration_ns: 40_000_000,
-                        end_ns: 50_000_000,
-                        fingerprint: 6903369137866438128,
-                        is_application: true,
-                        name: "function0".to_string(),
-                        sample_count: 2,
-                        start_ns: 10_000_000,
-                        frame: Frame {
-                            function: Some("function0".to_string()),
+                    CallTree::from_nested(vec![NestedNode(
+                        Node {
+                            duration_ns: 40_000_000,
+                            end_ns: 50_000_000,
+                            fingerprint: 6903369137866438128,
+                            is_application: true,
+                            name: "function0".to_string(),
+                            sample_count: 2,
+                            start_ns: 10_000_000,
+                            frame: Frame {
+                                function: Some("function0".to_string()),
+                                ..Default::default()
+                            }
+                            .into(),
                             ..Default::default()
                         },
-                        children: vec![
-                            Rc::new(RefCell::new(Node {
-                                duration_ns: 40_000_000,
-                                end_ns: 50_000_000,
-                                start_ns: 10_000_000,
-                                fingerprint: 17095743776245828002,
-                                is_application: true,
-                                name: "function1".to_string(),
-                                sample_count: 2,
-                                frame: Frame {
-                                    function: Some("function1".to_string()),
-                                    ..Default::default()
-                                },
-                                children: vec![Rc::new(RefCell::new(Node {
-                                    duration_ns: 10_000_000,
+                        vec![
+                            NestedNode(
+                                Node {
+                                    duration_ns: 40_000_000,
                                     end_ns: 50_000_000,
-                                    fingerprint: 16529420490907277225,
+                                    start_ns: 10_000_000,
+                                    fingerprint: 17095743776245828002,
                                     is_application: true,
-                                    name: "function2".to_string(),
-                                    sample_count: 1,
-                                    start_ns: 40_000_000,
+                                    name: "function1".to_string(),
+                                    sample_count: 2,
                                     frame: Frame {
-                                        function: Some("function2".to_string()),
+                                        function: Some("function1".to_string()),
                                         ..Default::default()
-                                    },
+                                    }
+                                    .into(),
                                     ..Default::default()
-                                }))],
-                                ..Default::default()
-                            })), // TODO finish
+                                },
+                                vec![NestedNode(
+                                    Node {
+                                        duration_ns: 10_000_000,
+                                        end_ns: 50_000_000,
+                                        fingerprint: 16529420490907277225,
+                                        is_application: true,
+                                        name: "function2".to_string(),
+                                        sample_count: 1,
+                                        start_ns: 40_000_000,
+                                        frame: Frame {
+                                            function: Some("function2".to_string()),
+                                            ..Default::default()
+                                        }
+                                        .into(),
+                                        ..Default::default()
+                                    },
+                                    vec![],
+                                )],
+                            ), // TODO finish
                         ],
-                        ..Default::default()
-                    }))],
+                    )]),
                 )]
                 .iter()
                 .cloned()
@@ -492,13 +1474,15 @@ mod tests {
                         samples: vec![
                             Sample {
                                 stack_id: 0,
-                                thread_id: "1".to_string(),
-                                timestamp: 0.010,
+                                thread_id: "1".into(),
+                                timestamp_ns: 10_000_000,
+                                weight: None,
                             },
                             Sample {
                                 stack_id: 1,
-                                thread_id: "1".to_string(),
-                                timestamp: 0.040,
+                                thread_id: "1".into(),
+                                timestamp_ns: 40_000_000,
+                                weight: None,
                             },
                         ],
                         stacks: vec![vec![1, 0], vec![2, 1, 0]],
@@ -522,34 +1506,41 @@ mod tests {
                 }, //end chucnk
                 want: [(
                     Cow::Borrowed("1"),
-                    vec![Rc::new(RefCell::new(Node {
-                        duration_ns: 30_000_000,
-                        end_ns: 40_000_000,
-                        fingerprint: 6903369137866438128,
-                        is_application: true,
-                        name: "function0".to_string(),
-                        sample_count: 1,
-                        start_ns: 10_000_000,
-                        frame: Frame {
-                            function: Some("function0".to_string()),
-                            ..Default::default()
-                        },
-                        children: vec![Rc::new(RefCell::new(Node {
+                    CallTree::from_nested(vec![NestedNode(
+                        Node {
                             duration_ns: 30_000_000,
                             end_ns: 40_000_000,
-                            fingerprint: 17095743776245828002,
+                            fingerprint: 6903369137866438128,
                             is_application: true,
-                            name: "function1".to_string(),
+                            name: "function0".to_string(),
                             sample_count: 1,
                             start_ns: 10_000_000,
                             frame: Frame {
-                                function: Some("function1".to_string()),
+                                function: Some("function0".to_string()),
                                 ..Default::default()
-                            },
+                            }
+                            .into(),
                             ..Default::default()
-                        }))],
-                        ..Default::default()
-                    }))],
+                        },
+                        vec![NestedNode(
+                            Node {
+                                duration_ns: 30_000_000,
+                                end_ns: 40_000_000,
+                                fingerprint: 17095743776245828002,
+                                is_application: true,
+                                name: "function1".to_string(),
+                                sample_count: 1,
+                                start_ns: 10_000_000,
+                                frame: Frame {
+                                    function: Some("function1".to_string()),
+                                    ..Default::default()
+                                }
+                                .into(),
+                                ..Default::default()
+                            },
+                            vec![],
+                        )],
+                    )]),
                 )]
                 .iter()
                 .cloned()
@@ -562,18 +1553,21 @@ mod tests {
                         samples: vec![
                             Sample {
                                 stack_id: 0,
-                                thread_id: "1".to_string(),
-                                timestamp: 0.010,
+                                thread_id: "1".into(),
+                                timestamp_ns: 10_000_000,
+                                weight: None,
                             },
                             Sample {
                                 stack_id: 1,
-                                thread_id: "1".to_string(),
-                                timestamp: 0.020,
+                                thread_id: "1".into(),
+                                timestamp_ns: 20_000_000,
+                                weight: None,
                             },
                             Sample {
                                 stack_id: 2,
-                                thread_id: "1".to_string(),
-                                timestamp: 0.030,
+                                thread_id: "1".into(),
+                                timestamp_ns: 30_000_000,
+                                weight: None,
                             },
                         ],
                         stacks: vec![vec![0], vec![1], vec![2]],
@@ -597,36 +1591,44 @@ mod tests {
                 }, //end chucnk
                 want: [(
                     Cow::Borrowed("1"),
-                    vec![
-                        Rc::new(RefCell::new(Node {
-                            duration_ns: 10_000_000,
-                            end_ns: 20_000_000,
-                            fingerprint: 6903369137866438128,
-                            is_application: true,
-                            name: "function0".to_string(),
-                            sample_count: 1,
-                            start_ns: 10_000_000,
-                            frame: Frame {
-                                function: Some("function0".to_string()),
+                    CallTree::from_nested(vec![
+                        NestedNode(
+                            Node {
+                                duration_ns: 10_000_000,
+                                end_ns: 20_000_000,
+                                fingerprint: 6903369137866438128,
+                                is_application: true,
+                                name: "function0".to_string(),
+                                sample_count: 1,
+                                start_ns: 10_000_000,
+                                frame: Frame {
+                                    function: Some("function0".to_string()),
+                                    ..Default::default()
+                                }
+                                .into(),
                                 ..Default::default()
                             },
-                            ..Default::default()
-                        })),
-                        Rc::new(RefCell::new(Node {
-                            duration_ns: 10_000_000,
-                            end_ns: 30_000_000,
-                            fingerprint: 6903370237378066339,
-                            is_application: true,
-                            name: "function1".to_string(),
-                            sample_count: 1,
-                            start_ns: 20_000_000,
-                            frame: Frame {
-                                function: Some("function1".to_string()),
+                            vec![],
+                        ),
+                        NestedNode(
+                            Node {
+                                duration_ns: 10_000_000,
+                                end_ns: 30_000_000,
+                                fingerprint: 6903370237378066339,
+                                is_application: true,
+                                name: "function1".to_string(),
+                                sample_count: 1,
+                                start_ns: 20_000_000,
+                                frame: Frame {
+                                    function: Some("function1".to_string()),
+                                    ..Default::default()
+                                }
+                                .into(),
                                 ..Default::default()
                             },
-                            ..Default::default()
-                        })),
-                    ],
+                            vec![],
+                        ),
+                    ]),
                 )]
                 .iter()
                 .cloned()
@@ -690,6 +1692,7 @@ mod tests {
                     profile: SampleData {
                         frames: vec![
                             Frame {
+                                category: Some(FrameCategory::App),
                                 file: Some("<string>".to_string()),
                                 module: Some("__main__".to_string()),
                                 in_app: Some(true),
@@ -700,6 +1703,7 @@ mod tests {
                                 ..Default::default()
                             },
                             Frame {
+                                category: Some(FrameCategory::App),
                                 file: Some("app/util.py".to_string()),
                                 module: Some("app.util".to_string()),
                                 in_app: Some(true),
@@ -737,8 +1741,9 @@ mod tests {
 
         let sample = |stack_id: i32| Sample {
             stack_id,
-            thread_id: "1".to_string(),
-            timestamp: 0.0,
+            thread_id: "1".into(),
+            timestamp_ns: 0,
+            weight: None,
         };
 
         let mut data = SampleData {
@@ -860,4 +1865,678 @@ mod tests {
         assert_eq!(cocoa.profile.frames.len(), 2);
         assert_eq!(cocoa.profile.stacks, vec![vec![0, 1]]);
     }
+
+    #[test]
+    fn test_normalize_react_native_chunk() {
+        let mut builder = SampleChunkBuilder::new("cocoa");
+        let main = builder.frame(Frame {
+            function: Some("main".to_string()),
+            package: Some("/private/var/containers/Bundle/Application/MyApp.app/MyApp".to_string()),
+            in_app: Some(true),
+            ..Default::default()
+        });
+        let render = builder.frame(Frame {
+            function: Some("render".to_string()),
+            path: Some("app:///index.bundle".to_string()),
+            in_app: Some(false),
+            platform: Some("javascript".to_string()),
+            ..Default::default()
+        });
+        let apply = builder.frame(Frame {
+            function: Some("[Native] functionPrototypeApply".to_string()),
+            in_app: Some(true),
+            platform: Some("javascript".to_string()),
+            ..Default::default()
+        });
+        builder
+            .sample("1", 10.00, &[apply, render, main])
+            .sample("1", 10.01, &[main]);
+        let mut chunk = builder.build();
+        chunk.normalize();
+
+        let frames = &chunk.profile.frames;
+        // the chunk platform is propagated to native frames, whose in_app
+        // value set by the SDK is trusted
+        assert_eq!(frames[main as usize].platform.as_deref(), Some("cocoa"));
+        assert_eq!(frames[main as usize].in_app, Some(true));
+        assert!(!frames[main as usize].is_react_native);
+        // the in_app value of javascript frames is recomputed with the
+        // javascript rules
+        assert!(frames[render as usize].is_react_native);
+        assert_eq!(frames[render as usize].in_app, Some(true));
+        assert!(frames[apply as usize].is_react_native);
+        assert_eq!(frames[apply as usize].in_app, Some(false));
+        assert_eq!(
+            frames[apply as usize].category,
+            Some(crate::frame::FrameCategory::System)
+        );
+    }
+
+    #[test]
+    fn test_normalize_skips_scheduler_frames() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let frame = |module: &str, function: &str| Frame {
+            function: Some(function.to_string()),
+            module: Some(module.to_string()),
+            ..Default::default()
+        };
+        let main = builder.frame(frame("app.main", "main"));
+        let run_forever = builder.frame(frame("asyncio.base_events", "run_forever"));
+        let run_once = builder.frame(frame("asyncio.base_events", "_run_once"));
+        let select = builder.frame(frame("selectors", "select"));
+        let handle = builder.frame(frame("app.handlers", "handle"));
+        builder
+            .sample("1", 10.0, &[handle, run_once, run_forever, main])
+            .sample("1", 10.1, &[select, run_once, run_forever, main]);
+        let mut chunk = builder.build();
+        chunk.normalize();
+
+        // the task runs right below main, the parked event loop is kept
+        assert_eq!(
+            chunk.profile.stacks,
+            vec![
+                vec![handle, main],
+                vec![select, run_once, run_forever, main]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_call_trees_group_unsymbolicated_frames() {
+        let mut builder = SampleChunkBuilder::new("cocoa");
+        let frames: Vec<i32> = ["0x100004010", "0x100004020"]
+            .iter()
+            .map(|addr| {
+                builder.frame(Frame {
+                    instruction_addr: Some(addr.to_string()),
+                    package: Some(
+                        "/private/var/containers/Bundle/Application/MyApp.app/MyApp".to_string(),
+                    ),
+                    sym_addr: Some("0x100004000".to_string()),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        builder
+            .sample("1", 10.00, &[frames[0]])
+            .sample("1", 10.01, &[frames[1]])
+            .sample("1", 10.02, &[frames[0]]);
+        let mut chunk = builder.build();
+
+        let call_trees = chunk.call_trees(None).unwrap();
+        let tree = &call_trees["1"];
+        assert_eq!(tree.roots().len(), 1);
+        assert_eq!(tree[tree.roots()[0]].sample_count, 2);
+    }
+
+    #[test]
+    fn test_split() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let frames: Vec<i32> = ["main", "work", "wait"]
+            .iter()
+            .map(|function| {
+                builder.frame(Frame {
+                    function: Some(function.to_string()),
+                    module: Some("app".to_string()),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let cpu_usage: Vec<(f64, f64)> = (0..10).map(|i| (10.0 + i as f64 * 0.1, 5.0)).collect();
+        builder.measurement("cpu_usage", "percent", &cpu_usage);
+        for i in 0..10 {
+            let leaf = if i < 4 { frames[1] } else { frames[2] };
+            builder.sample("1", 10.0 + i as f64 * 0.1, &[leaf, frames[0]]);
+        }
+        let chunk = builder.build();
+        let chunk_id = chunk.chunk_id.clone();
+        let payload = serde_json::to_vec(&chunk).unwrap();
+
+        let chunks = chunk.split(4, None).unwrap();
+        let sample_counts: Vec<usize> = chunks.iter().map(|c| c.profile.samples.len()).collect();
+        assert_eq!(sample_counts, vec![4, 4, 2]);
+        let measurement_counts: Vec<usize> = chunks
+            .iter()
+            .map(|c| c.measurements.as_ref().unwrap()["cpu_usage"].values.len())
+            .collect();
+        assert_eq!(measurement_counts, vec![4, 4, 2]);
+        assert_eq!(chunks[1].start_timestamp(), 10.4);
+        // only the frames and stacks used by the samples are kept
+        let functions: Vec<Option<&str>> = chunks[1]
+            .profile
+            .frames
+            .iter()
+            .map(|f| f.function.as_deref())
+            .collect();
+        assert_eq!(functions, vec![Some("wait"), Some("main")]);
+        assert_eq!(chunks[1].profile.stacks, vec![vec![0, 1]]);
+
+        let chunk_ids: Vec<&str> = chunks.iter().map(|c| c.chunk_id.as_str()).collect();
+        assert!(!chunk_ids.contains(&chunk_id.as_str()));
+        assert_eq!(chunk_ids[0].len(), 32);
+        assert_ne!(chunk_ids[0], chunk_ids[1]);
+        let resplit = serde_json::from_slice::<SampleChunk>(&payload)
+            .unwrap()
+            .split(4, None)
+            .unwrap();
+        assert_eq!(resplit, chunks);
+
+        // chunks within the limits are left as is
+        let chunk: SampleChunk = serde_json::from_slice(&payload).unwrap();
+        let chunks = chunk.split(10, Some(payload.len())).unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].chunk_id, chunk_id);
+
+        let chunk: SampleChunk = serde_json::from_slice(&payload).unwrap();
+        let max_bytes = payload.len() / 2;
+        let chunks = chunk.split(10, Some(max_bytes)).unwrap();
+        assert!(chunks.len() > 1);
+        assert!(chunks
+            .iter()
+            .all(|c| serde_json::to_vec(c).unwrap().len() <= max_bytes));
+        let sample_count: usize = chunks.iter().map(|c| c.profile.samples.len()).sum();
+        assert_eq!(sample_count, 10);
+    }
+
+    #[test]
+    fn test_slice() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let frames: Vec<i32> = ["main", "work", "wait"]
+            .iter()
+            .map(|function| {
+                builder.frame(Frame {
+                    function: Some(function.to_string()),
+                    module: Some("app".to_string()),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let cpu_usage: Vec<(f64, f64)> = (0..10).map(|i| (10.0 + i as f64 * 0.1, 5.0)).collect();
+        builder.measurement("cpu_usage", "percent", &cpu_usage);
+        for i in 0..10 {
+            let leaf = if i < 4 { frames[1] } else { frames[2] };
+            builder.sample("1", 10.0 + i as f64 * 0.1, &[leaf, frames[0]]);
+        }
+        let chunk = builder.build();
+
+        let slice = chunk.slice(10_500_000_000, 10_800_000_000);
+        let timestamps: Vec<u64> = slice
+            .profile
+            .samples
+            .iter()
+            .map(|sample| sample.timestamp_ns)
+            .collect();
+        assert_eq!(
+            timestamps,
+            vec![10_500_000_000, 10_600_000_000, 10_700_000_000]
+        );
+        assert_eq!(
+            slice.measurements.as_ref().unwrap()["cpu_usage"]
+                .values
+                .len(),
+            3
+        );
+        // only the frames and stacks used by the samples are kept
+        let functions: Vec<Option<&str>> = slice
+            .profile
+            .frames
+            .iter()
+            .map(|f| f.function.as_deref())
+            .collect();
+        assert_eq!(functions, vec![Some("wait"), Some("main")]);
+        assert_eq!(slice.profile.stacks, vec![vec![0, 1]]);
+        assert_eq!(slice.chunk_id, chunk.chunk_id);
+
+        assert!(chunk
+            .slice(20_000_000_000, 30_000_000_000)
+            .profile
+            .samples
+            .is_empty());
+    }
+
+    #[test]
+    fn test_thread_slice() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let main = builder.frame(Frame {
+            function: Some("main".to_string()),
+            ..Default::default()
+        });
+        for i in 0..5 {
+            builder.sample("1", 10.0 + i as f64 * 0.1, &[main]);
+            builder.sample("2", 10.0 + i as f64 * 0.1, &[main]);
+        }
+        let chunk = builder.build();
+
+        let slice = chunk.thread_slice("1", 10_050_000_000, 10_250_000_000);
+        let samples: Vec<(&str, u64)> = slice
+            .profile
+            .samples
+            .iter()
+            .map(|sample| (sample.thread_id.as_ref(), sample.timestamp_ns))
+            .collect();
+        // the sample at 10.3 ends the one at 10.2
+        assert_eq!(
+            samples,
+            vec![
+                ("1", 10_100_000_000),
+                ("1", 10_200_000_000),
+                ("1", 10_300_000_000)
+            ]
+        );
+        assert!(chunk
+            .thread_slice("3", 10_000_000_000, 11_000_000_000)
+            .profile
+            .samples
+            .is_empty());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let frames: Vec<i32> = ["main", "work", "wait"]
+            .iter()
+            .map(|function| {
+                builder.frame(Frame {
+                    function: Some(function.to_string()),
+                    module: Some("app".to_string()),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let cpu_usage: Vec<(f64, f64)> = (0..10).map(|i| (10.0 + i as f64 * 0.1, 5.0)).collect();
+        builder
+            .thread("1", "MainThread")
+            .measurement("cpu_usage", "percent", &cpu_usage);
+        for i in 0..10 {
+            let leaf = if i < 4 { frames[1] } else { frames[2] };
+            builder.sample("1", 10.0 + i as f64 * 0.1, &[leaf, frames[0]]);
+        }
+        let mut chunk = builder.build();
+
+        let chunks = chunk.clone().split(4, None).unwrap();
+        let mut merged = SampleChunk::merge(&chunks.iter().rev().collect::<Vec<_>>()).unwrap();
+        assert_eq!(merged.profile.frames.len(), 3);
+        assert_eq!(merged.profile.stacks.len(), 2);
+        assert_eq!(merged.profile.samples.len(), 10);
+        assert_eq!(merged.start_timestamp(), 10.0);
+        assert_eq!(
+            merged.measurements.as_ref().unwrap()["cpu_usage"],
+            chunk.measurements.as_ref().unwrap()["cpu_usage"]
+        );
+        assert_eq!(
+            merged.profile.thread_metadata,
+            chunk.profile.thread_metadata
+        );
+        // merging the same chunks again yields the same chunk ID
+        assert_eq!(
+            merged.chunk_id,
+            SampleChunk::merge(&chunks.iter().collect::<Vec<_>>())
+                .unwrap()
+                .chunk_id
+        );
+        assert_eq!(
+            merged.call_trees(None).unwrap(),
+            chunk.call_trees(None).unwrap()
+        );
+
+        assert_eq!(SampleChunk::merge(&[]), Err(MergeError::NoChunks));
+        let mut other = chunks[0].clone();
+        other.profiler_id = "other".to_string();
+        assert_eq!(
+            SampleChunk::merge(&[&chunks[1], &other]),
+            Err(MergeError::ProfilerMismatch)
+        );
+    }
+
+    #[test]
+    fn test_split_invalid_stack_id() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let main = builder.frame(Frame {
+            function: Some("main".to_string()),
+            ..Default::default()
+        });
+        builder
+            .sample("1", 1.0, &[main])
+            .sample("1", 1.1, &[main])
+            .sample("1", 1.2, &[main]);
+        let mut chunk = builder.build();
+        chunk.profile.samples[2].stack_id = 1;
+
+        let mut chunks = chunk.split(2, None).unwrap();
+        assert!(chunks[0].call_trees(None).is_ok());
+        assert_eq!(chunks[1].profile.samples[0].stack_id, -1);
+    }
+
+    #[test]
+    fn test_sample_chunk_builder() {
+        let mut builder = SampleChunkBuilder::new("cocoa");
+        let main = builder.frame(Frame {
+            function: Some("main".to_string()),
+            package: Some("MyApp".to_string()),
+            in_app: Some(true),
+            ..Default::default()
+        });
+        let work = builder.frame(Frame {
+            function: Some("work".to_string()),
+            package: Some("MyApp".to_string()),
+            in_app: Some(true),
+            ..Default::default()
+        });
+        builder
+            .thread("1", "main")
+            .sample("1", 10.00, &[work, main])
+            .sample("1", 10.01, &[work, main])
+            .sample("1", 10.02, &[main])
+            .sample("1", 10.03, &[main])
+            .measurement("cpu_usage", "percent", &[(10.0, 12.5)]);
+        let mut chunk = builder.build();
+
+        assert_eq!(chunk.profile.frames.len(), 2);
+        assert_eq!(chunk.profile.stacks, vec![vec![work, main], vec![main]]);
+        assert_eq!(chunk.start_timestamp(), 10.00);
+        assert_eq!(chunk.end_timestamp(), 10.03);
+
+        let call_trees = chunk.call_trees(None).unwrap();
+        let tree = &call_trees["1"];
+        let root = tree.roots()[0];
+        assert_eq!(tree[root].name, "main");
+        assert_eq!(tree[root].sample_count, 3);
+        assert_eq!(tree.children(root).len(), 1);
+        assert_eq!(tree[tree.children(root)[0]].name, "work");
+        assert_eq!(tree[tree.children(root)[0]].sample_count, 2);
+    }
+
+    #[test]
+    fn test_measurements_round_trip() {
+        let mut payload: serde_json::Value = serde_json::from_slice(include_bytes!(
+            "../../tests/fixtures/sample/v2/valid_cocoa.json"
+        ))
+        .unwrap();
+        payload["measurements"] = serde_json::json!({
+            "memory_footprint": {"unit": "byte", "values": []},
+            "custom_gauge": {"unit": "widgets", "values": [], "extra": true},
+            "cpu_usage": {"unit": "percent", "values": []},
+        });
+
+        let chunk: SampleChunk = serde_json::from_value(payload.clone()).unwrap();
+        let round_trip: serde_json::Value =
+            serde_json::from_slice(&chunk.to_json_vec().unwrap()).unwrap();
+        assert_eq!(
+            serde_json::to_string(&round_trip["measurements"]).unwrap(),
+            serde_json::to_string(&payload["measurements"]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_samples_share_thread_ids() {
+        let payload = r#"[
+            {"stack_id": 0, "thread_id": "1", "timestamp": 1.0},
+            {"stack_id": 1, "thread_id": "2", "timestamp": 1.0},
+            {"stack_id": 0, "thread_id": "1", "timestamp": 1.1},
+            {"stack_id": 0, "thread_id": "\u0031", "timestamp": 1.2}
+        ]"#;
+        let samples =
+            super::deserialize_samples(&mut serde_json::Deserializer::from_str(payload)).unwrap();
+        let thread_ids: Vec<&str> = samples.iter().map(|s| s.thread_id.as_ref()).collect();
+        assert_eq!(thread_ids, vec!["1", "2", "1", "1"]);
+        assert!(Arc::ptr_eq(&samples[0].thread_id, &samples[2].thread_id));
+        // escaped strings can't be borrowed, but are shared all the same
+        assert!(Arc::ptr_eq(&samples[0].thread_id, &samples[3].thread_id));
+    }
+
+    #[test]
+    fn test_sample_timestamps() {
+        let payload = r#"[
+            {"stack_id": 0, "thread_id": "1", "timestamp": 1737465052.42},
+            {"stack_id": 0, "thread_id": "1", "timestamp": 1737465052.43},
+            {"stack_id": 0, "thread_id": "1", "timestamp": 1737465053}
+        ]"#;
+        let samples =
+            super::deserialize_samples(&mut serde_json::Deserializer::from_str(payload)).unwrap();
+        let timestamps: Vec<u64> = samples.iter().map(|s| s.timestamp_ns).collect();
+        assert_eq!(
+            timestamps,
+            vec![
+                1_737_465_052_420_000_000,
+                1_737_465_052_430_000_000,
+                1_737_465_053_000_000_000
+            ]
+        );
+        assert_eq!(
+            serde_json::to_string(&samples[1]).unwrap(),
+            r#"{"stack_id":0,"thread_id":"1","timestamp":1737465052.43}"#
+        );
+
+        let err = super::deserialize_samples(&mut serde_json::Deserializer::from_str(
+            r#"[{"stack_id": 0, "thread_id": "1", "timestamp": -1.0}]"#,
+        ))
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid value: floating point `-1.0`, expected a positive timestamp in seconds at line 1 column 53"
+        );
+    }
+
+    #[test]
+    fn test_weighted_samples() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let main = builder.frame(Frame {
+            function: Some("main".to_string()),
+            module: Some("app".to_string()),
+            ..Default::default()
+        });
+        let work = builder.frame(Frame {
+            function: Some("work".to_string()),
+            module: Some("app".to_string()),
+            ..Default::default()
+        });
+        builder
+            .sample("1", 1.00, &[work, main])
+            .sample("1", 1.01, &[work, main])
+            .sample("1", 1.02, &[main])
+            .sample("1", 1.03, &[main]);
+        let mut chunk = builder.build();
+        chunk.profile.samples[1].weight = Some(3);
+        chunk.profile.samples[2].weight = Some(2);
+
+        let round_trip: SampleChunk =
+            serde_json::from_slice(&chunk.to_json_vec().unwrap()).unwrap();
+        assert_eq!(round_trip.profile.samples, chunk.profile.samples);
+
+        let call_trees = chunk.call_trees(None).unwrap();
+        let tree = &call_trees["1"];
+        let root = tree.roots()[0];
+        assert_eq!(tree[root].sample_count, 6);
+        assert_eq!(tree[root].duration_ns, 60_000_000);
+        assert_eq!(
+            (tree[root].start_ns, tree[root].end_ns),
+            (1_000_000_000, 1_030_000_000)
+        );
+        let work = tree.children(root)[0];
+        assert_eq!(tree[work].sample_count, 4);
+        assert_eq!(tree[work].duration_ns, 40_000_000);
+    }
+
+    #[test]
+    fn test_call_trees_collapse_recursion() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let [main, visit, work] = ["main", "visit", "work"].map(|function| {
+            builder.frame(Frame {
+                function: Some(function.to_string()),
+                module: Some("app".to_string()),
+                ..Default::default()
+            })
+        });
+        // the same function on another line is still the same function
+        let visit_other_line = builder.frame(Frame {
+            function: Some("visit".to_string()),
+            module: Some("app".to_string()),
+            line: Some(42),
+            ..Default::default()
+        });
+        builder
+            .sample("1", 1.00, &[work, visit_other_line, visit, visit, main])
+            .sample("1", 1.01, &[work, visit, main])
+            .sample("1", 1.02, &[visit, main, main])
+            .sample("1", 1.03, &[main]);
+        let mut chunk = builder.build();
+
+        let names = |tree: &CallTree| -> Vec<(usize, String, u64, u64)> {
+            let mut names = Vec::new();
+            let mut stack: Vec<(usize, NodeId)> =
+                tree.roots().iter().rev().map(|&id| (0, id)).collect();
+            while let Some((depth, id)) = stack.pop() {
+                let node = &tree[id];
+                names.push((
+                    depth,
+                    node.name.clone(),
+                    node.sample_count,
+                    node.recursion_count,
+                ));
+                stack.extend(
+                    tree.children(id)
+                        .iter()
+                        .rev()
+                        .map(|&child| (depth + 1, child)),
+                );
+            }
+            names
+        };
+
+        let call_trees = chunk
+            .call_trees_with_options(
+                None,
+                CallTreeOptions {
+                    collapse_recursion: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            names(&call_trees["1"]),
+            vec![
+                (0, "main".to_string(), 3, 1),
+                (1, "visit".to_string(), 3, 2),
+                (2, "work".to_string(), 2, 0),
+            ]
+        );
+
+        let call_trees = chunk.call_trees(None).unwrap();
+        let names = names(&call_trees["1"]);
+        assert_eq!(names.len(), 8);
+        assert!(names
+            .iter()
+            .all(|(_, _, _, recursion_count)| *recursion_count == 0));
+    }
+
+    #[test]
+    fn test_call_trees_truncate_deep_stacks() {
+        let max_depth = crate::limits::DEFAULT_MAX_STACK_DEPTH as usize;
+        let mut builder = SampleChunkBuilder::new("python");
+        let frames: Vec<i32> = (0..max_depth + 2)
+            .map(|i| {
+                builder.frame(Frame {
+                    function: Some(format!("function{i}")),
+                    module: Some("app".to_string()),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        // leaf first, function0 being the root
+        let deep: Vec<i32> = frames.iter().rev().copied().collect();
+        builder
+            .sample("1", 1.00, &deep)
+            .sample("1", 1.01, &deep[2..])
+            .sample("1", 1.02, &deep[3..])
+            .sample("1", 1.03, &[frames[0]]);
+        let mut chunk = builder.build();
+        assert_eq!(chunk.truncated_stacks(), 0);
+
+        let call_trees = chunk.call_trees(None).unwrap();
+        let tree = &call_trees["1"];
+        let roots: Vec<_> = tree
+            .roots()
+            .iter()
+            .map(|&root| (tree[root].name.as_str(), tree[root].truncated))
+            .collect();
+        // the truncated stack isn't merged with the one starting at the same
+        // frame for real
+        assert_eq!(roots, vec![("function2", true), ("function0", false)]);
+        let mut depth = 0;
+        let mut node = tree.roots()[0];
+        while let Some(&child) = tree.children(node).first() {
+            assert!(!tree[child].truncated);
+            node = child;
+            depth += 1;
+        }
+        assert_eq!(depth + 1, max_depth);
+        assert_eq!(tree[node].name, format!("function{}", max_depth + 1));
+        assert_eq!(chunk.truncated_stacks(), 1);
+    }
+
+    #[test]
+    fn test_call_trees_malformed_samples() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let main = builder.frame(Frame {
+            function: Some("main".to_string()),
+            module: Some("app".to_string()),
+            ..Default::default()
+        });
+        for i in 0..21 {
+            builder.sample("1", 1.0 + i as f64 * 0.01, &[main]);
+        }
+        let mut chunk = builder.build();
+        chunk.profile.samples[3].stack_id = 7;
+
+        // 1 of the 20 samples lasting until the next one is malformed
+        let call_trees = chunk.call_trees(None).unwrap();
+        let tree = &call_trees["1"];
+        let sample_count: u64 = tree
+            .roots()
+            .iter()
+            .map(|&root| tree[root].sample_count)
+            .sum();
+        assert_eq!(sample_count, 19);
+        assert_eq!(chunk.malformed_samples(), 1);
+
+        for sample in &mut chunk.profile.samples[4..6] {
+            sample.stack_id = -1;
+        }
+        let err = chunk.call_trees(None).unwrap_err();
+        assert_eq!(err.to_string(), "invalid stack id");
+    }
+
+    #[test]
+    fn test_call_trees_skip_idle_threads() {
+        let mut builder = SampleChunkBuilder::new("node");
+        let function = |name: &str| Frame {
+            function: Some(name.to_string()),
+            ..Default::default()
+        };
+        let main = builder.frame(function("main"));
+        let work = builder.frame(function("work"));
+        let wait = builder.frame(function("wait"));
+        builder
+            .sample("1", 1.00, &[work, main])
+            .sample("1", 1.01, &[main])
+            .sample("1", 1.02, &[main])
+            // parked worker
+            .sample("2", 1.00, &[wait, main])
+            .sample("2", 1.01, &[wait, main])
+            .sample("2", 1.02, &[wait, main])
+            // a single sample has no call tree to skip
+            .sample("3", 1.00, &[wait, main]);
+        let mut chunk = builder.build();
+
+        let call_trees = chunk.call_trees(None).unwrap();
+        assert!(call_trees.contains_key("2"));
+        assert_eq!(chunk.skipped_idle_threads(), 0);
+
+        let options = CallTreeOptions::default().with_skip_idle_threads(true);
+        let call_trees = chunk.call_trees_with_options(None, options).unwrap();
+        let mut thread_ids: Vec<&str> = call_trees.keys().map(AsRef::as_ref).collect();
+        thread_ids.sort();
+        assert_eq!(thread_ids, vec!["1"]);
+        assert_eq!(chunk.skipped_idle_threads(), 1);
+    }
 }
@@ -0,0 +1,121 @@
+//! Estimates the effective sampling frequency of profile chunks from the
+//! timestamps of their samples, for data-quality dashboards spotting SDKs
+//! which don't sample at the nominal rate (throttled apps, busy profilers).
+
+use crate::{sample::v2::SampleChunk, types::ChunkInterface};
+
+/// Sampling frequency of the SDK profilers, in Hz.
+const NOMINAL_SAMPLE_RATE_HZ: f64 = 101.0;
+
+/// Factor by which the estimated sampling interval may deviate from the
+/// nominal one before a chunk is flagged.
+const MAX_INTERVAL_DEVIATION: f64 = 2.0;
+
+/// Returns the median of a non-empty list of values.
+fn median(values: &mut [u64]) -> u64 {
+    values.sort_unstable();
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2
+    } else {
+        values[middle]
+    }
+}
+
+/// Returns the estimated sampling interval of a chunk in nanoseconds: the
+/// median of the medians of the deltas between the consecutive samples of
+/// each thread. Samples at the same timestamp (weighted samples, or threads
+/// sampled twice) are ignored.
+///
+/// Returns None for chunks with fewer than 2 samples per thread, and for
+/// android traces, whose events aren't sampled.
+pub fn sampling_interval_ns(chunk: &dyn ChunkInterface) -> Option<u64> {
+    chunk.as_any().downcast_ref::<SampleChunk>()?;
+    let mut thread_intervals: Vec<u64> = chunk
+        .sample_timestamps()
+        .into_values()
+        .filter_map(|mut timestamps| {
+            timestamps.sort_unstable();
+            let mut deltas: Vec<u64> = timestamps
+                .windows(2)
+                .map(|pair| pair[1] - pair[0])
+                .filter(|&delta| delta > 0)
+                .collect();
+            (!deltas.is_empty()).then(|| median(&mut deltas))
+        })
+        .collect();
+    (!thread_intervals.is_empty()).then(|| median(&mut thread_intervals))
+}
+
+/// Returns whether a sampling interval deviates from the nominal one by a
+/// factor of more than 2, in either direction.
+pub fn is_off_nominal_interval(interval_ns: u64) -> bool {
+    let nominal_interval_ns = 1e9 / NOMINAL_SAMPLE_RATE_HZ;
+    let ratio = interval_ns as f64 / nominal_interval_ns;
+    !(1.0 / MAX_INTERVAL_DEVIATION..=MAX_INTERVAL_DEVIATION).contains(&ratio)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_off_nominal_interval, sampling_interval_ns};
+    use crate::{
+        android::chunk::AndroidChunkBuilder, frame::Frame, sample::v2::SampleChunkBuilder,
+    };
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_sampling_interval_ns() {
+        assert_eq!(
+            sampling_interval_ns(&SampleChunkBuilder::new("python").build()),
+            None
+        );
+
+        let mut builder = SampleChunkBuilder::new("python");
+        let main = builder.frame(Frame {
+            function: Some("main".to_string()),
+            ..Default::default()
+        });
+
+        // thread 1 samples every 10ms, but for a 50ms gap
+        for timestamp in [10.0, 10.01, 10.02, 10.07, 10.08] {
+            builder.sample("1", timestamp, &[main]);
+        }
+        // thread 2 samples every 30ms, twice at the same timestamp
+        for timestamp in [10.0, 10.03, 10.03, 10.06] {
+            builder.sample("2", timestamp, &[main]);
+        }
+        // thread 3 samples every 20ms
+        for timestamp in [10.0, 10.02, 10.04] {
+            builder.sample("3", timestamp, &[main]);
+        }
+        let interval_ns = sampling_interval_ns(&builder.build()).unwrap();
+        assert!(interval_ns.abs_diff(20_000_000) < 1_000, "{interval_ns}");
+        // about half the nominal frequency
+        assert!(is_off_nominal_interval(interval_ns));
+
+        let mut builder = AndroidChunkBuilder::new(1737465052.0);
+        let on_create = builder.method("com.example.MainActivity", "onCreate", "()V", "");
+        builder
+            .thread(1, "main")
+            .enter(1, on_create, 0)
+            .exit(1, on_create, 10_000_000)
+            .enter(1, on_create, 20_000_000)
+            .exit(1, on_create, 30_000_000);
+        assert_eq!(sampling_interval_ns(&builder.build()), None);
+    }
+
+    #[test]
+    fn test_is_off_nominal_interval() {
+        let test_cases = [
+            (9_900_990, false),
+            (4_000_000, true),
+            (5_000_000, false),
+            (19_000_000, false),
+            (25_000_000, true),
+        ];
+        for (interval_ns, want) in test_cases {
+            assert_eq!(is_off_nominal_interval(interval_ns), want, "{interval_ns}");
+        }
+    }
+}
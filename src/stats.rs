@@ -0,0 +1,235 @@
+//! Statistics on the processing of a profile chunk (sizes, repairs, timings),
+//! so ingest can log and monitor them without instrumenting vroomrs.
+
+use std::{collections::HashSet, time::Duration};
+
+use pyo3::{pyclass, pymethods, PyErr};
+use serde::Serialize;
+
+use crate::{obfuscation::obfuscated_frames, types::ChunkInterface};
+
+/// Statistics on the processing of a profile chunk.
+#[pyclass]
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct ProcessingStats {
+    pub samples: usize,
+    pub threads: usize,
+    pub frames: usize,
+    pub stacks: usize,
+    pub unique_functions: usize,
+    pub obfuscated_frames: usize,
+    pub truncated_stacks: u64,
+    pub malformed_samples: u64,
+    pub parse_time_ns: u64,
+    pub build_time_ns: u64,
+}
+
+#[pymethods]
+impl ProcessingStats {
+    /// Returns the number of samples of the chunk (or of events, for android
+    /// trace chunks).
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of samples.
+    pub fn get_samples(&self) -> usize {
+        self.samples
+    }
+
+    /// Returns the number of threads sampled.
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of threads.
+    pub fn get_threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Returns the number of frames of the chunk (or of methods, for android
+    /// trace chunks).
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of frames.
+    pub fn get_frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Returns the number of stacks of the chunk. Android trace chunks,
+    /// recording events entering and exiting methods, have none.
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of stacks.
+    pub fn get_stacks(&self) -> usize {
+        self.stacks
+    }
+
+    /// Returns the number of distinct functions of the frames of the chunk,
+    /// by fingerprint.
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of unique functions.
+    pub fn get_unique_functions(&self) -> usize {
+        self.unique_functions
+    }
+
+    /// Returns the number of app frames whose class name looks renamed by
+    /// ProGuard, on platforms supporting deobfuscation.
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of obfuscated frames.
+    pub fn get_obfuscated_frames(&self) -> usize {
+        self.obfuscated_frames
+    }
+
+    /// Returns the number of stacks truncated at the maximum depth while
+    /// building the call trees.
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of stacks truncated.
+    pub fn get_truncated_stacks(&self) -> u64 {
+        self.truncated_stacks
+    }
+
+    /// Returns the number of malformed samples (referencing a stack or frame
+    /// which doesn't exist) dropped while building the call trees.
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of samples dropped.
+    pub fn get_malformed_samples(&self) -> u64 {
+        self.malformed_samples
+    }
+
+    /// Returns the time it took to parse the payload of the chunk.
+    ///
+    /// Returns:
+    ///     int
+    ///         The parse time in nanoseconds. 0 if the chunk wasn't parsed from
+    ///         a payload.
+    pub fn get_parse_time_ns(&self) -> u64 {
+        self.parse_time_ns
+    }
+
+    /// Returns the time it took to build the call trees of the chunk.
+    ///
+    /// Returns:
+    ///     int
+    ///         The build time in nanoseconds.
+    pub fn get_build_time_ns(&self) -> u64 {
+        self.build_time_ns
+    }
+
+    /// Serializes the stats, to be logged along with the chunk metadata.
+    ///
+    /// Returns:
+    ///     str
+    ///         A JSON string of the stats.
+    ///
+    /// Raises:
+    ///     pyo3.exceptions.PyException: If an error occurs during serialization.
+    pub fn to_json_str(&self) -> Result<String, PyErr> {
+        serde_json::to_string(&self)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))
+    }
+}
+
+/// Returns the processing stats of a chunk whose call trees were built, the
+/// repairs being counted while building them.
+pub fn processing_stats(
+    chunk: &dyn ChunkInterface,
+    parse_duration: Duration,
+    build_duration: Duration,
+) -> ProcessingStats {
+    let frames = chunk.frames();
+    let unique_functions: HashSet<u32> =
+        frames.iter().map(|frame| frame.fingerprint(None)).collect();
+    let (obfuscated_frames, _) = obfuscated_frames(&chunk.get_platform(), &frames);
+    ProcessingStats {
+        samples: chunk.sample_count(),
+        threads: chunk.sample_timestamps().len(),
+        frames: frames.len(),
+        stacks: chunk.stack_count(),
+        unique_functions: unique_functions.len(),
+        obfuscated_frames,
+        truncated_stacks: chunk.truncated_stacks(),
+        malformed_samples: chunk.malformed_samples(),
+        parse_time_ns: parse_duration.as_nanos() as u64,
+        build_time_ns: build_duration.as_nanos() as u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::processing_stats;
+    use crate::{
+        android::chunk::AndroidChunkBuilder, frame::Frame, sample::v2::SampleChunkBuilder,
+        types::ChunkInterface,
+    };
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_processing_stats() {
+        let mut builder = SampleChunkBuilder::new("python");
+        let frame = |function: &str, line: u32| Frame {
+            function: Some(function.to_string()),
+            module: Some("app".to_string()),
+            line: Some(line),
+            ..Default::default()
+        };
+        let main = builder.frame(frame("main", 1));
+        let load = builder.frame(frame("load", 2));
+        // another line of the same function
+        let load_again = builder.frame(frame("load", 3));
+        builder
+            .sample("1", 1.0, &[load, main])
+            .sample("1", 1.01, &[load_again, main])
+            .sample("1", 1.02, &[main])
+            .sample("2", 1.0, &[main]);
+        let mut chunk = builder.build();
+        chunk.call_trees(None).unwrap();
+
+        let stats = processing_stats(&chunk, Duration::from_micros(3), Duration::from_micros(2));
+        assert_eq!(
+            (
+                stats.samples,
+                stats.threads,
+                stats.frames,
+                stats.stacks,
+                stats.unique_functions,
+                stats.obfuscated_frames,
+            ),
+            (4, 2, 3, 3, 2, 0)
+        );
+        assert_eq!((stats.parse_time_ns, stats.build_time_ns), (3_000, 2_000));
+    }
+
+    #[test]
+    fn test_processing_stats_android_chunk() {
+        let mut builder = AndroidChunkBuilder::new(1737465052.0);
+        let on_create = builder.method("com.example.MainActivity", "onCreate", "()V", "");
+        let obfuscated = builder.method("com.example.a", "b", "()V", "");
+        builder
+            .thread(1, "main")
+            .enter(1, on_create, 0)
+            .enter(1, obfuscated, 10_000_000)
+            .exit(1, obfuscated, 20_000_000)
+            .exit(1, on_create, 30_000_000);
+        let mut chunk = builder.build();
+        chunk.call_trees(None).unwrap();
+
+        let stats = processing_stats(&chunk, Duration::ZERO, Duration::ZERO);
+        assert_eq!(
+            (stats.samples, stats.threads, stats.frames, stats.stacks),
+            (4, 1, 2, 0)
+        );
+        assert_eq!(stats.obfuscated_frames, 1);
+    }
+}
@@ -0,0 +1,187 @@
+//! Decides whether native chunks should be withheld from function metrics
+//! until symbolication completes, as functions of frames only known by their
+//! address would otherwise be aggregated under placeholder names.
+
+use pyo3::{pyclass, pymethods, PyErr};
+use serde::Serialize;
+
+use crate::{frame::Frame, types::ChunkInterface};
+
+/// Platforms whose frames are symbolicated from their instruction address:
+/// cocoa, and native for windows and linux.
+pub(crate) const NATIVE_PLATFORMS: &[&str] = &["cocoa", "native"];
+
+/// Symbolicator statuses of frames which couldn't be symbolicated.
+const UNSYMBOLICATED_STATUSES: &[&str] =
+    &["missing", "missing_symbol", "unknown_image", "malformed"];
+
+/// How far symbolication of the frames of a chunk went.
+#[pyclass]
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct SymbolicationStats {
+    pub symbolicated_frames: usize,
+    pub unsymbolicated_frames: usize,
+    pub pending_frames: usize,
+    pub resubmit_after_symbolication: bool,
+}
+
+#[pymethods]
+impl SymbolicationStats {
+    /// Returns the number of frames symbolicated.
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of frames symbolicated.
+    pub fn get_symbolicated_frames(&self) -> usize {
+        self.symbolicated_frames
+    }
+
+    /// Returns the number of frames symbolication failed for (missing debug
+    /// files, unknown images, ...), which waiting won't fix.
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of frames which couldn't be symbolicated.
+    pub fn get_unsymbolicated_frames(&self) -> usize {
+        self.unsymbolicated_frames
+    }
+
+    /// Returns the number of native frames not symbolicated yet, only known
+    /// by their instruction address.
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of frames pending symbolication.
+    pub fn get_pending_frames(&self) -> usize {
+        self.pending_frames
+    }
+
+    /// Returns whether the chunk should be withheld from function metrics,
+    /// and resubmitted once symbolicated.
+    ///
+    /// Returns:
+    ///     bool
+    ///         True if too many frames are pending symbolication.
+    pub fn get_resubmit_after_symbolication(&self) -> bool {
+        self.resubmit_after_symbolication
+    }
+
+    /// Serializes the stats, to be stored along with the chunk metadata.
+    ///
+    /// Returns:
+    ///     str
+    ///         A JSON string of the stats, including the
+    ///         `resubmit_after_symbolication` marker.
+    ///
+    /// Raises:
+    ///     pyo3.exceptions.PyException: If an error occurs during serialization.
+    pub fn to_json_str(&self) -> Result<String, PyErr> {
+        serde_json::to_string(&self)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))
+    }
+}
+
+/// Returns the symbolicator status of a frame, if it went through
+/// symbolication.
+fn symbolicator_status(frame: &Frame) -> Option<&str> {
+    frame
+        .data
+        .as_ref()
+        .and_then(|data| data.symbolicator_status.as_deref())
+        .filter(|status| !status.is_empty())
+}
+
+/// Returns whether a frame couldn't be symbolicated.
+pub(crate) fn is_unsymbolicated_frame(frame: &Frame) -> bool {
+    symbolicator_status(frame).is_some_and(|status| UNSYMBOLICATED_STATUSES.contains(&status))
+}
+
+/// Returns the symbolication stats of a chunk. Chunks of native platforms
+/// are to be resubmitted after symbolication when the ratio of their frames
+/// pending symbolication exceeds `max_pending_ratio`. Other chunks never are.
+pub fn symbolication_stats(
+    chunk: &dyn ChunkInterface,
+    max_pending_ratio: f64,
+) -> SymbolicationStats {
+    let platform = chunk.get_platform();
+    let frames = chunk.frames();
+    let mut stats = SymbolicationStats::default();
+    for frame in &frames {
+        match symbolicator_status(frame) {
+            Some("symbolicated") => stats.symbolicated_frames += 1,
+            Some(_) if is_unsymbolicated_frame(frame) => stats.unsymbolicated_frames += 1,
+            Some(_) => {}
+            None => {
+                let frame_platform = frame.platform.as_deref().unwrap_or(&platform);
+                if NATIVE_PLATFORMS.contains(&frame_platform)
+                    && frame.instruction_addr.is_some()
+                    && frame.function.as_deref().is_none_or(str::is_empty)
+                {
+                    stats.pending_frames += 1;
+                }
+            }
+        }
+    }
+    stats.resubmit_after_symbolication = NATIVE_PLATFORMS.contains(&platform.as_str())
+        && stats.pending_frames > 0
+        && stats.pending_frames as f64 / frames.len() as f64 > max_pending_ratio;
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::symbolication_stats;
+    use crate::{
+        frame::{Data, Frame},
+        sample::v2::SampleChunkBuilder,
+    };
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_symbolication_stats() {
+        let frame = |function: Option<&str>, status: Option<&str>| Frame {
+            function: function.map(str::to_string),
+            instruction_addr: Some("0x1000".to_string()),
+            data: status.map(|status| Data {
+                symbolicator_status: Some(status.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let build_chunk = |platform: &str| {
+            let mut builder = SampleChunkBuilder::new(platform);
+            let frames = [
+                builder.frame(frame(Some("main"), Some("symbolicated"))),
+                builder.frame(frame(None, Some("missing"))),
+                builder.frame(frame(None, None)),
+                builder.frame(frame(Some("work"), None)),
+            ];
+            let [main, missing, pending, work] = frames;
+            builder
+                .sample("1", 1.0, &[missing, main])
+                .sample("1", 1.1, &[work, pending, main]);
+            builder.build()
+        };
+
+        let chunk = build_chunk("cocoa");
+        let stats = symbolication_stats(&chunk, 0.0);
+        assert_eq!(
+            (
+                stats.symbolicated_frames,
+                stats.unsymbolicated_frames,
+                stats.pending_frames
+            ),
+            (1, 1, 1)
+        );
+        assert!(stats.resubmit_after_symbolication);
+        // a frame out of 4 is pending
+        assert!(!symbolication_stats(&chunk, 0.25).resubmit_after_symbolication);
+
+        // only native chunks are withheld
+        let chunk = build_chunk("python");
+        let stats = symbolication_stats(&chunk, 0.0);
+        assert_eq!(stats.pending_frames, 0);
+        assert!(!stats.resubmit_after_symbolication);
+    }
+}
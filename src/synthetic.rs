@@ -0,0 +1,295 @@
+//! Generates randomized but schema-valid profile chunks, to load test and
+//! fuzz the analysis pipeline.
+
+use std::fmt;
+
+use uuid::Uuid;
+
+use crate::{
+    android::chunk::{AndroidChunk, AndroidChunkBuilder},
+    frame::Frame,
+    sample::v2::{SampleChunk, SampleChunkBuilder},
+};
+
+/// Number of packages functions are spread over.
+const PACKAGE_COUNT: usize = 8;
+
+/// Chunk start timestamp, in seconds.
+const START_TIMESTAMP: f64 = 1_700_000_000.0;
+
+/// Shape of the generated chunks.
+#[derive(Debug, Clone)]
+pub struct SyntheticOptions {
+    /// Number of threads sampled, the first one being the main thread
+    pub threads: u64,
+
+    /// Maximum stack depth
+    pub max_depth: usize,
+
+    /// Sampling frequency, in Hz
+    pub sample_rate_hz: u64,
+
+    /// Duration of the chunk, in milliseconds
+    pub duration_ms: u64,
+
+    /// Number of distinct functions stacks are made of
+    pub unique_functions: usize,
+
+    /// Seed of the random generator, to reproduce a given chunk
+    pub seed: Option<u64>,
+}
+
+impl Default for SyntheticOptions {
+    fn default() -> Self {
+        SyntheticOptions {
+            threads: 1,
+            max_depth: 32,
+            sample_rate_hz: 100,
+            duration_ms: 1000,
+            unique_functions: 100,
+            seed: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SyntheticError {
+    TooManySamples,
+}
+
+impl fmt::Display for SyntheticError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyntheticError::TooManySamples => {
+                write!(
+                    f,
+                    "duration and sampling frequency overflow the sample count"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SyntheticError {}
+
+/// SplitMix64 generator: fast, seedable and good enough to shape stacks.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: Option<u64>) -> Self {
+        Rng(seed.unwrap_or_else(|| Uuid::new_v4().as_u64_pair().0))
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `[0, n)`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+}
+
+/// Walks a stack randomly: each step pops a few frames then pushes a few
+/// new ones, staying within `[1, max_depth]` frames. Stacks are ordered
+/// from the outermost frame to the innermost one.
+struct StackWalk {
+    stack: Vec<usize>,
+    max_depth: usize,
+    unique_functions: usize,
+}
+
+impl StackWalk {
+    fn new(rng: &mut Rng, options: &SyntheticOptions) -> Self {
+        let max_depth = options.max_depth.max(1);
+        let unique_functions = options.unique_functions.max(1);
+        let depth = 1 + rng.below(max_depth);
+        StackWalk {
+            stack: (0..depth).map(|_| rng.below(unique_functions)).collect(),
+            max_depth,
+            unique_functions,
+        }
+    }
+
+    fn step(&mut self, rng: &mut Rng) -> &[usize] {
+        let pop = rng.below(4).min(self.stack.len() - 1);
+        self.stack.truncate(self.stack.len() - pop);
+        let push = rng.below(4).min(self.max_depth - self.stack.len());
+        for _ in 0..push {
+            self.stack.push(rng.below(self.unique_functions));
+        }
+        &self.stack
+    }
+}
+
+fn sample_count(options: &SyntheticOptions) -> Result<u64, SyntheticError> {
+    let count = options
+        .duration_ms
+        .checked_mul(options.sample_rate_hz)
+        .ok_or(SyntheticError::TooManySamples)?;
+    Ok((count / 1000).max(2))
+}
+
+/// Generates a sample format chunk for the given platform.
+pub fn generate_sample_chunk(
+    platform: &str,
+    options: &SyntheticOptions,
+) -> Result<SampleChunk, SyntheticError> {
+    let sample_count = sample_count(options)?;
+    let mut rng = Rng::new(options.seed);
+    let mut builder = SampleChunkBuilder::new(platform);
+
+    let frames: Vec<i32> = (0..options.unique_functions.max(1))
+        .map(|i| {
+            builder.frame(Frame {
+                function: Some(format!("function_{i}")),
+                package: Some(format!("package_{}", i % PACKAGE_COUNT)),
+                in_app: Some(i % PACKAGE_COUNT == 0),
+                ..Default::default()
+            })
+        })
+        .collect();
+
+    let period = 1.0 / options.sample_rate_hz.max(1) as f64;
+    for thread in 0..options.threads.max(1) {
+        let thread_id = (thread + 1).to_string();
+        builder.thread(&thread_id, if thread == 0 { "main" } else { "worker" });
+
+        let mut walk = StackWalk::new(&mut rng, options);
+        for i in 0..sample_count {
+            // sample stacks are listed from the innermost frame
+            let stack: Vec<i32> = walk
+                .step(&mut rng)
+                .iter()
+                .rev()
+                .map(|&f| frames[f])
+                .collect();
+            builder.sample(&thread_id, START_TIMESTAMP + i as f64 * period, &stack);
+        }
+    }
+
+    Ok(builder.build())
+}
+
+/// Generates a chunk in the legacy android trace format.
+pub fn generate_android_chunk(options: &SyntheticOptions) -> Result<AndroidChunk, SyntheticError> {
+    let sample_count = sample_count(options)?;
+    let mut rng = Rng::new(options.seed);
+    let mut builder = AndroidChunkBuilder::new(START_TIMESTAMP);
+
+    let methods: Vec<u64> = (0..options.unique_functions.max(1))
+        .map(|i| {
+            builder.method(
+                &format!("com.example.package{}.Class{i}", i % PACKAGE_COUNT),
+                &format!("method{i}"),
+                "()V",
+                &format!("Class{i}.java"),
+            )
+        })
+        .collect();
+
+    let period_ns = 1_000_000_000 / options.sample_rate_hz.max(1);
+    let end_ns = sample_count
+        .checked_mul(period_ns)
+        .ok_or(SyntheticError::TooManySamples)?;
+    for thread_id in 1..=options.threads.max(1) {
+        builder.thread(thread_id, if thread_id == 1 { "main" } else { "worker" });
+
+        let mut walk = StackWalk::new(&mut rng, options);
+        let mut current: Vec<usize> = Vec::new();
+        for i in 0..sample_count {
+            let ts_ns = i * period_ns;
+            let next = walk.step(&mut rng).to_vec();
+            let common = current
+                .iter()
+                .zip(&next)
+                .take_while(|(a, b)| a == b)
+                .count();
+            for &m in current[common..].iter().rev() {
+                builder.exit(thread_id, methods[m], ts_ns);
+            }
+            for &m in &next[common..] {
+                builder.enter(thread_id, methods[m], ts_ns);
+            }
+            current = next;
+        }
+        for &m in current.iter().rev() {
+            builder.exit(thread_id, methods[m], end_ns);
+        }
+    }
+
+    Ok(builder.build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_android_chunk, generate_sample_chunk, SyntheticError, SyntheticOptions};
+    use crate::{android::chunk::AndroidChunk, sample::v2::SampleChunk, types::ChunkInterface};
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_generate_sample_chunk() {
+        let options = SyntheticOptions {
+            threads: 3,
+            max_depth: 16,
+            seed: Some(42),
+            ..Default::default()
+        };
+        let mut chunk = generate_sample_chunk("cocoa", &options).unwrap();
+
+        assert_eq!(chunk.profile.samples.len(), 300);
+        assert!(chunk.profile.stacks.iter().all(|s| s.len() <= 16));
+
+        // the same seed generates the same chunk
+        let payload = chunk.to_json_vec().unwrap();
+        let other = generate_sample_chunk("cocoa", &options).unwrap();
+        assert_eq!(chunk.profile, other.profile);
+
+        let r: Result<SampleChunk, _> = serde_json::from_slice(&payload);
+        assert!(r.is_ok(), "{r:#?}");
+
+        let call_trees = chunk.call_trees(None).unwrap();
+        assert_eq!(call_trees.len(), 3);
+    }
+
+    #[test]
+    fn test_generate_android_chunk() {
+        let options = SyntheticOptions {
+            threads: 2,
+            seed: Some(42),
+            ..Default::default()
+        };
+        let mut chunk = generate_android_chunk(&options).unwrap();
+
+        let payload = chunk.to_json_vec().unwrap();
+        let r: Result<AndroidChunk, _> = serde_json::from_slice(&payload);
+        assert!(r.is_ok(), "{r:#?}");
+
+        assert_eq!(chunk.duration_ms(), 1000);
+        // android call trees are only generated for the main thread
+        let call_trees = chunk.call_trees(None).unwrap();
+        assert_eq!(call_trees.len(), 1);
+        assert!(call_trees.contains_key("1"));
+    }
+
+    #[test]
+    fn test_generate_too_many_samples() {
+        let options = SyntheticOptions {
+            duration_ms: u64::MAX,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_sample_chunk("cocoa", &options).unwrap_err(),
+            SyntheticError::TooManySamples
+        );
+        assert_eq!(
+            generate_android_chunk(&options).unwrap_err(),
+            SyntheticError::TooManySamples
+        );
+    }
+}
@@ -0,0 +1,143 @@
+//! Conversions between timestamps in seconds, as floats, and in nanoseconds.
+//!
+//! Payloads carry timestamps as float seconds, which only hold about 16
+//! significant digits: a Unix timestamp multiplied by 1e9 is off by up to a
+//! few hundred nanoseconds. Timestamps are converted to integer nanoseconds
+//! when parsed instead, from the shortest decimal representation of the
+//! float. That's the representation the float was parsed from as long as it
+//! had at most 17 significant digits, so `1737465052.423` is
+//! 1_737_465_052_423_000_000 ns, exactly.
+
+use std::fmt::{self, Write};
+
+use serde::{de::Unexpected, Deserialize, Deserializer, Serializer};
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+/// Converts a timestamp in seconds to nanoseconds, rounded to the nearest
+/// nanosecond. Returns `None` if the timestamp is negative, not finite, or
+/// doesn't fit in 64 bits once in nanoseconds.
+pub(crate) fn nanos_from_seconds(seconds: f64) -> Option<u64> {
+    if seconds.is_nan() || seconds.is_infinite() || seconds < 0.0 {
+        return None;
+    }
+    if seconds == 0.0 {
+        return Some(0);
+    }
+
+    let mut buffer = Buffer::default();
+    write!(buffer, "{seconds:e}").ok()?;
+    let (mantissa, exponent) = buffer.as_str().split_once('e')?;
+    let exponent: i32 = exponent.parse().ok()?;
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    // at most 17 significant digits, which fit in a u64
+    let digits = integer
+        .bytes()
+        .chain(fraction.bytes())
+        .fold(0u64, |digits, digit| digits * 10 + u64::from(digit - b'0'));
+
+    let scale = exponent + 9 - fraction.len() as i32;
+    if scale >= 0 {
+        digits.checked_mul(10u64.checked_pow(scale as u32)?)
+    } else if scale < -19 {
+        Some(0)
+    } else {
+        let divisor = 10u64.pow(-scale as u32);
+        Some((digits + divisor / 2) / divisor)
+    }
+}
+
+/// Converts a timestamp in nanoseconds to seconds, to the closest float.
+pub(crate) fn seconds_from_nanos(timestamp_ns: u64) -> f64 {
+    (timestamp_ns / NANOS_PER_SECOND) as f64
+        + (timestamp_ns % NANOS_PER_SECOND) as f64 / NANOS_PER_SECOND as f64
+}
+
+/// Serde helpers for nanosecond timestamps represented as seconds in
+/// payloads, either floats or integers, to use with `#[serde(with)]`.
+pub(crate) mod seconds {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        timestamp_ns: &u64,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(seconds_from_nanos(*timestamp_ns))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        let seconds = f64::deserialize(deserializer)?;
+        nanos_from_seconds(seconds).ok_or_else(|| {
+            serde::de::Error::invalid_value(
+                Unexpected::Float(seconds),
+                &"a positive timestamp in seconds",
+            )
+        })
+    }
+}
+
+/// Formats the float on the stack, its scientific notation being at most 24
+/// bytes long.
+#[derive(Default)]
+struct Buffer {
+    bytes: [u8; 32],
+    len: usize,
+}
+
+impl Buffer {
+    fn as_str(&self) -> &str {
+        // only ever written from `str`s
+        std::str::from_utf8(&self.bytes[..self.len]).unwrap_or_default()
+    }
+}
+
+impl Write for Buffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.bytes
+            .get_mut(self.len..end)
+            .ok_or(fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{nanos_from_seconds, seconds_from_nanos};
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_nanos_from_seconds() {
+        let test_cases = [
+            (0.0, Some(0)),
+            (1.0, Some(1_000_000_000)),
+            (0.03, Some(30_000_000)),
+            (1.001, Some(1_001_000_000)),
+            (10.03, Some(10_030_000_000)),
+            (1737465052.423, Some(1_737_465_052_423_000_000)),
+            (1737465052.423456, Some(1_737_465_052_423_456_000)),
+            (1.5e-9, Some(2)),
+            (1.4e-9, Some(1)),
+            (1e-20, Some(0)),
+            (1.8e10, Some(18_000_000_000_000_000_000)),
+            (1.9e10, None),
+            (-1.0, None),
+            (f64::NAN, None),
+            (f64::INFINITY, None),
+        ];
+        for (seconds, want) in test_cases {
+            assert_eq!(nanos_from_seconds(seconds), want, "{seconds}");
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for seconds in [0.0, 0.03, 10.03, 1737465052.423, 1737465052.423456, 1.8e10] {
+            let nanos = nanos_from_seconds(seconds).unwrap();
+            assert_eq!(seconds_from_nanos(nanos), seconds);
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Ranks the frames of a profile by the self time they were sampled in
+//! across threads, a lighter-weight alternative to the function metrics for
+//! the header of the profile summary.
+
+use std::collections::HashMap;
+
+use pyo3::{pyclass, pymethods};
+
+use crate::nodetree::{CallTree, NodeId};
+
+/// Maximum number of stack fingerprints recorded for each frame.
+const MAX_STACK_FINGERPRINTS: usize = 5;
+
+/// A frame, with the self time it was sampled in across threads.
+#[pyclass]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TopFrame {
+    pub fingerprint: u32,
+    pub function: String,
+    pub package: String,
+    pub in_app: bool,
+    pub self_time_ns: u64,
+    pub sample_count: u64,
+    /// Fingerprints of the stacks the frame was sampled the longest in,
+    /// sorted by decreasing self time.
+    pub stack_fingerprints: Vec<u32>,
+}
+
+#[pymethods]
+impl TopFrame {
+    /// Returns the fingerprint of the frame, the same as the one of the
+    /// function in :meth:`CallTreeFunction.get_fingerprint`.
+    ///
+    /// Returns:
+    ///     int
+    ///         The fingerprint of the frame.
+    pub fn get_fingerprint(&self) -> u32 {
+        self.fingerprint
+    }
+
+    /// Returns the function name of the frame.
+    ///
+    /// Returns:
+    ///     str
+    ///         The function name.
+    pub fn get_function(&self) -> &str {
+        &self.function
+    }
+
+    /// Returns the package (or module) of the frame.
+    ///
+    /// Returns:
+    ///     str
+    ///         The package name.
+    pub fn get_package(&self) -> &str {
+        &self.package
+    }
+
+    /// Returns whether the frame is an app or system one.
+    ///
+    /// Returns:
+    ///     bool
+    ///         True if the frame is an app one, False otherwise.
+    pub fn get_in_app(&self) -> bool {
+        self.in_app
+    }
+
+    /// Returns the time the frame was sampled in as the leaf frame.
+    ///
+    /// Returns:
+    ///     int
+    ///         The self time in nanoseconds, summed across threads.
+    pub fn get_self_time_ns(&self) -> u64 {
+        self.self_time_ns
+    }
+
+    /// Returns the number of samples the frame was the leaf frame of.
+    ///
+    /// Returns:
+    ///     int
+    ///         The number of samples, summed across threads.
+    pub fn get_sample_count(&self) -> u64 {
+        self.sample_count
+    }
+
+    /// Returns examples of the stacks the frame was sampled in.
+    ///
+    /// Returns:
+    ///     list[int]
+    ///         Up to 5 fingerprints of the stacks up to the frame (see
+    ///         :meth:`CallTreeFunction.get_stack_fingerprint`), the ones the
+    ///         frame spent the most self time in first.
+    pub fn get_stack_fingerprints(&self) -> Vec<u32> {
+        self.stack_fingerprints.clone()
+    }
+}
+
+/// Returns the `n` frames with the most self time in the call trees,
+/// whatever their depth and whether they're app frames or not.
+pub fn top_frames<'a>(
+    call_trees: impl IntoIterator<Item = &'a CallTree>,
+    n: usize,
+) -> Vec<TopFrame> {
+    let mut frames: HashMap<u32, TopFrame> = HashMap::new();
+    let mut stack_self_times: HashMap<u32, HashMap<u32, u64>> = HashMap::new();
+    for call_tree in call_trees {
+        let mut stack: Vec<(NodeId, Option<u32>)> =
+            call_tree.roots().iter().map(|&root| (root, None)).collect();
+        while let Some((id, parent_fingerprint)) = stack.pop() {
+            let node = &call_tree[id];
+            let stack_fingerprint = node.frame.fingerprint(parent_fingerprint);
+            let children = call_tree.children(id);
+            stack.extend(
+                children
+                    .iter()
+                    .map(|&child| (child, Some(stack_fingerprint))),
+            );
+
+            let (children_duration_ns, children_sample_count) =
+                children
+                    .iter()
+                    .fold((0, 0), |(duration_ns, count), &child| {
+                        let child = &call_tree[child];
+                        (duration_ns + child.duration_ns, count + child.sample_count)
+                    });
+            let self_time_ns = node.duration_ns.saturating_sub(children_duration_ns);
+            if self_time_ns == 0 {
+                continue;
+            }
+            let fingerprint = node.frame.fingerprint(None);
+            let frame = frames.entry(fingerprint).or_insert_with(|| TopFrame {
+                fingerprint,
+                function: node.frame.function.clone().unwrap_or_default(),
+                package: node.frame.module_or_package(),
+                in_app: node.is_application,
+                ..Default::default()
+            });
+            frame.self_time_ns += self_time_ns;
+            frame.sample_count += node.sample_count.saturating_sub(children_sample_count);
+            *stack_self_times
+                .entry(fingerprint)
+                .or_default()
+                .entry(stack_fingerprint)
+                .or_default() += self_time_ns;
+        }
+    }
+
+    let mut frames: Vec<TopFrame> = frames.into_values().collect();
+    frames.sort_by(|a, b| {
+        b.self_time_ns
+            .cmp(&a.self_time_ns)
+            .then_with(|| a.fingerprint.cmp(&b.fingerprint))
+    });
+    frames.truncate(n);
+    for frame in &mut frames {
+        let mut stacks: Vec<(u32, u64)> = stack_self_times
+            .remove(&frame.fingerprint)
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        stacks.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        frame.stack_fingerprints = stacks
+            .into_iter()
+            .take(MAX_STACK_FINGERPRINTS)
+            .map(|(stack_fingerprint, _)| stack_fingerprint)
+            .collect();
+    }
+    frames
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::top_frames;
+    use crate::{
+        frame::Frame,
+        nodetree::{CallTree, NestedNode, Node},
+    };
+
+    use pretty_assertions::assert_eq;
+
+    fn node(function: &str, duration_ns: u64, children: Vec<NestedNode>) -> NestedNode {
+        NestedNode(
+            Node {
+                duration_ns,
+                sample_count: duration_ns / 10,
+                is_application: function != "read",
+                frame: Arc::new(Frame {
+                    function: Some(function.to_string()),
+                    module: Some("app".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            children,
+        )
+    }
+
+    #[test]
+    fn test_top_frames() {
+        let main_thread = CallTree::from_nested(vec![node(
+            "main",
+            100,
+            vec![node("load", 60, vec![node("read", 40, vec![])])],
+        )]);
+        let worker = CallTree::from_nested(vec![node("run", 45, vec![node("read", 30, vec![])])]);
+
+        let frames = top_frames([&main_thread, &worker], 3);
+        let ranking: Vec<(&str, u64, u64, usize)> = frames
+            .iter()
+            .map(|frame| {
+                (
+                    frame.function.as_str(),
+                    frame.self_time_ns,
+                    frame.sample_count,
+                    frame.stack_fingerprints.len(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            ranking,
+            vec![("read", 70, 7, 2), ("main", 40, 4, 1), ("load", 20, 2, 1)]
+        );
+        assert!(!frames[0].in_app);
+        // read spent more time below load than below run
+        let read = &worker[worker.children(worker.roots()[0])[0]].frame;
+        let run = &worker[worker.roots()[0]].frame;
+        assert_eq!(
+            frames[0].stack_fingerprints[1],
+            read.fingerprint(Some(run.fingerprint(None)))
+        );
+    }
+}
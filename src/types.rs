@@ -1,21 +1,22 @@
 use chrono::{DateTime, Utc};
 
-use pyo3::exceptions::PyValueError;
 use pyo3::{pyclass, PyErr};
 use serde::{Deserialize, Serialize};
 use std::any::Any;
 use std::borrow::Cow;
-use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
-use std::rc::Rc;
 
 use crate::android::AndroidError;
 use crate::debug_images::Image;
-use crate::nodetree::Node;
-use crate::sample::v1::Measurement;
+use crate::exceptions::{
+    CompressionError, InvalidStack, ParseError, UnsupportedVersion, VroomError,
+};
+use crate::frame::{Frame, FrameOverrideError, FrameOverrides, InAppRules};
+use crate::measurements::{Measurement, Measurements};
+use crate::nodetree::CallTree;
 use crate::sample::SampleError;
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ClientSDK {
     pub name: String,
     pub version: String,
@@ -47,7 +48,27 @@ impl DebugMeta {
     }
 }
 
+/// Links a chunk to the traces it was collected during, when the SDK sends
+/// them.
+#[derive(Default, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct TraceContext {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction_id: Option<String>,
+    /// IDs of the segments (root spans) overlapping the chunk.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub segment_ids: Vec<String>,
+}
+
+impl TraceContext {
+    pub fn is_empty(&self) -> bool {
+        self.trace_id.is_none() && self.transaction_id.is_none() && self.segment_ids.is_empty()
+    }
+}
+
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum CallTreeError {
     Sample(SampleError),
     Android(AndroidError),
@@ -67,14 +88,87 @@ impl fmt::Display for CallTreeError {
     }
 }
 
+impl std::error::Error for CallTreeError {}
+
 impl From<CallTreeError> for PyErr {
     fn from(error: CallTreeError) -> Self {
-        PyValueError::new_err(error.to_string())
+        match error {
+            CallTreeError::Sample(_) => InvalidStack::new_err(error.to_string()),
+            CallTreeError::Android(_) => VroomError::new_err(error.to_string()),
+        }
     }
 }
 
-pub type CallTreesU64 = HashMap<u64, Vec<Rc<RefCell<Node>>>>;
-pub type CallTreesStr<'a> = HashMap<Cow<'a, str>, Vec<Rc<RefCell<Node>>>>;
+/// Errors reading the payload of a profile or chunk.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PayloadError {
+    Json(serde_json::Error),
+    /// The version of the format of the payload isn't supported.
+    UnsupportedVersion(String),
+    Decompression(std::io::Error),
+}
+
+impl fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PayloadError::Json(error) => write!(f, "{error}"),
+            PayloadError::UnsupportedVersion(version) => {
+                write!(f, "unsupported version {version:?}")
+            }
+            PayloadError::Decompression(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for PayloadError {}
+
+impl From<serde_json::Error> for PayloadError {
+    fn from(error: serde_json::Error) -> Self {
+        PayloadError::Json(error)
+    }
+}
+
+impl From<PayloadError> for PyErr {
+    fn from(error: PayloadError) -> Self {
+        match error {
+            PayloadError::Json(_) => ParseError::new_err(error.to_string()),
+            PayloadError::UnsupportedVersion(_) => UnsupportedVersion::new_err(error.to_string()),
+            PayloadError::Decompression(_) => CompressionError::new_err(error.to_string()),
+        }
+    }
+}
+
+pub type CallTreesU64 = HashMap<u64, CallTree>;
+pub type CallTreesStr<'a> = HashMap<Cow<'a, str>, CallTree>;
+
+/// Options of how the call trees of chunks are built.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CallTreeOptions {
+    /// Collapses consecutive calls of the same function (frames with the
+    /// same fingerprint) into a single node, counting the calls collapsed in
+    /// its `recursion_count`, so deeply recursive workloads (parsers, JS
+    /// frameworks) don't blow out the depth of the trees.
+    pub collapse_recursion: bool,
+    /// Skips the threads whose samples all have the same stack (e.g. parked
+    /// worker pools), which would otherwise add a tree per thread without
+    /// telling anything about the workload. Android traces, whose threads
+    /// aren't sampled, never skip any.
+    pub skip_idle_threads: bool,
+}
+
+impl CallTreeOptions {
+    pub fn with_collapse_recursion(mut self, collapse_recursion: bool) -> Self {
+        self.collapse_recursion = collapse_recursion;
+        self
+    }
+
+    pub fn with_skip_idle_threads(mut self, skip_idle_threads: bool) -> Self {
+        self.skip_idle_threads = skip_idle_threads;
+        self
+    }
+}
 
 pub trait ChunkInterface {
     fn get_environment(&self) -> Option<&str>;
@@ -86,25 +180,122 @@ pub trait ChunkInterface {
     fn get_received(&self) -> f64;
     fn get_release(&self) -> Option<&str>;
     fn get_retention_days(&self) -> i32;
+    fn get_trace_context(&self) -> &TraceContext;
+    /// Returns the thread ID of the main thread, if it can be determined.
+    fn get_main_thread_id(&self) -> Option<String>;
+    fn set_organization_id(&mut self, organization_id: u64);
+    fn set_project_id(&mut self, project_id: u64);
+    fn set_received(&mut self, received: f64);
+    fn set_retention_days(&mut self, retention_days: i32);
     fn call_trees(
         &mut self,
         active_thread_id: Option<&str>,
+    ) -> Result<CallTreesStr<'_>, CallTreeError> {
+        self.call_trees_with_options(active_thread_id, CallTreeOptions::default())
+    }
+    fn call_trees_with_options(
+        &mut self,
+        active_thread_id: Option<&str>,
+        options: CallTreeOptions,
     ) -> Result<CallTreesStr<'_>, CallTreeError>;
 
     fn duration_ms(&self) -> u64;
     fn end_timestamp(&self) -> f64;
     fn start_timestamp(&self) -> f64;
+
+    /// Converts a call tree timestamp to a UTC Unix timestamp in nanoseconds.
+    /// Chunk call trees are already timestamped from the Unix epoch (android
+    /// events, relative to the chunk timestamp, are offset when building them).
+    fn absolute_timestamp_ns(&self, timestamp_ns: u64) -> u64 {
+        timestamp_ns
+    }
     fn sdk_name(&self) -> Option<&str>;
     fn sdk_version(&self) -> Option<&str>;
 
+    fn get_measurements(&self) -> Option<&Measurements>;
+
+    /// Returns the unique frames of the chunk (built from methods, for
+    /// android chunks), in the order `override_frames` indexes them.
+    fn frames(&self) -> Vec<Cow<'_, Frame>>;
+
+    /// Applies overrides to the frames at the given indices of `frames`.
+    /// No frame is modified if any of the overrides can't be applied.
+    fn override_frames(
+        &mut self,
+        overrides: &[(usize, FrameOverrides)],
+    ) -> Result<(), FrameOverrideError>;
+
+    /// Returns the measurement with the given name (e.g. "cpu_usage"),
+    /// or None if it wasn't collected or is malformed.
+    fn measurement(&self, name: &str) -> Option<&Measurement> {
+        self.get_measurements()?.get(name)
+    }
+
+    /// Returns the number of events fixed up while building the last call
+    /// trees (orphaned exits, frames closed without an exit event), by
+    /// thread ID. Only android trace chunks need fixing up.
+    fn fixed_up_events(&self) -> HashMap<String, u64> {
+        HashMap::new()
+    }
+
+    /// Returns the number of samples of the chunk (or of events, for
+    /// android trace chunks), to put the number of repairs in perspective.
+    fn sample_count(&self) -> usize;
+
+    /// Returns the number of stacks of the chunk. Android trace chunks,
+    /// recording events rather than stacks, have none.
+    fn stack_count(&self) -> usize;
+
+    /// Returns the names of the threads of the chunk, by thread ID. Unnamed
+    /// threads are left out.
+    fn thread_names(&self) -> HashMap<String, String>;
+
+    /// Returns the UTC Unix timestamps in nanoseconds of the samples of the
+    /// chunk, by thread ID. Android traces without samples report the events
+    /// entering methods instead.
+    fn sample_timestamps(&self) -> HashMap<String, Vec<u64>>;
+
+    /// Returns the number of timestamps repaired while building the call
+    /// trees, the clock of the profiler going backwards.
+    fn repaired_timestamps(&self) -> u64 {
+        0
+    }
+
+    /// Returns the number of stacks truncated at the maximum depth while
+    /// building the last call trees.
+    fn truncated_stacks(&self) -> u64 {
+        0
+    }
+
+    /// Returns the number of malformed samples dropped while building the
+    /// last call trees.
+    fn malformed_samples(&self) -> u64 {
+        0
+    }
+
+    /// Returns the number of idle threads skipped while building the last
+    /// call trees, see [`CallTreeOptions::skip_idle_threads`].
+    fn skipped_idle_threads(&self) -> u64 {
+        0
+    }
+
     fn storage_path(&self) -> String;
 
-    fn normalize(&mut self);
+    fn normalize(&mut self) {
+        self.normalize_with_rules(None);
+    }
+
+    /// Normalizes the chunk, the in_app rules of the project taking
+    /// precedence over the in_app heuristics and organization overrides.
+    fn normalize_with_rules(&mut self, in_app_rules: Option<&InAppRules>);
 
     /// Serialize the given data structure as a JSON byte vector.
     fn to_json_vec(&self) -> Result<Vec<u8>, serde_json::Error>;
 
     fn as_any(&self) -> &dyn Any;
+
+    /// Returns a copy of the chunk.
+    fn clone_box(&self) -> Box<dyn ChunkInterface + Send + Sync>;
 }
 
 #[pyclass]
@@ -220,6 +411,16 @@ pub trait ProfileInterface {
     fn get_release(&self) -> Option<&str>;
     fn get_retention_days(&self) -> i32;
     fn get_timestamp(&self) -> DateTime<Utc>;
+
+    /// Converts a call tree timestamp, relative to the profile timestamp,
+    /// to a UTC Unix timestamp in nanoseconds.
+    fn absolute_timestamp_ns(&self, timestamp_ns: u64) -> u64 {
+        let start_ns = self
+            .get_timestamp()
+            .timestamp_nanos_opt()
+            .unwrap_or_default();
+        start_ns.max(0) as u64 + timestamp_ns
+    }
     fn normalize(&mut self);
     fn call_trees(&mut self) -> Result<CallTreesU64, CallTreeError>;
     fn storage_path(&self) -> String;
@@ -229,15 +430,28 @@ pub trait ProfileInterface {
     fn get_transaction(&self) -> Cow<'_, Transaction>;
     /// Returns the thread ID of the main thread, if it can be determined.
     fn get_main_thread_id(&self) -> Option<u64>;
+    /// Returns the names of the threads of the profile, by thread ID.
+    /// Unnamed threads are left out.
+    fn thread_names(&self) -> HashMap<String, String>;
     fn get_transaction_tags(&self) -> &HashMap<String, String>;
     fn get_debug_meta(&self) -> &DebugMeta;
-    fn get_measurements(&self) -> Option<&HashMap<String, Measurement>>;
+    fn get_measurements(&self) -> Option<&Measurements>;
+
+    /// Returns the unique frames of the profile (built from methods, for
+    /// android profiles).
+    fn frames(&self) -> Vec<Cow<'_, Frame>>;
     fn is_sampled(&self) -> bool;
     fn set_profile_id(&mut self, profile_id: String);
+    fn set_organization_id(&mut self, organization_id: u64);
+    fn set_project_id(&mut self, project_id: u64);
+    fn set_received(&mut self, received: i64);
+    fn set_retention_days(&mut self, retention_days: i32);
     fn get_metadata(&self) -> Metadata;
 
     /// Serialize the given data structure as a JSON byte vector.
     fn to_json_vec(&self) -> Result<Vec<u8>, serde_json::Error>;
 
+    /// Returns the profile as `Any`, for tests to downcast it to its format.
+    #[cfg_attr(not(test), allow(dead_code))]
     fn as_any(&self) -> &dyn Any;
 }
@@ -1,19 +1,94 @@
-use std::io;
+use std::{io, ops::RangeInclusive};
 
 use lz4::{Decoder, EncoderBuilder};
 
-pub(crate) fn decompress_lz4(source: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+/// Magic number starting zstd frames, see RFC 8878.
+const ZSTD_MAGIC_NUMBER: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Codecs payloads can be compressed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Codec {
+    Lz4,
+    Zstd,
+}
+
+impl Codec {
+    pub(crate) fn from_name(name: &str) -> Result<Self, io::Error> {
+        match name {
+            "lz4" => Ok(Codec::Lz4),
+            "zstd" => Ok(Codec::Zstd),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown codec {name:?}, expected \"lz4\" or \"zstd\""),
+            )),
+        }
+    }
+
+    fn default_level(self) -> i32 {
+        match self {
+            Codec::Lz4 => 9,
+            Codec::Zstd => zstd::DEFAULT_COMPRESSION_LEVEL,
+        }
+    }
+
+    fn levels(self) -> RangeInclusive<i32> {
+        match self {
+            Codec::Lz4 => 0..=16,
+            Codec::Zstd => zstd::compression_level_range(),
+        }
+    }
+}
+
+/// Decompresses a payload compressed with any of the [`Codec`]s, telling
+/// them apart by their magic number.
+pub(crate) fn decompress(source: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+    if source.starts_with(&ZSTD_MAGIC_NUMBER) {
+        return zstd::decode_all(source);
+    }
+    decompress_lz4(source)
+}
+
+fn decompress_lz4(source: &[u8]) -> Result<Vec<u8>, std::io::Error> {
     let mut decoder = Decoder::new(source)?;
     let mut decoded_data: Vec<u8> = vec![];
     io::copy(&mut decoder, &mut decoded_data)?;
     Ok(decoded_data)
 }
 
-pub(crate) fn compress_lz4(source: &mut &[u8]) -> Result<Vec<u8>, std::io::Error> {
-    let b: Vec<u8> = vec![];
+/// Compresses a payload with the codec, at its default level if `level` is
+/// `None`. The output buffer is preallocated with `size_hint` bytes, if set.
+pub(crate) fn compress(
+    source: &[u8],
+    codec: Codec,
+    level: Option<i32>,
+    size_hint: Option<usize>,
+) -> Result<Vec<u8>, std::io::Error> {
+    let level = level.unwrap_or(codec.default_level());
+    if !codec.levels().contains(&level) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "invalid {codec:?} compression level {level}, expected {}..={}",
+                codec.levels().start(),
+                codec.levels().end()
+            ),
+        ));
+    }
+    let b: Vec<u8> = Vec::with_capacity(size_hint.unwrap_or_default());
+    match codec {
+        Codec::Lz4 => compress_lz4(&mut &source[..], b, level as u32),
+        Codec::Zstd => {
+            let mut encoder = zstd::Encoder::new(b, level)?;
+            io::copy(&mut &source[..], &mut encoder)?;
+            encoder.finish()
+        }
+    }
+}
+
+fn compress_lz4(source: &mut &[u8], b: Vec<u8>, level: u32) -> Result<Vec<u8>, std::io::Error> {
     let mut encoder = EncoderBuilder::new()
         .block_checksum(lz4::liblz4::BlockChecksum::NoBlockChecksum)
-        .level(9)
+        .level(level)
         .build(b)?;
     io::copy(source, &mut encoder)?;
     let (compressed_data, res) = encoder.finish();
@@ -1,9 +1,10 @@
-from typing import List, Optional, Union
+from typing import Any, Callable, Dict, List, Optional, Tuple
 
 class Profile:
     """
     This is a Profile class
     """
+
     def normalize(self) -> None:
         """
         Applies the various normalization steps,
@@ -28,7 +29,7 @@ class Profile:
             int: The organization ID to which the profile belongs.
         """
         ...
-    
+
     def get_platform(self) -> str:
         """
         Returns the profile platform.
@@ -46,7 +47,7 @@ class Profile:
             int: The project ID to which the profile belongs.
         """
         ...
-    
+
     def get_received(self) -> int:
         """
         Returns the received timestamp.
@@ -55,7 +56,7 @@ class Profile:
             int: The received timestamp.
         """
         ...
-    
+
     def get_release(self) -> Optional[str]:
         """
         Returns the release.
@@ -89,7 +90,55 @@ class Profile:
         """
         ...
 
-    def get_transaction(self) -> "Transaction":
+    def set_organization_id(self, organization_id: int) -> None:
+        """
+        Sets the organization ID.
+
+        Args:
+            organization_id (int): The ID of the organization to which the profile belongs.
+
+        Example:
+            >>> profile.set_organization_id(1)
+        """
+        ...
+
+    def set_project_id(self, project_id: int) -> None:
+        """
+        Sets the project ID.
+
+        Args:
+            project_id (int): The ID of the project to which the profile belongs.
+
+        Example:
+            >>> profile.set_project_id(1)
+        """
+        ...
+
+    def set_received(self, received: int) -> None:
+        """
+        Sets the received timestamp.
+
+        Args:
+            received (int): The Unix timestamp, in seconds, at which the profile was received.
+
+        Example:
+            >>> profile.set_received(1710805688)
+        """
+        ...
+
+    def set_retention_days(self, retention_days: int) -> None:
+        """
+        Sets the retention days.
+
+        Args:
+            retention_days (int): The number of days the profile is retained.
+
+        Example:
+            >>> profile.set_retention_days(90)
+        """
+        ...
+
+    def get_transaction(self) -> Transaction:
         """
         Returns the transaction information associated with the profile.
 
@@ -99,7 +148,7 @@ class Profile:
         """
         ...
 
-    def get_metadata(self) -> "Metadata":
+    def get_metadata(self) -> Metadata:
         """
         Returns metadata information associated with the profile.
 
@@ -120,7 +169,7 @@ class Profile:
             int: The retention days.
         """
         ...
-    
+
     def duration_ns(self) -> int:
         """
         Returns the duration of the profile in ns.
@@ -142,6 +191,25 @@ class Profile:
         """
         ...
 
+    def absolute_timestamp(self, timestamp_ns: int) -> float:
+        """
+        Converts a call tree timestamp to a UTC Unix timestamp.
+
+        Node timestamps are relative to the profile timestamp, this lines
+        them up with logs and spans from other tools.
+
+        Args:
+            timestamp_ns (int): The call tree timestamp, in nanoseconds.
+
+        Returns:
+            float: The Unix timestamp, in seconds.
+
+        Example:
+            >>> example = profile.extract_functions_metrics(1, False, max_examples=1)[0].get_examples()[0]
+            >>> profile.absolute_timestamp(example.get_start_ns())
+        """
+        ...
+
     def sdk_name(self) -> Optional[str]:
         """
         Returns the SDK name.
@@ -151,7 +219,7 @@ class Profile:
                 or None, if version is not available.
         """
         ...
-    
+
     def sdk_version(self) -> Optional[str]:
         """
         Returns the SDK version.
@@ -161,7 +229,7 @@ class Profile:
                 or None, if version is not available.
         """
         ...
-    
+
     def storage_path(self) -> str:
         """
         Returns the storage path of the profile.
@@ -190,8 +258,20 @@ class Profile:
             ...     binary_file.write(compressed_profile)
         """
         ...
-    
-    def extract_functions_metrics(self, min_depth: int, filter_system_frames: bool, max_unique_functions: Optional[int] = None, filter_non_leaf_functions: bool = True, generate_stack_fingerprints: bool = False) -> List["CallTreeFunction"]:
+
+    def extract_functions_metrics(
+        self,
+        min_depth: int,
+        filter_system_frames: bool,
+        max_unique_functions: Optional[int] = None,
+        filter_non_leaf_functions: bool = True,
+        generate_stack_fingerprints: bool = False,
+        max_examples: int = 0,
+        active_thread_only: bool = False,
+        max_lines_per_function: int = 0,
+        min_sample_count: int = 2,
+        sort_by: str = "self_time",
+    ) -> List[CallTreeFunction]:
         """
         Extracts function metrics from the profile.
 
@@ -210,6 +290,17 @@ class Profile:
                 If `False`, all functions including non-leaf functions with zero self-time will be included.
                 Defaults to `True`.
             generate_stack_fingerprints (bool): If `True`, the fingerprint of the stack up to the current function and the parent function's fingerprint will be generated.
+            max_examples (int): The maximum number of examples, the function calls with the longest self time,
+                to record for each function. Defaults to `0`.
+            active_thread_only (bool): If `True`, only functions sampled on the transaction's active thread are aggregated,
+                so background workers don't skew application metrics. Defaults to `False`.
+            min_sample_count (int): The minimum number of samples of the functions returned, to reduce the amount of
+                data. Lower it to keep short functions of platforms sampled at a low frequency (e.g. PHP at 10Hz).
+                The number of functions dropped is returned by :meth:`get_filtered_functions`. Defaults to `2`.
+            sort_by (str): What the functions are ranked by, in decreasing order, before keeping the top
+                `max_unique_functions`: "self_time" (their sum of self times), "sample_count" or "total_time" (their
+                sum of total times, see :meth:`CallTreeFunction.get_sum_total_time_ns`). Ties are broken by increasing
+                fingerprint, so the order is stable. Defaults to `"self_time"`.
 
         Returns:
             list[CallTreeFunction]: A list of CallTreeFunction objects, each containing metrics for a function in the call tree.
@@ -224,7 +315,11 @@ class Profile:
         """
         ...
 
-    def find_occurrences(self) -> "Occurrences":
+    def find_occurrences(
+        self,
+        detect_once_per_profile: bool = False,
+        recursion_depth_threshold: Optional[int] = None,
+    ) -> Occurrences:
         """
         Finds performance issues (occurrences) in the profile.
 
@@ -252,17 +347,136 @@ class Profile:
         """
         ...
 
+    def app_start_prelude(
+        self, window_ms: int = 2000, min_duration_ms: Optional[int] = None
+    ) -> Optional[AppStartPrelude]:
+        """
+        Analyzes the app start prelude of the profile: the work done on the
+        main thread before `main` runs, by dyld loading images and by the
+        Objective-C runtime running `+load` methods and static initializers.
+
+        Time is attributed to the innermost prelude frame of each stack, within
+        the first `window_ms` of the profile.
+
+        Args:
+            window_ms (int): The duration of the app start window, from the start of the profile, in milliseconds.
+                Defaults to `2000`.
+            min_duration_ms (int): If provided, a "slow app start prelude" occurrence is generated
+                when the prelude lasted at least this long (see :meth:`AppStartPrelude.get_occurrence`).
+
+        Returns:
+            :class:`AppStartPrelude` | None
+                The prelude, or `None` if the platform isn't supported (only cocoa is)
+                or the main thread is unknown.
+
+        Raises:
+            pyo3.exceptions.PyException: If an error occurs while building the call trees.
+
+        Example:
+            >>> prelude = profile.app_start_prelude(min_duration_ms=400)
+            >>> if prelude is not None and prelude.get_occurrence() is not None:
+            ...     send_occurrence(prelude.get_occurrence())
+        """
+        ...
+
+    def calibrate_detectors(
+        self,
+        detect_once_per_profile: bool = False,
+        recursion_depth_threshold: Optional[int] = None,
+    ) -> CalibrationStats:
+        """
+        Runs every detector of the platform of the profile, including the
+        disabled ones, without reporting occurrences: only the stats of the
+        occurrences they would have created are returned, to tune thresholds
+        on production traffic before enabling a detector.
+
+        Args:
+            detect_once_per_profile (bool): Whether to only count an issue
+                once when it's detected more than once in the profile, as in
+                :meth:`find_occurrences`. Defaults to False.
+
+        Returns:
+            :class:`CalibrationStats`
+                The number of occurrences and the histogram of their durations,
+                by category.
+
+        Raises:
+            pyo3.exceptions.PyException: If an error occurs during the detection process.
+        """
+        ...
+
+    def top_frames(self, n: int = 10) -> List[TopFrame]:
+        """
+        Returns the frames the profile spent the most self time in, across
+        threads.
+
+        Unlike :meth:`extract_functions_metrics`, frames are ranked whatever
+        their depth and whether they're app frames or not, making it a
+        lighter-weight alternative for the header of the profile summary.
+
+        Args:
+            n (int): The number of frames to return. Defaults to `10`.
+
+        Returns:
+            list[:class:`TopFrame`]
+                The frames, by decreasing self time.
+
+        Raises:
+            pyo3.exceptions.PyException: If an error occurs while building the call trees.
+
+        Example:
+            >>> for frame in profile.top_frames(5):
+            ...     print(frame.get_function(), frame.get_self_time_ns())
+        """
+        ...
+
+    def is_obfuscated(self) -> bool:
+        """
+        Returns whether the profile looks ProGuard-obfuscated, most of its app
+        frames having class names renamed to a letter or two (`a`, `b`, ...,
+        `aa`, ...), so ingest can tag it and the UI can suggest uploading a
+        mapping file.
+
+        Only frames of platforms supporting deobfuscation (android, java)
+        are considered.
+
+        Returns:
+            bool
+                True if the profile looks obfuscated, False otherwise.
+
+        Example:
+            >>> if profile.is_obfuscated():
+            ...     tags["obfuscated"] = "true"
+        """
+        ...
+
+    def get_filtered_functions(self) -> int:
+        """
+        Returns the number of functions dropped by the last call to
+        :meth:`extract_functions_metrics` for having fewer samples than its
+        `min_sample_count`, to monitor the data lost.
+
+        Returns:
+            int
+                The number of functions dropped. 0 if no function metrics were
+                extracted yet.
+        """
+        ...
+
 class ProfileChunk:
     """
     This is a ProfileChunk class
     """
-    def normalize(self) -> None:
+
+    def normalize(
+        self, in_app_rules: Optional[Dict[str, Dict[str, List[str]]]] = None
+    ) -> None:
         """
         Applies the various normalization steps,
         depending on the profile's platform.
         """
         ...
-    
+
     def get_environment(self) -> Optional[str]:
         """
         Returns the environment.
@@ -271,7 +485,7 @@ class ProfileChunk:
             str: The environment, or None, if release is not available.
         """
         ...
-    
+
     def get_chunk_id(self) -> str:
         """
         Returns the profile chunk ID.
@@ -280,7 +494,7 @@ class ProfileChunk:
             str: The profile chunk ID.
         """
         ...
-    
+
     def get_organization_id(self) -> int:
         """
         Returns the organization ID.
@@ -289,7 +503,7 @@ class ProfileChunk:
             int: The organization ID to which the profile belongs.
         """
         ...
-    
+
     def get_platform(self) -> str:
         """
         Returns the profile platform.
@@ -298,7 +512,7 @@ class ProfileChunk:
             str: The profile's platform.
         """
         ...
-    
+
     def get_profiler_id(self) -> str:
         """
         Returns the profiler ID.
@@ -307,7 +521,7 @@ class ProfileChunk:
             str: The profile ID of the profile chunk.
         """
         ...
-    
+
     def get_project_id(self) -> int:
         """
         Returns the project ID.
@@ -316,7 +530,7 @@ class ProfileChunk:
             int: The project ID to which the profile belongs.
         """
         ...
-    
+
     def get_received(self) -> float:
         """
         Returns the received timestamp.
@@ -325,7 +539,7 @@ class ProfileChunk:
             float: The received timestamp.
         """
         ...
-    
+
     def get_release(self) -> Optional[str]:
         """
         Returns the release.
@@ -335,7 +549,7 @@ class ProfileChunk:
                 or None, if release is not available.
         """
         ...
-    
+
     def get_retention_days(self) -> int:
         """
         Returns the retention days.
@@ -344,7 +558,55 @@ class ProfileChunk:
             int: The retention days.
         """
         ...
-    
+
+    def set_organization_id(self, organization_id: int) -> None:
+        """
+        Sets the organization ID.
+
+        Args:
+            organization_id (int): The ID of the organization to which the profile belongs.
+
+        Example:
+            >>> profile_chunk.set_organization_id(1)
+        """
+        ...
+
+    def set_project_id(self, project_id: int) -> None:
+        """
+        Sets the project ID.
+
+        Args:
+            project_id (int): The ID of the project to which the profile belongs.
+
+        Example:
+            >>> profile_chunk.set_project_id(1)
+        """
+        ...
+
+    def set_received(self, received: float) -> None:
+        """
+        Sets the received timestamp.
+
+        Args:
+            received (float): The Unix timestamp, in seconds, at which the profile was received.
+
+        Example:
+            >>> profile_chunk.set_received(1710805688.237)
+        """
+        ...
+
+    def set_retention_days(self, retention_days: int) -> None:
+        """
+        Sets the retention days.
+
+        Args:
+            retention_days (int): The number of days the profile is retained.
+
+        Example:
+            >>> profile_chunk.set_retention_days(90)
+        """
+        ...
+
     def duration_ms(self) -> int:
         """
         Returns the duration of the profile in ms.
@@ -353,7 +615,7 @@ class ProfileChunk:
             int: The duration of the profile in ms.
         """
         ...
-    
+
     def start_timestamp(self) -> float:
         """
         Returns the start timestamp of the profile.
@@ -365,7 +627,7 @@ class ProfileChunk:
             float: The start timestamp of the profile.
         """
         ...
-    
+
     def end_timestamp(self) -> float:
         """
         Returns the end timestamp of the profile.
@@ -377,7 +639,26 @@ class ProfileChunk:
             float: The end timestamp of the profile.
         """
         ...
-    
+
+    def absolute_timestamp(self, timestamp_ns: int) -> float:
+        """
+        Converts a call tree timestamp to a UTC Unix timestamp.
+
+        Chunk call trees are already timestamped from the Unix epoch, this
+        lines node timestamps up with logs and spans from other tools.
+
+        Args:
+            timestamp_ns (int): The call tree timestamp, in nanoseconds.
+
+        Returns:
+            float: The Unix timestamp, in seconds.
+
+        Example:
+            >>> example = profile_chunk.extract_functions_metrics(1, False, max_examples=1)[0].get_examples()[0]
+            >>> profile_chunk.absolute_timestamp(example.get_start_ns())
+        """
+        ...
+
     def sdk_name(self) -> Optional[str]:
         """
         Returns the SDK name.
@@ -387,7 +668,7 @@ class ProfileChunk:
                 or None, if version is not available.
         """
         ...
-    
+
     def sdk_version(self) -> Optional[str]:
         """
         Returns the SDK version.
@@ -397,7 +678,7 @@ class ProfileChunk:
                 or None, if version is not available.
         """
         ...
-    
+
     def storage_path(self) -> str:
         """
         Returns the storage path of the profile.
@@ -406,8 +687,13 @@ class ProfileChunk:
             str: The storage path of the profile.
         """
         ...
-    
-    def compress(self) -> bytes:
+
+    def compress(
+        self,
+        codec: str = "lz4",
+        level: Optional[int] = None,
+        compressed_size_hint: Optional[int] = None,
+    ) -> bytes:
         """
         Compresses the profile with lz4.
 
@@ -426,8 +712,22 @@ class ProfileChunk:
             ...     binary_file.write(compressed_profile)
         """
         ...
-    
-    def extract_functions_metrics(self, min_depth: int, filter_system_frames: bool, max_unique_functions: Optional[int] = None, filter_non_leaf_functions: bool = True, generate_stack_fingerprints: bool = False) -> List["CallTreeFunction"]:
+
+    def extract_functions_metrics(
+        self,
+        min_depth: int,
+        filter_system_frames: bool,
+        max_unique_functions: Optional[int] = None,
+        filter_non_leaf_functions: bool = True,
+        generate_stack_fingerprints: bool = False,
+        max_examples: int = 0,
+        active_thread_only: bool = False,
+        max_lines_per_function: int = 0,
+        collapse_recursion: bool = False,
+        skip_idle_threads: bool = False,
+        min_sample_count: int = 2,
+        sort_by: str = "self_time",
+    ) -> List[CallTreeFunction]:
         """
         Extracts function metrics from the profile chunk.
 
@@ -446,9 +746,22 @@ class ProfileChunk:
                 If `False`, all functions including non-leaf functions with zero self-time will be included.
                 Defaults to `True`.
             generate_stack_fingerprints (bool): If `True`, the fingerprint of the stack up to the current function and the parent function's fingerprint will be generated.
+            max_examples (int): The maximum number of examples, the function calls with the longest self time,
+                to record for each function. Defaults to `0`.
+            active_thread_only (bool): If `True`, only functions sampled on the main thread are aggregated,
+                so background workers don't skew application metrics. If the main thread can't be
+                determined, no function is returned. Defaults to `False`.
+            min_sample_count (int): The minimum number of samples of the functions returned, to reduce the amount of
+                data. Lower it to keep short functions of platforms sampled at a low frequency (e.g. PHP at 10Hz).
+                The number of functions dropped is returned by :meth:`get_filtered_functions`. Defaults to `2`.
+            sort_by (str): What the functions are ranked by, in decreasing order, before keeping the top
+                `max_unique_functions`: "self_time" (their sum of self times), "sample_count" or "total_time" (their
+                sum of total times, see :meth:`CallTreeFunction.get_sum_total_time_ns`). Ties are broken by increasing
+                fingerprint, so the order is stable. Defaults to `"self_time"`.
 
         Returns:
-            list[CallTreeFunction]: A list of CallTreeFunction objects, each containing metrics for a function in the call tree.
+            list[CallTreeFunction]: A list of CallTreeFunction objects, each containing metrics for a function in the call tree,
+                including the self time of each of its calls (see :meth:`CallTreeFunction.get_self_times_ns`).
 
         Raises:
             Exception: If an error occurs during the extraction process.
@@ -460,584 +773,3758 @@ class ProfileChunk:
         """
         ...
 
-class CallTreeFunction:
-    """
-    Represents function metrics from a call tree
-    """
-    def get_fingerprint(self) -> int:
+    def cpu_spikes(
+        self, window_ms: int, threshold: float, max_stacks: int = 5
+    ) -> List[CpuSpike]:
         """
-        Returns the function fingerprint.
+        Finds the CPU usage spikes of the profile chunk.
+
+        A window of `window_ms` is slid over the `cpu_usage` measurement, and
+        windows whose average usage reaches `threshold` are reported as spikes,
+        overlapping ones being merged. Each spike is attributed to the stacks
+        the most time was spent in while it happened.
+
+        Args:
+            window_ms (int): The size of the sliding window, in milliseconds.
+            threshold (float): The minimum average CPU usage, in percent, of a spike window.
+            max_stacks (int, optional): The maximum number of stacks to attribute each spike to.
+                Defaults to `5`.
 
         Returns:
-            int: The fingerprint of the function.
+            list[CpuSpike]: The spikes, sorted by start timestamp. The list is empty if
+                the chunk has no `cpu_usage` measurement.
+
+        Raises:
+            ValueError: If the window doesn't fit in nanoseconds.
+            Exception: If an error occurs while building the call trees.
+
+        Example:
+            >>> for spike in profile_chunk.cpu_spikes(window_ms=500, threshold=90.0):
+            ...     print(spike.get_start_ns(), spike.get_stacks()[0].get_frames())
         """
         ...
-    
-    def get_parent_fingerprint(self) -> Optional[int]:
+
+    def memory_growth_suspects(
+        self, min_growth_bytes: float = 0.0, max_suspects: int = 5
+    ) -> List[MemoryGrowth]:
         """
-        Returns the parent's function fingerprint.
+        Finds the intervals during which the memory footprint of the profile
+        chunk grew, along with the functions suspected to cause the growth.
+
+        Growth intervals are runs of monotonically increasing values of the
+        `memory_footprint` measurement (or `memory_native_footprint`, on android).
+        Suspects are the functions calling allocation functions (`malloc`,
+        `posix_memalign`, `PyObject_Malloc`, `operator new`, ...) during the
+        interval, credited to their closest application caller.
+
+        Args:
+            min_growth_bytes (float, optional): The minimum growth of an interval to be reported.
+                Defaults to `0`.
+            max_suspects (int, optional): The maximum number of suspects to report per interval.
+                Defaults to `5`.
 
         Returns:
-            int: If generate_stack_fingerprints is enabled, the parent fingerprint is the fingerprint of the
-                stack up to the parent function otherwise it'll be None.
-                If filter_system_frames is enabled, the parent fingerprint is the fingerprint of the
-                closest application frame.
+            list[MemoryGrowth]: The growth intervals, sorted by start timestamp. The list is empty if
+                the chunk has no memory footprint measurement.
+
+        Raises:
+            Exception: If an error occurs while building the call trees.
+
+        Example:
+            >>> for growth in profile_chunk.memory_growth_suspects(min_growth_bytes=10_000_000):
+            ...     print(growth.get_growth_bytes(), growth.get_suspects()[0].get_function())
         """
         ...
-    
-    def get_stack_fingerprint(self) -> Optional[int]:
+
+    def map_frames(self, callback: Callable[[dict], Optional[dict]]) -> int:
         """
-        Returns the stack fingerprint.
+        Calls `callback` once per unique frame of the profile chunk and
+        applies the overrides it returns, to enrich frames (e.g. from an
+        internal symbol server, or with ownership information) in place.
+
+        The callback receives a dict with the `function`, `module`, `package`,
+        `path`, `lineno`, `in_app` and `platform` of the frame, and returns
+        either None to leave the frame untouched, or a dict of the fields to
+        override (any of the above but `platform`). Only `path`, `lineno` and
+        `in_app` can be overridden on android trace chunks, their function and
+        package names being derived from the method signatures.
+
+        Frames are only modified once the callback returned for all of them,
+        so the chunk is left untouched if it raises or returns invalid overrides.
+
+        Args:
+            callback (Callable[[dict], Optional[dict]]): The function called for each frame.
 
         Returns:
-            int: If generate_stack_fingerprints is enabled, the stack fingerprint is the fingerprint of the
-                stack up to the current function otherwise it'll be None.
+            int
+                The number of frames modified.
+
+        Raises:
+            ValueError: If the callback returns a field which can't be overridden.
+            RuntimeError: If the chunk is being modified by another thread, or
+                was modified (e.g. normalized) while the callback ran.
+
+        Example:
+            >>> def own_vendored(frame):
+            ...     if (frame["module"] or "").startswith("vendored."):
+            ...         return {"in_app": False}
+            >>> profile_chunk.map_frames(own_vendored)
         """
         ...
-    
-    def get_depth(self) -> Optional[int]:
+
+    def extract_measurement_metrics(self) -> List[MeasurementMetric]:
         """
-        Returns the depth of the function in the call tree.
+        Extracts metrics from the device impact measurements of the profile
+        chunk, to be emitted alongside its function metrics.
+
+        The values of the `cpu_usage`, `memory_footprint` and
+        `memory_native_footprint` measurements are aggregated into their
+        minimum, maximum, mean and 90th percentile.
 
         Returns:
-            int: The depth of the function in the call tree, or None if not available.
+            list[:class:`MeasurementMetric`]
+                A metric per measurement, missing or empty measurements being
+                left out.
+
+        Example:
+            >>> for metric in profile_chunk.extract_measurement_metrics():
+            ...     print(metric.get_name(), metric.get_p90())
         """
         ...
-    
-    def get_function(self) -> str:
+
+    def get_fixed_up_events(self) -> Dict[str, int]:
         """
-        Returns the function name.
+        Returns the number of events fixed up while building the call trees
+        of the profile chunk, by thread ID, to monitor malformed traces.
+
+        Android traces can have exit events without a matching enter event,
+        which are ignored, and frames without an exit event (or unwound by an
+        exception past them), which are closed at the next exit of one of their
+        callers, or at the end of the chunk.
 
         Returns:
-            str: The function name.
+            dict[str, int]
+                The number of events fixed up, by thread ID. Empty if the call
+                trees weren't built yet, or if nothing needed fixing up.
         """
         ...
-    
-    def get_package(self) -> str:
+
+    def anomaly_score(self) -> AnomalyScore:
         """
-        Returns the package name.
+        Scores how anomalous the data of the profile chunk is, so
+        data-quality dashboards and filters can exclude junk chunks. The
+        score is meant to be stored along with the chunk metadata.
+
+        The score combines the ratio of unknown frames, of events repaired or
+        fixed up while building the call trees, of stacks truncated at the
+        maximum depth, of frames still obfuscated, and the skew between the
+        clock of the device and the time the chunk was received.
 
         Returns:
-            str: The package name.
-        """
-        ...
-    
-    def get_in_app(self) -> bool:
+            :class:`AnomalyScore`
+                The score, from 0 (nothing anomalous) to 1, and its signals.
+
+        Raises:
+            pyo3.exceptions.PyException: If an error occurs while building the call trees.
+
+        Example:
+            >>> if profile_chunk.anomaly_score().get_score() > 0.5:
+            ...     print("junk chunk", profile_chunk.get_chunk_id())
         """
-        Returns whether the function is in an app or system one.
+        ...
+
+    def get_truncated_stacks(self) -> int:
+        """
+        Returns the number of stacks truncated at the maximum depth (see
+        :func:`vroomrs.configure`) while building the call trees of the
+        profile chunk, to alert on pathological payloads.
+
+        Sample stacks are truncated from the root side, keeping their
+        innermost frames, and android traces drop the frames entered past
+        the maximum depth.
 
         Returns:
-            bool: True if the function is an app one, False otherwise.
+            int
+                The number of stacks truncated. 0 if the call trees weren't
+                built yet.
         """
         ...
-    
-    def get_self_times_ns(self) -> List[int]:
+
+    def clone(self) -> ProfileChunk:
         """
-        Returns the self times in nanoseconds.
+        Returns a copy of the profile chunk, to process it from several
+        threads at once, or to modify it while keeping the original.
+
+        Copies are cheap: they share the payload of the chunk, only copied
+        once either is modified, as building call trees which weren't built
+        yet does.
 
         Returns:
-            list[int]: The self times in nanoseconds.
+            :class:`ProfileChunk`
+                A copy of the profile chunk.
+
+        Example:
+            >>> copies = [profile_chunk.clone() for _ in range(workers)]
         """
         ...
-    
-    def get_sum_self_time_ns(self) -> int:
+
+    def __copy__(self) -> ProfileChunk:
         """
-        Returns the sum of self times in nanoseconds.
+        Returns a copy of the profile chunk, see :meth:`clone`.
 
         Returns:
-            int: The sum of self times in nanoseconds.
+            :class:`ProfileChunk`
+                A copy of the profile chunk.
         """
         ...
-    
-    def get_sample_count(self) -> int:
+
+    def __deepcopy__(self, _memo: Any) -> ProfileChunk:
         """
-        Returns the sample count.
+        Returns a copy of the profile chunk, see :meth:`clone`.
+
+        Args:
+            memo (dict): The memo of :func:`copy.deepcopy`, unused as chunks
+                don't reference Python objects.
 
         Returns:
-            int: The sample count.
+            :class:`ProfileChunk`
+                A copy of the profile chunk.
         """
         ...
-    
-    def get_thread_id(self) -> str:
+
+    def symbolication_stats(self, max_pending_ratio: float = 0.0) -> SymbolicationStats:
         """
-        Returns the thread ID.
+        Returns how far symbolication of the frames of the profile chunk went,
+        to decide whether native (cocoa, windows and linux) chunks should be
+        withheld from function metrics until symbolication completes.
+
+        Frames pending symbolication are native frames only known by their
+        instruction address, which symbolicator didn't process yet. Frames it
+        failed to symbolicate aren't pending, as waiting won't fix them.
+
+        Args:
+            max_pending_ratio (float): The maximum ratio of frames pending symbolication
+                of a chunk whose function metrics are extracted right away.
+                Defaults to `0`, withholding chunks with any frame pending symbolication.
 
         Returns:
-            str: The thread ID.
+            :class:`SymbolicationStats`
+                The stats, whose `resubmit_after_symbolication` marker is set if the chunk
+                should be withheld, and resubmitted once symbolicated.
+
+        Example:
+            >>> stats = profile_chunk.symbolication_stats()
+            >>> if stats.get_resubmit_after_symbolication():
+            ...     store_metadata(profile_chunk, stats.to_json_str())
+            ... else:
+            ...     metrics = profile_chunk.extract_functions_metrics(min_depth=1, filter_system_frames=True)
         """
         ...
-    
-    def get_max_duration(self) -> int:
+
+    def activity_timeline(self, bucket_ms: int) -> ActivityTimeline:
         """
-        Returns the maximum duration in nanoseconds.
+        Counts the samples of each thread of the profile chunk per time bucket,
+        for overviews of which thread was busy when (e.g. the minimap of the UI).
+
+        Android traces without samples count the events entering methods instead.
+
+        Args:
+            bucket_ms (int): The duration of the buckets, in milliseconds.
 
         Returns:
-            int: The maximum duration in nanoseconds.
+            :class:`ActivityTimeline`
+                The sample counts of each thread per bucket, buckets covering the chunk from its start.
+
+        Example:
+            >>> timeline = profile_chunk.activity_timeline(bucket_ms=100)
+            >>> for thread_id, counts in timeline.get_threads().items():
+            ...     draw_minimap_row(thread_id, counts)
         """
         ...
-    
-    def get_total_times_ns(self) -> List[int]:
+
+    def export_otel_spans(self, top_n: int = 10) -> List[OtelSpan]:
         """
-        Returns the total times in nanoseconds.
+        Exports the longest call tree nodes of the profile chunk as
+        OpenTelemetry spans, for profiling insights to be piped into OTel
+        pipelines.
+
+        Spans of the same profiler session share a trace ID, derived from the
+        profiler ID. A span is parented to the span of the closest ancestor of
+        its node which was also exported, and carries attributes identifying
+        the chunk it comes from (see :meth:`OtelSpan.get_attributes`).
+
+        Args:
+            top_n (int): The number of nodes to export, the longest ones.
+                Defaults to `10`.
 
         Returns:
-            list[int]: The total times in nanoseconds.
+            list[:class:`OtelSpan`]
+                The spans, sorted by start time.
+
+        Raises:
+            pyo3.exceptions.PyException: If an error occurs while building the call trees.
+
+        Example:
+            >>> spans = [json.loads(span.to_json_str()) for span in profile_chunk.export_otel_spans(top_n=20)]
+            >>> export_request = {"resourceSpans": [{"scopeSpans": [{"spans": spans}]}]}
         """
         ...
 
-class Occurrence:
-    """
-    Represents a detected performance issue (occurrence) in a profile.
+    def gc_durations_ns(self) -> Dict[str, int]:
+        """
+        Returns the time each thread of the profile chunk spent collecting
+        garbage, for runtimes whose garbage collection frames are folded into
+        the synthetic `Garbage Collection` frame on normalization (javascript,
+        node, android and java).
 
-    An occurrence is a specific instance of a performance problem detected through
-    profile analysis. It contains detailed information about the issue, including
-    the problematic function, stack trace, evidence, and metadata for issue tracking.
-    """
-    
-    def get_culprit(self) -> str:
+        Returns:
+            dict[str, int]
+                The garbage collection time in nanoseconds, by thread ID, for the
+                threads which spent any.
+
+        Raises:
+            pyo3.exceptions.PyException: If an error occurs while building the call trees.
+
+        Example:
+            >>> profile_chunk.normalize()
+            >>> gc_ns = sum(profile_chunk.gc_durations_ns().values())
         """
-        Returns the culprit (transaction name) where the issue occurred.
+        ...
+
+    def get_trace_id(self) -> Optional[str]:
+        """
+        Returns the ID of the trace the profile chunk was collected during,
+        if the SDK sent it.
 
         Returns:
-            str: The name of the transaction or main operation where the issue occurred.
+            str
+                The trace ID, or None.
         """
         ...
 
-    def get_detection_time(self) -> str:
+    def get_transaction_id(self) -> Optional[str]:
         """
-        Returns the detection time as an RFC 3339 formatted string.
+        Returns the ID of the transaction the profile chunk was collected
+        during, if the SDK sent it.
 
         Returns:
-            str: The detection time in RFC 3339 format.
+            str
+                The transaction ID, or None.
         """
         ...
 
-    def get_event(self) -> "Event":
+    def get_segment_ids(self) -> List[str]:
         """
-        Returns the event data.
+        Returns the IDs of the segments (root spans) overlapping the profile
+        chunk, to join it to its spans.
 
         Returns:
-            Event: Event data including platform, stack trace, and debug information.
+            list[str]
+                The segment IDs, empty if the SDK sent none.
         """
         ...
 
-    def get_evidence_data(self) -> "EvidenceData":
+    def sampling_interval_ns(self) -> Optional[int]:
         """
-        Returns the evidence data.
+        Returns the sampling interval of the profile chunk, estimated from the
+        timestamps of its samples: the median of the medians of the deltas
+        between the consecutive samples of each thread.
 
         Returns:
-            EvidenceData: Structured data about the performance issue.
+            int
+                The estimated sampling interval in nanoseconds, or None for chunks with
+                fewer than 2 samples per thread and for android traces, whose events aren't sampled.
         """
         ...
 
-    def get_evidence_display(self) -> List["Evidence"]:
+    def sample_rate(self) -> Optional[float]:
         """
-        Returns the evidence display list.
+        Returns the effective sampling frequency of the profile chunk, derived
+        from its estimated sampling interval.
 
         Returns:
-            list[Evidence]: Human-readable evidence for displaying the issue.
+            float
+                The estimated sampling frequency in Hz, or None if the sampling interval
+                can't be estimated.
+
+        Example:
+            >>> if (sample_rate := profile_chunk.sample_rate()) is not None:
+            ...     record_sample_rate(profile_chunk.sdk_name(), sample_rate)
         """
         ...
 
-    def get_fingerprint(self) -> List[str]:
+    def has_off_nominal_sample_rate(self) -> bool:
         """
-        Returns the fingerprint list.
+        Returns whether the estimated sampling interval of the profile chunk
+        deviates by a factor of more than 2 from the interval of the nominal
+        sampling frequency of the SDKs, 101Hz.
 
         Returns:
-            list[str]: Unique identifiers for grouping similar issues.
+            bool
+                True if the chunk wasn't sampled at the nominal frequency, False if it was
+                or if its sampling interval can't be estimated.
         """
         ...
 
-    def get_id(self) -> str:
+    def get_skipped_idle_threads(self) -> int:
         """
-        Returns the occurrence ID.
+        Returns the number of idle threads, whose samples all have the same
+        stack, skipped while building the call trees of the profile chunk
+        with `skip_idle_threads` (see :meth:`extract_functions_metrics`).
+
+        Android traces, whose threads aren't sampled, never skip any.
 
         Returns:
-            str: Unique identifier for this specific occurrence.
+            int
+                The number of threads skipped. 0 if the call trees weren't
+                built yet, or were built without skipping idle threads.
         """
         ...
 
-    def get_issue_title(self) -> str:
+    def call_trees_to_msgpack(self) -> bytes:
         """
-        Returns the issue title.
+        Serializes the call trees of the profile chunk to MessagePack.
+
+        The payload is a map of the root nodes of each thread, by thread ID,
+        each node holding its fields (``name``, ``package``, ``start_ns``,
+        ``duration_ns``, ``frame``...) and its ``children`` nodes.
 
         Returns:
-            str: Human-readable title describing the type of issue.
+            bytes
+                The MessagePack encoding of the call trees.
+
+        Raises:
+            pyo3.exceptions.PyException: If an error occurs while building the call trees.
+            ValueError: If the serialization fails due to invalid data.
+
+        Example:
+            >>> payload = profile_chunk.call_trees_to_msgpack()
         """
         ...
 
-    def get_level(self) -> str:
+    def to_arrow(self) -> bytes:
         """
-        Returns the severity level.
+        Exports the samples of the profile chunk as an Arrow record batch, in
+        the Arrow IPC streaming format.
+
+        Samples are flattened into a row per frame, with the columns
+        ``thread_id``, ``timestamp`` (UTC, in nanoseconds), ``depth`` (0 for
+        the root frame), ``function``, ``package``, ``in_app`` and
+        ``duration_ns`` (the time until the next sample of the thread).
 
         Returns:
-            str: Severity level of the issue (e.g., "info", "warning", "error").
+            bytes
+                The record batch, in the Arrow IPC streaming format.
+
+        Raises:
+            ValueError: If the chunk is in the legacy android trace format, which has no samples,
+                or if a sample references a missing stack or frame.
+
+        Example:
+            >>> table = pyarrow.ipc.open_stream(profile_chunk.to_arrow()).read_all()
+            >>> pyarrow.parquet.write_table(table, "samples.parquet")
         """
         ...
 
-    def get_payload_type(self) -> str:
+    def slice(self, start_ts: float, end_ts: float) -> ProfileChunk:
         """
-        Returns the payload type.
+        Returns a new profile chunk with only the samples within a time window,
+        e.g. to build the flamegraph of a span.
+
+        The chunk only keeps the frames and stacks used by the samples of the
+        window, and the measurement values within it. As in any chunk, the last
+        sample of each thread has no duration.
+
+        Args:
+            start_ts (float): The start of the window (inclusive), a Unix timestamp in seconds.
+            end_ts (float): The end of the window (exclusive), a Unix timestamp in seconds.
 
         Returns:
-            str: Type of payload, typically "occurrence".
+            :class:`ProfileChunk`
+                The chunk of the samples within the window, with the same chunk ID.
+
+        Raises:
+            ValueError: If a timestamp is negative or not finite, or if the chunk is in the legacy
+                android trace format, whose events can't be sliced.
+
+        Example:
+            >>> span_chunk = profile_chunk.slice(span["start_timestamp"], span["timestamp"])
         """
         ...
 
-    def get_project_id(self) -> int:
+    def call_trees_for_interval(
+        self, thread_id: str, start_ns: int, end_ns: int
+    ) -> Any:
         """
-        Returns the project ID.
+        Returns the call tree of a thread during a time interval, e.g. to show
+        what was running during a span, without transferring the whole chunk.
+
+        The tree is built from the samples of the thread within the interval
+        (see :meth:`slice`), each node holding its fields (``name``,
+        ``package``, ``start_ns``, ``duration_ns``, ``frame``...) and its
+        ``children`` nodes.
+
+        Args:
+            thread_id (str): The ID of the thread.
+            start_ns (int): The start of the interval (inclusive), a Unix timestamp in nanoseconds.
+            end_ns (int): The end of the interval (exclusive), a Unix timestamp in nanoseconds.
 
         Returns:
-            int: ID of the project where the issue was detected.
+            list[dict[str, Any]]
+                The root nodes of the call tree, in chronological order, empty if the thread
+                wasn't sampled during the interval.
+
+        Raises:
+            ValueError: If the chunk is in the legacy android trace format, whose events can't be
+                sliced, or if a sample references a missing stack or frame.
+
+        Example:
+            >>> roots = profile_chunk.call_trees_for_interval(span["thread_id"], start_ns, end_ns)
         """
         ...
 
-    def get_resource_id(self) -> Optional[str]:
+    def deobfuscation_ratio(self) -> Optional[float]:
         """
-        Returns the resource ID.
+        Returns the ratio of the frames of the profile chunk which went through
+        deobfuscation that were found in the ProGuard mapping file, to measure
+        how many mappings are missing.
+
+        Frames not found in the mapping file (whose deobfuscation status is
+        `missing`) are excluded from the function metrics.
 
         Returns:
-            str: Optional resource identifier, or None if not available.
+            float
+                The ratio of deobfuscated frames, from 0 to 1, or None if no frame went
+                through deobfuscation (no mapping file specified, or not an android chunk).
+
+        Example:
+            >>> if (ratio := profile_chunk.deobfuscation_ratio()) is not None:
+            ...     record_deobfuscation_ratio(profile_chunk.get_project_id(), ratio)
         """
         ...
 
-    def get_subtitle(self) -> str:
+    def get_filtered_functions(self) -> int:
         """
-        Returns the subtitle.
+        Returns the number of functions dropped by the last call to
+        :meth:`extract_functions_metrics` for having fewer samples than its
+        `min_sample_count`, to monitor the data lost.
 
         Returns:
-            str: Brief description, usually the function name where the issue occurred.
+            int
+                The number of functions dropped. 0 if no function metrics were
+                extracted yet.
         """
         ...
 
-    def get_type(self) -> int:
+    def stats(self) -> ProcessingStats:
         """
-        Returns the issue type.
+        Returns statistics on the processing of the profile chunk (its sizes,
+        the repairs made building its call trees, and how long parsing and
+        building took), to log and monitor them.
+
+        The call trees are built if they weren't yet, the repair counters and
+        build time being those of the call trees last built.
 
         Returns:
-            int: Numeric type identifier for the issue category.
+            :class:`ProcessingStats`
+                The processing stats of the chunk.
+
+        Raises:
+            pyo3.exceptions.PyException: If an error occurs while building the call trees.
+
+        Example:
+            >>> logger.info("chunk processed", extra=json.loads(profile_chunk.stats().to_json_str()))
         """
         ...
 
-    def get_category(self) -> str:
+class CallTreeFunction:
+    """
+    Represents function metrics from a call tree
+    """
+
+    def get_fingerprint(self) -> int:
         """
-        Returns the category name.
+        Returns the function fingerprint.
 
         Returns:
-            str: Category name for the performance issue.
+            int: The fingerprint of the function.
         """
         ...
 
-    def get_duration_ns(self) -> int:
+    def get_parent_fingerprint(self) -> Optional[int]:
         """
-        Returns the duration in nanoseconds.
+        Returns the parent's function fingerprint.
 
         Returns:
-            int: Duration of the problematic operation in nanoseconds.
+            int: If generate_stack_fingerprints is enabled, the parent fingerprint is the fingerprint of the
+                stack up to the parent function otherwise it'll be None.
+                If filter_system_frames is enabled, the parent fingerprint is the fingerprint of the
+                closest application frame.
         """
         ...
 
-    def get_sample_count(self) -> int:
+    def get_stack_fingerprint(self) -> Optional[int]:
         """
-        Returns the sample count.
+        Returns the stack fingerprint.
 
         Returns:
-            int: Number of samples where this issue was detected.
+            int: If generate_stack_fingerprints is enabled, the stack fingerprint is the fingerprint of the
+                stack up to the current function otherwise it'll be None.
         """
         ...
 
-    def to_json_str(self) -> str:
+    def get_depth(self) -> Optional[int]:
         """
-        Serializes the occurrence to a JSON string.
+        Returns the depth of the function in the call tree.
 
         Returns:
-            str: A JSON string representation of the occurrence.
+            int: The depth of the function in the call tree, or None if not available.
+        """
+        ...
 
-        Raises:
-            ValueError: If the serialization fails due to invalid data.
+    def get_function(self) -> str:
+        """
+        Returns the function name.
 
-        Example:
-            >>> occurrence = occurrences.occurrences[0]
-            >>> json_str = occurrence.to_json_str()
-            >>> print(json_str)
+        Returns:
+            str: The function name.
         """
         ...
 
-class Occurrences:
-    """
-    A wrapper class containing a list of Occurrence objects.
-    
-    This class wraps the results of occurrence detection, providing access to
-    the detected performance issues through the occurrences attribute.
-    """
-    occurrences: List[Occurrence]
-    
-    def to_json_str(self) -> str:
+    def get_package(self) -> str:
         """
-        Serializes the occurrences to a JSON string.
+        Returns the package name.
 
         Returns:
-            str: A JSON string representation of the occurrences list.
+            str: The package name.
+        """
+        ...
 
-        Raises:
-            ValueError: If the serialization fails due to invalid data.
+    def get_in_app(self) -> bool:
+        """
+        Returns whether the function is in an app or system one.
 
-        Example:
-            >>> occurrences = profile.find_occurrences()
-            >>> json_str = occurrences.to_json_str()
-            >>> print(json_str)
+        Returns:
+            bool: True if the function is an app one, False otherwise.
         """
         ...
 
-    def filter_none_type_issues(self) -> None:
+    def get_self_times_ns(self) -> List[int]:
         """
-        Filters occurrences to remove those with NONE_TYPE.
+        Returns the self times in nanoseconds, one per call of the function
+        aggregated, in no particular order: the distribution of its durations
+        in the chunk, which the p75, p95 and p99 are computed from.
 
-        This method removes all occurrences that have a type of NONE_TYPE,
-        keeping only meaningful performance issues in the collection.
+        Returns:
+            list[int]: The self times in nanoseconds.
+        """
+        ...
 
-        Example:
-            >>> occurrences = profile.find_occurrences()
-            >>> occurrences.filter_none_type_issues()
+    def get_sum_self_time_ns(self) -> int:
+        """
+        Returns the sum of self times in nanoseconds.
+
+        Returns:
+            int: The sum of self times in nanoseconds.
         """
         ...
 
-class Transaction:
-    """
-    Represents transaction information associated with a profile.
-    
-    Contains metadata about the transaction including identifiers, timing information,
-    and thread context for the profiled operation.
-    """
-    
-    active_thread_id: int
-    """The ID of the active thread during the transaction."""
+    def get_sample_count(self) -> int:
+        """
+        Returns the sample count.
+
+        Returns:
+            int: The sample count.
+        """
+        ...
+
+    def get_thread_id(self) -> str:
+        """
+        Returns the thread ID.
+
+        Returns:
+            str: The thread ID.
+        """
+        ...
+
+    def get_thread_ids(self) -> List[str]:
+        """
+        Returns the IDs of the threads the function was sampled on.
+
+        Returns:
+            list[str]: The sorted thread IDs.
+        """
+        ...
+
+    def get_examples(self) -> List[FunctionExample]:
+        """
+        Returns examples of the function calls with the longest self time.
+
+        Returns:
+            list[FunctionExample]: Up to `max_examples` examples, sorted by descending self time.
+        """
+        ...
+
+    def get_max_duration(self) -> int:
+        """
+        Returns the maximum duration in nanoseconds.
+
+        Returns:
+            int: The maximum duration in nanoseconds.
+        """
+        ...
+
+    def get_p75_self_time_ns(self) -> int:
+        """
+        Returns the 75th percentile of self times in nanoseconds.
+
+        Returns:
+            int: The p75 self time in nanoseconds.
+        """
+        ...
+
+    def get_p95_self_time_ns(self) -> int:
+        """
+        Returns the 95th percentile of self times in nanoseconds.
+
+        Returns:
+            int: The p95 self time in nanoseconds.
+        """
+        ...
+
+    def get_p99_self_time_ns(self) -> int:
+        """
+        Returns the 99th percentile of self times in nanoseconds.
+
+        Returns:
+            int: The p99 self time in nanoseconds.
+        """
+        ...
+
+    def get_total_times_ns(self) -> List[int]:
+        """
+        Returns the total times in nanoseconds.
+
+        Returns:
+            list[int]: The total times in nanoseconds.
+        """
+        ...
+
+    def get_line_self_times_ns(self) -> List[Tuple[int, int]]:
+        """
+        Returns the self time of the function by line, for line-level
+        heatmaps.
+
+        Each call is credited to the line of its frame. Lines are only
+        aggregated if `max_lines_per_function` was set when extracting the
+        metrics.
+
+        Returns:
+            list[tuple[int, int]]
+                Up to `max_lines_per_function` ``(line, self_time_ns)`` pairs, the
+                lines with the most self time, sorted by line.
+        """
+        ...
+
+    def get_filename(self) -> str:
+        """
+        Returns the name of the file of the function.
+
+        Returns:
+            str
+                The file name, without its directory, or an empty string if unknown.
+        """
+        ...
+
+    def get_line(self) -> Optional[int]:
+        """
+        Returns the line of the function.
+
+        Returns:
+            int
+                The lowest line of the function found in the samples, the closest
+                to its definition, or None if unknown.
+        """
+        ...
+
+    def get_thread_name(self) -> Optional[str]:
+        """
+        Returns the name of the thread of :meth:`get_thread_id`.
+
+        Returns:
+            str
+                The thread name, or None if the thread is unnamed.
+        """
+        ...
+
+    def get_main_thread(self) -> bool:
+        """
+        Returns whether the function was sampled on the main thread, to
+        filter main thread functions without joining on thread metadata.
+
+        Returns:
+            bool
+                True if the main thread is among :meth:`get_thread_ids`.
+        """
+        ...
+
+    def to_msgpack(self) -> bytes:
+        """
+        Serializes the function to MessagePack, as a map keyed by field name.
+
+        Returns:
+            bytes
+                The MessagePack encoding of the function.
+
+        Raises:
+            ValueError
+                If the serialization fails due to invalid data.
+
+        Example:
+            >>> producer.produce("profiles-call-tree-functions", function.to_msgpack())
+        """
+        ...
+
+    def to_row(self, chunk: ProfileChunk) -> Any:
+        """
+        Returns the row of the function in the ClickHouse functions table,
+        with the fields of the profile chunk it was extracted from.
+
+        Args:
+            chunk (:class:`ProfileChunk`): The profile chunk the function was extracted from.
+
+        Returns:
+            dict[str, Any]
+                The row: ``project_id``, ``timestamp`` (the start of the chunk, a Unix
+                timestamp in whole seconds), ``fingerprint``, ``name``, ``package``,
+                ``is_application``, ``durations`` (the self times in nanoseconds),
+                ``platform``, ``environment``, ``release`` and ``retention_days``.
+
+        Raises:
+            ValueError
+                If the serialization fails due to invalid data.
+
+        Example:
+            >>> functions = chunk.extract_functions_metrics(min_depth=1, filter_system_frames=True)
+            >>> rows = [function.to_row(chunk) for function in functions]
+        """
+        ...
+
+    def get_sum_total_time_ns(self) -> int:
+        """
+        Returns the sum of total (inclusive) times in nanoseconds, the time
+        spent in the function and its callees, e.g. to compute the share of
+        the profile spent in the function.
+
+        Calls nested in another call of the function (recursion) are covered
+        by the outer call, so they aren't counted twice.
+
+        Returns:
+            int
+                The sum of total times in nanoseconds.
+        """
+        ...
+
+class FunctionExample:
+    """
+    An example of a function call, pointing to the sample where it was found.
+    """
+
+    def get_profile_id(self) -> Optional[str]:
+        """
+        Returns the ID of the transaction profile the example was found in.
+
+        Returns:
+            str: The profile ID, or None if the example comes from a profile chunk.
+        """
+        ...
+
+    def get_chunk_id(self) -> Optional[str]:
+        """
+        Returns the ID of the profile chunk the example was found in.
+
+        Returns:
+            str: The chunk ID, or None if the example comes from a transaction profile.
+        """
+        ...
+
+    def get_thread_id(self) -> str:
+        """
+        Returns the thread ID.
+
+        Returns:
+            str: The ID of the thread the function was sampled on.
+        """
+        ...
+
+    def get_start_ns(self) -> int:
+        """
+        Returns the start timestamp of the function call in nanoseconds.
+
+        Returns:
+            int: The start timestamp, as found in the call tree.
+        """
+        ...
+
+    def get_end_ns(self) -> int:
+        """
+        Returns the end timestamp of the function call in nanoseconds.
+
+        Returns:
+            int: The end timestamp, as found in the call tree.
+        """
+        ...
+
+    def get_start_timestamp_ns(self) -> int:
+        """
+        Returns the start timestamp of the function call, as a UTC Unix
+        timestamp in nanoseconds.
+
+        Returns:
+            int: The start timestamp, to line the call up with logs and spans.
+        """
+        ...
+
+    def get_end_timestamp_ns(self) -> int:
+        """
+        Returns the end timestamp of the function call, as a UTC Unix
+        timestamp in nanoseconds.
+
+        Returns:
+            int: The end timestamp, to line the call up with logs and spans.
+        """
+        ...
+
+    def get_self_time_ns(self) -> int:
+        """
+        Returns the self time of the function call in nanoseconds.
+
+        Returns:
+            int: The self time in nanoseconds.
+        """
+        ...
+
+class CpuSpike:
+    """
+    A window during which the CPU usage stayed above a threshold.
+    """
+
+    def get_start_ns(self) -> int:
+        """
+        Returns the start timestamp of the spike in nanoseconds.
+
+        Returns:
+            int: The start timestamp of the spike window.
+        """
+        ...
+
+    def get_end_ns(self) -> int:
+        """
+        Returns the end timestamp of the spike in nanoseconds.
+
+        Returns:
+            int: The end timestamp of the spike window.
+        """
+        ...
+
+    def get_average_cpu_usage(self) -> float:
+        """
+        Returns the average CPU usage during the spike.
+
+        Returns:
+            float: The average of the cpu_usage values measured during the spike.
+        """
+        ...
+
+    def get_max_cpu_usage(self) -> float:
+        """
+        Returns the maximum CPU usage during the spike.
+
+        Returns:
+            float: The maximum of the cpu_usage values measured during the spike.
+        """
+        ...
+
+    def get_stacks(self) -> List[SpikeStack]:
+        """
+        Returns the dominant stacks during the spike.
+
+        Returns:
+            list[SpikeStack]: The stacks the most time was spent in, sorted by descending duration.
+        """
+        ...
+
+class SpikeStack:
+    """
+    A stack sampled during a CPU spike, with the time spent in it.
+    """
+
+    def get_thread_id(self) -> str:
+        """
+        Returns the thread ID.
+
+        Returns:
+            str: The ID of the thread the stack was sampled on.
+        """
+        ...
+
+    def get_frames(self) -> List[str]:
+        """
+        Returns the function names of the stack.
+
+        Returns:
+            list[str]: The function names, from the outermost frame to the innermost one.
+        """
+        ...
+
+    def get_duration_ns(self) -> int:
+        """
+        Returns the time spent in the stack during the spike in nanoseconds.
+
+        Returns:
+            int: The duration in nanoseconds.
+        """
+        ...
+
+class MemoryGrowth:
+    """
+    An interval during which the memory footprint kept growing.
+    """
+
+    def get_start_ns(self) -> int:
+        """
+        Returns the start timestamp of the growth interval in nanoseconds.
+
+        Returns:
+            int: The timestamp of the first measurement of the interval.
+        """
+        ...
+
+    def get_end_ns(self) -> int:
+        """
+        Returns the end timestamp of the growth interval in nanoseconds.
+
+        Returns:
+            int: The timestamp of the last measurement of the interval.
+        """
+        ...
+
+    def get_growth_bytes(self) -> float:
+        """
+        Returns how much the memory footprint grew during the interval.
+
+        Returns:
+            float: The growth, in the unit of the measurement (usually bytes).
+        """
+        ...
+
+    def get_suspects(self) -> List[MemoryGrowthSuspect]:
+        """
+        Returns the functions suspected to cause the growth.
+
+        Returns:
+            list[MemoryGrowthSuspect]: The suspects, sorted by descending time spent allocating.
+        """
+        ...
+
+class MemoryGrowthSuspect:
+    """
+    A function calling allocation functions while the memory footprint grew.
+    """
+
+    def get_function(self) -> str:
+        """
+        Returns the function name.
+
+        Returns:
+            str: The function name.
+        """
+        ...
+
+    def get_package(self) -> str:
+        """
+        Returns the package name.
+
+        Returns:
+            str: The package name.
+        """
+        ...
+
+    def get_in_app(self) -> bool:
+        """
+        Returns whether the function is in an app or system one.
+
+        Returns:
+            bool: True if the function is an app one, False otherwise.
+        """
+        ...
+
+    def get_allocation_duration_ns(self) -> int:
+        """
+        Returns the time spent in allocation functions called by the function.
+
+        Returns:
+            int: The duration in nanoseconds, within the growth interval.
+        """
+        ...
+
+class FlamechartComparison:
+    """
+    A pair of flamecharts sharing the same time axis.
+    """
+
+    def get_start_ns(self) -> int:
+        """
+        Returns the origin of the shared time axis.
+
+        Returns:
+            int: The Unix timestamp in nanoseconds span offsets are relative to.
+        """
+        ...
+
+    def get_end_ns(self) -> int:
+        """
+        Returns the end of the shared time axis.
+
+        Returns:
+            int: The Unix timestamp in nanoseconds of the end of the latest chunk.
+        """
+        ...
+
+    def get_duration_ns(self) -> int:
+        """
+        Returns the duration of the shared time axis.
+
+        Returns:
+            int: The duration in nanoseconds.
+        """
+        ...
+
+    def to_json_str(self) -> str:
+        """
+        Serializes the comparison to a JSON string.
+
+        Returns:
+            str: A JSON string with the time axis metadata and the `left` and
+                `right` flamecharts.
+
+        Raises:
+            ValueError: If the serialization fails due to invalid data.
+        """
+        ...
+
+class Occurrence:
+    """
+    Represents a detected performance issue (occurrence) in a profile.
+
+    An occurrence is a specific instance of a performance problem detected through
+    profile analysis. It contains detailed information about the issue, including
+    the problematic function, stack trace, evidence, and metadata for issue tracking.
+    """
+
+    def get_culprit(self) -> str:
+        """
+        Returns the culprit (transaction name) where the issue occurred.
+
+        Returns:
+            str: The name of the transaction or main operation where the issue occurred.
+        """
+        ...
+
+    def get_detection_time(self) -> str:
+        """
+        Returns the detection time as an RFC 3339 formatted string.
+
+        Returns:
+            str: The detection time in RFC 3339 format.
+        """
+        ...
+
+    def get_event(self) -> Event:
+        """
+        Returns the event data.
+
+        Returns:
+            Event: Event data including platform, stack trace, and debug information.
+        """
+        ...
+
+    def get_evidence_data(self) -> EvidenceData:
+        """
+        Returns the evidence data.
+
+        Returns:
+            EvidenceData: Structured data about the performance issue.
+        """
+        ...
+
+    def get_evidence_display(self) -> List[Evidence]:
+        """
+        Returns the evidence display list.
+
+        Returns:
+            list[Evidence]: Human-readable evidence for displaying the issue.
+        """
+        ...
+
+    def get_fingerprint(self) -> List[str]:
+        """
+        Returns the fingerprint list.
+
+        Returns:
+            list[str]: Unique identifiers for grouping similar issues.
+        """
+        ...
+
+    def get_fingerprint_version(self) -> int:
+        """
+        Returns the version of the scheme used to compute the fingerprint.
+
+        Returns:
+            int: The fingerprint scheme version.
+        """
+        ...
+
+    def get_id(self) -> str:
+        """
+        Returns the occurrence ID.
+
+        Returns:
+            str: Unique identifier for this specific occurrence.
+        """
+        ...
+
+    def get_issue_title(self) -> str:
+        """
+        Returns the issue title.
+
+        Returns:
+            str: Human-readable title describing the type of issue.
+        """
+        ...
+
+    def get_level(self) -> str:
+        """
+        Returns the severity level.
+
+        Returns:
+            str: Severity level of the issue (e.g., "info", "warning", "error").
+        """
+        ...
+
+    def get_payload_type(self) -> str:
+        """
+        Returns the payload type.
+
+        Returns:
+            str: Type of payload, typically "occurrence".
+        """
+        ...
+
+    def get_project_id(self) -> int:
+        """
+        Returns the project ID.
+
+        Returns:
+            int: ID of the project where the issue was detected.
+        """
+        ...
+
+    def get_resource_id(self) -> Optional[str]:
+        """
+        Returns the resource ID.
+
+        Returns:
+            str: Optional resource identifier, or None if not available.
+        """
+        ...
+
+    def get_subtitle(self) -> str:
+        """
+        Returns the subtitle.
+
+        Returns:
+            str: Brief description, usually the function name where the issue occurred.
+        """
+        ...
+
+    def get_type(self) -> int:
+        """
+        Returns the issue type.
+
+        Returns:
+            int: Numeric type identifier for the issue category.
+        """
+        ...
+
+    def get_category(self) -> str:
+        """
+        Returns the category name.
+
+        Returns:
+            str: Category name for the performance issue.
+        """
+        ...
+
+    def get_duration_ns(self) -> int:
+        """
+        Returns the duration in nanoseconds.
+
+        Returns:
+            int: Duration of the problematic operation in nanoseconds.
+        """
+        ...
+
+    def get_sample_count(self) -> int:
+        """
+        Returns the sample count.
+
+        Returns:
+            int: Number of samples where this issue was detected.
+        """
+        ...
+
+    def to_json_str(self) -> str:
+        """
+        Serializes the occurrence to a JSON string.
+
+        Returns:
+            str: A JSON string representation of the occurrence.
+
+        Raises:
+            ValueError: If the serialization fails due to invalid data.
+
+        Example:
+            >>> occurrence = occurrences.occurrences[0]
+            >>> json_str = occurrence.to_json_str()
+            >>> print(json_str)
+        """
+        ...
+
+    def to_dict(self) -> Any:
+        """
+        Returns the occurrence as an issue platform payload, to be published
+        as is to the occurrences topic.
+
+        The payload holds the fields the issue platform expects (evidence,
+        fingerprint, subtitle, culprit, and the event with its stack trace and
+        tags), leaving out the ones only used for stats (category, duration and
+        sample count).
+
+        Returns:
+            dict[str, Any]
+                The issue platform payload.
+
+        Raises:
+            ValueError
+                If the serialization fails due to invalid data.
+
+        Example:
+            >>> for occurrence in occurrences.occurrences:
+            ...     producer.produce("ingest-occurrences", json.dumps(occurrence.to_dict()))
+        """
+        ...
+
+    def to_msgpack(self) -> bytes:
+        """
+        Serializes the occurrence to MessagePack, with the same fields as
+        :meth:`to_json_str`.
+
+        Returns:
+            bytes
+                The MessagePack encoding of the occurrence.
+
+        Raises:
+            ValueError
+                If the serialization fails due to invalid data.
+
+        Example:
+            >>> payload = occurrence.to_msgpack()
+        """
+        ...
+
+class Occurrences:
+    """
+    A wrapper class containing a list of Occurrence objects.
+    
+    This class wraps the results of occurrence detection, providing access to
+    the detected performance issues through the occurrences attribute.
+    """
+
+    occurrences: List[Occurrence]
+
+    def to_json_str(self) -> str:
+        """
+        Serializes the occurrences to a JSON string.
+
+        Returns:
+            str: A JSON string representation of the occurrences list.
+
+        Raises:
+            ValueError: If the serialization fails due to invalid data.
+
+        Example:
+            >>> occurrences = profile.find_occurrences()
+            >>> json_str = occurrences.to_json_str()
+            >>> print(json_str)
+        """
+        ...
+
+    def filter_none_type_issues(self) -> None:
+        """
+        Filters occurrences to remove those with NONE_TYPE.
+
+        This method removes all occurrences that have a type of NONE_TYPE,
+        keeping only meaningful performance issues in the collection.
+
+        Example:
+            >>> occurrences = profile.find_occurrences()
+            >>> occurrences.filter_none_type_issues()
+        """
+        ...
+
+    def to_msgpack(self) -> bytes:
+        """
+        Serializes the occurrences to MessagePack, as an array with the same
+        fields as :meth:`to_json_str`.
+
+        Returns:
+            bytes
+                The MessagePack encoding of the occurrences list.
+
+        Raises:
+            ValueError
+                If the serialization fails due to invalid data.
+
+        Example:
+            >>> occurrences = profile.find_occurrences()
+            >>> payload = occurrences.to_msgpack()
+        """
+        ...
+
+class Transaction:
+    """
+    Represents transaction information associated with a profile.
     
+    Contains metadata about the transaction including identifiers, timing information,
+    and thread context for the profiled operation.
+    """
+
+    active_thread_id: int
+    """The ID of the active thread during the transaction."""
+
     duration_ns: Optional[int]
     """The duration of the transaction in nanoseconds, or None if not available."""
-    
+
     id: str
     """The unique identifier for this transaction."""
-    
+
     name: str
     """The name of the transaction."""
-    
-    trace_id: str
-    """The trace ID associated with this transaction."""
-    
-    segment_id: str
-    """The segment ID associated with this transaction."""
 
-class Metadata:
+    trace_id: str
+    """The trace ID associated with this transaction."""
+
+    segment_id: str
+    """The segment ID associated with this transaction."""
+
+class Metadata:
+    """
+    Represents comprehensive metadata information associated with a profile.
+    
+    Contains device characteristics, SDK information, transaction details,
+    and other profile-specific data for analysis and debugging purposes.
+    """
+
+    android_api_level: Optional[int]
+    """The Android API level of the device, or None if not available."""
+
+    architecture: str
+    """The device architecture (e.g., 'arm64', 'x86_64')."""
+
+    device_classification: Optional[str]
+    """The device classification or category, or None if not available."""
+
+    device_locale: Optional[str]
+    """The device locale setting, or None if not available."""
+
+    device_manufacturer: Optional[str]
+    """The device manufacturer name, or None if not available."""
+
+    device_model: str
+    """The device model name."""
+
+    device_os_build_number: Optional[str]
+    """The device OS build number, or None if not available."""
+
+    device_os_name: str
+    """The device operating system name."""
+
+    device_os_version: str
+    """The device operating system version."""
+
+    id: str
+    """The unique identifier for this profile."""
+
+    project_id: str
+    """The project ID as a string."""
+
+    sdk_name: Optional[str]
+    """The name of the SDK used to collect this profile, or None if not available."""
+
+    sdk_version: Optional[str]
+    """The version of the SDK used to collect this profile, or None if not available."""
+
+    timestamp: int
+    """The timestamp when the profile was collected (Unix timestamp)."""
+
+    trace_duration_ms: float
+    """The duration of the trace in milliseconds."""
+
+    transaction_id: str
+    """The unique identifier for the transaction."""
+
+    transaction_name: str
+    """The name of the transaction."""
+
+    version_code: Optional[str]
+    """The version code of the application, or None if not available."""
+
+    version_name: Optional[str]
+    """The version name of the application, or None if not available."""
+
+class SampleChunkBuilder:
+    """
+    Builds a sample format profile chunk programmatically, to write tests
+    without relying on large JSON fixtures.
+
+    Example:
+        >>> builder = vroomrs.SampleChunkBuilder("cocoa")
+        >>> main = builder.add_frame("main", package="MyApp", in_app=True)
+        >>> work = builder.add_frame("work", package="MyApp", in_app=True)
+        >>> builder.add_sample("1", 0.00, [work, main])
+        >>> builder.add_sample("1", 0.01, [work, main])
+        >>> chunk = builder.build()
+    """
+
+    def __init__(
+        self,
+        platform: str,
+        chunk_id: Optional[str] = None,
+        profiler_id: Optional[str] = None,
+        organization_id: int = 1,
+        project_id: int = 1,
+        release: Optional[str] = None,
+        environment: Optional[str] = None,
+    ) -> None: ...
+
+    def add_frame(
+        self,
+        function: str,
+        package: Optional[str] = None,
+        module: Optional[str] = None,
+        path: Optional[str] = None,
+        lineno: Optional[int] = None,
+        in_app: Optional[bool] = None,
+    ) -> int:
+        """
+        Adds a frame to the profile. Identical frames are only stored once.
+
+        Returns:
+            int: The index of the frame, to be used in stacks.
+        """
+        ...
+
+    def add_thread(self, thread_id: str, name: str) -> None:
+        """
+        Adds a thread name to the thread metadata.
+        """
+        ...
+
+    def add_sample(self, thread_id: str, timestamp: float, stack: List[int]) -> None:
+        """
+        Adds a sample to the profile. The last sample of a thread only marks
+        the end of the previous one.
+
+        Args:
+            thread_id (str): The thread ID.
+            timestamp (float): The sample timestamp, in seconds.
+            stack (list[int]): Frame indices, from the innermost (leaf) frame
+                to the outermost one.
+
+        Raises:
+            ValueError
+                If the timestamp is negative.
+        """
+        ...
+
+    def add_measurement(
+        self, name: str, unit: str, values: List[Tuple[float, float]]
+    ) -> None:
+        """
+        Adds a measurement made of ``(timestamp, value)`` pairs.
+        """
+        ...
+
+    def build(self) -> ProfileChunk:
+        """
+        Builds the profile chunk. The builder is left empty afterwards.
+
+        Returns:
+            ProfileChunk: The profile chunk built.
+        """
+        ...
+
+class AndroidChunkBuilder:
+    """
+    Builds an android trace format profile chunk programmatically, to write
+    tests without relying on large JSON fixtures.
+
+    Example:
+        >>> builder = vroomrs.AndroidChunkBuilder(timestamp=1737465052.0)
+        >>> builder.add_thread(1, "main")
+        >>> method = builder.add_method("com.example.MainActivity", "onCreate")
+        >>> builder.add_enter(1, method, 0)
+        >>> builder.add_exit(1, method, 50_000_000)
+        >>> chunk = builder.build()
+    """
+
+    def __init__(
+        self,
+        timestamp: float,
+        chunk_id: Optional[str] = None,
+        profiler_id: Optional[str] = None,
+        organization_id: int = 1,
+        project_id: int = 1,
+        release: Optional[str] = None,
+        environment: Optional[str] = None,
+    ) -> None: ...
+
+    def add_thread(self, thread_id: int, name: str) -> None:
+        """
+        Adds a thread to the profile. The thread named "main" is considered
+        the active thread.
+        """
+        ...
+
+    def add_method(
+        self, class_name: str, name: str, signature: str = "", source_file: str = ""
+    ) -> int:
+        """
+        Adds a method to the profile.
+
+        Returns:
+            int: The method ID, to be used in events.
+        """
+        ...
+
+    def add_enter(self, thread_id: int, method_id: int, ts_ns: int) -> None:
+        """
+        Adds a method enter event, ts_ns being relative to the chunk start.
+        """
+        ...
+
+    def add_exit(self, thread_id: int, method_id: int, ts_ns: int) -> None:
+        """
+        Adds a method exit event, ts_ns being relative to the chunk start.
+        """
+        ...
+
+    def add_measurement(
+        self, name: str, unit: str, values: List[Tuple[float, float]]
+    ) -> None:
+        """
+        Adds a measurement made of ``(timestamp, value)`` pairs.
+        """
+        ...
+
+    def build(self) -> ProfileChunk:
+        """
+        Builds the profile chunk. The builder is left empty afterwards.
+
+        Returns:
+            ProfileChunk: The profile chunk built.
+        """
+        ...
+
+    def add_sample(self, thread_id: int, ts_ns: int, stack: List[int]) -> None:
+        """
+        Adds a sampled stack, for chunks in the sampled variant of the
+        format, which can't have enter and exit events.
+
+        Args:
+            thread_id (int): The thread ID.
+            ts_ns (int): The sample time, in nanoseconds since the chunk start.
+            stack (list[int]): The method IDs, from the innermost frame to the outermost one.
+        """
+        ...
+
+    def add_unwind(self, thread_id: int, method_id: int, ts_ns: int) -> None:
+        """
+        Adds a method unwind event, the method exiting because of an exception.
+
+        Args:
+            thread_id (int): The thread ID.
+            method_id (int): The method ID.
+            ts_ns (int): The event time, in nanoseconds since the chunk start.
+        """
+        ...
+
+class Evidence:
+    def get_value(self) -> str:
+        """
+        Returns the evidence value.
+
+        Returns:
+            str
+                The value of the evidence.
+        """
+        ...
+
+    def get_important(self) -> bool:
+        """
+        Returns whether the evidence is important.
+
+        Returns:
+            bool
+                True if the evidence is marked as important, False otherwise.
+        """
+        ...
+
+    def get_name(self) -> str:
+        """
+        Returns the evidence name.
+
+        Returns:
+            str
+                The name of the evidence (e.g., "Duration", "Suspect function", "Package").
+        """
+        ...
+
+class EvidenceData:
+    def get_frame_duration_ns(self) -> int:
+        """
+        Returns the frame duration in nanoseconds.
+
+        Returns:
+            int
+                Duration of the frame in nanoseconds.
+        """
+        ...
+
+    def get_frame_module(self) -> str:
+        """
+        Returns the frame module.
+
+        Returns:
+            str
+                Module name where the frame is located.
+        """
+        ...
+
+    def get_frame_name(self) -> str:
+        """
+        Returns the frame name.
+
+        Returns:
+            str
+                Name of the frame/function.
+        """
+        ...
+
+    def get_frame_package(self) -> str:
+        """
+        Returns the frame package.
+
+        Returns:
+            str
+                Package name where the frame is located.
+        """
+        ...
+
+    def get_profile_duration_ns(self) -> int:
+        """
+        Returns the profile duration in nanoseconds.
+
+        Returns:
+            int
+                Total duration of the profile in nanoseconds.
+        """
+        ...
+
+    def get_template_name(self) -> str:
+        """
+        Returns the template name.
+
+        Returns:
+            str
+                Name of the template used.
+        """
+        ...
+
+    def get_transaction_id(self) -> str:
+        """
+        Returns the transaction ID.
+
+        Returns:
+            str
+                ID of the transaction.
+        """
+        ...
+
+    def get_transaction_name(self) -> str:
+        """
+        Returns the transaction name.
+
+        Returns:
+            str
+                Name of the transaction.
+        """
+        ...
+
+    def get_profile_id(self) -> str:
+        """
+        Returns the profile ID.
+
+        Returns:
+            str
+                ID of the profile.
+        """
+        ...
+
+    def get_sample_count(self) -> Optional[int]:
+        """
+        Returns the sample count.
+
+        Returns:
+            int
+                Number of samples, or None if not available.
+        """
+        ...
+
+    def get_profile_duration_ms(self) -> int:
+        """
+        Returns the profile duration in milliseconds.
+
+        Returns:
+            int
+                Total duration of the profile in milliseconds.
+        """
+        ...
+
+    def get_offender_duration_ms(self) -> int:
+        """
+        Returns the duration of the offending frame in milliseconds.
+
+        Returns:
+            int
+                Duration of the frame in milliseconds.
+        """
+        ...
+
+    def get_profile_percentage(self) -> float:
+        """
+        Returns the share of the profile the offending frame took.
+
+        Returns:
+            float
+                The frame duration, as a percentage of the profile duration
+                (e.g. 12.5 for 12.5%), or 0 if the profile has no duration.
+        """
+        ...
+
+class Event:
+    """
+    Options for detecting exact frames in profiling data.
+    """
+
+class PlatformCapabilities:
+    """
+    The analyses supported for profiles of a platform.
+    """
+
+    def get_in_app_rules(self) -> bool:
+        """
+        Returns whether frames are classified as application or system
+        frames by our rules when the SDK doesn't classify them.
+
+        Returns:
+            bool
+                True if the platform has in_app rules.
+        """
+        ...
+
+    def get_occurrence_detectors(self) -> List[str]:
+        """
+        Returns the categories of the occurrences detected in the frames of
+        the platform, including the ones of detectors registered at runtime.
+
+        Returns:
+            list[str]
+                The occurrence categories, sorted (e.g. "file_read", "image_decode").
+        """
+        ...
+
+    def get_frame_drop(self) -> bool:
+        """
+        Returns whether frame drop causes are detected, from frozen frame
+        measurements.
+
+        Returns:
+            bool
+                True if the platform SDKs measure frozen frames.
+        """
+        ...
+
+    def get_deobfuscation(self) -> bool:
+        """
+        Returns whether frames can be deobfuscated, in which case partially
+        deobfuscated frames are left out of function metrics.
+
+        Returns:
+            bool
+                True if the platform supports deobfuscation.
+        """
+        ...
+
+    def get_app_start_prelude(self) -> bool:
+        """
+        Returns whether the app start prelude (dyld, `+load` methods and
+        static initializers) can be analyzed.
+
+        Returns:
+            bool
+                True if the platform profiles can capture the app start prelude.
+        """
+        ...
+
+class MeasurementMetric:
+    """
+    Summary of the values of a measurement over a profile chunk.
+    """
+
+    def get_name(self) -> str:
+        """
+        Returns the name of the measurement.
+
+        Returns:
+            str
+                The measurement name (e.g. "cpu_usage").
+        """
+        ...
+
+    def get_unit(self) -> str:
+        """
+        Returns the unit of the measurement.
+
+        Returns:
+            str
+                The measurement unit (e.g. "percent", "byte").
+        """
+        ...
+
+    def get_count(self) -> int:
+        """
+        Returns the number of values aggregated.
+
+        Returns:
+            int
+                The number of values of the measurement.
+        """
+        ...
+
+    def get_min(self) -> float:
+        """
+        Returns the minimum value of the measurement.
+
+        Returns:
+            float
+                The minimum value.
+        """
+        ...
+
+    def get_max(self) -> float:
+        """
+        Returns the maximum value of the measurement.
+
+        Returns:
+            float
+                The maximum value.
+        """
+        ...
+
+    def get_mean(self) -> float:
+        """
+        Returns the mean value of the measurement.
+
+        Returns:
+            float
+                The mean value.
+        """
+        ...
+
+    def get_p90(self) -> float:
+        """
+        Returns the 90th percentile of the values of the measurement.
+
+        Returns:
+            float
+                The p90 value, using the nearest-rank method.
+        """
+        ...
+
+class ProcessedProfile:
+    """
+    Everything the profile consumer emits for a profile, computed in a
+    single pass over the profile (see :func:`process_profile`).
+    """
+
+    def get_metadata(self) -> Metadata:
+        """
+        Returns the metadata of the profile.
+
+        Returns:
+            Metadata
+                The metadata message of the profile, see :meth:`Profile.get_metadata`.
+        """
+        ...
+
+    def get_functions(self) -> List[CallTreeFunction]:
+        """
+        Returns the function metrics of the profile.
+
+        Returns:
+            list[:class:`CallTreeFunction`]
+                The functions, see :meth:`Profile.extract_functions_metrics`.
+        """
+        ...
+
+    def get_occurrences(self) -> Occurrences:
+        """
+        Returns the occurrences found in the profile.
+
+        Returns:
+            :class:`Occurrences`
+                The occurrences, see :meth:`Profile.find_occurrences`.
+        """
+        ...
+
+    def get_summary(self) -> ProfileSummary:
+        """
+        Returns the summary of the profile.
+
+        Returns:
+            :class:`ProfileSummary`
+                The aggregates describing the profile as a whole.
+        """
+        ...
+
+class ProfileSummary:
+    """
+    Aggregates describing a profile or a chunk as a whole.
+    """
+
+    def get_duration_ns(self) -> int:
+        """
+        Returns the duration of the profile in nanoseconds.
+
+        Returns:
+            int
+                The duration in nanoseconds.
+        """
+        ...
+
+    def get_thread_count(self) -> int:
+        """
+        Returns the number of threads sampled.
+
+        Returns:
+            int
+                The number of threads with at least one sample.
+        """
+        ...
+
+    def get_sample_count(self) -> int:
+        """
+        Returns the number of samples of the profile.
+
+        Returns:
+            int
+                The number of samples aggregated in the call trees, across all threads.
+        """
+        ...
+
+    def get_function_count(self) -> int:
+        """
+        Returns the number of function metrics extracted.
+
+        Returns:
+            int
+                The number of functions returned by :meth:`ProcessedProfile.get_functions`.
+        """
+        ...
+
+    def get_occurrence_count(self) -> int:
+        """
+        Returns the number of occurrences found.
+
+        Returns:
+            int
+                The number of occurrences returned by :meth:`ProcessedProfile.get_occurrences`,
+                0 for chunks, which aren't searched for occurrences.
+        """
+        ...
+
+class AnomalyScore:
+    """
+    The anomaly score of a profile chunk, along with the signals it combines.
+    Each signal is a ratio between 0 (nothing anomalous) and 1.
+    """
+
+    def get_score(self) -> float:
+        """
+        Returns the anomaly score, combining all the signals.
+
+        Returns:
+            float
+                The score, from 0 (nothing anomalous) to 1 (junk).
+        """
+        ...
+
+    def get_unknown_frames(self) -> float:
+        """
+        Returns the ratio of frames without a function name, or which
+        couldn't be symbolicated.
+
+        Returns:
+            float
+                The ratio of unknown frames.
+        """
+        ...
+
+    def get_repaired_events(self) -> float:
+        """
+        Returns the ratio of samples (or events) whose timestamp was repaired,
+        or fixed up while building the call trees.
+
+        Returns:
+            float
+                The ratio of repaired events.
+        """
+        ...
+
+    def get_truncated_stacks(self) -> float:
+        """
+        Returns the ratio of samples whose stack was truncated at the
+        maximum depth.
+
+        Returns:
+            float
+                The ratio of truncated stacks.
+        """
+        ...
+
+    def get_obfuscated_frames(self) -> float:
+        """
+        Returns the ratio of frames still obfuscated, on platforms supporting
+        deobfuscation.
+
+        Returns:
+            float
+                The ratio of obfuscated frames.
+        """
+        ...
+
+    def get_clock_skew(self) -> float:
+        """
+        Returns how far in the future the chunk ended when it was received,
+        the clock of the device being ahead.
+
+        Returns:
+            float
+                The clock skew, saturating at 1 for a minute or more.
+        """
+        ...
+
+class Limits:
+    """
+    The limits applied while processing profiles.
+    """
+
+    def get_max_stack_depth(self) -> int:
+        """
+        Returns the maximum depth of stacks. Deeper stacks of android
+        profiles are truncated when building call trees, and stack traces of
+        occurrences only keep this many frames.
+
+        Returns:
+            int
+                The maximum stack depth (128 by default).
+        """
+        ...
+
+    def get_max_in_app_rules(self) -> int:
+        """
+        Returns the maximum number of rules of an in_app override document.
+
+        Returns:
+            int
+                The maximum number of rules (1000 by default).
+        """
+        ...
+
+    def get_max_malformed_samples_ratio(self) -> float:
+        """
+        Returns the maximum ratio of the samples of a chunk which can be
+        malformed (referencing a stack or frame which doesn't exist). Up to
+        it, malformed samples are skipped when building call trees, and
+        counted in :meth:`ProcessingStats.get_malformed_samples`.
+
+        Returns:
+            float
+                The maximum ratio of malformed samples (0.1 by default), 0
+                failing on any malformed sample.
+        """
+        ...
+
+class AppStartPrelude:
+    """
+    The time spent in the app start prelude of a profile.
+    """
+
+    def get_window_ns(self) -> int:
+        """
+        Returns the duration of the app start window the prelude was looked
+        for in, from the start of the profile.
+
+        Returns:
+            int
+                The duration of the window in nanoseconds.
+        """
+        ...
+
+    def get_duration_ns(self) -> int:
+        """
+        Returns the time spent in the prelude on the main thread.
+
+        Returns:
+            int
+                The duration in nanoseconds.
+        """
+        ...
+
+    def get_frames(self) -> List[PreludeFrame]:
+        """
+        Returns the frames of the prelude.
+
+        Returns:
+            list[PreludeFrame]
+                The frames, sorted by descending duration.
+        """
+        ...
+
+    def get_occurrence(self) -> Optional[Occurrence]:
+        """
+        Returns the "slow app start prelude" occurrence, if one was requested
+        and the prelude was slow enough.
+
+        Returns:
+            Occurrence | None
+                The occurrence, blaming the frame the most time was spent in.
+        """
+        ...
+
+class PreludeFrame:
+    """
+    A frame of the app start prelude, with the time spent in it.
+    """
+
+    def get_function(self) -> str:
+        """
+        Returns the function name.
+
+        Returns:
+            str
+                The function name (e.g. "+[MyClass load]").
+        """
+        ...
+
+    def get_package(self) -> str:
+        """
+        Returns the package name.
+
+        Returns:
+            str
+                The package name.
+        """
+        ...
+
+    def get_kind(self) -> str:
+        """
+        Returns the kind of work done in the frame.
+
+        Returns:
+            str
+                "dyld", "objc_load" or "static_initializer".
+        """
+        ...
+
+    def get_duration_ns(self) -> int:
+        """
+        Returns the time spent in the frame, and in the frames it called
+        which aren't prelude frames themselves.
+
+        Returns:
+            int
+                The duration in nanoseconds, within the app start window.
+        """
+        ...
+
+class SymbolicationStats:
+    """
+    How far symbolication of the frames of a chunk went.
+    """
+
+    def get_symbolicated_frames(self) -> int:
+        """
+        Returns the number of frames symbolicated.
+
+        Returns:
+            int
+                The number of frames symbolicated.
+        """
+        ...
+
+    def get_unsymbolicated_frames(self) -> int:
+        """
+        Returns the number of frames symbolication failed for (missing debug
+        files, unknown images, ...), which waiting won't fix.
+
+        Returns:
+            int
+                The number of frames which couldn't be symbolicated.
+        """
+        ...
+
+    def get_pending_frames(self) -> int:
+        """
+        Returns the number of native frames not symbolicated yet, only known
+        by their instruction address.
+
+        Returns:
+            int
+                The number of frames pending symbolication.
+        """
+        ...
+
+    def get_resubmit_after_symbolication(self) -> bool:
+        """
+        Returns whether the chunk should be withheld from function metrics,
+        and resubmitted once symbolicated.
+
+        Returns:
+            bool
+                True if too many frames are pending symbolication.
+        """
+        ...
+
+    def to_json_str(self) -> str:
+        """
+        Serializes the stats, to be stored along with the chunk metadata.
+
+        Returns:
+            str
+                A JSON string of the stats, including the
+                `resubmit_after_symbolication` marker.
+
+        Raises:
+            pyo3.exceptions.PyException: If an error occurs during serialization.
+        """
+        ...
+
+class ActivityTimeline:
+    """
+    The number of samples of each thread per time bucket.
+    """
+
+    def get_start_ns(self) -> int:
+        """
+        Returns the start of the first bucket.
+
+        Returns:
+            int
+                The UTC Unix timestamp in nanoseconds of the start of the chunk.
+        """
+        ...
+
+    def get_bucket_ns(self) -> int:
+        """
+        Returns the duration of the buckets.
+
+        Returns:
+            int
+                The duration of a bucket, in nanoseconds.
+        """
+        ...
+
+    def get_threads(self) -> Dict[str, List[int]]:
+        """
+        Returns the number of samples of each thread per bucket, the i-th
+        bucket starting `i * bucket_ns` after the start of the chunk.
+
+        Returns:
+            dict[str, list[int]]
+                The sample counts by thread ID, every thread having the same
+                number of buckets.
+        """
+        ...
+
+class DetectorInfo:
+    """
+    A detector of the registry.
+    """
+
+    def get_name(self) -> str:
+        """
+        Returns the name of the detector.
+
+        Returns:
+            str
+                The name of the detector (e.g. "cocoa_main_thread", "frame_drop").
+        """
+        ...
+
+    def get_categories(self) -> List[str]:
+        """
+        Returns the categories of the occurrences the detector can create.
+
+        Returns:
+            list[str]
+                The occurrence categories, sorted.
+        """
+        ...
+
+    def get_platforms(self) -> List[str]:
+        """
+        Returns the platforms of the profiles the detector runs on.
+
+        Returns:
+            list[str]
+                The platforms of the detector.
+        """
+        ...
+
+    def get_enabled(self) -> bool:
+        """
+        Returns whether the detector runs when finding occurrences.
+
+        Returns:
+            bool
+                True unless the detector was disabled.
+        """
+        ...
+
+class CalibrationStats:
+    """
+    What the detectors would have reported, to tune their thresholds before
+    enabling them.
+    """
+
+    def get_occurrences(self) -> Dict[str, int]:
+        """
+        Returns the number of occurrences the detectors would have created.
+
+        Returns:
+            dict[str, int]
+                The number of occurrences, by category.
+        """
+        ...
+
+    def get_duration_buckets_ns(self) -> List[int]:
+        """
+        Returns the upper bounds of the buckets of the duration histograms.
+
+        Returns:
+            list[int]
+                The bounds, in nanoseconds, from 1ms to 4096ms by powers of 2.
+        """
+        ...
+
+    def get_duration_histograms(self) -> Dict[str, List[int]]:
+        """
+        Returns the histograms of the durations of the occurrences the
+        detectors would have created. The i-th count is of the durations
+        under the i-th bound of :meth:`get_duration_buckets_ns` and not under
+        the previous one, the last count of the durations over every bound.
+
+        Returns:
+            dict[str, list[int]]
+                The duration histograms, by category.
+        """
+        ...
+
+class OtelSpan:
+    """
+    A call tree node exported as an OpenTelemetry span.
+    """
+
+    def get_trace_id(self) -> str:
+        """
+        Returns the trace ID of the span, derived from the profiler ID so
+        the spans of all the chunks of a profiler session share it.
+
+        Returns:
+            str
+                The trace ID, 32 hexadecimal characters.
+        """
+        ...
+
+    def get_span_id(self) -> str:
+        """
+        Returns the span ID, stable across exports of the same chunk.
+
+        Returns:
+            str
+                The span ID, 16 hexadecimal characters.
+        """
+        ...
+
+    def get_parent_span_id(self) -> Optional[str]:
+        """
+        Returns the span of the closest ancestor of the node which was also
+        exported.
+
+        Returns:
+            str
+                The parent span ID, or None if no ancestor was exported.
+        """
+        ...
+
+    def get_name(self) -> str:
+        """
+        Returns the name of the span, the function of the node.
+
+        Returns:
+            str
+                The function name.
+        """
+        ...
+
+    def get_start_time_unix_nano(self) -> int:
+        """
+        Returns the start of the span.
+
+        Returns:
+            int
+                The UTC Unix timestamp in nanoseconds the node started at.
+        """
+        ...
+
+    def get_end_time_unix_nano(self) -> int:
+        """
+        Returns the end of the span.
+
+        Returns:
+            int
+                The UTC Unix timestamp in nanoseconds the node ended at.
+        """
+        ...
+
+    def get_attributes(self) -> Dict[str, str]:
+        """
+        Returns the attributes of the span: the profile it comes from
+        (`sentry.origin`, `sentry.profiler_id`, `sentry.chunk_id`), the thread
+        (`thread.id`) and the code (`code.function`, `code.namespace`,
+        `code.filepath`, `code.lineno`) of the node, and its number of samples
+        (`profile.sample_count`).
+
+        Returns:
+            dict[str, str]
+                The attributes, by key.
+        """
+        ...
+
+    def to_json_str(self) -> str:
+        """
+        Serializes the span in the OTLP JSON encoding, to be added to the
+        `spans` of a `ScopeSpans`.
+
+        Returns:
+            str
+                A JSON string of the span.
+        """
+        ...
+
+class TopFrame:
+    """
+    A frame, with the self time it was sampled in across threads.
+    """
+
+    def get_fingerprint(self) -> int:
+        """
+        Returns the fingerprint of the frame, the same as the one of the
+        function in :meth:`CallTreeFunction.get_fingerprint`.
+
+        Returns:
+            int
+                The fingerprint of the frame.
+        """
+        ...
+
+    def get_function(self) -> str:
+        """
+        Returns the function name of the frame.
+
+        Returns:
+            str
+                The function name.
+        """
+        ...
+
+    def get_package(self) -> str:
+        """
+        Returns the package (or module) of the frame.
+
+        Returns:
+            str
+                The package name.
+        """
+        ...
+
+    def get_in_app(self) -> bool:
+        """
+        Returns whether the frame is an app or system one.
+
+        Returns:
+            bool
+                True if the frame is an app one, False otherwise.
+        """
+        ...
+
+    def get_self_time_ns(self) -> int:
+        """
+        Returns the time the frame was sampled in as the leaf frame.
+
+        Returns:
+            int
+                The self time in nanoseconds, summed across threads.
+        """
+        ...
+
+    def get_sample_count(self) -> int:
+        """
+        Returns the number of samples the frame was the leaf frame of.
+
+        Returns:
+            int
+                The number of samples, summed across threads.
+        """
+        ...
+
+    def get_stack_fingerprints(self) -> List[int]:
+        """
+        Returns examples of the stacks the frame was sampled in.
+
+        Returns:
+            list[int]
+                Up to 5 fingerprints of the stacks up to the frame (see
+                :meth:`CallTreeFunction.get_stack_fingerprint`), the ones the
+                frame spent the most self time in first.
+        """
+        ...
+
+class ProcessingStats:
+    """
+    Statistics on the processing of a profile chunk.
+    """
+
+    def get_samples(self) -> int:
+        """
+        Returns the number of samples of the chunk (or of events, for android
+        trace chunks).
+
+        Returns:
+            int
+                The number of samples.
+        """
+        ...
+
+    def get_threads(self) -> int:
+        """
+        Returns the number of threads sampled.
+
+        Returns:
+            int
+                The number of threads.
+        """
+        ...
+
+    def get_frames(self) -> int:
+        """
+        Returns the number of frames of the chunk (or of methods, for android
+        trace chunks).
+
+        Returns:
+            int
+                The number of frames.
+        """
+        ...
+
+    def get_stacks(self) -> int:
+        """
+        Returns the number of stacks of the chunk. Android trace chunks,
+        recording events entering and exiting methods, have none.
+
+        Returns:
+            int
+                The number of stacks.
+        """
+        ...
+
+    def get_unique_functions(self) -> int:
+        """
+        Returns the number of distinct functions of the frames of the chunk,
+        by fingerprint.
+
+        Returns:
+            int
+                The number of unique functions.
+        """
+        ...
+
+    def get_obfuscated_frames(self) -> int:
+        """
+        Returns the number of app frames whose class name looks renamed by
+        ProGuard, on platforms supporting deobfuscation.
+
+        Returns:
+            int
+                The number of obfuscated frames.
+        """
+        ...
+
+    def get_truncated_stacks(self) -> int:
+        """
+        Returns the number of stacks truncated at the maximum depth while
+        building the call trees.
+
+        Returns:
+            int
+                The number of stacks truncated.
+        """
+        ...
+
+    def get_malformed_samples(self) -> int:
+        """
+        Returns the number of malformed samples (referencing a stack or frame
+        which doesn't exist) dropped while building the call trees.
+
+        Returns:
+            int
+                The number of samples dropped.
+        """
+        ...
+
+    def get_parse_time_ns(self) -> int:
+        """
+        Returns the time it took to parse the payload of the chunk.
+
+        Returns:
+            int
+                The parse time in nanoseconds. 0 if the chunk wasn't parsed from
+                a payload.
+        """
+        ...
+
+    def get_build_time_ns(self) -> int:
+        """
+        Returns the time it took to build the call trees of the chunk.
+
+        Returns:
+            int
+                The build time in nanoseconds.
+        """
+        ...
+
+    def to_json_str(self) -> str:
+        """
+        Serializes the stats, to be logged along with the chunk metadata.
+
+        Returns:
+            str
+                A JSON string of the stats.
+
+        Raises:
+            pyo3.exceptions.PyException: If an error occurs during serialization.
+        """
+        ...
+
+class VroomError(ValueError):
+    """
+    Base class of the errors raised processing profiles and chunks.
+    """
+
+class ParseError(VroomError):
+    """
+    The payload isn't valid JSON, or doesn't match the format of its version.
+    """
+
+class UnsupportedVersion(VroomError):
+    """
+    The payload is of a version of the profile format which isn't supported.
+    """
+
+class InvalidStack(VroomError):
+    """
+    Samples reference stacks or frames which don't exist.
+    """
+
+class CompressionError(VroomError):
+    """
+    The payload couldn't be decompressed.
+    """
+
+class ProcessedProfileChunk:
+    """
+    Everything the profile consumer emits for a chunk, computed in a single
+    pass over the chunk (see :func:`process_profile_chunk`).
+    """
+
+    def get_functions(self) -> List[CallTreeFunction]:
+        """
+        Returns the function metrics of the chunk.
+
+        Returns:
+            list[:class:`CallTreeFunction`]
+                The functions, see :meth:`ProfileChunk.extract_functions_metrics`.
+        """
+        ...
+
+    def get_stats(self) -> ProcessingStats:
+        """
+        Returns the processing stats of the chunk.
+
+        Returns:
+            :class:`ProcessingStats`
+                The processing stats, see :meth:`ProfileChunk.stats`.
+        """
+        ...
+
+    def get_summary(self) -> ProfileSummary:
+        """
+        Returns the summary of the chunk.
+
+        Returns:
+            :class:`ProfileSummary`
+                The aggregates describing the chunk as a whole.
+        """
+        ...
+
+def profile_chunk_from_json_str(
+    profile: str,
+    platform: Optional[str] = None,
+    in_app_rules: Optional[Dict[str, Dict[str, List[str]]]] = None,
+) -> ProfileChunk:
     """
-    Represents comprehensive metadata information associated with a profile.
-    
-    Contains device characteristics, SDK information, transaction details,
-    and other profile-specific data for analysis and debugging purposes.
+    Returns a `ProfileChunk` instance from a json string
+
+    .. deprecated::
+        The platform alone cannot distinguish the legacy android trace format
+        from sample v2. Use `profile_chunk_from_json_str_and_version` instead
+        whenever the profile version is known.
+
+    Arguments
+    ---------
+    profile : str
+       A profile serialized as json string
+
+    platform : Optional[str]
+       An optional string representing the profile platform.
+       If provided, we can directly deserialize to the right profile chunk
+       more efficiently.
+       If the platform is known at the time this function is invoked, it's
+       recommended to always pass it.
+
+    Returns
+    -------
+    ProfileChunk
+      A `ProfileChunk` instance
+
+    Raises
+    ------
+    vroomrs.ParseError
+        If the payload isn't valid JSON, or doesn't match the format of its version.
+    vroomrs.UnsupportedVersion
+        If the version of the payload isn't supported.
+    ValueError
+        If the in_app rules are invalid.
     """
-    
-    android_api_level: Optional[int]
-    """The Android API level of the device, or None if not available."""
-    
-    architecture: str
-    """The device architecture (e.g., 'arm64', 'x86_64')."""
-    
-    device_classification: Optional[str]
-    """The device classification or category, or None if not available."""
-    
-    device_locale: Optional[str]
-    """The device locale setting, or None if not available."""
-    
-    device_manufacturer: Optional[str]
-    """The device manufacturer name, or None if not available."""
-    
-    device_model: str
-    """The device model name."""
-    
-    device_os_build_number: Optional[str]
-    """The device OS build number, or None if not available."""
-    
-    device_os_name: str
-    """The device operating system name."""
-    
-    device_os_version: str
-    """The device operating system version."""
-    
-    id: str
-    """The unique identifier for this profile."""
-    
-    project_id: str
-    """The project ID as a string."""
-    
-    sdk_name: Optional[str]
-    """The name of the SDK used to collect this profile, or None if not available."""
-    
-    sdk_version: Optional[str]
-    """The version of the SDK used to collect this profile, or None if not available."""
-    
-    timestamp: int
-    """The timestamp when the profile was collected (Unix timestamp)."""
-    
-    trace_duration_ms: float
-    """The duration of the trace in milliseconds."""
-    
-    transaction_id: str
-    """The unique identifier for the transaction."""
-    
-    transaction_name: str
-    """The name of the transaction."""
-    
-    version_code: Optional[str]
-    """The version code of the application, or None if not available."""
-    
-    version_name: Optional[str]
-    """The version name of the application, or None if not available."""
+    ...
+
+def profile_chunk_from_json_str_and_version(profile: str, version: str) -> ProfileChunk:
+    """
+    Returns a `ProfileChunk` instance from a json string, using the profile
+    version to select the right format.
+
+    Arguments
+    ---------
+    profile : str
+       A profile serialized as json string
+
+    version : str
+       A string representing the profile version. It is used to directly
+       deserialize to the right profile chunk more efficiently
+       ("2.android-trace" and, as a fallback to the legacy behavior, an empty
+       string map to the legacy android trace format, other versions 2.x to the
+       sample v2 format).
+
+    Returns
+    -------
+    ProfileChunk
+      A `ProfileChunk` instance
+
+    Raises
+    ------
+    vroomrs.ParseError
+        If the payload isn't valid JSON, or doesn't match the format of its version.
+    vroomrs.UnsupportedVersion
+        If the version of the payload isn't supported.
+    """
+    ...
+
+def decompress_profile_chunk(profile: bytes) -> ProfileChunk:
+    """
+    Returns a `ProfileChunk` instance from a lz4 encoded profile.
+
+    Arguments
+    ---------
+    profile : bytes
+      A lz4 encoded profile.
+
+    Returns
+    -------
+    ProfileChunk
+      A `ProfileChunk` instance
+
+    Raises
+    ------
+    vroomrs.CompressionError
+        If the payload couldn't be decompressed.
+    vroomrs.ParseError
+        If the payload isn't valid JSON, or doesn't match the format of its version.
+    vroomrs.UnsupportedVersion
+        If the version of the payload isn't supported.
+
+    Example
+    -------
+        >>> with open("profile_compressed.lz4", "rb") as binary_file:
+        ...     profile = vroomrs.decompress_profile_chunk(binary_file.read())
+                # do something with the profile
+    """
+    ...
+
+def profile_from_json_str(profile: str, platform: Optional[str] = None) -> Profile:
+    """
+    Returns a `Profile` instance from a json string
+
+    Arguments
+    ---------
+    profile : str
+       A profile serialized as json string
+
+    platform : Optional[str]
+       An optional string representing the profile platform.
+       If provided, we can directly deserialize to the right profile more 
+       efficiently.
+       If the platform is known at the time this function is invoked, it's
+       recommended to always pass it.
+
+    Returns
+    -------
+    Profile
+      A `Profile` instance
+
+    Raises
+    ------
+    vroomrs.ParseError
+        If the payload isn't valid JSON, or doesn't match the format of its version.
+    vroomrs.UnsupportedVersion
+        If the version of the payload isn't supported.
+    """
+    ...
+
+def decompress_profile(profile: bytes) -> Profile:
+    """
+    Returns a `Profile` instance from a lz4 encoded profile.
+
+    Arguments
+    ---------
+    profile : bytes
+      A lz4 encoded profile.
+
+    Returns
+    -------
+    Profile
+      A `Profile` instance
+
+    Raises
+    ------
+    vroomrs.CompressionError
+        If the payload couldn't be decompressed.
+    vroomrs.ParseError
+        If the payload isn't valid JSON, or doesn't match the format of its version.
+    vroomrs.UnsupportedVersion
+        If the version of the payload isn't supported.
+
+    Example
+    -------
+        >>> with open("profile_compressed.lz4", "rb") as binary_file:
+        ...     profile = vroomrs.decompress_profile(binary_file.read())
+                # do something with the profile
+    """
+    ...
+
+def register_frame_detector(
+    category: str,
+    platform: str,
+    frame_names: List[str],
+    threshold_ns: int,
+    only_main_thread: bool = True,
+) -> None:
+    """
+    Registers a frame detector, run in addition to the built-in ones when
+    looking for occurrences in profiles of the given platform.
+
+    The detector is named after its platform and category (e.g.
+    "cocoa_json_decode"), see :func:`list_detectors`. Registering a detector
+    of the same platform and category again replaces it.
+
+    Arguments
+    ---------
+    category : str
+        The category of the occurrences created for the detected frames.
+
+    platform : str
+        The platform of the profiles the detector applies to.
+
+    frame_names : List[str]
+        The function names to detect. Names carrying a signature (e.g. android)
+        are also matched up to their first '('.
+
+    threshold_ns : int
+        The minimum duration, in nanoseconds, a frame needs to be detected.
+
+    only_main_thread : bool
+        Whether to only look at the main thread or at all threads.
+
+    Raises
+    ------
+    ValueError
+        If the detector would replace a built-in one.
+
+    Example
+    -------
+        >>> vroomrs.register_frame_detector(
+        ...     "json_decode", "cocoa", ["MyJSONParser.parse(Data)"], 16_000_000, True
+        ... )
+    """
+    ...
+
+def generate_synthetic_profile_chunk(
+    platform: str,
+    threads: int = 1,
+    max_depth: int = 32,
+    sample_rate_hz: int = 100,
+    duration_ms: int = 1000,
+    unique_functions: int = 100,
+    seed: Optional[int] = None,
+) -> ProfileChunk:
+    """
+    Returns a randomized but valid `ProfileChunk`, for load testing and fuzzing.
+
+    Arguments
+    ---------
+    platform : str
+        The profile platform. "android" generates a chunk in the legacy android
+        trace format, any other platform a sample v2 chunk.
+
+    threads : int
+        The number of threads sampled, the first one being the main thread.
+
+    max_depth : int
+        The maximum stack depth.
+
+    sample_rate_hz : int
+        The sampling frequency, in Hz.
+
+    duration_ms : int
+        The chunk duration, in milliseconds.
+
+    unique_functions : int
+        The number of distinct functions stacks are made of.
+
+    seed : Optional[int]
+        The seed of the random generator, to reproduce a given chunk.
+
+    Returns
+    -------
+    ProfileChunk
+      A `ProfileChunk` instance
+
+    Raises
+    ------
+    ValueError
+        If the duration and sampling frequency overflow the sample count.
+    """
+    ...
+
+def compare_profile_chunks(
+    left: ProfileChunk, right: ProfileChunk
+) -> FlamechartComparison:
+    """
+    Returns the flamecharts of two profile chunks covering the same wall-clock
+    interval (e.g. two pods, or react native JS and native code), on a shared
+    time axis for a synced dual view.
+
+    Arguments
+    ---------
+    left : ProfileChunk
+        The chunk displayed first.
+
+    right : ProfileChunk
+        The chunk displayed second.
+
+    Returns
+    -------
+    FlamechartComparison
+      The time axis metadata, and both flamecharts with spans offset from
+      the start of the earliest chunk.
+
+    Raises
+    ------
+    Exception
+        If an error occurs while building the call trees.
+
+    Example
+    -------
+        >>> comparison = vroomrs.compare_profile_chunks(js_chunk, native_chunk)
+        >>> payload = comparison.to_json_str()
+    """
+    ...
 
-def profile_chunk_from_json_str(
-    profile: str, platform: Optional[str] = None
-) -> ProfileChunk:
+def hashing_scheme() -> str:
     """
-    Returns a `ProfileChunk` instance from a json string
+    Returns the identifier of the hashing scheme used for fingerprints.
 
-    .. deprecated::
-        The platform alone cannot distinguish the legacy android trace format
-        from sample v2. Use `profile_chunk_from_json_str_and_version` instead
-        whenever the profile version is known.
+    Fingerprints only depend on their input, whatever the architecture of the
+    worker computing them. The identifier changes whenever the fingerprint of
+    a given input would, so fingerprints computed with different schemes are
+    never compared with each other.
+
+    Returns
+    -------
+    str
+        The hashing scheme identifier.
+    """
+    ...
+
+def set_in_app_overrides(organization_id: int, document: str) -> None:
+    """
+    Sets the in_app overrides of an organization, applied to its profiles
+    when they're normalized and taking precedence over the in_app heuristics.
+    They replace the previous overrides of the organization.
+
+    The document lists package prefixes along with the in_app value forced on
+    matching frames, e.g. ``{"rules": [{"prefix": "com.acme.", "in_app": true}]}``.
+    Prefixes are matched against the frame module, or its package if it has
+    no module, the longest matching prefix winning.
 
     Arguments
     ---------
-    profile : str
-       A profile serialized as json string
+    organization_id : int
+      The organization the overrides apply to.
+    document : str
+      The override document, as a json string.
 
-    platform : Optional[str]
-       An optional string representing the profile platform.
-       If provided, we can directly deserialize to the right profile chunk
-       more efficiently.
-       If the platform is known at the time this function is invoked, it's
-       recommended to always pass it.
+    Raises
+    -------
+    ValueError
+        If the document is invalid, see :func:`validate_in_app_overrides`.
+    """
+    ...
+
+def clear_in_app_overrides(organization_id: int) -> None:
+    """
+    Removes the in_app overrides of an organization.
+
+    Arguments
+    ---------
+    organization_id : int
+      The organization whose overrides are removed.
+    """
+    ...
+
+def validate_in_app_overrides(document: str) -> None:
+    """
+    Validates an in_app override document, without applying it.
+
+    Arguments
+    ---------
+    document : str
+      The override document, as a json string.
+
+    Raises
+    -------
+    ValueError
+        If the document isn't valid json, has unknown fields, empty or
+        duplicate prefixes, or more than 1000 rules.
+    """
+    ...
+
+def platform_capabilities() -> Dict[str, PlatformCapabilities]:
+    """
+    Returns the analyses supported for each platform, so work can be routed
+    and their availability surfaced without hardcoding them.
+
+    Platforms supporting no analysis are left out.
 
     Returns
     -------
-    ProfileChunk
-      A `ProfileChunk` instance
+    dict[str, :class:`vroomrs.PlatformCapabilities`]
+      The capabilities of each platform, by platform.
+    """
+    ...
+
+def process_profile(
+    profile: bytes,
+    min_depth: int,
+    filter_system_frames: bool,
+    max_unique_functions: Optional[int] = None,
+    filter_non_leaf_functions: bool = True,
+    generate_stack_fingerprints: bool = False,
+    max_examples: int = 0,
+    active_thread_only: bool = False,
+    max_lines_per_function: int = 0,
+    min_sample_count: int = 2,
+    sort_by: str = "self_time",
+) -> ProcessedProfile:
+    """
+    Returns everything extracted from a lz4 encoded profile (its metadata,
+    function metrics, occurrences and summary) in a single pass, the profile
+    being decompressed, parsed, normalized and its call trees built only once.
+
+    Arguments
+    ---------
+    profile : bytes
+      A lz4 encoded profile.
+    min_depth, filter_system_frames, max_unique_functions, filter_non_leaf_functions, generate_stack_fingerprints, max_examples, active_thread_only, min_sample_count, sort_by
+      The function metrics options, see :meth:`vroomrs.Profile.extract_functions_metrics`.
+
+    Returns
+    -------
+    :class:`vroomrs.ProcessedProfile`
+      The metadata, functions, occurrences and summary of the profile.
 
     Raises
     ------
-    Exception
-        If an error occurs during the extraction process.
+    vroomrs.CompressionError
+        If the payload couldn't be decompressed.
+    vroomrs.ParseError
+        If the payload isn't valid JSON, or doesn't match the format of its version.
+    vroomrs.UnsupportedVersion
+        If the version of the payload isn't supported.
+    vroomrs.VroomError
+        If an error occurs while processing the profile.
+
+    Example
+    --------
+        >>> processed = vroomrs.process_profile(payload, min_depth=1, filter_system_frames=True)
+        >>> functions = processed.get_functions()
+        >>> occurrences = processed.get_occurrences()
     """
     ...
 
-def profile_chunk_from_json_str_and_version(
-    profile: str, version: str
-) -> ProfileChunk:
+def stream_profile_chunk_functions_metrics(
+    profile: bytes,
+    min_depth: int,
+    filter_system_frames: bool,
+    max_unique_functions: Optional[int] = None,
+    filter_non_leaf_functions: bool = True,
+    generate_stack_fingerprints: bool = False,
+    max_examples: int = 0,
+    active_thread_only: bool = False,
+    max_lines_per_function: int = 0,
+    min_sample_count: int = 2,
+    sort_by: str = "self_time",
+) -> List[CallTreeFunction]:
     """
-    Returns a `ProfileChunk` instance from a json string, using the profile
-    version to select the right format.
+    Extracts the function metrics of a profile chunk straight from its json
+    payload, for very large chunks.
+
+    The chunk is normalized, and the call trees of sample chunks are built
+    while their samples are parsed, so the samples are never all held in
+    memory at once. This requires the samples of each thread to be sorted by
+    timestamp, as SDKs send them: otherwise, or for android trace chunks, the
+    chunk is parsed in full, as with :meth:`vroomrs.ProfileChunk.extract_functions_metrics`.
+
+    Arguments
+    ---------
+    profile : bytes
+      A profile chunk serialized as json.
+    min_depth, filter_system_frames, max_unique_functions, filter_non_leaf_functions, generate_stack_fingerprints, max_examples, active_thread_only, max_lines_per_function, min_sample_count, sort_by
+      The function metrics options, see :meth:`vroomrs.ProfileChunk.extract_functions_metrics`.
+
+    Returns
+    -------
+    list[:class:`vroomrs.CallTreeFunction`]
+      The metrics of the functions of the chunk.
+
+    Raises
+    ------
+    vroomrs.ParseError
+        If the payload isn't valid JSON, or doesn't match the format of its version.
+    vroomrs.UnsupportedVersion
+        If the version of the payload isn't supported.
+    vroomrs.InvalidStack
+        If too many samples reference stacks or frames which don't exist.
+    ValueError
+        If `sort_by` is unknown.
+
+    Example
+    --------
+        >>> functions = vroomrs.stream_profile_chunk_functions_metrics(payload, min_depth=1, filter_system_frames=True)
+    """
+    ...
+
+def profile_chunks_from_json_str(
+    profile: str, max_samples: int, max_bytes: Optional[int] = None
+) -> List[ProfileChunk]:
+    """
+    Returns `ProfileChunk` instances from a json string, splitting chunks
+    over the limits rather than rejecting them.
+
+    Samples are split in timestamp order. Each chunk gets the frames and
+    stacks its samples use, the measurements of its time range, and a chunk
+    ID derived from the original one (the same every time the chunk is split).
+    Chunks in the legacy android trace format are never split.
 
     Arguments
     ---------
     profile : str
-       A profile serialized as json string
+      A profile chunk serialized as json string
+    max_samples : int
+      The maximum number of samples of a chunk.
+    max_bytes : Optional[int]
+      An optional maximum size of a chunk serialized as json, in bytes.
+      Chunks made of a single sample are never split further.
 
-    version : str
-       A string representing the profile version. It is used to directly
-       deserialize to the right profile chunk more efficiently
-       ("2.android-trace" and, as a fallback to the legacy behavior, an empty
-       string map to the legacy android trace format, any other version to the
-       sample v2 format).
+    Returns
+    -------
+    list[:class:`vroomrs.ProfileChunk`]
+      The chunk, or the chunks it was split into, in timestamp order.
+
+    Raises
+    -------
+    vroomrs.ParseError
+        If the payload isn't valid JSON, or doesn't match the format of its version.
+    vroomrs.UnsupportedVersion
+        If the version of the payload isn't supported.
+
+    Example
+    --------
+        >>> chunks = vroomrs.profile_chunks_from_json_str(payload, max_samples=10000)
+    """
+    ...
+
+def configure(
+    max_stack_depth: Optional[int] = None,
+    max_in_app_rules: Optional[int] = None,
+    max_malformed_samples_ratio: Optional[float] = None,
+) -> Limits:
+    """
+    Configures the limits applied while processing profiles, process-wide.
+
+    Limits not given are left as they are, so calling it without arguments
+    returns the limits in effect. Limits are read when processing starts, so
+    they should be configured before any profile is processed.
+
+    Arguments
+    ---------
+    max_stack_depth : Optional[int]
+      The maximum depth of stacks (128 by default). Deeper stacks of android
+      profiles are truncated when building call trees, and stack traces of
+      occurrences only keep this many frames.
+    max_in_app_rules : Optional[int]
+      The maximum number of rules of an in_app override document (1000 by
+      default).
+    max_malformed_samples_ratio : Optional[float]
+      The maximum ratio of the samples of a chunk which can be malformed (0.1
+      by default). Up to it, malformed samples are skipped when building call
+      trees rather than failing, 0 failing on any malformed sample.
 
     Returns
     -------
-    ProfileChunk
-      A `ProfileChunk` instance
+    :class:`vroomrs.Limits`
+      The limits in effect.
 
     Raises
-    ------
-    Exception
-        If an error occurs during the extraction process.
+    -------
+    ValueError
+        If a limit is 0, or a ratio isn't between 0 and 1, in which case none are changed.
+
+    Example
+    --------
+        >>> vroomrs.configure(max_stack_depth=512).get_max_stack_depth()
+        512
     """
     ...
 
-def decompress_profile_chunk(profile: bytes) -> ProfileChunk:
+def fingerprint_function(name: str, package: str) -> int:
     """
-    Returns a `ProfileChunk` instance from a lz4 encoded profile.
+    Returns the fingerprint of a function, as found in function metrics.
+
+    The algorithm is the one of vroom (the 64-bit FNV-1a hash of
+    ``package:name``, truncated to 32 bits), so fingerprints match the ones
+    it stored.
 
     Arguments
     ---------
-    profile : bytes
-      A lz4 encoded profile.
+    name : str
+      The name of the function.
+    package : str
+      The module of the function, or its package if it has no module, as
+      returned by :meth:`vroomrs.CallTreeFunction.get_package`.
 
     Returns
     -------
-    ProfileChunk
-      A `ProfileChunk` instance
+    int
+      The fingerprint of the function.
+
+    Example
+    --------
+        >>> vroomrs.fingerprint_function("foo", "foo")
+        2655321105
+    """
+    ...
+
+def merge_profile_chunks(chunks: List[ProfileChunk]) -> ProfileChunk:
+    """
+    Merges profile chunks collected by the same profiler into a single chunk
+    covering the whole session.
+
+    Samples are concatenated in timestamp order, their stacks and frames being
+    re-indexed (and deduplicated) into those of the merged chunk, and thread
+    metadata, measurements and debug images are merged. Other metadata is
+    taken from the earliest chunk, and the chunk ID is derived from the IDs of
+    the chunks merged (the same every time the same chunks are merged).
+
+    Arguments
+    ---------
+    chunks : list[:class:`vroomrs.ProfileChunk`]
+      The chunks to merge, in any order.
+
+    Returns
+    -------
+    :class:`vroomrs.ProfileChunk`
+      The merged chunk.
 
     Raises
-    ------
-    Exception
-        If an error occurs during the extraction process.
+    -------
+    pyo3.exceptions.ValueError
+        If no chunks are given, if they were collected by different profilers
+        or on different platforms, or if any is in the legacy android trace format.
 
     Example
+    --------
+        >>> session = vroomrs.merge_profile_chunks(chunks)
+        >>> metrics = session.extract_functions_metrics(min_depth=1, filter_system_frames=True)
+    """
+    ...
+
+def list_detectors() -> List[DetectorInfo]:
+    """
+    Returns the detectors run when looking for occurrences, in the order they
+    run: the built-in ones, then the ones registered at runtime.
+
+    Returns
     -------
-        >>> with open("profile_compressed.lz4", "rb") as binary_file:
-        ...     profile = vroomrs.decompress_profile_chunk(binary_file.read())
-                # do something with the profile
+    list[:class:`vroomrs.DetectorInfo`]
+      The detectors, with their name, categories, platforms and whether
+      they're enabled.
     """
     ...
 
-def profile_from_json_str(profile: str, platform: Optional[str] = None) -> Profile:
+def set_detectors_enabled(names: List[str], enabled: bool = True) -> None:
     """
-    Returns a `Profile` instance from a json string
+    Enables or disables detectors, process-wide.
 
     Arguments
     ---------
-    profile : str
-       A profile serialized as json string
+    names : list[str]
+      The names of the detectors, see :func:`list_detectors`.
+    enabled : bool
+      Whether the detectors run when looking for occurrences.
+
+    Raises
+    -------
+    ValueError
+        If a detector is unknown, in which case none are changed.
+
+    Example
+    --------
+        >>> vroomrs.set_detectors_enabled(["frame_drop"], False)
+    """
+    ...
+
+def trim_package(package: str) -> str:
+    """
+    Formats a package (e.g. the path of a library) for display and
+    aggregation, as function metrics do: the file name without its
+    directory and library extension.
+
+    Arguments
+    ---------
+    package : str
+      The package, as sent by the SDK.
+
+    Returns
+    -------
+    str
+      The trimmed package.
+
+    Example
+    --------
+        >>> vroomrs.trim_package("/usr/lib/system/libsystem_kernel.dylib")
+        'libsystem_kernel'
+    """
+    ...
+
+def frame_module_or_package(
+    module: Optional[str] = None,
+    package: Optional[str] = None,
+    platform: Optional[str] = None,
+) -> str:
+    """
+    Returns the module of a frame, or its trimmed package if it has no
+    module (see :func:`trim_package`), as found in function metrics
+    (:meth:`vroomrs.CallTreeFunction.get_package`).
 
+    Arguments
+    ---------
+    module : Optional[str]
+      The module of the frame.
+    package : Optional[str]
+      The package of the frame.
     platform : Optional[str]
-       An optional string representing the profile platform.
-       If provided, we can directly deserialize to the right profile more 
-       efficiently.
-       If the platform is known at the time this function is invoked, it's
-       recommended to always pass it.
+      The platform of the frame. Composer packages of PHP frames are kept
+      whole.
 
     Returns
     -------
-    Profile
-      A `Profile` instance
+    str
+      The module or package, empty if the frame has neither.
+
+    Example
+    --------
+        >>> vroomrs.frame_module_or_package(package="/usr/lib/libc++.1.dylib")
+        'libc++.1'
+    """
+    ...
+
+def serialize_functions_msgpack(functions: List[CallTreeFunction]) -> bytes:
+    """
+    Serializes a list of functions to MessagePack in one go, as an array of
+    maps keyed by field name (see :meth:`vroomrs.CallTreeFunction.to_msgpack`).
+
+    Arguments
+    ---------
+    functions : list[:class:`vroomrs.CallTreeFunction`]
+      The functions to serialize, e.g. extracted from a profile chunk.
+
+    Returns
+    -------
+    bytes
+      The MessagePack encoding of the functions list.
 
     Raises
-    ------
-    Exception
-        If an error occurs during the extraction process.
+    -------
+    pyo3.exceptions.ValueError
+        If the serialization fails due to invalid data.
+
+    Example
+    --------
+        >>> functions = chunk.extract_functions_metrics(min_depth=1, filter_system_frames=True)
+        >>> producer.produce("profiles-call-tree-functions", vroomrs.serialize_functions_msgpack(functions))
     """
     ...
 
-def decompress_profile(profile: bytes) -> Profile:
+def set_demangling(enabled: bool, preserve_mangled: bool = False) -> None:
     """
-    Returns a `Profile` instance from a lz4 encoded profile.
+    Sets whether the mangled C++ (``_ZN...``, ``__ZN...``) and Rust function
+    names of frames are demangled when profiles are normalized, process-wide.
+
+    Demangling changes the fingerprint of the functions it renames, so it
+    should be set before any profile is processed.
+
+    Arguments
+    ---------
+    enabled : bool
+      Whether function names are demangled.
+    preserve_mangled : bool
+      Whether the mangled name is kept as the ``symbol`` of frames which
+      have none. Defaults to False.
+
+    Example
+    --------
+        >>> vroomrs.set_demangling(True, preserve_mangled=True)
+    """
+    ...
+
+def set_swift_simplification(
+    strip_generics: bool = True, strip_modules: bool = False
+) -> None:
+    """
+    Sets how the Swift function names of cocoa frames are simplified when
+    profiles are normalized, process-wide, so the specializations of generic
+    functions aren't different functions.
+
+    Simplification changes the fingerprint of the functions it renames, so it
+    should be set before any profile is processed. Names are left as is unless
+    it's set.
+
+    Arguments
+    ---------
+    strip_generics : bool
+      Whether generic parameters (``MyApp.Cache<Swift.String>.value()`` becomes
+      ``MyApp.Cache.value()``) and generic specialization prefixes are
+      stripped. Defaults to True.
+    strip_modules : bool
+      Whether the prefixes of the module of the frame and of the Swift
+      standard library (``MyApp.``, ``Swift.``) are stripped. Defaults to
+      False.
+
+    Example
+    --------
+        >>> vroomrs.set_swift_simplification(strip_generics=True, strip_modules=True)
+    """
+    ...
+
+def aggregate_functions_metrics(
+    chunks: List[ProfileChunk],
+    min_depth: int,
+    filter_system_frames: bool,
+    max_unique_functions: Optional[int] = None,
+    filter_non_leaf_functions: bool = True,
+    generate_stack_fingerprints: bool = False,
+    max_examples: int = 0,
+    min_sample_count: int = 2,
+    sort_by: str = "self_time",
+) -> List[CallTreeFunction]:
+    """
+    Aggregates the function metrics of several profile chunks in one call,
+    e.g. for the hourly functions rollup, building the call trees of the chunks
+    in parallel.
+
+    Functions are merged by fingerprint (or stack fingerprint) across chunks,
+    their self and total times, sample counts, threads and examples being
+    combined, before being filtered and sorted: `min_sample_count` applies to
+    the samples of all the chunks.
+
+    Arguments
+    ---------
+    chunks : list[:class:`vroomrs.ProfileChunk`]
+      The chunks to aggregate, normalized. Each chunk can only be given once.
+    min_depth, filter_system_frames, max_unique_functions, filter_non_leaf_functions, generate_stack_fingerprints, max_examples, min_sample_count, sort_by
+      The function metrics options, see :meth:`vroomrs.ProfileChunk.extract_functions_metrics`.
+
+    Returns
+    -------
+    list[:class:`vroomrs.CallTreeFunction`]
+      The metrics of the functions of all the chunks, sorted by `sort_by`.
+
+    Raises
+    -------
+    ValueError
+        If `sort_by` is unknown.
+    vroomrs.InvalidStack
+        If too many samples reference stacks or frames which don't exist.
+
+    Example
+    --------
+        >>> functions = vroomrs.aggregate_functions_metrics(chunks, min_depth=1, filter_system_frames=True)
+    """
+    ...
+
+def unregister_detector(name: str) -> None:
+    """
+    Unregisters a detector registered with :func:`register_frame_detector`.
+
+    Arguments
+    ---------
+    name : str
+      The name of the detector, see :func:`list_detectors`.
+
+    Raises
+    -------
+    ValueError
+        If the detector is unknown or built-in.
+
+    Example
+    --------
+        >>> vroomrs.unregister_detector("cocoa_json_decode")
+    """
+    ...
+
+def process_profile_chunk(
+    profile: bytes,
+    min_depth: int,
+    filter_system_frames: bool,
+    max_unique_functions: Optional[int] = None,
+    filter_non_leaf_functions: bool = True,
+    generate_stack_fingerprints: bool = False,
+    max_examples: int = 0,
+    active_thread_only: bool = False,
+    max_lines_per_function: int = 0,
+    collapse_recursion: bool = False,
+    skip_idle_threads: bool = False,
+    min_sample_count: int = 2,
+    sort_by: str = "self_time",
+) -> ProcessedProfileChunk:
+    """
+    Returns everything extracted from a lz4 encoded profile chunk (its
+    function metrics, processing stats and summary) in a single pass, the
+    chunk being decompressed, parsed, normalized and its call trees built
+    only once.
 
     Arguments
     ---------
     profile : bytes
-      A lz4 encoded profile.
+      A lz4 encoded profile chunk.
+    min_depth, filter_system_frames, max_unique_functions, filter_non_leaf_functions, generate_stack_fingerprints, max_examples, active_thread_only, max_lines_per_function, collapse_recursion, skip_idle_threads, min_sample_count, sort_by
+      The function metrics options, see :meth:`vroomrs.ProfileChunk.extract_functions_metrics`.
 
     Returns
     -------
-    Profile
-      A `Profile` instance
+    :class:`vroomrs.ProcessedProfileChunk`
+      The functions, processing stats and summary of the chunk. Chunks aren't
+      searched for occurrences, so the occurrence count of the summary is 0.
 
     Raises
     ------
-    Exception
-        If an error occurs during the extraction process.
+    vroomrs.CompressionError
+        If the payload couldn't be decompressed.
+    vroomrs.ParseError
+        If the payload isn't valid JSON, or doesn't match the format of its version.
+    vroomrs.UnsupportedVersion
+        If the version of the payload isn't supported.
+    vroomrs.VroomError
+        If an error occurs while processing the chunk.
+    ValueError
+        If `sort_by` is unknown.
 
     Example
-    -------
-        >>> with open("profile_compressed.lz4", "rb") as binary_file:
-        ...     profile = vroomrs.decompress_profile(binary_file.read())
-                # do something with the profile
+    --------
+        >>> processed = vroomrs.process_profile_chunk(payload, min_depth=1, filter_system_frames=True)
+        >>> functions = processed.get_functions()
     """
-    ...
\ No newline at end of file
+    ...